@@ -29,6 +29,15 @@ struct CalPage {
 
 const CAL_PAGE: CalPage = CalPage { ampl: 123.456, delay: 100 };
 
+// Large calibration page with 1024 parameters, to bench the copy on write sync
+// A 32x32 map, serde implements arrays up to 32 elements only
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPageLarge {
+    data: [[f64; 32]; 32],
+}
+
+const CAL_PAGE_LARGE: CalPageLarge = CalPageLarge { data: [[0.0; 32]; 32] };
+
 //-----------------------------------------------------------------------------
 // XCP client
 
@@ -82,6 +91,7 @@ impl XcpTextDecoder for ServTextDecoder {
 enum ClientMode {
     Wait,
     Calibrate,
+    CalibrateLarge,
     Measure,
     Stop,
 }
@@ -138,6 +148,10 @@ async fn xcp_client(dest_addr: std::net::SocketAddr, local_addr: std::net::Socke
                     info!("Start Calibration");
                 }
 
+                ClientMode::CalibrateLarge => {
+                    info!("Start Calibration of the large page");
+                }
+
                 _ => {}
             }
 
@@ -173,6 +187,16 @@ async fn xcp_client(dest_addr: std::net::SocketAddr, local_addr: std::net::Socke
                     warn!("Calibration operation duration average time exceeds 100us!")
                 };
             }
+            ClientMode::CalibrateLarge => {
+                // One calibration per second
+                let data = xcp_client
+                    .create_calibration_object("CalPageLarge.data")
+                    .await
+                    .expect("Failed to create calibration object for CalPageLarge.data");
+                let v = xcp_client.get_value_f64(data);
+                xcp_client.set_value_f64(data, v + 1.0).await.unwrap();
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
             ClientMode::Stop => {
                 info!("Stop");
                 break;
@@ -237,6 +261,8 @@ fn xcp_benchmark(c: &mut Criterion) {
     // Create a calibration segment
     let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
     cal_page.register_fields();
+    let cal_page_large = xcp.create_calseg("CalPageLarge", &CAL_PAGE_LARGE);
+    cal_page_large.register_fields();
 
     // Measurement signal
     let mut signal1: u32 = 0;
@@ -313,6 +339,26 @@ fn xcp_benchmark(c: &mut Criterion) {
     *mode.lock() = ClientMode::Wait;
     info!("Calibration bench done, changes observed: {}", count);
 
+    // Bench copy on write sync of a large calibration page with one calibration per second
+    // Only the modified parameter is copied, instead of the whole page
+    info!("Start large page sync bench");
+    *mode.lock() = ClientMode::CalibrateLarge;
+    let mut count = 0;
+    c.bench_function("sync large page", |b| {
+        b.iter(|| {
+            if cal_page_large.sync() {
+                count += 1;
+            }
+        })
+    });
+    *mode.lock() = ClientMode::Wait;
+    info!(
+        "Large page sync bench done, changes observed: {}, bytes copied: {} (whole page copy: {})",
+        count,
+        count * std::mem::size_of::<f64>(),
+        count * std::mem::size_of::<CalPageLarge>()
+    );
+
     thread::sleep(Duration::from_millis(200));
 
     // Bench measurement trigger
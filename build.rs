@@ -13,21 +13,29 @@ fn main() {
         .blocklist_type("T_CLOCK_INFO")
         // Protocol layer
         .allowlist_function("XcpInit")
-        //.allowlist_function("XcpStart")
+        .allowlist_function("XcpStart")
+        .allowlist_function("XcpReset")
         .allowlist_function("XcpDisconnect")
-        // Transport layer
-        //.allowlist_function("XcpTlInit")
-        //.allowlist_function("XcpTlShutdown")
-        //.allowlist_function("XcpTlCommand")
-        //.allowlist_function("XcpTlTransmitQueuePeekMsg")
-        //.allowlist_function("XcpTlTransmitQueueNextMsg")
-        //.allowlist_function("XcpTlTransmitQueueHasMsg")
+        // Transport layer (used by the async server)
+        .allowlist_function("XcpTlSetMaxSizes")
+        .allowlist_function("XcpTlInit")
+        .allowlist_function("XcpTlShutdown")
+        .allowlist_function("XcpTlCommand")
+        .allowlist_function("XcpTlTransmitQueuePeekMsg")
+        .allowlist_function("XcpTlTransmitQueueNextMsg")
+        .allowlist_function("XcpTlTransmitQueueHasMsg")
+        .allowlist_function("XcpTlFlushTransmitBuffer")
+        .allowlist_function("XcpTlGetTransmitQueueSize")
+        .allowlist_function("XcpTlGetTransmitQueueFree")
+        .allowlist_function("XcpTlGetMaxDtoSize")
+        .allowlist_function("XcpTlGetStats")
         .allowlist_function("XcpEthTlGetInfo")
+        .allowlist_function("XcpEthTlSetOptions")
         // ETH server
         .allowlist_function("XcpEthServerInit")
         .allowlist_function("XcpEthServerShutdown")
         .allowlist_function("XcpEthServerStatus")
-        //.allowlist_function("XcpGetSessionStatus")
+        .allowlist_function("XcpGetSessionStatus")
         // DAQ
         .allowlist_function("XcpEvent")
         .allowlist_function("XcpEventExt")
@@ -35,9 +43,12 @@ fn main() {
         .allowlist_function("XcpPrint")
         .allowlist_function("ApplXcpSetLogLevel")
         .allowlist_function("ApplXcpSetA2lName")
+        .allowlist_function("ApplXcpSetA2lData")
         .allowlist_function("ApplXcpSetEpk")
         .allowlist_function("ApplXcpGetAddr")
+        .allowlist_function("ApplXcpGetClock64")
         .allowlist_function("ApplXcpRegisterCallbacks")
+        .allowlist_function("ApplXcpRegisterCommandCallback")
         //
         .generate()
         .expect("Unable to generate bindings");
@@ -45,7 +56,12 @@ fn main() {
 */
 
     // Build a XCP on ETH version of XCPlite as a library
-    cc::Build::new()
+    let mut build = cc::Build::new();
+    // Feature jumbo_frames sets the maximum MTU selectable at runtime to 9000
+    if std::env::var_os("CARGO_FEATURE_JUMBO_FRAMES").is_some() {
+        build.define("OPTION_MAX_MTU", "9000");
+    }
+    build
         .include("xcplib/src/")
         .include("xcplib/")
         .file("xcplib/xcpAppl.c")
@@ -3,6 +3,8 @@
 
 // Run the demo
 // cargo run --features serde --example hello_xcp
+// With the calibration parameters saved as json5 with field comments
+// cargo run --features json5 --example hello_xcp
 
 // Run the test XCP client in another terminal or start CANape with the project in folder examples/hello_xcp/CANape
 // cargo run --example xcp_client
@@ -74,6 +76,9 @@ fn main() -> Result<()> {
     #[cfg(feature = "serde")]
     if cal_page.load("hello_xcp.json").is_err() {
         cal_page.save("hello_xcp.json").unwrap();
+        // XCP: Save a human readable copy with the field comments
+        #[cfg(feature = "json5")]
+        cal_page.save_with_comments("hello_xcp.json5").unwrap();
     }
 
     // Measurement variables on stack
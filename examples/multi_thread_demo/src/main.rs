@@ -54,7 +54,8 @@ const CAL_PAGE: CalPage1 = CalPage1 {
 fn demo_task(id: u32, cal_seg: CalSeg<CalPage1>) {
     // Create a thread local event instance
     // The capacity of the event capture buffer is 16 bytes
-    let mut event = daq_create_event_tli!("demo_task", 16);
+    // The task id is used as instance label, to get stable signal names independent from thread spawn order
+    let mut event = daq_create_event_tli!("demo_task", 16, instance = id);
     println!("Task {id} started");
 
     // Demo signals
@@ -88,6 +88,7 @@ struct Point {
 
 #[derive(Debug, serde::Serialize, IdlGenerator)]
 struct PointCloud {
+    #[idl(max_len = MAX_POINT_COUNT)]
     points: Vec<Point>,
 }
 
@@ -152,6 +153,12 @@ fn main() -> Result<()> {
     let mut time = 0.0;
     daq_register!(time, event_point_cloud);
 
+    // Number of point clouds dropped, because the transmit queue was full
+    let mut drop_count: u32 = 0;
+    daq_register!(drop_count, event_point_cloud);
+
+    let mut queue_gauge = TransmitQueueGauge::new("transmit_queue");
+
     loop {
         thread::sleep(Duration::from_millis(10));
         time = start_time.elapsed().as_micros() as f64 * 0.000001; // s
@@ -171,11 +178,14 @@ fn main() -> Result<()> {
         }
         calculate_point_cloud(&params, &mut point_cloud, time, phi, h);
 
-        // Serialize point_cloud into the event capture buffer
-        daq_serialize!(point_cloud, event_point_cloud, "point cloud demo");
-
-        // Trigger the measurement event
-        event_point_cloud.trigger();
+        // Serialize point_cloud into the event capture buffer and trigger the measurement event
+        // Drop the point cloud, if the transmit queue has no space for it
+        if daq_try_serialize!(point_cloud, event_point_cloud, "point cloud demo").is_ok() {
+            event_point_cloud.trigger();
+        } else {
+            drop_count += 1;
+        }
+        queue_gauge.trigger();
 
         // Simply recreate the point cloud, when the number of points has changed
         let point_count = params.point_count;
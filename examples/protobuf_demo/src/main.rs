@@ -139,7 +139,8 @@ This method is useful when you want to work purely in Rust and still leverage Pr
 
 //-----------------------------------------------------------------------------
 
-#[derive(Clone, PartialEq, Message)]
+// XcpProtoMeasurement describes the fields with the types of the prost attributes, to measure them as individual signals
+#[derive(Clone, Copy, PartialEq, Message, XcpProtoMeasurement)]
 pub struct TestData {
     #[prost(fixed32, tag = "1")] // use fixed32, varint zizag encoding not recomended
     pub counter: u32,
@@ -171,40 +172,28 @@ fn main() -> Result<()> {
      */
 
     // Create a proto description for the data struct
-    let annotation = r#"/begin ANNOTATION ANNOTATION_LABEL "ObjectDescription" ANNOTATION_ORIGIN "application/protobuf"
-    /begin ANNOTATION_TEXT
-        "<DynamicObject>"
-        "<RootType>TestData</RootType>"
-        "</DynamicObject>"
-        "message TestData {"
-        "  fixed32 counter = 1;"
-        "  double signal = 2;"
-        "}"
-    /end ANNOTATION_TEXT
-/end ANNOTATION"#
-        .to_string();
-
-    // Register the data struct and create a buffer
-
+    let annotation = RegistryAnnotation::new(
+        "ObjectDescription",
+        "application/protobuf",
+        [
+            "<DynamicObject>",
+            "<RootType>TestData</RootType>",
+            "</DynamicObject>",
+            "message TestData {",
+            "  fixed32 counter = 1;",
+            "  double signal = 2;",
+            "}",
+        ],
+    )
+    .to_string();
+
+    // Register the fields of the data struct as measurements (TestData.counter, TestData.signal) in the capture buffer of the event
+    let mut event = daq_create_event!("test_data", 256);
+    let struct_offset = event.add_capture_struct(&test_data, "test data");
+
+    // Register the serialized data struct as variable length blob in the remaining capture buffer
+    let offset = event.add_blob("test_data", None, "proto serialized test data", annotation);
     let mut buf = Vec::new();
-    let event = xcp.create_event("test_data");
-    xcp.get_registry()
-        .lock()
-        .add_measurement(RegistryMeasurement::new(
-            "test_data",
-            RegistryDataType::Blob,
-            1,
-            1,
-            event,
-            0,
-            0u64,
-            1.0,
-            0.0,
-            "proto serialized test data",
-            "",
-            Some(annotation),
-        ))
-        .expect("Duplicate");
 
     // Loop
     loop {
@@ -212,12 +201,13 @@ fn main() -> Result<()> {
         test_data.signal += 0.1;
 
         // Serialize data and trigger measurememt event
+        // The blob is captured with its actual length
         buf.clear();
         test_data.encode(&mut buf).unwrap();
         println!("Capacity: {}, Data: {:?}", buf.capacity(), buf);
-        unsafe {
-            event.trigger_ext(buf.as_ptr());
-        }
+        event.capture_blob(&buf, offset);
+        event.capture_struct(&test_data, struct_offset);
+        event.trigger();
 
         thread::sleep(Duration::from_micros(1000000));
 
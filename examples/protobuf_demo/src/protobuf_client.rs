@@ -0,0 +1,109 @@
+// protobuf_client
+// XCP client for the protobuf_demo, measures and decodes the protobuf serialized TestData
+// Start protobuf_demo first:
+// cargo run --example protobuf_demo
+// cargo run --example protobuf_client
+
+use anyhow::Result;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Duration;
+
+use xcp_client::xcp_client::*;
+
+use prost::Message;
+
+//-----------------------------------------------------------------------------
+
+// Same message as in protobuf_demo
+#[derive(Clone, PartialEq, Message)]
+pub struct TestData {
+    #[prost(fixed32, tag = "1")]
+    pub counter: u32,
+    #[prost(double, tag = "2")]
+    pub signal: f64,
+}
+
+//-----------------------------------------------------------------------------
+// Blob decoder for TestData
+
+struct TestDataDecoder;
+
+impl XcpBlobDecoder for TestDataDecoder {
+    fn decode(&mut self, name: &str, timestamp: u64, data: &[u8]) {
+        match TestData::decode(data) {
+            Ok(test_data) => println!("{} t={}: {:?}", name, timestamp, test_data),
+            Err(e) => warn!("{}: protobuf decode error {}", name, e),
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, passes all blobs to the blob decoder
+// Only one ODT per DAQ list
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    daq_header_size: u8,
+    blob_decoder: TestDataDecoder,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        self.daq_header_size = daq_header_size;
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        // ODT header and 32 bit raw timestamp
+        let (daq, data) = if self.daq_header_size == 4 {
+            (u16::from_le_bytes([buf[2], buf[3]]), &buf[4..])
+        } else {
+            (buf[1] as u16, &buf[2..])
+        };
+        let timestamp = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as u64;
+        let data = &data[4..];
+
+        for odt_entry in &self.odt_entries[daq as usize] {
+            if let Some(blob) = odt_entry.get_blob(data) {
+                self.blob_decoder.decode(&odt_entry.name, timestamp, blob);
+            }
+        }
+    }
+}
+
+struct ServTextDecoder;
+
+impl XcpTextDecoder for ServTextDecoder {}
+
+//-----------------------------------------------------------------------------
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    println!("protobuf client");
+
+    env_logger::Builder::new().target(env_logger::Target::Stdout).filter_level(log::LevelFilter::Info).init();
+
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse()?, "0.0.0.0:0".parse()?);
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        daq_header_size: 0,
+        blob_decoder: TestDataDecoder,
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.map_err(|e| anyhow::anyhow!("{e}"))?;
+    xcp_client.upload_a2l(false).await.map_err(|e| anyhow::anyhow!("{e}"))?;
+
+    // Measure the protobuf serialized test_data for 5s
+    xcp_client.create_measurement_object("test_data").expect("test_data not found");
+    xcp_client.start_measurement().await.map_err(|e| anyhow::anyhow!("{e}"))?;
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    xcp_client.stop_measurement().await.map_err(|e| anyhow::anyhow!("{e}"))?;
+
+    xcp_client.disconnect().await.map_err(|e| anyhow::anyhow!("{e}"))?;
+    Ok(())
+}
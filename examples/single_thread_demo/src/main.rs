@@ -47,6 +47,7 @@ const CAL_PAGE: CalPage = CalPage {
 //-----------------------------------------------------------------------------
 // Demo application main
 
+#[allow(unused_assignments)] // Measurement variables are written only, they are read by XCP DAQ
 fn main() -> Result<()> {
     println!("XCPlite Single Thread Demo");
 
@@ -78,6 +79,7 @@ fn main() -> Result<()> {
     // Measurement variable
     let mut counter: u32 = 0;
     let mut channel_1: f64 = 0.0;
+    let mut channel_2: i16 = 0; // channel_1 in mV
 
     // Create a measurement event with a unique name "task"
     // This will apear as measurement mode in the CANape measurement configuration
@@ -87,6 +89,9 @@ fn main() -> Result<()> {
     daq_register!(counter, event);
     daq_register!(channel_1, event, "sine wave signal", "Volt");
 
+    // Register "channel_2", stored as i16 in mV, the physical value in Volt is a f64
+    daq_register!(channel_2, event, "sine wave signal in mV", "Volt", 0.001, 0.0, f64);
+
     loop {
         // A saw tooth counter with max from a calibration parameter
         counter += 1;
@@ -97,7 +102,7 @@ fn main() -> Result<()> {
         // A sine signal with amplitude and period from calibration parameters
         let time = start_time.elapsed().as_micros() as f64 * 0.000001; // s
         channel_1 = calseg.ampl * (PI * time / calseg.period).sin();
-        let _channel_2 = channel_1;
+        channel_2 = (channel_1 * 1000.0) as i16;
 
         // Triger the measurement event "task"
         // The measurement event timestamp is taken here and captured data is sent to CANape
@@ -2,12 +2,14 @@
 
 // Demo the usual measurement and calibration operations in an async environment
 // Demo how to visualize tokio tasks start/stop in a tokio worker thread pool
+// Run with --async-server to use the XCP server on the tokio runtime instead of the XCPlite server threads
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 
 use core::f64::consts::PI;
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use xcp::*;
 
@@ -67,32 +69,41 @@ const CAL_PAGE1: CalPage1 = CalPage1 {
 };
 
 //-----------------------------------------------------------------------------
-// Experimental
 // Asynchronous task, trigger measurement of local variable index, sleep 200us, measure -index and stop
-// Demonstrates multi instance measurement
-// There will be an event instance and an instance of variable index for each worker thread tokio uses
-// Note:
-// Once the A2L registry is created on XCP client connect, the event and variable instances are fixed and addional instances are not visible
-// Tokio occasionally creates new worker threads and destroys old ones very late, so the number of instances may change
-// Check what happens, when increasing/decreasing calpage0.task_count
+// Tokio may move the task to another worker thread at each await
+// The value of index is captured into the capture buffer of the event, which is part of the future and moves with the task
+// All instances of the task share the event "task" and the measurement variable index
 #[allow(dead_code)]
 async fn task(task_index: u16) {
     let mut index: i16 = task_index as i16;
 
     trace!("task {} start", index);
 
-    let event = daq_create_event_tli!("task");
-    daq_register_tli!(index, event, "Task index", "");
-    event.trigger();
-
-    tokio::time::sleep(tokio::time::Duration::from_micros(200)).await;
-    index = -index;
+    let mut event = daq_create_event!("task", 16);
+    for _ in 0..2 {
+        daq_capture_async!(index, event, "Task index", "");
+        event.trigger();
 
-    event.trigger();
+        tokio::time::sleep(tokio::time::Duration::from_micros(200)).await;
+        index = -index;
+    }
 
     trace!("task {} end", index);
 }
 
+//-----------------------------------------------------------------------------
+// Stop the mainloop on SIGTERM, the calibration segment is frozen before exit
+
+static TERMINATE: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+async fn wait_for_sigterm() {
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).expect("failed to install SIGTERM handler");
+    sigterm.recv().await;
+    info!("SIGTERM received");
+    TERMINATE.store(true, Ordering::Relaxed);
+}
+
 //-----------------------------------------------------------------------------
 // Main
 
@@ -104,10 +115,19 @@ async fn main() -> Result<(), Box<dyn Error>> {
     env_logger::Builder::new().target(env_logger::Target::Stdout).filter_level(log::LevelFilter::Info).init();
 
     // Initialize XCP
-    let xcp = XcpBuilder::new("tokio_demo")
-        .set_log_level(2)
-        .set_epk("EPK_12345678")
-        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)?;
+    // Optionally run the XCP server as tasks on this tokio runtime
+    let async_server = std::env::args().any(|arg| arg == "--async-server");
+    let xcp_builder = XcpBuilder::new("tokio_demo").set_log_level(2).set_epk("EPK_12345678");
+    let xcp = if async_server {
+        xcp_builder.start_async_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555, 0).await?
+    } else {
+        xcp_builder.start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)?
+    };
+
+    // Write freeze files from XCP freeze requests asynchronously, to avoid blocking the executor in calseg.sync()
+    xcp.set_freeze_executor(tokio::runtime::Handle::current());
+    #[cfg(unix)]
+    tokio::spawn(wait_for_sigterm());
 
     // Create and register a static calibration parameter set
     let calpage = CAL_PAGE.get().unwrap();
@@ -152,6 +172,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
             info!("mainloop stopped by calpage.run=false");
             break;
         }
+        if TERMINATE.load(Ordering::Relaxed) {
+            // Freeze the calibration segment to CalPage1.json without blocking the executor
+            calseg.save_async("CalPage1.json").await?;
+            break;
+        }
 
         // Sleep for a calibratable amount of microseconds
         tokio::time::sleep(tokio::time::Duration::from_micros(calpage0.task1_cycle_time_us as u64)).await;
@@ -188,7 +213,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
     info!("mainloop stopped");
 
     // Stop the XCP server
-    xcp.stop_server();
+    if async_server {
+        xcp.stop_async_server().await;
+    } else {
+        xcp.stop_server();
+    }
 
     Ok(())
 }
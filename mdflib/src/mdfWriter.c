@@ -16,10 +16,9 @@
 #include "mdf4.h"
 #include "mdfWriter.h"
 
-#define MDF_TIME_CHANNEL_SIZE 4
 #define MD_COMMENT_LEN 512
 #define CC_UNIT_LEN 32
-#define CN_NAME_LEN 32
+#define CN_NAME_LEN 128
 
 
 
@@ -114,6 +113,7 @@ struct mdfChannelGroup
 
     uint32_t recordId;
     uint32_t dataChannelCount;
+    uint64_t cycleCount;
 
     uint32_t groupHeaderSize;
     mdf_link_t pos;
@@ -326,12 +326,12 @@ static struct mdfChannel *mdfCreateChannelBlock(
     c->b.c.txHeaderUnit.id = GENERATE_ID('T', 'X');
     c->b.c.txHeaderUnit.length = MDF4_TX_MIN_LENGTH + CC_UNIT_LEN;
     c->b.c.txHeaderUnit.link_count = 0;
-    strncpy(c->b.c.unit, unit, CC_UNIT_LEN);
+    strncpy(c->b.c.unit, unit, CC_UNIT_LEN - 1);
 
     c->b.c.txHeader.id = GENERATE_ID('T', 'X');
     c->b.c.txHeader.length = MDF4_TX_MIN_LENGTH + CN_NAME_LEN;
     c->b.c.txHeader.link_count = 0;
-    strncpy(c->b.c.name, name, CN_NAME_LEN);
+    strncpy(c->b.c.name, name, CN_NAME_LEN - 1);
 
     c->b.a.caHeader.id = GENERATE_ID('C', 'A');
     c->b.a.caHeader.length = MDF4_CA_MIN_LENGTH + sizeof(c->b.a.ca_dim_size[0]);
@@ -410,8 +410,6 @@ int mdfOpen(const char *filename)
         return 0;
     }
 
-    assert(sizeof(long) == 4);
-
     mdfHeader = NULL;
     mdfChannelGroupFirst = mdfChannelGroupLast = NULL;
     mdfChannelGroupCount = 0;
@@ -440,12 +438,15 @@ int mdfCreateChannelGroup(uint32_t recordId, uint32_t recordLen, uint32_t timeCh
     g->dataChannelLast = NULL;
     g->dataChannelCount = 0;
 
-    g->timeChannel = mdfCreateChannelBlock(TRUE, "Time", MDF4_CN_VAL_UNSIGN_INTEL, 1 /* dim*/, 0 /*byteoffset*/, timeChannelSize, 0 /*next*/, timeChannelConv, 0.0, "s");
+    g->cycleCount = 0;
+
+    // Time channel size is in bytes
+    g->timeChannel = mdfCreateChannelBlock(TRUE, "Time", MDF4_CN_VAL_UNSIGN_INTEL, 1 /* dim*/, 0 /*byteoffset*/, timeChannelSize * 8, 0 /*next*/, timeChannelConv, 0.0, "s");
     if (g->timeChannel == NULL)
         return 0;
 
     g->recordLen = recordLen;                                    /* including recordIdLen, 0=unknown yet */
-    g->actualRecordLen = mdfRecordIdLen + MDF_TIME_CHANNEL_SIZE; /* including recordIdLen */
+    g->actualRecordLen = mdfRecordIdLen + timeChannelSize; /* including recordIdLen */
 
     if (mdfChannelGroupLast == NULL)
     {
@@ -614,8 +615,19 @@ int mdfWriteHeader()
 int mdfWriteRecord(const uint8_t *record, uint32_t recordLen)
 {
 
-    // Increment data group cycle count
-    // @@@@@@@@@@@@@@@@
+    if (mdfFile == NULL || recordLen < mdfRecordIdLen)
+        return 0;
+
+    // Increment the cycle count of the channel group with the record id of this record
+    uint16_t recordId = (uint16_t)(record[0] | (record[1] << 8));
+    for (struct mdfChannelGroup *g = mdfChannelGroupFirst; g != NULL; g = g->next)
+    {
+        if (g->recordId == recordId)
+        {
+            g->cycleCount++;
+            break;
+        }
+    }
     mdfDataBlockLen += recordLen;
     mdfCycleCount++;
     size_t s = fwrite(record, 1, recordLen, mdfFile);
@@ -637,7 +649,7 @@ int mdfClose()
         // Update channel group cycle count
         for (struct mdfChannelGroup *g = mdfChannelGroupFirst; g != NULL; g = g->next)
         {
-            g->b.cgData.cg_cycle_count = mdfCycleCount;
+            g->b.cgData.cg_cycle_count = g->cycleCount;
             if (fseek(mdfFile, g->pos, SEEK_SET) != 0)
                 return 0;
             if (!mdfWriteBlock(mdfFile, (BLOCK_HEADER *)g, sizeof(struct mdfChannelGroupBlock), FALSE))
@@ -102,24 +102,65 @@
 mod xcp;
 pub use xcp::cal::cal_seg::CalPageField;
 pub use xcp::cal::cal_seg::CalSeg;
+#[cfg(feature = "serde")]
+pub use xcp::cal::cal_limits::{CalLimitViolation, CalLoadError, CalLoadPolicy};
+pub use xcp::cal::cal_audit::{cal_audit_csv_sink, CalAuditSink, CalChange};
+pub use xcp::cal::cal_guard::GuardRailsMode;
+pub use xcp::cal::cal_slew::CalSlewMode;
+pub use xcp::cal::cal_observer::{CalSegChange, CalSegObserver, ChannelObserver};
+pub use xcp::cal::cal_snapshot::{CalDiffEntry, CalSnapshot, McValue};
 pub use xcp::daq::daq_event::DaqEvent;
+pub use xcp::daq::daq_event::DaqError;
+pub use xcp::daq::daq_event::DaqTrigger;
+pub use xcp::daq::daq_event::{DaqTimingStats, DAQ_TIMING_HIST_BINS};
+pub use xcp::daq::daq_event::{daq_slice_range, DaqArrayLen};
 pub use xcp::Xcp;
 pub use xcp::XcpBuilder;
 pub use xcp::XcpCalPage;
+pub use xcp::clock::{XcpClockCorrelation, XcpClockEpoch, XcpClockInfo};
 pub use xcp::XcpError;
 pub use xcp::XcpEvent;
+pub use xcp::XcpEventConsistency;
+pub use xcp::XcpEventPriority;
+pub use xcp::instance::XcpInstance;
+pub use xcp::instance::XcpInstanceBuilder;
 pub use xcp::XcpSessionStatus;
 pub use xcp::XcpTransportLayer;
+pub use xcp::XcpTransportOptions;
+#[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+pub use xcp::XcpLoopback;
+pub use xcp::TransportStats;
 
 // @@@@ Reexport for integration tests
 pub use xcp::xcp_test::test_reinit;
 
 // Submodule reg
 mod reg;
+pub use reg::A2lCheckFinding;
+pub use reg::A2lCheckSeverity;
+#[cfg(feature = "a2l_reader")]
+pub use reg::A2lImportReport;
+pub use reg::DefaultLimits;
+pub use reg::RegistryAnnotation;
 pub use reg::RegistryCharacteristic;
+pub use reg::RegistryCloseListener;
 pub use reg::RegistryDataType;
 pub use reg::RegistryDataTypeTrait;
 pub use reg::RegistryMeasurement;
+pub use reg::SortOrder;
+pub use reg::BLOB_LENGTH_PREFIX_SIZE;
+
+// Submodule metrics
+mod metrics;
+pub use metrics::RollingWindow;
+pub use metrics::RollingWindowMetric;
+pub use metrics::RollingWindowMetricCalPage;
+pub use metrics::{Alarm, AlarmHandler};
+pub use metrics::TransmitQueueGauge;
+#[cfg(feature = "metrics-percentile")]
+pub use metrics::{PercentileMetric, TDigest};
+#[cfg(feature = "metrics-exporter")]
+pub use metrics::{metrics_text, start_http_exporter, start_http_exporter_with_prefix, METRICS_EXPORTER_PREFIX};
 
 // Submodule daemon
 mod daemon;
@@ -160,6 +201,8 @@ macro_rules! cal_register_static {
 }
 
 /// Register a static measurement variable
+/// Absolute addressing mode, the ECU_ADDRESS is the address of the variable relative to the module load address
+/// The event must be triggered with trigger or trigger_abs, no base pointer is needed
 #[macro_export]
 macro_rules! daq_register_static {
     (   $variable:expr, $event:ident ) => {{
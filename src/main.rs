@@ -301,7 +301,7 @@ fn task1(calseg: CalSeg<CalPage>, calseg1: CalSeg<CalPage1>) {
     let static_cal_page = STATIC_CAL_PAGE.get().unwrap();
 
     // Create an event with capture capacity of 1024 bytes for point_cloud serialization
-    let event = daq_create_event!("task1");
+    let event = daq_create_event!("task1", period_us = TASK1_CYCLE_TIME_US);
 
     // Register signals of bassic types or array to be captured directly from stack
     daq_register!(counter, event, "", "", 1.0, 0.0);
@@ -466,7 +466,7 @@ fn main() {
     let mut mainloop_map = Box::new([[0u8; 16]; 16]);
 
     // Create associated event and register
-    let mainloop_event = daq_create_event!("mainloop");
+    let mainloop_event = daq_create_event!("mainloop", period_us = MAINLOOP_CYCLE_TIME * 1000);
     daq_register!(mainloop_counter1, mainloop_event);
 
     // Mutable static variables (borrowed from a StaticCell<StaticVars>)
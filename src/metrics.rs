@@ -0,0 +1,30 @@
+//-----------------------------------------------------------------------------
+// Module metrics
+// Runtime metrics of the application, measurable by XCP
+
+//-----------------------------------------------------------------------------
+// Submodules
+
+// Rolling window statistics of cycle times
+mod rolling_window;
+pub use rolling_window::*;
+
+// Threshold alarms
+mod alarm;
+pub use alarm::*;
+
+// Transmit queue fill level
+mod queue_level;
+pub use queue_level::*;
+
+// Percentiles with a t-digest sketch
+#[cfg(feature = "metrics-percentile")]
+mod percentile;
+#[cfg(feature = "metrics-percentile")]
+pub use percentile::*;
+
+// Prometheus/OpenMetrics HTTP exporter
+#[cfg(feature = "metrics-exporter")]
+mod exporter;
+#[cfg(feature = "metrics-exporter")]
+pub use exporter::*;
@@ -0,0 +1,109 @@
+//----------------------------------------------------------------------------------------------
+// Module alarm
+// Threshold alarms of metrics, notified to the XCP client tool or an application alarm handler
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::Xcp;
+
+//----------------------------------------------------------------------------------------------
+// AlarmHandler
+
+/// Alternative alarm delivery, e.g. to a log file, instead of the XCP service text (SERV_TEXT)
+/// Set with Xcp::set_alarm_handler
+pub trait AlarmHandler: Send {
+    /// Called when the alarm condition is first violated (active = true) and when it clears (active = false)
+    fn alarm(&self, name: &str, message: &str, active: bool);
+}
+
+//----------------------------------------------------------------------------------------------
+// Alarm
+
+/// Threshold alarm with alarm state
+/// Notifies when the threshold is first exceeded and when the value drops below the threshold again
+#[derive(Debug)]
+pub struct Alarm {
+    name: &'static str,
+    threshold: f64,
+    message: &'static str,
+    active: AtomicBool,
+}
+
+impl Alarm {
+    pub const fn new(name: &'static str, threshold: f64, message: &'static str) -> Alarm {
+        Alarm {
+            name,
+            threshold,
+            message,
+            active: AtomicBool::new(false),
+        }
+    }
+
+    /// Evaluate the threshold, notify on state changes
+    /// Returns true, if the alarm is active
+    pub fn check(&self, value: f64) -> bool {
+        let active = value > self.threshold;
+        if self.active.swap(active, Ordering::Relaxed) != active {
+            Xcp::get().notify_alarm(self.name, self.message, active);
+        }
+        active
+    }
+
+    /// Get the alarm state
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+}
+
+/// Evaluate a threshold alarm for a value on each call
+/// The message is sent to the XCP client tool when the value first exceeds the threshold, and a clear message when it drops below again
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! metrics_alarm {
+    ( $name:expr, $value:expr, $threshold:expr, $message:expr ) => {{
+        static ALARM__: Alarm = Alarm::new($name, $threshold, $message);
+        ALARM__.check($value as f64)
+    }};
+}
+
+//-----------------------------------------------------------------------------
+// Test module
+
+#[cfg(test)]
+mod alarm_tests {
+
+    use super::*;
+    use crate::xcp::xcp_test;
+    use std::sync::{Arc, Mutex};
+
+    struct TestAlarmHandler(Arc<Mutex<Vec<(String, bool)>>>);
+
+    impl AlarmHandler for TestAlarmHandler {
+        fn alarm(&self, name: &str, _message: &str, active: bool) {
+            self.0.lock().unwrap().push((name.to_string(), active));
+        }
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test alarm notification on threshold violation and clear
+    #[test]
+    fn test_metrics_alarm() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+
+        let alarms = Arc::new(Mutex::new(Vec::new()));
+        xcp.set_alarm_handler(Box::new(TestAlarmHandler(Arc::clone(&alarms))));
+
+        let mut active = Vec::new();
+        for counter in [1u32, 5, 6, 2, 7] {
+            active.push(metrics_alarm!("test_counter", counter, 4.0, "WARNING: test_counter exceeded 4"));
+        }
+        assert_eq!(active, [false, true, true, false, true]);
+        assert_eq!(
+            *alarms.lock().unwrap(),
+            [("test_counter".to_string(), true), ("test_counter".to_string(), false), ("test_counter".to_string(), true)]
+        );
+    }
+}
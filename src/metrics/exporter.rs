@@ -0,0 +1,232 @@
+//----------------------------------------------------------------------------------------------
+// Module exporter
+// Prometheus/OpenMetrics text exposition of the metrics, served by a minimal HTTP server
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::{DaqTimingStats, Xcp, DAQ_TIMING_HIST_BINS};
+
+/// Default prefix of the exported metric names
+pub const METRICS_EXPORTER_PREFIX: &str = "xcp";
+
+// Content type of the OpenMetrics text format
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+// Name, help text and value of a per event gauge
+type DaqTimingGauge = (&'static str, &'static str, fn(&DaqTimingStats) -> f64);
+
+// Timeout for reading a request, a stalled client must not block the exporter for long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+//----------------------------------------------------------------------------------------------
+// OpenMetrics text format
+
+// Metric name in the Prometheus charset [a-zA-Z_:][a-zA-Z0-9_:]*, other characters are replaced by '_'
+fn sanitize_name(name: &str) -> String {
+    let mut s: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == ':' { c } else { '_' }).collect();
+    if s.starts_with(|c: char| c.is_ascii_digit()) {
+        s.insert(0, '_');
+    }
+    s
+}
+
+// Label value with escaped backslash, double quote and line feed
+fn escape_label(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n")
+}
+
+/// Get the metrics in the OpenMetrics text format, as served by start_http_exporter
+/// The metric names are prefixed with the sanitized prefix, per event statistics have the label event="<event name>"
+/// Exported are the trigger timing statistics of all events with enabled timing metrics (DaqEvent::enable_timing_metrics),
+/// the transmit queue size and free space and the number of dropped calibration audit records
+/// The values are read from atomics, triggers of the measured events are never blocked
+pub fn metrics_text(prefix: &str) -> String {
+    let prefix = if prefix.is_empty() { String::new() } else { format!("{}_", sanitize_name(prefix)) };
+    let xcp = Xcp::get();
+    let daq_timing_stats = xcp.get_daq_timing_stats();
+    let mut s = String::new();
+
+    // Per event gauges
+    let gauges: [DaqTimingGauge; 4] = [
+        ("daq_cycle_min_us", "Minimum cycle time of a DAQ event in us", |t| f64::from(t.cycle_min_us)),
+        ("daq_cycle_max_us", "Maximum cycle time of a DAQ event in us", |t| f64::from(t.cycle_max_us)),
+        ("daq_cycle_mean_us", "Mean cycle time of a DAQ event in us", |t| t.cycle_mean_us),
+        ("daq_jitter_max_us", "Maximum cycle time jitter of a DAQ event in us", |t| f64::from(t.jitter_max_us)),
+    ];
+    writeln!(s, "# TYPE {prefix}daq_cycles counter").unwrap();
+    writeln!(s, "# HELP {prefix}daq_cycles Number of measured cycles of a DAQ event").unwrap();
+    for (event, t) in &daq_timing_stats {
+        writeln!(s, "{prefix}daq_cycles_total{{event=\"{}\"}} {}", escape_label(event), t.count).unwrap();
+    }
+    for (name, help, value) in gauges {
+        writeln!(s, "# TYPE {prefix}{name} gauge").unwrap();
+        writeln!(s, "# HELP {prefix}{name} {help}").unwrap();
+        for (event, t) in &daq_timing_stats {
+            writeln!(s, "{prefix}{name}{{event=\"{}\"}} {}", escape_label(event), value(t)).unwrap();
+        }
+    }
+
+    // Jitter histogram with the bin boundaries of DaqTimingStats::jitter_hist
+    // Bin i counts the integer jitter values in [2^(i-1), 2^i), the inclusive upper bound is 2^i-1
+    writeln!(s, "# TYPE {prefix}daq_jitter_us histogram").unwrap();
+    writeln!(s, "# HELP {prefix}daq_jitter_us Cycle time jitter of a DAQ event in us").unwrap();
+    for (event, t) in &daq_timing_stats {
+        let event = escape_label(event);
+        let mut count: u64 = 0;
+        for (i, n) in t.jitter_hist.iter().enumerate().take(DAQ_TIMING_HIST_BINS - 1) {
+            count += u64::from(*n);
+            writeln!(s, "{prefix}daq_jitter_us_bucket{{event=\"{event}\",le=\"{}\"}} {count}", (1u64 << i) - 1).unwrap();
+        }
+        count += u64::from(t.jitter_hist[DAQ_TIMING_HIST_BINS - 1]);
+        writeln!(s, "{prefix}daq_jitter_us_bucket{{event=\"{event}\",le=\"+Inf\"}} {count}").unwrap();
+        writeln!(s, "{prefix}daq_jitter_us_count{{event=\"{event}\"}} {count}").unwrap();
+    }
+
+    // Transmit queue and calibration audit
+    writeln!(s, "# TYPE {prefix}transmit_queue_size_bytes gauge").unwrap();
+    writeln!(s, "{prefix}transmit_queue_size_bytes {}", xcp.get_transmit_queue_size()).unwrap();
+    writeln!(s, "# TYPE {prefix}transmit_queue_free_bytes gauge").unwrap();
+    writeln!(s, "{prefix}transmit_queue_free_bytes {}", xcp.get_transmit_queue_free()).unwrap();
+    writeln!(s, "# TYPE {prefix}cal_audit_dropped counter").unwrap();
+    writeln!(s, "# HELP {prefix}cal_audit_dropped Number of calibration audit records dropped by a full queue").unwrap();
+    writeln!(s, "{prefix}cal_audit_dropped_total {}", xcp.get_cal_audit_dropped()).unwrap();
+
+    s.push_str("# EOF\n");
+    s
+}
+
+//----------------------------------------------------------------------------------------------
+// HTTP exporter
+
+/// Start a thread serving the metrics at http://<bind_addr>/metrics in the OpenMetrics text format for Prometheus
+/// The metric names have the prefix METRICS_EXPORTER_PREFIX
+/// Returns the bound address, e.g. to get the port of bind_addr "127.0.0.1:0"
+pub fn start_http_exporter<A: ToSocketAddrs>(bind_addr: A) -> std::io::Result<SocketAddr> {
+    start_http_exporter_with_prefix(bind_addr, METRICS_EXPORTER_PREFIX)
+}
+
+/// Start the metrics HTTP exporter with a metric name prefix, see start_http_exporter
+/// The prefix is sanitized to the Prometheus charset, an empty prefix disables it
+pub fn start_http_exporter_with_prefix<A: ToSocketAddrs>(bind_addr: A, prefix: &str) -> std::io::Result<SocketAddr> {
+    let listener = TcpListener::bind(bind_addr)?;
+    let addr = listener.local_addr()?;
+    let prefix = prefix.to_string();
+    std::thread::Builder::new().name("metrics_exporter".to_string()).spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = serve_request(&stream, &prefix) {
+                        debug!("Metrics exporter request failed: {}", e);
+                    }
+                }
+                Err(e) => warn!("Metrics exporter accept failed: {}", e),
+            }
+        }
+    })?;
+    info!("Metrics exporter listening on http://{}/metrics", addr);
+    Ok(addr)
+}
+
+// Answer one HTTP request and close the connection
+fn serve_request(stream: &TcpStream, prefix: &str) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut request = request_line.split_whitespace();
+    let (status, content_type, body) = match (request.next(), request.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", OPENMETRICS_CONTENT_TYPE, metrics_text(prefix)),
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "Method not allowed\n".to_string()),
+    };
+    trace!("Metrics exporter: {} -> {}", request_line.trim_end(), status);
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+//-----------------------------------------------------------------------------
+// Test module
+
+#[cfg(test)]
+mod exporter_tests {
+
+    use super::*;
+    use crate::xcp::xcp_test;
+    use crate::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("my app.v2"), "my_app_v2");
+        assert_eq!(sanitize_name("2nd:task"), "_2nd:task");
+        assert_eq!(escape_label("a\"b\\c\nd"), r#"a\"b\\c\nd"#);
+    }
+
+    #[test]
+    fn test_http_exporter() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+
+        let mut event = daq_create_event!("exporter_task", 8);
+        event.enable_timing_metrics();
+        let signal: u32 = 0;
+        daq_capture!(signal, event);
+        for _ in 0..=5 {
+            std::thread::sleep(Duration::from_millis(1));
+            event.trigger();
+        }
+
+        let addr = start_http_exporter_with_prefix("127.0.0.1:0", "test app").unwrap();
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = get("/metrics");
+        let (header, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(header.starts_with("HTTP/1.1 200 OK"));
+        assert!(header.contains(&format!("Content-Type: {}", OPENMETRICS_CONTENT_TYPE)));
+        assert!(header.contains(&format!("Content-Length: {}", body.len())));
+
+        // Counter
+        assert!(body.contains("# TYPE test_app_daq_cycles counter\n"));
+        assert!(body.contains("test_app_daq_cycles_total{event=\"exporter_task\"} 5\n"));
+
+        // Histogram with cumulative buckets
+        assert!(body.contains("# TYPE test_app_daq_jitter_us histogram\n"));
+        let buckets: Vec<u64> = body
+            .lines()
+            .filter(|l| l.starts_with("test_app_daq_jitter_us_bucket{event=\"exporter_task\","))
+            .map(|l| l.rsplit(' ').next().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(buckets.len(), DAQ_TIMING_HIST_BINS);
+        assert!(buckets.windows(2).all(|w| w[0] <= w[1]));
+        assert!(body.contains("test_app_daq_jitter_us_bucket{event=\"exporter_task\",le=\"0\"} "));
+        assert!(body.contains("test_app_daq_jitter_us_bucket{event=\"exporter_task\",le=\"+Inf\"} 5\n"));
+        assert!(body.contains("test_app_daq_jitter_us_count{event=\"exporter_task\"} 5\n"));
+        assert!(body.ends_with("# EOF\n"));
+
+        assert!(get("/").starts_with("HTTP/1.1 404 Not Found"));
+    }
+}
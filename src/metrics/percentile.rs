@@ -0,0 +1,323 @@
+//----------------------------------------------------------------------------------------------
+// Module percentile
+// Percentiles (p50, p95, p99, p999) of a measured value with a t-digest sketch
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{DaqEvent, RegistryDataType, Xcp};
+
+// Number of samples between two compress operations of the t-digest
+const COMPRESS_INTERVAL: usize = 100;
+
+// Percentiles measured by PercentileMetric
+const QUANTILES: [f64; 4] = [0.5, 0.95, 0.99, 0.999];
+
+//----------------------------------------------------------------------------------------------
+// TDigest
+
+// Centroid of the t-digest, mean and number of samples
+#[derive(Debug, Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// Approximate quantiles of a stream of samples with a merging t-digest
+/// The sketch is a sorted list of at most about resolution centroids, new samples are buffered and merged by compress
+/// Centroids near the tails hold fewer samples than centroids near the median, which keeps high percentiles accurate for any distribution
+#[derive(Debug)]
+pub struct TDigest {
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    resolution: usize,
+    count: f64,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new(resolution: usize) -> TDigest {
+        assert!(resolution >= 10, "Resolution must be >= 10");
+        TDigest {
+            centroids: Vec::with_capacity(resolution),
+            buffer: Vec::with_capacity(COMPRESS_INTERVAL),
+            resolution,
+            count: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Add a sample, NaN is ignored
+    /// The sample is buffered until the next compress
+    pub fn add(&mut self, sample: f64) {
+        if sample.is_nan() {
+            return;
+        }
+        self.buffer.push(sample);
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+    }
+
+    /// Number of samples, including buffered samples
+    #[allow(clippy::cast_precision_loss)]
+    pub fn count(&self) -> f64 {
+        self.count + self.buffer.len() as f64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0.0
+    }
+
+    /// Merge the buffered samples into the centroids
+    /// Adjacent centroids are merged as long as the merged centroid spans at most 1 of the scale function k(q) = resolution / (2 * PI) * asin(2 * q - 1),
+    /// which limits the number of centroids to about resolution
+    #[allow(clippy::cast_precision_loss)]
+    pub fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all: Vec<Centroid> = self.centroids.drain(..).chain(self.buffer.drain(..).map(|mean| Centroid { mean, weight: 1.0 })).collect();
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+        let resolution = self.resolution as f64;
+        let k = |q: f64| resolution / (2.0 * std::f64::consts::PI) * (2.0 * q - 1.0).asin();
+
+        let mut merged = all[0];
+        let mut weight_before = 0.0; // Weight of the centroids before the merged centroid
+        let mut k_limit = k(0.0) + 1.0;
+        for c in all.into_iter().skip(1) {
+            let weight = merged.weight + c.weight;
+            if k(((weight_before + weight) / total).min(1.0)) <= k_limit {
+                merged.mean += (c.mean - merged.mean) * c.weight / weight;
+                merged.weight = weight;
+            } else {
+                weight_before += merged.weight;
+                k_limit = k(weight_before / total) + 1.0;
+                self.centroids.push(merged);
+                merged = c;
+            }
+        }
+        self.centroids.push(merged);
+        self.count = total;
+    }
+
+    /// Approximate quantile q (0.0..=1.0) of all samples, 0.0 if there are no samples
+    /// Interpolates linearly between the centers of the centroids, compresses buffered samples first
+    pub fn quantile(&mut self, q: f64) -> f64 {
+        self.compress();
+        if self.centroids.is_empty() {
+            return 0.0;
+        }
+        let target = q.clamp(0.0, 1.0) * self.count;
+        let mut weight_before = 0.0;
+        let mut prev: Option<(f64, f64)> = None; // Center position and mean of the previous centroid
+        for c in &self.centroids {
+            let center = weight_before + c.weight / 2.0;
+            if target < center {
+                return match prev {
+                    Some((prev_center, prev_mean)) => prev_mean + (c.mean - prev_mean) * (target - prev_center) / (center - prev_center),
+                    None => self.min + (c.mean - self.min) * target / center,
+                };
+            }
+            prev = Some((center, c.mean));
+            weight_before += c.weight;
+        }
+        let (last_center, last_mean) = prev.unwrap();
+        if self.count > last_center {
+            last_mean + (self.max - last_mean) * (target - last_center) / (self.count - last_center)
+        } else {
+            last_mean
+        }
+    }
+
+    /// Remove all samples
+    pub fn reset(&mut self) {
+        self.centroids.clear();
+        self.buffer.clear();
+        self.count = 0.0;
+        self.min = f64::INFINITY;
+        self.max = f64::NEG_INFINITY;
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// PercentileMetric
+
+// Size of the DAQ capture buffer for p50, p95, p99 and p999
+const CAPTURE_SIZE: usize = 32;
+
+/// Percentiles of a measured value
+/// The percentiles are measurement signals <name>.p50, <name>.p95, <name>.p99 and <name>.p999 of a multi instance event
+/// The event is <name> and triggered with each sample, or the event metrics_flush, triggered after each compress of the t-digest
+/// The percentiles are updated on each compress of the t-digest, every 100 samples
+/// Usually instanciated as thread local static by the macro metrics_percentile!
+#[derive(Debug)]
+pub struct PercentileMetric {
+    digest: TDigest,
+    percentiles: [f64; 4],
+    event: DaqEvent<CAPTURE_SIZE>,
+    offsets: [i16; 4],
+    flush_event: bool,
+}
+
+impl PercentileMetric {
+    /// Create a new percentile metric instance, resolution is the number of centroids of the t-digest
+    /// With flush_event, the percentiles are measured on the event metrics_flush, triggered only when the percentiles are updated
+    pub fn new(name: &'static str, resolution: usize, flush_event: bool) -> PercentileMetric {
+        let event_name = if flush_event { "metrics_flush" } else { name };
+        let mut event = DaqEvent::<CAPTURE_SIZE>::new_from(&Xcp::get().create_event_ext(event_name, true, 0));
+        let offsets = [
+            event.add_capture(leak_name(name, "p50"), 8, RegistryDataType::Float64Ieee, 1, 1, 1.0, 0.0, "", "Median", None),
+            event.add_capture(leak_name(name, "p95"), 8, RegistryDataType::Float64Ieee, 1, 1, 1.0, 0.0, "", "95th percentile", None),
+            event.add_capture(leak_name(name, "p99"), 8, RegistryDataType::Float64Ieee, 1, 1, 1.0, 0.0, "", "99th percentile", None),
+            event.add_capture(leak_name(name, "p999"), 8, RegistryDataType::Float64Ieee, 1, 1, 1.0, 0.0, "", "99.9th percentile", None),
+        ];
+        PercentileMetric {
+            digest: TDigest::new(resolution),
+            percentiles: [0.0; 4],
+            event,
+            offsets,
+            flush_event,
+        }
+    }
+
+    /// Add a sample, update the percentiles every 100 samples and trigger the measurement event
+    pub fn add(&mut self, sample: f64) {
+        self.digest.add(sample);
+        let update = self.digest.buffer.len() >= COMPRESS_INTERVAL;
+        if update {
+            for (p, q) in self.percentiles.iter_mut().zip(QUANTILES) {
+                *p = self.digest.quantile(q);
+            }
+        }
+        if update || !self.flush_event {
+            for (p, offset) in self.percentiles.iter().zip(self.offsets) {
+                self.event.capture(&p.to_le_bytes(), offset);
+            }
+            self.event.trigger();
+        }
+    }
+
+    /// Percentiles p50, p95, p99 and p999 at the last update
+    pub fn percentiles(&self) -> [f64; 4] {
+        self.percentiles
+    }
+
+    /// Remove all samples
+    pub fn reset(&mut self) {
+        self.digest.reset();
+        self.percentiles = [0.0; 4];
+    }
+}
+
+// Registry names must be static, metrics live until the end of the application
+fn leak_name(name: &str, suffix: &str) -> &'static str {
+    Box::leak(format!("{}.{}", name, suffix).into_boxed_str())
+}
+
+/// Measure the percentiles p50, p95, p99 and p999 of a value
+/// Each thread has its own instance of the metric, resolution is the number of centroids of the t-digest sketch
+/// Optional metrics_flush_event, to measure the percentiles on the event metrics_flush instead of the event <name> triggered with each sample
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! metrics_percentile {
+    ( $name:expr, $resolution:expr, $value:expr, metrics_flush_event ) => {{
+        thread_local! {
+            static METRICS__: std::cell::RefCell<Option<PercentileMetric>> = const { std::cell::RefCell::new(None) }
+        }
+        METRICS__.with_borrow_mut(|metric| {
+            metric.get_or_insert_with(|| PercentileMetric::new($name, $resolution, true)).add($value as f64);
+        });
+    }};
+    ( $name:expr, $resolution:expr, $value:expr ) => {{
+        thread_local! {
+            static METRICS__: std::cell::RefCell<Option<PercentileMetric>> = const { std::cell::RefCell::new(None) }
+        }
+        METRICS__.with_borrow_mut(|metric| {
+            metric.get_or_insert_with(|| PercentileMetric::new($name, $resolution, false)).add($value as f64);
+        });
+    }};
+}
+
+//-----------------------------------------------------------------------------
+// Test module
+
+#[cfg(test)]
+mod percentile_tests {
+
+    use super::*;
+    use crate::xcp::xcp_test;
+
+    // Gaussian samples with the Box-Muller transform of a xorshift generator, deterministic
+    fn gaussian(n: usize, mean: f64, stddev: f64) -> Vec<f64> {
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut uniform = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            ((state >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+        };
+        (0..n)
+            .map(|_| {
+                let (u1, u2) = (uniform(), uniform());
+                mean + stddev * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            })
+            .collect()
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the percentiles of a known Gaussian distribution
+    #[test]
+    fn test_tdigest_gaussian() {
+        const MEAN: f64 = 1000.0;
+        const STDDEV: f64 = 100.0;
+        let samples = gaussian(10000, MEAN, STDDEV);
+        let mut digest = TDigest::new(100);
+        for (i, s) in samples.iter().enumerate() {
+            digest.add(*s);
+            if i % COMPRESS_INTERVAL == COMPRESS_INTERVAL - 1 {
+                digest.compress();
+            }
+        }
+        assert_eq!(digest.count(), 10000.0);
+        assert!(digest.centroids.len() <= 100);
+
+        // z-scores of the standard normal distribution
+        for (q, z) in [(0.5, 0.0), (0.95, 1.6449), (0.99, 2.3263)] {
+            let expected = MEAN + z * STDDEV;
+            let p = digest.quantile(q);
+            assert!((p - expected).abs() / expected < 0.05, "q={} p={} expected={}", q, p, expected);
+        }
+
+        // p99 is also close to the exact p99 of the samples
+        let mut sorted = samples.clone();
+        sorted.sort_by(f64::total_cmp);
+        let exact = sorted[9899];
+        assert!((digest.quantile(0.99) - exact).abs() / exact < 0.01);
+
+        digest.reset();
+        assert!(digest.is_empty());
+        assert_eq!(digest.quantile(0.99), 0.0);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the registration of the percentile measurement signals
+    #[test]
+    fn test_percentile_metric() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+
+        for i in 0..200 {
+            metrics_percentile!("test_latency", 50, i);
+            metrics_percentile!("test_flushed", 50, i, metrics_flush_event);
+        }
+
+        let a2l = xcp.generate_a2l_string().unwrap();
+        for name in ["p50", "p95", "p99", "p999"] {
+            assert!(a2l.contains(&format!("test_latency.{}_1", name)));
+            assert!(a2l.contains(&format!("test_flushed.{}_1", name)));
+        }
+        assert!(a2l.contains("/begin EVENT \"metrics_flush_1\""));
+    }
+}
@@ -0,0 +1,98 @@
+//----------------------------------------------------------------------------------------------
+// Module queue_level
+// Fill level gauge of the XCP transmit queue
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{DaqEvent, RegistryDataType, Xcp};
+
+//----------------------------------------------------------------------------------------------
+// TransmitQueueGauge
+
+// Size of the DAQ capture buffer for level_bytes, free_bytes and level_percent
+const CAPTURE_SIZE: usize = 16;
+
+/// Fill level gauge of the XCP transmit queue
+/// The gauge values are measurement signals <name>.level_bytes, <name>.free_bytes and <name>.level_percent of a single instance event <name>
+/// Sampled on each trigger, e.g. from the main loop or from a producer before serializing large objects
+#[derive(Debug)]
+pub struct TransmitQueueGauge {
+    event: DaqEvent<CAPTURE_SIZE>,
+    offsets: [i16; 3],
+}
+
+impl TransmitQueueGauge {
+    /// Create a new transmit queue gauge, must be called before the A2L file is written
+    pub fn new(name: &'static str) -> TransmitQueueGauge {
+        let mut event = DaqEvent::<CAPTURE_SIZE>::new_from(&Xcp::get().create_event_ext(name, false, 0));
+        let offsets = [
+            event.add_capture(
+                leak_name(name, "level_bytes"),
+                4,
+                RegistryDataType::Ulong,
+                1,
+                1,
+                1.0,
+                0.0,
+                "byte",
+                "Transmit queue level",
+                None,
+            ),
+            event.add_capture(
+                leak_name(name, "free_bytes"),
+                4,
+                RegistryDataType::Ulong,
+                1,
+                1,
+                1.0,
+                0.0,
+                "byte",
+                "Transmit queue free space",
+                None,
+            ),
+            event.add_capture(
+                leak_name(name, "level_percent"),
+                8,
+                RegistryDataType::Float64Ieee,
+                1,
+                1,
+                1.0,
+                0.0,
+                "%",
+                "Transmit queue fill level",
+                None,
+            ),
+        ];
+        TransmitQueueGauge { event, offsets }
+    }
+
+    /// Sample the transmit queue level and trigger the measurement event
+    pub fn trigger(&mut self) {
+        let (level, free, percent) = Self::sample();
+        self.event.capture(&level.to_le_bytes(), self.offsets[0]);
+        self.event.capture(&free.to_le_bytes(), self.offsets[1]);
+        self.event.capture(&percent.to_le_bytes(), self.offsets[2]);
+        self.event.trigger();
+    }
+
+    /// Get the transmit queue fill level in percent, 0.0 if the XCP server is not started
+    pub fn level_percent(&self) -> f64 {
+        Self::sample().2
+    }
+
+    // Queue level and free space in bytes and fill level in percent
+    fn sample() -> (u32, u32, f64) {
+        let xcp = Xcp::get();
+        let size = u32::try_from(xcp.get_transmit_queue_size()).unwrap_or(u32::MAX);
+        let free = u32::try_from(xcp.get_transmit_queue_free()).unwrap_or(u32::MAX).min(size);
+        let level = size - free;
+        let percent = if size == 0 { 0.0 } else { f64::from(level) * 100.0 / f64::from(size) };
+        (level, free, percent)
+    }
+}
+
+// Registry names must be static, metrics live until the end of the application
+fn leak_name(name: &str, suffix: &str) -> &'static str {
+    Box::leak(format!("{}.{}", name, suffix).into_boxed_str())
+}
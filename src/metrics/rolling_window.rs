@@ -0,0 +1,353 @@
+//----------------------------------------------------------------------------------------------
+// Module rolling_window
+// Rolling window statistics (mean, standard deviation, min, max) of the cycle time between triggers
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use super::Alarm;
+use crate::{reg::RegistryCharacteristic, CalSeg, DaqEvent, RegistryDataType, Xcp};
+
+//----------------------------------------------------------------------------------------------
+// RollingWindow
+
+/// Circular buffer of the last samples with mean and variance over the window
+/// Mean and variance are updated incrementally with Welford's online algorithm, extended by removal of the oldest sample
+#[derive(Debug)]
+pub struct RollingWindow {
+    samples: VecDeque<u32>,
+    window_size: usize,
+    mean: f64,
+    m2: f64, // Sum of squared differences from the mean
+}
+
+impl RollingWindow {
+    pub fn new(window_size: usize) -> RollingWindow {
+        assert!(window_size > 0, "Window size must be > 0");
+        RollingWindow {
+            samples: VecDeque::with_capacity(window_size),
+            window_size,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    /// Add a sample, the oldest sample is removed when the window is full
+    pub fn push(&mut self, sample: u32) {
+        if self.samples.len() == self.window_size {
+            let oldest = self.samples.pop_front().unwrap();
+            self.remove_sample(oldest as f64);
+        }
+        self.samples.push_back(sample);
+        self.add_sample(sample as f64);
+    }
+
+    // Number of samples as f64, the window size is small
+    #[allow(clippy::cast_precision_loss)]
+    fn count(&self) -> f64 {
+        self.samples.len() as f64
+    }
+
+    fn add_sample(&mut self, x: f64) {
+        let n = self.count();
+        let delta = x - self.mean;
+        self.mean += delta / n;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn remove_sample(&mut self, x: f64) {
+        if self.samples.is_empty() {
+            self.mean = 0.0;
+            self.m2 = 0.0;
+            return;
+        }
+        let n = self.count();
+        let delta = x - self.mean;
+        self.mean -= delta / n;
+        self.m2 -= delta * (x - self.mean);
+        if self.m2 < 0.0 {
+            self.m2 = 0.0; // Rounding errors
+        }
+    }
+
+    /// Remove all samples
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.mean = 0.0;
+        self.m2 = 0.0;
+    }
+
+    /// Number of samples in the window
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Population variance of the samples in the window
+    pub fn variance(&self) -> f64 {
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.m2 / self.count()
+        }
+    }
+
+    pub fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    pub fn min(&self) -> u32 {
+        self.samples.iter().copied().min().unwrap_or(0)
+    }
+
+    pub fn max(&self) -> u32 {
+        self.samples.iter().copied().max().unwrap_or(0)
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// RollingWindowMetric
+
+/// Calibration page of a rolling window metric
+/// Setting reset from false to true, resets the statistics
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy)]
+pub struct RollingWindowMetricCalPage {
+    pub reset: bool,
+}
+
+static ROLLING_WINDOW_METRIC_CAL_PAGE: RollingWindowMetricCalPage = RollingWindowMetricCalPage { reset: false };
+
+// Size of the DAQ capture buffer for mean_us, stddev_us, min_us and max_us
+const CAPTURE_SIZE: usize = 24;
+
+/// Rolling window statistics of the cycle time between triggers
+/// The statistics are measurement signals <name>.mean_us, <name>.stddev_us, <name>.min_us and <name>.max_us of a multi instance event <name>
+/// The calibration parameter <name>.reset resets the statistics
+/// Usually instanciated as thread local static by the macro metrics_rolling_window!
+#[derive(Debug)]
+pub struct RollingWindowMetric {
+    name: &'static str,
+    window: RollingWindow,
+    resolution_us: u32,
+    last_trigger: Option<Instant>,
+    event: DaqEvent<CAPTURE_SIZE>,
+    offsets: [i16; 4],
+    calseg: CalSeg<RollingWindowMetricCalPage>,
+    reset_flag: bool, // Last state of the calibration parameter reset
+    alarm: Option<Alarm>,
+}
+
+impl RollingWindowMetric {
+    /// Create the calibration segment <name> with the reset parameter, shared by all instances of the metric
+    pub fn create_calseg(name: &'static str) -> CalSeg<RollingWindowMetricCalPage> {
+        let calseg = Xcp::get().add_calseg(name, &ROLLING_WINDOW_METRIC_CAL_PAGE);
+        let c = RegistryCharacteristic::new(
+            Some(name),
+            leak_name(name, "reset"),
+            RegistryDataType::Ubyte,
+            "Reset the rolling window statistics",
+            0.0,
+            1.0,
+            "",
+            1,
+            1,
+            0,
+        );
+        if Xcp::get().get_registry().lock().add_characteristic(c).is_err() {
+            error!("Error: Calibration parameter {}.reset already exists", name);
+        }
+        calseg
+    }
+
+    /// Create a new rolling window metric instance
+    /// window_size is the number of cycle times in the window, resolution_us is the resolution of the cycle times in us
+    pub fn new(name: &'static str, window_size: usize, resolution_us: u64, calseg: CalSeg<RollingWindowMetricCalPage>) -> RollingWindowMetric {
+        assert!(resolution_us > 0, "Resolution must be > 0");
+        let resolution_us: u32 = resolution_us.try_into().expect("Resolution out of range");
+        let mut event = DaqEvent::<CAPTURE_SIZE>::new_from(&Xcp::get().create_event_ext(name, true, 0));
+        let offsets = [
+            event.add_capture(leak_name(name, "mean_us"), 8, RegistryDataType::Float64Ieee, 1, 1, 1.0, 0.0, "us", "Mean cycle time", None),
+            event.add_capture(leak_name(name, "stddev_us"), 8, RegistryDataType::Float64Ieee, 1, 1, 1.0, 0.0, "us", "Standard deviation of the cycle time", None),
+            event.add_capture(leak_name(name, "min_us"), 4, RegistryDataType::Ulong, 1, 1, 1.0, 0.0, "us", "Minimum cycle time", None),
+            event.add_capture(leak_name(name, "max_us"), 4, RegistryDataType::Ulong, 1, 1, 1.0, 0.0, "us", "Maximum cycle time", None),
+        ];
+        RollingWindowMetric {
+            name,
+            window: RollingWindow::new(window_size),
+            resolution_us,
+            last_trigger: None,
+            event,
+            offsets,
+            calseg,
+            reset_flag: false,
+            alarm: None,
+        }
+    }
+
+    /// Notify an alarm, when the maximum cycle time in the window exceeds max_threshold_us
+    pub fn set_alarm(&mut self, max_threshold_us: f64, message: &'static str) {
+        self.alarm = Some(Alarm::new(self.name, max_threshold_us, message));
+    }
+
+    /// Add the time since the last trigger to the window and trigger the measurement event
+    pub fn trigger(&mut self) {
+        let now = Instant::now();
+        if let Some(last_trigger) = self.last_trigger {
+            let cycle_time = (now - last_trigger).as_micros() / u128::from(self.resolution_us);
+            self.window.push(u32::try_from(cycle_time).unwrap_or(u32::MAX));
+        }
+        self.last_trigger = Some(now);
+
+        // Reset on a rising edge of the calibration parameter reset
+        self.calseg.sync();
+        let reset_flag = self.calseg.reset;
+        if reset_flag && !self.reset_flag {
+            self.reset();
+        }
+        self.reset_flag = reset_flag;
+
+        self.event.capture(&self.mean_us().to_le_bytes(), self.offsets[0]);
+        self.event.capture(&self.stddev_us().to_le_bytes(), self.offsets[1]);
+        self.event.capture(&self.min_us().to_le_bytes(), self.offsets[2]);
+        self.event.capture(&self.max_us().to_le_bytes(), self.offsets[3]);
+        self.event.trigger();
+
+        if let Some(alarm) = &self.alarm {
+            alarm.check(f64::from(self.max_us()));
+        }
+    }
+
+    /// Reset the statistics, the next trigger starts a new cycle time measurement
+    pub fn reset(&mut self) {
+        debug!("Reset rolling window metric {}", self.name);
+        self.window.reset();
+        self.last_trigger = None;
+    }
+
+    pub fn mean_us(&self) -> f64 {
+        self.window.mean() * f64::from(self.resolution_us)
+    }
+
+    pub fn stddev_us(&self) -> f64 {
+        self.window.stddev() * f64::from(self.resolution_us)
+    }
+
+    pub fn min_us(&self) -> u32 {
+        u32::try_from(u64::from(self.window.min()) * u64::from(self.resolution_us)).unwrap_or(u32::MAX)
+    }
+
+    pub fn max_us(&self) -> u32 {
+        u32::try_from(u64::from(self.window.max()) * u64::from(self.resolution_us)).unwrap_or(u32::MAX)
+    }
+}
+
+// Registry names must be static, metrics live until the end of the application
+fn leak_name(name: &str, suffix: &str) -> &'static str {
+    Box::leak(format!("{}.{}", name, suffix).into_boxed_str())
+}
+
+/// Measure the rolling window statistics of the cycle time between calls
+/// Each thread has its own instance of the metric, the calibration parameter reset is shared
+/// window_size is the number of cycle times in the window, resolution_us is the resolution of the cycle times in us
+/// Optional alarm message, when the maximum cycle time in the window exceeds max_threshold_us
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! metrics_rolling_window {
+    ( $name:expr, $window_size:expr, $resolution_us:expr, max_threshold_us = $max_threshold_us:expr, $message:expr ) => {{
+        lazy_static::lazy_static! {
+            static ref METRICS_CALSEG__: std::sync::Mutex<CalSeg<RollingWindowMetricCalPage>> = std::sync::Mutex::new(RollingWindowMetric::create_calseg($name));
+        }
+        thread_local! {
+            static METRICS__: std::cell::RefCell<Option<RollingWindowMetric>> = const { std::cell::RefCell::new(None) }
+        }
+        METRICS__.with_borrow_mut(|metric| {
+            metric
+                .get_or_insert_with(|| {
+                    let mut metric = RollingWindowMetric::new($name, $window_size, $resolution_us, METRICS_CALSEG__.lock().unwrap().clone());
+                    metric.set_alarm($max_threshold_us as f64, $message);
+                    metric
+                })
+                .trigger();
+        });
+    }};
+    ( $name:expr, $window_size:expr, $resolution_us:expr ) => {{
+        lazy_static::lazy_static! {
+            static ref METRICS_CALSEG__: std::sync::Mutex<CalSeg<RollingWindowMetricCalPage>> = std::sync::Mutex::new(RollingWindowMetric::create_calseg($name));
+        }
+        thread_local! {
+            static METRICS__: std::cell::RefCell<Option<RollingWindowMetric>> = const { std::cell::RefCell::new(None) }
+        }
+        METRICS__.with_borrow_mut(|metric| {
+            metric
+                .get_or_insert_with(|| RollingWindowMetric::new($name, $window_size, $resolution_us, METRICS_CALSEG__.lock().unwrap().clone()))
+                .trigger();
+        });
+    }};
+}
+
+//-----------------------------------------------------------------------------
+// Test module
+
+#[cfg(test)]
+mod rolling_window_tests {
+
+    use super::*;
+    use crate::xcp::xcp_test;
+
+    //-----------------------------------------------------------------------------
+    // Test the window statistics with samples of known variance
+    #[test]
+    fn test_rolling_window_statistics() {
+        let mut window = RollingWindow::new(100);
+
+        // Fill the window with samples which will be removed
+        for _ in 0..100 {
+            window.push(5000);
+        }
+        assert!(window.stddev() < 1e-6);
+
+        // 100 samples 900,1100,900,... with mean 1000 and standard deviation 100
+        for i in 0..100 {
+            window.push(if i % 2 == 0 { 900 } else { 1100 });
+        }
+        assert_eq!(window.len(), 100);
+        assert!((window.mean() - 1000.0).abs() < 10.0);
+        assert!((window.stddev() - 100.0).abs() < 1.0);
+        assert_eq!(window.min(), 900);
+        assert_eq!(window.max(), 1100);
+
+        window.reset();
+        assert!(window.is_empty());
+        assert!(window.mean().abs() < 1e-6);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the registration of the metric measurement signals and calibration parameter
+    #[test]
+    fn test_rolling_window_metric() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+
+        for _ in 0..3 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            metrics_rolling_window!("test_cycle", 10, 1);
+        }
+
+        let a2l = xcp.generate_a2l_string().unwrap();
+        assert!(a2l.contains("test_cycle.mean_us_1"));
+        assert!(a2l.contains("test_cycle.stddev_us_1"));
+        assert!(a2l.contains("test_cycle.min_us_1"));
+        assert!(a2l.contains("test_cycle.max_us_1"));
+        assert!(a2l.contains("/begin CHARACTERISTIC test_cycle.reset"));
+    }
+}
@@ -133,6 +133,1759 @@ mod registry_tests {
         let _ = std::fs::remove_file("test_registry_2.a2l");
     }
 
+    //-----------------------------------------------------------------------------
+    // Test curve with a shared axis (COM_AXIS with AXIS_PTS_REF)
+    #[test]
+    fn test_registry_axis() {
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_axis");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 64);
+
+        let mut axis = RegistryCharacteristic::new(
+            Some("test_cal_seg"),
+            "test_axis",
+            crate::RegistryDataType::Float32Ieee,
+            "axis",
+            0.0,
+            1000.0,
+            "rpm",
+            8,
+            1,
+            0,
+        );
+        axis.set_axis();
+        axis.set_monotone_increasing();
+        reg.add_characteristic(axis).unwrap();
+
+        let mut curve = RegistryCharacteristic::new(
+            Some("test_cal_seg"),
+            "test_curve",
+            crate::RegistryDataType::Float32Ieee,
+            "curve",
+            0.0,
+            100.0,
+            "",
+            8,
+            1,
+            32,
+        );
+        curve.set_axis_pts_ref("test_axis");
+        reg.add_characteristic(curve).unwrap();
+
+        reg.write_a2l().unwrap();
+        let a2l = std::fs::read_to_string("test_registry_axis.a2l").unwrap();
+        assert!(a2l.contains("/begin AXIS_PTS test_axis \"axis\" 0x80010000 NO_INPUT_QUANTITY A_F32 0 NO_COMPU_METHOD 8 0 1000 PHYS_UNIT \"rpm\" MONOTONY STRICT_INCREASE"));
+        assert!(a2l.contains("/begin CHARACTERISTIC test_curve \"curve\" CURVE 0x80010020 F32"));
+        assert!(a2l.contains("/begin AXIS_DESCR COM_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 8 0 1000 AXIS_PTS_REF test_axis /end AXIS_DESCR"));
+
+        #[cfg(feature = "a2l_reader")]
+        {
+            if let Err(e) = reg.a2l_load("test_registry_axis.a2l") {
+                log::error!("A2l file check error: {}", e);
+            } else {
+                log::info!("A2L file check ok");
+            }
+        }
+
+        let _ = std::fs::remove_file("test_registry_axis.a2l");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test validation of shared axis lengths and references
+    fn add_axis_test_characteristic(reg: &mut Registry, name: &'static str, x_dim: usize, y_dim: usize, offset: u64, axis: Option<&'static str>) {
+        let mut c = RegistryCharacteristic::new(
+            Some("test_cal_seg"),
+            name,
+            crate::RegistryDataType::Float32Ieee,
+            "",
+            0.0,
+            100.0,
+            "",
+            x_dim,
+            y_dim,
+            offset,
+        );
+        match axis {
+            Some(axis) => c.set_axis_pts_ref(axis),
+            None => c.set_axis(),
+        }
+        reg.add_characteristic(c).unwrap();
+    }
+
+    #[test]
+    fn test_registry_axis_check() {
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_axis_check");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 1024);
+
+        add_axis_test_characteristic(&mut reg, "axis_9", 9, 1, 0, None);
+        add_axis_test_characteristic(&mut reg, "axis_16", 16, 1, 36, None);
+        add_axis_test_characteristic(&mut reg, "curve_ok", 16, 1, 100, Some("axis_16"));
+        add_axis_test_characteristic(&mut reg, "curve_y_ok", 1, 9, 164, Some("axis_9"));
+        add_axis_test_characteristic(&mut reg, "map_ok", 9, 2, 200, Some("axis_9"));
+        assert!(reg.check_axis_refs().is_ok());
+
+        // Mismatching lengths, unknown axis and a reference to a calibration parameter which is not an axis
+        add_axis_test_characteristic(&mut reg, "curve_16", 16, 1, 272, Some("axis_9"));
+        add_axis_test_characteristic(&mut reg, "map_4x4", 4, 4, 336, Some("axis_16"));
+        add_axis_test_characteristic(&mut reg, "curve_unknown", 8, 1, 400, Some("axis_unknown"));
+        add_axis_test_characteristic(&mut reg, "curve_not_axis", 8, 1, 432, Some("curve_ok"));
+
+        let Err(RegistryError::AxisCheck(errors)) = reg.check_axis_refs() else {
+            panic!("axis check must fail");
+        };
+        assert_eq!(errors.len(), 4);
+        assert!(matches!(&errors[0], RegistryError::AxisLength { characteristic, axis, len: 16, axis_len: 9 } if characteristic == "curve_16" && axis == "axis_9"));
+        assert!(matches!(&errors[1], RegistryError::AxisLength { characteristic, axis, len: 4, axis_len: 16 } if characteristic == "map_4x4" && axis == "axis_16"));
+        assert!(matches!(&errors[2], RegistryError::AxisRef { characteristic, axis } if characteristic == "curve_unknown" && axis == "axis_unknown"));
+        assert!(matches!(&errors[3], RegistryError::AxisRef { characteristic, axis } if characteristic == "curve_not_axis" && axis == "curve_ok"));
+
+        // All errors are reported at once
+        let message = reg.write_a2l_to(&mut Vec::new()).unwrap_err().to_string();
+        assert!(message.contains("`curve_16` has 16 x axis points, but its axis `axis_9` has 9 points"));
+        assert!(message.contains("`curve_not_axis` refers to `curve_ok`, which is not a registered axis"));
+        assert_eq!(message.lines().count(), 4);
+
+        // Warning only mode for transition, unknown axes are written as fixed axis
+        reg.set_axis_check_warn_only(true);
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert!(a2l.contains("COM_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 16 0 100 AXIS_PTS_REF axis_9"));
+        assert!(!a2l.contains("AXIS_PTS_REF axis_unknown") && !a2l.contains("AXIS_PTS_REF curve_ok"));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test user annotations of measurement signals and calibration parameters
+    #[test]
+    fn test_registry_annotation() {
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_annotation");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 4);
+
+        let mut m = RegistryMeasurement::new(
+            "test_signal",
+            crate::RegistryDataType::Float32Ieee,
+            1,
+            1,
+            crate::XcpEvent::new(0, 0),
+            0,
+            0,
+            1.0,
+            0.0,
+            "",
+            "",
+            None,
+        );
+        m.add_annotation("Metadata", "application/json", ["{\"sensor\": \"front\",", " \"path\": \"C:\\data\"}"]);
+        reg.add_measurement(m).unwrap();
+        let c = RegistryCharacteristic::new(Some("test_cal_seg"), "test_param", crate::RegistryDataType::Ulong, "", 0.0, 100.0, "", 1, 1, 0);
+        reg.add_characteristic(c).unwrap();
+
+        // Multi line text and annotation of an already registered symbol
+        reg.add_annotation("test_param", RegistryAnnotation::new("Note", "", ["line 1\nline 2"])).unwrap();
+        assert!(matches!(
+            reg.add_annotation("unknown", RegistryAnnotation::new("Note", "", ["text"])),
+            Err(RegistryError::NotFound("unknown"))
+        ));
+
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert!(a2l.contains(r#"/begin ANNOTATION ANNOTATION_LABEL "Metadata" ANNOTATION_ORIGIN "application/json""#));
+        assert!(a2l.contains(r#""{\"sensor\": \"front\",""#));
+        assert!(a2l.contains(r#"" \"path\": \"C:\\data\"}""#));
+        assert!(a2l.contains("    \"line 1\"\n    \"line 2\"\n"));
+
+        // The annotations survive loading the A2L
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        let annotation = &module.measurement.iter().find(|m| m.name == "test_signal").unwrap().annotation[0];
+        assert_eq!(annotation.annotation_label.as_ref().unwrap().label, "Metadata");
+        let text = &annotation.annotation_text.as_ref().unwrap().annotation_text_list;
+        assert_eq!(text[1], r#" "path": "C:\data"}"#);
+        let annotation = &module.characteristic.iter().find(|c| c.name == "test_param").unwrap().annotation[0];
+        assert_eq!(annotation.annotation_text.as_ref().unwrap().annotation_text_list, vec!["line 1", "line 2"]);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test display format, step size and extended limits of calibration parameters and measurement signals
+    #[test]
+    fn test_registry_display() {
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        struct DisplayPage {
+            #[type_description(min = "0", max = "100", step = "0.5", format = "%8.3", extended_min = "-10", extended_max = "200")]
+            a: f64,
+            b: f64,
+        }
+        let page = DisplayPage { a: 0.0, b: 0.0 };
+
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_display");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 16);
+        for field in page.type_description().unwrap().iter() {
+            let mut c = RegistryCharacteristic::new(
+                Some("test_cal_seg"),
+                field.name().to_string(),
+                RegistryDataType::from_rust_type(field.datatype()),
+                field.comment(),
+                field.min(),
+                field.max(),
+                field.unit(),
+                1,
+                1,
+                field.offset() as u64,
+            );
+            if let Some(step) = field.step() {
+                c.set_step(step);
+            }
+            if !field.format().is_empty() {
+                c.set_format(field.format());
+            }
+            if let Some((min, max)) = field.extended_limits() {
+                c.set_extended_limits(min, max);
+            }
+            reg.add_characteristic(c).unwrap();
+        }
+
+        let mut m = RegistryMeasurement::new(
+            "test_signal",
+            crate::RegistryDataType::Float32Ieee,
+            1,
+            1,
+            crate::XcpEvent::new(0, 0),
+            0,
+            0,
+            1.0,
+            0.0,
+            "",
+            "",
+            None,
+        );
+        m.set_format("%6.1");
+        reg.add_measurement(m).unwrap();
+
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        let a = a2l.lines().find(|l| l.contains("CHARACTERISTIC DisplayPage.a ")).unwrap();
+        assert!(a.contains(r#"FORMAT "%8.3""#) && a.contains("STEP_SIZE 0.5") && a.contains("EXTENDED_LIMITS -10 200"));
+        let b = a2l.lines().find(|l| l.contains("CHARACTERISTIC DisplayPage.b ")).unwrap();
+        assert!(!b.contains("FORMAT") && !b.contains("STEP_SIZE") && !b.contains("EXTENDED_LIMITS"));
+        assert!(a2l.lines().any(|l| l.contains("MEASUREMENT test_signal ") && l.contains(r#"FORMAT "%6.1""#)));
+
+        // The attributes survive loading the A2L
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        let a = module.characteristic.iter().find(|c| c.name == "DisplayPage.a").unwrap();
+        assert_eq!(a.format.as_ref().unwrap().format_string, "%8.3");
+        assert_eq!(a.step_size.as_ref().unwrap().step_size, 0.5);
+        let limits = a.extended_limits.as_ref().unwrap();
+        assert_eq!((limits.lower_limit, limits.upper_limit), (-10.0, 200.0));
+        let b = module.characteristic.iter().find(|c| c.name == "DisplayPage.b").unwrap();
+        assert!(b.format.is_none() && b.step_size.is_none() && b.extended_limits.is_none());
+        let m = module.measurement.iter().find(|m| m.name == "test_signal").unwrap();
+        assert_eq!(m.format.as_ref().unwrap().format_string, "%6.1");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test measurement signals with a physical type different from the storage type
+    #[test]
+    fn test_registry_phys_type() {
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_phys_type");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        let event = crate::XcpEvent::new(0, 0);
+
+        // Voltage in mV stored as i16, physical value in Volt
+        let voltage = RegistryMeasurement::new("voltage", RegistryDataType::Sword, 1, 1, event, 0, 0, 0.001, 0.0, "", "Volt", None);
+        reg.add_measurement(voltage).unwrap();
+        reg.set_measurement_phys_type("voltage", event, RegistryDataType::Float64Ieee).unwrap();
+
+        // Percentage stored as u16, physical value is an integer 0..100
+        let mut level = RegistryMeasurement::new("level", RegistryDataType::Uword, 1, 1, event, 2, 0, 1.0, 0.0, "", "%", None);
+        level.set_phys_type(RegistryDataType::Ubyte);
+        level.set_format("%3.0");
+        reg.add_measurement(level).unwrap();
+        assert!(reg.set_measurement_phys_type("unknown", event, RegistryDataType::Float64Ieee).is_err());
+
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert!(a2l.contains(r#"/begin COMPU_METHOD voltage.Conv "" LINEAR "%6.3" "Volt" COEFFS_LINEAR 0.001 0 /end COMPU_METHOD"#));
+        assert!(a2l.contains(r#"/begin COMPU_METHOD level.Conv "" LINEAR "%.0" "%" COEFFS_LINEAR 1 0 /end COMPU_METHOD"#));
+
+        // The raw type is the storage type, the limits are physical values
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        let voltage = module.measurement.iter().find(|m| m.name == "voltage").unwrap();
+        assert_eq!(voltage.datatype, a2lfile::DataType::Sword);
+        assert_eq!(voltage.conversion, "voltage.Conv");
+        assert!((voltage.lower_limit + 32.768).abs() < 1e-9 && (voltage.upper_limit - 32.767).abs() < 1e-9);
+        let level = module.measurement.iter().find(|m| m.name == "level").unwrap();
+        assert_eq!(level.datatype, a2lfile::DataType::Uword);
+        assert_eq!((level.lower_limit, level.upper_limit), (0.0, 255.0));
+        assert_eq!(level.format.as_ref().unwrap().format_string, "%3.0");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the limits of measurement signals, integer limits are written as integers
+    #[test]
+    fn test_registry_measurement_limits() {
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_measurement_limits");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        let event = crate::XcpEvent::new(0, 0);
+
+        // Without limits, the limits are the value range of the integer type
+        reg.add_measurement(RegistryMeasurement::new("counter", RegistryDataType::Slong, 1, 1, event, 0, 0, 1.0, 0.0, "", "", None))
+            .unwrap();
+        reg.add_measurement(RegistryMeasurement::new("ticks", RegistryDataType::AUint64, 1, 1, event, 8, 0, 1.0, 0.0, "", "", None))
+            .unwrap();
+        reg.add_measurement(RegistryMeasurement::new("delta", RegistryDataType::AInt64, 1, 1, event, 16, 0, 1.0, 0.0, "", "", None))
+            .unwrap();
+
+        // Explicit limits are rounded and clamped for integer types, floats and converted values are unchanged
+        let mut level = RegistryMeasurement::new("level", RegistryDataType::Uword, 1, 1, event, 24, 0, 1.0, 0.0, "", "", None);
+        level.set_limits(-10.0, 1000.4);
+        reg.add_measurement(level).unwrap();
+        reg.add_measurement(RegistryMeasurement::new("position", RegistryDataType::AInt64, 1, 1, event, 32, 0, 1.0, 0.0, "", "", None))
+            .unwrap();
+        reg.set_measurement_limits("position", event, -1e19, 1e19).unwrap();
+        let mut temperature = RegistryMeasurement::new("temperature", RegistryDataType::Float32Ieee, 1, 1, event, 40, 0, 1.0, 0.0, "", "", None);
+        temperature.set_limits(-40.5, 150.25);
+        reg.add_measurement(temperature).unwrap();
+        let mut voltage = RegistryMeasurement::new("voltage", RegistryDataType::Sword, 1, 1, event, 44, 0, 0.001, 0.0, "", "V", None);
+        voltage.set_limits(-0.5, 12.5);
+        reg.add_measurement(voltage).unwrap();
+        assert!(reg.set_measurement_limits("unknown", event, 0.0, 1.0).is_err());
+
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert!(a2l.contains(r#"/begin MEASUREMENT counter "" SLONG NO_COMPU_METHOD 0 0 -2147483648 2147483647 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT ticks "" A_UINT64 NO_COMPU_METHOD 0 0 0 18446744073709551615 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT delta "" A_INT64 NO_COMPU_METHOD 0 0 -9223372036854775808 9223372036854775807 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT level "" UWORD NO_COMPU_METHOD 0 0 0 1000 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT position "" A_INT64 NO_COMPU_METHOD 0 0 -9223372036854775808 9223372036854775807 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT temperature "" FLOAT32_IEEE NO_COMPU_METHOD 0 0 -40.5 150.25 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT voltage "" SWORD voltage.Conv 0 0 -0.5 12.5 "#));
+
+        // The i32 range is exact in the parsed A2L file
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let counter = a2l_file.project.module[0].measurement.iter().find(|m| m.name == "counter").unwrap();
+        assert_eq!((counter.lower_limit, counter.upper_limit), (i32::MIN as f64, i32::MAX as f64));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test display names of calibration parameters in nested structs and of measurement signals
+    #[test]
+    fn test_registry_display_identifier() {
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        struct Point {
+            #[type_description(display = "PointX")]
+            x: f32,
+            y: f32,
+        }
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        struct Data {
+            point: Point,
+        }
+        let data = Data { point: Point { x: 0.0, y: 0.0 } };
+
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_display_identifier");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 8);
+        for field in data.type_description().unwrap().iter() {
+            let mut c = RegistryCharacteristic::new(
+                Some("test_cal_seg"),
+                field.name().to_string(),
+                RegistryDataType::from_rust_type(field.datatype()),
+                field.comment(),
+                field.min(),
+                field.max(),
+                field.unit(),
+                1,
+                1,
+                field.offset() as u64,
+            );
+            if !field.display().is_empty() {
+                c.set_display(field.display());
+            }
+            reg.add_characteristic(c).unwrap();
+        }
+
+        let mut m = RegistryMeasurement::new(
+            "engine.speed",
+            crate::RegistryDataType::Float32Ieee,
+            1,
+            1,
+            crate::XcpEvent::new(0, 0),
+            0,
+            0,
+            1.0,
+            0.0,
+            "",
+            "",
+            None,
+        );
+        m.set_display("RPM");
+        reg.add_measurement(m).unwrap();
+
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert!(a2l.lines().any(|l| l.contains("CHARACTERISTIC Data.Point.x ") && l.contains("DISPLAY_IDENTIFIER PointX")));
+        assert!(a2l.lines().any(|l| l.contains("CHARACTERISTIC Data.Point.y ") && !l.contains("DISPLAY_IDENTIFIER")));
+        assert!(a2l.lines().any(|l| l.contains("MEASUREMENT engine.speed ") && l.contains("DISPLAY_IDENTIFIER RPM")));
+
+        // The canonical names are kept, the display names survive loading the A2L
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        let x = module.characteristic.iter().find(|c| c.name == "Data.Point.x").unwrap();
+        assert_eq!(x.display_identifier.as_ref().unwrap().display_name, "PointX");
+        let m = module.measurement.iter().find(|m| m.name == "engine.speed").unwrap();
+        assert_eq!(m.display_identifier.as_ref().unwrap().display_name, "RPM");
+
+        // Display names must be valid identifiers
+        let mut m = RegistryMeasurement::new("rpm", crate::RegistryDataType::Float32Ieee, 1, 1, crate::XcpEvent::new(0, 0), 4, 0, 1.0, 0.0, "", "", None);
+        m.set_display("engine speed");
+        reg.add_measurement(m).unwrap();
+        assert!(has_finding(&reg.check_a2l(), A2lCheckSeverity::Error, "engine speed"));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test verbal conversion of bool calibration parameters
+    #[test]
+    fn test_registry_verbal_bool() {
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        struct BoolPage {
+            #[type_description(verbal_bool = "true")]
+            enable: bool,
+            #[type_description(verbal_bool = "true")]
+            flags: [bool; 4],
+            run: bool,
+        }
+        let page = BoolPage {
+            enable: false,
+            flags: [false; 4],
+            run: false,
+        };
+
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_verbal_bool");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 8);
+        for field in page.type_description().unwrap().iter() {
+            let mut c = RegistryCharacteristic::new(
+                Some("test_cal_seg"),
+                field.name().to_string(),
+                RegistryDataType::from_rust_type(field.datatype()),
+                field.comment(),
+                field.min(),
+                field.max(),
+                field.unit(),
+                field.x_dim().max(1),
+                field.y_dim().max(1),
+                field.offset() as u64,
+            );
+            if field.verbal_bool() == Some(true) {
+                c.set_verbal_bool();
+            }
+            reg.add_characteristic(c).unwrap();
+        }
+
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert_eq!(a2l.matches("/begin COMPU_VTAB").count(), 1);
+
+        // The data type stays a byte, the tool shows false and true
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        let vtab = &module.compu_vtab[0];
+        let pairs: Vec<(f64, &str)> = vtab.value_pairs.iter().map(|p| (p.in_val, p.out_val.as_str())).collect();
+        assert_eq!(pairs, [(0.0, "false"), (1.0, "true")]);
+        assert_eq!(module.compu_method[0].compu_tab_ref.as_ref().unwrap().conversion_table, vtab.name);
+        for name in ["BoolPage.enable", "BoolPage.flags"] {
+            let c = module.characteristic.iter().find(|c| c.name == name).unwrap();
+            assert_eq!(c.conversion, module.compu_method[0].name);
+            assert_eq!((c.lower_limit, c.upper_limit), (0.0, 1.0));
+            assert_eq!(c.deposit, "U8");
+        }
+        let run = module.characteristic.iter().find(|c| c.name == "BoolPage.run").unwrap();
+        assert_eq!(run.conversion, "NO_COMPU_METHOD");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test A2L FUNCTION groups of calibration parameters
+    #[test]
+    fn test_registry_function() {
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        struct Pid {
+            kp: f32,
+            #[type_description(function = "Tuning")]
+            ki: f32,
+        }
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        struct FunctionPage {
+            #[type_description(function = "IdleControl")]
+            idle_speed: u16,
+            #[type_description(function = "IdleControl", ref_function = "Diagnosis", ref_function = "Tuning")]
+            idle_gain: f32,
+            #[type_description(function = "IdleControl")]
+            pid: Pid,
+            #[type_description(ref_function = "Diagnosis")]
+            limit: u16,
+            other: u8,
+        }
+        let page = FunctionPage {
+            idle_speed: 0,
+            idle_gain: 0.0,
+            pid: Pid { kp: 0.0, ki: 0.0 },
+            limit: 0,
+            other: 0,
+        };
+
+        // Register the fields in the given order
+        fn registry(page: &FunctionPage, reverse: bool) -> String {
+            let mut reg = Registry::new();
+            reg.set_name("test_registry_function");
+            reg.set_epk("TEST_EPK", 0x80000000);
+            reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+            reg.add_cal_seg("test_cal_seg", 0, 32);
+            let mut fields: Vec<_> = page.type_description().unwrap().into_iter().collect();
+            if reverse {
+                fields.reverse();
+            }
+            for field in fields {
+                let mut c = RegistryCharacteristic::new(
+                    Some("test_cal_seg"),
+                    field.name().to_string(),
+                    RegistryDataType::from_rust_type(field.datatype()),
+                    field.comment(),
+                    field.min(),
+                    field.max(),
+                    field.unit(),
+                    1,
+                    1,
+                    field.offset() as u64,
+                );
+                if !field.function().is_empty() {
+                    c.set_function(field.function());
+                }
+                for function in field.ref_functions() {
+                    c.add_ref_function(*function);
+                }
+                reg.add_characteristic(c).unwrap();
+            }
+            let mut a2l = Vec::new();
+            reg.write_a2l_to(&mut a2l).unwrap();
+            String::from_utf8(a2l).unwrap()
+        }
+
+        // The grouping does not depend on the registration order
+        let a2l = registry(&page, false);
+        assert_eq!(a2l, registry(&page, true));
+
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        let functions: Vec<(&str, Vec<&str>, Vec<&str>)> = module
+            .function
+            .iter()
+            .map(|f| {
+                (
+                    f.name.as_str(),
+                    f.def_characteristic.as_ref().map_or(Vec::new(), |d| d.identifier_list.iter().map(String::as_str).collect()),
+                    f.ref_characteristic.as_ref().map_or(Vec::new(), |r| r.identifier_list.iter().map(String::as_str).collect()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            functions,
+            [
+                ("Diagnosis", vec![], vec!["FunctionPage.idle_gain", "FunctionPage.limit"]),
+                ("IdleControl", vec!["FunctionPage.Pid.kp", "FunctionPage.idle_gain", "FunctionPage.idle_speed"], vec![]),
+                ("Tuning", vec!["FunctionPage.Pid.ki"], vec!["FunctionPage.idle_gain"]),
+            ]
+        );
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test limits derived from and clamped to the value range of integer data types
+    #[test]
+    fn test_registry_type_limits() {
+        fn new_limits_registry(type_limits: bool) -> Registry {
+            let mut reg = Registry::new();
+            reg.set_name("test_registry_type_limits");
+            reg.set_epk("TEST_EPK", 0x80000000);
+            reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+            reg.add_cal_seg("test_cal_seg", 0, 64);
+            reg.set_type_limits(type_limits);
+            reg
+        }
+        fn new_limits_characteristic(name: &'static str, datatype: &str, min: f64, max: f64) -> RegistryCharacteristic {
+            RegistryCharacteristic::new(Some("test_cal_seg"), name, RegistryDataType::from_rust_type(datatype), "", min, max, "", 1, 1, 0)
+        }
+
+        // No limits, default to the value range of each integer type
+        let mut reg = new_limits_registry(true);
+        for (name, limits) in [
+            ("u8", (0.0, 255.0)),
+            ("i8", (-128.0, 127.0)),
+            ("u16", (0.0, 65535.0)),
+            ("i16", (-32768.0, 32767.0)),
+            ("u32", (0.0, 4294967295.0)),
+            ("i32", (-2147483648.0, 2147483647.0)),
+            ("u64", (0.0, 1E12)),
+            ("i64", (-1E12, 1E12)),
+        ] {
+            reg.add_characteristic(new_limits_characteristic(name, name, 0.0, 0.0)).unwrap();
+            assert_eq!(reg.find_characteristic(name).unwrap().get_limits(), limits, "{}", name);
+        }
+
+        // Floating point types are unchanged
+        reg.add_characteristic(new_limits_characteristic("f32", "f32", 0.0, 0.0)).unwrap();
+        assert_eq!(reg.find_characteristic("f32").unwrap().get_limits(), (0.0, 0.0));
+
+        // Explicit limits take precedence
+        reg.add_characteristic(new_limits_characteristic("u8_limits", "u8", 10.0, 20.0)).unwrap();
+        assert_eq!(reg.find_characteristic("u8_limits").unwrap().get_limits(), (10.0, 20.0));
+
+        // Limits exceeding the value range are clamped
+        let mut c = new_limits_characteristic("u8_clamped", "u8", -1.0, 300.0);
+        c.set_extended_limits(-10.0, 1000.0);
+        reg.add_characteristic(c).unwrap();
+        let c = reg.find_characteristic("u8_clamped").unwrap();
+        assert_eq!(c.get_limits(), (0.0, 255.0));
+        assert_eq!(c.get_extended_limits(), Some((0.0, 255.0)));
+        reg.add_characteristic(new_limits_characteristic("i16_clamped", "i16", -40000.0, 100.0)).unwrap();
+        assert_eq!(reg.find_characteristic("i16_clamped").unwrap().get_limits(), (-32768.0, 100.0));
+
+        // Limits from type description attributes
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        struct LimitsPage {
+            #[type_description(min = "-1")]
+            level: u8,
+            #[type_description(min = "-10", max = "10")]
+            offset: i8,
+        }
+        let page = LimitsPage { level: 0, offset: 0 };
+        for field in page.type_description().unwrap().iter() {
+            let name: &'static str = Box::leak(field.name().to_string().into_boxed_str());
+            reg.add_characteristic(new_limits_characteristic(name, field.datatype(), field.min(), field.max())).unwrap();
+        }
+        assert_eq!(reg.find_characteristic("LimitsPage.level").unwrap().get_limits(), (0.0, 255.0));
+        assert_eq!(reg.find_characteristic("LimitsPage.offset").unwrap().get_limits(), (-10.0, 10.0));
+
+        // Disabled, the limits are unchanged
+        let mut reg = new_limits_registry(false);
+        reg.add_characteristic(new_limits_characteristic("u8", "u8", 0.0, 0.0)).unwrap();
+        assert_eq!(reg.find_characteristic("u8").unwrap().get_limits(), (0.0, 0.0));
+        reg.add_characteristic(new_limits_characteristic("u8_clamped", "u8", -1.0, 300.0)).unwrap();
+        assert_eq!(reg.find_characteristic("u8_clamped").unwrap().get_limits(), (-1.0, 300.0));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the registry default limits of signals without limits and without conversion
+    #[test]
+    fn test_registry_default_limits() {
+        fn write_a2l(default_limits: DefaultLimits) -> String {
+            let mut reg = Registry::new();
+            reg.set_name("test_registry_default_limits");
+            reg.set_epk("TEST_EPK", 0x80000000);
+            reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+            reg.add_cal_seg("test_cal_seg", 0, 64);
+            reg.set_type_limits(false);
+            reg.set_default_limits(default_limits);
+            let event = crate::XcpEvent::new(0, 0);
+            reg.add_measurement(RegistryMeasurement::new("counter", RegistryDataType::Uword, 1, 1, event, 0, 0, 1.0, 0.0, "", "", None))
+                .unwrap();
+            reg.add_measurement(RegistryMeasurement::new("speed", RegistryDataType::Float32Ieee, 1, 1, event, 4, 0, 1.0, 0.0, "", "", None))
+                .unwrap();
+            reg.add_measurement(RegistryMeasurement::new("voltage", RegistryDataType::Sword, 1, 1, event, 8, 0, 0.001, 0.0, "", "V", None))
+                .unwrap();
+            let mut level = RegistryMeasurement::new("level", RegistryDataType::Uword, 1, 1, event, 12, 0, 1.0, 0.0, "", "", None);
+            level.set_limits(0.0, 100.0);
+            reg.add_measurement(level).unwrap();
+            let gain = RegistryCharacteristic::new(Some("test_cal_seg"), "gain", RegistryDataType::Float32Ieee, "", 0.0, 0.0, "", 1, 1, 0);
+            reg.add_characteristic(gain).unwrap();
+            let offset = RegistryCharacteristic::new(Some("test_cal_seg"), "offset", RegistryDataType::Sbyte, "", -10.0, 10.0, "", 1, 1, 4);
+            reg.add_characteristic(offset).unwrap();
+            let mut a2l = Vec::new();
+            reg.write_a2l_to(&mut a2l).unwrap();
+            String::from_utf8(a2l).unwrap()
+        }
+
+        // Type range, the default
+        let a2l = write_a2l(DefaultLimits::default());
+        assert!(a2l.contains(r#"/begin MEASUREMENT counter "" UWORD NO_COMPU_METHOD 0 0 0 65535 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT speed "" FLOAT32_IEEE NO_COMPU_METHOD 0 0 -1E15 1E15 "#));
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC gain "" VALUE 0x80010000 F32 0 NO_COMPU_METHOD -1E15 1E15"#));
+
+        // Sentinels for signals without limits and without conversion, explicit limits and conversions are unchanged
+        let a2l = write_a2l(DefaultLimits::Sentinel);
+        assert!(a2l.contains(r#"/begin MEASUREMENT counter "" UWORD NO_COMPU_METHOD 0 0 -3.4E38 3.4E38 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT speed "" FLOAT32_IEEE NO_COMPU_METHOD 0 0 -3.4E38 3.4E38 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT voltage "" SWORD voltage.Conv 0 0 -32768 32767 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT level "" UWORD NO_COMPU_METHOD 0 0 0 100 "#));
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC gain "" VALUE 0x80010000 F32 0 NO_COMPU_METHOD -3.4E38 3.4E38"#));
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC offset "" VALUE 0x80010004 S8 0 NO_COMPU_METHOD -10 10"#));
+
+        // The sentinels are valid A2L limits
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let counter = a2l_file.project.module[0].measurement.iter().find(|m| m.name == "counter").unwrap();
+        assert_eq!((counter.lower_limit, counter.upper_limit), (-3.4E38, 3.4E38));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the RECORD_LAYOUT of row and column major maps
+    #[test]
+    fn test_registry_row_major() {
+        fn write_a2l(row_major: bool) -> String {
+            let mut reg = Registry::new();
+            reg.set_name("test_registry_row_major");
+            reg.set_epk("TEST_EPK", 0x80000000);
+            reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+            reg.add_cal_seg("test_cal_seg", 0, 128);
+            reg.set_row_major(row_major);
+            let map = RegistryCharacteristic::new(Some("test_cal_seg"), "map", RegistryDataType::Float32Ieee, "", -10.0, 10.0, "", 2, 4, 0);
+            reg.add_characteristic(map).unwrap();
+            let map_u8 = RegistryCharacteristic::new(Some("test_cal_seg"), "map_u8", RegistryDataType::Ubyte, "", 0.0, 255.0, "", 2, 4, 32);
+            reg.add_characteristic(map_u8).unwrap();
+            let curve = RegistryCharacteristic::new(Some("test_cal_seg"), "curve", RegistryDataType::Float32Ieee, "", -10.0, 10.0, "", 8, 1, 40);
+            reg.add_characteristic(curve).unwrap();
+            let mut a2l = Vec::new();
+            reg.write_a2l_to(&mut a2l).unwrap();
+            String::from_utf8(a2l).unwrap()
+        }
+
+        // Row major, the default
+        let a2l = write_a2l(true);
+        assert!(a2l.contains("/begin RECORD_LAYOUT F32 FNC_VALUES 1 FLOAT32_IEEE ROW_DIR DIRECT /end RECORD_LAYOUT"));
+        assert!(a2l.contains("/begin RECORD_LAYOUT S64 FNC_VALUES 1 A_INT64 ROW_DIR DIRECT /end RECORD_LAYOUT"));
+        assert!(!a2l.contains("COLUMN_DIR"));
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC map "" MAP 0x80010000 F32 0 NO_COMPU_METHOD -10 10"#));
+
+        // Column major maps reference a COLUMN_DIR record layout for each data type, curves are unchanged
+        let a2l = write_a2l(false);
+        assert!(a2l.contains("/begin RECORD_LAYOUT F32_COL FNC_VALUES 1 FLOAT32_IEEE COLUMN_DIR DIRECT /end RECORD_LAYOUT"));
+        assert!(a2l.contains("/begin RECORD_LAYOUT U8_COL FNC_VALUES 1 UBYTE COLUMN_DIR DIRECT /end RECORD_LAYOUT"));
+        assert_eq!(a2l.matches("/begin RECORD_LAYOUT F32_COL ").count(), 1);
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC map "" MAP 0x80010000 F32_COL 0 NO_COMPU_METHOD -10 10"#));
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC map_u8 "" MAP 0x80010020 U8_COL 0 NO_COMPU_METHOD 0 255"#));
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC curve "" CURVE 0x80010028 F32 0 NO_COMPU_METHOD -10 10"#));
+
+        // The record layouts are valid and referenced
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        let map = module.characteristic.iter().find(|c| c.name == "map").unwrap();
+        let record_layout = module.record_layout.iter().find(|r| r.name == map.deposit).unwrap();
+        assert_eq!(record_layout.fnc_values.as_ref().unwrap().index_mode, a2lfile::IndexMode::ColumnDir);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test physically possible limits separate from plausible limits
+    #[test]
+    fn test_registry_extended_limits() {
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_extended_limits");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 64);
+        let event = crate::XcpEvent::new(0, 0);
+        let mut temperature = RegistryMeasurement::new("temperature", RegistryDataType::Sword, 1, 1, event, 0, 0, 1.0, 0.0, "", "", None);
+        temperature.set_limits(-40.0, 150.0);
+        temperature.set_extended_limits(-273.0, 1000.0);
+        assert_eq!(temperature.get_limits(), Some((-40.0, 150.0)));
+        assert_eq!(temperature.get_extended_limits(), Some((-273.0, 1000.0)));
+        reg.add_measurement(temperature).unwrap();
+        let mut pressure = RegistryMeasurement::new("pressure", RegistryDataType::Float32Ieee, 1, 1, event, 4, 0, 1.0, 0.0, "", "", None);
+        pressure.set_limits(0.0, 10.0);
+        reg.add_measurement(pressure).unwrap();
+        reg.set_measurement_extended_limits("pressure", event, -1.0, 20.5).unwrap();
+        let mut gain = RegistryCharacteristic::new(Some("test_cal_seg"), "gain", RegistryDataType::Float32Ieee, "", 0.0, 10.0, "", 1, 1, 0);
+        gain.set_extended_limits(-100.0, 100.0);
+        reg.add_characteristic(gain).unwrap();
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+
+        // Measurements have the physical range as LIMITS and the plausible range as annotation
+        assert!(a2l.contains(r#"/begin MEASUREMENT temperature "" SWORD NO_COMPU_METHOD 0 0 -273 1000 "#));
+        assert!(a2l.contains(r#"/begin MEASUREMENT pressure "" FLOAT32_IEEE NO_COMPU_METHOD 0 0 -1 20.5 "#));
+        assert_eq!(a2l.matches("ANNOTATION_LABEL \"PLAUSIBLE_LIMITS\"").count(), 2);
+        assert!(a2l.contains("EXTENDED_LIMITS -100 100"));
+
+        // Both pairs are parsed back
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        let m = module.measurement.iter().find(|m| m.name == "temperature").unwrap();
+        assert_eq!((m.lower_limit, m.upper_limit), (-273.0, 1000.0));
+        let annotation = &m.annotation[0];
+        assert_eq!(annotation.annotation_label.as_ref().unwrap().label, "PLAUSIBLE_LIMITS");
+        assert_eq!(annotation.annotation_text.as_ref().unwrap().annotation_text_list, ["-40", "150"]);
+        let m = module.measurement.iter().find(|m| m.name == "pressure").unwrap();
+        assert_eq!(m.annotation[0].annotation_text.as_ref().unwrap().annotation_text_list, ["0", "10"]);
+        let c = module.characteristic.iter().find(|c| c.name == "gain").unwrap();
+        assert_eq!((c.lower_limit, c.upper_limit), (0.0, 10.0));
+        let extended_limits = c.extended_limits.as_ref().unwrap();
+        assert_eq!((extended_limits.lower_limit, extended_limits.upper_limit), (-100.0, 100.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Limits must be inside the extended limits")]
+    fn test_registry_extended_limits_validation() {
+        let mut m = RegistryMeasurement::new("m", RegistryDataType::Sword, 1, 1, crate::XcpEvent::new(0, 0), 0, 0, 1.0, 0.0, "", "", None);
+        m.set_extended_limits(0.0, 100.0);
+        m.set_limits(-1.0, 50.0);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the EPK memory segment and read only EPK calibration parameter
+    #[test]
+    fn test_registry_epk() {
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_epk");
+        reg.set_epk("TEST_EPK", crate::Xcp::XCP_EPK_ADDR);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC EPK "EPK" ASCII 0x80000000 U8 0 NO_COMPU_METHOD 0 255 READ_ONLY NUMBER 8 /end CHARACTERISTIC"#));
+
+        // The EPK parameter is located in the EPK memory segment at ADDR_EPK
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        let mod_par = module.mod_par.as_ref().unwrap();
+        let c = module.characteristic.iter().find(|c| c.name == "EPK").unwrap();
+        assert!(c.read_only.is_some());
+        assert_eq!(c.address, mod_par.addr_epk[0].address);
+        let segment = mod_par.memory_segment.iter().find(|s| s.name == "epk").unwrap();
+        assert_eq!((segment.address, segment.size), (c.address, 8));
+
+        // The A2L version is the EPK
+        let version = a2l_file.project.header.as_ref().and_then(|h| h.version.as_ref()).unwrap();
+        assert_eq!(version.version_identifier, "TEST_EPK");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test system constants in MOD_PAR
+    #[test]
+    fn test_registry_system_constants() {
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_system_constants");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_system_constant("FEATURE_ABS", "1").unwrap();
+        reg.add_system_constant("CAL_VARIANT", r#"V2 "eu" \ 4wd"#).unwrap();
+        assert!(matches!(reg.add_system_constant("FEATURE_ABS", "0"), Err(RegistryError::Duplicate(_))));
+        assert_eq!(reg.get_system_constants().len(), 2);
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert!(a2l.contains(r#"SYSTEM_CONSTANT "FEATURE_ABS" "1""#));
+        assert!(a2l.contains(r#"SYSTEM_CONSTANT "CAL_VARIANT" "V2 \"eu\" \\ 4wd""#));
+
+        // Quotes and backslashes are read back unchanged
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let system_constants = &a2l_file.project.module[0].mod_par.as_ref().unwrap().system_constant;
+        assert_eq!(system_constants.len(), 2);
+        assert_eq!((system_constants[0].name.as_str(), system_constants[0].value.as_str()), ("FEATURE_ABS", "1"));
+        assert_eq!(system_constants[1].value, r#"V2 "eu" \ 4wd"#);
+    }
+
+    #[test]
+    #[should_panic(expected = "must not contain line breaks")]
+    fn test_registry_system_constant_line_break() {
+        let mut reg = Registry::new();
+        reg.add_system_constant("NAME", "line1\nline2").unwrap();
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test vendor specific IF_DATA passthrough
+    #[test]
+    fn test_registry_ifdata() {
+        const MODULE_IFDATA: &str = "/begin IF_DATA CANAPE_EXT\n    100\n    /begin DATA_GROUP \"a /end IF_DATA in a string\" /end DATA_GROUP\n/end IF_DATA";
+        const MEASUREMENT_IFDATA: &str = "/begin IF_DATA CANAPE_EXT 100 LINK_MAP \"speed\" 0x0 0 0 0 1 0x87 0x0 /end IF_DATA";
+        const CHARACTERISTIC_IFDATA: &str = "/begin IF_DATA ETK\n  KP_BLOB 0x1000 INTERN 4\n/end IF_DATA";
+
+        fn new_ifdata_registry() -> Registry {
+            #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+            struct IfDataPage {
+                #[type_description(ifdata = "/begin IF_DATA CANAPE_EXT 100 /end IF_DATA")]
+                a: u32,
+                b: u32,
+            }
+            let page = IfDataPage { a: 0, b: 0 };
+
+            let mut reg = Registry::new();
+            reg.set_name("test_registry_ifdata");
+            reg.set_epk("TEST_EPK", 0x80000000);
+            reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+            reg.add_cal_seg("test_cal_seg", 0, 8);
+            for field in page.type_description().unwrap().iter() {
+                let mut c = RegistryCharacteristic::new(
+                    Some("test_cal_seg"),
+                    field.name().to_string(),
+                    RegistryDataType::from_rust_type(field.datatype()),
+                    field.comment(),
+                    field.min(),
+                    field.max(),
+                    field.unit(),
+                    1,
+                    1,
+                    field.offset() as u64,
+                );
+                for ifdata in field.ifdata() {
+                    c.add_ifdata(*ifdata);
+                }
+                reg.add_characteristic(c).unwrap();
+            }
+            let m = RegistryMeasurement::new(
+                "speed",
+                crate::RegistryDataType::Float32Ieee,
+                1,
+                1,
+                crate::XcpEvent::new(0, 0),
+                0,
+                0,
+                1.0,
+                0.0,
+                "",
+                "",
+                None,
+            );
+            reg.add_measurement(m).unwrap();
+            reg
+        }
+
+        fn write_a2l(reg: &mut Registry) -> String {
+            let mut a2l = Vec::new();
+            reg.write_a2l_to(&mut a2l).unwrap();
+            String::from_utf8(a2l).unwrap()
+        }
+
+        // Existing A2L with vendor specific IF_DATA, IF_DATA XCP and unregistered objects are ignored
+        let existing = format!(
+            r#"ASAP2_VERSION 1 71
+/begin PROJECT test ""
+/begin MODULE test ""
+/* /begin IF_DATA COMMENTED /end IF_DATA */
+{MODULE_IFDATA}
+/begin IF_DATA XCP /begin PROTOCOL_LAYER 0x104 /end PROTOCOL_LAYER /end IF_DATA
+/begin MEASUREMENT speed "/begin IF_DATA" FLOAT32_IEEE NO_COMPU_METHOD 0 0 0 100 ECU_ADDRESS 0x0
+{MEASUREMENT_IFDATA}
+/begin IF_DATA XCP /begin DAQ_EVENT FIXED_EVENT_LIST EVENT 0 /end DAQ_EVENT /end IF_DATA
+/end MEASUREMENT
+/begin CHARACTERISTIC IfDataPage.b "" VALUE 0x80010004 U32 0 NO_COMPU_METHOD 0 255 {CHARACTERISTIC_IFDATA} /end CHARACTERISTIC
+/begin CHARACTERISTIC removed "" VALUE 0x80010008 U32 0 NO_COMPU_METHOD 0 255 /begin IF_DATA ETK 0 /end IF_DATA /end CHARACTERISTIC
+/end MODULE
+/end PROJECT
+"#
+        );
+
+        let mut reg = new_ifdata_registry();
+        assert_eq!(reg.import_a2l_ifdata(&existing), 3);
+        assert_eq!(reg.get_module_ifdata(), [MODULE_IFDATA]);
+        reg.add_module_ifdata("/begin IF_DATA ASAP1B_CCP 0 /end IF_DATA");
+        let a2l = write_a2l(&mut reg);
+
+        // The blocks survive byte for byte in the right scope
+        let module_start = a2l.find("/begin MODULE").unwrap();
+        let first_object = a2l.find("/begin MEASUREMENT").unwrap();
+        assert!(a2l[module_start..first_object].contains(MODULE_IFDATA));
+        assert!(a2l[module_start..first_object].contains("/begin IF_DATA ASAP1B_CCP 0 /end IF_DATA"));
+        let object = |keyword: &str, name: &str| {
+            let start = a2l.find(&format!("/begin {keyword} {name} ")).unwrap();
+            let end = start + a2l[start..].find(&format!("/end {keyword}")).unwrap();
+            &a2l[start..end]
+        };
+        assert!(object("MEASUREMENT", "speed").contains(MEASUREMENT_IFDATA));
+        assert!(object("CHARACTERISTIC", "IfDataPage.a").contains("/begin IF_DATA CANAPE_EXT 100 /end IF_DATA"));
+        assert!(object("CHARACTERISTIC", "IfDataPage.b").contains(CHARACTERISTIC_IFDATA));
+        assert!(!a2l.contains("COMMENTED") && !a2l.contains("/begin IF_DATA ETK 0 /end IF_DATA"));
+
+        // Round trip, the generated A2L is a valid A2L and reproduces itself
+        let mut log_msgs = Vec::new();
+        a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let mut reg = new_ifdata_registry();
+        assert_eq!(reg.import_a2l_ifdata(&a2l), 4);
+        assert_eq!(write_a2l(&mut reg), a2l);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test A2L sort order by name and by address
+    #[test]
+    fn test_registry_sort_order() {
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_sort_order");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 16);
+        for (name, offset) in [("z_param", 0), ("a_param", 8), ("m_param", 4)] {
+            reg.add_characteristic(RegistryCharacteristic::new(
+                Some("test_cal_seg"),
+                name,
+                RegistryDataType::Ulong,
+                "",
+                0.0,
+                100.0,
+                "",
+                1,
+                1,
+                offset,
+            ))
+            .unwrap();
+        }
+        for (name, offset) in [("z_signal", 0), ("a_signal", 8), ("m_signal", 4)] {
+            reg.add_measurement(RegistryMeasurement::new(
+                name,
+                RegistryDataType::Ulong,
+                1,
+                1,
+                crate::XcpEvent::new(0, 0),
+                offset,
+                0,
+                1.0,
+                0.0,
+                "",
+                "",
+                None,
+            ))
+            .unwrap();
+        }
+
+        fn a2l_order(reg: &mut Registry, keyword: &str) -> Vec<String> {
+            let mut a2l = Vec::new();
+            reg.write_a2l_to(&mut a2l).unwrap();
+            let a2l = String::from_utf8(a2l).unwrap();
+            a2l.lines()
+                .filter_map(|l| l.trim().strip_prefix(keyword))
+                .map(|l| l.split_whitespace().next().unwrap().to_string())
+                .collect()
+        }
+
+        // Default order is by name, the EPK parameter is always first
+        assert_eq!(a2l_order(&mut reg, "/begin CHARACTERISTIC "), ["EPK", "a_param", "m_param", "z_param"]);
+        assert_eq!(a2l_order(&mut reg, "/begin MEASUREMENT "), ["a_signal", "m_signal", "z_signal"]);
+
+        // By address, in memory layout order of the calibration segment and of the event
+        reg.set_sort_order(SortOrder::ByAddress);
+        assert_eq!(a2l_order(&mut reg, "/begin CHARACTERISTIC "), ["EPK", "z_param", "m_param", "a_param"]);
+        assert_eq!(a2l_order(&mut reg, "/begin MEASUREMENT "), ["z_signal", "m_signal", "a_signal"]);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the structured A2L check
+
+    fn new_check_registry(name: &'static str) -> Registry {
+        let mut reg = Registry::new();
+        reg.set_name(name);
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 16);
+        reg
+    }
+
+    fn new_check_measurement(name: &'static str, factor: f64) -> RegistryMeasurement {
+        RegistryMeasurement::new(name, RegistryDataType::Ulong, 1, 1, crate::XcpEvent::new(0, 0), 0, 0, factor, 0.0, "", "", None)
+    }
+
+    fn new_check_characteristic(name: &'static str, x_dim: usize, offset: u64) -> RegistryCharacteristic {
+        RegistryCharacteristic::new(Some("test_cal_seg"), name, RegistryDataType::Ubyte, "", 0.0, 255.0, "", x_dim, 1, offset)
+    }
+
+    fn has_finding(findings: &[A2lCheckFinding], severity: A2lCheckSeverity, name: &str) -> bool {
+        findings.iter().any(|f| f.severity == severity && f.name == name)
+    }
+
+    #[test]
+    fn test_a2l_check_ident() {
+        let mut reg = new_check_registry("test_a2l_check_ident");
+        reg.add_measurement(new_check_measurement("valid.name[0]", 1.0)).unwrap();
+        reg.add_measurement(new_check_measurement("invalid name", 1.0)).unwrap();
+        reg.add_measurement(new_check_measurement("0invalid", 1.0)).unwrap();
+        reg.add_characteristic(new_check_characteristic("Page<u8>.value", 1, 0)).unwrap();
+        let findings = reg.check_a2l();
+        assert_eq!(findings.len(), 3);
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "invalid name"));
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "0invalid"));
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "Page<u8>.value"));
+    }
+
+    #[test]
+    fn test_a2l_check_duplicate() {
+        let mut reg = new_check_registry("test_a2l_check_duplicate");
+        reg.add_measurement(new_check_measurement("speed", 1.0)).unwrap();
+        reg.add_characteristic(new_check_characteristic("speed", 1, 0)).unwrap();
+        let findings = reg.check_a2l();
+        assert_eq!(findings.len(), 1);
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "speed"));
+    }
+
+    #[test]
+    fn test_a2l_check_axis() {
+        let mut reg = new_check_registry("test_a2l_check_axis");
+        let mut curve = new_check_characteristic("curve", 4, 0);
+        curve.set_axis_pts_ref("unknown_axis");
+        reg.add_characteristic(curve).unwrap();
+        let mut curve = new_check_characteristic("curve_input", 4, 4);
+        curve.set_x_axis_input("unknown_signal");
+        reg.add_characteristic(curve).unwrap();
+        let findings = reg.check_a2l();
+        assert_eq!(findings.len(), 2);
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "curve"));
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "curve_input"));
+
+        // Shared axis errors are warnings, if opted out
+        reg.set_axis_check_warn_only(true);
+        let findings = reg.check_a2l();
+        assert!(has_finding(&findings, A2lCheckSeverity::Warning, "curve"));
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "curve_input"));
+    }
+
+    #[test]
+    fn test_a2l_check_segment() {
+        let mut reg = new_check_registry("test_a2l_check_segment");
+        reg.add_characteristic(new_check_characteristic("inside", 8, 0)).unwrap();
+        reg.add_characteristic(new_check_characteristic("overlap", 1, 7)).unwrap();
+        reg.add_characteristic(new_check_characteristic("exceeds", 8, 12)).unwrap();
+        reg.add_characteristic(RegistryCharacteristic::new(
+            Some("unknown_cal_seg"),
+            "unknown",
+            RegistryDataType::Ubyte,
+            "",
+            0.0,
+            255.0,
+            "",
+            1,
+            1,
+            0,
+        ))
+        .unwrap();
+        let findings = reg.check_a2l();
+        assert_eq!(findings.len(), 3);
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "overlap"));
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "exceeds"));
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "unknown"));
+    }
+
+    #[test]
+    fn test_a2l_check_segment_gap() {
+        let mut reg = new_check_registry("test_a2l_check_segment_gap");
+        reg.add_cal_seg("gap_cal_seg", 1, 64);
+        for (name, x_dim, offset) in [("a", 8, 0), ("b", 4, 12), ("c", 8, 40)] {
+            let c = RegistryCharacteristic::new(Some("gap_cal_seg"), name, RegistryDataType::Ubyte, "", 0.0, 255.0, "", x_dim, 1, offset);
+            reg.add_characteristic(c).unwrap();
+        }
+
+        // Alignment padding is not reported, segments without calibration parameters are not checked
+        let findings = reg.check_a2l();
+        let descriptions: Vec<&str> = findings.iter().map(|f| f.description.as_str()).collect();
+        assert_eq!(descriptions, ["24 bytes at offset 16 are not registered", "16 bytes at offset 48 are not registered"]);
+        assert!(findings.iter().all(|f| f.severity == A2lCheckSeverity::Warning && f.name == "gap_cal_seg"));
+    }
+
+    #[test]
+    fn test_registry_alias() {
+        let mut reg = new_check_registry("test_registry_alias");
+        reg.add_measurement(new_check_measurement("speed", 1.0)).unwrap();
+        reg.add_characteristic(new_check_characteristic("ampl", 4, 0)).unwrap();
+        reg.add_characteristic(new_check_characteristic("period", 1, 4)).unwrap();
+        reg.add_alias("speed", "velocity").unwrap();
+        reg.add_alias("ampl", "amplitude").unwrap();
+        reg.add_alias("amplitude", "gain").unwrap();
+        assert!(matches!(reg.add_alias("unknown", "alias"), Err(RegistryError::NotFound("unknown"))));
+        assert!(matches!(reg.add_alias("ampl", "period"), Err(RegistryError::Duplicate(_))));
+
+        // Exact overlap of aliases is not an error
+        assert!(reg.check_a2l().is_empty());
+        assert!(reg.check_layout().is_ok());
+
+        // Same address, different name
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert!(a2l.contains("/begin MEASUREMENT velocity "));
+        for name in ["ampl", "amplitude", "gain"] {
+            assert!(a2l.contains(&format!("/begin CHARACTERISTIC {} \"\" CURVE 0x80010000 U8 ", name)), "{}", name);
+        }
+
+        // A partial overlap of an alias is still an error
+        let mut reg = new_check_registry("test_registry_alias");
+        reg.add_characteristic(new_check_characteristic("ampl", 4, 0)).unwrap();
+        reg.add_alias("ampl", "amplitude").unwrap();
+        reg.add_characteristic(new_check_characteristic("overlap", 1, 2)).unwrap();
+        assert!(has_finding(&reg.check_a2l(), A2lCheckSeverity::Error, "overlap"));
+        assert!(matches!(reg.check_layout(), Err(RegistryError::Overlap(_))));
+    }
+
+    #[test]
+    fn test_registry_128bit() {
+        let mut reg = new_check_registry("test_registry_128bit");
+        reg.add_measurement(RegistryMeasurement::new(
+            "timestamp",
+            RegistryDataType::from_rust_type("u128"),
+            1,
+            1,
+            crate::XcpEvent::new(0, 0),
+            16,
+            0,
+            1.0,
+            0.0,
+            "",
+            "ns",
+            None,
+        ))
+        .unwrap();
+        reg.add_characteristic(RegistryCharacteristic::new(
+            Some("test_cal_seg"),
+            "offset",
+            RegistryDataType::AInt128,
+            "",
+            -1e12,
+            1e12,
+            "",
+            1,
+            1,
+            0,
+        ))
+        .unwrap();
+        assert!(matches!(
+            reg.add_characteristic(new_check_characteristic("offset.hi", 1, 0)),
+            Err(RegistryError::Duplicate(_))
+        ));
+        assert!(reg.check_a2l().is_empty());
+
+        // Two adjacent 64 bit words, the high word of a signed integer is signed
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        assert!(a2l.contains("/begin MEASUREMENT timestamp.lo \"\" A_UINT64 timestamp.lo.Conv 0 0 0 18446744073709551615 PHYS_UNIT \"ns\" ECU_ADDRESS 0x10 "));
+        assert!(a2l.contains("/begin MEASUREMENT timestamp.hi \"\" A_UINT64 timestamp.hi.Conv 0 0 0 18446744073709551615 PHYS_UNIT \"ns\" ECU_ADDRESS 0x18 "));
+        assert!(a2l.contains("/begin CHARACTERISTIC offset.lo \"\" VALUE 0x80010000 U64 "));
+        assert!(a2l.contains("/begin CHARACTERISTIC offset.hi \"\" VALUE 0x80010008 S64 "));
+        assert!(!a2l.contains("/begin MEASUREMENT timestamp \""));
+    }
+
+    // Adds the rate of change of each measurement signal named *_raw
+    struct RateListener;
+
+    impl RegistryCloseListener for RateListener {
+        fn on_close(&self, registry: &mut Registry) {
+            let names: Vec<String> = registry
+                .get_measurement_list()
+                .iter()
+                .filter(|m| m.get_name().ends_with("_raw"))
+                .map(|m| m.get_name().to_string())
+                .collect();
+            for name in names {
+                let name: &'static str = Box::leak(name.replace("_raw", "_rate").into_boxed_str());
+                registry.add_measurement(new_check_measurement(name, 1.0)).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_registry_close_listener() {
+        let mut reg = new_check_registry("test_registry_close_listener");
+        reg.add_measurement(new_check_measurement("sensor_raw", 1.0)).unwrap();
+        reg.add_close_listener(Box::new(RateListener));
+
+        // The listener is called once on A2L generation and sees all entries registered before
+        reg.add_measurement(new_check_measurement("speed_raw", 1.0)).unwrap();
+        let mut a2l = Vec::new();
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+        for name in ["sensor_raw", "sensor_rate", "speed_raw", "speed_rate"] {
+            assert!(a2l.contains(&format!("/begin MEASUREMENT {} ", name)), "{}", name);
+        }
+
+        // The listener has been dropped, it would fail on the duplicate measurements
+        reg.write_a2l_to(&mut Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn test_a2l_check_conversion() {
+        let mut reg = new_check_registry("test_a2l_check_conversion");
+        reg.add_measurement(new_check_measurement("scaled", 0.1)).unwrap();
+        reg.add_measurement(new_check_measurement("zero_factor", 0.0)).unwrap();
+        reg.add_measurement(new_check_measurement("nan_factor", f64::NAN)).unwrap();
+        reg.add_measurement(RegistryMeasurement::new(
+            "blob",
+            RegistryDataType::Blob,
+            64,
+            1,
+            crate::XcpEvent::new(0, 0),
+            0,
+            0,
+            1.0,
+            0.0,
+            "",
+            "",
+            None,
+        ))
+        .unwrap();
+        let findings = reg.check_a2l();
+        assert_eq!(findings.len(), 3);
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "zero_factor"));
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "nan_factor"));
+        assert!(has_finding(&findings, A2lCheckSeverity::Error, "blob"));
+    }
+
+    #[test]
+    fn test_a2l_check_strict() {
+        let mut reg = new_check_registry("test_a2l_check_strict");
+        reg.add_measurement(new_check_measurement("invalid name", 1.0)).unwrap();
+
+        // Findings are only logged by default
+        let mut a2l = Vec::new();
+        assert!(reg.write_a2l_to(&mut a2l).is_ok());
+
+        // Errors fail the A2L generation in strict mode
+        reg.set_a2l_strict(true);
+        let mut a2l = Vec::new();
+        let err = reg.write_a2l_to(&mut a2l).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("invalid name"));
+    }
+
+    #[test]
+    fn test_a2l_check_shorten_identifiers() {
+        const LONG_AXIS: &str = "very_long_module_name.VeryLongStructName.shared_axis";
+        const LONG_SIGNAL: &str = "very_long_module_name.very_long_task_name.engine_speed";
+
+        let mut reg = new_check_registry("test_a2l_check_shorten_identifiers");
+        reg.set_ident_max_len(32);
+        reg.add_measurement(new_check_measurement(LONG_SIGNAL, 1.0)).unwrap();
+        let mut axis = new_check_characteristic(LONG_AXIS, 4, 0);
+        axis.set_axis();
+        reg.add_characteristic(axis).unwrap();
+        let mut curve = new_check_characteristic("short_curve", 4, 4);
+        curve.set_axis_pts_ref(LONG_AXIS);
+        curve.set_x_axis_input(LONG_SIGNAL);
+        reg.add_characteristic(curve).unwrap();
+
+        let mut a2l = Vec::new();
+        reg.set_a2l_strict(true);
+        reg.write_a2l_to(&mut a2l).unwrap();
+        let a2l = String::from_utf8(a2l).unwrap();
+
+        // Long names are shortened to the max length, with a deterministic suffix, short names are unchanged
+        let map = reg.get_ident_map();
+        assert_eq!(map.len(), 2);
+        for (short, long) in map {
+            assert_eq!(short.len(), 32);
+            assert!(short.starts_with(&long[..23]));
+            assert!(!a2l.contains(long.as_str()));
+        }
+        let short_axis = &map.iter().find(|(_, long)| long == LONG_AXIS).unwrap().0;
+        let short_signal = &map.iter().find(|(_, long)| long == LONG_SIGNAL).unwrap().0;
+        assert!(a2l.contains("short_curve"));
+
+        // The references to the axis and the axis input quantity are renamed and the A2L loads
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let module = &a2l_file.project.module[0];
+        assert!(module.axis_pts.iter().any(|a| a.name == *short_axis));
+        assert!(module.measurement.iter().any(|m| m.name == *short_signal));
+        let curve = module.characteristic.iter().find(|c| c.name == "short_curve").unwrap();
+        let axis_descr = &curve.axis_descr[0];
+        assert_eq!(axis_descr.input_quantity, *short_signal);
+        assert_eq!(axis_descr.axis_pts_ref.as_ref().unwrap().axis_points, *short_axis);
+
+        // The same name is always shortened to the same identifier
+        let mut reg2 = new_check_registry("test_a2l_check_shorten_identifiers");
+        reg2.set_ident_max_len(32);
+        reg2.add_measurement(new_check_measurement(LONG_SIGNAL, 1.0)).unwrap();
+        let mut a2l2 = Vec::new();
+        reg2.write_a2l_to(&mut a2l2).unwrap();
+        assert_eq!(reg2.get_ident_map()[0].0, *short_signal);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test A2L import
+    #[cfg(feature = "a2l_reader")]
+    #[test]
+    fn test_registry_a2l_import() {
+        // A2L file of a component with a scalar, a curve and a map
+        let mut reg = new_check_registry("test_registry_api");
+        reg.add_characteristic(new_check_characteristic("ampl", 1, 0)).unwrap();
+        reg.add_characteristic(new_check_characteristic("curve", 4, 4)).unwrap();
+        let map = RegistryCharacteristic::new(Some("test_cal_seg"), "map", RegistryDataType::Ubyte, "", 0.0, 255.0, "V", 2, 4, 8);
+        reg.add_characteristic(map).unwrap();
+        reg.write_a2l().unwrap();
+
+        // All calibration parameters are prefixed, the segment is identified by name
+        let mut reg = new_check_registry("test_registry_a2l_import");
+        assert_eq!(reg.load_a2l_append("test_registry_api.a2l", "ext").unwrap(), 3);
+        let map = reg.find_characteristic("ext.map").unwrap();
+        assert_eq!(map.get_dim(), (2, 4));
+        assert_eq!(map.get_addr_offset(), 8);
+        assert_eq!(map.get_unit(), "V");
+        assert_eq!(reg.find_characteristic("ext.curve").unwrap().get_dim(), (4, 1));
+        assert!(reg.find_characteristic("ext.ampl").is_some());
+        assert!(reg.find_characteristic("ampl").is_none());
+        assert!(matches!(reg.load_a2l_append("test_registry_api.a2l", "ext"), Err(RegistryError::Duplicate(_))));
+        assert!(matches!(reg.add_characteristic(new_check_characteristic("EPK", 1, 16)), Err(RegistryError::Duplicate(_))));
+        assert!(reg.check_a2l().is_empty());
+        let _ = std::fs::remove_file("test_registry_api.a2l");
+
+        // Extended limits are imported
+        let mut reg = new_check_registry("test_registry_api");
+        let mut gain = RegistryCharacteristic::new(Some("test_cal_seg"), "gain", RegistryDataType::Float32Ieee, "", 0.0, 10.0, "", 1, 1, 0);
+        gain.set_extended_limits(-100.0, 100.0);
+        reg.add_characteristic(gain).unwrap();
+        reg.write_a2l().unwrap();
+        let mut reg = new_check_registry("test_registry_a2l_import");
+        assert_eq!(reg.load_a2l_append("test_registry_api.a2l", "ext").unwrap(), 1);
+        assert_eq!(reg.find_characteristic("ext.gain").unwrap().get_extended_limits(), Some((-100.0, 100.0)));
+        let _ = std::fs::remove_file("test_registry_api.a2l");
+
+        // Instances of nested typedef structures, typedef names must be unique
+        std::fs::write(
+            "test_registry_typedef.a2l",
+            r#"ASAP2_VERSION 1 71
+/begin PROJECT test ""
+/begin MODULE test ""
+/begin MOD_PAR "" /begin MEMORY_SEGMENT test_cal_seg "" DATA FLASH INTERN 0x80010000 16 -1 -1 -1 -1 -1 /end MEMORY_SEGMENT /end MOD_PAR
+/begin RECORD_LAYOUT U16 FNC_VALUES 1 UWORD ROW_DIR DIRECT /end RECORD_LAYOUT
+/begin TYPEDEF_CHARACTERISTIC T_u16 "" VALUE U16 0 NO_COMPU_METHOD 0 1000 /end TYPEDEF_CHARACTERISTIC
+/begin TYPEDEF_STRUCTURE Point "" 4
+  /begin STRUCTURE_COMPONENT x T_u16 0 /end STRUCTURE_COMPONENT
+  /begin STRUCTURE_COMPONENT y T_u16 2 /end STRUCTURE_COMPONENT
+/end TYPEDEF_STRUCTURE
+/begin TYPEDEF_STRUCTURE Line "" 8
+  /begin STRUCTURE_COMPONENT points Point 0 MATRIX_DIM 2 /end STRUCTURE_COMPONENT
+/end TYPEDEF_STRUCTURE
+/begin INSTANCE line "" Line 0x80010008 /end INSTANCE
+/end MODULE
+/end PROJECT
+"#,
+        )
+        .unwrap();
+        assert_eq!(reg.load_a2l_append("test_registry_typedef.a2l", "sup").unwrap(), 4);
+        assert_eq!(reg.find_characteristic("sup.line.points[1].y").unwrap().get_addr_offset(), 14);
+        assert!(matches!(reg.load_a2l_append("test_registry_typedef.a2l", "sup2"), Err(RegistryError::Duplicate(_))));
+        assert!(reg.find_characteristic("sup2.line.points[0].x").is_none());
+        let _ = std::fs::remove_file("test_registry_typedef.a2l");
+    }
+
+    // Unknown keywords are skipped in lenient mode
+    #[cfg(feature = "a2l_reader")]
+    #[test]
+    fn test_registry_a2l_lenient() {
+        std::fs::write(
+            "test_registry_lenient.a2l",
+            r#"ASAP2_VERSION 1 71
+/begin PROJECT test ""
+/begin MODULE test ""
+/begin MOD_PAR "" /begin MEMORY_SEGMENT test_cal_seg "" DATA FLASH INTERN 0x80010000 16 -1 -1 -1 -1 -1 /end MEMORY_SEGMENT /end MOD_PAR
+/begin RECORD_LAYOUT U8 FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+/begin CHARACTERISTIC ampl "" VALUE 0x80010000 U8 0 NO_COMPU_METHOD 0 100
+  FUTURE_KEYWORD "value"
+  PHYS_UNIT "V"
+/end CHARACTERISTIC
+/end MODULE
+/end PROJECT
+"#,
+        )
+        .unwrap();
+
+        // Strict import fails
+        let mut reg = new_check_registry("test_registry_a2l_lenient");
+        assert!(matches!(reg.load_a2l_append("test_registry_lenient.a2l", "ext"), Err(RegistryError::A2l(_))));
+        assert!(reg.find_characteristic("ext.ampl").is_none());
+
+        // Lenient import skips the keyword and its value
+        let report = reg.load_a2l_lenient("test_registry_lenient.a2l", "ext").unwrap();
+        assert_eq!(
+            report,
+            A2lImportReport {
+                count: 1,
+                warning_count: 1,
+                skipped_keywords: vec!["FUTURE_KEYWORD".to_string()],
+            }
+        );
+        let ampl = reg.find_characteristic("ext.ampl").unwrap();
+        assert_eq!(ampl.get_unit(), "V");
+        assert_eq!(ampl.get_limits(), (0.0, 100.0));
+        let _ = std::fs::remove_file("test_registry_lenient.a2l");
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_registry_sqlite() {
+        use std::path::Path;
+
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_sqlite");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 128);
+        reg.add_module_ifdata("/begin IF_DATA CANAPE_EXT\n  100\n/end IF_DATA");
+        let event = crate::XcpEvent::new(0, 0);
+        reg.add_event_ext("test_event", event, 1500, crate::XcpEventConsistency::Event);
+
+        let mut m = RegistryMeasurement::new(
+            "test_signal",
+            crate::RegistryDataType::Sword,
+            4,
+            1,
+            event,
+            -8,
+            0,
+            0.5,
+            1.0,
+            "signal",
+            "V",
+            None,
+        );
+        m.set_display("Test Signal");
+        m.set_phys_type(crate::RegistryDataType::Float64Ieee);
+        m.set_limits(-100.0, 100.0);
+        m.set_extended_limits(-1000.0, 1000.0);
+        m.add_ifdata("/begin IF_DATA CANAPE_EXT 100 /end IF_DATA");
+        m.add_annotation("Metadata", "application/json", ["{", "}"]);
+        reg.add_measurement(m).unwrap();
+
+        let mut axis = RegistryCharacteristic::new(Some("test_cal_seg"), "test_axis", crate::RegistryDataType::Float32Ieee, "axis", 0.0, 1000.0, "rpm", 8, 1, 0);
+        axis.set_axis();
+        axis.set_monotone_increasing();
+        reg.add_characteristic(axis).unwrap();
+        let mut curve = RegistryCharacteristic::new(Some("test_cal_seg"), "test_curve", crate::RegistryDataType::Float32Ieee, "curve", 0.0, 100.0, "", 8, 1, 32);
+        curve.set_axis_pts_ref("test_axis");
+        curve.set_step(0.5);
+        curve.set_format("%6.2");
+        curve.set_extended_limits(-10.0, 200.0);
+        curve.set_function("test_function");
+        curve.add_ref_function("test_ref_function");
+        curve.set_slew_rate(10.0);
+        reg.add_characteristic(curve).unwrap();
+        reg.add_alias("test_curve", "test_curve_alias").unwrap();
+        let mut flag = RegistryCharacteristic::new(Some("test_cal_seg"), "test_flag", crate::RegistryDataType::Ubyte, "flag", 0.0, 1.0, "", 1, 1, 64);
+        flag.set_verbal_bool();
+        reg.add_characteristic(flag).unwrap();
+
+        let mut a2l1 = Vec::new();
+        reg.write_a2l_to(&mut a2l1).unwrap();
+        reg.freeze();
+
+        let path1 = Path::new("test_registry_sqlite_1.db");
+        let path2 = Path::new("test_registry_sqlite_2.db");
+        reg.export_sqlite(path1).unwrap();
+        let mut imported = Registry::import_sqlite(path1).unwrap();
+        imported.export_sqlite(path2).unwrap();
+        let db1 = std::fs::read(path1).unwrap();
+        let db2 = std::fs::read(path2).unwrap();
+        let _ = std::fs::remove_file(path1);
+        let _ = std::fs::remove_file(path2);
+        assert!(db1 == db2, "Re-exported SQLite database differs");
+
+        assert_eq!(imported.get_name(), Some("test_registry_sqlite"));
+        assert_eq!(imported.get_module_ifdata(), ["/begin IF_DATA CANAPE_EXT\n  100\n/end IF_DATA"]);
+        let m = &imported.get_measurement_list()[0];
+        assert_eq!(m.get_name(), "test_signal");
+        assert_eq!(m.get_phys_type(), Some(crate::RegistryDataType::Float64Ieee));
+        assert_eq!(m.get_limits(), Some((-100.0, 100.0)));
+        assert_eq!(m.get_extended_limits(), Some((-1000.0, 1000.0)));
+        let curve = imported.find_characteristic("test_curve").unwrap();
+        assert_eq!(curve.get_step(), Some(0.5));
+        assert_eq!(curve.get_extended_limits(), Some((-10.0, 200.0)));
+        assert_eq!(curve.get_function(), Some("test_function"));
+        assert!(curve.get_ref_functions().eq(["test_ref_function"]));
+        assert_eq!(curve.get_slew_rate(), Some(10.0));
+        assert_eq!(imported.find_characteristic("test_curve_alias").unwrap().get_alias_of(), Some("test_curve"));
+        assert!(imported.check_axis_refs().is_ok()); // test_axis is still an axis of matching length
+        assert!(imported.find_characteristic("test_axis").unwrap().is_monotone_increasing());
+
+        // The A2L of the imported registry is identical
+        let mut a2l2 = Vec::new();
+        imported.write_a2l_to(&mut a2l2).unwrap();
+        assert!(a2l1 == a2l2, "A2L of the imported registry differs");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test measurement signal as input quantity of a curve axis
+    #[test]
+    fn test_registry_axis_input() {
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        struct AxisInputPage {
+            #[type_description(x_axis_input = "engine_speed")]
+            curve: [f32; 8],
+        }
+        let page = AxisInputPage { curve: [0.0; 8] };
+        let field = page.type_description().unwrap().into_iter().next().unwrap();
+        assert_eq!(field.x_axis_input(), "engine_speed");
+
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_axis_input");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("test_cal_seg", 0, 32);
+
+        let mut curve = RegistryCharacteristic::new(
+            Some("test_cal_seg"),
+            "test_curve",
+            crate::RegistryDataType::Float32Ieee,
+            "curve",
+            0.0,
+            100.0,
+            "",
+            8,
+            1,
+            0,
+        );
+        curve.set_x_axis_input(field.x_axis_input());
+        reg.add_characteristic(curve).unwrap();
+
+        // Referenced measurement not registered
+        assert!(matches!(reg.check_axis_inputs(), Err(RegistryError::AxisInput(_))));
+        assert!(reg.write_a2l().is_err());
+
+        reg.add_measurement(RegistryMeasurement::new(
+            "engine_speed",
+            crate::RegistryDataType::Float32Ieee,
+            1,
+            1,
+            crate::XcpEvent::new(0, 0),
+            0,
+            0,
+            1.0,
+            0.0,
+            "engine speed",
+            "rpm",
+            None,
+        ))
+        .unwrap();
+        assert!(reg.check_axis_inputs().is_ok());
+
+        reg.write_a2l().unwrap();
+        let a2l = std::fs::read_to_string("test_registry_axis_input.a2l").unwrap();
+        assert!(a2l.contains("/begin CHARACTERISTIC test_curve \"curve\" CURVE 0x80010000 F32 0 NO_COMPU_METHOD 0 100"));
+        assert!(a2l.contains("/begin AXIS_DESCR FIX_AXIS engine_speed NO_COMPU_METHOD  8 0 7 FIX_AXIS_PAR_DIST 0 1 8 /end AXIS_DESCR"));
+
+        let _ = std::fs::remove_file("test_registry_axis_input.a2l");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test type description of conditionally compiled fields
+    #[test]
+    fn test_type_description_cfg_field() {
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        struct CfgPage {
+            basic_param: u32,
+            #[cfg(feature = "serde")]
+            #[type_description(comment = "Only with feature serde")]
+            optional_param: f64,
+            #[cfg(not(feature = "serde"))]
+            fallback_param: u8,
+        }
+        let page = CfgPage {
+            basic_param: 0,
+            #[cfg(feature = "serde")]
+            optional_param: 0.0,
+            #[cfg(not(feature = "serde"))]
+            fallback_param: 0,
+        };
+
+        let names: Vec<String> = page.type_description().unwrap().iter().map(|f| f.name().to_string()).collect();
+        if cfg!(feature = "serde") {
+            assert_eq!(names, ["CfgPage.basic_param", "CfgPage.optional_param"]);
+        } else {
+            assert_eq!(names, ["CfgPage.basic_param", "CfgPage.fallback_param"]);
+        }
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test calibration segment memory layout check and report
+    #[test]
+    fn test_registry_layout() {
+        #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+        #[repr(C)]
+        struct LayoutPage {
+            a: u8,
+            b: u32,
+            c: [u16; 3],
+        }
+        const LAYOUT_PAGE: LayoutPage = LayoutPage { a: 0, b: 0, c: [0; 3] };
+
+        let mut reg = Registry::new();
+        reg.set_name("test_registry_layout");
+        reg.set_epk("TEST_EPK", 0x80000000);
+        reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        reg.add_cal_seg("layout_page", 0, std::mem::size_of::<LayoutPage>().try_into().unwrap());
+        for field in LAYOUT_PAGE.type_description().unwrap().iter() {
+            reg.add_characteristic(RegistryCharacteristic::new(
+                Some("layout_page"),
+                field.name().to_string(),
+                RegistryDataType::from_rust_type(field.datatype()),
+                field.comment(),
+                field.min(),
+                field.max(),
+                field.unit(),
+                if field.x_dim() == 0 { 1 } else { field.x_dim() },
+                if field.y_dim() == 0 { 1 } else { field.y_dim() },
+                field.offset() as u64,
+            ))
+            .unwrap();
+        }
+
+        // Padding after a and at the end of the page
+        assert!(reg.check_layout().is_ok());
+        let report = reg.layout_report();
+        log::info!("{}", report);
+        assert!(report.contains("CalSeg layout_page size=16"));
+        assert!(report.contains("  0x0001   3                     <gap>"));
+        assert!(report.contains("  0x0004   4      ULONG[1][1]    LayoutPage.b"));
+        assert!(report.contains("  0x0008   6      UWORD[3][1]    LayoutPage.c"));
+        assert!(report.contains("  0x000E   2                     <gap>"));
+
+        // Manual registration overlapping the array c
+        reg.add_characteristic(RegistryCharacteristic::new(
+            Some("layout_page"),
+            "overlap",
+            RegistryDataType::Uword,
+            "",
+            0.0,
+            100.0,
+            "",
+            1,
+            1,
+            12,
+        ))
+        .unwrap();
+        assert!(matches!(reg.check_layout(), Err(RegistryError::Overlap(_))));
+        assert!(reg.write_a2l().is_err());
+        let _ = std::fs::remove_file("test_registry_layout.a2l");
+    }
+
     //-----------------------------------------------------------------------------
     // Test A2L writer
 
@@ -245,4 +1998,34 @@ mod registry_tests {
 
         let _ = std::fs::remove_file("test_registry_1.a2l");
     }
+
+    //-----------------------------------------------------------------------------
+    // Test A2L generation to a string without A2L file
+    #[test]
+    fn test_registry_a2l_string() {
+        crate::xcp::xcp_test::test_setup(log::LevelFilter::Info);
+
+        let xcp = crate::Xcp::get();
+        {
+            let reg_ref = xcp.get_registry();
+            let mut reg = reg_ref.lock();
+            reg.set_name("test_registry_a2l_string");
+            reg.set_epk("TEST_EPK", 0x80000000);
+            reg.set_tl_params("UDP", Ipv4Addr::LOCALHOST, 5555);
+        }
+
+        let _calseg = xcp.create_calseg("test_cal_seg", &CAL_PAGE).register_fields();
+        let _event = xcp.create_event("test_event");
+
+        let a2l = xcp.generate_a2l_string().unwrap();
+        assert!(a2l.contains("/begin PROJECT test_registry_a2l_string"));
+        assert!(a2l.contains("EPK \"TEST_EPK\""));
+        assert!(a2l.contains("/begin CHARACTERISTIC CalPage.test_characteristic_1"));
+        assert!(a2l.contains("test_event"));
+        assert!(!std::path::Path::new("test_registry_a2l_string.a2l").exists());
+
+        // Registry is finalized
+        assert!(xcp.get_registry().lock().is_frozen());
+        assert!(xcp.generate_a2l_string().is_err());
+    }
 }
@@ -11,11 +11,27 @@ use core::panic;
 use std::{borrow::Cow, net::Ipv4Addr};
 
 use crate::xcp;
+use xcp::cal::MAX_SEGMENT_SIZE;
 use xcp::XcpEvent;
+use xcp::XcpEventConsistency;
+use xcp::XcpEventPriority;
 
 mod a2l_writer;
 use a2l_writer::A2lWriter;
 
+mod a2l_check;
+pub use a2l_check::{A2lCheckFinding, A2lCheckSeverity, A2L_IDENT_MAX_LEN};
+
+mod a2l_ifdata;
+
+#[cfg(feature = "a2l_reader")]
+mod a2l_import;
+#[cfg(feature = "a2l_reader")]
+pub use a2l_import::A2lImportReport;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
 //----------------------------------------------------------------------------------------------
 // Registry error
 
@@ -32,10 +48,43 @@ pub enum RegistryError {
     #[error("registry error: `{0}` not found")]
     NotFound(&'static str),
 
+    #[error("registry error: overlapping calibration parameters `{0}`")]
+    Overlap(String),
+
+    #[error("registry error: axis input quantity `{0}` is not a measurement")]
+    AxisInput(String),
+
+    #[error("registry error: `{characteristic}` has {len} x axis points, but its axis `{axis}` has {axis_len} points")]
+    AxisLength {
+        characteristic: String,
+        axis: String,
+        len: usize,
+        axis_len: usize,
+    },
+
+    #[error("registry error: `{characteristic}` refers to `{axis}`, which is not a registered axis")]
+    AxisRef { characteristic: String, axis: String },
+
+    #[error("{}", format_errors(.0))]
+    AxisCheck(Vec<RegistryError>),
+
+    #[cfg(feature = "sqlite")]
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[cfg(feature = "a2l_reader")]
+    #[error("A2L file error: {0}")]
+    A2l(String),
+
     #[error("unknown error")]
     Unknown,
 }
 
+// All errors of a check, one per line
+fn format_errors(errors: &[RegistryError]) -> String {
+    errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")
+}
+
 //-------------------------------------------------------------------------------------------------
 // Datatype
 
@@ -51,12 +100,24 @@ pub enum RegistryDataType {
     Sword,
     Slong,
     AInt64,
+    AUint128, // Split into the two 64 bit words <name>.lo and <name>.hi on registration
+    AInt128,  // Split into the two 64 bit words <name>.lo and <name>.hi on registration
     Float32Ieee,
     Float64Ieee,
     Blob,
     Unknown,
 }
 
+/// Size of the length prefix (u16 little endian) of variable length blobs (RegistryDataType::Blob)
+/// Blob measurements have a fixed max size (x_dim), the prefix contains the actual length of the blob data
+pub const BLOB_LENGTH_PREFIX_SIZE: usize = 2;
+
+// Annotation label of measurements in a runtime gated measurement group, the annotation text is the group name
+const MEASUREMENT_GROUP_ANNOTATION_LABEL: &str = "MEASUREMENT_GROUP";
+
+// Name of the read only calibration parameter of the EPK, reserved
+const EPK_CHARACTERISTIC_NAME: &str = "EPK";
+
 impl RegistryDataType {
     /// Get minimum value for data type
     /// Used by the register macros
@@ -65,17 +126,19 @@ impl RegistryDataType {
             RegistryDataType::Sbyte => i8::MIN as f64,
             RegistryDataType::Sword => i16::MIN as f64,
             RegistryDataType::Slong => i32::MIN as f64,
-            RegistryDataType::AInt64 | RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee => -1E12,
+            RegistryDataType::AInt64 | RegistryDataType::AInt128 | RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee => -1E12,
             _ => 0.0,
         }
     }
 
+    // Integer types are written with their exact value range
     fn get_min_str(self) -> &'static str {
         match self {
             RegistryDataType::Sbyte => "-128",
             RegistryDataType::Sword => "-32768",
             RegistryDataType::Slong => "-2147483648",
-            RegistryDataType::AInt64 | RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee => "-1E15",
+            RegistryDataType::AInt64 => "-9223372036854775808",
+            RegistryDataType::AInt128 | RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee => "-1E15",
             _ => "0",
         }
     }
@@ -90,12 +153,61 @@ impl RegistryDataType {
             RegistryDataType::Sword => i16::MAX as f64,
             RegistryDataType::Ulong => u32::MAX as f64,
             RegistryDataType::Slong => i32::MAX as f64,
-            RegistryDataType::AUint64 | RegistryDataType::AInt64 | RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee => 1E12,
+            RegistryDataType::AUint64
+            | RegistryDataType::AInt64
+            | RegistryDataType::AUint128
+            | RegistryDataType::AInt128
+            | RegistryDataType::Float32Ieee
+            | RegistryDataType::Float64Ieee => 1E12,
             RegistryDataType::Blob => 0.0,
             RegistryDataType::Unknown => panic!("get_max: Unsupported data type"),
         }
     }
 
+    /// Get the value range representable by an integer data type, None for floating point and blob types
+    /// The limits of the 64 and 128 bit types are rounded to the nearest f64
+    #[allow(clippy::cast_precision_loss)]
+    pub fn get_int_range(self) -> Option<(f64, f64)> {
+        match self {
+            RegistryDataType::Ubyte => Some((0.0, u8::MAX as f64)),
+            RegistryDataType::Sbyte => Some((i8::MIN as f64, i8::MAX as f64)),
+            RegistryDataType::Uword => Some((0.0, u16::MAX as f64)),
+            RegistryDataType::Sword => Some((i16::MIN as f64, i16::MAX as f64)),
+            RegistryDataType::Ulong => Some((0.0, u32::MAX as f64)),
+            RegistryDataType::Slong => Some((i32::MIN as f64, i32::MAX as f64)),
+            RegistryDataType::AUint64 => Some((0.0, u64::MAX as f64)),
+            RegistryDataType::AInt64 => Some((i64::MIN as f64, i64::MAX as f64)),
+            RegistryDataType::AUint128 => Some((0.0, u128::MAX as f64)),
+            RegistryDataType::AInt128 => Some((i128::MIN as f64, i128::MAX as f64)),
+            _ => None,
+        }
+    }
+
+    // Exact value range of a 64 bit or smaller integer data type
+    fn get_int_bounds(self) -> Option<(i128, i128)> {
+        match self {
+            RegistryDataType::Ubyte => Some((0, u8::MAX as i128)),
+            RegistryDataType::Sbyte => Some((i8::MIN as i128, i8::MAX as i128)),
+            RegistryDataType::Uword => Some((0, u16::MAX as i128)),
+            RegistryDataType::Sword => Some((i16::MIN as i128, i16::MAX as i128)),
+            RegistryDataType::Ulong => Some((0, u32::MAX as i128)),
+            RegistryDataType::Slong => Some((i32::MIN as i128, i32::MAX as i128)),
+            RegistryDataType::AUint64 => Some((0, u64::MAX as i128)),
+            RegistryDataType::AInt64 => Some((i64::MIN as i128, i64::MAX as i128)),
+            _ => None,
+        }
+    }
+
+    // Format a limit of this data type for the A2L file
+    // Integer limits are rounded and clamped to the value range and written without exponent, as f64 can not represent the 64 bit range exactly
+    #[allow(clippy::cast_possible_truncation)]
+    fn format_limit(self, value: f64) -> String {
+        match self.get_int_bounds() {
+            Some((min, max)) => (value.round() as i128).clamp(min, max).to_string(),
+            None => value.to_string(),
+        }
+    }
+
     pub fn get_max_str(self) -> &'static str {
         match self {
             RegistryDataType::Ubyte => "255",
@@ -104,7 +216,9 @@ impl RegistryDataType {
             RegistryDataType::Sword => "32767",
             RegistryDataType::Ulong => "4294967295",
             RegistryDataType::Slong => "2147483647",
-            RegistryDataType::AUint64 | RegistryDataType::AInt64 | RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee => "1E15",
+            RegistryDataType::AUint64 => "18446744073709551615",
+            RegistryDataType::AInt64 => "9223372036854775807",
+            RegistryDataType::AUint128 | RegistryDataType::AInt128 | RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee => "1E15",
             RegistryDataType::Blob => "0",
             RegistryDataType::Unknown => panic!("get_max: Unsupported data type"),
         }
@@ -125,7 +239,26 @@ impl RegistryDataType {
             RegistryDataType::Float32Ieee => "FLOAT32_IEEE",
             RegistryDataType::Float64Ieee => "FLOAT64_IEEE",
             RegistryDataType::Blob => "BLOB",
-            RegistryDataType::Unknown => panic!("get_type_str: Unsupported data type"),
+            RegistryDataType::AUint128 | RegistryDataType::AInt128 | RegistryDataType::Unknown => panic!("get_type_str: Unsupported data type"),
+        }
+    }
+
+    // Get data type from str, inverse of get_type_str
+    // Used by the SQLite import
+    fn from_type_str(s: &str) -> Option<RegistryDataType> {
+        match s {
+            "UBYTE" => Some(RegistryDataType::Ubyte),
+            "UWORD" => Some(RegistryDataType::Uword),
+            "ULONG" => Some(RegistryDataType::Ulong),
+            "A_UINT64" => Some(RegistryDataType::AUint64),
+            "SBYTE" => Some(RegistryDataType::Sbyte),
+            "SWORD" => Some(RegistryDataType::Sword),
+            "SLONG" => Some(RegistryDataType::Slong),
+            "A_INT64" => Some(RegistryDataType::AInt64),
+            "FLOAT32_IEEE" => Some(RegistryDataType::Float32Ieee),
+            "FLOAT64_IEEE" => Some(RegistryDataType::Float64Ieee),
+            "BLOB" => Some(RegistryDataType::Blob),
+            _ => None,
         }
     }
 
@@ -144,7 +277,7 @@ impl RegistryDataType {
             RegistryDataType::Float32Ieee => "F32",
             RegistryDataType::Float64Ieee => "F64",
             RegistryDataType::Blob => "BLOB",
-            RegistryDataType::Unknown => panic!("get_deposit_str: Unsupported data type"),
+            RegistryDataType::AUint128 | RegistryDataType::AInt128 | RegistryDataType::Unknown => panic!("get_deposit_str: Unsupported data type"),
         }
     }
 
@@ -156,6 +289,7 @@ impl RegistryDataType {
             RegistryDataType::Uword | RegistryDataType::Sword => 2,
             RegistryDataType::Ulong | RegistryDataType::Slong | RegistryDataType::Float32Ieee => 4,
             RegistryDataType::AUint64 | RegistryDataType::AInt64 | RegistryDataType::Float64Ieee => 8,
+            RegistryDataType::AUint128 | RegistryDataType::AInt128 => 16,
             RegistryDataType::Blob => 0,
             RegistryDataType::Unknown => panic!("get_size: Unsupported data type"),
         }
@@ -173,12 +307,24 @@ impl RegistryDataType {
             "i32" => RegistryDataType::Slong,
             "u64" | "usize" => RegistryDataType::AUint64, // @@@@ Check if usize is correct
             "i64" | "isize" => RegistryDataType::AInt64,  // @@@@ Check if isize is correct
+            "u128" => RegistryDataType::AUint128,
+            "i128" => RegistryDataType::AInt128,
             "f32" => RegistryDataType::Float32Ieee,
             "f64" => RegistryDataType::Float64Ieee,
+            "blob" => RegistryDataType::Blob, // protobuf bytes (XcpProtoMeasurement)
             _ => RegistryDataType::Unknown,
         }
     }
 
+    // Data type of the high word of a 128 bit integer, the low word is always unsigned
+    fn get_hi_word_type(self) -> Option<RegistryDataType> {
+        match self {
+            RegistryDataType::AUint128 => Some(RegistryDataType::AUint64),
+            RegistryDataType::AInt128 => Some(RegistryDataType::AInt64),
+            _ => None,
+        }
+    }
+
     /// Convert from Rust type as str
     /// Used by the register macros
     pub fn from_rust_type(s: &str) -> RegistryDataType {
@@ -285,6 +431,16 @@ impl RegistryDataTypeTrait for usize {
         RegistryDataType::AUint64
     }
 }
+impl RegistryDataTypeTrait for i128 {
+    fn get_type(&self) -> RegistryDataType {
+        RegistryDataType::AInt128
+    }
+}
+impl RegistryDataTypeTrait for u128 {
+    fn get_type(&self) -> RegistryDataType {
+        RegistryDataType::AUint128
+    }
+}
 impl RegistryDataTypeTrait for f32 {
     fn get_type(&self) -> RegistryDataType {
         RegistryDataType::Float32Ieee
@@ -326,6 +482,8 @@ struct RegistryEvent {
     name: &'static str,
     xcp_event: XcpEvent,
     cycle_time_ns: u32, // 0 -> no cycle time = sporadic event
+    consistency: XcpEventConsistency,
+    priority: XcpEventPriority,
 }
 
 #[derive(Debug)]
@@ -376,6 +534,19 @@ impl RegistryCalSeg {
             size,
         }
     }
+
+    /// Name, address and size of the A2L memory segments of this calibration segment
+    /// Calibration segments larger than MAX_SEGMENT_SIZE are split into chunks <name>_0, <name>_1, ... with consecutive indices in the address
+    #[allow(clippy::cast_possible_truncation)]
+    fn get_memory_segments(&self) -> Vec<(String, u32, u32)> {
+        let chunk_size = MAX_SEGMENT_SIZE as u32;
+        if self.size <= chunk_size {
+            return vec![(self.name.to_string(), self.addr, self.size)];
+        }
+        (0..self.size.div_ceil(chunk_size))
+            .map(|i| (format!("{}_{}", self.name, i), self.addr + i * chunk_size, chunk_size.min(self.size - i * chunk_size)))
+            .collect()
+    }
 }
 
 #[derive(Debug)]
@@ -392,6 +563,10 @@ impl RegistryCalSegList {
     fn iter(&self) -> std::slice::Iter<RegistryCalSeg> {
         self.0.iter()
     }
+
+    fn find(&self, name: &str) -> Option<&RegistryCalSeg> {
+        self.0.iter().find(|s| s.name == name)
+    }
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -409,6 +584,78 @@ impl RegistryEpk {
     }
 }
 
+//-------------------------------------------------------------------------------------------------
+// Annotations
+
+/// User annotation of a measurement signal or calibration parameter (A2L ANNOTATION)
+/// Attaches arbitrary metadata, e.g. JSON or a serialization schema, to a symbol
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegistryAnnotation {
+    label: Cow<'static, str>,
+    origin: Cow<'static, str>,
+    text: Vec<String>,
+}
+
+impl RegistryAnnotation {
+    /// Create an annotation with label, origin and text lines
+    /// Text lines containing line breaks are split into multiple lines
+    pub fn new<L, O, I, T>(label: L, origin: O, text_lines: I) -> Self
+    where
+        L: Into<Cow<'static, str>>,
+        O: Into<Cow<'static, str>>,
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let text = text_lines.into_iter().flat_map(|t| t.as_ref().lines().map(String::from).collect::<Vec<_>>()).collect();
+        RegistryAnnotation {
+            label: label.into(),
+            origin: origin.into(),
+            text,
+        }
+    }
+
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn get_text(&self) -> &[String] {
+        &self.text
+    }
+}
+
+// Escape a string for an A2L string literal
+fn a2l_escape(s: &str) -> String {
+    let mut r = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            '\t' => r.push_str("\\t"),
+            '\r' => {}
+            c => r.push(c),
+        }
+    }
+    r
+}
+
+/// A2L ANNOTATION block, each text line is a separate quoted string
+impl std::fmt::Display for RegistryAnnotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            r#"/begin ANNOTATION ANNOTATION_LABEL "{}" ANNOTATION_ORIGIN "{}""#,
+            a2l_escape(&self.label),
+            a2l_escape(&self.origin)
+        )?;
+        writeln!(f, "  /begin ANNOTATION_TEXT")?;
+        for line in &self.text {
+            writeln!(f, r#"    "{}""#, a2l_escape(line))?;
+        }
+        writeln!(f, "  /end ANNOTATION_TEXT")?;
+        write!(f, "/end ANNOTATION")
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // Measurement signals
 
@@ -422,6 +669,8 @@ pub struct RegistryMeasurement {
     x_dim: u16,                 // 1 = basic type (A2L MEASUREMENT), >1 = array[dim] of basic type (A2L MEASUREMENT with MATRIX_DIM x (max u16))
     y_dim: u16,                 // 1 = basic type (A2L MEASUREMENT), >1 = array[x_dim,y_dim] of basic type (A2L MEASUREMENT with MATRIX_DIM x,y (max u16))
     annotation: Option<String>, // For serialized data of variable size (RegistryDataType::Blob)
+    idl_hash: Option<u32>,      // Content hash of the IDL annotation, prefix of the serialized data
+    annotations: Vec<RegistryAnnotation>,
     // Addressing
     xcp_event: XcpEvent, // Raw XCP event associated with the measurement signal
     addr_offset: i16,    // If addr==0, signed offset relative to event memory context (XCP_ADDR_EXT_DYN)
@@ -429,8 +678,14 @@ pub struct RegistryMeasurement {
     // Metadata
     factor: f64,
     offset: f64,
+    phys_type: Option<RegistryDataType>, // Type of the physical value after conversion with factor and offset, if different from datatype
     comment: &'static str,
     unit: &'static str,
+    limits: Option<(f64, f64)>,          // Limits of the physical value (A2L LowerLimit and UpperLimit), default is the value range of the type
+    extended_limits: Option<(f64, f64)>, // Physically possible range, which encloses the plausible range limits (A2L LowerLimit and UpperLimit, limits as annotation)
+    format: Option<&'static str>,        // Display format (A2L FORMAT)
+    display: Option<&'static str>,       // Display name shown in the tool instead of the name (A2L DISPLAY_IDENTIFIER)
+    ifdata: Vec<String>,                 // Vendor specific IF_DATA blocks, written verbatim
 }
 
 impl RegistryMeasurement {
@@ -461,9 +716,160 @@ impl RegistryMeasurement {
             addr,
             factor,
             offset,
+            phys_type: None,
             comment,
             unit,
             annotation,
+            idl_hash: None,
+            annotations: Vec::new(),
+            limits: None,
+            extended_limits: None,
+            format: None,
+            display: None,
+            ifdata: Vec::new(),
+        }
+    }
+
+    // Split a 128 bit integer into the two adjacent 64 bit words <name>.lo and <name>.hi (little endian), A2L has no 128 bit type
+    fn split_128(self) -> Vec<RegistryMeasurement> {
+        let Some(hi_type) = self.datatype.get_hi_word_type() else {
+            return vec![self];
+        };
+        assert!(self.x_dim == 1 && self.y_dim == 1, "Arrays of 128 bit integers are not supported");
+        let mut lo = self;
+        lo.datatype = RegistryDataType::AUint64;
+        lo.factor = 1.0;
+        lo.offset = 0.0;
+        lo.phys_type = None;
+        lo.limits = None;
+        lo.extended_limits = None;
+        lo.display = None;
+        let mut hi = lo.clone();
+        lo.name = Cow::Owned(format!("{}.lo", lo.name));
+        hi.name = Cow::Owned(format!("{}.hi", hi.name));
+        hi.datatype = hi_type;
+        if hi.addr != 0 {
+            hi.addr += 8;
+        } else {
+            hi.addr_offset += 8;
+        }
+        vec![lo, hi]
+    }
+
+    /// Set the display format, e.g. "%8.3" (A2L FORMAT)
+    pub fn set_format(&mut self, format: &'static str) {
+        self.format = Some(format);
+    }
+
+    /// Set the display name, which is shown in the tool instead of the name (A2L DISPLAY_IDENTIFIER)
+    /// The name is still used for addressing
+    pub fn set_display(&mut self, display: &'static str) {
+        self.display = Some(display);
+    }
+
+    /// Set the type of the physical value, e.g. Float64Ieee for a voltage in mV stored as Sword with factor 0.001
+    /// The raw value keeps its storage type, the conversion rule (A2L COMPU_METHOD) gets the display format and the limits of the physical type
+    pub fn set_phys_type(&mut self, phys_type: RegistryDataType) {
+        assert!(
+            !matches!(
+                phys_type,
+                RegistryDataType::AUint128 | RegistryDataType::AInt128 | RegistryDataType::Blob | RegistryDataType::Unknown
+            ),
+            "Physical type must be a basic type"
+        );
+        self.phys_type = Some(phys_type);
+    }
+
+    /// Get the type of the physical value, if different from the storage type
+    pub fn get_phys_type(&self) -> Option<RegistryDataType> {
+        self.phys_type
+    }
+
+    /// Set the limits of the physical value (A2L LowerLimit and UpperLimit)
+    /// Without limits, the limits are the value range of the data type
+    /// Limits of integer physical values are rounded, clamped to the value range of the type and written as integers
+    pub fn set_limits(&mut self, min: f64, max: f64) {
+        assert!(min <= max, "Lower limit must not be greater than upper limit");
+        if let Some((extended_min, extended_max)) = self.extended_limits {
+            assert!(min >= extended_min && max <= extended_max, "Limits must be inside the extended limits");
+        }
+        self.limits = Some((min, max));
+    }
+
+    /// Get the limits of the physical value, if set explicitly
+    pub fn get_limits(&self) -> Option<(f64, f64)> {
+        self.limits
+    }
+
+    /// Set the physically possible range of the physical value, which must enclose the plausible range set with set_limits
+    /// The physical range is written as A2L LowerLimit and UpperLimit and used by the tool for axis scaling,
+    /// the plausible range is written as annotation PLAUSIBLE_LIMITS, A2L MEASUREMENT has no EXTENDED_LIMITS
+    pub fn set_extended_limits(&mut self, min: f64, max: f64) {
+        assert!(min <= max, "Lower limit must not be greater than upper limit");
+        if let Some((plausible_min, plausible_max)) = self.limits {
+            assert!(min <= plausible_min && max >= plausible_max, "Extended limits must enclose the limits");
+        }
+        self.extended_limits = Some((min, max));
+    }
+
+    /// Get the physically possible range of the physical value, if set
+    pub fn get_extended_limits(&self) -> Option<(f64, f64)> {
+        self.extended_limits
+    }
+
+    // Integer type of the physical value, if the physical value is an integer
+    // Without an explicit physical type, this is the storage type, if there is no conversion
+    fn get_int_phys_type(&self) -> Option<RegistryDataType> {
+        let phys_type = match self.phys_type {
+            Some(phys_type) => phys_type,
+            None if (self.factor - 1.0).abs() <= f64::EPSILON && self.offset == 0.0 => self.datatype,
+            None => return None,
+        };
+        phys_type.get_int_bounds().map(|_| phys_type)
+    }
+
+    /// Add a vendor specific IF_DATA block, e.g. "/begin IF_DATA CANAPE_EXT ... /end IF_DATA"
+    /// The block is written verbatim to the A2L file
+    pub fn add_ifdata<T: Into<String>>(&mut self, text: T) {
+        let text = text.into();
+        assert!(a2l_ifdata::is_ifdata_block(&text), "Not an IF_DATA block");
+        self.ifdata.push(text);
+    }
+
+    /// Add a user annotation
+    pub fn add_annotation<L, O, I, T>(&mut self, label: L, origin: O, text_lines: I)
+    where
+        L: Into<Cow<'static, str>>,
+        O: Into<Cow<'static, str>>,
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        self.annotations.push(RegistryAnnotation::new(label, origin, text_lines));
+    }
+
+    /// Set the content hash of the IDL annotation of serialized data (RegistryDataType::Blob)
+    pub fn set_idl_hash(&mut self, idl_hash: u32) {
+        assert!(self.datatype == RegistryDataType::Blob, "IDL hash requires a Blob");
+        self.idl_hash = Some(idl_hash);
+    }
+
+    pub fn get_idl_hash(&self) -> Option<u32> {
+        self.idl_hash
+    }
+
+    /// Get the name, with instance suffix of a multi instance event
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the A2L address extension and address
+    fn get_ext_addr(&self) -> (u8, u32) {
+        if self.addr == 0 {
+            // DYN
+            self.xcp_event.get_dyn_ext_addr(self.addr_offset)
+        } else {
+            // ABS
+            crate::Xcp::get_abs_ext_addr(self.addr)
         }
     }
 }
@@ -488,9 +894,17 @@ impl RegistryMeasurementList {
         self.0.iter()
     }
 
+    fn iter_mut(&mut self) -> std::slice::IterMut<RegistryMeasurement> {
+        self.0.iter_mut()
+    }
+
     fn sort(&mut self) {
         self.0.sort_by(|a, b| a.name.cmp(&b.name));
     }
+
+    fn sort_by_address(&mut self) {
+        self.0.sort_by(|a, b| (a.get_ext_addr(), &a.name).cmp(&(b.get_ext_addr(), &b.name)));
+    }
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -516,6 +930,25 @@ pub struct RegistryCharacteristic {
     min: f64,
     max: f64,
     unit: &'static str,
+    step: Option<f64>,                     // Step size for increment and decrement in the calibration tool (A2L STEP_SIZE)
+    format: Option<&'static str>,          // Display format (A2L FORMAT)
+    extended_limits: Option<(f64, f64)>,   // Limits which enclose min and max, the calibration tool may override min and max up to these limits (A2L EXTENDED_LIMITS)
+    display: Option<&'static str>,         // Display name shown in the tool instead of the name (A2L DISPLAY_IDENTIFIER)
+    ifdata: Vec<String>,                   // Vendor specific IF_DATA blocks, written verbatim
+    alias_of: Option<Cow<'static, str>>,   // Name of the calibration parameter this one is an alias of, same address and size
+    verbal_bool: bool,                     // Show the values 0 and 1 as "false" and "true" in the tool (A2L COMPU_VTAB)
+    function: Option<Cow<'static, str>>,   // Name of the ECU function which defines this calibration parameter (A2L FUNCTION DEF_CHARACTERISTIC)
+    ref_functions: Vec<Cow<'static, str>>, // Names of the ECU functions which use this calibration parameter (A2L FUNCTION REF_CHARACTERISTIC)
+    slew_rate: Option<f64>,                // Max rate of change in units per second of the value applied by a calibration segment with slew enabled
+
+    // Axis
+    is_axis: bool,                           // This calibration parameter is a shared axis (A2L AXIS_PTS)
+    monotone_increasing: bool,               // Axis values must be strictly increasing, checked on XCP writes (A2L MONOTONY STRICT_INCREASE)
+    axis_pts_ref: Option<Cow<'static, str>>, // Name of a shared axis used as x axis of this curve or map (A2L COM_AXIS)
+    x_axis_input: Option<Cow<'static, str>>, // Name of the measurement signal which is the input quantity of the x axis
+
+    // User annotations
+    annotations: Vec<RegistryAnnotation>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -545,6 +978,202 @@ impl RegistryCharacteristic {
             unit,
             addr_offset,
             event: None,
+            is_axis: false,
+            monotone_increasing: false,
+            axis_pts_ref: None,
+            x_axis_input: None,
+            annotations: Vec::new(),
+            step: None,
+            format: None,
+            extended_limits: None,
+            display: None,
+            ifdata: Vec::new(),
+            alias_of: None,
+            verbal_bool: false,
+            function: None,
+            ref_functions: Vec::new(),
+            slew_rate: None,
+        }
+    }
+
+    // Name of the original calibration parameter of an alias
+    fn get_original_name(&self) -> &str {
+        self.alias_of.as_deref().unwrap_or(&self.name)
+    }
+
+    // Both are names of the same memory, the original calibration parameter and an alias or two aliases
+    fn is_alias(&self, other: &RegistryCharacteristic) -> bool {
+        self.calseg_name == other.calseg_name && self.addr_offset == other.addr_offset && self.get_original_name() == other.get_original_name()
+    }
+
+    /// Get the name
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the data type of a single element
+    pub fn get_datatype(&self) -> RegistryDataType {
+        self.datatype
+    }
+
+    /// Get the dimensions (x_dim, y_dim), (1, 1) for scalars
+    pub fn get_dim(&self) -> (usize, usize) {
+        (self.x_dim, self.y_dim)
+    }
+
+    /// Get the offset relative to the calibration segment, or the absolute address
+    pub fn get_addr_offset(&self) -> u64 {
+        self.addr_offset
+    }
+
+    /// Get the physical unit
+    pub fn get_unit(&self) -> &'static str {
+        self.unit
+    }
+
+    /// Get the limits (min, max)
+    pub fn get_limits(&self) -> (f64, f64) {
+        (self.min, self.max)
+    }
+
+    /// Get the extended limits, if any
+    pub fn get_extended_limits(&self) -> Option<(f64, f64)> {
+        self.extended_limits
+    }
+
+    /// Get the step size, if any
+    pub fn get_step(&self) -> Option<f64> {
+        self.step
+    }
+
+    /// Values must be strictly increasing, see set_monotone_increasing
+    pub fn is_monotone_increasing(&self) -> bool {
+        self.monotone_increasing
+    }
+
+    /// Get the name of the ECU function which defines this calibration parameter, if any
+    pub fn get_function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    /// Get the names of the ECU functions which use this calibration parameter
+    pub fn get_ref_functions(&self) -> impl Iterator<Item = &str> {
+        self.ref_functions.iter().map(AsRef::as_ref)
+    }
+
+    /// Get the name of the original calibration parameter, if this one is an alias
+    pub fn get_alias_of(&self) -> Option<&str> {
+        self.alias_of.as_deref()
+    }
+
+    /// Get the slew rate in units per second, if any
+    pub fn get_slew_rate(&self) -> Option<f64> {
+        self.slew_rate
+    }
+
+    /// Set the step size for increment and decrement in the calibration tool (A2L STEP_SIZE)
+    pub fn set_step(&mut self, step: f64) {
+        assert!(step > 0.0, "Step size must be positive");
+        self.step = Some(step);
+    }
+
+    // Split a 128 bit integer into the two adjacent 64 bit words <name>.lo and <name>.hi (little endian), A2L has no 128 bit type
+    fn split_128(self) -> Vec<RegistryCharacteristic> {
+        let Some(hi_type) = self.datatype.get_hi_word_type() else {
+            return vec![self];
+        };
+        assert!(self.x_dim == 1 && self.y_dim == 1, "Arrays of 128 bit integers are not supported");
+        let mut lo = self;
+        lo.datatype = RegistryDataType::AUint64;
+        lo.min = lo.datatype.get_min();
+        lo.max = lo.datatype.get_max();
+        lo.step = None;
+        lo.extended_limits = None;
+        lo.display = None;
+        let mut hi = lo.clone();
+        lo.name = Cow::Owned(format!("{}.lo", lo.name));
+        hi.name = Cow::Owned(format!("{}.hi", hi.name));
+        hi.datatype = hi_type;
+        hi.min = hi_type.get_min();
+        hi.max = hi_type.get_max();
+        hi.addr_offset += 8;
+        vec![lo, hi]
+    }
+
+    // Apply the value range of an integer data type to the limits
+    // Without limits (min and max 0), the limits default to the limits of the data type
+    // Limits outside of the value range are clamped with a warning, floating point types are unchanged
+    // clamp returns the value unchanged if it is in range, so the exact compare detects clamped limits
+    #[allow(clippy::float_cmp)]
+    fn apply_type_limits(&mut self) {
+        let Some((type_min, type_max)) = self.datatype.get_int_range() else {
+            return;
+        };
+        if self.min == 0.0 && self.max == 0.0 {
+            self.min = self.datatype.get_min();
+            self.max = self.datatype.get_max();
+            return;
+        }
+        let (min, max) = (self.min.clamp(type_min, type_max), self.max.clamp(type_min, type_max));
+        if min != self.min || max != self.max {
+            warn!(
+                "Limits [{}, {}] of {} exceed the value range of {:?}, clamped to [{}, {}]",
+                self.min, self.max, self.name, self.datatype, min, max
+            );
+            self.min = min;
+            self.max = max;
+        }
+        if let Some((extended_min, extended_max)) = self.extended_limits {
+            self.extended_limits = Some((extended_min.clamp(type_min, type_max), extended_max.clamp(type_min, type_max)));
+        }
+    }
+
+    /// Set the display format, e.g. "%8.3" (A2L FORMAT)
+    pub fn set_format(&mut self, format: &'static str) {
+        self.format = Some(format);
+    }
+
+    /// Set the extended limits, which must enclose min and max (A2L EXTENDED_LIMITS)
+    pub fn set_extended_limits(&mut self, min: f64, max: f64) {
+        assert!(min <= self.min && max >= self.max, "Extended limits must enclose min and max");
+        self.extended_limits = Some((min, max));
+    }
+
+    /// Set the display name, which is shown in the tool instead of the name (A2L DISPLAY_IDENTIFIER)
+    /// The name is still used for addressing
+    pub fn set_display(&mut self, display: &'static str) {
+        self.display = Some(display);
+    }
+
+    /// Add a vendor specific IF_DATA block, e.g. "/begin IF_DATA CANAPE_EXT ... /end IF_DATA"
+    /// The block is written verbatim to the A2L file
+    pub fn add_ifdata<T: Into<String>>(&mut self, text: T) {
+        let text = text.into();
+        assert!(a2l_ifdata::is_ifdata_block(&text), "Not an IF_DATA block");
+        self.ifdata.push(text);
+    }
+
+    /// Show the values 0 and 1 of a bool as "false" and "true" in the tool (A2L COMPU_METHOD TAB_VERB)
+    /// The data type stays a single byte, the limits are set to 0 and 1
+    pub fn set_verbal_bool(&mut self) {
+        assert!(self.datatype == RegistryDataType::Ubyte && !self.is_axis, "Verbal bool requires a bool");
+        self.verbal_bool = true;
+        self.min = 0.0;
+        self.max = 1.0;
+        self.extended_limits = None;
+    }
+
+    /// Assign this calibration parameter to the ECU function which defines it (A2L FUNCTION with DEF_CHARACTERISTIC)
+    /// The tool shows the calibration parameters grouped by function
+    pub fn set_function<T: Into<Cow<'static, str>>>(&mut self, function: T) {
+        self.function = Some(function.into());
+    }
+
+    /// Add an ECU function which uses this calibration parameter, but does not define it (A2L FUNCTION with REF_CHARACTERISTIC)
+    pub fn add_ref_function<T: Into<Cow<'static, str>>>(&mut self, function: T) {
+        let function = function.into();
+        if !self.ref_functions.contains(&function) {
+            self.ref_functions.push(function);
         }
     }
 
@@ -554,6 +1183,52 @@ impl RegistryCharacteristic {
         self.event = Some(event);
     }
 
+    /// Register this one dimensional calibration parameter as shared axis points (A2L AXIS_PTS)
+    /// Curves and maps may refer to it with set_axis_pts_ref
+    pub fn set_axis(&mut self) {
+        assert!(self.y_dim == 1, "Axis must be one dimensional");
+        self.is_axis = true;
+    }
+
+    /// Require strictly increasing values of this one dimensional calibration parameter
+    /// XCP writes which violate this are rejected or clamped, depending on the GuardRailsMode
+    pub fn set_monotone_increasing(&mut self) {
+        assert!(self.x_dim > 1 && self.y_dim == 1, "Monotony requires a one dimensional array");
+        self.monotone_increasing = true;
+    }
+
+    /// Limit the rate of change of the value applied by a calibration segment with slew enabled (CalSeg::enable_slew) to rate units per second
+    /// XCP writes are ramped from the old to the new value, the XCP tool sees the new value immediately
+    pub fn set_slew_rate(&mut self, rate: f64) {
+        assert!(rate.is_finite() && rate > 0.0, "Slew rate must be positive");
+        self.slew_rate = Some(rate);
+    }
+
+    /// Use a separately registered shared axis as x axis of this curve or map
+    /// The x axis is written as A2L COM_AXIS with AXIS_PTS_REF instead of FIX_AXIS
+    /// name is the full name of the axis instance, e.g. "CalPage.axis", the A2L file has flat instances only, references are not resolved relative to a typedef instance
+    pub fn set_axis_pts_ref<T: Into<Cow<'static, str>>>(&mut self, name: T) {
+        self.axis_pts_ref = Some(name.into());
+    }
+
+    /// Link a measurement signal to the x axis of this curve or map (A2L AXIS_DESCR InputQuantity)
+    /// The measurement tool shows the operating point of the signal as a cursor on the axis
+    pub fn set_x_axis_input<T: Into<Cow<'static, str>>>(&mut self, name: T) {
+        assert!(self.x_dim > 1 || self.y_dim > 1, "Axis input requires a curve or map");
+        self.x_axis_input = Some(name.into());
+    }
+
+    /// Add a user annotation
+    pub fn add_annotation<L, O, I, T>(&mut self, label: L, origin: O, text_lines: I)
+    where
+        L: Into<Cow<'static, str>>,
+        O: Into<Cow<'static, str>>,
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        self.annotations.push(RegistryAnnotation::new(label, origin, text_lines));
+    }
+
     /// Get the A2L object type of the calibration parameter
     fn get_type_str(&self) -> &'static str {
         if self.x_dim > 1 && self.y_dim > 1 {
@@ -564,6 +1239,18 @@ impl RegistryCharacteristic {
             "VALUE"
         }
     }
+
+    /// Get the A2L address extension and address
+    fn get_ext_addr(&self, cal_seg_list: &RegistryCalSegList) -> (u8, u32) {
+        if let Some(calseg_name) = self.calseg_name {
+            // Segment relative addressing
+            let index = cal_seg_list.find(calseg_name).expect("unknown calseg").index;
+            crate::Xcp::get_calseg_ext_addr(index, self.addr_offset.try_into().expect("offset too large"))
+        } else {
+            // Absolute addressing
+            crate::Xcp::get_abs_ext_addr(self.addr_offset)
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -582,24 +1269,95 @@ impl RegistryCharacteristicList {
         self.0.sort_by(|a, b| a.name.cmp(&b.name));
     }
 
+    fn sort_by_address(&mut self, cal_seg_list: &RegistryCalSegList) {
+        self.0.sort_by(|a, b| (a.get_ext_addr(cal_seg_list), &a.name).cmp(&(b.get_ext_addr(cal_seg_list), &b.name)));
+    }
+
     pub fn iter(&self) -> std::slice::Iter<RegistryCharacteristic> {
         self.0.iter()
     }
+
+    fn iter_mut(&mut self) -> std::slice::IterMut<RegistryCharacteristic> {
+        self.0.iter_mut()
+    }
 }
 
 //-------------------------------------------------------------------------------------------------
 // Registry
 
+/// Order of measurements and calibration parameters in the A2L file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Sorted by name
+    #[default]
+    ByName,
+    /// Sorted by address extension and address, symbols with equal address by name
+    /// Lists the parameters of each calibration segment and the signals of each event in memory layout order
+    ByAddress,
+}
+
+/// A2L limits of measurements and calibration parameters without explicit limits and without conversion
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DefaultLimits {
+    /// Value range of the data type, floating point types with -1E15 and 1E15 (default)
+    #[default]
+    TypeRange,
+    /// Float sentinels -3.4E38 and 3.4E38 for all data types, the tool does not restrict the displayed or entered values
+    Sentinel,
+}
+
+impl DefaultLimits {
+    /// A2L lower and upper limit of a data type
+    pub fn get_limits_str(self, datatype: RegistryDataType) -> (&'static str, &'static str) {
+        match self {
+            DefaultLimits::TypeRange => (datatype.get_min_str(), datatype.get_max_str()),
+            DefaultLimits::Sentinel => ("-3.4E38", "3.4E38"),
+        }
+    }
+}
+
+/// Listener called when the registry is closed for A2L generation
+/// Plugins may finalize their own entries, e.g. add a measurement signal derived from an already registered one
+pub trait RegistryCloseListener {
+    /// Called once at the beginning of the A2L generation, before identifiers are shortened and the content is checked
+    fn on_close(&self, registry: &mut Registry);
+}
+
+// Close listeners of the open registry
+#[derive(Default)]
+struct RegistryCloseListenerList(Vec<Box<dyn RegistryCloseListener + Send>>);
+
+impl std::fmt::Debug for RegistryCloseListenerList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RegistryCloseListenerList({})", self.0.len())
+    }
+}
+
 #[derive(Debug)]
 pub struct Registry {
     freeze: bool,
     name: Option<&'static str>,
     tl_params: Option<RegistryXcpTransportLayer>,
+    max_cto_dto: Option<(u16, u16)>, // Max CTO and DTO size in the A2L protocol layer parameters, if set at runtime
     mod_par: RegistryEpk,
     cal_seg_list: RegistryCalSegList,
     characteristic_list: RegistryCharacteristicList,
     event_list: RegistryEventList,
     measurement_list: RegistryMeasurementList,
+    layout_report: bool,
+    axis_check_warn_only: bool,
+    sort_order: SortOrder,
+    a2l_strict: bool,                 // Fail the A2L generation on errors of the A2L check
+    ident_max_len: Option<usize>,     // Shorten longer identifiers
+    ident_map: Vec<(String, String)>, // Shortened identifiers (short, long)
+    module_ifdata: Vec<String>,       // Vendor specific IF_DATA blocks on module level, written verbatim
+    close_listeners: RegistryCloseListenerList,
+    typedef_names: Vec<String>, // Names of the TYPEDEF_STRUCTUREs of imported A2L files
+    verbal_bool: bool,          // Show all bool calibration parameters as "false" and "true"
+    type_limits: bool,          // Derive and clamp the limits of integer calibration parameters from their data type
+    default_limits: DefaultLimits,
+    row_major: bool,                         // Memory layout of two dimensional calibration parameters (A2L RECORD_LAYOUT ROW_DIR or COLUMN_DIR)
+    system_constants: Vec<(String, String)>, // Build time constants (A2L MOD_PAR SYSTEM_CONSTANT)
 }
 
 impl Default for Registry {
@@ -615,11 +1373,26 @@ impl Registry {
             freeze: false,
             name: None,
             tl_params: None,
+            max_cto_dto: None,
             mod_par: RegistryEpk::new(),
             cal_seg_list: RegistryCalSegList::new(),
             characteristic_list: RegistryCharacteristicList::new(),
             event_list: RegistryEventList::new(),
             measurement_list: RegistryMeasurementList::new(),
+            layout_report: false,
+            axis_check_warn_only: false,
+            sort_order: SortOrder::ByName,
+            a2l_strict: false,
+            ident_max_len: None,
+            ident_map: Vec::new(),
+            module_ifdata: Vec::new(),
+            close_listeners: RegistryCloseListenerList::default(),
+            typedef_names: Vec::new(),
+            verbal_bool: false,
+            type_limits: true,
+            default_limits: DefaultLimits::default(),
+            row_major: true,
+            system_constants: Vec::new(),
         }
     }
 
@@ -629,11 +1402,26 @@ impl Registry {
         self.freeze = false;
         self.name = None;
         self.tl_params = None;
+        self.max_cto_dto = None;
         self.mod_par = RegistryEpk::new();
         self.cal_seg_list = RegistryCalSegList::new();
         self.characteristic_list = RegistryCharacteristicList::new();
         self.event_list = RegistryEventList::new();
         self.measurement_list = RegistryMeasurementList::new();
+        self.layout_report = false;
+        self.axis_check_warn_only = false;
+        self.sort_order = SortOrder::ByName;
+        self.a2l_strict = false;
+        self.ident_max_len = None;
+        self.ident_map = Vec::new();
+        self.module_ifdata = Vec::new();
+        self.close_listeners = RegistryCloseListenerList::default();
+        self.typedef_names = Vec::new();
+        self.verbal_bool = false;
+        self.type_limits = true;
+        self.default_limits = DefaultLimits::default();
+        self.row_major = true;
+        self.system_constants = Vec::new();
     }
 
     /// Freeze registry
@@ -647,6 +1435,22 @@ impl Registry {
         self.freeze
     }
 
+    /// Add a listener, which is called when the registry is closed for A2L generation
+    /// The listener may add entries, it is dropped after it has been called
+    pub fn add_close_listener(&mut self, listener: Box<dyn RegistryCloseListener + Send>) {
+        debug!("Registry add_close_listener");
+        assert!(!self.is_frozen(), "Registry is closed");
+        self.close_listeners.0.push(listener);
+    }
+
+    // Call and drop all close listeners
+    fn notify_close_listeners(&mut self) {
+        let listeners = std::mem::take(&mut self.close_listeners.0);
+        for listener in &listeners {
+            listener.on_close(self);
+        }
+    }
+
     /// Set name
     pub fn set_name(&mut self, name: &'static str) {
         debug!("Registry set_name({})", name);
@@ -670,19 +1474,130 @@ impl Registry {
         self.mod_par.epk
     }
 
+    /// Write the calibration segment memory layout report <name>_layout.txt next to the A2L file
+    pub fn set_layout_report(&mut self, enable: bool) {
+        self.layout_report = enable;
+    }
+
+    /// Report shared axis length and reference errors as warnings instead of failing the A2L generation
+    /// For transition of existing code only, the generated A2L file will not be consistent
+    pub fn set_axis_check_warn_only(&mut self, warn_only: bool) {
+        self.axis_check_warn_only = warn_only;
+    }
+
+    /// Set the order of measurements and calibration parameters in the A2L file, default is SortOrder::ByName
+    pub fn set_sort_order(&mut self, sort_order: SortOrder) {
+        self.sort_order = sort_order;
+    }
+
+    /// Fail the A2L generation, if the A2L check has errors
+    /// Otherwise the findings of the A2L check are only logged
+    pub fn set_a2l_strict(&mut self, strict: bool) {
+        self.a2l_strict = strict;
+    }
+
+    /// Show all bool calibration parameters of calibration segments as "false" and "true" in the tool
+    /// Fields with the type description attribute verbal_bool = "false" keep the numeric display
+    pub fn set_verbal_bool(&mut self, enable: bool) {
+        self.verbal_bool = enable;
+    }
+
+    /// Get the default for bool calibration parameters, see set_verbal_bool
+    pub fn get_verbal_bool(&self) -> bool {
+        self.verbal_bool
+    }
+
+    /// Derive the limits of integer calibration parameters without limits from their data type
+    /// Clamp limits exceeding the value range of the data type with a warning
+    /// Enabled by default, disable to keep the limits of previously generated A2L files
+    pub fn set_type_limits(&mut self, enable: bool) {
+        self.type_limits = enable;
+    }
+
+    /// Set the A2L limits of measurements and calibration parameters without explicit limits and without conversion, default is DefaultLimits::TypeRange
+    /// Applied when the A2L file is written
+    pub fn set_default_limits(&mut self, default_limits: DefaultLimits) {
+        self.default_limits = default_limits;
+    }
+
+    /// Set the memory layout of two dimensional calibration parameters (A2L MAP), default is row major as Rust arrays [[T; y_dim]; x_dim]
+    /// Column major maps reference a RECORD_LAYOUT with FNC_VALUES COLUMN_DIR, e.g. for values in the layout of C or Fortran code with swapped dimensions
+    pub fn set_row_major(&mut self, row_major: bool) {
+        assert!(!self.is_frozen(), "Registry is closed");
+        self.row_major = row_major;
+    }
+
+    /// Shorten measurement and calibration parameter names longer than max_len to a prefix and a hash of the full name
+    /// The mapping table <name>_identifiers.txt is written next to the A2L file
+    pub fn set_ident_max_len(&mut self, max_len: usize) {
+        assert!((16..=A2L_IDENT_MAX_LEN).contains(&max_len), "max_len must be in 16..={}", A2L_IDENT_MAX_LEN);
+        self.ident_max_len = Some(max_len);
+    }
+
     // Set transport layer parameters
     pub fn set_tl_params(&mut self, protocol_name: &'static str, addr: Ipv4Addr, port: u16) {
         debug!("Registry set_tl_params: {} {} {}", protocol_name, addr, port);
         self.tl_params = Some(RegistryXcpTransportLayer { protocol_name, addr, port });
     }
 
+    /// Add a system constant, written as SYSTEM_CONSTANT "name" "value" in the A2L MOD_PAR
+    /// Exposes build time constants, e.g. feature flags or a calibration variant id, to the tool
+    /// Quotes and backslashes are escaped, names and values must not contain line breaks
+    /// Fails with RegistryError::Duplicate, if a system constant with the same name already exists
+    pub fn add_system_constant(&mut self, name: &str, value: &str) -> Result<(), RegistryError> {
+        debug!("Registry add_system_constant: {} = {}", name, value);
+        assert!(!self.is_frozen(), "Registry is closed");
+        assert!(!name.is_empty(), "Empty system constant name");
+        assert!(
+            !name.contains(['\n', '\r']) && !value.contains(['\n', '\r']),
+            "System constant {} must not contain line breaks",
+            name
+        );
+        if self.system_constants.iter().any(|(n, _)| n == name) {
+            return Err(RegistryError::Duplicate(Cow::Owned(name.to_string())));
+        }
+        self.system_constants.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    /// Get the system constants (name, value)
+    pub fn get_system_constants(&self) -> &[(String, String)] {
+        &self.system_constants
+    }
+
+    /// Set the max CTO and DTO size for the A2L protocol layer parameters
+    #[allow(clippy::similar_names)]
+    pub fn set_max_cto_dto(&mut self, max_cto: u16, max_dto: u16) {
+        debug!("Registry set_max_cto_dto: {} {}", max_cto, max_dto);
+        self.max_cto_dto = Some((max_cto, max_dto));
+    }
+
     /// Add an XCP event with name and cycle time in ns
     /// cycle_time_ns = 0 is sporadic or unknown
     pub fn add_event(&mut self, name: &'static str, xcp_event: XcpEvent, cycle_time_ns: u32) {
+        self.add_event_ext(name, xcp_event, cycle_time_ns, XcpEventConsistency::default());
+    }
+
+    /// Add an XCP event with name, cycle time in ns and data consistency
+    pub fn add_event_ext(&mut self, name: &'static str, xcp_event: XcpEvent, cycle_time_ns: u32, consistency: XcpEventConsistency) {
         debug!("Registry add_event: channel={}, index={}", xcp_event.get_channel(), xcp_event.get_index());
         assert!(!self.is_frozen(), "Registry is closed");
 
-        self.event_list.push(RegistryEvent { name, xcp_event, cycle_time_ns });
+        self.event_list.push(RegistryEvent {
+            name,
+            xcp_event,
+            cycle_time_ns,
+            consistency,
+            priority: XcpEventPriority::default(),
+        });
+    }
+
+    /// Set the transmit priority of an XCP event
+    pub fn set_event_priority(&mut self, xcp_event: XcpEvent, priority: XcpEventPriority) {
+        assert!(!self.is_frozen(), "Registry is closed");
+        if let Some(e) = self.event_list.0.iter_mut().find(|e| e.xcp_event == xcp_event) {
+            e.priority = priority;
+        }
     }
 
     // Add a calibration segment
@@ -710,6 +1625,18 @@ impl Registry {
         self.cal_seg_list.push(RegistryCalSeg::new(name, index, addr, addr_ext, size));
     }
 
+    /// Remove a calibration segment and all its calibration parameters
+    /// Returns the number of removed calibration parameters
+    pub fn remove_cal_seg(&mut self, name: &str) -> usize {
+        debug!("Registry remove_cal_seg: {}", name);
+        assert!(!self.is_frozen(), "Registry is closed");
+
+        self.cal_seg_list.0.retain(|s| s.name != name);
+        let len = self.characteristic_list.0.len();
+        self.characteristic_list.0.retain(|c| c.calseg_name != Some(name));
+        len - self.characteristic_list.0.len()
+    }
+
     // Get calibration segment index by name
     pub fn get_cal_seg_index(&self, name: &str) -> Option<u16> {
         for s in self.cal_seg_list.iter() {
@@ -726,7 +1653,7 @@ impl Registry {
     }
 
     /// Add an instance of a measurement signal associated to a measurement events
-    /// The event index (for multi instance events) is appended to the name
+    /// The event instance label or index (for multi instance events) is appended to the name
     /// # panics
     ///   If a measurement with the same name already exists
     ///   If the registry is closed
@@ -744,23 +1671,79 @@ impl Registry {
         // Panic if registry is closed
         assert!(!self.is_frozen(), "Registry is closed");
 
-        // Append event index to name in case of a multi instance event (index>0)
+        // Append event instance label or index to name in case of a multi instance event (index>0)
         if m.xcp_event.get_index() > 0 {
-            m.name = std::borrow::Cow::Owned(format!("{}_{}", m.name, m.xcp_event.get_index()));
+            m.name = std::borrow::Cow::Owned(format!("{}_{}", m.name, m.xcp_event.get_instance_suffix()));
         }
 
         // Panic if symbol_name with same name already exists
-        for m1 in self.measurement_list.iter() {
-            if m1.name == m.name {
-                return Err(RegistryError::Duplicate(m.name));
+        let split = m.split_128();
+        for m in &split {
+            if self.measurement_list.iter().any(|m1| m1.name == m.name) {
+                return Err(RegistryError::Duplicate(m.name.clone()));
             }
         }
 
         // Add to list
-        self.measurement_list.push(m);
+        for m in split {
+            self.measurement_list.push(m);
+        }
+        Ok(())
+    }
+
+    // Find a measurement registered on the given event
+    // The event instance label or index (for multi instance events) is appended to the name, as in add_measurement
+    fn find_event_measurement_mut(&mut self, name: &'static str, xcp_event: XcpEvent) -> Result<&mut RegistryMeasurement, RegistryError> {
+        assert!(!self.is_frozen(), "Registry is closed");
+        let instance_name = if xcp_event.get_index() > 0 {
+            Some(format!("{}_{}", name, xcp_event.get_instance_suffix()))
+        } else {
+            None
+        };
+        self.measurement_list
+            .iter_mut()
+            .find(|m| m.xcp_event == xcp_event && m.name == instance_name.as_deref().unwrap_or(name))
+            .ok_or(RegistryError::NotFound(name))
+    }
+
+    /// Set the type of the physical value of a measurement registered on the given event
+    /// The event instance label or index (for multi instance events) is appended to the name, as in add_measurement
+    pub fn set_measurement_phys_type(&mut self, name: &'static str, xcp_event: XcpEvent, phys_type: RegistryDataType) -> Result<(), RegistryError> {
+        self.find_event_measurement_mut(name, xcp_event)?.set_phys_type(phys_type);
+        Ok(())
+    }
+
+    /// Set the limits of the physical value of a measurement registered on the given event, see RegistryMeasurement::set_limits
+    pub fn set_measurement_limits(&mut self, name: &'static str, xcp_event: XcpEvent, min: f64, max: f64) -> Result<(), RegistryError> {
+        self.find_event_measurement_mut(name, xcp_event)?.set_limits(min, max);
+        Ok(())
+    }
+
+    /// Set the physically possible range of a measurement registered on the given event, see RegistryMeasurement::set_extended_limits
+    pub fn set_measurement_extended_limits(&mut self, name: &'static str, xcp_event: XcpEvent, min: f64, max: f64) -> Result<(), RegistryError> {
+        self.find_event_measurement_mut(name, xcp_event)?.set_extended_limits(min, max);
         Ok(())
     }
 
+    /// Move a measurement registered on the given event to the auxiliary event of a measurement group gate, see DaqEvent::add_gate
+    /// The measurement is annotated with its group, the tool knows it is only transmitted while the group is enabled in the ECU
+    pub fn set_measurement_gate(&mut self, name: &'static str, xcp_event: XcpEvent, gate_event: XcpEvent, group: &'static str) -> Result<(), RegistryError> {
+        let m = self.find_event_measurement_mut(name, xcp_event)?;
+        m.xcp_event = gate_event;
+        m.annotations.push(RegistryAnnotation::new(MEASUREMENT_GROUP_ANNOTATION_LABEL, "", [group]));
+        Ok(())
+    }
+
+    /// Get the event offsets and sizes in bytes of the measurements with relative addressing (XCP_ADDR_EXT_DYN) registered on the given event
+    /// Used to validate the base objects of a trigger, see XcpEvent::trigger_with
+    pub fn get_event_offsets(&self, xcp_event: XcpEvent) -> Vec<(i16, usize)> {
+        self.measurement_list
+            .iter()
+            .filter(|m| m.addr == 0 && m.xcp_event == xcp_event)
+            .map(|m| (m.addr_offset, m.datatype.get_size().max(1) * m.x_dim as usize * m.y_dim as usize)) // Blob size is x_dim
+            .collect()
+    }
+
     // pub fn find_measurement(&self, name: &str) -> Option<&RegistryMeasurement> {
     //     self.measurement_list.iter().find(|m| m.name == name)
     // }
@@ -769,24 +1752,32 @@ impl Registry {
     /// # panics
     ///   If a measurement with the same name already exists
     ///   If the registry is closed
-    pub fn add_characteristic(&mut self, c: RegistryCharacteristic) -> Result<(), RegistryError> {
+    pub fn add_characteristic(&mut self, mut c: RegistryCharacteristic) -> Result<(), RegistryError> {
         debug!("Registry add_characteristic: {:?}.{} type={:?} offset={}", c.calseg_name, c.name, c.datatype, c.addr_offset);
 
         // Panic if registry is closed
         assert!(!self.is_frozen(), "Registry is closed");
 
-        // Panic if duplicate
-        for c1 in self.characteristic_list.iter() {
-            if c.name == c1.name {
-                return Err(RegistryError::Duplicate(c.name));
-            }
-        }
-
         // Check dimensions
         assert!(c.x_dim > 0);
         assert!(c.y_dim > 0);
 
-        self.characteristic_list.push(c);
+        // Limits from the value range of the data type
+        if self.type_limits && !c.verbal_bool {
+            c.apply_type_limits();
+        }
+
+        // Panic if duplicate, the name of the EPK calibration parameter is reserved
+        let split = c.split_128();
+        for c in &split {
+            if c.name == EPK_CHARACTERISTIC_NAME || self.characteristic_list.iter().any(|c1| c1.name == c.name) {
+                return Err(RegistryError::Duplicate(c.name.clone()));
+            }
+        }
+
+        for c in split {
+            self.characteristic_list.push(c);
+        }
         Ok(())
     }
 
@@ -794,13 +1785,62 @@ impl Registry {
         self.characteristic_list.iter().find(|c| c.name == name)
     }
 
+    /// Add a user annotation to an already registered measurement signal or calibration parameter
+    /// Measurement signals of multi instance events are identified by their name with instance suffix
+    pub fn add_annotation(&mut self, name: &'static str, annotation: RegistryAnnotation) -> Result<(), RegistryError> {
+        debug!("Registry add_annotation: {} {}", name, annotation.label);
+        assert!(!self.is_frozen(), "Registry is closed");
+
+        if let Some(m) = self.measurement_list.0.iter_mut().find(|m| m.name == name) {
+            m.annotations.push(annotation);
+        } else if let Some(c) = self.characteristic_list.0.iter_mut().find(|c| c.name == name) {
+            c.annotations.push(annotation);
+        } else {
+            return Err(RegistryError::NotFound(name));
+        }
+        Ok(())
+    }
+
+    /// Add an alias of an already registered measurement signal or calibration parameter
+    /// The alias is a copy with the same address, type and metadata, the calibration tool shows both names
+    /// Measurement signals of multi instance events are identified by their name with instance suffix, the alias name is used as is
+    pub fn add_alias(&mut self, name: &'static str, alias: &'static str) -> Result<(), RegistryError> {
+        debug!("Registry add_alias: {} -> {}", alias, name);
+        assert!(!self.is_frozen(), "Registry is closed");
+
+        if self.measurement_list.iter().any(|m| m.name == alias) || self.characteristic_list.iter().any(|c| c.name == alias) {
+            return Err(RegistryError::Duplicate(Cow::Borrowed(alias)));
+        }
+        if let Some(m) = self.measurement_list.iter().find(|m| m.name == name) {
+            let mut m = m.clone();
+            m.name = Cow::Borrowed(alias);
+            self.measurement_list.push(m);
+        } else if let Some(c) = self.find_characteristic(name) {
+            // An alias of an alias refers to the original
+            let mut c = c.clone();
+            c.alias_of = Some(c.alias_of.take().unwrap_or(c.name));
+            c.name = Cow::Borrowed(alias);
+            self.characteristic_list.push(c);
+        } else {
+            return Err(RegistryError::NotFound(name));
+        }
+        Ok(())
+    }
+
     #[cfg(feature = "a2l_reader")]
     pub fn a2l_load<P: AsRef<std::path::Path>>(&mut self, filename: P) -> Result<a2lfile::A2lFile, String> {
+        self.a2l_load_ext(filename, true, &mut Vec::new())
+    }
+
+    // Load an A2L file, with strict == false unknown keywords and blocks are skipped with a warning
+    // The warnings of the loader are returned in logmsgs
+    #[cfg(feature = "a2l_reader")]
+    #[allow(clippy::unused_self)]
+    fn a2l_load_ext<P: AsRef<std::path::Path>>(&mut self, filename: P, strict: bool, logmsgs: &mut Vec<a2lfile::A2lError>) -> Result<a2lfile::A2lFile, String> {
         let filename = filename.as_ref();
         trace!("Load A2L file {}", filename.display());
-        let mut logmsgs = Vec::<a2lfile::A2lError>::new();
-        let res = a2lfile::load(filename, None, &mut logmsgs, true);
-        for log_msg in logmsgs {
+        let res = a2lfile::load(filename, None, logmsgs, strict);
+        for log_msg in logmsgs.iter() {
             warn!("A2l Loader: {}", log_msg);
         }
         match res {
@@ -819,16 +1859,192 @@ impl Registry {
     }
 
     /// Generate A2L file from registry
-    pub fn write_a2l(&mut self) -> Result<(), std::io::Error> {
+    // All calibration parameters of a calibration segment with offset and size in bytes, sorted by offset
+    fn get_cal_seg_layout(&self, calseg_name: &str) -> Vec<(u64, u64, &RegistryCharacteristic)> {
+        let mut layout: Vec<(u64, u64, &RegistryCharacteristic)> = self
+            .characteristic_list
+            .iter()
+            .filter(|c| c.calseg_name == Some(calseg_name))
+            .map(|c| (c.addr_offset, (c.datatype.get_size() * c.x_dim * c.y_dim) as u64, c))
+            .collect();
+        layout.sort_by_key(|(offset, _, c)| (*offset, c.name.clone()));
+        layout
+    }
+
+    /// All calibration parameters of a calibration segment, sorted by offset
+    pub fn get_cal_seg_characteristics(&self, calseg_name: &str) -> Vec<&RegistryCharacteristic> {
+        self.get_cal_seg_layout(calseg_name).into_iter().map(|(_, _, c)| c).collect()
+    }
+
+    /// Check the memory layout of all calibration segments
+    /// Overlapping calibration parameters are an error, padding gaps are reported as info
+    pub fn check_layout(&self) -> Result<(), RegistryError> {
+        for s in self.cal_seg_list.iter() {
+            let mut end: u64 = 0;
+            let mut last: Option<&RegistryCharacteristic> = None;
+            for (offset, size, c) in self.get_cal_seg_layout(s.name) {
+                if offset < end && !last.is_some_and(|last| last.is_alias(c)) {
+                    let last = last.unwrap();
+                    error!("CalSeg {}: {} at offset {} overlaps {}", s.name, c.name, offset, last.name);
+                    return Err(RegistryError::Overlap(format!("{} {}", last.name, c.name)));
+                }
+                if offset > end {
+                    info!("CalSeg {}: {} bytes padding at offset {}", s.name, offset - end, end);
+                }
+                if size > 0 && offset / MAX_SEGMENT_SIZE as u64 != (offset + size - 1) / MAX_SEGMENT_SIZE as u64 {
+                    warn!("CalSeg {}: {} at offset {} crosses the boundary of the memory segments {}", s.name, c.name, offset, s.name);
+                }
+                end = offset + size;
+                last = Some(c);
+            }
+            if end > s.size as u64 {
+                error!("CalSeg {}: {} exceeds the segment size {}", s.name, last.unwrap().name, s.size);
+                return Err(RegistryError::Overlap(format!("{} {}", last.unwrap().name, s.name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Memory layout report of all calibration segments
+    /// A table of name, offset, size and type of all calibration parameters and padding gaps for each segment
+    pub fn layout_report(&self) -> String {
+        use std::fmt::Write;
+
+        let mut report = String::new();
+        for s in self.cal_seg_list.iter() {
+            writeln!(report, "CalSeg {} size={}", s.name, s.size).unwrap();
+            writeln!(report, "  offset   size   type           name").unwrap();
+            let mut end: u64 = 0;
+            for (offset, size, c) in self.get_cal_seg_layout(s.name) {
+                if offset > end {
+                    writeln!(report, "  0x{:04X}   {:<6}                <gap>", end, offset - end).unwrap();
+                }
+                let type_str = format!("{}[{}][{}]", c.datatype.get_type_str(), c.x_dim, c.y_dim);
+                writeln!(report, "  0x{:04X}   {:<6} {:<14} {}", offset, size, type_str, c.name).unwrap();
+                end = end.max(offset + size);
+            }
+            if (s.size as u64) > end {
+                writeln!(report, "  0x{:04X}   {:<6}                <gap>", end, s.size as u64 - end).unwrap();
+            }
+            report.push('\n');
+        }
+        report
+    }
+
+    /// Check that all axis input quantities of curves and maps are registered measurements
+    pub fn check_axis_inputs(&self) -> Result<(), RegistryError> {
+        for c in self.characteristic_list.iter() {
+            if let Some(x_axis_input) = &c.x_axis_input {
+                if !self.measurement_list.iter().any(|m| m.name == *x_axis_input) {
+                    error!("{}: axis input quantity {} is not a measurement", c.name, x_axis_input);
+                    return Err(RegistryError::AxisInput(x_axis_input.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the shared axes of all curves and maps
+    /// The referenced axis must be registered as axis and the point count must match the length of the x axis of the curve or map
+    /// All errors are collected in RegistryError::AxisCheck
+    pub fn check_axis_refs(&self) -> Result<(), RegistryError> {
+        let mut errors = Vec::new();
+        for c in self.characteristic_list.iter() {
+            if let Some(axis_pts_ref) = &c.axis_pts_ref {
+                match self.find_characteristic(axis_pts_ref) {
+                    Some(axis) if axis.is_axis => {
+                        let len = if c.x_dim > 1 { c.x_dim } else { c.y_dim };
+                        if len != axis.x_dim {
+                            errors.push(RegistryError::AxisLength {
+                                characteristic: c.name.to_string(),
+                                axis: axis_pts_ref.to_string(),
+                                len,
+                                axis_len: axis.x_dim,
+                            });
+                        }
+                    }
+                    _ => errors.push(RegistryError::AxisRef {
+                        characteristic: c.name.to_string(),
+                        axis: axis_pts_ref.to_string(),
+                    }),
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(RegistryError::AxisCheck(errors))
+        }
+    }
+
+    // Check the registry content and sort for A2L generation
+    fn prepare_a2l(&mut self) -> Result<(), std::io::Error> {
         // Error if registry is closed
         if self.is_frozen() {
             return Err(std::io::Error::new(std::io::ErrorKind::Other, "Registry is closed"));
         }
 
+        // Let plugins finalize their entries
+        self.notify_close_listeners();
+
+        // Shorten long identifiers, if enabled
+        self.shorten_identifiers();
+
+        // Log all findings of the A2L check, errors fail the A2L generation in strict mode
+        let findings = self.check_a2l();
+        for f in &findings {
+            match f.severity {
+                A2lCheckSeverity::Warning => warn!("A2L check: {}", f),
+                A2lCheckSeverity::Error => error!("A2L check: {}", f),
+            }
+        }
+        if self.a2l_strict && findings.iter().any(|f| f.severity == A2lCheckSeverity::Error) {
+            let errors: Vec<String> = findings.iter().filter(|f| f.severity == A2lCheckSeverity::Error).map(ToString::to_string).collect();
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, errors.join("\n")));
+        }
+
+        // Error if calibration parameters overlap or axis input quantities are unknown
+        if let Err(e) = self.check_layout().and_then(|()| self.check_axis_inputs()) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()));
+        }
+
+        // Error if shared axes are unknown or do not fit, warning only if opted out
+        if let Err(e) = self.check_axis_refs() {
+            if self.axis_check_warn_only {
+                warn!("{}", e);
+            } else {
+                error!("{}", e);
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()));
+            }
+        }
+
         // Sort measurement and calibration lists to get deterministic order
         // Event and CalSeg lists stay in the order the were added
-        self.measurement_list.sort();
-        self.characteristic_list.sort();
+        match self.sort_order {
+            SortOrder::ByName => {
+                self.measurement_list.sort();
+                self.characteristic_list.sort();
+            }
+            SortOrder::ByAddress => {
+                self.measurement_list.sort_by_address();
+                self.characteristic_list.sort_by_address(&self.cal_seg_list);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the A2L to any writer, e.g. a Vec<u8>, instead of the A2L file
+    pub fn write_a2l_to(&mut self, writer: &mut dyn std::io::Write) -> Result<(), std::io::Error> {
+        self.prepare_a2l()?;
+
+        let a2l_name = self.name.unwrap();
+        let mut a2l_writer = A2lWriter::new(writer, self);
+        a2l_writer.write_a2l(a2l_name, a2l_name)
+    }
+
+    pub fn write_a2l(&mut self) -> Result<(), std::io::Error> {
+        self.prepare_a2l()?;
 
         // Write to A2L file
         let a2l_name = self.name.unwrap();
@@ -840,6 +2056,20 @@ impl Registry {
         let mut a2l_writer = A2lWriter::new(writer, self);
         a2l_writer.write_a2l(a2l_name, a2l_name)?;
 
+        // Write the calibration segment memory layout report
+        if self.layout_report {
+            let layout_path = std::path::PathBuf::from(format!("{}_layout.txt", a2l_name));
+            info!("Write layout report {}", layout_path.display());
+            std::fs::write(&layout_path, self.layout_report())?;
+        }
+
+        // Write the mapping table of shortened identifiers
+        if !self.ident_map.is_empty() {
+            let ident_path = std::path::PathBuf::from(format!("{}_identifiers.txt", a2l_name));
+            info!("Write identifier mapping {}", ident_path.display());
+            std::fs::write(&ident_path, self.ident_map_report())?;
+        }
+
         // stdout
         // {
         //     let mut stdout = std::io::stdout().lock();
@@ -847,18 +2077,6 @@ impl Registry {
         //     a2l_writer.write_a2l(a2l_name, a2l_name)?;
         // }
 
-        // Vec - String - Hash
-        // {
-        //     let mut vec = std::io::Cursor::new(Vec::with_capacity(1024));
-        //     let mut a2l_writer = A2lWriter::new(&mut vec, self);
-        //     a2l_writer.write_a2l(a2l_name, a2l_name)?;
-        //     let s = String::from_utf8(vec.into_inner()).unwrap();
-        //     let mut hasher = std::hash::DefaultHasher::new();
-        //     std::hash::Hash::hash(&s.as_str(), &mut hasher);
-        //     let a2l_hash: u64 = hasher.finish();
-        //     info!("Current A2L hash = {}", a2l_hash);
-        // }
-
         // @@@@ Dev
         // Check A2L file
         #[cfg(feature = "a2l_reader")]
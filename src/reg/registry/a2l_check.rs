@@ -0,0 +1,331 @@
+//-----------------------------------------------------------------------------
+// Sub Module a2l_check
+// Structured check of super::registry before A2L generation
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use super::*;
+
+/// Max length of an A2L identifier (ASAM MCD-2 MC)
+pub const A2L_IDENT_MAX_LEN: usize = 1024;
+
+// Min size of an unregistered byte range in a calibration segment, which is reported as warning
+// Smaller gaps may be alignment padding, the largest alignment of a primitive type is 16 (u128)
+const A2L_CALSEG_GAP_WARN_SIZE: u64 = 16;
+
+/// Severity of an A2L check finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum A2lCheckSeverity {
+    /// The A2L file is valid, but may not work as intended
+    Warning,
+    /// The A2L file is invalid or will be rejected by the tool
+    Error,
+}
+
+impl std::fmt::Display for A2lCheckSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            A2lCheckSeverity::Warning => write!(f, "warning"),
+            A2lCheckSeverity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Finding of the A2L check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct A2lCheckFinding {
+    pub severity: A2lCheckSeverity,
+    pub name: String, // Name of the A2L object
+    pub description: String,
+}
+
+impl A2lCheckFinding {
+    fn new<N: Into<String>, D: Into<String>>(severity: A2lCheckSeverity, name: N, description: D) -> Self {
+        A2lCheckFinding {
+            severity,
+            name: name.into(),
+            description: description.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for A2lCheckFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}: {}", self.severity, self.name, self.description)
+    }
+}
+
+// Check the A2L identifier rules, first character is a letter or underscore, then letters, digits, underscore, dot and brackets
+fn check_ident(name: &str) -> Option<String> {
+    if name.is_empty() {
+        return Some("identifier is empty".to_string());
+    }
+    if name.len() > A2L_IDENT_MAX_LEN {
+        return Some(format!("identifier length {} exceeds {}", name.len(), A2L_IDENT_MAX_LEN));
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return Some("identifier must start with a letter or underscore".to_string());
+    }
+    if let Some(c) = name.chars().find(|&c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']'))) {
+        return Some(format!("invalid character {:?} in identifier", c));
+    }
+    None
+}
+
+// Deterministic short name, the prefix of the name and the FNV-1a hash of the full name
+fn shorten_ident(name: &str, max_len: usize) -> String {
+    let hash = name.bytes().fold(0x811C_9DC5u32, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193));
+    let prefix: String = name.chars().take(max_len - 9).collect();
+    format!("{}_{:08X}", prefix, hash)
+}
+
+impl Registry {
+    /// Check the registry content for A2L generation
+    /// Returns all findings, an empty list if the A2L file will be valid
+    pub fn check_a2l(&self) -> Vec<A2lCheckFinding> {
+        let mut findings = Vec::new();
+        self.check_a2l_idents(&mut findings);
+        self.check_a2l_duplicates(&mut findings);
+        self.check_a2l_axis(&mut findings);
+        self.check_a2l_segments(&mut findings);
+        self.check_a2l_conversions(&mut findings);
+        findings
+    }
+
+    // Names of events which are used as A2L group names, events with more than one measurement
+    fn get_event_group_names(&self) -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = Vec::new();
+        for e in self.event_list.iter() {
+            if e.xcp_event.get_index() > 1 || names.contains(&e.name) {
+                continue;
+            }
+            if self.measurement_list.iter().filter(|m| self.event_list.get_name(m.xcp_event) == Some(e.name)).count() > 1 {
+                names.push(e.name);
+            }
+        }
+        names
+    }
+
+    // Identifier charset and length of all objects, groups and display names
+    fn check_a2l_idents(&self, findings: &mut Vec<A2lCheckFinding>) {
+        let names = self
+            .measurement_list
+            .iter()
+            .map(|m| m.name.as_ref())
+            .chain(self.characteristic_list.iter().map(|c| c.name.as_ref()))
+            .chain(self.cal_seg_list.iter().map(|s| s.name))
+            .chain(self.get_event_group_names())
+            .chain(self.measurement_list.iter().filter_map(|m| m.display))
+            .chain(self.characteristic_list.iter().filter_map(|c| c.display));
+        for name in names {
+            if let Some(description) = check_ident(name) {
+                findings.push(A2lCheckFinding::new(A2lCheckSeverity::Error, name, description));
+            }
+        }
+    }
+
+    // Duplicate names of measurements and calibration parameters, and of groups
+    fn check_a2l_duplicates(&self, findings: &mut Vec<A2lCheckFinding>) {
+        let mut objects: HashMap<&str, &str> = HashMap::new();
+        for c in self.characteristic_list.iter() {
+            objects.insert(c.name.as_ref(), if c.is_axis { "axis" } else { "calibration parameter" });
+        }
+        for m in self.measurement_list.iter() {
+            if let Some(kind) = objects.get(m.name.as_ref()) {
+                findings.push(A2lCheckFinding::new(
+                    A2lCheckSeverity::Error,
+                    m.name.as_ref(),
+                    format!("measurement has the same name as a {}", kind),
+                ));
+            }
+        }
+
+        let event_groups = self.get_event_group_names();
+        for s in self.cal_seg_list.iter() {
+            if event_groups.contains(&s.name) {
+                findings.push(A2lCheckFinding::new(
+                    A2lCheckSeverity::Error,
+                    s.name,
+                    "calibration segment group has the same name as an event group",
+                ));
+            }
+        }
+    }
+
+    // Shared axis references and axis input quantities
+    fn check_a2l_axis(&self, findings: &mut Vec<A2lCheckFinding>) {
+        let severity = if self.axis_check_warn_only {
+            A2lCheckSeverity::Warning
+        } else {
+            A2lCheckSeverity::Error
+        };
+        for c in self.characteristic_list.iter() {
+            if let Some(axis_pts_ref) = &c.axis_pts_ref {
+                match self.find_characteristic(axis_pts_ref) {
+                    Some(axis) if axis.is_axis => {
+                        let len = if c.x_dim > 1 { c.x_dim } else { c.y_dim };
+                        if len != axis.x_dim {
+                            findings.push(A2lCheckFinding::new(
+                                severity,
+                                c.name.as_ref(),
+                                format!("{} x axis points, but axis {} has {} points", len, axis_pts_ref, axis.x_dim),
+                            ));
+                        }
+                    }
+                    _ => findings.push(A2lCheckFinding::new(severity, c.name.as_ref(), format!("{} is not a registered axis", axis_pts_ref))),
+                }
+            }
+            if let Some(x_axis_input) = &c.x_axis_input {
+                if !self.measurement_list.iter().any(|m| m.name == *x_axis_input) {
+                    findings.push(A2lCheckFinding::new(
+                        A2lCheckSeverity::Error,
+                        c.name.as_ref(),
+                        format!("axis input quantity {} is not a measurement", x_axis_input),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Calibration parameters must be inside their calibration segment and must not overlap
+    // Large unregistered byte ranges are uploaded to the tool, but can not be calibrated
+    fn check_a2l_segments(&self, findings: &mut Vec<A2lCheckFinding>) {
+        for c in self.characteristic_list.iter() {
+            if let Some(calseg_name) = c.calseg_name {
+                if self.cal_seg_list.find(calseg_name).is_none() {
+                    findings.push(A2lCheckFinding::new(
+                        A2lCheckSeverity::Error,
+                        c.name.as_ref(),
+                        format!("unknown calibration segment {}", calseg_name),
+                    ));
+                }
+            }
+        }
+        for s in self.cal_seg_list.iter() {
+            // Segments without any registered calibration parameter are intentionally opaque
+            let layout = self.get_cal_seg_layout(s.name);
+            if layout.is_empty() {
+                continue;
+            }
+            let mut end: u64 = 0;
+            let mut last: Option<&RegistryCharacteristic> = None;
+            for (offset, size, c) in layout {
+                // Aliases share the memory of the original
+                if offset < end && !last.is_some_and(|last| last.is_alias(c)) {
+                    findings.push(A2lCheckFinding::new(
+                        A2lCheckSeverity::Error,
+                        c.name.as_ref(),
+                        format!("offset {} overlaps {}", offset, last.unwrap().name),
+                    ));
+                }
+                if offset + size > s.size as u64 {
+                    findings.push(A2lCheckFinding::new(
+                        A2lCheckSeverity::Error,
+                        c.name.as_ref(),
+                        format!("offset {} size {} exceeds the size {} of calibration segment {}", offset, size, s.size, s.name),
+                    ));
+                }
+                if offset >= end + A2L_CALSEG_GAP_WARN_SIZE {
+                    findings.push(A2lCheckFinding::new(
+                        A2lCheckSeverity::Warning,
+                        s.name,
+                        format!("{} bytes at offset {} are not registered", offset - end, end),
+                    ));
+                }
+                end = end.max(offset + size);
+                last = Some(c);
+            }
+            if s.size as u64 >= end + A2L_CALSEG_GAP_WARN_SIZE {
+                findings.push(A2lCheckFinding::new(
+                    A2lCheckSeverity::Warning,
+                    s.name,
+                    format!("{} bytes at offset {} are not registered", s.size as u64 - end, end),
+                ));
+            }
+        }
+    }
+
+    // Linear conversions of measurements must be invertible, serialized data needs a type description
+    fn check_a2l_conversions(&self, findings: &mut Vec<A2lCheckFinding>) {
+        for m in self.measurement_list.iter() {
+            if m.datatype == RegistryDataType::Blob {
+                if m.annotation.is_none() {
+                    findings.push(A2lCheckFinding::new(
+                        A2lCheckSeverity::Error,
+                        m.name.as_ref(),
+                        "serialized data without type description annotation",
+                    ));
+                }
+            } else if m.factor == 0.0 || !m.factor.is_finite() || !m.offset.is_finite() {
+                findings.push(A2lCheckFinding::new(
+                    A2lCheckSeverity::Error,
+                    m.name.as_ref(),
+                    format!("invalid linear conversion factor={} offset={}", m.factor, m.offset),
+                ));
+            }
+        }
+    }
+
+    // Shorten measurement and calibration parameter names longer than the configured max length
+    // References of axis and axis input quantities are renamed accordingly, the mapping is kept in ident_map
+    pub(super) fn shorten_identifiers(&mut self) {
+        let Some(max_len) = self.ident_max_len else {
+            return;
+        };
+
+        let mut map: HashMap<String, String> = HashMap::new();
+        for m in self.measurement_list.iter_mut() {
+            if m.name.len() > max_len {
+                let short = shorten_ident(&m.name, max_len);
+                map.insert(m.name.to_string(), short.clone());
+                m.name = Cow::Owned(short);
+            }
+        }
+        for c in self.characteristic_list.iter_mut() {
+            if c.name.len() > max_len {
+                let short = shorten_ident(&c.name, max_len);
+                map.insert(c.name.to_string(), short.clone());
+                c.name = Cow::Owned(short);
+            }
+        }
+        if map.is_empty() {
+            return;
+        }
+
+        for c in self.characteristic_list.iter_mut() {
+            if let Some(short) = c.axis_pts_ref.as_ref().and_then(|r| map.get(r.as_ref())) {
+                c.axis_pts_ref = Some(Cow::Owned(short.clone()));
+            }
+            if let Some(short) = c.x_axis_input.as_ref().and_then(|r| map.get(r.as_ref())) {
+                c.x_axis_input = Some(Cow::Owned(short.clone()));
+            }
+            if let Some(short) = c.alias_of.as_ref().and_then(|r| map.get(r.as_ref())) {
+                c.alias_of = Some(Cow::Owned(short.clone()));
+            }
+        }
+
+        let mut map: Vec<(String, String)> = map.into_iter().map(|(long, short)| (short, long)).collect();
+        map.sort();
+        for (short, long) in &map {
+            debug!("Shortened identifier {} -> {}", long, short);
+        }
+        self.ident_map.extend(map);
+    }
+
+    /// Mapping of shortened identifiers to the original names, as (short, long) pairs sorted by short name
+    pub fn get_ident_map(&self) -> &[(String, String)] {
+        &self.ident_map
+    }
+
+    // Mapping table of shortened identifiers, one line per identifier
+    pub(super) fn ident_map_report(&self) -> String {
+        use std::fmt::Write;
+
+        let mut report = String::new();
+        for (short, long) in &self.ident_map {
+            writeln!(report, "{} {}", short, long).unwrap();
+        }
+        report
+    }
+}
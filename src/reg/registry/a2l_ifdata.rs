@@ -0,0 +1,153 @@
+//-----------------------------------------------------------------------------
+// Sub Module a2l_ifdata
+// Vendor specific IF_DATA blocks, passed through verbatim to the A2L file
+
+use super::*;
+
+// Check if the text is a single IF_DATA block
+pub(super) fn is_ifdata_block(text: &str) -> bool {
+    let text = text.trim();
+    text.starts_with("/begin IF_DATA") && text.ends_with("/end IF_DATA")
+}
+
+// Split the A2L text into tokens with their byte offsets
+// Strings are single tokens, comments are skipped
+fn tokenize(a2l: &str) -> Vec<(usize, &str)> {
+    let bytes = a2l.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        if bytes[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if a2l[i..].starts_with("/*") {
+            i = a2l[i + 2..].find("*/").map_or(bytes.len(), |n| i + 2 + n + 2);
+            continue;
+        } else if a2l[i..].starts_with("//") {
+            i = a2l[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            continue;
+        } else if bytes[i] == b'"' {
+            // Escaped quotes are \" or ""
+            i += 1;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'\\' => i += 2,
+                    b'"' if bytes.get(i + 1) == Some(&b'"') => i += 2,
+                    b'"' => {
+                        i += 1;
+                        break;
+                    }
+                    _ => i += 1,
+                }
+            }
+        } else {
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+        }
+        tokens.push((start, &a2l[start..i.min(bytes.len())]));
+    }
+    tokens
+}
+
+// Find all IF_DATA blocks other than XCP with the name of the enclosing MEASUREMENT, CHARACTERISTIC, AXIS_PTS or BLOB
+// The name is None for blocks on module level, blocks in other scopes are ignored
+fn scan_ifdata(a2l: &str) -> Vec<(Option<&str>, &str)> {
+    let tokens = tokenize(a2l);
+    let mut blocks = Vec::new();
+    let mut scopes: Vec<(&str, Option<&str>)> = Vec::new(); // Keyword and name of the enclosing blocks
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i].1 {
+            "/begin" if i + 1 < tokens.len() => {
+                let keyword = tokens[i + 1].1;
+                if keyword == "IF_DATA" && tokens.get(i + 2).is_some_and(|t| t.1 != "XCP") {
+                    // Skip to the matching /end IF_DATA
+                    let start = tokens[i].0;
+                    let mut depth = 0;
+                    let mut j = i;
+                    while j + 1 < tokens.len() {
+                        match tokens[j].1 {
+                            "/begin" => depth += 1,
+                            "/end" => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break;
+                        }
+                        j += 1;
+                    }
+                    if depth != 0 {
+                        break; // Incomplete block at the end of the file
+                    }
+                    let end = tokens[j + 1].0 + tokens[j + 1].1.len();
+                    match scopes.last() {
+                        Some(("MODULE", _)) => blocks.push((None, &a2l[start..end])),
+                        Some((keyword, name)) if matches!(*keyword, "MEASUREMENT" | "CHARACTERISTIC" | "AXIS_PTS" | "BLOB") => blocks.push((*name, &a2l[start..end])),
+                        _ => {}
+                    }
+                    i = j + 2;
+                    continue;
+                }
+                scopes.push((keyword, tokens.get(i + 2).map(|t| t.1)));
+                i += 2;
+            }
+            "/end" => {
+                scopes.pop();
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+    blocks
+}
+
+impl Registry {
+    /// Add a vendor specific IF_DATA block on module level, e.g. "/begin IF_DATA CANAPE_EXT ... /end IF_DATA"
+    /// The block is written verbatim to the A2L file
+    pub fn add_module_ifdata<T: Into<String>>(&mut self, text: T) {
+        let text = text.into();
+        debug!("Registry add_module_ifdata: {}", text);
+        assert!(!self.is_frozen(), "Registry is closed");
+        assert!(is_ifdata_block(&text), "Not an IF_DATA block");
+        self.module_ifdata.push(text);
+    }
+
+    /// Vendor specific IF_DATA blocks on module level
+    pub fn get_module_ifdata(&self) -> &[String] {
+        &self.module_ifdata
+    }
+
+    /// Capture the vendor specific IF_DATA blocks of an existing A2L
+    /// Blocks on module level and blocks of already registered measurement signals and calibration parameters are kept verbatim,
+    /// IF_DATA XCP is generated and ignored here
+    /// Returns the number of captured blocks, blocks which are already registered are not counted
+    pub fn import_a2l_ifdata(&mut self, a2l: &str) -> usize {
+        assert!(!self.is_frozen(), "Registry is closed");
+
+        let mut count = 0;
+        for (name, text) in scan_ifdata(a2l) {
+            let ifdata = match name {
+                None => &mut self.module_ifdata,
+                Some(name) => {
+                    if let Some(m) = self.measurement_list.iter_mut().find(|m| m.name == name) {
+                        &mut m.ifdata
+                    } else if let Some(c) = self.characteristic_list.iter_mut().find(|c| c.name == name) {
+                        &mut c.ifdata
+                    } else {
+                        warn!("IF_DATA of {} ignored, not registered", name);
+                        continue;
+                    }
+                }
+            };
+            // Blocks already registered, e.g. by a type description attribute, are not duplicated
+            if !ifdata.iter().any(|b| b == text) {
+                ifdata.push(text.to_string());
+                count += 1;
+            }
+        }
+        debug!("Registry import_a2l_ifdata: {} blocks", count);
+        count
+    }
+}
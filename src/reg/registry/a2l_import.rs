@@ -0,0 +1,239 @@
+//-----------------------------------------------------------------------------
+// Sub Module a2l_import
+// Merge the calibration parameters of an external A2L file into super::registry
+
+use std::borrow::Cow;
+
+use a2lfile::{A2lError, A2lFile, CharacteristicType, DataType, Module, ParserError};
+
+use super::*;
+
+fn leak(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
+// Registry data type of a record layout
+fn get_deposit_type(module: &Module, deposit: &str) -> Option<RegistryDataType> {
+    let fnc_values = module.record_layout.iter().find(|r| r.name == deposit)?.fnc_values.as_ref()?;
+    match fnc_values.datatype {
+        DataType::Ubyte => Some(RegistryDataType::Ubyte),
+        DataType::Sbyte => Some(RegistryDataType::Sbyte),
+        DataType::Uword => Some(RegistryDataType::Uword),
+        DataType::Sword => Some(RegistryDataType::Sword),
+        DataType::Ulong => Some(RegistryDataType::Ulong),
+        DataType::Slong => Some(RegistryDataType::Slong),
+        DataType::AUint64 => Some(RegistryDataType::AUint64),
+        DataType::AInt64 => Some(RegistryDataType::AInt64),
+        DataType::Float32Ieee => Some(RegistryDataType::Float32Ieee),
+        DataType::Float64Ieee => Some(RegistryDataType::Float64Ieee),
+        DataType::Float16Ieee => None,
+    }
+}
+
+// Dimensions (x_dim, y_dim) from the axis descriptions or the MATRIX_DIM of a calibration parameter
+fn get_dim(characteristic_type: CharacteristicType, axis_points: &[u16], matrix_dim: Option<&a2lfile::MatrixDim>) -> (usize, usize) {
+    match characteristic_type {
+        CharacteristicType::Curve | CharacteristicType::Map => (axis_points.first().map_or(1, |&n| n as usize), axis_points.get(1).map_or(1, |&n| n as usize)),
+        _ => match matrix_dim.map(|m| m.dim_list.as_slice()) {
+            Some([x]) => (*x as usize, 1),
+            Some([x, y, ..]) => (*x as usize, *y as usize),
+            _ => (1, 1),
+        },
+    }
+}
+
+// Calibration parameters imported from an A2L file
+struct A2lImporter<'a> {
+    module: &'a Module,
+    namespace: &'a str,
+    characteristics: Vec<RegistryCharacteristic>,
+}
+
+impl A2lImporter<'_> {
+    // Calibration segment and offset of an address, the segment is identified by the name of the MEMORY_SEGMENT of the imported file
+    fn get_calseg(&self, name: &str, addr: u32) -> Option<(&'static str, u64)> {
+        let segment = self.module.mod_par.as_ref()?.memory_segment.iter().find(|s| addr >= s.address && addr - s.address < s.size);
+        if segment.is_none() {
+            warn!("A2L import: {} at 0x{:08X} is not in a memory segment, ignored", name, addr);
+        }
+        segment.map(|s| (leak(s.name.clone()), (addr - s.address) as u64))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add(
+        &mut self,
+        name: &str,
+        comment: &str,
+        deposit: &str,
+        addr: u32,
+        dim: (usize, usize),
+        min: f64,
+        max: f64,
+        extended_limits: Option<&a2lfile::ExtendedLimits>,
+        unit: Option<&str>,
+    ) {
+        let Some(datatype) = get_deposit_type(self.module, deposit) else {
+            warn!("A2L import: {} has unsupported record layout {}, ignored", name, deposit);
+            return;
+        };
+        let Some((calseg_name, offset)) = self.get_calseg(name, addr) else {
+            return;
+        };
+        let mut c = RegistryCharacteristic::new(
+            Some(calseg_name),
+            format!("{}.{}", self.namespace, name),
+            datatype,
+            leak(comment.to_string()),
+            min,
+            max,
+            leak(unit.unwrap_or_default().to_string()),
+            dim.0,
+            dim.1,
+            offset,
+        );
+        if let Some(e) = extended_limits {
+            if e.lower_limit <= min && e.upper_limit >= max {
+                c.set_extended_limits(e.lower_limit, e.upper_limit);
+            } else {
+                warn!("A2L import: {} has EXTENDED_LIMITS not enclosing LIMITS, ignored", c.get_name());
+            }
+        }
+        self.characteristics.push(c);
+    }
+
+    fn import_characteristics(&mut self) {
+        for c in &self.module.characteristic {
+            // The EPK of the imported file is not a calibration parameter
+            if c.name == EPK_CHARACTERISTIC_NAME {
+                continue;
+            }
+            let axis_points: Vec<u16> = c.axis_descr.iter().map(|a| a.max_axis_points).collect();
+            let dim = get_dim(c.characteristic_type, &axis_points, c.matrix_dim.as_ref());
+            let unit = c.phys_unit.as_ref().map(|u| u.unit.as_str());
+            self.add(
+                &c.name,
+                &c.long_identifier,
+                &c.deposit,
+                c.address,
+                dim,
+                c.lower_limit,
+                c.upper_limit,
+                c.extended_limits.as_ref(),
+                unit,
+            );
+        }
+    }
+
+    // Expand an instance of a TYPEDEF_STRUCTURE or TYPEDEF_CHARACTERISTIC, nested structures recursively
+    fn import_instance(&mut self, type_ref: &str, name: &str, addr: u32, matrix_dim: Option<&a2lfile::MatrixDim>) {
+        let module = self.module;
+        if let Some(s) = module.typedef_structure.iter().find(|t| t.name == type_ref) {
+            let dim: u32 = matrix_dim.map_or(1, |d| d.dim_list.iter().map(|&d| d as u32).product());
+            for i in 0..dim {
+                let name = if matrix_dim.is_some() { format!("{}[{}]", name, i) } else { name.to_string() };
+                for c in &s.structure_component {
+                    let component_name = format!("{}.{}", name, c.component_name);
+                    self.import_instance(&c.component_type, &component_name, addr + i * s.total_size + c.address_offset, c.matrix_dim.as_ref());
+                }
+            }
+        } else if let Some(t) = module.typedef_characteristic.iter().find(|t| t.name == type_ref) {
+            let axis_points: Vec<u16> = t.axis_descr.iter().map(|a| a.max_axis_points).collect();
+            let dim = get_dim(t.characteristic_type, &axis_points, matrix_dim.or(t.matrix_dim.as_ref()));
+            let unit = t.phys_unit.as_ref().map(|u| u.unit.as_str());
+            self.add(
+                name,
+                &t.long_identifier,
+                &t.record_layout,
+                addr,
+                dim,
+                t.lower_limit,
+                t.upper_limit,
+                t.extended_limits.as_ref(),
+                unit,
+            );
+        } else {
+            debug!("A2L import: {} of type {} is not a calibration parameter, ignored", name, type_ref);
+        }
+    }
+}
+
+/// Result of a lenient A2L import, see Registry::load_a2l_lenient
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct A2lImportReport {
+    pub count: usize,                  // Number of imported calibration parameters
+    pub warning_count: usize,          // Number of warnings of the A2L loader, including the skipped keywords
+    pub skipped_keywords: Vec<String>, // Unknown keywords, which were skipped with their value or block
+}
+
+impl Registry {
+    /// Merge the calibration parameters of an external A2L file into the registry
+    /// CHARACTERISTICs and INSTANCEs of TYPEDEF_STRUCTUREs are imported as calibration parameters with the name prefix "<namespace>."
+    /// The calibration segment of a parameter is the MEMORY_SEGMENT of the imported file it is located in, identified by its name
+    /// Measurement signals are not imported, their events are unknown in this registry
+    /// Returns the number of imported calibration parameters
+    /// Fails with RegistryError::Duplicate, if a TYPEDEF_STRUCTURE name has already been imported or a prefixed name is already registered
+    pub fn load_a2l_append<P: AsRef<std::path::Path>>(&mut self, path: P, namespace: &str) -> Result<usize, RegistryError> {
+        let path = path.as_ref();
+        info!("Import A2L file {} with namespace {}", path.display(), namespace);
+        let a2l_file: A2lFile = self.a2l_load(path).map_err(RegistryError::A2l)?;
+        self.append_a2l(&a2l_file, namespace)
+    }
+
+    /// Merge the calibration parameters of an external A2L file into the registry like load_a2l_append
+    /// Unknown keywords, e.g. of newer or older ASAM A2L versions or of non conforming tools, are skipped with their value or block and logged as warning
+    /// Returns the number of imported calibration parameters, the number of warnings and the skipped keywords
+    pub fn load_a2l_lenient<P: AsRef<std::path::Path>>(&mut self, path: P, namespace: &str) -> Result<A2lImportReport, RegistryError> {
+        let path = path.as_ref();
+        info!("Import A2L file {} with namespace {}, skip unknown keywords", path.display(), namespace);
+        let mut logmsgs = Vec::new();
+        let a2l_file: A2lFile = self.a2l_load_ext(path, false, &mut logmsgs).map_err(RegistryError::A2l)?;
+        let skipped_keywords = logmsgs
+            .iter()
+            .filter_map(|e| match e {
+                A2lError::ParserError {
+                    parser_error: ParserError::UnknownSubBlock { tag, .. },
+                } => Some(tag.clone()),
+                _ => None,
+            })
+            .collect();
+        let count = self.append_a2l(&a2l_file, namespace)?;
+        Ok(A2lImportReport {
+            count,
+            warning_count: logmsgs.len(),
+            skipped_keywords,
+        })
+    }
+
+    // Merge the calibration parameters of a loaded A2L file
+    fn append_a2l(&mut self, a2l_file: &A2lFile, namespace: &str) -> Result<usize, RegistryError> {
+        assert!(!self.is_frozen(), "Registry is closed");
+        let module = a2l_file.project.module.first().ok_or_else(|| RegistryError::A2l("no MODULE".to_string()))?;
+
+        // Typedefs keep their original name, all names are checked before anything is merged
+        if let Some(t) = module.typedef_structure.iter().find(|t| self.typedef_names.contains(&t.name)) {
+            return Err(RegistryError::Duplicate(Cow::Owned(t.name.clone())));
+        }
+
+        let mut importer = A2lImporter {
+            module,
+            namespace,
+            characteristics: Vec::new(),
+        };
+        importer.import_characteristics();
+        for instance in &module.instance {
+            importer.import_instance(&instance.type_ref, &instance.name, instance.start_address, instance.matrix_dim.as_ref());
+        }
+        let characteristics = importer.characteristics;
+        if let Some(c) = characteristics.iter().find(|c| self.find_characteristic(&c.name).is_some()) {
+            return Err(RegistryError::Duplicate(c.name.clone()));
+        }
+
+        let count = characteristics.len();
+        for c in characteristics {
+            self.add_characteristic(c)?;
+        }
+        self.typedef_names.extend(module.typedef_structure.iter().map(|t| t.name.clone()));
+        debug!("Registry load_a2l_append: {} calibration parameters", count);
+        Ok(count)
+    }
+}
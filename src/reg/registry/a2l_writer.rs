@@ -8,7 +8,13 @@ use std::io::Write;
 use log::{debug, error, info, trace, warn};
 
 use super::*;
-use crate::Xcp;
+
+// Conversion of bool calibration parameters with verbal bool
+const VERBAL_BOOL_COMPU_METHOD: &str = "bool.Conv";
+const VERBAL_BOOL_COMPU_VTAB: &str = "bool.Tab";
+const COLUMN_DIR_RECORD_LAYOUT_SUFFIX: &str = "_COL";
+const PLAUSIBLE_LIMITS_ANNOTATION_LABEL: &str = "PLAUSIBLE_LIMITS";
+const DEFAULT_A2L_VERSION: &str = "1.0";
 
 trait GenerateA2l {
     fn write_a2l(&self, writer: &mut A2lWriter) -> std::io::Result<()>;
@@ -36,7 +42,7 @@ impl GenerateA2l for RegistryEvent {
 
         trace!("Write event {} index={}  channel={}", name, index, channel);
 
-        let priority = 0; // Priority is always set to normal priority
+        let priority = self.priority.to_a2l_priority();
 
         // Convert cycle time to ASAM coding timeCycle and timeUnit
         // "UNIT_1NS" = 0, "UNIT_10NS" = 1, ...
@@ -52,14 +58,25 @@ impl GenerateA2l for RegistryEvent {
         // short name 8+1 characters
         // TimeCycle 0
         // TimeUnit 0
-        // Priority 0
+        // Priority 0 normal, 1 high
         // @@@@ ToDo: CANape does not accept CONSISTENCY EVENT for serialized data types
-        if index > 0 {
+        if let Some(label) = self.xcp_event.get_instance_label() {
+            let name = format!("{}_{}", name, label);
+            write!(writer, "/begin EVENT \"{:.100}\" \"{:.8}\" ", name, name)?;
+        } else if index > 0 {
             write!(writer, "/begin EVENT \"{:.98}_{}\" \"{:.6}_{}\" ", name, index, name, index)?;
         } else {
             write!(writer, "/begin EVENT \"{:.100}\" \"{:.8}\" ", name, name)?;
         }
-        writeln!(writer, "{} DAQ 0xFF {} {} {} CONSISTENCY DAQ /end EVENT", channel, time_cycle, time_unit, priority)
+        writeln!(
+            writer,
+            "{} DAQ 0xFF {} {} {} CONSISTENCY {} /end EVENT",
+            channel,
+            time_cycle,
+            time_unit,
+            priority,
+            self.consistency.to_a2l_str()
+        )
     }
 }
 
@@ -84,15 +101,33 @@ impl GenerateA2l for RegistryEpk {
     }
 }
 
+impl RegistryEpk {
+    // The EPK as read only ASCII calibration parameter in the EPK memory segment, to read the EPK with standard addressing
+    fn write_a2l_characteristic(&self, writer: &mut A2lWriter) -> std::io::Result<()> {
+        if let Some(epk) = self.epk {
+            writeln!(
+                writer,
+                r#"/begin CHARACTERISTIC {EPK_CHARACTERISTIC_NAME} "EPK" ASCII 0x{:X} U8 0 NO_COMPU_METHOD 0 255 READ_ONLY NUMBER {} /end CHARACTERISTIC"#,
+                self.epk_addr,
+                epk.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 
 impl GenerateA2l for RegistryCalSegList {
     fn write_a2l(&self, writer: &mut A2lWriter) -> std::io::Result<()> {
-        for (n, calseg) in self.iter().enumerate() {
-            trace!("write A2lMemorySegment: {}  {}:0x{:X} size={}", calseg.name, calseg.addr_ext, calseg.addr, calseg.size);
-            writeln!(
-                writer,
-                r#" 
+        let mut n = 0;
+        for calseg in self.iter() {
+            for (name, addr, size) in calseg.get_memory_segments() {
+                n += 1;
+                trace!("write A2lMemorySegment: {}  {}:0x{:X} size={}", name, calseg.addr_ext, addr, size);
+                writeln!(
+                    writer,
+                    r#" 
             /begin MEMORY_SEGMENT
                 {} "" DATA FLASH INTERN 0x{:X} {} -1 -1 -1 -1 -1
                 /begin IF_DATA XCP
@@ -103,12 +138,9 @@ impl GenerateA2l for RegistryCalSegList {
                     /end SEGMENT
                 /end IF_DATA
             /end MEMORY_SEGMENT"#,
-                calseg.name,
-                calseg.addr,
-                calseg.size,
-                n + 1,
-                calseg.addr_ext,
-            )?;
+                    name, addr, size, n, calseg.addr_ext,
+                )?;
+            }
         }
         Ok(())
     }
@@ -118,13 +150,7 @@ impl GenerateA2l for RegistryCalSegList {
 
 impl GenerateA2l for RegistryMeasurement {
     fn write_a2l(&self, writer: &mut A2lWriter) -> std::io::Result<()> {
-        let (ext, addr) = if self.addr == 0 {
-            // DYN
-            self.xcp_event.get_dyn_ext_addr(self.addr_offset)
-        } else {
-            // ABS
-            Xcp::get_abs_ext_addr(self.addr)
-        };
+        let (ext, addr) = self.get_ext_addr();
 
         trace!(
             "write measurement: {} {} {}:0x{:08X} event={}+{}, addr=0x{:08X}",
@@ -141,8 +167,6 @@ impl GenerateA2l for RegistryMeasurement {
         let comment = self.comment;
         let unit = self.unit;
         let factor = self.factor;
-        let min = self.datatype.get_min_str();
-        let max = self.datatype.get_max_str();
         let offset = self.offset;
         let type_str = self.datatype.get_type_str();
         let x_dim = self.x_dim;
@@ -174,10 +198,38 @@ impl GenerateA2l for RegistryMeasurement {
  "#
             )?;
         } else {
-            if (self.factor - 1.0).abs() > f64::EPSILON || self.offset != 0.0 || !self.unit.is_empty() {
+            // Limits are physical values
+            // Explicit limits of integer physical values are written as integers
+            // With an explicit physical type, they default to the converted range of the storage type, bounded by the range of the physical type
+            // Without conversion, they default to the registry default limits
+            // With extended limits, the physically possible range is written and the plausible range is an annotation
+            let has_conversion = self.phys_type.is_some() || (self.factor - 1.0).abs() > f64::EPSILON || self.offset != 0.0;
+            let (min, max) = if has_conversion {
+                (self.datatype.get_min_str(), self.datatype.get_max_str())
+            } else {
+                writer.registry.default_limits.get_limits_str(self.datatype)
+            };
+            let (min, max) = match (self.extended_limits.or(self.limits), self.phys_type) {
+                (Some((min, max)), _) => match self.get_int_phys_type() {
+                    Some(int_type) => (int_type.format_limit(min), int_type.format_limit(max)),
+                    None => (min.to_string(), max.to_string()),
+                },
+                (None, Some(phys_type)) => {
+                    let (a, b) = (self.datatype.get_min() * factor + offset, self.datatype.get_max() * factor + offset);
+                    (a.min(b).max(phys_type.get_min()).to_string(), a.max(b).min(phys_type.get_max()).to_string())
+                }
+                (None, None) => (min.to_string(), max.to_string()),
+            };
+
+            if has_conversion || !self.unit.is_empty() {
+                // Integer physical values are displayed without decimals
+                let format = match self.phys_type {
+                    Some(RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee) | None => "%6.3",
+                    Some(_) => "%.0",
+                };
                 writeln!(
                     writer,
-                    r#"/begin COMPU_METHOD {name}.Conv "" LINEAR "%6.3" "{unit}" COEFFS_LINEAR {factor} {offset} /end COMPU_METHOD"#
+                    r#"/begin COMPU_METHOD {name}.Conv "" LINEAR "{format}" "{unit}" COEFFS_LINEAR {factor} {offset} /end COMPU_METHOD"#
                 )?;
                 write!(
                     writer,
@@ -198,11 +250,37 @@ impl GenerateA2l for RegistryMeasurement {
             } else if y_dim > 1 {
                 write!(writer, " MATRIX_DIM {}", y_dim)?;
             }
+
+            if let Some(format) = self.format {
+                write!(writer, r#" FORMAT "{}""#, format)?;
+            }
+            if let Some(display) = self.display {
+                write!(writer, " DISPLAY_IDENTIFIER {}", display)?;
+            }
+
+            // Plausible range, if there is a physically possible range
+            if let (Some(_), Some((min, max))) = (self.extended_limits, self.limits) {
+                let (min, max) = match self.get_int_phys_type() {
+                    Some(int_type) => (int_type.format_limit(min), int_type.format_limit(max)),
+                    None => (min.to_string(), max.to_string()),
+                };
+                write!(writer, "\n{}\n", RegistryAnnotation::new(PLAUSIBLE_LIMITS_ANNOTATION_LABEL, "", [min, max]))?;
+            }
+        }
+
+        // User annotations
+        for annotation in &self.annotations {
+            write!(writer, "\n{annotation}\n")?;
         }
 
         // Fixed event
         write!(writer, " /begin IF_DATA XCP /begin DAQ_EVENT FIXED_EVENT_LIST EVENT {event} /end DAQ_EVENT /end IF_DATA")?;
 
+        // Vendor specific IF_DATA
+        for ifdata in &self.ifdata {
+            write!(writer, "\n{ifdata}")?;
+        }
+
         if self.datatype == RegistryDataType::Blob {
             writeln!(writer, r#" /end BLOB"#)?;
             // writeln!(writer, r#" /end CHARACTERISTIC"#)?;
@@ -220,44 +298,89 @@ impl GenerateA2l for RegistryCharacteristic {
     fn write_a2l(&self, writer: &mut A2lWriter) -> std::io::Result<()> {
         let characteristic_type = self.get_type_str();
         let datatype = self.datatype.get_deposit_str();
+        // Column major maps reference the RECORD_LAYOUT with COLUMN_DIR
+        let record_layout = if self.x_dim > 1 && self.y_dim > 1 && !writer.registry.row_major {
+            format!("{}{}", datatype, COLUMN_DIR_RECORD_LAYOUT_SUFFIX)
+        } else {
+            datatype.to_string()
+        };
 
         // Calculate the address extension and address of this Characteristic
-        let (a2l_ext, a2l_addr) = if let Some(calseg_name) = self.calseg_name {
-            // Segment relative addressing
-            let index = writer.registry.get_cal_seg_index(calseg_name).expect("unknown calseg");
-            Xcp::get_calseg_ext_addr(index, self.addr_offset.try_into().expect("offset too large"))
+        let (a2l_ext, a2l_addr) = self.get_ext_addr(&writer.registry.cal_seg_list);
+
+        // Without limits, the registry default limits apply
+        let (min, max) = if self.min == 0.0 && self.max == 0.0 && !self.verbal_bool {
+            let (min, max) = writer.registry.default_limits.get_limits_str(self.datatype);
+            (min.to_string(), max.to_string())
         } else {
-            // Absolute addressing
-            Xcp::get_abs_ext_addr(self.addr_offset)
+            (self.min.to_string(), self.max.to_string())
         };
 
+        // Shared axis
+        if self.is_axis {
+            write!(
+                writer,
+                r#"
+/begin AXIS_PTS {} "{}" 0x{:X} NO_INPUT_QUANTITY A_{} 0 NO_COMPU_METHOD {} {} {}"#,
+                self.name, self.comment, a2l_addr, datatype, self.x_dim, min, max,
+            )?;
+            if !self.unit.is_empty() {
+                write!(writer, r#" PHYS_UNIT "{}""#, self.unit)?;
+            }
+            if a2l_ext != 0 {
+                write!(writer, " ECU_ADDRESS_EXTENSION {}", a2l_ext)?;
+            }
+            if self.monotone_increasing {
+                write!(writer, " MONOTONY STRICT_INCREASE")?;
+            }
+            self.write_a2l_display(writer)?;
+            for annotation in &self.annotations {
+                write!(writer, "\n{annotation}\n")?;
+            }
+            self.write_a2l_ifdata(writer)?;
+            write!(writer, " /end AXIS_PTS")?;
+            return Ok(());
+        }
+
+        let conversion = if self.verbal_bool { VERBAL_BOOL_COMPU_METHOD } else { "NO_COMPU_METHOD" };
         write!(
             writer,
             r#"
-/begin CHARACTERISTIC {} "{}" {} 0x{:X} {} 0 NO_COMPU_METHOD {} {}"#,
-            self.name, self.comment, characteristic_type, a2l_addr, datatype, self.min, self.max,
+/begin CHARACTERISTIC {} "{}" {} 0x{:X} {} 0 {} {} {}"#,
+            self.name, self.comment, characteristic_type, a2l_addr, record_layout, conversion, min, max,
         )?;
 
         if self.x_dim > 1 || self.y_dim > 1 {
-            let mut axis_par: (usize, usize, usize);
+            // First axis is the x axis, which may refer to a shared axis
+            let x_dim = if self.x_dim > 1 { self.x_dim } else { self.y_dim };
+            let x_input = self.x_axis_input.as_deref().unwrap_or("NO_INPUT_QUANTITY");
+            // An unknown shared axis is only possible with registry axis check warn only, fall back to a fixed axis
+            let axis = self.axis_pts_ref.as_ref().and_then(|r| writer.registry.find_characteristic(r)).filter(|a| a.is_axis);
+            if let (Some(axis_pts_ref), Some(axis)) = (&self.axis_pts_ref, axis) {
+                write!(
+                    writer,
+                    " /begin AXIS_DESCR COM_AXIS {} NO_COMPU_METHOD {} {} {} AXIS_PTS_REF {} /end AXIS_DESCR",
+                    x_input, x_dim, axis.min, axis.max, axis_pts_ref
+                )?;
+            } else {
+                write!(
+                    writer,
+                    r#" /begin AXIS_DESCR FIX_AXIS {} NO_COMPU_METHOD  {} 0 {} FIX_AXIS_PAR_DIST 0 1 {} /end AXIS_DESCR"#,
+                    x_input,
+                    x_dim,
+                    x_dim - 1,
+                    x_dim
+                )?;
+            }
             if self.x_dim > 1 && self.y_dim > 1 {
-                axis_par = (self.x_dim, self.x_dim - 1, self.x_dim);
                 write!(
                     writer,
                     r#" /begin AXIS_DESCR FIX_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD  {} 0 {} FIX_AXIS_PAR_DIST 0 1 {} /end AXIS_DESCR"#,
-                    axis_par.0, axis_par.1, axis_par.2
+                    self.y_dim,
+                    self.y_dim - 1,
+                    self.y_dim
                 )?;
-                axis_par = (self.y_dim, self.y_dim - 1, self.y_dim);
-            } else if self.x_dim > 1 {
-                axis_par = (self.x_dim, self.x_dim - 1, self.x_dim);
-            } else {
-                axis_par = (self.y_dim, self.y_dim - 1, self.y_dim);
             }
-            write!(
-                writer,
-                r#" /begin AXIS_DESCR FIX_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD  {} 0 {} FIX_AXIS_PAR_DIST 0 1 {} /end AXIS_DESCR"#,
-                axis_par.0, axis_par.1, axis_par.2
-            )?;
         }
 
         if !self.unit.is_empty() {
@@ -268,6 +391,12 @@ impl GenerateA2l for RegistryCharacteristic {
             write!(writer, " ECU_ADDRESS_EXTENSION {}", a2l_ext)?;
         }
 
+        self.write_a2l_display(writer)?;
+
+        for annotation in &self.annotations {
+            write!(writer, "\n{annotation}\n")?;
+        }
+
         if let Some(event) = self.event {
             write!(
                 writer,
@@ -276,11 +405,40 @@ impl GenerateA2l for RegistryCharacteristic {
             )?;
         }
 
+        self.write_a2l_ifdata(writer)?;
+
         write!(writer, " /end CHARACTERISTIC")?;
         Ok(())
     }
 }
 
+impl RegistryCharacteristic {
+    // Optional display format, step size, extended limits and display name
+    fn write_a2l_display(&self, writer: &mut A2lWriter) -> std::io::Result<()> {
+        if let Some(format) = self.format {
+            write!(writer, r#" FORMAT "{}""#, format)?;
+        }
+        if let Some(step) = self.step {
+            write!(writer, " STEP_SIZE {}", step)?;
+        }
+        if let Some((min, max)) = self.extended_limits {
+            write!(writer, " EXTENDED_LIMITS {} {}", min, max)?;
+        }
+        if let Some(display) = self.display {
+            write!(writer, " DISPLAY_IDENTIFIER {}", display)?;
+        }
+        Ok(())
+    }
+
+    // Vendor specific IF_DATA
+    fn write_a2l_ifdata(&self, writer: &mut A2lWriter) -> std::io::Result<()> {
+        for ifdata in &self.ifdata {
+            write!(writer, "\n{ifdata}")?;
+        }
+        Ok(())
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 
 pub struct A2lWriter<'a> {
@@ -303,12 +461,14 @@ impl<'a> A2lWriter<'a> {
     }
 
     fn write_a2l_head(&mut self, project_name: &str, module_name: &str) -> std::io::Result<()> {
+        // The A2L version is the EPK, to check that A2L and application match
+        let version = self.registry.mod_par.epk.map_or(DEFAULT_A2L_VERSION.to_string(), a2l_escape);
         write!(
             self,
             r#"
     ASAP2_VERSION 1 71 /* written by xcp-lite registry */
     /begin PROJECT {project_name} ""
-    /begin HEADER "" VERSION "1.0" /end HEADER
+    /begin HEADER "" VERSION "{version}" /end HEADER
     
     /begin MODULE {module_name} ""
     
@@ -332,9 +492,19 @@ impl<'a> A2lWriter<'a> {
             /begin RECORD_LAYOUT U32 FNC_VALUES 1 ULONG ROW_DIR DIRECT /end RECORD_LAYOUT
             /begin RECORD_LAYOUT S32 FNC_VALUES 1 SLONG ROW_DIR DIRECT /end RECORD_LAYOUT
             /begin RECORD_LAYOUT U64 FNC_VALUES 1 A_UINT64 ROW_DIR DIRECT /end RECORD_LAYOUT
-            /begin RECORD_LAYOUT S64 FNC_VALUES 1 A_UINT64 ROW_DIR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT S64 FNC_VALUES 1 A_INT64 ROW_DIR DIRECT /end RECORD_LAYOUT
             /begin RECORD_LAYOUT F32 FNC_VALUES 1 FLOAT32_IEEE ROW_DIR DIRECT /end RECORD_LAYOUT
             /begin RECORD_LAYOUT F64 FNC_VALUES 1 FLOAT64_IEEE ROW_DIR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_U8 AXIS_PTS_X 1 UBYTE INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_S8 AXIS_PTS_X 1 SBYTE INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_U16 AXIS_PTS_X 1 UWORD INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_S16 AXIS_PTS_X 1 SWORD INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_U32 AXIS_PTS_X 1 ULONG INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_S32 AXIS_PTS_X 1 SLONG INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_U64 AXIS_PTS_X 1 A_UINT64 INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_S64 AXIS_PTS_X 1 A_INT64 INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_F32 AXIS_PTS_X 1 FLOAT32_IEEE INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /begin RECORD_LAYOUT A_F64 AXIS_PTS_X 1 FLOAT64_IEEE INDEX_INCR DIRECT /end RECORD_LAYOUT
         
 /*
 
@@ -385,16 +555,23 @@ impl<'a> A2lWriter<'a> {
         mod_par.write_a2l(self)?;
         memory_segments.write_a2l(self)?;
 
+        // System constants
+        for (name, value) in &self.registry.system_constants {
+            write!(self, "\n\t\t\tSYSTEM_CONSTANT \"{}\" \"{}\"", a2l_escape(name), a2l_escape(value))?;
+        }
+
         writeln!(self, "\n\t\t/end MOD_PAR")
     }
 
+    #[allow(clippy::similar_names)]
     fn write_a2l_if_data(&mut self) -> std::io::Result<()> {
+        let (max_cto, max_dto) = self.registry.max_cto_dto.unwrap_or((252, 1468));
         write!(
             self,
             r#"
         /begin IF_DATA XCP
             /begin PROTOCOL_LAYER
-            0x104 1000 2000 0 0 0 0 0 252 1468 BYTE_ORDER_MSB_LAST ADDRESS_GRANULARITY_BYTE
+            0x104 1000 2000 0 0 0 0 0 {max_cto} {max_dto} BYTE_ORDER_MSB_LAST ADDRESS_GRANULARITY_BYTE
             OPTIONAL_CMD GET_COMM_MODE_INFO
             OPTIONAL_CMD GET_ID
             OPTIONAL_CMD SET_MTA
@@ -450,6 +627,11 @@ impl<'a> A2lWriter<'a> {
         }
 
         write!(self, "\n\t\t/end IF_DATA\n\n")?;
+
+        // Vendor specific IF_DATA
+        for ifdata in &self.registry.module_ifdata {
+            writeln!(self, "{ifdata}\n")?;
+        }
         Ok(())
     }
 
@@ -487,6 +669,36 @@ impl<'a> A2lWriter<'a> {
     }
 
     fn write_a2l_characteristics(&mut self) -> std::io::Result<()> {
+        // Verbal conversion table shared by all bool calibration parameters
+        if self.registry.characteristic_list.iter().any(|c| c.verbal_bool) {
+            writeln!(
+                self,
+                r#"
+/begin COMPU_METHOD {VERBAL_BOOL_COMPU_METHOD} "" TAB_VERB "%.0" "" COMPU_TAB_REF {VERBAL_BOOL_COMPU_VTAB} /end COMPU_METHOD
+/begin COMPU_VTAB {VERBAL_BOOL_COMPU_VTAB} "" TAB_VERB 2 0 "false" 1 "true" /end COMPU_VTAB"#
+            )?;
+        }
+
+        // Record layouts of column major maps, for each data type used
+        if !self.registry.row_major {
+            let datatypes: std::collections::BTreeSet<(&str, &str)> = self
+                .registry
+                .characteristic_list
+                .iter()
+                .filter(|c| c.x_dim > 1 && c.y_dim > 1)
+                .map(|c| (c.datatype.get_deposit_str(), c.datatype.get_type_str()))
+                .collect();
+            for (deposit, datatype) in datatypes {
+                writeln!(
+                    self,
+                    "/begin RECORD_LAYOUT {deposit}{COLUMN_DIR_RECORD_LAYOUT_SUFFIX} FNC_VALUES 1 {datatype} COLUMN_DIR DIRECT /end RECORD_LAYOUT"
+                )?;
+            }
+        }
+
+        // EPK
+        self.registry.mod_par.write_a2l_characteristic(self)?;
+
         // Characteristics not in a in calibration segment
         for c in self.registry.characteristic_list.iter() {
             if c.calseg_name.is_none() {
@@ -516,6 +728,33 @@ impl<'a> A2lWriter<'a> {
             writeln!(self, "/end REF_CHARACTERISTIC /end GROUP\n")?;
         }
 
+        self.write_a2l_functions()
+    }
+
+    // ECU functions with the calibration parameters they define and use
+    // Functions and calibration parameters are sorted by name, the A2L does not depend on the registration order
+    fn write_a2l_functions(&mut self) -> std::io::Result<()> {
+        let mut functions: std::collections::BTreeMap<&str, (Vec<&str>, Vec<&str>)> = std::collections::BTreeMap::new();
+        for c in self.registry.characteristic_list.iter() {
+            if let Some(function) = c.get_function() {
+                functions.entry(function).or_default().0.push(c.get_name());
+            }
+            for function in c.get_ref_functions() {
+                functions.entry(function).or_default().1.push(c.get_name());
+            }
+        }
+        for (function, (mut def, mut refs)) in functions {
+            def.sort_unstable();
+            refs.sort_unstable();
+            write!(self, "\n/begin FUNCTION {} \"\"", function)?;
+            if !def.is_empty() {
+                write!(self, " /begin DEF_CHARACTERISTIC {} /end DEF_CHARACTERISTIC", def.join(" "))?;
+            }
+            if !refs.is_empty() {
+                write!(self, " /begin REF_CHARACTERISTIC {} /end REF_CHARACTERISTIC", refs.join(" "))?;
+            }
+            writeln!(self, " /end FUNCTION")?;
+        }
         Ok(())
     }
 
@@ -0,0 +1,457 @@
+//-----------------------------------------------------------------------------
+// Sub Module sqlite
+// Export and import super::registry to a SQLite database for external tools
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::path::Path;
+
+use rusqlite::{params, types::Type, Connection, OptionalExtension};
+
+use super::*;
+use crate::Xcp;
+
+// Database schema
+// typedefs and typedef_fields are part of the schema for external tools, the registry has flat instances only, so they stay empty
+const SCHEMA: &str = r"
+CREATE TABLE module (name TEXT NOT NULL, epk TEXT, epk_addr INTEGER NOT NULL, protocol TEXT, addr TEXT, port INTEGER);
+CREATE TABLE instances (
+    id INTEGER PRIMARY KEY, name TEXT NOT NULL, object_type TEXT NOT NULL, value_type TEXT NOT NULL, x_dim INTEGER NOT NULL, y_dim INTEGER NOT NULL,
+    min REAL, max REAL, unit TEXT NOT NULL, comment TEXT NOT NULL, addr_offset INTEGER NOT NULL, addr_ext INTEGER NOT NULL, event_id INTEGER, calseg_name TEXT,
+    factor REAL, offset REAL, axis_pts_ref TEXT, x_axis_input TEXT, annotation TEXT, idl_hash INTEGER,
+    step REAL, format TEXT, ext_min REAL, ext_max REAL, display TEXT, alias_of TEXT, verbal_bool INTEGER NOT NULL, phys_type TEXT, monotone INTEGER NOT NULL, function TEXT, slew_rate REAL
+);
+CREATE TABLE annotations (instance_id INTEGER NOT NULL, label TEXT NOT NULL, origin TEXT NOT NULL, text TEXT NOT NULL);
+CREATE TABLE ifdata (instance_id INTEGER, text TEXT NOT NULL);
+CREATE TABLE ref_functions (instance_id INTEGER NOT NULL, name TEXT NOT NULL);
+CREATE TABLE typedefs (id INTEGER PRIMARY KEY, name TEXT NOT NULL, size INTEGER NOT NULL);
+CREATE TABLE typedef_fields (typedef_id INTEGER NOT NULL, name TEXT NOT NULL, value_type TEXT NOT NULL, offset INTEGER NOT NULL);
+CREATE TABLE events (id INTEGER PRIMARY KEY, name TEXT NOT NULL, period_us REAL NOT NULL, instance_index INTEGER NOT NULL, instance_label TEXT, consistency TEXT NOT NULL, priority INTEGER NOT NULL);
+CREATE TABLE calseg_list (id INTEGER PRIMARY KEY, name TEXT NOT NULL, size INTEGER NOT NULL);
+";
+
+const OBJECT_TYPE_MEASUREMENT: &str = "MEASUREMENT";
+const OBJECT_TYPE_CHARACTERISTIC: &str = "CHARACTERISTIC";
+const OBJECT_TYPE_AXIS: &str = "AXIS";
+
+// The registry holds static strings, strings of an imported registry live until the end of the process
+fn leak(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
+fn conversion_error(column: usize, msg: String) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, Type::Text, msg.into())
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn period_us_to_ns(period_us: f64) -> u32 {
+    (period_us * 1000.0).round() as u32
+}
+
+fn consistency_from_str(s: &str) -> Option<XcpEventConsistency> {
+    [XcpEventConsistency::Daq, XcpEventConsistency::Event, XcpEventConsistency::Odt, XcpEventConsistency::None]
+        .into_iter()
+        .find(|c| c.to_a2l_str() == s)
+}
+
+impl Registry {
+    /// Export the registry to a SQLite database file for external tools
+    /// An existing file is replaced
+    /// Call after the registry has been finalized (A2L written), to get the final event channels and a sorted instance list
+    #[allow(clippy::too_many_lines)]
+    pub fn export_sqlite(&self, path: &Path) -> Result<(), RegistryError> {
+        info!("Export registry to SQLite database {}", path.display());
+        if let Err(e) = std::fs::remove_file(path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+
+        let mut conn = Connection::open(path)?;
+        let tx = conn.transaction()?;
+        tx.execute_batch(SCHEMA)?;
+
+        // Module
+        let (protocol, addr, port) = match &self.tl_params {
+            Some(tl) => (Some(tl.protocol_name), Some(tl.addr.to_string()), Some(tl.port)),
+            None => (None, None, None),
+        };
+        tx.execute(
+            "INSERT INTO module VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![self.name.unwrap_or_default(), self.mod_par.epk, self.mod_par.epk_addr, protocol, addr, port],
+        )?;
+        Self::export_ifdata(&tx, None, &self.module_ifdata)?;
+
+        // Events
+        for e in self.event_list.iter() {
+            tx.execute(
+                "INSERT INTO events VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    e.xcp_event.get_channel(),
+                    e.name,
+                    e.cycle_time_ns as f64 / 1000.0,
+                    e.xcp_event.get_index(),
+                    e.xcp_event.get_instance_label(),
+                    e.consistency.to_a2l_str(),
+                    e.priority.to_a2l_priority()
+                ],
+            )?;
+        }
+
+        // Calibration segments
+        for s in self.cal_seg_list.iter() {
+            tx.execute("INSERT INTO calseg_list VALUES (?1, ?2, ?3)", params![s.index, s.name, s.size])?;
+        }
+
+        // Instances, measurement signals first, then calibration parameters
+        let mut id: i64 = 0;
+        for m in self.measurement_list.iter() {
+            id += 1;
+            let (addr_ext, addr_offset) = if m.addr == 0 {
+                (Xcp::XCP_ADDR_EXT_DYN, m.addr_offset as i64)
+            } else {
+                (Xcp::XCP_ADDR_EXT_ABS, m.addr as i64)
+            };
+            // Explicit limits of the physical value, or the value range of the type
+            let (min, max) = if m.datatype == RegistryDataType::Blob {
+                (None, None)
+            } else {
+                let (min, max) = m.limits.unwrap_or((m.datatype.get_min(), m.datatype.get_max()));
+                (Some(min), Some(max))
+            };
+            tx.execute(
+                "INSERT INTO instances VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, NULL, ?14, ?15, NULL, NULL, ?16, ?17, NULL, ?18, ?19, ?20, ?21, NULL, 0, ?22, 0, NULL, NULL)",
+                params![
+                    id,
+                    m.name,
+                    OBJECT_TYPE_MEASUREMENT,
+                    m.datatype.get_type_str(),
+                    m.x_dim,
+                    m.y_dim,
+                    min,
+                    max,
+                    m.unit,
+                    m.comment,
+                    addr_offset,
+                    addr_ext,
+                    m.xcp_event.get_channel(),
+                    m.factor,
+                    m.offset,
+                    m.annotation,
+                    m.idl_hash,
+                    m.format,
+                    m.extended_limits.map(|l| l.0),
+                    m.extended_limits.map(|l| l.1),
+                    m.display,
+                    m.phys_type.map(RegistryDataType::get_type_str)
+                ],
+            )?;
+            Self::export_annotations(&tx, id, &m.annotations)?;
+            Self::export_ifdata(&tx, Some(id), &m.ifdata)?;
+        }
+        for c in self.characteristic_list.iter() {
+            id += 1;
+            let addr_ext = if c.calseg_name.is_some() { Xcp::XCP_ADDR_EXT_APP } else { Xcp::XCP_ADDR_EXT_ABS };
+            tx.execute(
+                "INSERT INTO instances VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, NULL, NULL, ?15, ?16, NULL, NULL, ?17, ?18, ?19, ?20, ?21, ?22, ?23, NULL, ?24, ?25, ?26)",
+                params![
+                    id,
+                    c.name,
+                    if c.is_axis { OBJECT_TYPE_AXIS } else { OBJECT_TYPE_CHARACTERISTIC },
+                    c.datatype.get_type_str(),
+                    c.x_dim,
+                    c.y_dim,
+                    c.min,
+                    c.max,
+                    c.unit,
+                    c.comment,
+                    c.addr_offset as i64,
+                    addr_ext,
+                    c.event.map(XcpEvent::get_channel),
+                    c.calseg_name,
+                    c.axis_pts_ref,
+                    c.x_axis_input,
+                    c.step,
+                    c.format,
+                    c.extended_limits.map(|l| l.0),
+                    c.extended_limits.map(|l| l.1),
+                    c.display,
+                    c.alias_of,
+                    c.verbal_bool,
+                    c.monotone_increasing,
+                    c.function,
+                    c.slew_rate
+                ],
+            )?;
+            Self::export_annotations(&tx, id, &c.annotations)?;
+            Self::export_ifdata(&tx, Some(id), &c.ifdata)?;
+            for function in &c.ref_functions {
+                tx.execute("INSERT INTO ref_functions VALUES (?1, ?2)", params![id, function])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn export_annotations(tx: &rusqlite::Transaction, id: i64, annotations: &[RegistryAnnotation]) -> Result<(), RegistryError> {
+        for a in annotations {
+            tx.execute(
+                "INSERT INTO annotations VALUES (?1, ?2, ?3, ?4)",
+                params![id, a.label, a.origin, a.text.join("\n")],
+            )?;
+        }
+        Ok(())
+    }
+
+    // IF_DATA blocks of an instance, or of the module if id is None
+    fn export_ifdata(tx: &rusqlite::Transaction, id: Option<i64>, ifdata: &[String]) -> Result<(), RegistryError> {
+        for text in ifdata {
+            tx.execute("INSERT INTO ifdata VALUES (?1, ?2)", params![id, text])?;
+        }
+        Ok(())
+    }
+
+    /// Reconstruct a registry from a SQLite database file created with export_sqlite
+    /// Event channels are the final channels of the exported registry, they are not remapped
+    /// Strings of the imported registry are leaked, the registry is intended for tools, which import once
+    #[allow(clippy::too_many_lines, clippy::cast_sign_loss)]
+    pub fn import_sqlite(path: &Path) -> Result<Registry, RegistryError> {
+        info!("Import registry from SQLite database {}", path.display());
+        let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut registry = Registry::new();
+
+        // Module
+        let module = conn
+            .query_row("SELECT name, epk, epk_addr, protocol, addr, port FROM module", [], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, u32>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<u16>>(5)?,
+                ))
+            })
+            .optional()?;
+        if let Some((name, epk, epk_addr, protocol, addr, port)) = module {
+            registry.name = Some(leak(name));
+            registry.mod_par.epk = epk.map(leak);
+            registry.mod_par.epk_addr = epk_addr;
+            if let (Some(protocol), Some(addr), Some(port)) = (protocol, addr, port) {
+                let addr = addr.parse().map_err(|e| conversion_error(4, format!("{}", e)))?;
+                registry.tl_params = Some(RegistryXcpTransportLayer {
+                    protocol_name: leak(protocol),
+                    addr,
+                    port,
+                });
+            }
+        }
+
+        // Events
+        let mut stmt = conn.prepare("SELECT id, name, period_us, instance_index, instance_label, consistency, priority FROM events ORDER BY rowid")?;
+        let events = stmt.query_map([], |row| {
+            let channel: u16 = row.get(0)?;
+            let index: u16 = row.get(3)?;
+            let label = row.get::<_, Option<String>>(4)?.map(leak);
+            let xcp_event = XcpEvent::new_unmapped(channel, index, label);
+            let consistency: String = row.get(5)?;
+            Ok(RegistryEvent {
+                name: leak(row.get(1)?),
+                xcp_event,
+                cycle_time_ns: period_us_to_ns(row.get(2)?),
+                consistency: consistency_from_str(&consistency).ok_or_else(|| conversion_error(5, consistency))?,
+                priority: if row.get::<_, u8>(6)? == 0 { XcpEventPriority::Normal } else { XcpEventPriority::High },
+            })
+        })?;
+        for e in events {
+            registry.event_list.push(e?);
+        }
+
+        // Calibration segments
+        let mut stmt = conn.prepare("SELECT id, name, size FROM calseg_list ORDER BY rowid")?;
+        let calsegs = stmt.query_map([], |row| Ok((row.get::<_, u16>(0)?, row.get::<_, String>(1)?, row.get::<_, u32>(2)?)))?;
+        for s in calsegs {
+            let (index, name, size) = s?;
+            let (addr_ext, addr) = Xcp::get_calseg_ext_addr_base(index);
+            registry.cal_seg_list.push(RegistryCalSeg::new(leak(name), index, addr, addr_ext, size));
+        }
+
+        // Annotations
+        let mut annotations: Vec<(i64, RegistryAnnotation)> = Vec::new();
+        let mut stmt = conn.prepare("SELECT instance_id, label, origin, text FROM annotations ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| {
+            let text: String = row.get(3)?;
+            Ok((row.get(0)?, RegistryAnnotation::new(row.get::<_, String>(1)?, row.get::<_, String>(2)?, [text])))
+        })?;
+        for a in rows {
+            annotations.push(a?);
+        }
+        let get_annotations = |id: i64| annotations.iter().filter(|(i, _)| *i == id).map(|(_, a)| a.clone()).collect::<Vec<_>>();
+
+        // IF_DATA blocks, module level blocks have no instance id
+        let mut ifdata: Vec<(Option<i64>, String)> = Vec::new();
+        let mut stmt = conn.prepare("SELECT instance_id, text FROM ifdata ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for i in rows {
+            ifdata.push(i?);
+        }
+        let get_ifdata = |id: Option<i64>| ifdata.iter().filter(|(i, _)| *i == id).map(|(_, text)| text.clone()).collect::<Vec<_>>();
+        for text in get_ifdata(None) {
+            registry.add_module_ifdata(text);
+        }
+
+        // ECU functions which use a calibration parameter
+        let mut ref_functions: Vec<(i64, String)> = Vec::new();
+        let mut stmt = conn.prepare("SELECT instance_id, name FROM ref_functions ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for f in rows {
+            ref_functions.push(f?);
+        }
+
+        // Instances
+        let mut stmt = conn.prepare(
+            "SELECT id, name, object_type, value_type, x_dim, y_dim, min, max, unit, comment, addr_offset, addr_ext, event_id, calseg_name,
+                factor, offset, axis_pts_ref, x_axis_input, annotation, idl_hash, step, format, ext_min, ext_max, display, alias_of, verbal_bool, phys_type, monotone, function, slew_rate FROM instances ORDER BY id",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let name: String = row.get(1)?;
+            let object_type: String = row.get(2)?;
+            let value_type: String = row.get(3)?;
+            let datatype = RegistryDataType::from_type_str(&value_type).ok_or_else(|| conversion_error(3, value_type))?;
+            let addr_offset: i64 = row.get(10)?;
+            let addr_ext: u8 = row.get(11)?;
+            let event_id: Option<u16> = row.get(12)?;
+            // Events of measurement signals and calibration parameters are the events registered in the database
+            let xcp_event = event_id.map(|channel| {
+                registry
+                    .event_list
+                    .iter()
+                    .find(|e| e.xcp_event.get_channel() == channel)
+                    .map_or(XcpEvent::new_unmapped(channel, 0, None), |e| e.xcp_event)
+            });
+
+            let format = row.get::<_, Option<String>>(21)?.map(leak);
+            let extended_limits = match (row.get::<_, Option<f64>>(22)?, row.get::<_, Option<f64>>(23)?) {
+                (Some(min), Some(max)) => Some((min, max)),
+                _ => None,
+            };
+            let display = row.get::<_, Option<String>>(24)?.map(leak);
+
+            match object_type.as_str() {
+                OBJECT_TYPE_MEASUREMENT => {
+                    let (addr, addr_offset) = if addr_ext == Xcp::XCP_ADDR_EXT_DYN {
+                        (0, i16::try_from(addr_offset).map_err(|e| conversion_error(10, format!("{}", e)))?)
+                    } else {
+                        (addr_offset as u64, 0)
+                    };
+                    let mut m = RegistryMeasurement::new(
+                        leak(name),
+                        datatype,
+                        row.get(4)?,
+                        row.get(5)?,
+                        xcp_event.ok_or_else(|| conversion_error(12, "measurement without event".into()))?,
+                        addr_offset,
+                        addr,
+                        row.get(14)?,
+                        row.get(15)?,
+                        leak(row.get(9)?),
+                        leak(row.get(8)?),
+                        row.get(18)?,
+                    );
+                    if let Some(idl_hash) = row.get(19)? {
+                        m.set_idl_hash(idl_hash);
+                    }
+                    if let Some(format) = format {
+                        m.set_format(format);
+                    }
+                    // Limits which are not the value range of the type are explicit limits
+                    if let (Some(min), Some(max)) = (row.get::<_, Option<f64>>(6)?, row.get::<_, Option<f64>>(7)?) {
+                        if (min, max) != (datatype.get_min(), datatype.get_max()) {
+                            m.set_limits(min, max);
+                        }
+                    }
+                    if let Some((min, max)) = extended_limits {
+                        m.set_extended_limits(min, max);
+                    }
+                    if let Some(display) = display {
+                        m.set_display(display);
+                    }
+                    if let Some(phys_type) = row.get::<_, Option<String>>(27)? {
+                        m.set_phys_type(RegistryDataType::from_type_str(&phys_type).ok_or_else(|| conversion_error(27, phys_type))?);
+                    }
+                    m.annotations = get_annotations(id);
+                    for text in get_ifdata(Some(id)) {
+                        m.add_ifdata(text);
+                    }
+                    registry.measurement_list.push(m);
+                }
+                OBJECT_TYPE_CHARACTERISTIC | OBJECT_TYPE_AXIS => {
+                    let mut c = RegistryCharacteristic::new(
+                        row.get::<_, Option<String>>(13)?.map(leak),
+                        name,
+                        datatype,
+                        leak(row.get(9)?),
+                        row.get(6)?,
+                        row.get(7)?,
+                        leak(row.get(8)?),
+                        row.get(4)?,
+                        row.get(5)?,
+                        addr_offset as u64,
+                    );
+                    if object_type == OBJECT_TYPE_AXIS {
+                        c.set_axis();
+                    }
+                    if row.get(26)? {
+                        c.set_verbal_bool();
+                    }
+                    if row.get(28)? {
+                        c.set_monotone_increasing();
+                    }
+                    if let Some(event) = xcp_event {
+                        c.set_event(event);
+                    }
+                    if let Some(axis_pts_ref) = row.get::<_, Option<String>>(16)? {
+                        c.set_axis_pts_ref(axis_pts_ref);
+                    }
+                    if let Some(x_axis_input) = row.get::<_, Option<String>>(17)? {
+                        c.set_x_axis_input(x_axis_input);
+                    }
+                    if let Some(step) = row.get(20)? {
+                        c.set_step(step);
+                    }
+                    if let Some(format) = format {
+                        c.set_format(format);
+                    }
+                    if let Some((min, max)) = extended_limits {
+                        c.set_extended_limits(min, max);
+                    }
+                    if let Some(display) = display {
+                        c.set_display(display);
+                    }
+                    c.alias_of = row.get::<_, Option<String>>(25)?.map(Cow::Owned);
+                    if let Some(function) = row.get::<_, Option<String>>(29)? {
+                        c.set_function(function);
+                    }
+                    for (_, function) in ref_functions.iter().filter(|(i, _)| *i == id) {
+                        c.add_ref_function(function.clone());
+                    }
+                    if let Some(slew_rate) = row.get(30)? {
+                        c.set_slew_rate(slew_rate);
+                    }
+                    c.annotations = get_annotations(id);
+                    for text in get_ifdata(Some(id)) {
+                        c.add_ifdata(text);
+                    }
+                    registry.characteristic_list.push(c);
+                }
+                _ => return Err(conversion_error(2, object_type).into()),
+            }
+        }
+
+        Ok(registry)
+    }
+}
@@ -5,9 +5,10 @@ use parking_lot::Mutex;
 use std::{
     net::Ipv4Addr,
     sync::{
-        atomic::{AtomicU8, Ordering},
-        Arc,
+        atomic::{AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        Arc, Once,
     },
+    time::{Duration, Instant},
 };
 
 // Using sync version of OnceCell from once_cell crate for the static event remapping array
@@ -19,6 +20,7 @@ use lazy_static::lazy_static;
 // Using bitflags crate for the XCP session status flags
 use bitflags::bitflags;
 
+use crate::metrics::AlarmHandler;
 use crate::reg;
 use reg::*;
 
@@ -30,8 +32,17 @@ pub mod daq;
 
 // Submodule cal
 pub mod cal;
+use cal::cal_audit::{CalAudit, CalAuditSink};
+use cal::cal_guard::GuardRailsMode;
 use cal::cal_seg::{CalPageTrait, CalSeg};
-use cal::CalSegList;
+use cal::{CalSegList, CalWriteStatus};
+
+// Submodule instance
+pub mod instance;
+
+// Submodule clock
+pub mod clock;
+use clock::{XcpClockCorrelation, XcpClockInfo, XCP_CLOCK_INFO};
 
 // Use XCPlite xcplib as XCP server
 // Enable XCPlite FFI bindings in xcplib.rs
@@ -42,6 +53,12 @@ mod xcplib;
 #[cfg(feature = "xcp_server")]
 mod xcplib_rs;
 
+// XCP on Ethernet server on the tokio runtime, using the xcplib protocol layer and transmit queue
+#[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+mod async_server;
+#[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+pub use async_server::XcpLoopback;
+
 //----------------------------------------------------------------------------------------------
 // XCP error
 
@@ -55,6 +72,18 @@ pub enum XcpError {
     #[error("xcplib error: `{0}` ")]
     XcpLib(&'static str),
 
+    #[error("registry error")]
+    Registry(#[from] RegistryError),
+
+    #[error("xcplib error: multiple XCP server instances are not supported")]
+    UnsupportedMultiInstance,
+
+    #[error("xcp error: not possible while an XCP client is connected")]
+    ClientConnected,
+
+    #[error("xcp error: calibration segment `{0}` not found")]
+    CalSegNotFound(String),
+
     #[error("unknown error")]
     Unknown,
 }
@@ -81,14 +110,65 @@ bitflags! {
 // The remapping cell is initialized when the registry is finalized and the A2L is written
 static XCP_EVENT_MAP: OnceCell<[u16; XcpEvent::XCP_MAX_EVENTS]> = OnceCell::new();
 
+/// Data consistency of an event, the A2L EVENT attribute CONSISTENCY
+/// Tells the XCP tool, which ODT entries of an event are captured as one consistent snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum XcpEventConsistency {
+    /// All DAQ lists of the event are consistent (default)
+    #[default]
+    Daq,
+    /// All ODT entries of the event are consistent, e.g. the fields of a struct measured via one event
+    Event,
+    /// Consistency only within one ODT
+    Odt,
+    /// No consistency
+    None,
+}
+
+impl XcpEventConsistency {
+    /// A2L keyword
+    pub fn to_a2l_str(self) -> &'static str {
+        match self {
+            XcpEventConsistency::Daq => "DAQ",
+            XcpEventConsistency::Event => "EVENT",
+            XcpEventConsistency::Odt => "ODT",
+            XcpEventConsistency::None => "NONE",
+        }
+    }
+}
+
+/// Transmit priority of an event, the A2L EVENT attribute PRIORITY
+/// Under transmit queue pressure, DaqEvent triggers with priority Normal are dropped first,
+/// to keep the queue space for the events with priority High
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum XcpEventPriority {
+    /// Queued, dropped first (default)
+    #[default]
+    Normal,
+    /// Queue space is reserved, transmitted without delay
+    High,
+}
+
+impl XcpEventPriority {
+    /// A2L EVENT priority, xcplib flushes the transmit buffer after each cycle of a DAQ list with priority > 0
+    pub fn to_a2l_priority(self) -> u8 {
+        match self {
+            XcpEventPriority::Normal => 0,
+            XcpEventPriority::High => 1,
+        }
+    }
+}
+
 /// Represents a measurement event  
 /// Glue needed for the macros
 /// Holds the raw u16 XCP event number used in the XCP protocol and in A2L IF_DATA to identify an event
 /// May have an index > 0 to express multiple events with the same name are instanciated in different thread local instances
 #[derive(Debug, Clone, Copy)]
 pub struct XcpEvent {
-    channel: u16, // Number used in A2L and XCP protocol
-    index: u16,   // Instance index, 0 if single instance
+    channel: u16,                 // Number used in A2L and XCP protocol
+    index: u16,                   // Instance index, 0 if single instance
+    label: Option<&'static str>, // Explicit instance label provided by the application, replaces the instance index in names
+    remap: bool,                  // Channel is remapped by the event map of the Xcp singleton, false for events of an XcpInstance
 }
 
 impl XcpEvent {
@@ -101,12 +181,36 @@ impl XcpEvent {
     pub const XCP_UNDEFINED_EVENT: XcpEvent = XcpEvent {
         channel: XcpEvent::XCP_UNDEFINED_EVENT_CHANNEL,
         index: 0,
+        label: None,
+        remap: true,
     };
 
     /// Create a new XCP event
     pub fn new(channel: u16, index: u16) -> XcpEvent {
         assert!((channel as usize) < XcpEvent::XCP_MAX_EVENTS, "Maximum number of events exceeded");
-        XcpEvent { channel, index }
+        XcpEvent {
+            channel,
+            index,
+            label: None,
+            remap: true,
+        }
+    }
+
+    /// Create a new multi instance XCP event with an explicit instance label
+    pub fn new_with_label(channel: u16, index: u16, label: &'static str) -> XcpEvent {
+        assert!(index > 0, "Instance label requires a multi instance event");
+        let mut event = XcpEvent::new(channel, index);
+        event.label = Some(label);
+        event
+    }
+
+    /// Create an XCP event with a final channel number, which is not remapped by the event map of the Xcp singleton
+    /// Used for events of a registry not owned by the Xcp singleton
+    pub(crate) fn new_unmapped(channel: u16, index: u16, label: Option<&'static str>) -> XcpEvent {
+        let mut event = XcpEvent::new(channel, index);
+        event.label = label;
+        event.remap = false;
+        event
     }
 
     /// Get the event name
@@ -117,10 +221,9 @@ impl XcpEvent {
     /// Get the event number as u16
     /// Event number is a unique number for each event
     pub fn get_channel(self) -> u16 {
-        if let Some(event_map) = XCP_EVENT_MAP.get() {
-            event_map[self.channel as usize]
-        } else {
-            self.channel
+        match XCP_EVENT_MAP.get() {
+            Some(event_map) if self.remap => event_map[self.channel as usize],
+            _ => self.channel,
         }
     }
 
@@ -131,6 +234,20 @@ impl XcpEvent {
         self.index
     }
 
+    /// Get the explicit instance label, if the application provided one
+    pub fn get_instance_label(self) -> Option<&'static str> {
+        self.label
+    }
+
+    /// Get the suffix attached to event and signal names of a multi instance event
+    /// This is the explicit instance label, if provided, otherwise the instance index, which depends on creation order
+    pub fn get_instance_suffix(self) -> std::borrow::Cow<'static, str> {
+        match self.label {
+            Some(label) => std::borrow::Cow::Borrowed(label),
+            None => std::borrow::Cow::Owned(self.index.to_string()),
+        }
+    }
+
     /// Get address extension and address for A2L generation for XCP_ADDR_EXT_DYN addressing mode
     /// Used by A2L writer
     pub fn get_dyn_ext_addr(self, offset: i16) -> (u8, u32) {
@@ -142,6 +259,7 @@ impl XcpEvent {
 
     /// Trigger a XCP event and provide a base pointer for relative addressing mode (XCP_ADDR_EXT_DYN)
     /// Address of the associated measurement variables must be relative to base
+    /// Use this for variables on stack or in a capture buffer, which have a different address on each trigger
     ///
     /// # Safety
     /// This is a C ffi call, which gets a pointer to a daq capture buffer
@@ -162,6 +280,7 @@ impl XcpEvent {
 
     /// Trigger a XCP event for absolute addressing DAQ lists (XCP_ADDR_EXT_ABS)
     /// Address of the associated measurement variables must be absolute (relative to ApplXcpGetBaseAddr)
+    /// Use this for variables with stable address, in static memory or leaked heap, no base pointer is needed
     ///
     /// # Safety
     /// This is a C ffi call, which gets a pointer to static memory segment
@@ -179,12 +298,16 @@ impl XcpEvent {
         }
     }
 
-    /// Trigger a XCP event for measurement objects in absolute addressing mode (XCP_ADDR_EXT_DYN)
+    /// Trigger a XCP event for measurement objects in absolute addressing mode (XCP_ADDR_EXT_ABS)
     /// Address of the associated measurement variable must be relative to module load addr
     /// In 64 applications, this offset might overflow in the A2L description - this is checked wenn generating A2L
+    /// Same as trigger, for measurement objects on heap registered with add_heap or daq_register_slice
     ///
     /// # Safety
-    /// This is a C ffi call, which gets a pointer to a daq capture buffer
+    /// This is a C ffi call without pointer arguments
+    /// The A2L addresses of absolute addressing mode are only valid for this build and this run of the application
+    /// Statics are relative to the module load address and may move with every build, heap addresses change with every run
+    /// The A2L file must be regenerated or uploaded, the EPK identifies the build
     #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn trigger_abs(self) {
         #[cfg(not(feature = "xcp_server"))]
@@ -212,6 +335,8 @@ struct XcpEventInfo {
     name: &'static str,
     event: XcpEvent,
     cycle_time_ns: u32, // 0 -sporadic or unknown
+    consistency: XcpEventConsistency,
+    priority: XcpEventPriority,
 }
 
 struct EventList(Vec<XcpEventInfo>);
@@ -234,14 +359,61 @@ impl EventList {
         None
     }
 
+    // Name of an event as in the A2L file, with instance suffix of a multi instance event
+    fn get_full_name(e: &XcpEventInfo) -> std::borrow::Cow<'static, str> {
+        if e.event.get_index() > 0 {
+            std::borrow::Cow::Owned(format!("{}_{}", e.name, e.event.get_instance_suffix()))
+        } else {
+            std::borrow::Cow::Borrowed(e.name)
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<XcpEvent> {
+        self.0.iter().find(|e| EventList::get_full_name(e) == name).map(|e| e.event)
+    }
+
+    fn get_events(&self) -> Vec<(String, XcpEvent)> {
+        self.0.iter().map(|e| (EventList::get_full_name(e).into_owned(), e.event)).collect()
+    }
+
+    // Instances with explicit label are sorted by label, to be independent from creation order
     fn sort_by_name_and_index(&mut self) {
-        self.0.sort_by(|a, b| if a.name == b.name { a.event.index.cmp(&b.event.index) } else { a.name.cmp(b.name) });
+        self.0.sort_by(|a, b| {
+            if a.name == b.name {
+                a.event.label.cmp(&b.event.label).then(a.event.index.cmp(&b.event.index))
+            } else {
+                a.name.cmp(b.name)
+            }
+        });
     }
 
-    fn register(&mut self) {
+    // Sort the event list and check for collisions of instance names
+    fn sort_and_check(&mut self) -> Result<(), RegistryError> {
         // Sort the event list by name and then instance index
         self.sort_by_name_and_index();
 
+        // Check for collisions of instance names
+        for (i, e) in self.0.iter().enumerate() {
+            if e.event.index > 0 && self.0[..i].iter().any(|e1| e1.name == e.name && e1.event.index > 0 && e1.event.get_instance_suffix() == e.event.get_instance_suffix()) {
+                let name = format!("{}_{}", e.name, e.event.get_instance_suffix());
+                log::error!("Duplicate event instance {}", name);
+                return Err(RegistryError::Duplicate(name.into()));
+            }
+        }
+        Ok(())
+    }
+
+    // Add all events to the registry
+    fn add_to_registry(&self, registry: &mut Registry) {
+        self.0.iter().for_each(|e| {
+            registry.add_event_ext(e.name, e.event, e.cycle_time_ns, e.consistency);
+            registry.set_event_priority(e.event, e.priority);
+        });
+    }
+
+    fn register(&mut self) -> Result<(), RegistryError> {
+        self.sort_and_check()?;
+
         // Remap the event numbers
         // Problem is, that the event numbers are not deterministic, they depend on order of creation
         // This is not a problem for the XCP client, but the A2L file might change unnessesarily on every start of the application
@@ -254,10 +426,8 @@ impl EventList {
 
         // Register all events
         let r = Xcp::get().get_registry();
-        {
-            let mut l = r.lock();
-            self.0.iter().for_each(|e| l.add_event(e.name, e.event, e.cycle_time_ns));
-        }
+        self.add_to_registry(&mut r.lock());
+        Ok(())
     }
 
     fn create_event_ext(&mut self, name: &'static str, indexed: bool, cycle_time_ns: u32) -> XcpEvent {
@@ -279,8 +449,57 @@ impl EventList {
         log::debug!("Create event {} channel={}, index={}", name, event.get_channel(), event.get_index());
 
         // Add XcpEventInfo to event list
-        self.0.push(XcpEventInfo { name, event, cycle_time_ns });
+        self.0.push(XcpEventInfo {
+            name,
+            event,
+            cycle_time_ns,
+            consistency: XcpEventConsistency::default(),
+            priority: XcpEventPriority::default(),
+        });
+
+        event
+    }
+
+    // Create an event of an XcpInstance, its channel is not remapped by the event map of the Xcp singleton
+    fn create_instance_local_event(&mut self, name: &'static str, indexed: bool, cycle_time_ns: u32) -> XcpEvent {
+        let event = self.create_event_ext(name, indexed, cycle_time_ns);
+        let event = XcpEvent::new_unmapped(event.channel, event.index, event.label);
+        self.0.last_mut().unwrap().event = event;
+        event
+    }
+
+    fn set_consistency(&mut self, event: XcpEvent, consistency: XcpEventConsistency) {
+        let e = self.0.iter_mut().find(|e| e.event == event).expect("Event not found");
+        e.consistency = consistency;
+    }
+
+    fn set_priority(&mut self, event: XcpEvent, priority: XcpEventPriority) {
+        let e = self.0.iter_mut().find(|e| e.event == event).expect("Event not found");
+        e.priority = priority;
+    }
 
+    fn get_priority(&self, event: XcpEvent) -> XcpEventPriority {
+        self.0.iter().find(|e| e.event == event).map_or(XcpEventPriority::default(), |e| e.priority)
+    }
+
+    // Get or create the auxiliary event "<name>_<suffix>" of a single instance event, e.g. "<name>_onchange"
+    fn get_or_create_aux_event(&mut self, parent: XcpEvent, suffix: &str) -> XcpEvent {
+        let parent_name = self.get_name(parent).expect("Event not found");
+        assert!(parent.get_index() == 0, "Auxiliary event {} requires a single instance event", suffix);
+        let name = format!("{}_{}", parent_name, suffix);
+        if let Some(e) = self.0.iter().find(|e| e.name == name) {
+            return e.event;
+        }
+        // The name lives as long as the event, events are never dropped
+        let name: &'static str = Box::leak(name.into_boxed_str());
+        self.create_event_ext(name, false, 0)
+    }
+
+    fn create_event_instance(&mut self, name: &'static str, label: &'static str) -> XcpEvent {
+        let event = self.create_event_ext(name, true, 0);
+        let event = XcpEvent::new_with_label(event.channel, event.index, label);
+        log::debug!("Event {} channel={} has instance label {}", name, event.get_channel(), label);
+        self.0.last_mut().unwrap().event = event;
         event
     }
 }
@@ -333,21 +552,87 @@ impl XcpTransportLayer {
     }
 }
 
+//------------------------------------------------------------------------------------------
+// XcpTransportOptions
+
+// Transport layer parameters of xcplib, must match xcptl_cfg.h
+pub(crate) const XCPTL_TRANSPORT_LAYER_HEADER_SIZE: usize = 4;
+pub(crate) const XCPTL_MAX_CTO_SIZE: u16 = 248;
+
+/// Transport layer options of the XCP on Ethernet server, see XcpBuilder::start_server_with_options
+/// 0 selects the default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct XcpTransportOptions {
+    /// MTU of the network path in bytes, determines the max DTO size, up to 9000 for jumbo frames with feature jumbo_frames
+    /// The default MTU is 1500, ignored if the max DTO size is set explicitly with XcpBuilder::set_max_cto_dto
+    pub mtu: u16,
+    /// Socket send buffer size in bytes, the default is the operating system default
+    pub send_buffer_size: u32,
+    /// Socket receive buffer size in bytes, the default is the operating system default
+    pub receive_buffer_size: u32,
+}
+
+impl XcpTransportOptions {
+    // Size of the IP, UDP and XCP transport layer headers not available for the DTO
+    const MTU_OVERHEAD: u16 = 20 + 8 + 8;
+
+    // Max CTO and DTO size for the MTU, the DTO size is a multiple of 4, the CTO size a multiple of 8 and not larger than the DTO size
+    #[allow(clippy::similar_names)]
+    fn max_cto_dto(&self) -> (u16, u16) {
+        let max_dto = self.mtu.saturating_sub(Self::MTU_OVERHEAD) & !0x03;
+        let max_cto = XCPTL_MAX_CTO_SIZE.min(max_dto & !0x07);
+        (max_cto, max_dto)
+    }
+}
+
+//------------------------------------------------------------------------------------------
+// TransportStats
+
+/// Statistics of the XCP transport layer since the server was started
+/// A transmitted packet is a UDP datagram or TCP segment, which may contain multiple XCP packets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransportStats {
+    /// Number of transmitted packets
+    pub tx_packets: u64,
+    /// Number of transmitted bytes, including the XCP transport layer headers
+    pub tx_bytes: u64,
+    /// Number of XCP packets lost, because the transmit queue was full
+    pub tx_queue_overruns: u64,
+    /// Number of received command packets
+    pub rx_packets: u64,
+}
+
 //------------------------------------------------------------------------------------------
 // XcpBuilder
 
 /// A builder to initialize the singleton instance of the XCP server
 #[derive(Debug)]
 pub struct XcpBuilder {
-    log_level: u8,      // log level for the server
-    name: &'static str, // Registry name, file name for the registry A2L generator
-    epk: &'static str,  // EPK string for A2L version check
+    log_level: u8,                   // log level for the server
+    name: &'static str,              // Registry name, file name for the registry A2L generator
+    epk: &'static str,               // EPK string for A2L version check
+    max_cto_dto: Option<(u16, u16)>, // Max CTO and DTO size, default is the xcplib maximum
+    a2l_strict: bool,                // Fail the A2L generation on errors of the A2L check
+    verbal_bool: bool,               // Show bool calibration parameters as "false" and "true"
+    type_limits: bool,               // Derive and clamp the limits of integer calibration parameters from their data type
+    default_limits: DefaultLimits,   // A2L limits of signals without limits and without conversion
+    guard_rails: GuardRailsMode,     // Checks of calibration writes of the XCP tool
 }
 
 impl XcpBuilder {
     /// Create a XcpBuilder
     pub fn new(name: &'static str) -> XcpBuilder {
-        XcpBuilder { log_level: 3, name, epk: "EPK" }
+        XcpBuilder {
+            log_level: 3,
+            name,
+            epk: "EPK",
+            max_cto_dto: None,
+            a2l_strict: false,
+            verbal_bool: false,
+            type_limits: true,
+            default_limits: DefaultLimits::default(),
+            guard_rails: GuardRailsMode::default(),
+        }
     }
 
     /// Set log level
@@ -364,12 +649,59 @@ impl XcpBuilder {
         self
     }
 
-    /// Start the XCP on Ethernet Server
-    pub fn start_server<A>(self, tl: XcpTransportLayer, addr: A, port: u16) -> Result<&'static Xcp, XcpError>
-    where
-        A: Into<Ipv4Addr>,
-    {
-        let ipv4_addr: Ipv4Addr = addr.into();
+    /// Set the max CTO and DTO size, to interoperate with transports or gateways which can not handle large frames
+    /// Limited by the xcplib compile time maximum, the CTO size must be a multiple of 8, the DTO size a multiple of 4 and not smaller than the CTO size
+    /// Invalid combinations are rejected when the server is started
+    #[must_use]
+    #[allow(clippy::similar_names)]
+    pub fn set_max_cto_dto(mut self, max_cto: u16, max_dto: u16) -> Self {
+        self.max_cto_dto = Some((max_cto, max_dto));
+        self
+    }
+
+    /// Fail the A2L generation, if the A2L check reports errors
+    /// Otherwise the findings of the A2L check are only logged
+    #[must_use]
+    pub fn set_a2l_strict(mut self, strict: bool) -> Self {
+        self.a2l_strict = strict;
+        self
+    }
+
+    /// Show all bool calibration parameters as "false" and "true" in the tool instead of 0 and 1
+    /// Single fields may override this with the type description attribute verbal_bool
+    #[must_use]
+    pub fn set_verbal_bool(mut self, enable: bool) -> Self {
+        self.verbal_bool = enable;
+        self
+    }
+
+    /// Derive the limits of integer calibration parameters without limits from their data type
+    /// Limits exceeding the value range of the data type are clamped with a warning
+    /// Enabled by default, disable to keep the limits of previously generated A2L files
+    #[must_use]
+    pub fn set_type_limits(mut self, enable: bool) -> Self {
+        self.type_limits = enable;
+        self
+    }
+
+    /// Set the A2L limits of measurements and calibration parameters without limits and without conversion
+    /// Default is DefaultLimits::TypeRange, DefaultLimits::Sentinel writes -3.4E38 and 3.4E38 for tools which treat the type range as a restriction
+    #[must_use]
+    pub fn set_default_limits(mut self, default_limits: DefaultLimits) -> Self {
+        self.default_limits = default_limits;
+        self
+    }
+
+    /// Set the checks of calibration writes of the XCP tool, default is GuardRailsMode::Monotonicity
+    #[must_use]
+    pub fn set_guard_rails(mut self, mode: GuardRailsMode) -> Self {
+        self.guard_rails = mode;
+        self
+    }
+
+    // Apply the builder parameters to the Xcp singleton
+    #[allow(clippy::similar_names)]
+    fn init(&self) -> Result<&'static Xcp, XcpError> {
         let xcp = &XCP_SINGLETON;
 
         // xcplib server log level parameter
@@ -379,22 +711,70 @@ impl XcpBuilder {
         // EPV parameter
         xcp.set_epk(self.epk);
 
+        // Calibration write checks
+        xcp.set_guard_rails(self.guard_rails);
+
         // Register name and epk
         {
             let mut r = xcp.registry.lock();
             r.set_name(self.name);
             r.set_epk(self.epk, Xcp::XCP_EPK_ADDR); // EPK
+            r.set_a2l_strict(self.a2l_strict);
+            r.set_verbal_bool(self.verbal_bool);
+            r.set_type_limits(self.type_limits);
+            r.set_default_limits(self.default_limits);
+        }
+
+        // Max CTO and DTO size, 0 selects the xcplib maximum
+        #[cfg(not(feature = "xcp_server"))]
+        {
+            let (max_cto, max_dto) = self.max_cto_dto.unwrap_or((0, 0));
+            // @@@@ Unsafe - C library call
+            if 0 == unsafe { xcplib::XcpTlSetMaxSizes(max_cto, max_dto) } {
+                return Err(XcpError::XcpLib("Error: XcpTlSetMaxSizes() failed, invalid max CTO/DTO size"));
+            }
+        }
+        if let Some((max_cto, max_dto)) = self.max_cto_dto {
+            xcp.registry.lock().set_max_cto_dto(max_cto, max_dto);
         }
 
+        Ok(xcp)
+    }
+
+    /// Start the XCP on Ethernet Server
+    pub fn start_server<A>(self, tl: XcpTransportLayer, addr: A, port: u16) -> Result<&'static Xcp, XcpError>
+    where
+        A: Into<Ipv4Addr>,
+    {
+        self.start_server_with_options(tl, addr, port, 0, XcpTransportOptions::default())
+    }
+
+    /// Start the XCP on Ethernet Server with transport layer options
+    /// queue_size is the transmit queue size in XCP packets, 0 or values larger than the xcplib maximum (OPTION_QUEUE_SIZE) select the maximum, larger values with a warning
+    /// The max DTO size is derived from the MTU in options, unless it is set explicitly with set_max_cto_dto, the A2L file contains the resulting max DTO size
+    #[allow(unused_variables)]
+    pub fn start_server_with_options<A>(mut self, tl: XcpTransportLayer, addr: A, port: u16, queue_size: u32, options: XcpTransportOptions) -> Result<&'static Xcp, XcpError>
+    where
+        A: Into<Ipv4Addr>,
+    {
+        let ipv4_addr: Ipv4Addr = addr.into();
+        if options.mtu != 0 && self.max_cto_dto.is_none() {
+            self.max_cto_dto = Some(options.max_cto_dto());
+        }
+        let xcp = self.init()?;
+
         // Initialize the XCP Server and ETH transport layer
         #[cfg(not(feature = "xcp_server"))]
         unsafe {
             let a: [u8; 4] = ipv4_addr.octets();
             // @@@@ Unsafe - C library call
+            xcplib::XcpEthTlSetOptions(queue_size, options.send_buffer_size, options.receive_buffer_size);
+            // @@@@ Unsafe - C library call
             if 0 == xcplib::XcpEthServerInit(&a as *const u8, port, (tl == XcpTransportLayer::Tcp) as u8) {
                 return Err(XcpError::XcpLib("Error: XcpEthServerInit() failed"));
             }
         }
+        xcp.check_transmit_queue_size(queue_size);
         #[cfg(feature = "xcp_server")]
         {
             if !xcplib_rs::server_init(ipv4_addr, port, tl) {
@@ -422,8 +802,32 @@ impl XcpBuilder {
             r.set_tl_params(tl.protocol_name(), ipv4_addr, port); // Transport layer parameters
         }
 
+        // Start the connection supervision
+        xcp.start_keepalive();
+
+        Ok(xcp)
+    }
+
+    /// Start the XCP on Ethernet Server as tasks on the current tokio runtime
+    /// See Xcp::start_async_server
+    #[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+    pub async fn start_async_server<A>(self, tl: XcpTransportLayer, addr: A, port: u16, queue_size: u32) -> Result<&'static Xcp, XcpError>
+    where
+        A: Into<Ipv4Addr>,
+    {
+        let xcp = self.init()?;
+        xcp.start_async_server(tl, addr, port, queue_size).await?;
         Ok(xcp)
     }
+
+    /// Start the XCP server with the in-process loopback transport as tasks on the current tokio runtime
+    /// See Xcp::start_loopback_server
+    #[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+    pub fn start_loopback_server(self, queue_size: u32) -> Result<(&'static Xcp, XcpLoopback), XcpError> {
+        let xcp = self.init()?;
+        let loopback = xcp.start_loopback_server(queue_size)?;
+        Ok((xcp, loopback))
+    }
 }
 
 //------------------------------------------------------------------------------------------
@@ -434,10 +838,24 @@ impl XcpBuilder {
 pub struct Xcp {
     ecu_cal_page: AtomicU8,
     xcp_cal_page: AtomicU8,
+    daq_start_count: AtomicU32,
     event_list: Arc<Mutex<EventList>>,
     registry: Arc<Mutex<Registry>>,
     calseg_list: Arc<Mutex<CalSegList>>,
-    epk: Mutex<&'static str>,
+    embedded_a2l: Mutex<Option<&'static str>>,
+    alarm_handler: Mutex<Option<Box<dyn AlarmHandler>>>,
+    cal_audit: Mutex<Option<CalAudit>>,
+    guard_rails: Mutex<GuardRailsMode>,
+    #[cfg(feature = "tokio")]
+    freeze_executor: Mutex<Option<tokio::runtime::Handle>>,
+    #[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+    async_server: Mutex<Option<async_server::AsyncServer>>,
+    start_time: Instant,
+    last_command_time: AtomicU64,      // Time of the last client command in ns since start_time
+    keepalive_idle_ms: AtomicU64,      // Client idle time until a heartbeat is sent, 0 = keep-alive disabled
+    keepalive_timeout_ms: AtomicU64,   // Time to wait for any client command after the heartbeat
+    daq_priority_reserve: AtomicUsize, // Transmit queue space in bytes reserved for events with priority High
+    daq_drop_count: [AtomicU64; 2],    // Dropped DaqEvent triggers per XcpEventPriority
 }
 
 lazy_static! {
@@ -455,8 +873,14 @@ impl Xcp {
     /// Addr of the EPK
     pub const XCP_EPK_ADDR: u32 = 0x80000000;
 
+    /// Default client idle time until the server sends a keep-alive heartbeat
+    pub const KEEPALIVE_IDLE_MS: u64 = 10000;
+    /// Default time the server waits for any client command after the keep-alive heartbeat
+    pub const KEEPALIVE_TIMEOUT_MS: u64 = 5000;
+
     /// Get address extension and address for A2L generation for XCP_ADDR_EXT_ABS addressing mode
     /// Used by A2L writer
+    /// The A2L ECU_ADDRESS is 32 bit, 64 bit pointers are mapped relative to the module load address, there is no 64 bit absolute address extension
     pub fn get_abs_ext_addr(addr: u64) -> (u8, u32) {
         #[cfg(not(feature = "xcp_server"))]
         {
@@ -495,6 +919,8 @@ impl Xcp {
                 Some(cb_write),
                 Some(cb_flush),
             );
+            // @@@@ Unsafe - C library calls
+            xcplib::ApplXcpRegisterCommandCallback(Some(cb_command));
         }
         #[cfg(feature = "xcp_server")]
         {
@@ -515,15 +941,30 @@ impl Xcp {
                 Some(cb_write),
                 Some(cb_flush),
             );
+            xcplib_rs::register_command_callback(Some(cb_command));
         }
 
         Xcp {
             ecu_cal_page: AtomicU8::new(XcpCalPage::Ram as u8), // ECU page defaults on RAM
             xcp_cal_page: AtomicU8::new(XcpCalPage::Ram as u8), // XCP page defaults on RAM
+            daq_start_count: AtomicU32::new(0),
             event_list: Arc::new(Mutex::new(EventList::new())),
             registry: Arc::new(Mutex::new(Registry::new())),
             calseg_list: Arc::new(Mutex::new(CalSegList::new())),
-            epk: Mutex::new("DEFAULT_EPK"),
+            embedded_a2l: Mutex::new(None),
+            alarm_handler: Mutex::new(None),
+            cal_audit: Mutex::new(None),
+            guard_rails: Mutex::new(GuardRailsMode::default()),
+            #[cfg(feature = "tokio")]
+            freeze_executor: Mutex::new(None),
+            #[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+            async_server: Mutex::new(None),
+            start_time: Instant::now(),
+            last_command_time: AtomicU64::new(0),
+            keepalive_idle_ms: AtomicU64::new(Xcp::KEEPALIVE_IDLE_MS),
+            keepalive_timeout_ms: AtomicU64::new(Xcp::KEEPALIVE_TIMEOUT_MS),
+            daq_priority_reserve: AtomicUsize::new(0),
+            daq_drop_count: [AtomicU64::new(0), AtomicU64::new(0)],
         }
     }
 
@@ -560,12 +1001,38 @@ impl Xcp {
         }
     }
 
+    //------------------------------------------------------------------------------------------
+    // Alarms
+
+    /// Set a handler for metrics alarms, as alternative to the XCP service text (SERV_TEXT) to the XCP client tool
+    pub fn set_alarm_handler(&self, handler: Box<dyn AlarmHandler>) {
+        *self.alarm_handler.lock() = Some(handler);
+    }
+
+    /// Notify an alarm state change to the alarm handler, or print the message to the XCP client tool console
+    /// Note that XcpPrint is not guaranteed to be delivered during heavy DAQ load, the message may be lost when the transmit queue is full
+    pub fn notify_alarm(&self, name: &str, message: &str, active: bool) {
+        if let Some(handler) = self.alarm_handler.lock().as_ref() {
+            handler.alarm(name, message, active);
+        } else if active {
+            log::warn!("Alarm {}: {}", name, message);
+            self.print(message);
+        } else {
+            log::info!("Alarm {} cleared", name);
+            self.print(&format!("{} cleared", name));
+        }
+    }
+
     //------------------------------------------------------------------------------------------
     // Server
 
     /// Check if the XCP server is ok and running
     #[allow(clippy::unused_self)]
     pub fn check_server(&self) -> bool {
+        #[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+        if let Some(server) = self.async_server.lock().as_ref() {
+            return server.is_running();
+        }
         #[cfg(not(feature = "xcp_server"))]
         unsafe {
             // @@@@ Unsafe - C library call
@@ -577,7 +1044,113 @@ impl Xcp {
         }
     }
 
+    /// Get the XCP session status
+    #[allow(clippy::unused_self)]
+    pub fn get_session_status(&self) -> XcpSessionStatus {
+        #[cfg(not(feature = "xcp_server"))]
+        let status = unsafe {
+            // @@@@ Unsafe - C library call
+            xcplib::XcpGetSessionStatus()
+        };
+        #[cfg(feature = "xcp_server")]
+        let status = xcplib_rs::get_session_status();
+        XcpSessionStatus::from_bits_truncate(status)
+    }
+
+    /// Get the transmit queue size in bytes, 0 if the XCP server is not started
+    #[allow(clippy::unused_self)]
+    pub fn get_transmit_queue_size(&self) -> usize {
+        #[cfg(not(feature = "xcp_server"))]
+        let size = unsafe {
+            // @@@@ Unsafe - C library call
+            xcplib::XcpTlGetTransmitQueueSize()
+        };
+        #[cfg(feature = "xcp_server")]
+        let size = xcplib_rs::get_transmit_queue_size();
+        size as usize
+    }
+
+    /// Get the free space in the transmit queue in bytes
+    /// The value is a snapshot, other threads may concurrently fill the queue
+    #[allow(clippy::unused_self)]
+    pub fn get_transmit_queue_free(&self) -> usize {
+        #[cfg(not(feature = "xcp_server"))]
+        let free = unsafe {
+            // @@@@ Unsafe - C library call
+            xcplib::XcpTlGetTransmitQueueFree()
+        };
+        #[cfg(feature = "xcp_server")]
+        let free = xcplib_rs::get_transmit_queue_free();
+        free as usize
+    }
+
+    /// Get the maximum DTO size of the XCP server
+    #[allow(clippy::unused_self)]
+    pub fn get_max_dto_size(&self) -> usize {
+        #[cfg(not(feature = "xcp_server"))]
+        let max_dto = unsafe {
+            // @@@@ Unsafe - C library call
+            xcplib::XcpTlGetMaxDtoSize()
+        };
+        #[cfg(feature = "xcp_server")]
+        let max_dto = xcplib_rs::get_max_dto_size();
+        max_dto as usize
+    }
+
+    /// Get the transport layer statistics since the server was started
+    #[allow(clippy::unused_self)]
+    pub fn get_transport_stats(&self) -> TransportStats {
+        #[cfg(not(feature = "xcp_server"))]
+        {
+            let mut stats = TransportStats::default();
+            unsafe {
+                // @@@@ Unsafe - C library call
+                xcplib::XcpTlGetStats(&mut stats.tx_packets, &mut stats.tx_bytes, &mut stats.tx_queue_overruns, &mut stats.rx_packets);
+            }
+            stats
+        }
+        #[cfg(feature = "xcp_server")]
+        {
+            let (tx_packets, tx_bytes, tx_queue_overruns, rx_packets) = xcplib_rs::get_transport_stats();
+            TransportStats {
+                tx_packets,
+                tx_bytes,
+                tx_queue_overruns,
+                rx_packets,
+            }
+        }
+    }
+
+    //------------------------------------------------------------------------------------------
+    // Clock
+
+    /// Get the XCP clock, which timestamps the DAQ data
+    /// See clock_epoch_info for resolution and epoch
+    #[allow(clippy::unused_self)]
+    pub fn get_clock(&self) -> u64 {
+        #[cfg(not(feature = "xcp_server"))]
+        unsafe {
+            // @@@@ Unsafe - C library call
+            xcplib::ApplXcpGetClock64()
+        }
+        #[cfg(feature = "xcp_server")]
+        xcplib_rs::get_clock()
+    }
+
+    /// Get resolution and epoch of the XCP clock
+    #[allow(clippy::unused_self)]
+    pub fn clock_epoch_info(&self) -> XcpClockInfo {
+        XCP_CLOCK_INFO
+    }
+
+    /// Correlate Instant and SystemTime with the XCP clock now
+    /// Used to express application side timestamps, e.g. of externally sourced events, in the timebase of the DAQ data
+    pub fn correlate_clock(&self) -> XcpClockCorrelation {
+        XcpClockCorrelation::new(self.clock_epoch_info(), || self.get_clock())
+    }
+
     /// Stop the XCP server
+    /// A server started with start_async_server is stopped with stop_async_server
     #[allow(clippy::unused_self)]
     pub fn stop_server(&self) {
         #[cfg(not(feature = "xcp_server"))]
@@ -594,13 +1167,159 @@ impl Xcp {
         }
     }
 
+    /// Start the XCP on Ethernet server as tasks on the current tokio runtime, instead of the XCPlite server threads
+    /// The UDP or TCP transport is handled with tokio sockets, the protocol layer and the transmit queue are the same as for start_server
+    /// queue_size is the transmit queue size in XCP packets, 0 or values larger than the xcplib maximum (OPTION_QUEUE_SIZE) select the maximum, larger values with a warning
+    /// Must be called from within a tokio runtime, the server is stopped with stop_async_server
+    #[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+    pub async fn start_async_server<A>(&'static self, tl: XcpTransportLayer, addr: A, port: u16, queue_size: u32) -> Result<(), XcpError>
+    where
+        A: Into<Ipv4Addr>,
+    {
+        // @@@@ Unsafe - C library call
+        if self.async_server.lock().is_some() || 0 != unsafe { xcplib::XcpEthServerStatus() } {
+            return Err(XcpError::XcpLib("Error: XCP server already running"));
+        }
+
+        let (server, addr) = async_server::AsyncServer::start(tl, addr.into(), port, queue_size).await?;
+        *self.async_server.lock() = Some(server);
+        self.check_transmit_queue_size(queue_size);
+
+        // Register transport layer parameters and actual ip addr of the server to make the A2L plug&play
+        self.registry.lock().set_tl_params(tl.protocol_name(), addr, port);
+
+        // Start the connection supervision
+        self.start_keepalive();
+
+        Ok(())
+    }
+
+    /// Start the XCP server with an in-process loopback transport instead of sockets, for tests of a client in the same process
+    /// Returns the client side of the transport, see XcpClient::new_loopback, the server terminates when it is dropped
+    /// Must be called from within a tokio runtime, the server is stopped with stop_async_server
+    #[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+    pub fn start_loopback_server(&'static self, queue_size: u32) -> Result<XcpLoopback, XcpError> {
+        // @@@@ Unsafe - C library call
+        if self.async_server.lock().is_some() || 0 != unsafe { xcplib::XcpEthServerStatus() } {
+            return Err(XcpError::XcpLib("Error: XCP server already running"));
+        }
+
+        let (server, loopback) = async_server::AsyncServer::start_loopback(queue_size)?;
+        *self.async_server.lock() = Some(server);
+        self.check_transmit_queue_size(queue_size);
+
+        // Start the connection supervision
+        self.start_keepalive();
+
+        Ok(loopback)
+    }
+
+    /// Stop the XCP server started with start_async_server
+    /// Disconnects the client, waits for the server tasks to terminate and shuts down the protocol layer
+    #[cfg(all(feature = "tokio", not(feature = "xcp_server")))]
+    pub async fn stop_async_server(&self) {
+        let server = self.async_server.lock().take();
+        if let Some(server) = server {
+            server.stop().await;
+        }
+    }
+
+    //------------------------------------------------------------------------------------------
+    // Keep-alive
+
+    /// Set the keep-alive parameters of the connection supervision
+    /// After idle_secs without any client command, the server sends a SERV_TEXT heartbeat to the client
+    /// If there is no client command within timeout_secs after the heartbeat, the server disconnects
+    /// idle_secs == 0 disables the keep-alive
+    pub fn set_keepalive(&self, idle_secs: u64, timeout_secs: u64) {
+        self.keepalive_idle_ms.store(idle_secs * 1000, Ordering::Relaxed);
+        self.keepalive_timeout_ms.store(timeout_secs * 1000, Ordering::Relaxed);
+    }
+
+    /// Get the time of the last client command, relative to the creation of the Xcp singleton
+    pub fn get_last_command_time(&self) -> Duration {
+        Duration::from_nanos(self.last_command_time.load(Ordering::Relaxed))
+    }
+
+    // Store the time of the last client command
+    fn set_last_command_time(&self) {
+        self.last_command_time.store(self.start_time.elapsed().as_nanos().try_into().unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+
+    // Warn, if the requested transmit queue size exceeds the xcplib maximum (OPTION_QUEUE_SIZE) and has been clamped
+    fn check_transmit_queue_size(&self, queue_size: u32) {
+        let max_queue_size = self.get_transmit_queue_size() / (self.get_max_dto_size() + XCPTL_TRANSPORT_LAYER_HEADER_SIZE);
+        if queue_size as usize > max_queue_size {
+            log::warn!("Transmit queue size {} exceeds the xcplib maximum, clamped to {} packets", queue_size, max_queue_size);
+        }
+    }
+
+    // Start the keep-alive thread, once for all server starts
+    fn start_keepalive(&'static self) {
+        static KEEPALIVE: Once = Once::new();
+        KEEPALIVE.call_once(|| {
+            std::thread::Builder::new()
+                .name("xcp_keepalive".into())
+                .spawn(move || self.keepalive_task())
+                .expect("Failed to spawn XCP keep-alive thread");
+        });
+    }
+
+    // Connection supervision
+    // Sends a heartbeat every second while the client is idle and disconnects on timeout
+    fn keepalive_task(&self) {
+        let mut heartbeat_time: Option<Duration> = None;
+        let mut print_time = Duration::ZERO;
+        loop {
+            std::thread::sleep(Duration::from_millis(100));
+
+            let idle = Duration::from_millis(self.keepalive_idle_ms.load(Ordering::Relaxed));
+            if idle.is_zero() || !self.get_session_status().contains(XcpSessionStatus::SS_CONNECTED) {
+                heartbeat_time = None;
+                continue;
+            }
+            let now = self.start_time.elapsed();
+            if now.saturating_sub(self.get_last_command_time()) < idle {
+                heartbeat_time = None;
+                continue;
+            }
+
+            match heartbeat_time {
+                None => {
+                    log::debug!("XCP client idle for {}ms, send keep-alive heartbeat", idle.as_millis());
+                    self.print("XCP keep-alive");
+                    heartbeat_time = Some(now);
+                    print_time = now;
+                }
+                Some(t) => {
+                    let timeout = Duration::from_millis(self.keepalive_timeout_ms.load(Ordering::Relaxed));
+                    if now.saturating_sub(t) >= timeout {
+                        log::warn!("XCP client did not respond to keep-alive within {}ms, disconnect", timeout.as_millis());
+                        #[cfg(not(feature = "xcp_server"))]
+                        unsafe {
+                            // @@@@ Unsafe - C library call
+                            xcplib::XcpDisconnect();
+                        }
+                        #[cfg(feature = "xcp_server")]
+                        xcplib_rs::disconnect();
+                        heartbeat_time = None;
+                    } else if now.saturating_sub(print_time) >= Duration::from_secs(1) {
+                        // Repeat the heartbeat, SERV_TEXT may be lost under heavy DAQ load
+                        self.print("XCP keep-alive");
+                        print_time = now;
+                    }
+                }
+            }
+        }
+    }
+
     //------------------------------------------------------------------------------------------
     // Calibration segments
 
     /// Create a calibration segment  
     /// # Panics  
     /// Panics if the calibration segment name already exists  
-    /// Panics if the calibration page size exceeds 64k
+    /// Calibration pages larger than 64k are registered as multiple memory segments <name>_0, <name>_1, ...
     pub fn create_calseg<T>(&self, name: &'static str, default_page: &'static T) -> CalSeg<T>
     where
         T: CalPageTrait,
@@ -608,10 +1327,24 @@ impl Xcp {
         self.calseg_list.lock().create_calseg(name, default_page)
     }
 
+    /// Create a calibration segment with a default page computed at runtime, e.g. read from a file
+    /// The default page is moved to the heap and leaked, it becomes the "FLASH" page with the same 'static lifetime as the default of create_calseg
+    /// Calibration segments are never dropped, so the leak happens once per segment
+    /// # Panics
+    /// Panics if the calibration segment name already exists
+    /// Calibration pages larger than 64k are registered as multiple memory segments, see create_calseg
+    pub fn create_calseg_boxed<T>(&self, name: &'static str, default_page: T) -> CalSeg<T>
+    where
+        T: CalPageTrait,
+    {
+        let default_page: &'static T = Box::leak(Box::new(default_page));
+        self.create_calseg(name, default_page)
+    }
+
     /// Create a calibration segment, don't register fields and don't load json  
     /// # Panics  
     /// Panics if the calibration segment name already exists  
-    /// Panics if the calibration page size exceeds 64k
+    /// Calibration pages larger than 64k are registered as multiple memory segments, see create_calseg
     pub fn add_calseg<T>(&self, name: &'static str, default_page: &'static T) -> CalSeg<T>
     where
         T: CalPageTrait,
@@ -619,6 +1352,24 @@ impl Xcp {
         self.calseg_list.lock().create_calseg(name, default_page)
     }
 
+    /// Remove a calibration segment and its registered calibration parameters
+    /// Only possible before the registry is finalized, e.g. to drop segments of optional features
+    /// The remaining segments are renumbered, their addresses are assigned when the registry is finalized
+    pub fn remove_calseg(&self, name: &'static str) -> Result<(), XcpError> {
+        if self.get_session_status().contains(XcpSessionStatus::SS_CONNECTED) {
+            return Err(XcpError::ClientConnected);
+        }
+        if self.registry.lock().is_frozen() {
+            return Err(XcpError::Io(std::io::Error::other("Registry is closed")));
+        }
+        if !self.calseg_list.lock().remove_calseg(name) {
+            return Err(XcpError::Registry(RegistryError::NotFound(name)));
+        }
+        let count = self.registry.lock().remove_cal_seg(name);
+        log::info!("Remove CalSeg: {}, {} calibration parameters", name, count);
+        Ok(())
+    }
+
     /// Get calibration segment index by name
     pub fn get_calseg_index(&self, name: &str) -> Option<usize> {
         self.calseg_list.lock().get_index(name)
@@ -629,6 +1380,72 @@ impl Xcp {
         self.calseg_list.lock().get_name(index)
     }
 
+    /// Get the names of all calibration segments
+    pub fn get_calseg_names(&self) -> Vec<&'static str> {
+        self.calseg_list.lock().get_names()
+    }
+
+    /// Get the names of all calibration segments modified by the XCP tool since their last save or freeze
+    pub fn get_changed_calsegs(&self) -> Vec<&'static str> {
+        self.calseg_list.lock().changed()
+    }
+
+    /// Set a sink for the audit log of all calibration writes of the XCP tool, replaces a previous sink
+    /// The sink is called on a separate thread with the written range, the resolved calibration parameter names and the old and new bytes and values
+    /// Writes are queued in a small bounded queue, writes are dropped and counted (get_cal_audit_dropped), when the sink can not keep up
+    /// Delayed writes (consistent calibration) are recorded when written, not on flush
+    pub fn set_cal_audit_sink(&self, sink: CalAuditSink) {
+        let previous = self.cal_audit.lock().replace(CalAudit::new(sink));
+        drop(previous); // Deliver the pending records of the previous sink
+    }
+
+    /// Remove the calibration audit sink, all pending records are delivered before it returns
+    pub fn clear_cal_audit_sink(&self) {
+        let previous = self.cal_audit.lock().take();
+        drop(previous);
+    }
+
+    /// Get the number of calibration writes dropped, because the calibration audit sink could not keep up
+    pub fn get_cal_audit_dropped(&self) -> u64 {
+        self.cal_audit.lock().as_ref().map_or(0, CalAudit::get_dropped)
+    }
+
+    /// Set the checks of calibration writes of the XCP tool
+    pub fn set_guard_rails(&self, mode: GuardRailsMode) {
+        *self.guard_rails.lock() = mode;
+    }
+
+    /// Get the checks of calibration writes of the XCP tool
+    pub fn get_guard_rails(&self) -> GuardRailsMode {
+        *self.guard_rails.lock()
+    }
+
+    /// Set the memory layout of two dimensional calibration parameters, written as FNC_VALUES ROW_DIR or COLUMN_DIR of the A2L RECORD_LAYOUT
+    /// Default is row major, the layout of Rust arrays [[T; y_dim]; x_dim]
+    /// Must be called before the A2L file is written
+    pub fn set_row_major(&self, row_major: bool) {
+        let mut registry = self.registry.lock();
+        if registry.is_frozen() {
+            log::warn!("Row major {} ignored, A2L already written", row_major);
+            return;
+        }
+        registry.set_row_major(row_major);
+    }
+
+    /// Add a system constant, written as SYSTEM_CONSTANT "name" "value" in the MOD_PAR of the A2L file
+    /// Exposes build time constants, e.g. feature flags or a calibration variant id, to the tool
+    /// Must be called before the A2L file is written
+    /// Fails with RegistryError::Duplicate, if a system constant with the same name already exists
+    pub fn add_system_constant(&self, name: &str, value: &str) -> Result<(), XcpError> {
+        let mut registry = self.registry.lock();
+        if registry.is_frozen() {
+            log::warn!("System constant {} = {} ignored, A2L already written", name, value);
+            return Ok(());
+        }
+        registry.add_system_constant(name, value)?;
+        Ok(())
+    }
+
     /// Get A2L addr (ext,addr) of a CalSeg
     pub fn get_calseg_ext_addr_base(calseg_index: u16) -> (u8, u32) {
         // Address format for calibration segment field is index | 0x8000 in high word, addr_ext is 0 (CANape does not support addr_ext in memory segments)
@@ -639,18 +1456,39 @@ impl Xcp {
 
     /// Get A2L addr (ext,addr) for a calibration value field at offset in a CalSeg
     /// The address is relative to the base addr of the calibration segment
-    pub fn get_calseg_ext_addr(calseg_index: u16, offset: u16) -> (u8, u32) {
+    /// Offsets beyond 64k carry into the index, which addresses the following memory segments of a large calibration segment
+    pub fn get_calseg_ext_addr(calseg_index: u16, offset: u32) -> (u8, u32) {
         let (addr_ext, mut addr) = Xcp::get_calseg_ext_addr_base(calseg_index);
-        addr += offset as u32;
+        addr += offset;
         (addr_ext, addr)
     }
 
     //------------------------------------------------------------------------------------------
     // EPK
 
-    // Set EPK
+    // Set EPK, the EPK is read only memory segment 0 of the XCP address
     fn set_epk(&self, epk: &'static str) {
-        *self.epk.lock() = epk;
+        self.calseg_list.lock().set_epk(epk);
+    }
+
+    //------------------------------------------------------------------------------------------
+    // Embedded A2L
+
+    /// Set an A2L, which is embedded in the application binary, e.g. with include_str!  
+    /// The embedded A2L is uploaded via XCP instead of the A2L file  
+    /// If set before the A2L is written, write_a2l will not write the A2L file, intended for targets without a writable filesystem  
+    /// The A2L must have been generated from a build with identical registrations, e.g. with generate_a2l_string on the host,
+    /// addresses and events are not checked  
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn set_embedded_a2l(&self, a2l: &'static str) {
+        *self.embedded_a2l.lock() = Some(a2l);
+
+        // Set the A2L data in the XCPlite server
+        #[cfg(not(feature = "xcp_server"))]
+        unsafe {
+            // @@@@ Unsafe - C library call
+            xcplib::ApplXcpSetA2lData(a2l.as_ptr() as *const std::os::raw::c_char, a2l.len() as u32);
+        }
     }
 
     //------------------------------------------------------------------------------------------
@@ -663,12 +1501,87 @@ impl Xcp {
         self.event_list.lock().create_event_ext(name, indexed, cycle_time_ns)
     }
 
+    /// Create a multi instance XCP event with an explicit instance label
+    /// The label (e.g. a loop index or a name like "front_left") is attached to the event and signal names instead of the instance index,
+    /// which depends on creation order
+    pub fn create_event_instance<T: std::fmt::Display>(&self, name: &'static str, instance: T) -> XcpEvent {
+        // The label lives as long as the event, events are never dropped
+        let label: &'static str = Box::leak(instance.to_string().into_boxed_str());
+        self.event_list.lock().create_event_instance(name, label)
+    }
+
     /// Create XCP event  
     /// Single instance  
     pub fn create_event(&self, name: &'static str) -> XcpEvent {
         self.event_list.lock().create_event_ext(name, false, 0)
     }
 
+    /// Set the data consistency of an event, written as CONSISTENCY attribute of the A2L EVENT
+    /// Must be called before the A2L file is written
+    pub fn set_event_consistency(&self, event: XcpEvent, consistency: XcpEventConsistency) {
+        if self.registry.lock().is_frozen() {
+            log::warn!("Event {} consistency {:?} ignored, A2L already written", event.get_name(), consistency);
+            return;
+        }
+        self.event_list.lock().set_consistency(event, consistency);
+    }
+
+    /// Set the transmit priority of an event, written as PRIORITY attribute of the A2L EVENT
+    /// Must be called before the A2L file is written and before DaqEvents of the event are created
+    pub fn set_event_priority(&self, event: XcpEvent, priority: XcpEventPriority) {
+        if self.registry.lock().is_frozen() {
+            log::warn!("Event {} priority {:?} ignored, A2L already written", event.get_name(), priority);
+            return;
+        }
+        self.event_list.lock().set_priority(event, priority);
+    }
+
+    /// Get the transmit priority of an event
+    pub fn get_event_priority(&self, event: XcpEvent) -> XcpEventPriority {
+        self.event_list.lock().get_priority(event)
+    }
+
+    /// Number of DaqEvent triggers with the given priority, which were dropped or did not fit into the transmit queue
+    pub fn get_daq_drop_count(&self, priority: XcpEventPriority) -> u64 {
+        self.daq_drop_count[priority as usize].load(Ordering::Relaxed)
+    }
+
+    /// Find an event by name, multi instance events are found by name with instance suffix, e.g. "task_1" or "task_front_left"
+    /// Allows code, which did not create the event, to trigger it, e.g. a central scheduler
+    ///
+    /// XcpEvent is Copy, Send and Sync, an event may be triggered from any thread
+    /// XcpEvent::trigger_abs is only valid for events with measurement variables in absolute addressing mode (add_heap, daq_register_ref, daq_register_slice)
+    /// Events with variables on stack or in a capture buffer (DaqEvent) need the base pointer of their creator, they must be triggered by the DaqEvent
+    pub fn find_event(&self, name: &str) -> Option<XcpEvent> {
+        self.event_list.lock().find(name)
+    }
+
+    /// Get all events with their names, in order of creation
+    pub fn get_events(&self) -> Vec<(String, XcpEvent)> {
+        self.event_list.lock().get_events()
+    }
+
+    /// Get or create the auxiliary event "<event>_onchange" for on change measurements of a single instance event
+    pub fn create_on_change_event(&self, event: XcpEvent) -> XcpEvent {
+        self.event_list.lock().get_or_create_aux_event(event, "onchange")
+    }
+
+    /// Get or create the auxiliary event "<event>_timing" for the timing metrics of a single instance event
+    pub fn create_timing_event(&self, event: XcpEvent) -> XcpEvent {
+        self.event_list.lock().get_or_create_aux_event(event, "timing")
+    }
+
+    /// Get or create the auxiliary event "<event>_<group>" for the measurements of a runtime gated measurement group of a single instance event
+    pub fn create_gate_event(&self, event: XcpEvent, group: &str) -> XcpEvent {
+        self.event_list.lock().get_or_create_aux_event(event, group)
+    }
+
+    /// Number of DAQ starts by the XCP client
+    /// Used to retransmit on change measurements on DAQ start
+    pub fn get_daq_start_count(&self) -> u32 {
+        self.daq_start_count.load(Ordering::Relaxed)
+    }
+
     //------------------------------------------------------------------------------------------
     // Registry
 
@@ -685,11 +1598,13 @@ impl Xcp {
         self.calseg_list.lock().register();
 
         // Register all events
-        self.event_list.lock().register();
+        self.event_list.lock().register()?;
 
         {
-            // Write A2L file from registry
-            self.registry.lock().write_a2l()?;
+            // Write A2L file from registry, not needed if an embedded A2L is uploaded instead
+            if self.embedded_a2l.lock().is_none() {
+                self.registry.lock().write_a2l()?;
+            }
 
             // A2L exists and is up to date on disk
             // Set the name of the A2L file in the XCPlite server to enable upload via XCP
@@ -717,6 +1632,62 @@ impl Xcp {
         Ok(true)
     }
 
+    /// Generate the A2L as a string, without writing the A2L file
+    /// Finalizes the registry like write_a2l, all registrations from now on will cause panic
+    /// The A2L name is not set in the XCPlite server, so upload of the A2L via XCP is not available
+    /// Useful for tests, or to serve the A2L over other channels on systems without a writable filesystem
+    pub fn generate_a2l_string(&self) -> Result<String, XcpError> {
+        // Error, if the registry is already written
+        if self.registry.lock().is_frozen() {
+            return Err(XcpError::Io(std::io::Error::other("Registry is closed")));
+        }
+
+        // Register all calibration segments
+        self.calseg_list.lock().register();
+
+        // Register all events
+        self.event_list.lock().register()?;
+
+        // Write A2L to memory
+        let mut a2l = Vec::with_capacity(16 * 1024);
+        self.registry.lock().write_a2l_to(&mut a2l)?;
+
+        // Set the EPK in the XCPlite server
+        #[cfg(not(feature = "xcp_server"))]
+        unsafe {
+            let epk = std::ffi::CString::new(self.registry.lock().get_epk().unwrap()).unwrap();
+            // @@@@ Unsafe - C library call
+            xcplib::ApplXcpSetEpk(epk.as_ptr());
+            std::mem::forget(epk); // This memory is never dropped, it is moved to xcplib singleton
+        }
+
+        // All registrations from now on, will cause panic
+        self.registry.lock().freeze();
+
+        Ok(String::from_utf8(a2l).expect("A2L is not valid UTF-8"))
+    }
+
+    /// Register an alias name of an already registered measurement signal or calibration parameter
+    /// The alias has the same address, both names may be used in the calibration tool
+    pub fn register_alias(&self, name: &'static str, alias: &'static str) -> Result<(), XcpError> {
+        self.registry.lock().add_alias(name, alias)?;
+        Ok(())
+    }
+
+    /// Import the calibration parameters of an external A2L file, e.g. of a supplier component, with the name prefix "<namespace>."
+    /// See Registry::load_a2l_append
+    #[cfg(feature = "a2l_reader")]
+    pub fn import_a2l<P: AsRef<std::path::Path>>(&self, path: P, namespace: &str) -> Result<usize, XcpError> {
+        Ok(self.registry.lock().load_a2l_append(path, namespace)?)
+    }
+
+    /// Import the calibration parameters of an external A2L file like import_a2l, unknown keywords are skipped with a warning
+    /// See Registry::load_a2l_lenient
+    #[cfg(feature = "a2l_reader")]
+    pub fn import_a2l_lenient<P: AsRef<std::path::Path>>(&self, path: P, namespace: &str) -> Result<A2lImportReport, XcpError> {
+        Ok(self.registry.lock().load_a2l_lenient(path, namespace)?)
+    }
+
     /// Get a clone of the registry
     pub fn get_registry(&self) -> Arc<Mutex<Registry>> {
         Arc::clone(&self.registry)
@@ -725,19 +1696,37 @@ impl Xcp {
     //------------------------------------------------------------------------------------------
     // Calibration page switching
 
-    /// Set the active calibration page for the ECU access (used for test only)
+    // The active page applies to all calibration segments, switching individual segments is not supported
+
+    // Set the active calibration page for the ECU access
     fn set_ecu_cal_page(&self, page: XcpCalPage) {
         self.ecu_cal_page.store(page as u8, Ordering::Relaxed);
     }
 
-    /// Set the active calibration page for the XCP access (used for test only)
+    // Set the active calibration page for the XCP access
     fn set_xcp_cal_page(&self, page: XcpCalPage) {
         self.xcp_cal_page.store(page as u8, Ordering::Relaxed);
     }
 
+    /// Switch all calibration segments to the RAM or FLASH (default) page, for the ECU and the XCP access
+    /// Same as SET_CAL_PAGE from the XCP tool, CalSeg readers see the switched page on their next access
+    pub fn set_cal_page_all(&self, page: XcpCalPage) {
+        log::debug!("Set calibration page of all segments to {:?}", page);
+        self.set_ecu_cal_page(page);
+        self.set_xcp_cal_page(page);
+    }
+
+    /// Get the active calibration page for the ECU access of a calibration segment
+    pub fn get_cal_page(&self, name: &str) -> Result<XcpCalPage, XcpError> {
+        if self.get_calseg_index(name).is_none() {
+            return Err(XcpError::CalSegNotFound(name.to_string()));
+        }
+        Ok(self.get_ecu_cal_page())
+    }
+
     /// Get the active calibration page for the ECU access
     #[inline]
-    fn get_ecu_cal_page(&self) -> XcpCalPage {
+    pub fn get_ecu_cal_page(&self) -> XcpCalPage {
         if self.ecu_cal_page.load(Ordering::Relaxed) == XcpCalPage::Ram as u8 {
             XcpCalPage::Ram
         } else {
@@ -746,7 +1735,7 @@ impl Xcp {
     }
 
     /// Get the active calibration page for the XCP access
-    fn get_xcp_cal_page(&self) -> XcpCalPage {
+    pub fn get_xcp_cal_page(&self) -> XcpCalPage {
         if self.xcp_cal_page.load(Ordering::Relaxed) == XcpCalPage::Ram as u8 {
             XcpCalPage::Ram
         } else {
@@ -757,17 +1746,45 @@ impl Xcp {
     //------------------------------------------------------------------------------------------
     // Freeze and Init
 
-    /// Set calibration segment init request  
-    /// Called on init cal from XCP server  
-    fn set_init_request(&self) {
+    /// Copy the FLASH (default) page to the RAM page of all calibration segments
+    /// Same as COPY_CAL_PAGE from the XCP tool, done by each CalSeg clone on its next sync
+    pub fn init_all(&self) {
         self.calseg_list.lock().set_init_request();
     }
 
-    /// Set calibration segment freeze request  
-    /// Called on freeze cal from XCP server  
-    fn set_freeze_request(&self) {
+    /// Save the RAM page of all calibration segments to their json files (feature serde)
+    /// Same as freeze from the XCP tool, done on the next sync of each calibration segment
+    pub fn freeze_all(&self) {
         self.calseg_list.lock().set_freeze_request();
     }
+
+    /// Copy the FLASH (default) page to the RAM page of a calibration segment, see init_all
+    pub fn init_calseg(&self, name: &str) -> Result<(), XcpError> {
+        if !self.calseg_list.lock().set_calseg_init_request(name) {
+            return Err(XcpError::CalSegNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Save the RAM page of a calibration segment to its json file, see freeze_all
+    pub fn freeze_calseg(&self, name: &str) -> Result<(), XcpError> {
+        if !self.calseg_list.lock().set_calseg_freeze_request(name) {
+            return Err(XcpError::CalSegNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Set a tokio runtime to write the freeze files of calibration segments asynchronously
+    /// Without, freeze blocks the thread calling CalSeg::sync until the file is written
+    #[cfg(feature = "tokio")]
+    pub fn set_freeze_executor(&self, handle: tokio::runtime::Handle) {
+        *self.freeze_executor.lock() = Some(handle);
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn get_freeze_executor(&self) -> Option<tokio::runtime::Handle> {
+        self.freeze_executor.lock().clone()
+    }
 }
 
 //--------------------------------------------------------------------------------------------------------------------------------------------------
@@ -781,6 +1798,7 @@ const CRC_CMD_OK: u8 = 0;
 const CRC_PAGE_MODE_NOT_VALID: u8 = 0x27;
 //const CRC_SEGMENT_NOT_VALID: u8 = 0x28;
 const CRC_ACCESS_DENIED: u8 = 0x24;
+const CRC_WRITE_PROTECTED: u8 = 0x23;
 
 // Modes for page switching
 // @@@@ Clarify: Individual segment switching is not supported yet
@@ -792,6 +1810,7 @@ const CAL_PAGE_MODE_ALL: u8 = 0x80; // switch all segments simultaneously
 extern "C" fn cb_connect() -> u8 {
     log::trace!("cb_connect: generate and write Al2 file");
     let xcp = Xcp::get();
+    xcp.set_last_command_time();
     if let Err(e) = xcp.write_a2l() {
         log::error!("connect refused, A2L file write failed, {}", e);
         return FALSE;
@@ -799,6 +1818,11 @@ extern "C" fn cb_connect() -> u8 {
     TRUE
 }
 
+#[no_mangle]
+extern "C" fn cb_command(_cmd: u8) {
+    Xcp::get().set_last_command_time();
+}
+
 #[no_mangle]
 extern "C" fn cb_prepare_daq() -> u8 {
     log::trace!("cb_prepare_daq");
@@ -808,6 +1832,7 @@ extern "C" fn cb_prepare_daq() -> u8 {
 #[no_mangle]
 extern "C" fn cb_start_daq() -> u8 {
     log::trace!("cb_start_daq");
+    Xcp::get().daq_start_count.fetch_add(1, Ordering::Relaxed);
     TRUE
 }
 
@@ -847,11 +1872,13 @@ extern "C" fn cb_set_cal_page(segment: u8, page: u8, mode: u8) -> u8 {
     // }
 
     let xcp = Xcp::get();
-    if (mode & CAL_PAGE_MODE_ECU) != 0 {
-        xcp.set_ecu_cal_page(XcpCalPage::from(page));
-    }
-    if (mode & CAL_PAGE_MODE_XCP) != 0 {
-        xcp.set_xcp_cal_page(XcpCalPage::from(page));
+    let page = XcpCalPage::from(page);
+    if (mode & CAL_PAGE_MODE_ECU) != 0 && (mode & CAL_PAGE_MODE_XCP) != 0 {
+        xcp.set_cal_page_all(page);
+    } else if (mode & CAL_PAGE_MODE_ECU) != 0 {
+        xcp.set_ecu_cal_page(page);
+    } else if (mode & CAL_PAGE_MODE_XCP) != 0 {
+        xcp.set_xcp_cal_page(page);
     }
 
     CRC_CMD_OK
@@ -860,14 +1887,14 @@ extern "C" fn cb_set_cal_page(segment: u8, page: u8, mode: u8) -> u8 {
 #[no_mangle]
 extern "C" fn cb_init_cal(_src_page: u8, _dst_page: u8) -> u8 {
     log::trace!("cb_init_cal");
-    Xcp::get().set_init_request();
+    Xcp::get().init_all();
     CRC_CMD_OK
 }
 
 #[no_mangle]
 extern "C" fn cb_freeze_cal() -> u8 {
     log::trace!("cb_freeze_cal");
-    Xcp::get().set_freeze_request();
+    Xcp::get().freeze_all();
     CRC_CMD_OK
 }
 
@@ -887,34 +1914,13 @@ unsafe extern "C" fn cb_read(addr: u32, len: u8, dst: *mut u8) -> u8 {
     let index: u16 = (addr >> 16) as u16 & 0x7FFF;
     let offset: u16 = (addr & 0xFFFF) as u16;
 
-    // EPK read
-    // This might be more elegantlty solved with a EPK segment in the registry, but this is a simple solution
-    // Otherwise we would have to introduce a read only CalSeg
-    if index == 0 {
-        let m = Xcp::get().epk.lock();
-        let epk = *m;
-        let epk_len = epk.len();
-
-        // @@@@ callbacks should not panic
-        assert!(
-            offset as usize + len as usize <= epk_len && epk_len <= 0xFF,
-            "cb_read: EPK length error ! offset={} len={} epk_len={}",
-            offset,
-            len,
-            epk_len
-        );
-
-        let src = epk.as_ptr().add(offset as usize);
-        std::ptr::copy_nonoverlapping(src, dst, len as usize);
-
+    // Read from the EPK memory segment or a calibration segment
+    // read_addr is Unsafe function
+    let calseg_list = Xcp::get().calseg_list.lock();
+    if calseg_list.read_addr(index as usize, offset, len, dst) {
         CRC_CMD_OK
-    }
-    // Calibration segment read
-    // read_from is Unsafe function
-    else if !Xcp::get().calseg_list.lock().read_from((index - 1) as usize, offset, len, dst) {
-        CRC_ACCESS_DENIED
     } else {
-        CRC_CMD_OK
+        CRC_ACCESS_DENIED
     }
 }
 
@@ -928,17 +1934,32 @@ unsafe extern "C" fn cb_write(addr: u32, len: u8, src: *const u8, delay: u8) ->
     // Decode addr
     assert!((addr & 0x80000000) != 0, "cb_write: invalid address");
     let index: u16 = (addr >> 16) as u16 & 0x7FFF;
-    if index == 0 {
-        return CRC_ACCESS_DENIED; // EPK is read only
-    }
     let offset: u16 = (addr & 0xFFFF) as u16;
 
-    // Write to calibration segment
-    // read_from is Unsafe function
-    if !Xcp::get().calseg_list.lock().write_to((index - 1) as usize, offset, len, src, delay) {
-        CRC_ACCESS_DENIED
-    } else {
-        CRC_CMD_OK
+    // Write to calibration segment, the EPK memory segment is read only
+    // write_to is Unsafe function
+    let xcp = Xcp::get();
+    let audit = xcp.cal_audit.lock().is_some();
+    let (status, notification) = {
+        let calseg_list = xcp.calseg_list.lock();
+        match calseg_list.find_addr(index as usize, offset) {
+            Some((index, offset)) => calseg_list.write_to(index, offset, len, src, delay, audit),
+            None => (CalWriteStatus::AccessDenied, None),
+        }
+    };
+
+    // Record the write and notify the calibration segment observers after the calibration segment list lock is released
+    if let Some(notification) = notification {
+        if let Some(cal_audit) = xcp.cal_audit.lock().as_ref() {
+            notification.audit(cal_audit);
+        }
+        notification.notify();
+    }
+
+    match status {
+        CalWriteStatus::Ok => CRC_CMD_OK,
+        CalWriteStatus::AccessDenied => CRC_ACCESS_DENIED,
+        CalWriteStatus::WriteProtected => CRC_WRITE_PROTECTED,
     }
 }
 
@@ -989,6 +2010,7 @@ pub mod xcp_test {
         {
             let mut s = xcp.calseg_list.lock();
             s.clear();
+            s.set_epk("TEST_EPK");
         }
         {
             let mut r = xcp.registry.lock();
@@ -998,6 +2020,16 @@ pub mod xcp_test {
         }
         xcp.set_ecu_cal_page(XcpCalPage::Ram);
         xcp.set_xcp_cal_page(XcpCalPage::Ram);
+        *xcp.alarm_handler.lock() = None;
+        xcp.clear_cal_audit_sink();
+        xcp.set_guard_rails(GuardRailsMode::default());
+        #[cfg(feature = "tokio")]
+        {
+            *xcp.freeze_executor.lock() = None;
+        }
+        xcp.set_keepalive(Xcp::KEEPALIVE_IDLE_MS / 1000, Xcp::KEEPALIVE_TIMEOUT_MS / 1000);
+        xcp.daq_priority_reserve.store(0, Ordering::Relaxed);
+        xcp.daq_drop_count.iter().for_each(|c| c.store(0, Ordering::Relaxed));
         log::info!("Test reinit done");
         xcp
     }
@@ -0,0 +1,482 @@
+//----------------------------------------------------------------------------------------------
+// Module async_server
+// XCP on Ethernet server running as tokio tasks, alternative to the threaded XCPlite server
+// The UDP/TCP transport is implemented with tokio sockets, received commands are fed into the xcplib protocol layer
+// and the xcplib transmit queue is drained by an async task
+// The loopback transport connects a client in the same process with channels instead of sockets
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{tcp::OwnedWriteHalf, TcpListener, UdpSocket},
+    sync::{mpsc, watch, Notify},
+    task::JoinHandle,
+    time::Instant,
+};
+
+use super::xcplib;
+use super::{Xcp, XcpError, XcpTransportLayer, XCPTL_TRANSPORT_LAYER_HEADER_SIZE};
+
+//----------------------------------------------------------------------------------------------
+// Transport layer parameters, must match xcptl_cfg.h
+
+const XCPTL_MAX_CTO_SIZE: usize = super::XCPTL_MAX_CTO_SIZE as usize;
+const XCPTL_QUEUE_FLUSH_CYCLE: Duration = Duration::from_millis(100); // Send a DTO packet at least every 100ms
+const XCPTL_QUEUE_POLLING_TIME: Duration = Duration::from_millis(1); // Transmit queue polling cycle, same as the threaded server on Linux
+const XCPTL_QUEUE_MAX_LOOPS: u32 = 20; // Maximum number of messages sent before yielding to the executor
+
+const LOOPBACK_CHANNEL_SIZE: usize = 256; // Number of transport layer segments buffered in each direction of the loopback transport
+
+const CC_CONNECT: u8 = 0xFF;
+
+//----------------------------------------------------------------------------------------------
+// CTO message buffer
+
+// The protocol layer reads the command packet as u32 array, the buffer must be 4 byte aligned
+#[derive(Clone, Copy)]
+#[repr(C, align(4))]
+struct CtoBuffer([u8; XCPTL_TRANSPORT_LAYER_HEADER_SIZE + XCPTL_MAX_CTO_SIZE]);
+
+impl CtoBuffer {
+    fn new() -> CtoBuffer {
+        CtoBuffer([0; XCPTL_TRANSPORT_LAYER_HEADER_SIZE + XCPTL_MAX_CTO_SIZE])
+    }
+
+    fn dlc(&self) -> usize {
+        u16::from_le_bytes([self.0[0], self.0[1]]) as usize
+    }
+
+    fn is_connect(&self) -> bool {
+        self.dlc() == 2 && self.0[XCPTL_TRANSPORT_LAYER_HEADER_SIZE] == CC_CONNECT
+    }
+}
+
+// Execute a XCP command in the protocol layer
+// Commands may block (e.g. wait for the transmit queue to be empty), so they are executed on the blocking thread pool
+async fn command(buf: CtoBuffer) {
+    let len = XCPTL_TRANSPORT_LAYER_HEADER_SIZE + buf.dlc();
+    #[allow(clippy::cast_possible_truncation)]
+    let res = tokio::task::spawn_blocking(move || unsafe {
+        // @@@@ Unsafe - C library call
+        xcplib::XcpTlCommand(len as u16, buf.0.as_ptr())
+    })
+    .await;
+    if let Ok(err) = res {
+        if err != 0 {
+            log::debug!("XCP command 0x{:02X} returned error 0x{:02X}", buf.0[XCPTL_TRANSPORT_LAYER_HEADER_SIZE], err);
+        }
+    }
+}
+
+fn is_connected() -> bool {
+    Xcp::get().get_session_status().contains(super::XcpSessionStatus::SS_CONNECTED)
+}
+
+//----------------------------------------------------------------------------------------------
+// Transport, destination of the transmit queue messages
+
+enum Transport {
+    Udp {
+        socket: Arc<UdpSocket>,
+        master: Arc<Mutex<Option<SocketAddr>>>, // Address of the connected client
+    },
+    Tcp {
+        stream: Arc<tokio::sync::Mutex<Option<OwnedWriteHalf>>>, // Write half of the accepted client connection
+    },
+    Loopback {
+        tx: mpsc::Sender<Vec<u8>>, // Transport layer segments to the client in the same process
+    },
+}
+
+impl Transport {
+    // Send a transport layer message segment to the client
+    // Messages without a client are dropped
+    async fn send(&self, data: &[u8]) -> Result<(), XcpError> {
+        match self {
+            Transport::Udp { socket, master } => {
+                let master = *master.lock();
+                if let Some(master) = master {
+                    socket.send_to(data, master).await?;
+                } else {
+                    log::debug!("XCP async server: no client, message dropped");
+                }
+            }
+            Transport::Tcp { stream } => {
+                if let Some(stream) = stream.lock().await.as_mut() {
+                    stream.write_all(data).await?;
+                } else {
+                    log::debug!("XCP async server: no client, message dropped");
+                }
+            }
+            Transport::Loopback { tx } => {
+                if tx.send(data.to_vec()).await.is_err() {
+                    log::debug!("XCP async server: no client, message dropped");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// Receive tasks
+
+// UDP command receive loop
+async fn udp_receive_task(socket: Arc<UdpSocket>, master: Arc<Mutex<Option<SocketAddr>>>, notify: Arc<Notify>) -> Result<(), XcpError> {
+    let mut buf = CtoBuffer::new();
+    loop {
+        let (n, src) = socket.recv_from(&mut buf.0).await?;
+        if n < XCPTL_TRANSPORT_LAYER_HEADER_SIZE || buf.dlc() != n - XCPTL_TRANSPORT_LAYER_HEADER_SIZE {
+            log::warn!("XCP async server: corrupt message received from {}, ignored", src);
+            continue;
+        }
+
+        let connected = is_connected();
+        if connected {
+            // The client address and port are not allowed to change
+            let m = *master.lock();
+            if m.is_some_and(|m| m != src) {
+                log::warn!("XCP async server: message from unknown new client {}, disconnecting!", src);
+                tokio::task::spawn_blocking(|| unsafe {
+                    // @@@@ Unsafe - C library call
+                    xcplib::XcpDisconnect();
+                })
+                .await
+                .ok();
+                *master.lock() = None;
+                continue;
+            }
+        } else if buf.is_connect() {
+            // Save the client address, so the CONNECT response can be sent
+            *master.lock() = Some(src);
+        }
+
+        command(buf).await;
+        notify.notify_one();
+
+        if !connected {
+            if is_connected() {
+                log::info!("XCP client connected on UDP addr={}", src);
+            } else {
+                *master.lock() = None; // Any client can connect
+            }
+        }
+    }
+}
+
+// TCP accept and command receive loop, one client at a time
+async fn tcp_receive_task(listener: TcpListener, stream: Arc<tokio::sync::Mutex<Option<OwnedWriteHalf>>>, notify: Arc<Notify>) -> Result<(), XcpError> {
+    loop {
+        let (s, addr) = listener.accept().await?;
+        log::info!("XCP client {} accepted", addr);
+        s.set_nodelay(true)?;
+        let (mut rx, tx) = s.into_split();
+        *stream.lock().await = Some(tx);
+
+        let mut buf = CtoBuffer::new();
+        loop {
+            if rx.read_exact(&mut buf.0[..XCPTL_TRANSPORT_LAYER_HEADER_SIZE]).await.is_err() {
+                break; // Connection closed
+            }
+            let dlc = buf.dlc();
+            if dlc > XCPTL_MAX_CTO_SIZE {
+                log::error!("XCP async server: corrupt message received, closing connection");
+                break;
+            }
+            if rx.read_exact(&mut buf.0[XCPTL_TRANSPORT_LAYER_HEADER_SIZE..XCPTL_TRANSPORT_LAYER_HEADER_SIZE + dlc]).await.is_err() {
+                break; // Connection closed
+            }
+            command(buf).await;
+            notify.notify_one();
+        }
+
+        log::info!("XCP client closed TCP connection! XCP disconnected.");
+        tokio::task::spawn_blocking(|| unsafe {
+            // @@@@ Unsafe - C library call
+            xcplib::XcpDisconnect();
+        })
+        .await
+        .ok();
+        *stream.lock().await = None;
+    }
+}
+
+// Loopback command receive loop, terminates when the client side is dropped
+async fn loopback_receive_task(mut rx: mpsc::Receiver<Vec<u8>>, notify: Arc<Notify>) -> Result<(), XcpError> {
+    let mut buf = CtoBuffer::new();
+    while let Some(msg) = rx.recv().await {
+        if msg.len() < XCPTL_TRANSPORT_LAYER_HEADER_SIZE || msg.len() > buf.0.len() {
+            log::warn!("XCP async server: corrupt loopback message received, ignored");
+            continue;
+        }
+        buf.0[..msg.len()].copy_from_slice(&msg);
+        if buf.dlc() != msg.len() - XCPTL_TRANSPORT_LAYER_HEADER_SIZE {
+            log::warn!("XCP async server: corrupt loopback message received, ignored");
+            continue;
+        }
+        command(buf).await;
+        notify.notify_one();
+    }
+
+    log::info!("XCP loopback client closed! XCP disconnected.");
+    tokio::task::spawn_blocking(|| unsafe {
+        // @@@@ Unsafe - C library call
+        xcplib::XcpDisconnect();
+    })
+    .await
+    .ok();
+    Ok(())
+}
+
+//----------------------------------------------------------------------------------------------
+// Transmit task
+
+// Drain the transmit queue
+// Waits for queue data, polling the queue level or woken up after a command, and flushes the current message after the flush cycle time
+// Yields to the executor after every XCPTL_QUEUE_MAX_LOOPS messages, to cooperate with other tasks on the same runtime
+async fn transmit_task(transport: Transport, notify: Arc<Notify>, mut shutdown: watch::Receiver<bool>) -> Result<(), XcpError> {
+    loop {
+        // Wait for transmit data available, time out at least for the required flush cycle
+        let flush_time = Instant::now() + XCPTL_QUEUE_FLUSH_CYCLE;
+        // @@@@ Unsafe - C library call
+        while unsafe { xcplib::XcpTlTransmitQueueHasMsg() } == 0 {
+            if Instant::now() >= flush_time {
+                // @@@@ Unsafe - C library call
+                unsafe { xcplib::XcpTlFlushTransmitBuffer() }; // Flush after timeout to keep data visualization going
+                break;
+            }
+            tokio::select! {
+                biased;
+                _ = shutdown.changed() => return Ok(()),
+                () = notify.notified() => {},
+                () = tokio::time::sleep(XCPTL_QUEUE_POLLING_TIME) => {},
+            }
+        }
+
+        // Transmit all completed messages from the transmit queue
+        let mut loops = 0;
+        loop {
+            let mut len: u16 = 0;
+            // @@@@ Unsafe - C library call
+            let msg = unsafe { xcplib::XcpTlTransmitQueuePeekMsg(&mut len) };
+            if msg.is_null() {
+                break; // Queue is empty or not fully committed
+            }
+            // The message stays valid in the queue until XcpTlTransmitQueueNextMsg
+            // @@@@ Unsafe - Slice from a raw pointer into the transmit queue
+            let data = unsafe { std::slice::from_raw_parts(msg, len as usize) };
+            transport.send(data).await?;
+            // @@@@ Unsafe - C library call
+            unsafe { xcplib::XcpTlTransmitQueueNextMsg() };
+
+            loops += 1;
+            if loops % XCPTL_QUEUE_MAX_LOOPS == 0 {
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// XcpLoopback
+
+/// Client side of the in-process loopback transport, see Xcp::start_loopback_server
+/// Both directions carry transport layer segments as on XCP on UDP, commands to the server and responses, events and DTOs from the server
+#[derive(Debug)]
+pub struct XcpLoopback {
+    /// Commands to the server
+    pub tx: mpsc::Sender<Vec<u8>>,
+    /// Segments from the server
+    pub rx: mpsc::Receiver<Vec<u8>>,
+}
+
+//----------------------------------------------------------------------------------------------
+// AsyncServer
+
+pub(crate) struct AsyncServer {
+    running: Arc<AtomicBool>,
+    shutdown: watch::Sender<bool>,
+    receive_task: JoinHandle<()>,
+    transmit_task: JoinHandle<()>,
+}
+
+impl AsyncServer {
+    // Spawn a server task, the running state is cleared when the task terminates on error
+    fn spawn<F>(name: &'static str, running: &Arc<AtomicBool>, f: F) -> JoinHandle<()>
+    where
+        F: std::future::Future<Output = Result<(), XcpError>> + Send + 'static,
+    {
+        let running = Arc::clone(running);
+        tokio::spawn(async move {
+            if let Err(e) = f.await {
+                log::error!("XCP async server {} task failed: {}", name, e);
+            }
+            running.store(false, Ordering::Relaxed);
+        })
+    }
+
+    // Bind the sockets, initialize and start the xcplib transport and protocol layer and spawn the server tasks
+    pub(crate) async fn start(tl: XcpTransportLayer, addr: Ipv4Addr, port: u16, queue_size: u32) -> Result<(AsyncServer, Ipv4Addr), XcpError> {
+        let bind_addr = SocketAddr::new(addr.into(), port);
+        let notify = Arc::new(Notify::new());
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let running = Arc::new(AtomicBool::new(true));
+
+        let (receive_task, transport, local_addr) = match tl {
+            XcpTransportLayer::Udp => {
+                let socket = Arc::new(UdpSocket::bind(bind_addr).await?);
+                let local_addr = socket.local_addr()?;
+                let master = Arc::new(Mutex::new(None));
+                let mut shutdown_rx = shutdown_rx.clone();
+                let f = {
+                    let socket = Arc::clone(&socket);
+                    let master = Arc::clone(&master);
+                    let notify = Arc::clone(&notify);
+                    async move {
+                        tokio::select! {
+                            res = udp_receive_task(socket, master, notify) => res,
+                            _ = shutdown_rx.changed() => Ok(()),
+                        }
+                    }
+                };
+                log::info!("XCP async server listening for XCP commands on UDP {}", local_addr);
+                (AsyncServer::spawn("receive", &running, f), Transport::Udp { socket, master }, local_addr)
+            }
+            XcpTransportLayer::Tcp => {
+                let listener = TcpListener::bind(bind_addr).await?;
+                let local_addr = listener.local_addr()?;
+                let stream = Arc::new(tokio::sync::Mutex::new(None));
+                let mut shutdown_rx = shutdown_rx.clone();
+                let f = {
+                    let stream = Arc::clone(&stream);
+                    let notify = Arc::clone(&notify);
+                    async move {
+                        tokio::select! {
+                            res = tcp_receive_task(listener, stream, notify) => res,
+                            _ = shutdown_rx.changed() => Ok(()),
+                        }
+                    }
+                };
+                log::info!("XCP async server listening for TCP connections on {}", local_addr);
+                (AsyncServer::spawn("receive", &running, f), Transport::Tcp { stream }, local_addr)
+            }
+        };
+
+        if let Err(e) = AsyncServer::init_protocol_layer(queue_size) {
+            receive_task.abort();
+            return Err(e);
+        }
+
+        let transmit_task = AsyncServer::spawn("transmit", &running, transmit_task(transport, notify, shutdown_rx));
+
+        // If bound to any, get the actual ip address
+        let local_ip = match local_addr {
+            SocketAddr::V4(a) if !a.ip().is_unspecified() => *a.ip(),
+            _ => get_local_ip().unwrap_or(Ipv4Addr::LOCALHOST),
+        };
+
+        Ok((
+            AsyncServer {
+                running,
+                shutdown,
+                receive_task,
+                transmit_task,
+            },
+            local_ip,
+        ))
+    }
+
+    // Create the loopback channels, initialize and start the xcplib transport and protocol layer and spawn the server tasks
+    pub(crate) fn start_loopback(queue_size: u32) -> Result<(AsyncServer, XcpLoopback), XcpError> {
+        let notify = Arc::new(Notify::new());
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let running = Arc::new(AtomicBool::new(true));
+        let (client_tx, server_rx) = mpsc::channel(LOOPBACK_CHANNEL_SIZE);
+        let (server_tx, client_rx) = mpsc::channel(LOOPBACK_CHANNEL_SIZE);
+
+        AsyncServer::init_protocol_layer(queue_size)?;
+
+        let mut receive_shutdown_rx = shutdown_rx.clone();
+        let f = {
+            let notify = Arc::clone(&notify);
+            async move {
+                tokio::select! {
+                    res = loopback_receive_task(server_rx, notify) => res,
+                    _ = receive_shutdown_rx.changed() => Ok(()),
+                }
+            }
+        };
+        let receive_task = AsyncServer::spawn("receive", &running, f);
+        let transmit_task = AsyncServer::spawn("transmit", &running, transmit_task(Transport::Loopback { tx: server_tx }, notify, shutdown_rx));
+        log::info!("XCP async server listening for XCP commands on the loopback transport");
+
+        Ok((
+            AsyncServer {
+                running,
+                shutdown,
+                receive_task,
+                transmit_task,
+            },
+            XcpLoopback { tx: client_tx, rx: client_rx },
+        ))
+    }
+
+    // Initialize the XCP transport layer queue and start the protocol layer
+    fn init_protocol_layer(queue_size: u32) -> Result<(), XcpError> {
+        unsafe {
+            // @@@@ Unsafe - C library calls
+            xcplib::XcpInit();
+            if xcplib::XcpTlInit(queue_size) == 0 {
+                return Err(XcpError::XcpLib("Error: XcpTlInit() failed"));
+            }
+            xcplib::XcpStart();
+        }
+        Ok(())
+    }
+
+    // Check the server tasks are running
+    pub(crate) fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    // Disconnect the client, terminate the server tasks and shutdown the transport and protocol layer
+    pub(crate) async fn stop(self) {
+        // Disconnect while the transmit task is still running, to let the transmit queue empty
+        tokio::task::spawn_blocking(|| unsafe {
+            // @@@@ Unsafe - C library call
+            xcplib::XcpDisconnect();
+        })
+        .await
+        .ok();
+
+        self.shutdown.send(true).ok();
+        self.receive_task.await.ok();
+        self.transmit_task.await.ok();
+
+        unsafe {
+            // @@@@ Unsafe - C library calls
+            xcplib::XcpTlShutdown();
+            xcplib::XcpReset();
+        }
+    }
+}
+
+// Get the ip address of the interface used for outgoing traffic
+// Connecting a UDP socket does not send any packets
+fn get_local_ip() -> Option<Ipv4Addr> {
+    let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(8, 8, 8, 8), 80)).ok()?;
+    match socket.local_addr().ok()? {
+        SocketAddr::V4(a) => Some(*a.ip()),
+        SocketAddr::V6(_) => None,
+    }
+}
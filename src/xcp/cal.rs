@@ -12,6 +12,31 @@ use cal_seg::CalPageTrait;
 use cal_seg::CalSeg;
 use cal_seg::CalSegTrait;
 
+// Calibration page snapshot and diff
+pub mod cal_snapshot;
+
+// Calibration parameter change notification
+pub mod cal_observer;
+
+// Audit log of calibration writes
+pub mod cal_audit;
+
+// Guard rails for calibration writes
+pub mod cal_guard;
+use cal_guard::GuardRailsMode;
+
+// Slew rate limited application of calibration writes
+pub mod cal_slew;
+
+// Limit check of loaded calibration pages
+#[cfg(feature = "serde")]
+pub mod cal_limits;
+
+// Calibration page as JSON5 file with field comments
+#[cfg(feature = "json5")]
+pub mod cal_json5;
+use cal_observer::CalSegWriteNotification;
+
 //-----------------------------------------------------------------------------
 
 use crate::reg;
@@ -33,6 +58,11 @@ where
     fn register_fields(&self, calseg_name: &'static str) -> &Self;
 }
 
+// Rust type name is bool or an array of bool
+fn is_bool(datatype: &str) -> bool {
+    datatype.trim_start_matches('[').split(';').next() == Some("bool")
+}
+
 impl<T> RegisterFieldsTrait for T
 where
     T: Sized + Send + Sync + Copy + Clone + 'static + xcp_type_description::XcpTypeDescription,
@@ -40,8 +70,9 @@ where
     fn register_fields(&self, calseg_name: &'static str) -> &Self {
         trace!("Register all fields in {}", calseg_name);
 
+        let verbal_bool = Xcp::get().get_registry().lock().get_verbal_bool();
         for field in self.type_description().unwrap().iter() {
-            let c = reg::RegistryCharacteristic::new(
+            let mut c = reg::RegistryCharacteristic::new(
                 Some(calseg_name),
                 field.name().to_string(),
                 reg::RegistryDataType::from_rust_type(field.datatype()),
@@ -53,6 +84,39 @@ where
                 if field.y_dim() == 0 { 1 } else { field.y_dim() },
                 field.offset() as u64,
             );
+            if !field.x_axis_input().is_empty() {
+                c.set_x_axis_input(field.x_axis_input());
+            }
+            if let Some(step) = field.step() {
+                c.set_step(step);
+            }
+            if !field.format().is_empty() {
+                c.set_format(field.format());
+            }
+            if let Some((min, max)) = field.extended_limits() {
+                c.set_extended_limits(min, max);
+            }
+            if !field.display().is_empty() {
+                c.set_display(field.display());
+            }
+            for ifdata in field.ifdata() {
+                c.add_ifdata(*ifdata);
+            }
+            if is_bool(field.datatype()) && field.verbal_bool().unwrap_or(verbal_bool) {
+                c.set_verbal_bool();
+            }
+            if field.monotone_increasing() {
+                c.set_monotone_increasing();
+            }
+            if !field.function().is_empty() {
+                c.set_function(field.function());
+            }
+            for function in field.ref_functions() {
+                c.add_ref_function(*function);
+            }
+            if let Some(slew_rate) = field.slew_rate() {
+                c.set_slew_rate(slew_rate);
+            }
 
             Xcp::get().get_registry().lock().add_characteristic(c).expect("Duplicate");
         }
@@ -63,15 +127,26 @@ where
 //-----------------------------------------------------------------------------
 // CalSegDescriptor
 
+/// Max size of a memory segment in the A2L file
+/// The offset in a memory segment is the low word of the XCP address, larger calibration pages are split into chunks of this size,
+/// which are registered as consecutive memory segments <name>_0, <name>_1, ...
+pub const MAX_SEGMENT_SIZE: usize = 0x10000;
+
 struct CalSegDescriptor {
     name: &'static str,
     calseg: Arc<Mutex<dyn CalSegTrait>>,
     size: usize,
+    addr_index: usize, // Index of the first memory segment in the XCP address
 }
 
 impl CalSegDescriptor {
     pub fn new(name: &'static str, calseg: Arc<Mutex<dyn CalSegTrait>>, size: usize) -> CalSegDescriptor {
-        CalSegDescriptor { name, calseg, size }
+        CalSegDescriptor {
+            name,
+            calseg,
+            size,
+            addr_index: 0,
+        }
     }
     pub fn get_name(&self) -> &'static str {
         self.name
@@ -79,6 +154,10 @@ impl CalSegDescriptor {
     pub fn get_size(&self) -> usize {
         self.size
     }
+    // Number of memory segments of this calibration segment
+    pub fn get_chunk_count(&self) -> usize {
+        self.size.div_ceil(MAX_SEGMENT_SIZE)
+    }
     pub fn set_init_request(&mut self) {
         self.calseg.lock().set_init_request();
     }
@@ -86,6 +165,60 @@ impl CalSegDescriptor {
     pub fn set_freeze_request(&mut self) {
         self.calseg.lock().set_freeze_request();
     }
+
+    pub fn is_dirty(&self) -> bool {
+        self.calseg.lock().is_dirty()
+    }
+}
+
+//-----------------------------------------------------------------------------
+// CalWriteStatus
+
+/// Result of a calibration write of the XCP tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalWriteStatus {
+    Ok,
+    /// Write to the default page or invalid range
+    AccessDenied,
+    /// Rejected by the guard rails, see GuardRailsMode
+    WriteProtected,
+}
+
+impl CalWriteStatus {
+    fn from_ok(ok: bool) -> CalWriteStatus {
+        if ok {
+            CalWriteStatus::Ok
+        } else {
+            CalWriteStatus::AccessDenied
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+// EpkSegment
+
+/// Index of the read only EPK memory segment in the XCP address, the memory segments of the calibration segments follow
+pub const EPK_SEGMENT_INDEX: usize = 0;
+
+// Read only memory segment with the EPK string
+struct EpkSegment {
+    epk: &'static str,
+}
+
+impl EpkSegment {
+    // Read from the EPK string
+    // # Safety
+    // Raw pointer dst must point to valid memory with len bytes size
+    // @@@@ Unsafe - direct memory access with pointer arithmetic
+    unsafe fn read(&self, offset: u32, len: u8, dst: *mut u8) -> bool {
+        if offset as usize + len as usize > self.epk.len() {
+            warn!("EPK read out of range: offset={} len={} epk_len={}", offset, len, self.epk.len());
+            return false;
+        }
+        let src = self.epk.as_ptr().add(offset as usize);
+        std::ptr::copy_nonoverlapping(src, dst, len as usize);
+        true
+    }
 }
 
 //-----------------------------------------------------------------------------
@@ -94,27 +227,32 @@ impl CalSegDescriptor {
 /// Calibration segment descriptor list
 /// The Xcp singleton holds this type
 /// Calibration segments are created via the Xcp singleton
-pub struct CalSegList(Vec<CalSegDescriptor>);
+/// The first memory segment in the XCP address is the read only EPK, the calibration segments follow
+pub struct CalSegList {
+    list: Vec<CalSegDescriptor>,
+    epk: EpkSegment,
+}
 
 impl CalSegList {
     /// Create a calibration segment  
+    /// Calibration pages larger than MAX_SEGMENT_SIZE are registered as multiple memory segments
     /// # Panics  
     /// Panics if the calibration segment name already exists  
-    /// Panics if the calibration page size exceeds 64k or is ZeroSized
+    /// Panics if the calibration page is ZeroSized
     pub fn create_calseg<T>(&mut self, name: &'static str, default_page: &'static T) -> CalSeg<T>
     where
         T: CalPageTrait,
     {
         // Check size of calibration page
-        assert!(std::mem::size_of::<T>() <= 0x10000 && std::mem::size_of::<T>() != 0, "CalPage size is 0 or exceeds 64k");
+        assert!(std::mem::size_of::<T>() != 0, "CalPage size is 0");
 
         // Check for duplicate name
-        self.0.iter().for_each(|s| {
+        self.list.iter().for_each(|s| {
             assert!(s.get_name() != name, "CalSeg {} already exists", name);
         });
 
         // Create the calibration segment
-        let index = self.0.len();
+        let index = self.list.len();
         let calseg = CalSeg::new(index, *default_page, default_page);
 
         // Create the calibration segment descriptor
@@ -123,26 +261,28 @@ impl CalSegList {
         let calseg_descr = CalSegDescriptor::new(name, a, std::mem::size_of::<T>());
 
         // Add the calibration segment descriptor to the list
-        self.0.push(calseg_descr);
+        self.list.push(calseg_descr);
+        self.update_indices();
 
         info!(
-            "Create CalSeg: {} index={}, clone_count={}, sizeof<Page>={}, sizeof<CalSeg>={}",
+            "Create CalSeg: {} index={}, clone_count={}, sizeof<Page>={}, sizeof<CalSeg>={}, memory segments={}",
             name,
             index,
             calseg.get_clone_count(),
             std::mem::size_of::<T>(),
-            std::mem::size_of::<CalSeg<T>>()
+            std::mem::size_of::<CalSeg<T>>(),
+            self.list[index].get_chunk_count()
         );
 
         calseg
     }
 
     pub fn get_name(&self, i: usize) -> &'static str {
-        self.0[i].get_name()
+        self.list[i].get_name()
     }
 
     pub fn get_index(&self, name: &str) -> Option<usize> {
-        for (i, s) in self.0.iter().enumerate() {
+        for (i, s) in self.list.iter().enumerate() {
             if s.get_name() == name {
                 return Some(i);
             }
@@ -150,11 +290,31 @@ impl CalSegList {
         None
     }
 
-    pub fn sort_by_name(&mut self) {
-        self.0.sort_by(|a, b| a.get_name().cmp(b.get_name()));
-        self.0.iter_mut().enumerate().for_each(|(i, s)| {
+    // Renumber the calibration segments and assign the indices of their memory segments in the XCP address
+    // A calibration segment larger than MAX_SEGMENT_SIZE occupies one index for each chunk
+    fn update_indices(&mut self) {
+        let mut addr_index = 0;
+        self.list.iter_mut().enumerate().for_each(|(i, s)| {
             s.calseg.lock().set_index(i);
+            s.addr_index = addr_index;
+            addr_index += s.get_chunk_count();
         });
+        assert!(addr_index < 0x7FFF, "Too many calibration memory segments");
+    }
+
+    // Remove a calibration segment, the remaining segments are renumbered
+    pub fn remove_calseg(&mut self, name: &str) -> bool {
+        let Some(index) = self.get_index(name) else {
+            return false;
+        };
+        self.list.remove(index);
+        self.update_indices();
+        true
+    }
+
+    pub fn sort_by_name(&mut self) {
+        self.list.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        self.update_indices();
     }
 
     pub fn register(&mut self) {
@@ -162,27 +322,77 @@ impl CalSegList {
         self.sort_by_name();
 
         // Register all calibration segments in the registry
-        // Address is index<<16, addr_ext is 0
-        for (i, d) in self.0.iter().enumerate() {
+        // Address is index<<16, addr_ext is 0, the registry splits large calibration segments into memory segments with consecutive indices
+        for (i, d) in self.list.iter().enumerate() {
             trace!("Register CalSeg {}, size={}", d.get_name(), d.get_size());
             assert!(i == d.calseg.lock().get_index());
             Xcp::get()
                 .get_registry()
                 .lock()
-                .add_cal_seg(d.get_name(), i.try_into().unwrap(), d.get_size().try_into().unwrap());
+                .add_cal_seg(d.get_name(), d.addr_index.try_into().unwrap(), d.get_size().try_into().unwrap());
         }
     }
 
+    // Set the EPK string of the read only EPK memory segment
+    pub fn set_epk(&mut self, epk: &'static str) {
+        self.epk.epk = epk;
+    }
+
+    // Get the calibration segment index and the offset in its calibration page from the memory segment index and offset of an XCP address
+    // The read only EPK memory segment is not a calibration segment
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn find_addr(&self, addr_index: usize, offset: u16) -> Option<(usize, u32)> {
+        let addr_index = addr_index.checked_sub(EPK_SEGMENT_INDEX + 1)?;
+        let index = self
+            .list
+            .iter()
+            .position(|s| addr_index >= s.addr_index && addr_index < s.addr_index + s.get_chunk_count())?;
+        let chunk = addr_index - self.list[index].addr_index;
+        Some((index, (chunk * MAX_SEGMENT_SIZE) as u32 + offset as u32))
+    }
+
     pub fn clear(&mut self) {
-        self.0.clear();
+        self.list.clear();
     }
 
     pub fn set_freeze_request(&mut self) {
-        self.0.iter_mut().for_each(CalSegDescriptor::set_freeze_request);
+        self.list.iter_mut().for_each(CalSegDescriptor::set_freeze_request);
     }
 
     pub fn set_init_request(&mut self) {
-        self.0.iter_mut().for_each(CalSegDescriptor::set_init_request);
+        self.list.iter_mut().for_each(CalSegDescriptor::set_init_request);
+    }
+
+    // Set the freeze request of a single calibration segment, false if the name is unknown
+    pub fn set_calseg_freeze_request(&mut self, name: &str) -> bool {
+        match self.list.iter_mut().find(|s| s.get_name() == name) {
+            Some(s) => {
+                s.set_freeze_request();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Set the init request of a single calibration segment, false if the name is unknown
+    pub fn set_calseg_init_request(&mut self, name: &str) -> bool {
+        match self.list.iter_mut().find(|s| s.get_name() == name) {
+            Some(s) => {
+                s.set_init_request();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Get the names of all calibration segments in index order
+    pub fn get_names(&self) -> Vec<&'static str> {
+        self.list.iter().map(CalSegDescriptor::get_name).collect()
+    }
+
+    // Get the names of all calibration segments modified since their last save or freeze
+    pub fn changed(&self) -> Vec<&'static str> {
+        self.list.iter().filter(|s| s.is_dirty()).map(CalSegDescriptor::get_name).collect()
     }
 
     // Read from xcp_page or default_page depending on the active XCP page
@@ -193,8 +403,22 @@ impl CalSegList {
     // Invalid calibration segment index
     // offset out of calibration segment boundaries
     // @@@@ Unsafe - direct memory access with pointer arithmetic
-    pub unsafe fn read_from(&self, index: usize, offset: u16, len: u8, dst: *mut u8) -> bool {
-        self.0[index].calseg.lock().read(offset, len, dst)
+    pub unsafe fn read_from(&self, index: usize, offset: u32, len: u8, dst: *mut u8) -> bool {
+        self.list[index].calseg.lock().read(offset, len, dst)
+    }
+
+    // Read from a memory segment of the XCP address, the EPK or a calibration segment
+    // # Safety
+    // Raw pointer dst must point to valid memory with len bytes size
+    // @@@@ Unsafe - direct memory access with pointer arithmetic
+    pub unsafe fn read_addr(&self, addr_index: usize, offset: u16, len: u8, dst: *mut u8) -> bool {
+        if addr_index == EPK_SEGMENT_INDEX {
+            return self.epk.read(offset as u32, len, dst);
+        }
+        match self.find_addr(addr_index, offset) {
+            Some((index, offset)) => self.read_from(index, offset, len, dst),
+            None => false,
+        }
     }
 
     // Write to xcp_page
@@ -205,19 +429,56 @@ impl CalSegList {
     // Invalid calibration segment index
     // offset out of calibration segment boundaries
     // @@@@ Unsafe - direct memory access with pointer arithmetic
-    pub unsafe fn write_to(&self, index: usize, offset: u16, len: u8, src: *const u8, delay: u8) -> bool {
-        self.0[index].calseg.lock().write(offset, len, src, delay)
+    // Writes to monotone axes are checked and maybe clamped according to the guard rails mode, before they are written
+    // Returns the pending change notification, if the calibration segment has observers or audit is requested
+    // The notification must be delivered after the calibration segment list lock is released
+    pub unsafe fn write_to(&self, index: usize, offset: u32, len: u8, src: *const u8, delay: u8, audit: bool) -> (CalWriteStatus, Option<CalSegWriteNotification>) {
+        // Collect the monotone axes before the calibration segment is locked, the registry lock is never taken while holding a calibration segment lock
+        let guard_rails = Xcp::get().get_guard_rails();
+        let axes = match guard_rails {
+            GuardRailsMode::Off => Vec::new(),
+            _ => cal_guard::get_monotone_axes(self.list[index].get_name(), offset as usize, len as usize),
+        };
+
+        let calseg = self.list[index].calseg.lock();
+        let clamped: Vec<u8>;
+        let mut src = src;
+        if !axes.is_empty() {
+            let mut bytes = std::slice::from_raw_parts(src, len as usize).to_vec();
+            for axis in &axes {
+                if !axis.check_write(&*calseg, offset as usize, &mut bytes, guard_rails == GuardRailsMode::MonotonicityClamped) {
+                    return (CalWriteStatus::WriteProtected, None);
+                }
+            }
+            clamped = bytes;
+            src = clamped.as_ptr();
+        }
+
+        let observers = calseg.get_observers();
+        if observers.is_empty() && !audit {
+            return (CalWriteStatus::from_ok(calseg.write(offset, len, src, delay)), None);
+        }
+        let mut old_bytes = vec![0u8; len as usize];
+        if !calseg.read(offset, len, old_bytes.as_mut_ptr()) || !calseg.write(offset, len, src, delay) {
+            return (CalWriteStatus::AccessDenied, None);
+        }
+        let new_bytes = std::slice::from_raw_parts(src, len as usize).to_vec();
+        let notification = CalSegWriteNotification::new(self.list[index].get_name(), offset as usize, old_bytes, new_bytes, observers);
+        (CalWriteStatus::Ok, Some(notification))
     }
 
     // Flush delayed modifications in all calibration segments
     pub fn flush(&self) {
-        self.0.iter().for_each(|s| {
+        self.list.iter().for_each(|s| {
             s.calseg.lock().flush();
         });
     }
 
     pub fn new() -> CalSegList {
-        CalSegList(Vec::new())
+        CalSegList {
+            list: Vec::new(),
+            epk: EpkSegment { epk: "DEFAULT_EPK" },
+        }
     }
 }
 
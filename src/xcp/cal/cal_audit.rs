@@ -0,0 +1,217 @@
+//----------------------------------------------------------------------------------------------
+// Module cal_audit
+// Audit log of the calibration parameter changes by the XCP tool
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::cal_snapshot::{element_name, McValue};
+use crate::reg::RegistryDataType;
+use crate::xcp::Xcp;
+
+// Number of pending audit records, further changes are dropped and counted, when the audit sink can not keep up
+const CAL_AUDIT_QUEUE_SIZE: usize = 256;
+
+//----------------------------------------------------------------------------------------------
+// CalChange
+
+/// A calibration write of the XCP tool, recorded by the calibration audit sink (Xcp::set_cal_audit_sink)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalChange {
+    /// Time of the write
+    pub timestamp: SystemTime,
+    /// Name of the calibration segment
+    pub calseg_name: &'static str,
+    /// Registered calibration parameters overlapping the written range, with index suffix [x] or [x][y], if the write is within a single array element
+    /// The calibration segment name, if no calibration parameter is registered at the written range
+    pub names: Vec<String>,
+    /// Byte offset of the write in the calibration segment
+    pub offset: usize,
+    /// Number of bytes written
+    pub len: usize,
+    /// Written range before the write
+    pub old_bytes: Vec<u8>,
+    /// Written range after the write
+    pub new_bytes: Vec<u8>,
+    /// Physical values before and after the write, if the written range is exactly one calibration parameter or array element
+    pub old_value: Option<McValue>,
+    pub new_value: Option<McValue>,
+}
+
+/// Receiver of the calibration audit records, called on the calibration audit thread in the order of the writes
+pub type CalAuditSink = Box<dyn Fn(CalChange) + Send + Sync>;
+
+//----------------------------------------------------------------------------------------------
+// CalAudit
+
+// Raw calibration write, queued on the XCP server thread, name resolution is done on the audit thread
+struct CalWrite {
+    timestamp: SystemTime,
+    calseg_name: &'static str,
+    offset: usize,
+    old_bytes: Vec<u8>,
+    new_bytes: Vec<u8>,
+}
+
+// Bounded queue and thread which resolve the calibration writes and pass them to the audit sink
+pub(crate) struct CalAudit {
+    sender: Option<SyncSender<CalWrite>>,
+    thread: Option<JoinHandle<()>>,
+    dropped: AtomicU64,
+}
+
+impl CalAudit {
+    pub(crate) fn new(sink: CalAuditSink) -> CalAudit {
+        let (sender, receiver) = sync_channel(CAL_AUDIT_QUEUE_SIZE);
+        let thread = std::thread::Builder::new()
+            .name("cal_audit".to_string())
+            .spawn(move || CalAudit::run(&receiver, &sink))
+            .expect("could not spawn the calibration audit thread");
+        CalAudit {
+            sender: Some(sender),
+            thread: Some(thread),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn run(receiver: &Receiver<CalWrite>, sink: &CalAuditSink) {
+        while let Ok(write) = receiver.recv() {
+            sink(resolve(write));
+        }
+    }
+
+    // Queue a calibration write, never blocks
+    pub(crate) fn record(&self, calseg_name: &'static str, offset: usize, old_bytes: &[u8], new_bytes: &[u8]) {
+        let write = CalWrite {
+            timestamp: SystemTime::now(),
+            calseg_name,
+            offset,
+            old_bytes: old_bytes.to_vec(),
+            new_bytes: new_bytes.to_vec(),
+        };
+        if self.sender.as_ref().unwrap().try_send(write).is_err() && self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+            warn!("Calibration audit queue overflow, changes are dropped");
+        }
+    }
+
+    pub(crate) fn get_dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+// Deliver all queued records and stop the audit thread
+impl Drop for CalAudit {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Calibration audit sink panicked");
+            }
+        }
+    }
+}
+
+// Resolve the names of the calibration parameters overlapping the written range and decode the values of a single parameter or array element
+#[allow(clippy::cast_possible_truncation)]
+fn resolve(write: CalWrite) -> CalChange {
+    let len = write.old_bytes.len();
+    let mut names = Vec::new();
+    let mut value_type = None;
+    {
+        let registry = Xcp::get().get_registry();
+        let registry = registry.lock();
+        for c in registry.get_cal_seg_characteristics(write.calseg_name) {
+            if c.get_alias_of().is_some() {
+                continue;
+            }
+            let datatype = c.get_datatype();
+            let size = datatype.get_size();
+            let (x_dim, y_dim) = c.get_dim();
+            let start = c.get_addr_offset() as usize;
+            let end = start + size * x_dim * y_dim;
+            if end <= write.offset || start >= write.offset + len {
+                continue;
+            }
+            if datatype == RegistryDataType::Blob || size == 0 {
+                names.push(c.get_name().to_string());
+                continue;
+            }
+            let first = (write.offset.max(start) - start) / size;
+            let last = ((write.offset + len).min(end) - 1 - start) / size;
+            if first == last {
+                names.push(element_name(c.get_name(), x_dim, y_dim, first));
+                if write.offset == start + first * size && len == size {
+                    value_type = Some(datatype);
+                }
+            } else {
+                names.push(c.get_name().to_string());
+            }
+        }
+    }
+    if names.len() != 1 {
+        value_type = None;
+    }
+    if names.is_empty() {
+        names.push(write.calseg_name.to_string());
+    }
+    CalChange {
+        timestamp: write.timestamp,
+        calseg_name: write.calseg_name,
+        names,
+        offset: write.offset,
+        len,
+        old_value: value_type.and_then(|t| McValue::decode(t, &write.old_bytes)),
+        new_value: value_type.and_then(|t| McValue::decode(t, &write.new_bytes)),
+        old_bytes: write.old_bytes,
+        new_bytes: write.new_bytes,
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// CSV file sink
+
+/// Create a calibration audit sink, which appends each change as a line to a CSV file
+/// Columns are timestamp (s since the UNIX epoch), calibration segment, parameter names (separated by ';'), offset, length, old and new bytes (hex) and old and new value
+/// An existing file is overwritten, each line is written immediately
+pub fn cal_audit_csv_sink<P: AsRef<Path>>(path: P) -> std::io::Result<CalAuditSink> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "timestamp,segment,names,offset,len,old_bytes,new_bytes,old_value,new_value")?;
+    let file = parking_lot::Mutex::new(file);
+    Ok(Box::new(move |change: CalChange| {
+        let line = csv_line(&change);
+        if let Err(e) = file.lock().write_all(line.as_bytes()) {
+            error!("Calibration audit: write to CSV file failed: {}", e);
+        }
+    }))
+}
+
+fn csv_line(change: &CalChange) -> String {
+    let timestamp = change.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    let hex = |bytes: &[u8]| {
+        bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+            let _ = write!(s, "{:02X}", b);
+            s
+        })
+    };
+    let value = |v: Option<McValue>| v.map(|v| v.to_string()).unwrap_or_default();
+    format!(
+        "{:.6},{},{},{},{},{},{},{},{}\n",
+        timestamp,
+        change.calseg_name,
+        change.names.join(";"),
+        change.offset,
+        change.len,
+        hex(&change.old_bytes),
+        hex(&change.new_bytes),
+        value(change.old_value),
+        value(change.new_value)
+    )
+}
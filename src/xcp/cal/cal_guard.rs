@@ -0,0 +1,175 @@
+//----------------------------------------------------------------------------------------------
+// Module cal_guard
+// Guard rails for calibration writes of the XCP tool
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use super::cal_seg::CalSegTrait;
+use super::cal_snapshot::McValue;
+use crate::reg::RegistryDataType;
+use crate::xcp::Xcp;
+
+//----------------------------------------------------------------------------------------------
+// GuardRailsMode
+
+/// Checks of the calibration writes of the XCP tool, done before the written bytes become visible to the application
+/// Axes with the type description attribute axis = "monotone_increasing" must stay strictly increasing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GuardRailsMode {
+    /// No checks
+    Off,
+    /// Writes which violate the monotony of an axis are rejected with CRC_WRITE_PROTECTED, the axis is not modified
+    #[default]
+    Monotonicity,
+    /// Written axis values are clamped to the previous value plus the minimal increment and to the next value minus the minimal increment
+    /// The minimal increment is the step size of the axis, 1 for integer types or the next representable value for float types
+    /// Writes which can not be clamped are rejected with CRC_WRITE_PROTECTED
+    MonotonicityClamped,
+}
+
+//----------------------------------------------------------------------------------------------
+// MonotoneAxis
+
+// Strictly increasing axis of a calibration segment
+pub(crate) struct MonotoneAxis {
+    name: String,
+    datatype: RegistryDataType,
+    offset: usize, // Offset of the first element in the calibration segment
+    dim: usize,
+    step: Option<f64>,
+}
+
+// Get the monotone axes of the calibration segment calseg_name, which overlap the written range
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn get_monotone_axes(calseg_name: &str, offset: usize, len: usize) -> Vec<MonotoneAxis> {
+    let registry = Xcp::get().get_registry();
+    let registry = registry.lock();
+    registry
+        .get_cal_seg_characteristics(calseg_name)
+        .into_iter()
+        .filter(|c| c.is_monotone_increasing() && c.get_alias_of().is_none())
+        .map(|c| MonotoneAxis {
+            name: c.get_name().to_string(),
+            datatype: c.get_datatype(),
+            offset: c.get_addr_offset() as usize,
+            dim: c.get_dim().0,
+            step: c.get_step(),
+        })
+        .filter(|a| a.offset < offset + len && a.offset + a.dim * a.datatype.get_size() > offset)
+        .collect()
+}
+
+impl MonotoneAxis {
+    // Check a write of bytes at offset against the current axis values in calseg
+    // Returns false, if the write must be rejected
+    // In clamp mode, written values which violate the monotony are clamped in bytes
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn check_write(&self, calseg: &dyn CalSegTrait, offset: usize, bytes: &mut [u8], clamp: bool) -> bool {
+        let size = self.datatype.get_size();
+
+        // Current axis values with the write applied
+        let mut axis = vec![0u8; self.dim * size];
+        for (i, chunk) in axis.chunks_mut(128).enumerate() {
+            // @@@@ Unsafe - direct memory access with pointer arithmetic, the axis is within the calibration segment
+            if !unsafe { calseg.read((self.offset + i * 128) as u32, chunk.len() as u8, chunk.as_mut_ptr()) } {
+                return false;
+            }
+        }
+        let start = offset.max(self.offset);
+        let end = (offset + bytes.len()).min(self.offset + axis.len());
+        axis[start - self.offset..end - self.offset].copy_from_slice(&bytes[start - offset..end - offset]);
+        let mut values = self.decode(&axis);
+        if is_increasing(&values) {
+            return true;
+        }
+
+        // Clamp whole written elements, starting with the first one
+        let first = (start - self.offset) / size;
+        let last = (end - self.offset - 1) / size;
+        if clamp && (start - self.offset) % size == 0 && (end - self.offset) % size == 0 {
+            for i in first..=last {
+                if i > 0 {
+                    values[i] = values[i].max(self.next_up(values[i - 1]));
+                }
+                if i == last && i + 1 < self.dim {
+                    values[i] = values[i].min(self.next_down(values[i + 1]));
+                }
+                McValue::Float(values[i]).encode(self.datatype, &mut axis[i * size..(i + 1) * size]);
+            }
+            if is_increasing(&self.decode(&axis)) {
+                warn!("{}: written values clamped to keep the axis strictly increasing", self.name);
+                bytes[start - offset..end - offset].copy_from_slice(&axis[start - self.offset..end - self.offset]);
+                return true;
+            }
+        }
+        warn!("{}[{}..{}]: write rejected, the axis would not be strictly increasing", self.name, first, last + 1);
+        false
+    }
+
+    fn decode(&self, axis: &[u8]) -> Vec<f64> {
+        axis.chunks(self.datatype.get_size())
+            .map(|b| McValue::decode(self.datatype, b).map_or(f64::NAN, McValue::as_f64))
+            .collect()
+    }
+
+    // Smallest value greater than v with the minimal increment
+    #[allow(clippy::cast_possible_truncation)]
+    fn next_up(&self, v: f64) -> f64 {
+        match (self.datatype, self.step) {
+            (RegistryDataType::Float32Ieee, None) => next_f32(v as f32, true) as f64,
+            (RegistryDataType::Float64Ieee, None) => next_f64(v, true),
+            (RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee, Some(step)) => v + step,
+            (_, step) => (v + step.unwrap_or(1.0)).ceil(),
+        }
+    }
+
+    // Largest value less than v with the minimal increment
+    #[allow(clippy::cast_possible_truncation)]
+    fn next_down(&self, v: f64) -> f64 {
+        match (self.datatype, self.step) {
+            (RegistryDataType::Float32Ieee, None) => next_f32(v as f32, false) as f64,
+            (RegistryDataType::Float64Ieee, None) => next_f64(v, false),
+            (RegistryDataType::Float32Ieee | RegistryDataType::Float64Ieee, Some(step)) => v - step,
+            (_, step) => (v - step.unwrap_or(1.0)).floor(),
+        }
+    }
+}
+
+// NaN is never part of a strictly increasing axis
+fn is_increasing(values: &[f64]) -> bool {
+    values.windows(2).all(|w| w[0] < w[1])
+}
+
+// Next representable float value up or down, f32::next_up requires Rust 1.86
+fn next_f32(v: f32, up: bool) -> f32 {
+    if !v.is_finite() {
+        v
+    } else if v == 0.0 {
+        if up {
+            f32::from_bits(1)
+        } else {
+            -f32::from_bits(1)
+        }
+    } else if (v > 0.0) == up {
+        f32::from_bits(v.to_bits() + 1)
+    } else {
+        f32::from_bits(v.to_bits() - 1)
+    }
+}
+
+fn next_f64(v: f64, up: bool) -> f64 {
+    if !v.is_finite() {
+        v
+    } else if v == 0.0 {
+        if up {
+            f64::from_bits(1)
+        } else {
+            -f64::from_bits(1)
+        }
+    } else if (v > 0.0) == up {
+        f64::from_bits(v.to_bits() + 1)
+    } else {
+        f64::from_bits(v.to_bits() - 1)
+    }
+}
@@ -0,0 +1,59 @@
+//----------------------------------------------------------------------------------------------
+// Module cal_json5
+// Calibration page as JSON5 file with the type description of each field as comment
+
+use std::fmt::Write;
+
+use xcp_type_description::{FieldDescriptor, StructDescriptor};
+
+// Indentation of nested objects
+const INDENT: &str = "  ";
+
+// Comment line of a field "// <comment> [unit] min=<min> max=<max>"
+fn field_comment(field: &FieldDescriptor) -> String {
+    let mut s = String::from("//");
+    let comment = field.comment().lines().collect::<Vec<_>>().join(" ");
+    if !comment.is_empty() {
+        write!(s, " {}", comment).unwrap();
+    }
+    if !field.unit().is_empty() {
+        write!(s, " [{}]", field.unit()).unwrap();
+    }
+    write!(s, " min={} max={}", field.min(), field.max()).unwrap();
+    s
+}
+
+// Write the members of an object, each leaf value is paired with the next field of the type description
+// Nested structs are nested objects in serde and flattened in the type description, both in declaration order
+// A field is only used as comment, if its name ends with the member name
+fn write_object(s: &mut String, object: &serde_json::Map<String, serde_json::Value>, fields: &mut std::slice::Iter<FieldDescriptor>, depth: usize) {
+    s.push_str("{\n");
+    let indent = INDENT.repeat(depth + 1);
+    for (i, (key, value)) in object.iter().enumerate() {
+        let key_json = serde_json::Value::String(key.clone());
+        if let serde_json::Value::Object(inner) = value {
+            write!(s, "{}{}: ", indent, key_json).unwrap();
+            write_object(s, inner, fields, depth + 1);
+        } else {
+            if let Some(field) = fields.next() {
+                if field.name().rsplit('.').next() == Some(key.as_str()) {
+                    writeln!(s, "{}{}", indent, field_comment(field)).unwrap();
+                }
+            }
+            write!(s, "{}{}: {}", indent, key_json, value).unwrap();
+        }
+        s.push_str(if i + 1 < object.len() { ",\n" } else { "\n" });
+    }
+    write!(s, "{}}}", INDENT.repeat(depth)).unwrap();
+}
+
+/// Format a serialized calibration page as JSON5, with a comment line before each field from its type description
+pub fn to_json5_with_comments(value: &serde_json::Value, fields: &StructDescriptor) -> String {
+    let mut s = String::new();
+    match value {
+        serde_json::Value::Object(object) => write_object(&mut s, object, &mut fields.iter(), 0),
+        value => write!(s, "{}", value).unwrap(),
+    }
+    s.push('\n');
+    s
+}
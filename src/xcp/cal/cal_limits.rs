@@ -0,0 +1,101 @@
+//----------------------------------------------------------------------------------------------
+// Module cal_limits
+// Check of loaded calibration pages against the limits of the registered calibration parameters
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use thiserror::Error;
+
+use super::cal_snapshot::{element_name, McValue};
+use crate::reg::RegistryDataType;
+use crate::xcp::Xcp;
+
+//----------------------------------------------------------------------------------------------
+// CalLoadPolicy
+
+/// Handling of calibration parameter values out of their registered limits in CalSeg::load_checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalLoadPolicy {
+    /// Load the values unchanged, only report them
+    Accept,
+    /// Clamp the values to the limits
+    Clamp,
+    /// Reject the file, the calibration page is not modified
+    Reject,
+}
+
+//----------------------------------------------------------------------------------------------
+// CalLimitViolation
+
+/// A calibration parameter or array element with a value out of its registered limits
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalLimitViolation {
+    pub name: String, // Name of the calibration parameter, with index suffix [x] or [x][y] for array elements
+    pub value: McValue,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl std::fmt::Display for CalLimitViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {} out of range [{}, {}]", self.name, self.value, self.min, self.max)
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// CalLoadError
+
+#[derive(Error, Debug)]
+pub enum CalLoadError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{} values out of range", .0.len())]
+    OutOfRange(Vec<CalLimitViolation>),
+}
+
+//----------------------------------------------------------------------------------------------
+
+// Check the values of a calibration page of the calibration segment calseg_name against the limits of its registered calibration parameters
+// Returns all values out of range, sorted by offset, clamps them in page if clamp is true
+// Calibration parameters without limits (min and max 0), blobs and aliases are not checked
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn check_limits(calseg_name: &'static str, page: &mut [u8], clamp: bool) -> Vec<CalLimitViolation> {
+    let mut violations = Vec::new();
+    let registry = Xcp::get().get_registry();
+    let registry = registry.lock();
+    for c in registry.get_cal_seg_characteristics(calseg_name) {
+        let datatype = c.get_datatype();
+        let (min, max) = c.get_limits();
+        if c.get_alias_of().is_some() || datatype == RegistryDataType::Blob || (min == 0.0 && max == 0.0) {
+            continue;
+        }
+        let size = datatype.get_size();
+        let (x_dim, y_dim) = c.get_dim();
+        for i in 0..x_dim * y_dim {
+            let offset = c.get_addr_offset() as usize + i * size;
+            let Some(bytes) = page.get_mut(offset..offset + size) else {
+                warn!("{} exceeds the size of calibration segment {}", c.get_name(), calseg_name);
+                break;
+            };
+            let Some(value) = McValue::decode(datatype, bytes) else {
+                break;
+            };
+            let v = value.as_f64();
+            if v >= min && v <= max {
+                continue;
+            }
+            if clamp {
+                value.clamp(min, max).encode(datatype, bytes);
+            }
+            violations.push(CalLimitViolation {
+                name: element_name(c.get_name(), x_dim, y_dim, i),
+                value,
+                min,
+                max,
+            });
+        }
+    }
+    violations
+}
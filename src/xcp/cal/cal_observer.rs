@@ -0,0 +1,133 @@
+//----------------------------------------------------------------------------------------------
+// Module cal_observer
+// Notification of calibration parameter changes by the XCP tool
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+use super::cal_audit::CalAudit;
+use crate::xcp::Xcp;
+
+//----------------------------------------------------------------------------------------------
+// CalSegObserver
+
+/// Observer of the calibration parameter changes of a calibration segment, subscribed with CalSeg::subscribe
+/// on_change is called after each successful XCP write, for each calibration parameter touched by the write
+/// field_name is the registered name of the calibration parameter, or the calibration segment name, if no parameter is registered at the written range
+/// old_bytes and new_bytes are the written range of the parameter before and after the write
+/// Called on the XCP server thread after the write is complete and all calibration segment locks are released, must not block
+pub trait CalSegObserver: Send + Sync {
+    fn on_change(&self, field_name: &str, old_bytes: &[u8], new_bytes: &[u8]);
+}
+
+//----------------------------------------------------------------------------------------------
+// CalSegChange
+
+/// A calibration parameter change notified by ChannelObserver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalSegChange {
+    pub field_name: String,
+    pub old_bytes: Vec<u8>,
+    pub new_bytes: Vec<u8>,
+}
+
+//----------------------------------------------------------------------------------------------
+// ChannelObserver
+
+/// Calibration segment observer which forwards the changes to a channel
+/// Notifications are dropped, when the receiver is gone
+#[derive(Debug)]
+pub struct ChannelObserver {
+    sender: Sender<CalSegChange>,
+}
+
+impl ChannelObserver {
+    pub fn new(sender: Sender<CalSegChange>) -> ChannelObserver {
+        ChannelObserver { sender }
+    }
+}
+
+impl CalSegObserver for ChannelObserver {
+    fn on_change(&self, field_name: &str, old_bytes: &[u8], new_bytes: &[u8]) {
+        let change = CalSegChange {
+            field_name: field_name.to_string(),
+            old_bytes: old_bytes.to_vec(),
+            new_bytes: new_bytes.to_vec(),
+        };
+        if self.sender.send(change).is_err() {
+            trace!("ChannelObserver: receiver dropped, change of {} not delivered", field_name);
+        }
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// CalSegWriteNotification
+
+// Pending notification of the observers of a calibration segment about an XCP write
+// Created while the calibration segment locks are held, delivered after they are released
+pub(crate) struct CalSegWriteNotification {
+    calseg_name: &'static str,
+    offset: usize,
+    old_bytes: Vec<u8>,
+    new_bytes: Vec<u8>,
+    observers: Vec<Arc<dyn CalSegObserver>>,
+}
+
+impl CalSegWriteNotification {
+    pub(crate) fn new(calseg_name: &'static str, offset: usize, old_bytes: Vec<u8>, new_bytes: Vec<u8>, observers: Vec<Arc<dyn CalSegObserver>>) -> CalSegWriteNotification {
+        CalSegWriteNotification {
+            calseg_name,
+            offset,
+            old_bytes,
+            new_bytes,
+            observers,
+        }
+    }
+
+    // Record the write in the calibration audit log
+    pub(crate) fn audit(&self, audit: &CalAudit) {
+        audit.record(self.calseg_name, self.offset, &self.old_bytes, &self.new_bytes);
+    }
+
+    // Notify all observers, once for each calibration parameter touched by the write
+    pub(crate) fn notify(self) {
+        for (field_name, range) in get_changed_fields(self.calseg_name, self.offset, self.old_bytes.len()) {
+            let range = range.start - self.offset..range.end - self.offset;
+            for observer in &self.observers {
+                observer.on_change(&field_name, &self.old_bytes[range.clone()], &self.new_bytes[range.clone()]);
+            }
+        }
+    }
+}
+
+// Registered calibration parameters of the segment overlapping the written range [offset, offset+len) with their range
+// Falls back to the calibration segment name for unregistered ranges
+#[allow(clippy::cast_possible_truncation)]
+fn get_changed_fields(calseg_name: &'static str, offset: usize, len: usize) -> Vec<(String, std::ops::Range<usize>)> {
+    let registry = Xcp::get().get_registry();
+    let registry = registry.lock();
+    let fields: Vec<(String, std::ops::Range<usize>)> = registry
+        .get_cal_seg_characteristics(calseg_name)
+        .into_iter()
+        .filter(|c| c.get_alias_of().is_none())
+        .filter_map(|c| {
+            let (x_dim, y_dim) = c.get_dim();
+            let start = c.get_addr_offset() as usize;
+            let end = start + c.get_datatype().get_size() * x_dim * y_dim;
+            let range = start.max(offset)..end.min(offset + len);
+            if range.is_empty() {
+                None
+            } else {
+                Some((c.get_name().to_string(), range))
+            }
+        })
+        .collect();
+    if fields.is_empty() {
+        vec![(calseg_name.to_string(), offset..offset + len)]
+    } else {
+        fields
+    }
+}
@@ -7,11 +7,27 @@
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 
+#[cfg(feature = "serde")]
+use super::cal_limits::{check_limits, CalLimitViolation, CalLoadError, CalLoadPolicy};
+#[cfg(feature = "json5")]
+use super::cal_json5::to_json5_with_comments;
+use super::cal_observer::CalSegObserver;
+use super::cal_slew::{get_slew_fields, CalSlew, CalSlewMode};
+use super::cal_snapshot::{CalDiffEntry, CalSnapshot};
 use super::RegisterFieldsTrait;
 use crate::reg;
 use crate::xcp;
 use parking_lot::Mutex;
-use std::{marker::PhantomData, ops::Deref, sync::Arc};
+use std::{
+    cell::{RefCell, UnsafeCell},
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::Deref,
+    sync::atomic::{fence, AtomicBool, AtomicU16, AtomicU32, AtomicUsize, Ordering},
+    sync::{Arc, Weak},
+    time::Duration,
+};
+use xcp::daq::daq_event::DaqEvent;
 use xcp::Xcp;
 use xcp::XcpCalPage;
 
@@ -108,6 +124,149 @@ struct CalPage<T: CalPageTrait> {
     page: T,
 }
 
+impl<T: CalPageTrait> CalPage<T> {
+    // Create a page wrapper with a byte copy of page in zeroed memory
+    // Struct assignment leaves the padding bytes of T undefined, they would be uploaded to the tool
+    fn new(page: &T) -> CalPage<T> {
+        let mut p = MaybeUninit::<CalPage<T>>::zeroed();
+        // @@@@ Unsafe - All fields are initialized, ctr and the flags are valid as zero, page is a byte copy of a valid T
+        unsafe {
+            let dst = std::ptr::addr_of_mut!((*p.as_mut_ptr()).page);
+            core::ptr::copy_nonoverlapping(page as *const T as *const u8, dst as *mut u8, std::mem::size_of::<T>());
+            p.assume_init()
+        }
+    }
+}
+
+// Byte copy of a calibration page, including the padding bytes
+fn copy_page<T: CalPageTrait>(dst: &mut T, src: &T) {
+    // @@@@ Unsafe - Byte copy of a Copy type
+    unsafe {
+        core::ptr::copy_nonoverlapping(src as *const T as *const u8, dst as *mut T as *mut u8, std::mem::size_of::<T>());
+    }
+}
+
+// Byte copy of a calibration page from a byte copy of a valid T
+#[cfg(feature = "serde")]
+fn copy_page_from_bytes<T: CalPageTrait>(dst: &mut T, src: &[u8]) {
+    assert_eq!(src.len(), std::mem::size_of::<T>());
+    // @@@@ Unsafe - Byte copy of a Copy type, src is a byte copy of a valid T with only the values of some fields replaced by valid values of the same type
+    unsafe {
+        core::ptr::copy_nonoverlapping(src.as_ptr(), dst as *mut T as *mut u8, std::mem::size_of::<T>());
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// Modification counter and modified byte range of the XCP page for copy on write sync
+
+// Byte range marker for the whole page
+const CALPAGE_RANGE_ALL: (u32, u32) = (0, u32::MAX);
+// Byte range marker for no modification
+const CALPAGE_RANGE_NONE: (u32, u32) = (u32::MAX, 0);
+
+// Shared by all clones, modified only while the xcp_page mutex is held
+// The modification counter mirrors xcp_page.ctr and allows sync to check for modifications without taking the lock
+// The byte range dirty_min..dirty_max has been modified by the last increment of the modification counter, delayed writes are collected in pending_min..pending_max until flush
+#[derive(Debug)]
+struct CalPageVersion {
+    ctr: AtomicU16,
+    request: AtomicBool,   // Pending init or freeze request
+    init_count: AtomicU16, // Number of executed init requests, an init cancels the ramps of slew rate limited calibration parameters
+    dirty_min: AtomicU32,
+    dirty_max: AtomicU32,
+    pending_min: AtomicU32,
+    pending_max: AtomicU32,
+}
+
+impl CalPageVersion {
+    fn new() -> CalPageVersion {
+        CalPageVersion {
+            ctr: AtomicU16::new(0),
+            request: AtomicBool::new(false),
+            init_count: AtomicU16::new(0),
+            dirty_min: AtomicU32::new(CALPAGE_RANGE_NONE.0),
+            dirty_max: AtomicU32::new(CALPAGE_RANGE_NONE.1),
+            pending_min: AtomicU32::new(CALPAGE_RANGE_NONE.0),
+            pending_max: AtomicU32::new(CALPAGE_RANGE_NONE.1),
+        }
+    }
+
+    // Add a modified byte range to the pending range
+    fn add_range(&self, min: u32, max: u32) {
+        self.pending_min.fetch_min(min, Ordering::Relaxed);
+        self.pending_max.fetch_max(max, Ordering::Relaxed);
+    }
+
+    // The pending range becomes the range of the new modification counter value
+    fn commit(&self, ctr: u16) {
+        self.dirty_min.store(self.pending_min.swap(CALPAGE_RANGE_NONE.0, Ordering::Relaxed), Ordering::Relaxed);
+        self.dirty_max.store(self.pending_max.swap(CALPAGE_RANGE_NONE.1, Ordering::Relaxed), Ordering::Relaxed);
+        self.ctr.store(ctr, Ordering::Release);
+    }
+
+    // Byte range modified by the last increment of the modification counter
+    fn get_range(&self) -> (u32, u32) {
+        (self.dirty_min.load(Ordering::Relaxed), self.dirty_max.load(Ordering::Relaxed))
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// Published copy of the XCP page for wait free readers (seqlock)
+
+/// Max number of retries of CalSeg::read_seq on concurrent modifications, before it falls back to the last synced page
+const CALSEG_READ_SEQ_RETRIES: usize = 16;
+
+// Copy of the xcp page, published by the XCP write path on each increment of the modification counter
+// The sequence counter is odd while the writer copies the page
+#[derive(Debug)]
+struct SeqPage<T: CalPageTrait> {
+    seq: AtomicUsize,
+    page: UnsafeCell<T>,
+}
+
+// @@@@ Unsafe - Implementation of Sync for SeqPage
+// The page is written only while the xcp_page mutex is held (single writer), readers validate their copy with the sequence counter
+unsafe impl<T: CalPageTrait> Sync for SeqPage<T> {}
+
+impl<T: CalPageTrait> SeqPage<T> {
+    fn new(page: T) -> SeqPage<T> {
+        SeqPage {
+            seq: AtomicUsize::new(0),
+            page: UnsafeCell::new(page),
+        }
+    }
+
+    // Publish a new page
+    // Must only be called while the xcp_page mutex is held
+    fn publish(&self, page: &T) {
+        let seq = self.seq.load(Ordering::Relaxed);
+        self.seq.store(seq.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        // @@@@ Unsafe - Concurrent readers may see a torn page, which is detected by the sequence counter
+        unsafe {
+            std::ptr::write_volatile(self.page.get(), *page);
+        }
+        self.seq.store(seq.wrapping_add(2), Ordering::Release);
+    }
+
+    // Try to read a consistent copy of the page
+    // Returns None, if the writer modified the page concurrently
+    fn try_read(&self) -> Option<T> {
+        let seq1 = self.seq.load(Ordering::Acquire);
+        if seq1 & 1 != 0 {
+            return None;
+        }
+        // @@@@ Unsafe - The copy may be torn and is not assumed to be a valid T before the sequence counter has been checked
+        let page = unsafe { std::ptr::read_volatile(self.page.get() as *const MaybeUninit<T>) };
+        fence(Ordering::Acquire);
+        let seq2 = self.seq.load(Ordering::Relaxed);
+        if seq1 != seq2 {
+            return None;
+        }
+        Some(unsafe { page.assume_init() })
+    }
+}
+
 //-----------------------------------------------------------------------------
 // CalPageTrait
 
@@ -153,6 +312,11 @@ where
     default_page: &'static T,
     ecu_page: Box<CalPage<T>>,
     xcp_page: Arc<Mutex<CalPage<T>>>,
+    version: Arc<CalPageVersion>,
+    seq_page: Arc<SeqPage<T>>,
+    dirty: Arc<AtomicBool>,
+    observers: Arc<Mutex<Vec<Weak<dyn CalSegObserver>>>>,
+    slew: RefCell<Option<Box<CalSlew>>>,
     //_not_send_sync_marker: PhantomData<*mut ()>,
     _not_sync_marker: PhantomData<std::cell::Cell<()>>,
 }
@@ -178,15 +342,18 @@ where
 {
     /// Load a calibration segment from json file
     /// Requires the calibration page type to implement serde::Serialize + serde::de::DeserializeOwned
-
     pub fn load<P: AsRef<std::path::Path>>(&self, filename: P) -> Result<(), std::io::Error> {
         let path = filename.as_ref();
         info!("Load {} from file {} ", self.get_name(), path.display());
         if let Ok(file) = std::fs::File::open(path) {
             let reader = std::io::BufReader::new(file);
             let page = serde_json::from_reader::<_, T>(reader)?;
-            self.xcp_page.lock().page = page;
-            self.xcp_page.lock().ctr += 1;
+            {
+                let mut xcp_page = self.xcp_page.lock();
+                copy_page(&mut xcp_page.page, &page);
+                self.version.add_range(CALPAGE_RANGE_ALL.0, CALPAGE_RANGE_ALL.1);
+                self.increment_ctr(&mut xcp_page);
+            }
             self.sync();
             Ok(())
         } else {
@@ -195,6 +362,33 @@ where
         }
     }
 
+    /// Load a calibration segment from json file and check the loaded values against the limits of the registered calibration parameters
+    /// Returns the values out of range, which are loaded unchanged, clamped to the limits or rejected with CalLoadError::OutOfRange, depending on policy
+    /// Only registered calibration parameters are checked, e.g. with register_fields
+    /// Requires the calibration page type to implement serde::Serialize + serde::de::DeserializeOwned
+    pub fn load_checked<P: AsRef<std::path::Path>>(&self, filename: P, policy: CalLoadPolicy) -> Result<Vec<CalLimitViolation>, CalLoadError> {
+        let path = filename.as_ref();
+        info!("Load {} from file {} with limit check", self.get_name(), path.display());
+        let file = std::fs::File::open(path)?;
+        let page = serde_json::from_reader::<_, T>(std::io::BufReader::new(file)).map_err(std::io::Error::from)?;
+        let mut bytes = page_bytes(&page);
+        let violations = check_limits(self.get_name(), &mut bytes, policy == CalLoadPolicy::Clamp);
+        for v in &violations {
+            warn!("{}: {}", path.display(), v);
+        }
+        if policy == CalLoadPolicy::Reject && !violations.is_empty() {
+            return Err(CalLoadError::OutOfRange(violations));
+        }
+        {
+            let mut xcp_page = self.xcp_page.lock();
+            copy_page_from_bytes(&mut xcp_page.page, &bytes);
+            self.version.add_range(CALPAGE_RANGE_ALL.0, CALPAGE_RANGE_ALL.1);
+            self.increment_ctr(&mut xcp_page);
+        }
+        self.sync();
+        Ok(violations)
+    }
+
     /// Write a calibrationsegment to json file
     /// Requires the calibration page type to implement serde::Serialize + serde::de::DeserializeOwned
     pub fn save<P: AsRef<std::path::Path>>(&self, filename: P) -> Result<(), std::io::Error> {
@@ -202,8 +396,78 @@ where
         info!("Save {} to file {}", self.get_name(), path.display());
         let file = std::fs::File::create(path)?;
         let mut writer = std::io::BufWriter::new(file);
-        let s = serde_json::to_string(&self.xcp_page.lock().page).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("serde_json::to_string failed: {}", e)))?;
+        let s = serde_json::to_string(&self.xcp_page.lock().page).map_err(|e| std::io::Error::other(format!("serde_json::to_string failed: {}", e)))?;
         std::io::Write::write_all(&mut writer, s.as_ref())?;
+        self.dirty.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Load a calibration segment from json5 file, e.g. written by save_with_comments
+    /// Requires the calibration page type to implement serde::Serialize + serde::de::DeserializeOwned
+    #[cfg(feature = "json5")]
+    pub fn load_json5<P: AsRef<std::path::Path>>(&self, filename: P) -> Result<(), std::io::Error> {
+        let path = filename.as_ref();
+        info!("Load {} from json5 file {} ", self.get_name(), path.display());
+        let s = std::fs::read_to_string(path)?;
+        let page = json5::from_str::<T>(&s).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("json5::from_str failed: {}", e)))?;
+        {
+            let mut xcp_page = self.xcp_page.lock();
+            copy_page(&mut xcp_page.page, &page);
+            self.version.add_range(CALPAGE_RANGE_ALL.0, CALPAGE_RANGE_ALL.1);
+            self.increment_ctr(&mut xcp_page);
+        }
+        self.sync();
+        Ok(())
+    }
+
+    /// Load a calibration segment from json file without blocking the tokio executor
+    /// Requires the calibration page type to implement serde::Serialize + serde::de::DeserializeOwned
+    #[cfg(feature = "tokio")]
+    pub async fn load_async<P: AsRef<std::path::Path>>(&self, filename: P) -> Result<(), std::io::Error> {
+        let path = filename.as_ref();
+        info!("Load {} from file {} ", self.get_name(), path.display());
+        let bytes = tokio::fs::read(path).await?;
+        let page = serde_json::from_slice::<T>(&bytes)?;
+        {
+            let mut xcp_page = self.xcp_page.lock();
+            copy_page(&mut xcp_page.page, &page);
+            self.version.add_range(CALPAGE_RANGE_ALL.0, CALPAGE_RANGE_ALL.1);
+            self.increment_ctr(&mut xcp_page);
+        }
+        self.sync();
+        Ok(())
+    }
+
+    /// Write a calibration segment to json file without blocking the tokio executor
+    /// The page is serialized synchronously, only the file write is asynchronous
+    /// Requires the calibration page type to implement serde::Serialize + serde::de::DeserializeOwned
+    #[cfg(feature = "tokio")]
+    pub async fn save_async<P: AsRef<std::path::Path>>(&self, filename: P) -> Result<(), std::io::Error> {
+        let path = filename.as_ref();
+        info!("Save {} to file {}", self.get_name(), path.display());
+        let bytes = serde_json::to_vec(&self.xcp_page.lock().page)?;
+        tokio::fs::write(path, bytes).await?;
+        self.dirty.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+// Impl save with field comments for types which implement xcp_type_description::XcpTypeDescription
+#[cfg(feature = "json5")]
+impl<T> CalSeg<T>
+where
+    T: CalPageTrait + xcp_type_description::XcpTypeDescription,
+{
+    /// Write a calibration segment to json5 file for human readers, which is loaded with load_json5
+    /// Each field is preceded by a comment line "// <comment> [unit] min=<min> max=<max>" from its type description
+    pub fn save_with_comments<P: AsRef<std::path::Path>>(&self, filename: P) -> Result<(), std::io::Error> {
+        let path = filename.as_ref();
+        info!("Save {} to json5 file {}", self.get_name(), path.display());
+        let page = self.xcp_page.lock().page;
+        let value = serde_json::to_value(page)?;
+        let s = to_json5_with_comments(&value, &page.type_description().unwrap_or_default());
+        std::fs::write(path, s)?;
+        self.dirty.store(false, Ordering::Relaxed);
         Ok(())
     }
 }
@@ -227,18 +491,13 @@ where
         CalSeg {
             index,
             default_page,
-            ecu_page: Box::new(CalPage {
-                ctr: 0,
-                init_request: false,
-                freeze_request: false,
-                page: init_page,
-            }),
-            xcp_page: Arc::new(Mutex::new(CalPage {
-                ctr: 0,
-                init_request: false,
-                freeze_request: false,
-                page: init_page,
-            })),
+            ecu_page: Box::new(CalPage::new(&init_page)),
+            xcp_page: Arc::new(Mutex::new(CalPage::new(&init_page))),
+            version: Arc::new(CalPageVersion::new()),
+            seq_page: Arc::new(SeqPage::new(init_page)),
+            dirty: Arc::new(AtomicBool::new(false)),
+            observers: Arc::new(Mutex::new(Vec::new())),
+            slew: RefCell::new(None),
             //_not_send_sync_marker: PhantomData,
             _not_sync_marker: PhantomData,
         }
@@ -249,6 +508,11 @@ where
         Xcp::get().get_calseg_name(self.index)
     }
 
+    /// Check if the calibration segment was modified by the XCP tool since the last save or freeze
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Relaxed)
+    }
+
     /// Manually add a field description
     pub fn add_field(&self, field: CalPageField) -> &CalSeg<T> {
         trace!("add_field: {:?}", field);
@@ -275,12 +539,26 @@ where
         self
     }
 
+    /// Subscribe an observer to the calibration parameter changes by the XCP tool
+    /// The calibration segment holds a weak reference only, the observer is unsubscribed when it is dropped
+    /// Multiple observers are notified in the order of subscription, the subscription is shared by all clones
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn subscribe(&self, observer: Arc<dyn CalSegObserver>) {
+        self.observers.lock().push(Arc::downgrade(&observer));
+    }
+
+    /// Unsubscribe all observers
+    pub fn unsubscribe_all(&self) {
+        self.observers.lock().clear();
+    }
+
     /// Get the calibration segment clone count
     pub fn get_clone_count(&self) -> usize {
         Arc::strong_count(&self.xcp_page)
     }
 
     /// Consistent read access to the calibration segment while the lock guard is held
+    /// Syncs this clone of the calibration segment, which may block while the XCP write path holds the lock
     pub fn read_lock(&self) -> ReadLockGuard<'_, T> {
         self.sync();
         // page swap logic inside deref
@@ -288,20 +566,149 @@ where
         ReadLockGuard { page: xcp_or_default_page }
     }
 
+    /// Wait free read access to the calibration segment for hard real time threads
+    /// Calls f with a consistent copy of the most recent calibration page published by the XCP write path, never takes the lock
+    /// Retries on concurrent modifications, after CALSEG_READ_SEQ_RETRIES it falls back to the last synced page of this clone
+    /// Compared to read_lock, this clone is not synced, delayed modifications are visible after flush and each call copies the page
+    pub fn read_seq<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        if xcp::XCP_SINGLETON.ecu_cal_page.load(Ordering::Relaxed) != XcpCalPage::Ram as u8 {
+            return f(self.default_page);
+        }
+        for _ in 0..CALSEG_READ_SEQ_RETRIES {
+            if let Some(page) = self.seq_page.try_read() {
+                return f(&page);
+            }
+            std::hint::spin_loop();
+        }
+        f(&self.ecu_page.page)
+    }
+
+    /// Enable slew rate limited application of XCP changes in this clone of the calibration segment
+    /// Calibration parameters with slew rate (type description attribute slew_rate = "10.0", units per second) ramp from the old to the new value, all others apply immediately
+    /// The ramps are advanced by tick (CalSlewMode::Tick) or by the elapsed time on each sync (CalSlewMode::RealTime)
+    /// The XCP tool sees the new value immediately, read_seq is not ramped
+    /// The calibration parameters must be registered before, e.g. with register_fields
+    pub fn enable_slew(&self, mode: CalSlewMode) {
+        let fields = get_slew_fields(self.get_name());
+        if fields.is_empty() {
+            warn!("enable_slew: {} has no calibration parameters with slew rate", self.get_name());
+        }
+        let init_count = self.version.init_count.load(Ordering::Relaxed);
+        let cal_page = xcp::XCP_SINGLETON.ecu_cal_page.load(Ordering::Relaxed);
+        *self.slew.borrow_mut() = Some(Box::new(CalSlew::new(mode, fields, init_count, cal_page)));
+    }
+
+    /// Sync and advance the ramps of slew rate limited calibration parameters by dt, called from the control loop
+    pub fn tick(&self, dt: Duration) {
+        self.sync();
+        if let Some(slew) = self.slew.borrow_mut().as_deref_mut() {
+            slew.advance(dt, self.ecu_page_bytes());
+        }
+    }
+
+    /// Check if slew rate limited calibration parameters of this clone are still ramping to their new values
+    pub fn is_ramping(&self) -> bool {
+        self.slew.borrow().as_ref().is_some_and(|slew| slew.is_ramping())
+    }
+
+    /// Register the applied values of the slew rate limited calibration parameters of this clone as measurements "<name>.applied" of the event
+    /// The measurements are absolute addresses of this clone, the event must be triggered with trigger_abs
+    pub fn add_slew_measurements<const N: usize>(&self, event: &DaqEvent<N>) {
+        let slew = self.slew.borrow();
+        let Some(slew) = slew.as_deref() else {
+            return;
+        };
+        for field in &slew.fields {
+            let name: &'static str = Box::leak(format!("{}.applied", field.name).into_boxed_str());
+            // @@@@ Unsafe - Address of the field in the ecu page of this clone, the page is heap allocated and does not move
+            let ptr = unsafe { (std::ptr::addr_of!(self.ecu_page.page) as *const u8).add(field.offset) };
+            event.add_heap(
+                name,
+                ptr,
+                field.datatype,
+                field.dim.0.try_into().expect("x_dim out of range"),
+                field.dim.1.try_into().expect("y_dim out of range"),
+                1.0,
+                0.0,
+                "",
+                "applied value",
+            );
+        }
+    }
+
+    /// Snapshot of the RAM page, including modifications by the XCP tool not yet synced to this clone
+    pub fn snapshot(&self) -> CalSnapshot {
+        let xcp_page = self.xcp_page.lock();
+        CalSnapshot::new(self.get_name(), page_bytes(&xcp_page.page))
+    }
+
+    /// Modified calibration parameters of the RAM page compared to the default (FLASH) page
+    pub fn diff_against_default(&self) -> Vec<CalDiffEntry> {
+        CalSnapshot::new(self.get_name(), page_bytes(self.default_page)).diff(&self.snapshot())
+    }
+
+    // Increment the modification counter to distribute the modified xcp page to all clones
+    // Must be called while the xcp_page mutex is held
+    fn increment_ctr(&self, xcp_page: &mut CalPage<T>) {
+        xcp_page.ctr = xcp_page.ctr.wrapping_add(1);
+        self.version.commit(xcp_page.ctr);
+        self.seq_page.publish(&xcp_page.page);
+    }
+
     /// Sync the calibration segment
     /// If calibration changes from XCP tool happened since last sync, copy the xcp page to the ecu page
+    /// Copy on write: Without modifications, sync returns without taking the lock, if this clone missed only the last modification, only the modified byte range is copied
     /// Handle freeze and init operations on request here
+    /// With slew enabled, modified calibration parameters with slew rate start to ramp from the applied value, init and page switch cancel the ramps
     /// # Returns
     /// true, if the calibration segment was modified
     pub fn sync(&self) -> bool {
+        let mut slew = self.slew.borrow_mut();
+        let Some(slew) = slew.as_deref_mut() else {
+            return self.sync_page(false);
+        };
+
+        // Slew enabled, the whole page is synced and the ramps start from the values applied before the sync
+        let old = if self.is_sync_pending() { page_bytes(&self.ecu_page.page) } else { Vec::new() };
+        let modified = self.sync_page(true);
+        let page = self.ecu_page_bytes();
+        let init_count = self.version.init_count.load(Ordering::Relaxed);
+        let cal_page = xcp::XCP_SINGLETON.ecu_cal_page.load(Ordering::Relaxed);
+        if !slew.check_cancel(init_count, cal_page, page, modified) && modified {
+            slew.update(&old, page);
+        }
+        if slew.mode == CalSlewMode::RealTime && slew.is_ramping() {
+            slew.advance_to_now(page);
+        }
+        modified
+    }
+
+    // Check for modifications or requests without taking the lock
+    fn is_sync_pending(&self) -> bool {
+        self.version.ctr.load(Ordering::Acquire) != self.ecu_page.ctr || self.version.request.load(Ordering::Acquire)
+    }
+
+    // Active page of this clone as bytes, modified by the slew ramps
+    #[allow(clippy::mut_from_ref)]
+    fn ecu_page_bytes(&self) -> &mut [u8] {
+        // @@@@ Unsafe - The ecu page is modified only by sync and tick of this clone, CalSeg is not Sync
+        unsafe { std::slice::from_raw_parts_mut(std::ptr::addr_of!(self.ecu_page.page) as *mut u8, std::mem::size_of::<T>()) }
+    }
+
+    // Sync the ecu page with the xcp page, all forces a copy of the whole page
+    fn sync_page(&self, all: bool) -> bool {
+        if !self.is_sync_pending() {
+            return false;
+        }
+
         let mut modified = false;
 
         // Check for modifications and copy xcp_page to ecu_page, when active page is "RAM"
         // let xcp = Xcp::get();
         // if xcp.get_xcp_cal_page() == XcpCalPage::Ram
         {
-            // @@@@ ToDo: Avoid the lock, when there is no pending modification for the XCP page
             let mut xcp_page = self.xcp_page.lock();
+            self.version.request.store(false, Ordering::Relaxed);
 
             // Freeze - save xcp page to json file
             // @@@@ don't panic, if the file can't be written
@@ -313,40 +720,62 @@ where
                 let mut path = std::path::PathBuf::from(self.get_name());
                 path.set_extension("json");
 
-                let file = std::fs::File::create(path).unwrap();
-                let mut writer = std::io::BufWriter::new(file);
                 let s = serde_json::to_string(&xcp_page.page)
-                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("serde_json::to_string failed: {}", e)))
+                    .map_err(|e| std::io::Error::other(format!("serde_json::to_string failed: {}", e)))
                     .unwrap();
-                std::io::Write::write_all(&mut writer, s.as_ref()).unwrap();
+
+                // Write the file on the freeze executor, if there is one
+                #[cfg(feature = "tokio")]
+                if let Some(handle) = Xcp::get().get_freeze_executor() {
+                    handle.spawn(async move {
+                        if let Err(e) = tokio::fs::write(&path, s).await {
+                            error!("freeze: write {} failed: {}", path.display(), e);
+                        }
+                    });
+                } else {
+                    std::fs::write(path, s).unwrap();
+                }
+                #[cfg(not(feature = "tokio"))]
+                std::fs::write(path, s).unwrap();
+
+                self.dirty.store(false, Ordering::Relaxed);
             }
 
             // Init - copy the default calibration page back to xcp page to reset it to default values
             if xcp_page.init_request {
                 xcp_page.init_request = false;
-                // @@@@ Unsafe - Implementation of init cal page in sync() with non mut self
-                unsafe {
-                    info!("init: {}: default_page => xcp_page ({})", self.get_name(), xcp_page.ctr,);
-
-                    let src_ptr = self.default_page as *const T;
-                    #[allow(clippy::ptr_cast_constness)]
-                    let dst_ptr = &xcp_page.page as *const _ as *mut T;
-                    core::ptr::copy_nonoverlapping(src_ptr, dst_ptr, 1);
-                }
+                info!("init: {}: default_page => xcp_page ({})", self.get_name(), xcp_page.ctr,);
+                copy_page(&mut xcp_page.page, self.default_page);
+                self.version.init_count.fetch_add(1, Ordering::Relaxed);
 
                 // Increment the modification counter to distribute the new xcp page to all clones
-                xcp_page.ctr += 1;
+                self.version.add_range(CALPAGE_RANGE_ALL.0, CALPAGE_RANGE_ALL.1);
+                self.increment_ctr(&mut xcp_page);
+                self.dirty.store(true, Ordering::Relaxed);
             }
 
             // Sync - Copy shared (ctr,xcp_page) to (ctr,ecu_page) in this clone of the calibration segment
+            // If this clone missed only the last modification, copy only the modified byte range, otherwise the whole page
             if xcp_page.ctr != self.ecu_page.ctr {
-                trace!("sync: {}: xcp_page ({}) => ecu_page ({})", self.get_name(), xcp_page.ctr, self.ecu_page.ctr);
-                // @@@@ Unsafe - Copy xcp_page to ecu_page
+                let size = std::mem::size_of::<T>();
+                let (min, max) = if xcp_page.ctr == self.ecu_page.ctr.wrapping_add(1) && !all {
+                    match self.version.get_range() {
+                        CALPAGE_RANGE_ALL => (0, size),
+                        (min, max) => (min as usize, (max as usize).min(size)),
+                    }
+                } else {
+                    (0, size)
+                };
+                trace!("sync: {}: xcp_page ({}) => ecu_page ({}), bytes {}..{}", self.get_name(), xcp_page.ctr, self.ecu_page.ctr, min, max);
+                // @@@@ Unsafe - Copy the modified range of xcp_page to ecu_page
                 unsafe {
-                    let dst_ptr: *mut u8 = self.ecu_page.as_ref() as *const _ as *mut u8;
-                    let src_ptr: *const u8 = &*xcp_page as *const _ as *const u8;
-                    let size: usize = std::mem::size_of::<(usize, T)>();
-                    core::ptr::copy_nonoverlapping(src_ptr, dst_ptr, size);
+                    let ecu_page = (self.ecu_page.as_ref() as *const CalPage<T>).cast_mut();
+                    if min < max {
+                        let dst_ptr: *mut u8 = (std::ptr::addr_of_mut!((*ecu_page).page) as *mut u8).add(min);
+                        let src_ptr: *const u8 = (std::ptr::addr_of!(xcp_page.page) as *const u8).add(min);
+                        core::ptr::copy_nonoverlapping(src_ptr, dst_ptr, max - min);
+                    }
+                    (*ecu_page).ctr = xcp_page.ctr;
                 }
                 modified = true;
             }
@@ -356,6 +785,12 @@ where
     }
 }
 
+// Copy of the memory of a calibration page
+fn page_bytes<T: CalPageTrait>(page: &T) -> Vec<u8> {
+    // @@@@ Unsafe - Byte copy of a Copy type
+    unsafe { std::slice::from_raw_parts(page as *const T as *const u8, std::mem::size_of::<T>()) }.to_vec()
+}
+
 //----------------------------------------------------------------------------------------------
 // Trait CalSegTrait
 
@@ -378,20 +813,26 @@ where
     // Set init request
     fn set_init_request(&self);
 
+    // Check for modifications since the last save or freeze
+    fn is_dirty(&self) -> bool;
+
     // Read from xcp_page or default_page depending on the active XCP page
     // # Safety
     // dst must be valid
     // @@@@ Unsafe function
-    unsafe fn read(&self, offset: u16, len: u8, dst: *mut u8) -> bool;
+    unsafe fn read(&self, offset: u32, len: u8, dst: *mut u8) -> bool;
 
     // Write to xcp_page
     // # Safety
     // src must be valid
     // @@@@ Unsafe function
-    unsafe fn write(&self, offset: u16, len: u8, src: *const u8, delay: u8) -> bool;
+    unsafe fn write(&self, offset: u32, len: u8, src: *const u8, delay: u8) -> bool;
 
     // Flush delayed modifications
     fn flush(&self);
+
+    // Get the subscribed observers which are still alive
+    fn get_observers(&self) -> Vec<Arc<dyn CalSegObserver>>;
 }
 
 impl<T> CalSegTrait for CalSeg<T>
@@ -411,14 +852,20 @@ where
     }
     fn set_freeze_request(&self) {
         self.xcp_page.lock().freeze_request = true;
+        self.version.request.store(true, Ordering::Release);
     }
 
     fn set_init_request(&self) {
         self.xcp_page.lock().init_request = true;
+        self.version.request.store(true, Ordering::Release);
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Relaxed)
     }
 
     // @@@@ Unsafe
-    unsafe fn read(&self, offset: u16, len: u8, dst: *mut u8) -> bool {
+    unsafe fn read(&self, offset: u32, len: u8, dst: *mut u8) -> bool {
         assert!(offset as usize + len as usize <= std::mem::size_of::<T>());
         if Xcp::get().get_xcp_cal_page() == XcpCalPage::Ram {
             let xcp_page = self.xcp_page.lock();
@@ -433,16 +880,18 @@ where
     }
 
     // @@@@ Unsafe
-    unsafe fn write(&self, offset: u16, len: u8, src: *const u8, delay: u8) -> bool {
+    unsafe fn write(&self, offset: u32, len: u8, src: *const u8, delay: u8) -> bool {
         assert!(offset as usize + len as usize <= std::mem::size_of::<T>());
         if Xcp::get().get_xcp_cal_page() == XcpCalPage::Ram {
             let mut xcp_page = self.xcp_page.lock(); // .unwrap(); // std::sync::MutexGuard
             let dst: *mut u8 = (&xcp_page.page as *const _ as *mut u8).add(offset as usize);
             core::ptr::copy_nonoverlapping(src, dst, len as usize);
+            self.version.add_range(offset, offset.saturating_add(len as u32));
             if delay == 0 {
                 // Increment modification counter
-                xcp_page.ctr = xcp_page.ctr.wrapping_add(1);
+                self.increment_ctr(&mut xcp_page);
             }
+            self.dirty.store(true, Ordering::Relaxed);
             true
         } else {
             false // Write to default page is not allowed
@@ -451,7 +900,13 @@ where
 
     fn flush(&self) {
         let mut xcp_page = self.xcp_page.lock();
-        xcp_page.ctr = xcp_page.ctr.wrapping_add(1); // Increment modification counter
+        self.increment_ctr(&mut xcp_page); // Increment modification counter
+    }
+
+    fn get_observers(&self) -> Vec<Arc<dyn CalSegObserver>> {
+        let mut observers = self.observers.lock();
+        observers.retain(|o| o.strong_count() > 0);
+        observers.iter().filter_map(Weak::upgrade).collect()
     }
 }
 
@@ -492,7 +947,8 @@ where
     fn deref_mut(&mut self) -> &mut Self::Target {
         warn!("Unsafe deref mut to XCP page of {}, this is undefined behaviour !!", self.get_name());
         let mut p = self.xcp_page.lock();
-        p.ctr = p.ctr.wrapping_add(1);
+        self.version.add_range(CALPAGE_RANGE_ALL.0, CALPAGE_RANGE_ALL.1);
+        self.increment_ctr(&mut p);
         let r: *mut T = &mut p.page;
         // @@@@ Usafe - For testing only
         unsafe { &mut *r }
@@ -509,9 +965,14 @@ where
     fn clone(&self) -> Self {
         CalSeg {
             index: self.index,
-            default_page: self.default_page,      // &T
-            ecu_page: self.ecu_page.clone(),      // Clone for each thread
-            xcp_page: Arc::clone(&self.xcp_page), // Share Arc<Mutex<T>>
+            default_page: self.default_page,        // &T
+            ecu_page: self.ecu_page.clone(),        // Clone for each thread
+            xcp_page: Arc::clone(&self.xcp_page),   // Share Arc<Mutex<T>>
+            version: Arc::clone(&self.version),     // Share Arc<CalPageVersion>
+            seq_page: Arc::clone(&self.seq_page),   // Share Arc<SeqPage<T>>
+            dirty: Arc::clone(&self.dirty),         // Share Arc<AtomicBool>
+            observers: Arc::clone(&self.observers), // Share the observer list
+            slew: self.slew.clone(),                // Ramp state of this clone
             //_not_send_sync_marker: PhantomData,
             _not_sync_marker: PhantomData,
         }
@@ -577,6 +1038,10 @@ mod cal_tests {
     #![allow(dead_code)]
     use super::*;
     use crate::xcp;
+    use crate::xcp::cal::cal_audit::{cal_audit_csv_sink, CalChange};
+    use crate::xcp::cal::cal_observer::{CalSegChange, ChannelObserver};
+    use crate::xcp::cal::cal_snapshot::McValue;
+    use crate::xcp::cal::CalWriteStatus;
     use std::sync::Arc;
     use std::thread;
     use xcp::*;
@@ -988,6 +1453,700 @@ mod cal_tests {
         let _ = std::fs::remove_file("test2.json");
     }
 
+    //-----------------------------------------------------------------------------
+    // Test page switch, init and freeze from the application
+    #[test]
+    fn test_cal_page_api() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+
+        let calseg1 = xcp.create_calseg("test_api1", &FLASH_PAGE1);
+        let calseg2 = xcp.create_calseg("test_api2", &FLASH_PAGE2);
+        let mut names = xcp.get_calseg_names();
+        names.sort_unstable();
+        assert_eq!(names, ["test_api1", "test_api2"]);
+
+        // Modify the RAM page of calseg1
+        let offset: u32 = std::mem::offset_of!(CalPage1, b).try_into().unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert!(calseg1.write(offset, 4, 7u32.to_ne_bytes().as_ptr(), 0));
+        }
+        assert_eq!(calseg1.read_lock().b, 7);
+        assert_eq!(xcp.get_cal_page("test_api1").unwrap(), XcpCalPage::Ram);
+
+        // Switch all segments to FLASH and back
+        xcp.set_cal_page_all(XcpCalPage::Flash);
+        assert_eq!(xcp.get_cal_page("test_api1").unwrap(), XcpCalPage::Flash);
+        assert_eq!(xcp.get_cal_page("test_api2").unwrap(), XcpCalPage::Flash);
+        assert_eq!(xcp.get_xcp_cal_page(), XcpCalPage::Flash);
+        assert_eq!(calseg1.read_lock().b, FLASH_PAGE1.b);
+        assert_eq!(calseg1.read_seq(|page| page.b), FLASH_PAGE1.b);
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert!(!calseg1.write(offset, 4, 8u32.to_ne_bytes().as_ptr(), 0)); // FLASH is read only
+        }
+        xcp.set_cal_page_all(XcpCalPage::Ram);
+        assert_eq!(calseg1.read_lock().b, 7);
+
+        // Init copies FLASH to RAM, only for the given segment
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert!(calseg2.write(offset, 4, 9u32.to_ne_bytes().as_ptr(), 0));
+        }
+        xcp.init_calseg("test_api1").unwrap();
+        assert_eq!(calseg1.read_lock().b, FLASH_PAGE1.b);
+        assert_eq!(calseg2.read_lock().b, 9);
+        xcp.init_all();
+        assert_eq!(calseg2.read_lock().b, FLASH_PAGE2.b);
+
+        // Unknown segment names
+        assert!(matches!(xcp.get_cal_page("unknown"), Err(XcpError::CalSegNotFound(name)) if name == "unknown"));
+        assert!(matches!(xcp.init_calseg("unknown"), Err(XcpError::CalSegNotFound(_))));
+        assert!(matches!(xcp.freeze_calseg("unknown"), Err(XcpError::CalSegNotFound(_))));
+
+        // Freeze saves the RAM page to <name>.json on the next sync
+        #[cfg(feature = "serde")]
+        {
+            let _ = std::fs::remove_file("test_api1.json");
+            xcp.freeze_calseg("test_api1").unwrap();
+            calseg1.sync();
+            assert!(std::path::Path::new("test_api1.json").exists());
+            assert!(!std::path::Path::new("test_api2.json").exists());
+            let _ = std::fs::remove_file("test_api1.json");
+        }
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test dirty tracking, a write from the XCP tool marks the segment dirty, save clears it
+
+    #[test]
+    fn test_cal_seg_dirty() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+
+        let calseg1 = xcp.create_calseg("test1", &FLASH_PAGE1);
+        let calseg2 = xcp.create_calseg("test2", &FLASH_PAGE2);
+        assert!(!calseg1.is_dirty());
+        assert!(!calseg2.is_dirty());
+        assert!(xcp.get_changed_calsegs().is_empty());
+
+        // Simulate a write from the XCP tool to calseg1
+        let value = 7u32.to_ne_bytes();
+        let offset: u32 = std::mem::offset_of!(CalPage1, b).try_into().unwrap();
+        let index = xcp.get_calseg_index("test1").unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert_eq!(xcp.calseg_list.lock().write_to(index, offset, 4, value.as_ptr(), 0, false).0, CalWriteStatus::Ok);
+        }
+        assert!(calseg1.is_dirty());
+        assert!(calseg1.clone().is_dirty()); // Shared by all clones
+        assert!(!calseg2.is_dirty());
+        assert_eq!(xcp.get_changed_calsegs(), vec!["test1"]);
+        calseg1.sync();
+        assert_eq!(calseg1.b, 7);
+
+        // Save clears the dirty flag
+        #[cfg(feature = "serde")]
+        {
+            calseg1.save("test_dirty.json").unwrap();
+            assert!(!calseg1.is_dirty());
+            assert!(xcp.get_changed_calsegs().is_empty());
+            let _ = std::fs::remove_file("test_dirty.json");
+        }
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test copy on write sync, only the modified byte range is copied to clones which missed one modification
+
+    #[test]
+    fn test_cal_seg_copy_on_write() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+
+        let calseg1 = xcp.create_calseg("test_cow", &FLASH_PAGE1);
+        let calseg2 = calseg1.clone();
+        let offset_a: u32 = std::mem::offset_of!(CalPage1, a).try_into().unwrap();
+        let offset_b: u32 = std::mem::offset_of!(CalPage1, b).try_into().unwrap();
+        let offset_c: u32 = std::mem::offset_of!(CalPage1, c).try_into().unwrap();
+        assert!(!calseg1.sync());
+
+        // Single write, range is the written field
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert!(calseg1.write(offset_b, 4, 7u32.to_ne_bytes().as_ptr(), 0));
+        }
+        assert_eq!(calseg1.version.get_range(), (offset_b, offset_b + 4));
+        assert!(calseg1.sync());
+        assert_eq!((calseg1.a, calseg1.b, calseg1.c), (2, 7, 6));
+        assert!(!calseg1.sync());
+
+        // Delayed writes, range covers all writes until flush
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert!(calseg1.write(offset_a, 4, 1u32.to_ne_bytes().as_ptr(), 1));
+            assert!(calseg1.write(offset_c, 4, 9u32.to_ne_bytes().as_ptr(), 1));
+        }
+        assert!(!calseg1.sync()); // Not visible before flush
+        calseg1.flush();
+        assert_eq!(calseg1.version.get_range(), (offset_a.min(offset_c), offset_a.max(offset_c) + 4));
+        assert!(calseg1.sync());
+        assert_eq!((calseg1.a, calseg1.b, calseg1.c), (1, 7, 9));
+
+        // calseg2 missed two modifications and copies the whole page
+        assert!(calseg2.sync());
+        assert_eq!((calseg2.a, calseg2.b, calseg2.c), (1, 7, 9));
+        assert!(!calseg2.sync());
+
+        // Init request resets all clones to the default page
+        calseg1.set_init_request();
+        assert!(calseg1.sync());
+        assert!(calseg2.sync());
+        assert_eq!((calseg1.a, calseg1.b, calseg1.c), (2, 4, 6));
+        assert_eq!((calseg2.a, calseg2.b, calseg2.c), (2, 4, 6));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test calibration segment with a default page computed at runtime
+
+    #[test]
+    fn test_cal_seg_boxed() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+
+        let c = std::hint::black_box(5);
+        let calseg = xcp.create_calseg_boxed("test_boxed", CalPage1 { a: 1, b: 3, c });
+        assert_eq!(calseg.c, 5);
+
+        // Simulate a write from the XCP tool
+        let value = 7u32.to_ne_bytes();
+        let offset: u32 = std::mem::offset_of!(CalPage1, b).try_into().unwrap();
+        let index = xcp.get_calseg_index("test_boxed").unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert_eq!(xcp.calseg_list.lock().write_to(index, offset, 4, value.as_ptr(), 0, false).0, CalWriteStatus::Ok);
+        }
+        calseg.sync();
+        assert_eq!(calseg.b, 7);
+
+        // The default page is the runtime value
+        xcp.set_ecu_cal_page(XcpCalPage::Flash);
+        calseg.sync();
+        assert_eq!(calseg.b, 3);
+        xcp.set_ecu_cal_page(XcpCalPage::Ram);
+        calseg.sync();
+        assert_eq!(calseg.b, 7);
+
+        // Init copies the runtime default page to the working page
+        cb_init_cal(0, 0);
+        calseg.sync();
+        assert_eq!(calseg.a, 1);
+        assert_eq!(calseg.b, 3);
+        assert_eq!(calseg.c, 5);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test removal of a calibration segment before the registry is finalized
+
+    #[test]
+    fn test_cal_seg_remove() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+
+        let calseg1 = xcp.create_calseg("test_remove1", &FLASH_PAGE1);
+        calseg1.register_fields();
+        let calseg2 = xcp.create_calseg("test_remove2", &FLASH_PAGE2);
+        calseg2.register_fields();
+        assert_eq!(xcp.get_calseg_index("test_remove2"), Some(1));
+
+        xcp.remove_calseg("test_remove1").unwrap();
+        assert!(matches!(xcp.remove_calseg("test_remove1"), Err(XcpError::Registry(RegistryError::NotFound(_)))));
+
+        // The remaining segment is renumbered, its parameters are still registered
+        assert_eq!(xcp.get_calseg_index("test_remove1"), None);
+        assert_eq!(xcp.get_calseg_index("test_remove2"), Some(0));
+        {
+            let registry = xcp.get_registry();
+            let registry = registry.lock();
+            assert!(registry.find_characteristic("CalPage1.a").is_none());
+            assert!(registry.find_characteristic("CalPage2.a").is_some());
+        }
+
+        // A write to index 0 modifies the remaining segment
+        let value = 7u32.to_ne_bytes();
+        let offset: u32 = std::mem::offset_of!(CalPage2, b).try_into().unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert_eq!(xcp.calseg_list.lock().write_to(0, offset, 4, value.as_ptr(), 0, false).0, CalWriteStatus::Ok);
+        }
+        calseg2.sync();
+        assert_eq!(calseg2.b, 7);
+
+        // Not possible after the registry is finalized
+        xcp.write_a2l().unwrap();
+        assert!(xcp.remove_calseg("test_remove2").is_err());
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test snapshot and diff of calibration pages
+
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+    struct CalPageDiff {
+        #[type_description(unit = "ms")]
+        cycle_time: u32,
+        #[type_description(unit = "V")]
+        gain: f64,
+        offset: i16,
+        curve: [u8; 4],
+        map: [[i16; 3]; 2],
+    }
+
+    const FLASH_PAGE_DIFF: CalPageDiff = CalPageDiff {
+        cycle_time: 10,
+        gain: 1.0,
+        offset: -5,
+        curve: [0, 1, 2, 3],
+        map: [[0; 3]; 2],
+    };
+
+    #[test]
+    fn test_cal_seg_diff() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let calseg = xcp.create_calseg("CalPageDiff", &FLASH_PAGE_DIFF);
+        calseg.register_fields();
+
+        let snapshot = calseg.snapshot();
+        assert!(snapshot.diff(&calseg.snapshot()).is_empty());
+        assert!(calseg.diff_against_default().is_empty());
+
+        // Modify a scalar, an element of the curve and an element of the map with the XCP write path
+        // @@@@ Unsafe - Test
+        unsafe {
+            let offset: u32 = std::mem::offset_of!(CalPageDiff, gain).try_into().unwrap();
+            assert!(calseg.write(offset, 8, 2.5f64.to_ne_bytes().as_ptr(), 0));
+            let offset: u32 = (std::mem::offset_of!(CalPageDiff, curve) + 2).try_into().unwrap();
+            assert!(calseg.write(offset, 1, [7u8].as_ptr(), 0));
+            let offset: u32 = (std::mem::offset_of!(CalPageDiff, map) + (3 + 2) * 2).try_into().unwrap();
+            assert!(calseg.write(offset, 2, (-3i16).to_ne_bytes().as_ptr(), 0));
+        }
+
+        let diff = snapshot.diff(&calseg.snapshot());
+        assert_eq!(
+            diff.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(),
+            vec!["CalPageDiff.gain", "CalPageDiff.curve[2]", "CalPageDiff.map[1][2]"]
+        );
+        assert_eq!(diff[0].old, McValue::Float(1.0));
+        assert_eq!(diff[0].new, McValue::Float(2.5));
+        assert_eq!(diff[0].delta(), 1.5);
+        assert_eq!(diff[0].to_string(), "CalPageDiff.gain: 1 -> 2.5 V");
+        assert_eq!((diff[1].old, diff[1].new), (McValue::Unsigned(2), McValue::Unsigned(7)));
+        assert_eq!((diff[2].old, diff[2].new), (McValue::Signed(0), McValue::Signed(-3)));
+        assert_eq!(calseg.diff_against_default(), diff);
+
+        // A later modification is relative to the new snapshot
+        let snapshot = calseg.snapshot();
+        // @@@@ Unsafe - Test
+        unsafe {
+            let offset: u32 = std::mem::offset_of!(CalPageDiff, cycle_time).try_into().unwrap();
+            assert!(calseg.write(offset, 4, 20u32.to_ne_bytes().as_ptr(), 0));
+        }
+        let diff = snapshot.diff(&calseg.snapshot());
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].to_string(), "CalPageDiff.cycle_time: 10 -> 20 ms");
+        assert_eq!(calseg.diff_against_default().len(), 4);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test limit check of loaded json files
+
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+    struct CalPageLimits {
+        #[type_description(min = "0", max = "100")]
+        level: u8,
+        #[type_description(min = "-10", max = "10")]
+        offset: i16,
+        #[type_description(min = "0", max = "50")]
+        curve: [u8; 3],
+        #[type_description(min = "0.5", max = "2.0")]
+        gain: f64,
+    }
+
+    #[cfg(feature = "serde")]
+    const FLASH_PAGE_LIMITS: CalPageLimits = CalPageLimits {
+        level: 50,
+        offset: 0,
+        curve: [0, 10, 20],
+        gain: 1.0,
+    };
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_cal_seg_load_checked() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let calseg = xcp.create_calseg("CalPageLimits", &FLASH_PAGE_LIMITS);
+        calseg.register_fields();
+
+        // Values within the limits
+        let page = CalPageLimits {
+            level: 100,
+            offset: -10,
+            curve: [0, 25, 50],
+            gain: 2.0,
+        };
+        save(&page, "test_limits.json").unwrap();
+        assert!(calseg.load_checked("test_limits.json", CalLoadPolicy::Reject).unwrap().is_empty());
+        assert_eq!((calseg.level, calseg.offset, calseg.curve, calseg.gain), (100, -10, [0, 25, 50], 2.0));
+
+        // Values out of range
+        let page = CalPageLimits {
+            level: 200,
+            offset: -20,
+            curve: [0, 60, 50],
+            gain: 0.1,
+        };
+        save(&page, "test_limits.json").unwrap();
+        let names = |violations: &[CalLimitViolation]| {
+            let mut names: Vec<String> = violations.iter().map(|v| v.name.clone()).collect();
+            names.sort();
+            names
+        };
+        let expected = ["CalPageLimits.curve[1]", "CalPageLimits.gain", "CalPageLimits.level", "CalPageLimits.offset"];
+
+        // Reject, the page is not modified
+        let Err(CalLoadError::OutOfRange(violations)) = calseg.load_checked("test_limits.json", CalLoadPolicy::Reject) else {
+            panic!("Out of range values not rejected");
+        };
+        assert_eq!(names(&violations), expected);
+        assert_eq!(
+            *violations.iter().find(|v| v.name == "CalPageLimits.level").unwrap(),
+            CalLimitViolation {
+                name: "CalPageLimits.level".to_string(),
+                value: McValue::Unsigned(200),
+                min: 0.0,
+                max: 100.0
+            }
+        );
+        calseg.sync();
+        assert_eq!((calseg.level, calseg.offset, calseg.curve, calseg.gain), (100, -10, [0, 25, 50], 2.0));
+
+        // Clamp to the limits
+        let violations = calseg.load_checked("test_limits.json", CalLoadPolicy::Clamp).unwrap();
+        assert_eq!(names(&violations), expected);
+        assert_eq!((calseg.level, calseg.offset, calseg.curve, calseg.gain), (100, -10, [0, 50, 50], 0.5));
+
+        // Accept unchanged
+        let violations = calseg.load_checked("test_limits.json", CalLoadPolicy::Accept).unwrap();
+        assert_eq!(names(&violations), expected);
+        assert_eq!((calseg.level, calseg.offset, calseg.curve, calseg.gain), (200, -20, [0, 60, 50], 0.1));
+
+        assert!(matches!(calseg.load_checked("test_limits_missing.json", CalLoadPolicy::Accept), Err(CalLoadError::Io(_))));
+        let _ = std::fs::remove_file("test_limits.json");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test save with field comments and load of json5
+
+    #[cfg(feature = "json5")]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+    struct CalPageJson5Inner {
+        #[type_description(comment = "Inner gain", unit = "dB", min = "-20", max = "20")]
+        gain: f32,
+        #[type_description(comment = "Inner curve", min = "0", max = "255")]
+        curve: [u8; 3],
+    }
+
+    #[cfg(feature = "json5")]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+    struct CalPageJson5 {
+        #[type_description(comment = "Amplitude", unit = "Volt", min = "0", max = "400")]
+        ampl: f64,
+        inner: CalPageJson5Inner,
+        #[type_description(comment = "Enable\nmulti line comment")]
+        enable: bool,
+    }
+
+    #[cfg(feature = "json5")]
+    const FLASH_PAGE_JSON5: CalPageJson5 = CalPageJson5 {
+        ampl: 100.0,
+        inner: CalPageJson5Inner { gain: -3.5, curve: [1, 2, 3] },
+        enable: true,
+    };
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn test_cal_seg_json5() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let calseg = xcp.create_calseg("CalPageJson5", &FLASH_PAGE_JSON5);
+
+        // Each field is preceded by its comment, nested structs are nested objects
+        calseg.save_with_comments("test_cal_seg.json5").unwrap();
+        let s = std::fs::read_to_string("test_cal_seg.json5").unwrap();
+        info!("test_cal_seg.json5:\n{}", s);
+        let lines: Vec<&str> = s.lines().map(str::trim).collect();
+        let comment = |key: &str| lines[lines.iter().position(|l| l.starts_with(&format!("\"{}\":", key))).unwrap() - 1];
+        assert_eq!(comment("ampl"), "// Amplitude [Volt] min=0 max=400");
+        assert_eq!(comment("gain"), "// Inner gain [dB] min=-20 max=20");
+        assert_eq!(comment("curve"), "// Inner curve min=0 max=255");
+        assert!(comment("enable").starts_with("// Enable"));
+        assert!(lines.contains(&"\"inner\": {"));
+
+        // The output is loadable
+        let offset: u32 = std::mem::offset_of!(CalPageJson5, ampl).try_into().unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert!(calseg.write(offset, 8, 200.0f64.to_ne_bytes().as_ptr(), 0));
+        }
+        calseg.sync();
+        assert_eq!(calseg.ampl, 200.0);
+        calseg.load_json5("test_cal_seg.json5").unwrap();
+        assert_eq!(calseg.ampl, 100.0);
+        assert_eq!(calseg.inner.gain, -3.5);
+        assert_eq!(calseg.inner.curve, [1, 2, 3]);
+        assert!(calseg.enable);
+
+        assert!(calseg.load_json5("test_cal_seg_missing.json5").is_err());
+        let _ = std::fs::remove_file("test_cal_seg.json5");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test change notifications of calibration segment observers
+
+    #[test]
+    fn test_cal_seg_observer() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let calseg = xcp.create_calseg("CalPageObserver", &FLASH_PAGE_DIFF);
+        calseg.register_fields();
+        let (_, base_addr) = Xcp::get_calseg_ext_addr_base(calseg.get_index().try_into().unwrap());
+
+        let (tx1, rx1) = std::sync::mpsc::channel();
+        let observer1: Arc<dyn CalSegObserver> = Arc::new(ChannelObserver::new(tx1));
+        calseg.subscribe(Arc::clone(&observer1));
+        let (tx2, rx2) = std::sync::mpsc::channel();
+        let observer2: Arc<dyn CalSegObserver> = Arc::new(ChannelObserver::new(tx2));
+        calseg.clone().subscribe(Arc::clone(&observer2)); // Shared by all clones
+
+        // A write from the XCP tool notifies all observers
+        let offset: u32 = std::mem::offset_of!(CalPageDiff, gain).try_into().unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert_eq!(cb_write(base_addr | offset, 8, 2.5f64.to_ne_bytes().as_ptr(), 0), CRC_CMD_OK);
+        }
+        let change = CalSegChange {
+            field_name: "CalPageDiff.gain".to_string(),
+            old_bytes: 1.0f64.to_ne_bytes().to_vec(),
+            new_bytes: 2.5f64.to_ne_bytes().to_vec(),
+        };
+        assert_eq!(rx1.try_recv().unwrap(), change);
+        assert_eq!(rx2.try_recv().unwrap(), change);
+        assert!(rx1.try_recv().is_err());
+
+        // A write of an array element notifies the written bytes of the array
+        let offset: u32 = (std::mem::offset_of!(CalPageDiff, curve) + 2).try_into().unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert_eq!(cb_write(base_addr | offset, 2, [7u8, 8u8].as_ptr(), 0), CRC_CMD_OK);
+        }
+        let change = rx1.try_recv().unwrap();
+        assert_eq!(change.field_name, "CalPageDiff.curve");
+        assert_eq!((change.old_bytes, change.new_bytes), (vec![2, 3], vec![7, 8]));
+        assert_eq!(rx2.try_recv().unwrap().field_name, "CalPageDiff.curve");
+
+        // A dropped observer is unsubscribed
+        drop(observer2);
+        assert!(matches!(rx2.try_recv(), Err(std::sync::mpsc::TryRecvError::Disconnected)));
+        let offset: u32 = std::mem::offset_of!(CalPageDiff, cycle_time).try_into().unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert_eq!(cb_write(base_addr | offset, 4, 20u32.to_ne_bytes().as_ptr(), 0), CRC_CMD_OK);
+        }
+        assert_eq!(rx1.try_recv().unwrap().field_name, "CalPageDiff.cycle_time");
+
+        // No notifications after unsubscribe_all
+        calseg.unsubscribe_all();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert_eq!(cb_write(base_addr | offset, 4, 30u32.to_ne_bytes().as_ptr(), 0), CRC_CMD_OK);
+        }
+        assert!(rx1.try_recv().is_err());
+        calseg.sync();
+        assert_eq!(calseg.cycle_time, 30);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the audit log of calibration writes
+
+    #[test]
+    fn test_cal_audit() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let calseg = xcp.create_calseg("CalPageAudit", &FLASH_PAGE_DIFF);
+        calseg.register_fields();
+        let (_, base_addr) = Xcp::get_calseg_ext_addr_base(calseg.get_index().try_into().unwrap());
+        let (tx, rx) = std::sync::mpsc::channel();
+        xcp.set_cal_audit_sink(Box::new(move |change| tx.send(change).unwrap()));
+
+        // @@@@ Unsafe - Test
+        unsafe {
+            // Scalar, delayed write of a map element, partial write of an array
+            let offset: u32 = std::mem::offset_of!(CalPageDiff, gain).try_into().unwrap();
+            assert_eq!(cb_write(base_addr | offset, 8, 2.5f64.to_ne_bytes().as_ptr(), 0), CRC_CMD_OK);
+            let offset: u32 = (std::mem::offset_of!(CalPageDiff, map) + 3 * 2).try_into().unwrap();
+            assert_eq!(cb_write(base_addr | offset, 2, (-7i16).to_ne_bytes().as_ptr(), 1), CRC_CMD_OK);
+            assert_eq!(cb_flush(), CRC_CMD_OK);
+            let offset: u32 = (std::mem::offset_of!(CalPageDiff, curve) + 1).try_into().unwrap();
+            assert_eq!(cb_write(base_addr | offset, 2, [7u8, 8u8].as_ptr(), 0), CRC_CMD_OK);
+        }
+        xcp.clear_cal_audit_sink(); // Deliver all records
+        let changes: Vec<CalChange> = rx.try_iter().collect();
+        assert_eq!(changes.len(), 3);
+        assert!(changes.iter().all(|c| c.calseg_name == "CalPageAudit"));
+
+        assert_eq!(changes[0].names, vec!["CalPageDiff.gain"]);
+        assert_eq!((changes[0].offset, changes[0].len), (std::mem::offset_of!(CalPageDiff, gain), 8));
+        assert_eq!((changes[0].old_value, changes[0].new_value), (Some(McValue::Float(1.0)), Some(McValue::Float(2.5))));
+
+        assert_eq!(changes[1].names, vec!["CalPageDiff.map[1][0]"]);
+        assert_eq!((changes[1].old_bytes.clone(), changes[1].new_bytes.clone()), (vec![0, 0], (-7i16).to_ne_bytes().to_vec()));
+        assert_eq!((changes[1].old_value, changes[1].new_value), (Some(McValue::Signed(0)), Some(McValue::Signed(-7))));
+
+        assert_eq!(changes[2].names, vec!["CalPageDiff.curve"]);
+        assert_eq!((changes[2].old_bytes.clone(), changes[2].new_bytes.clone()), (vec![1, 2], vec![7, 8]));
+        assert_eq!((changes[2].old_value, changes[2].new_value), (None, None));
+        assert!(changes[0].timestamp <= changes[2].timestamp);
+
+        // Writes are dropped and counted, when the sink blocks
+        let (gate_tx, gate_rx) = std::sync::mpsc::channel::<()>();
+        let gate_rx = parking_lot::Mutex::new(gate_rx);
+        xcp.set_cal_audit_sink(Box::new(move |_| {
+            let _ = gate_rx.lock().recv();
+        }));
+        let offset: u32 = std::mem::offset_of!(CalPageDiff, cycle_time).try_into().unwrap();
+        for i in 0..300u32 {
+            // @@@@ Unsafe - Test
+            unsafe {
+                assert_eq!(cb_write(base_addr | offset, 4, i.to_ne_bytes().as_ptr(), 0), CRC_CMD_OK);
+            }
+        }
+        assert!(xcp.get_cal_audit_dropped() >= 300 - 256 - 1);
+        drop(gate_tx);
+        xcp.clear_cal_audit_sink();
+        assert_eq!(xcp.get_cal_audit_dropped(), 0);
+
+        // CSV file sink
+        xcp.set_cal_audit_sink(cal_audit_csv_sink("test_cal_audit.csv").unwrap());
+        let offset: u32 = std::mem::offset_of!(CalPageDiff, offset).try_into().unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert_eq!(cb_write(base_addr | offset, 2, 3i16.to_ne_bytes().as_ptr(), 0), CRC_CMD_OK);
+        }
+        xcp.clear_cal_audit_sink();
+        let csv = std::fs::read_to_string("test_cal_audit.csv").unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "timestamp,segment,names,offset,len,old_bytes,new_bytes,old_value,new_value");
+        assert!(lines[1].ends_with(&format!(",CalPageAudit,CalPageDiff.offset,{},2,FBFF,0300,-5,3", offset))); // Little endian
+        let _ = std::fs::remove_file("test_cal_audit.csv");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the padding bytes of a calibration page are zero and stable
+
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+    #[repr(C)]
+    struct CalPagePadding {
+        a: u8,  // 3 bytes padding
+        b: u32, //
+        c: u8,  // 7 bytes padding
+        d: u64,
+    }
+
+    static FLASH_PAGE_PADDING: CalPagePadding = CalPagePadding { a: 1, b: 2, c: 3, d: 4 };
+
+    #[test]
+    fn test_cal_seg_padding() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let calseg = xcp.create_calseg("test_padding", &FLASH_PAGE_PADDING);
+        let size = std::mem::size_of::<CalPagePadding>();
+        assert_eq!(size, 24);
+
+        // Upload the whole page with the XCP read path
+        let upload = || {
+            let mut buf = [0xFFu8; 24];
+            // @@@@ Unsafe - Test
+            unsafe {
+                assert!(calseg.read(0, size as u8, buf.as_mut_ptr()));
+            }
+            buf
+        };
+        let padding = |buf: &[u8; 24]| buf[1..4].iter().chain(&buf[9..16]).all(|&b| b == 0);
+
+        let page = upload();
+        assert!(padding(&page));
+        assert_eq!(page, upload());
+
+        // Write and init keep the padding
+        // @@@@ Unsafe - Test
+        unsafe {
+            let offset: u32 = std::mem::offset_of!(CalPagePadding, b).try_into().unwrap();
+            assert!(calseg.write(offset, 4, 7u32.to_ne_bytes().as_ptr(), 0));
+        }
+        calseg.sync();
+        let modified = upload();
+        assert!(padding(&modified));
+        assert_eq!(calseg.b, 7);
+        calseg.set_init_request();
+        calseg.sync();
+        assert_eq!(upload(), page);
+
+        // Clones share the same page
+        let calseg2 = calseg.clone();
+        calseg2.sync();
+        assert_eq!(calseg2.snapshot().as_bytes(), page);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test async save, load and freeze
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_cal_seg_async() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let calseg1 = xcp.create_calseg("test_async", &FLASH_PAGE1);
+
+        // Simulate a write from the XCP tool and save
+        let offset: u32 = std::mem::offset_of!(CalPage1, b).try_into().unwrap();
+        // @@@@ Unsafe - Test
+        unsafe {
+            assert!(calseg1.write(offset, 4, 7u32.to_ne_bytes().as_ptr(), 0));
+        }
+        assert!(calseg1.is_dirty());
+        calseg1.save_async("test_async.json").await.unwrap();
+        assert!(!calseg1.is_dirty());
+
+        // Modify and reload the saved page
+        unsafe {
+            assert!(calseg1.write(offset, 4, 9u32.to_ne_bytes().as_ptr(), 0));
+        }
+        calseg1.sync();
+        assert_eq!(calseg1.b, 9);
+        calseg1.load_async("test_async.json").await.unwrap();
+        assert_eq!(calseg1.b, 7);
+
+        // Freeze on the freeze executor
+        let _ = std::fs::remove_file("test_async.json");
+        xcp.set_freeze_executor(tokio::runtime::Handle::current());
+        cb_freeze_cal();
+        calseg1.sync();
+        let mut retries = 100;
+        while std::fs::metadata("test_async.json").is_err() {
+            retries -= 1;
+            assert!(retries > 0, "freeze file not written");
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+
+        let _ = std::fs::remove_file("test_async.json");
+    }
+
     //-----------------------------------------------------------------------------
     // Test cal page trait compiler errors
 
@@ -1050,4 +2209,300 @@ mod cal_tests {
         });
         t.join().unwrap();
     }
+
+    //-----------------------------------------------------------------------------
+    // Stress test wait free read access, readers must always observe a consistent page while the XCP write path modifies the page
+
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[derive(Debug, Clone, Copy)]
+    struct CalPageSeq {
+        a: u64,
+        data: [u64; 16],
+        b: u64,
+    }
+
+    static CAL_PAGE_SEQ: CalPageSeq = CalPageSeq { a: 0, data: [0; 16], b: 0 };
+
+    #[test]
+    fn test_cal_page_read_seq() {
+        const WRITE_COUNT: u64 = 100000;
+
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let cal_seg = xcp.create_calseg("CalPageSeq", &CAL_PAGE_SEQ);
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        // Readers
+        let mut readers = Vec::new();
+        for _ in 0..2 {
+            let cal_seg = cal_seg.clone();
+            let done = Arc::clone(&done);
+            readers.push(thread::spawn(move || {
+                let mut read_count = 0u64;
+                loop {
+                    let finished = done.load(std::sync::atomic::Ordering::Acquire);
+                    let a = cal_seg.read_seq(|page| {
+                        assert_eq!(page.a, page.b);
+                        assert!(page.data.iter().all(|&d| d == page.a));
+                        page.a
+                    });
+                    read_count += 1;
+                    if finished {
+                        assert_eq!(a, WRITE_COUNT); // Most recent page is visible after the writer is done
+                        break;
+                    }
+                }
+                read_count
+            }));
+        }
+
+        // Writer, modifies the page with delayed writes and flush like the XCP write path
+        let writer = cal_seg.clone();
+        let offset_a: u32 = std::mem::offset_of!(CalPageSeq, a).try_into().unwrap();
+        let offset_data: u32 = std::mem::offset_of!(CalPageSeq, data).try_into().unwrap();
+        let offset_b: u32 = std::mem::offset_of!(CalPageSeq, b).try_into().unwrap();
+        for i in 1..=WRITE_COUNT {
+            let value = i.to_ne_bytes();
+            let data = [i; 16];
+            // @@@@ Unsafe - Test
+            unsafe {
+                assert!(writer.write(offset_a, 8, value.as_ptr(), 1));
+                assert!(writer.write(offset_data, 128, data.as_ptr().cast::<u8>(), 1));
+                assert!(writer.write(offset_b, 8, value.as_ptr(), 1));
+            }
+            writer.flush();
+        }
+        done.store(true, std::sync::atomic::Ordering::Release);
+
+        for r in readers {
+            let read_count = r.join().unwrap();
+            info!("read_seq count = {}", read_count);
+        }
+
+        // read_lock observes the same page
+        cal_seg.sync();
+        assert_eq!(cal_seg.read_lock().a, WRITE_COUNT);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test slew rate limited application of XCP writes
+
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+    struct CalPageSlew {
+        #[type_description(slew_rate = "10.0")]
+        speed: f64,
+        #[type_description(slew_rate = "100")]
+        curve: [i16; 2],
+        gain: f64,
+    }
+
+    const FLASH_PAGE_SLEW: CalPageSlew = CalPageSlew {
+        speed: 0.0,
+        curve: [0, 0],
+        gain: 1.0,
+    };
+
+    #[test]
+    fn test_cal_seg_slew() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let calseg = xcp.create_calseg("CalPageSlew", &FLASH_PAGE_SLEW);
+        calseg.register_fields();
+        calseg.enable_slew(CalSlewMode::Tick);
+        assert!(!calseg.is_ramping());
+        let offset_speed: u32 = std::mem::offset_of!(CalPageSlew, speed).try_into().unwrap();
+        let offset_curve: u32 = std::mem::offset_of!(CalPageSlew, curve).try_into().unwrap();
+        let offset_gain: u32 = std::mem::offset_of!(CalPageSlew, gain).try_into().unwrap();
+        let write = |offset: u32, bytes: &[u8]| {
+            // @@@@ Unsafe - Test
+            unsafe {
+                assert!(calseg.write(offset, bytes.len().try_into().unwrap(), bytes.as_ptr(), 0));
+            }
+        };
+
+        // Parameters with slew rate ramp, others apply immediately
+        write(offset_speed, &2.0f64.to_ne_bytes());
+        write(offset_curve, &[10i16.to_ne_bytes(), (-10i16).to_ne_bytes()].concat());
+        write(offset_gain, &2.0f64.to_ne_bytes());
+        calseg.tick(Duration::from_millis(100));
+        assert!(calseg.is_ramping());
+        assert_eq!(calseg.speed, 1.0);
+        assert_eq!(calseg.curve, [10, -10]);
+        assert_eq!(calseg.gain, 2.0);
+        assert_eq!(calseg.read_seq(|page| page.speed), 2.0); // The XCP tool sees the target value
+        calseg.tick(Duration::from_millis(100));
+        assert_eq!(calseg.speed, 2.0);
+        assert!(!calseg.is_ramping());
+
+        // A write during a ramp restarts from the applied value towards the new target
+        write(offset_speed, &5.0f64.to_ne_bytes());
+        calseg.tick(Duration::from_millis(100));
+        assert_eq!(calseg.speed, 3.0);
+        write(offset_speed, &0.0f64.to_ne_bytes());
+        calseg.tick(Duration::from_millis(100));
+        assert_eq!(calseg.speed, 2.0);
+
+        // Clones continue the ramps independently
+        let calseg2 = calseg.clone();
+        calseg2.tick(Duration::from_millis(300));
+        assert!(!calseg2.is_ramping());
+        assert_eq!(calseg2.speed, 0.0);
+        assert_eq!(calseg.speed, 2.0);
+
+        // Init cancels the ramps and applies the default page immediately
+        write(offset_speed, &8.0f64.to_ne_bytes());
+        calseg.tick(Duration::from_millis(100));
+        assert!(calseg.is_ramping());
+        calseg.set_init_request();
+        calseg.tick(Duration::from_millis(100));
+        assert!(!calseg.is_ramping());
+        assert_eq!(calseg.speed, 0.0);
+        assert_eq!(calseg.gain, 1.0);
+
+        // A page switch cancels the ramps and applies the target values
+        write(offset_speed, &4.0f64.to_ne_bytes());
+        calseg.tick(Duration::from_millis(100));
+        assert_eq!(calseg.speed, 1.0);
+        xcp.set_cal_page_all(XcpCalPage::Flash);
+        calseg.tick(Duration::from_millis(100));
+        assert!(!calseg.is_ramping());
+        xcp.set_cal_page_all(XcpCalPage::Ram);
+        calseg.tick(Duration::from_millis(100));
+        assert!(!calseg.is_ramping());
+        assert_eq!(calseg.speed, 4.0);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test a calibration segment larger than one memory segment
+
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[derive(Debug, Clone, Copy, XcpTypeDescription)]
+    #[repr(C)]
+    struct CalPageLarge {
+        first: u32,
+        map0: [[f64; 32]; 32],
+        map1: [[f64; 32]; 32],
+        map2: [[f64; 32]; 32],
+        map3: [[f64; 32]; 32],
+        map4: [[f64; 32]; 32],
+        map5: [[f64; 32]; 32],
+        map6: [[f64; 32]; 32],
+        map7: [[f64; 32]; 32],
+        map8: [[f64; 32]; 32],
+        map9: [[f64; 32]; 32],
+        map10: [[f64; 32]; 32],
+        map11: [[f64; 32]; 32],
+        map12: [[f64; 32]; 32],
+        last: u32,
+    }
+
+    const FLASH_PAGE_LARGE: CalPageLarge = CalPageLarge {
+        first: 1,
+        map0: [[0.0; 32]; 32],
+        map1: [[0.0; 32]; 32],
+        map2: [[0.0; 32]; 32],
+        map3: [[0.0; 32]; 32],
+        map4: [[0.0; 32]; 32],
+        map5: [[0.0; 32]; 32],
+        map6: [[0.0; 32]; 32],
+        map7: [[0.0; 32]; 32],
+        map8: [[0.0; 32]; 32],
+        map9: [[0.0; 32]; 32],
+        map10: [[0.0; 32]; 32],
+        map11: [[0.0; 32]; 32],
+        map12: [[0.0; 32]; 32],
+        last: 2,
+    };
+
+    #[test]
+    fn test_cal_seg_large() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let size = std::mem::size_of::<CalPageLarge>();
+        assert_eq!(size, 8 + 13 * 8192 + 8);
+        let calseg = xcp.create_calseg("test_large", &FLASH_PAGE_LARGE);
+        calseg.register_fields();
+        let calseg_small = xcp.create_calseg("test_large_z", &FLASH_PAGE1);
+        calseg_small.register_fields();
+
+        // The large segment is split into 2 memory segments, the following segment is shifted by one index
+        let a2l = xcp.generate_a2l_string().unwrap();
+        assert!(a2l.contains(r#"test_large_0 "" DATA FLASH INTERN 0x80010000 65536"#));
+        assert!(a2l.contains(&format!(r#"test_large_1 "" DATA FLASH INTERN 0x80020000 {}"#, size - 0x10000)));
+        assert!(a2l.contains(r#"test_large_z "" DATA FLASH INTERN 0x80030000 12"#));
+        assert!(!a2l.contains("test_large_2"));
+
+        // Calibration parameters in the second memory segment have adjusted offsets
+        let offset_last = std::mem::offset_of!(CalPageLarge, last);
+        let offset_map = std::mem::offset_of!(CalPageLarge, map12);
+        let addr_last = 0x80020000 + (offset_last - 0x10000) as u32;
+        let addr_map = 0x80020000 + (offset_map - 0x10000) as u32;
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC CalPageLarge.first "" VALUE 0x80010000 "#));
+        assert!(a2l.contains(&format!(r#"/begin CHARACTERISTIC CalPageLarge.last "" VALUE 0x{:X} "#, addr_last)));
+        assert!(a2l.contains(&format!(r#"/begin CHARACTERISTIC CalPageLarge.map12 "" MAP 0x{:X} "#, addr_map)));
+
+        // Read and write fields in the second memory segment with the XCP callbacks
+        // @@@@ Unsafe - Test
+        unsafe {
+            let mut buf = [0u8; 4];
+            assert_eq!(cb_read(addr_last, 4, buf.as_mut_ptr()), CRC_CMD_OK);
+            assert_eq!(u32::from_ne_bytes(buf), 2);
+            assert_eq!(cb_write(addr_last, 4, 7u32.to_ne_bytes().as_ptr(), 0), CRC_CMD_OK);
+            let mut buf = [0u8; 8];
+            let addr = addr_map + (2 * 32 + 3) * 8;
+            assert_eq!(cb_write(addr, 8, 1.5f64.to_ne_bytes().as_ptr(), 0), CRC_CMD_OK);
+            assert_eq!(cb_read(addr, 8, buf.as_mut_ptr()), CRC_CMD_OK);
+            assert_eq!(f64::from_ne_bytes(buf), 1.5);
+
+            // The memory segment after the large segment
+            assert_eq!(cb_write(0x80030000 + 4, 4, 9u32.to_ne_bytes().as_ptr(), 0), CRC_CMD_OK);
+            assert_eq!(cb_read(0x80040000, 8, buf.as_mut_ptr()), CRC_ACCESS_DENIED);
+        }
+
+        // The application sees one coherent page
+        calseg.sync();
+        calseg_small.sync();
+        assert_eq!(calseg.first, 1);
+        assert_eq!(calseg.last, 7);
+        assert_eq!(calseg.read_lock().map12[2][3], 1.5);
+        assert_eq!(calseg_small.b, 9);
+
+        // Persistence of the whole page in one json file
+        #[cfg(feature = "serde")]
+        {
+            calseg.save("test_large.json").unwrap();
+            calseg.set_init_request();
+            calseg.sync();
+            assert_eq!(calseg.last, 2);
+            calseg.load("test_large.json").unwrap();
+            calseg.sync();
+            assert_eq!(calseg.last, 7);
+            assert_eq!(calseg.map12[2][3], 1.5);
+            std::fs::remove_file("test_large.json").ok();
+        }
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the read only EPK memory segment
+    #[test]
+    fn test_cal_seg_epk() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let calseg = xcp.create_calseg("test_epk", &FLASH_PAGE1);
+        calseg.register_fields();
+        let a2l = xcp.generate_a2l_string().unwrap();
+        assert!(a2l.contains(r#"/begin CHARACTERISTIC EPK "EPK" ASCII 0x80000000 U8 0 NO_COMPU_METHOD 0 255 READ_ONLY NUMBER 8 "#));
+
+        // The EPK is read with the generic memory segment read, it is read only and out of range reads are rejected
+        // @@@@ Unsafe - Test
+        unsafe {
+            let mut buf = [0u8; 8];
+            assert_eq!(cb_read(Xcp::XCP_EPK_ADDR, 8, buf.as_mut_ptr()), CRC_CMD_OK);
+            assert_eq!(&buf, b"TEST_EPK");
+            assert_eq!(cb_read(Xcp::XCP_EPK_ADDR + 4, 4, buf.as_mut_ptr()), CRC_CMD_OK);
+            assert_eq!(&buf[0..4], b"_EPK");
+            assert_eq!(cb_read(Xcp::XCP_EPK_ADDR + 4, 8, buf.as_mut_ptr()), CRC_ACCESS_DENIED);
+            assert_eq!(cb_write(Xcp::XCP_EPK_ADDR, 1, buf.as_ptr(), 0), CRC_ACCESS_DENIED);
+
+            // The first calibration segment follows the EPK memory segment
+            assert_eq!(cb_read(0x80010000, 4, buf.as_mut_ptr()), CRC_CMD_OK);
+        }
+    }
 }
@@ -0,0 +1,192 @@
+//----------------------------------------------------------------------------------------------
+// Module cal_slew
+// Slew rate limited application of calibration changes by the XCP tool
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::time::{Duration, Instant};
+
+use super::cal_snapshot::McValue;
+use crate::reg::RegistryDataType;
+use crate::xcp::Xcp;
+
+//----------------------------------------------------------------------------------------------
+// CalSlewMode
+
+/// Clock which advances the ramps of a calibration segment with slew enabled (CalSeg::enable_slew)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalSlewMode {
+    /// Ramps advance by the time step given to CalSeg::tick, called from the control loop
+    Tick,
+    /// Ramps advance by the elapsed time on each sync of the calibration segment, e.g. with read_lock
+    RealTime,
+}
+
+//----------------------------------------------------------------------------------------------
+// SlewField
+
+// Calibration parameter with slew rate of a calibration segment
+#[derive(Debug, Clone)]
+pub(crate) struct SlewField {
+    pub(crate) name: String,
+    pub(crate) datatype: RegistryDataType,
+    pub(crate) offset: usize, // Offset of the first element in the calibration segment
+    pub(crate) dim: (usize, usize),
+    rate: f64, // Units per second
+}
+
+// Get the calibration parameters with slew rate of the calibration segment calseg_name
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn get_slew_fields(calseg_name: &str) -> Vec<SlewField> {
+    let registry = Xcp::get().get_registry();
+    let registry = registry.lock();
+    registry
+        .get_cal_seg_characteristics(calseg_name)
+        .into_iter()
+        .filter(|c| c.get_alias_of().is_none())
+        .filter_map(|c| {
+            c.get_slew_rate().map(|rate| SlewField {
+                name: c.get_name().to_string(),
+                datatype: c.get_datatype(),
+                offset: c.get_addr_offset() as usize,
+                dim: c.get_dim(),
+                rate,
+            })
+        })
+        .collect()
+}
+
+//----------------------------------------------------------------------------------------------
+// CalSlew
+
+// Ramp of a single value from the applied value to the target value
+#[derive(Debug, Clone)]
+struct Ramp {
+    offset: usize,
+    datatype: RegistryDataType,
+    rate: f64,
+    value: f64,
+    target: f64,
+    target_bytes: Vec<u8>, // Exact target value, applied when the ramp completes
+}
+
+// Ramp state of a calibration segment clone with slew enabled
+// The page bytes are the active page of the clone, which holds the applied values
+#[derive(Debug, Clone)]
+pub(crate) struct CalSlew {
+    pub(crate) mode: CalSlewMode,
+    pub(crate) fields: Vec<SlewField>,
+    ramps: Vec<Ramp>,
+    last_time: Instant,
+    init_count: u16, // Init count of the calibration segment at the last update, an init cancels all ramps
+    cal_page: u8,    // Active ECU page at the last update, a page switch cancels all ramps
+}
+
+impl CalSlew {
+    pub(crate) fn new(mode: CalSlewMode, fields: Vec<SlewField>, init_count: u16, cal_page: u8) -> CalSlew {
+        CalSlew {
+            mode,
+            fields,
+            ramps: Vec::new(),
+            last_time: Instant::now(),
+            init_count,
+            cal_page,
+        }
+    }
+
+    pub(crate) fn is_ramping(&self) -> bool {
+        !self.ramps.is_empty()
+    }
+
+    // Cancel all ramps, if the calibration segment was initialized or the active page was switched since the last update
+    // The target values are applied immediately, if the page has not just been synced with the XCP page
+    // Returns true, if the ramps have been cancelled
+    pub(crate) fn check_cancel(&mut self, init_count: u16, cal_page: u8, page: &mut [u8], synced: bool) -> bool {
+        if init_count == self.init_count && cal_page == self.cal_page {
+            return false;
+        }
+        self.init_count = init_count;
+        self.cal_page = cal_page;
+        if !self.ramps.is_empty() {
+            debug!("slew: {} ramps cancelled", self.ramps.len());
+            for ramp in self.ramps.drain(..) {
+                if !synced {
+                    page[ramp.offset..ramp.offset + ramp.target_bytes.len()].copy_from_slice(&ramp.target_bytes);
+                }
+            }
+        }
+        true
+    }
+
+    // The whole page has been synced with the XCP page, old is the page before the sync
+    // Start or restart a ramp from the applied value for each modified value with slew rate and apply the start value
+    // A value equal to the target ends or skips the ramp, the compared values are decoded from the same datatype, so they compare exactly
+    #[allow(clippy::float_cmp)]
+    pub(crate) fn update(&mut self, old: &[u8], page: &mut [u8]) {
+        if self.ramps.is_empty() {
+            self.last_time = Instant::now();
+        }
+        for field in &self.fields {
+            let size = field.datatype.get_size();
+            for i in 0..field.dim.0 * field.dim.1 {
+                let offset = field.offset + i * size;
+                let target_bytes = &page[offset..offset + size];
+                let ramp = self.ramps.iter().position(|r| r.offset == offset);
+                if ramp.is_none() && target_bytes == &old[offset..offset + size] {
+                    continue;
+                }
+                let (Some(target), Some(old_value)) = (McValue::decode(field.datatype, target_bytes), McValue::decode(field.datatype, &old[offset..offset + size])) else {
+                    continue;
+                };
+                let value = ramp.map_or(old_value.as_f64(), |r| self.ramps[r].value);
+                let target = target.as_f64();
+                let target_bytes = target_bytes.to_vec();
+                match ramp {
+                    Some(r) if value == target => {
+                        self.ramps.swap_remove(r);
+                    }
+                    Some(r) => {
+                        self.ramps[r].target = target;
+                        self.ramps[r].target_bytes = target_bytes;
+                    }
+                    None if value == target => {}
+                    None => self.ramps.push(Ramp {
+                        offset,
+                        datatype: field.datatype,
+                        rate: field.rate,
+                        value,
+                        target,
+                        target_bytes,
+                    }),
+                }
+            }
+        }
+        for ramp in &self.ramps {
+            McValue::Float(ramp.value).encode(ramp.datatype, &mut page[ramp.offset..]);
+        }
+    }
+
+    // Advance all ramps by dt towards their target values and apply the new values
+    pub(crate) fn advance(&mut self, dt: Duration, page: &mut [u8]) {
+        self.last_time = Instant::now();
+        let dt = dt.as_secs_f64();
+        self.ramps.retain_mut(|ramp| {
+            let step = ramp.rate * dt;
+            if (ramp.target - ramp.value).abs() <= step {
+                page[ramp.offset..ramp.offset + ramp.target_bytes.len()].copy_from_slice(&ramp.target_bytes);
+                false
+            } else {
+                ramp.value += step.copysign(ramp.target - ramp.value);
+                McValue::Float(ramp.value).encode(ramp.datatype, &mut page[ramp.offset..]);
+                true
+            }
+        });
+    }
+
+    // Advance all ramps by the time elapsed since the last advance
+    pub(crate) fn advance_to_now(&mut self, page: &mut [u8]) {
+        let dt = self.last_time.elapsed();
+        self.advance(dt, page);
+    }
+}
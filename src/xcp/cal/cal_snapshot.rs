@@ -0,0 +1,214 @@
+//----------------------------------------------------------------------------------------------
+// Module cal_snapshot
+// Snapshot of a calibration page and field by field comparison of two snapshots
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::reg::RegistryDataType;
+use crate::xcp::Xcp;
+
+//----------------------------------------------------------------------------------------------
+// McValue
+
+/// Value of a single calibration parameter element, decoded from a calibration page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum McValue {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+}
+
+impl McValue {
+    // Decode a value of type datatype from native endian bytes
+    pub(crate) fn decode(datatype: RegistryDataType, bytes: &[u8]) -> Option<McValue> {
+        let size = datatype.get_size();
+        let mut b = [0u8; 8];
+        b[..size].copy_from_slice(bytes.get(..size)?);
+        Some(match datatype {
+            RegistryDataType::Ubyte => McValue::Unsigned(b[0] as u64),
+            RegistryDataType::Uword => McValue::Unsigned(u16::from_ne_bytes([b[0], b[1]]) as u64),
+            RegistryDataType::Ulong => McValue::Unsigned(u32::from_ne_bytes([b[0], b[1], b[2], b[3]]) as u64),
+            RegistryDataType::AUint64 => McValue::Unsigned(u64::from_ne_bytes(b)),
+            RegistryDataType::Sbyte => McValue::Signed(b[0] as i8 as i64),
+            RegistryDataType::Sword => McValue::Signed(i16::from_ne_bytes([b[0], b[1]]) as i64),
+            RegistryDataType::Slong => McValue::Signed(i32::from_ne_bytes([b[0], b[1], b[2], b[3]]) as i64),
+            RegistryDataType::AInt64 => McValue::Signed(i64::from_ne_bytes(b)),
+            RegistryDataType::Float32Ieee => McValue::Float(f32::from_ne_bytes([b[0], b[1], b[2], b[3]]) as f64),
+            RegistryDataType::Float64Ieee => McValue::Float(f64::from_ne_bytes(b)),
+            _ => return None,
+        })
+    }
+
+    // Encode the value as type datatype to native endian bytes
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub(crate) fn encode(self, datatype: RegistryDataType, bytes: &mut [u8]) {
+        let b = match datatype {
+            RegistryDataType::Ubyte => vec![self.as_f64() as u8],
+            RegistryDataType::Uword => (self.as_f64() as u16).to_ne_bytes().to_vec(),
+            RegistryDataType::Ulong => (self.as_f64() as u32).to_ne_bytes().to_vec(),
+            RegistryDataType::Sbyte => (self.as_f64() as i8).to_ne_bytes().to_vec(),
+            RegistryDataType::Sword => (self.as_f64() as i16).to_ne_bytes().to_vec(),
+            RegistryDataType::Slong => (self.as_f64() as i32).to_ne_bytes().to_vec(),
+            RegistryDataType::AUint64 => match self {
+                McValue::Unsigned(v) => v.to_ne_bytes().to_vec(),
+                _ => (self.as_f64() as u64).to_ne_bytes().to_vec(),
+            },
+            RegistryDataType::AInt64 => match self {
+                McValue::Signed(v) => v.to_ne_bytes().to_vec(),
+                _ => (self.as_f64() as i64).to_ne_bytes().to_vec(),
+            },
+            RegistryDataType::Float32Ieee => (self.as_f64() as f32).to_ne_bytes().to_vec(),
+            RegistryDataType::Float64Ieee => self.as_f64().to_ne_bytes().to_vec(),
+            _ => return,
+        };
+        bytes[..b.len()].copy_from_slice(&b);
+    }
+
+    // Clamp the value to the limits min and max, integers to the integer values within the limits
+    #[cfg(feature = "serde")]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+    pub(crate) fn clamp(self, min: f64, max: f64) -> McValue {
+        match self {
+            McValue::Unsigned(v) => McValue::Unsigned((v as f64).max(min.ceil()).min(max.floor()) as u64),
+            McValue::Signed(v) => McValue::Signed((v as f64).max(min.ceil()).min(max.floor()) as i64),
+            McValue::Float(v) => McValue::Float(v.max(min).min(max)),
+        }
+    }
+
+    /// Physical value
+    /// Calibration parameters have no conversion rule other than identity, the physical value is the raw value
+    #[allow(clippy::cast_precision_loss)]
+    pub fn as_f64(self) -> f64 {
+        match self {
+            McValue::Unsigned(v) => v as f64,
+            McValue::Signed(v) => v as f64,
+            McValue::Float(v) => v,
+        }
+    }
+}
+
+impl std::fmt::Display for McValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            McValue::Unsigned(v) => write!(f, "{}", v),
+            McValue::Signed(v) => write!(f, "{}", v),
+            McValue::Float(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// CalDiffEntry
+
+/// A modified calibration parameter or array element
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalDiffEntry {
+    pub name: String, // Name of the calibration parameter, with index suffix [x] or [x][y] for array elements
+    pub old: McValue,
+    pub new: McValue,
+    pub unit: &'static str,
+}
+
+impl CalDiffEntry {
+    /// Change of the physical value
+    pub fn delta(&self) -> f64 {
+        self.new.as_f64() - self.old.as_f64()
+    }
+}
+
+impl std::fmt::Display for CalDiffEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", self.name, self.old, self.new)?;
+        if !self.unit.is_empty() {
+            write!(f, " {}", self.unit)?;
+        }
+        Ok(())
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// CalSnapshot
+
+/// Copy of a calibration page of a calibration segment
+/// The registered calibration parameters of the segment are looked up in the registry by diff
+#[derive(Debug, Clone)]
+pub struct CalSnapshot {
+    calseg_name: &'static str,
+    bytes: Vec<u8>,
+}
+
+impl CalSnapshot {
+    pub(crate) fn new(calseg_name: &'static str, bytes: Vec<u8>) -> CalSnapshot {
+        CalSnapshot { calseg_name, bytes }
+    }
+
+    /// Get the name of the calibration segment
+    pub fn get_name(&self) -> &'static str {
+        self.calseg_name
+    }
+
+    /// Get the page content
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Compare with a later snapshot of the same calibration segment
+    /// Returns all modified calibration parameters with old (self) and new (other) value, sorted by offset
+    /// Arrays and maps report each modified element, aliases are not reported
+    ///
+    /// # Panics
+    /// If the snapshots are from different calibration segments
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn diff(&self, other: &CalSnapshot) -> Vec<CalDiffEntry> {
+        assert_eq!(self.calseg_name, other.calseg_name, "Snapshots of different calibration segments");
+        assert_eq!(self.bytes.len(), other.bytes.len());
+
+        let mut diff = Vec::new();
+        let registry = Xcp::get().get_registry();
+        let registry = registry.lock();
+        for c in registry.get_cal_seg_characteristics(self.calseg_name) {
+            if c.get_alias_of().is_some() {
+                continue;
+            }
+            let datatype = c.get_datatype();
+            if datatype == RegistryDataType::Blob {
+                continue;
+            }
+            let size = datatype.get_size();
+            let (x_dim, y_dim) = c.get_dim();
+            for i in 0..x_dim * y_dim {
+                let offset = c.get_addr_offset() as usize + i * size;
+                let (Some(old), Some(new)) = (self.bytes.get(offset..offset + size), other.bytes.get(offset..offset + size)) else {
+                    warn!("{} exceeds the size of calibration segment {}", c.get_name(), self.calseg_name);
+                    break;
+                };
+                if old == new {
+                    continue;
+                }
+                let (Some(old), Some(new)) = (McValue::decode(datatype, old), McValue::decode(datatype, new)) else {
+                    break;
+                };
+                diff.push(CalDiffEntry {
+                    name: element_name(c.get_name(), x_dim, y_dim, i),
+                    old,
+                    new,
+                    unit: c.get_unit(),
+                });
+            }
+        }
+        diff
+    }
+}
+
+// Name of element i of a calibration parameter, with index suffix [x] or [x][y] for array elements
+// Element [x][y] of a map is at x * y_dim + y, same as the Rust array [[T; y_dim]; x_dim]
+pub(crate) fn element_name(name: &str, x_dim: usize, y_dim: usize, i: usize) -> String {
+    if x_dim > 1 && y_dim > 1 {
+        format!("{}[{}][{}]", name, i / y_dim, i % y_dim)
+    } else if x_dim > 1 || y_dim > 1 {
+        format!("{}[{}]", name, i)
+    } else {
+        name.to_string()
+    }
+}
@@ -0,0 +1,207 @@
+//----------------------------------------------------------------------------------------------
+// Module clock
+// Resolution and epoch of the XCP clock and correlation with application timestamps
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+//----------------------------------------------------------------------------------------------
+// XcpClockInfo
+
+/// Epoch of the XCP clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XcpClockEpoch {
+    /// Arbitrary epoch, e.g. the system start, monotonic (xcplib OPTION_CLOCK_EPOCH_ARB)
+    Arbitrary,
+    /// Time since 1.1.1970, may be synchronized with PTP (xcplib OPTION_CLOCK_EPOCH_PTP)
+    Unix,
+}
+
+/// Resolution and epoch of the XCP clock, which timestamps the DAQ data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XcpClockInfo {
+    pub epoch: XcpClockEpoch,
+    /// Clock ticks per second
+    pub ticks_per_s: u64,
+}
+
+impl XcpClockInfo {
+    /// Duration of one clock tick
+    pub fn resolution(&self) -> Duration {
+        Duration::from_nanos(1_000_000_000 / self.ticks_per_s)
+    }
+
+    /// Convert a number of clock ticks to a duration
+    pub fn ticks_to_duration(&self, ticks: u64) -> Duration {
+        Duration::from_nanos((u128::from(ticks) * 1_000_000_000 / u128::from(self.ticks_per_s)).try_into().unwrap_or(u64::MAX))
+    }
+
+    /// Convert a duration to a number of clock ticks
+    pub fn duration_to_ticks(&self, duration: Duration) -> u64 {
+        (duration.as_nanos() * u128::from(self.ticks_per_s) / 1_000_000_000).try_into().unwrap_or(u64::MAX)
+    }
+}
+
+// Clock of xcplib, must match OPTION_CLOCK_EPOCH_xxx and OPTION_CLOCK_TICKS_xxx in xcplib/main_cfg.h
+pub(crate) const XCP_CLOCK_INFO: XcpClockInfo = XcpClockInfo {
+    epoch: XcpClockEpoch::Arbitrary,
+    ticks_per_s: 1_000_000_000,
+};
+
+//----------------------------------------------------------------------------------------------
+// XcpClockCorrelation
+
+// Number of attempts to read the clocks, the attempt with the shortest read time is used
+const CLOCK_CORRELATION_SAMPLES: usize = 8;
+
+/// Instant and SystemTime correlated with the XCP clock at one point in time
+/// Converts application side timestamps to the timebase of the DAQ data and back
+/// The clocks may drift apart slowly, e.g. by NTP adjustments of the clock behind Instant, correlate again periodically for long measurements
+#[derive(Debug, Clone, Copy)]
+pub struct XcpClockCorrelation {
+    info: XcpClockInfo,
+    clock: u64,
+    instant: Instant,
+    system_time: SystemTime,
+    uncertainty: Duration,
+}
+
+impl XcpClockCorrelation {
+    // Sample Instant and SystemTime between two reads of the XCP clock
+    pub(crate) fn new(info: XcpClockInfo, get_clock: impl Fn() -> u64) -> XcpClockCorrelation {
+        let sample = || {
+            let c1 = get_clock();
+            let instant = Instant::now();
+            let system_time = SystemTime::now();
+            let c2 = get_clock();
+            let width = c2.saturating_sub(c1);
+            (width, c1 + width / 2, instant, system_time)
+        };
+        let (width, clock, instant, system_time) = (0..CLOCK_CORRELATION_SAMPLES).map(|_| sample()).min_by_key(|s| s.0).unwrap();
+        XcpClockCorrelation {
+            info,
+            clock,
+            instant,
+            system_time,
+            uncertainty: info.ticks_to_duration(width / 2),
+        }
+    }
+
+    /// XCP clock value at the point of correlation
+    pub fn get_clock(&self) -> u64 {
+        self.clock
+    }
+
+    /// Instant at the point of correlation
+    pub fn get_instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// SystemTime at the point of correlation
+    pub fn get_system_time(&self) -> SystemTime {
+        self.system_time
+    }
+
+    /// Max error of the correlation, half of the time it took to read the clocks
+    pub fn get_uncertainty(&self) -> Duration {
+        self.uncertainty
+    }
+
+    /// Resolution and epoch of the XCP clock
+    pub fn get_clock_info(&self) -> XcpClockInfo {
+        self.info
+    }
+
+    /// XCP clock value of an Instant
+    pub fn instant_to_clock(&self, instant: Instant) -> u64 {
+        if instant >= self.instant {
+            self.clock.saturating_add(self.info.duration_to_ticks(instant - self.instant))
+        } else {
+            self.clock.saturating_sub(self.info.duration_to_ticks(self.instant - instant))
+        }
+    }
+
+    /// Instant of an XCP clock value
+    /// # Panics
+    /// Panics, if the clock value is before the earliest Instant of the platform
+    pub fn clock_to_instant(&self, clock: u64) -> Instant {
+        if clock >= self.clock {
+            self.instant + self.info.ticks_to_duration(clock - self.clock)
+        } else {
+            self.instant
+                .checked_sub(self.info.ticks_to_duration(self.clock - clock))
+                .expect("clock value out of Instant range")
+        }
+    }
+
+    /// XCP clock value of a SystemTime
+    pub fn system_time_to_clock(&self, system_time: SystemTime) -> u64 {
+        match system_time.duration_since(self.system_time) {
+            Ok(d) => self.clock.saturating_add(self.info.duration_to_ticks(d)),
+            Err(e) => self.clock.saturating_sub(self.info.duration_to_ticks(e.duration())),
+        }
+    }
+
+    /// Offset of the XCP clock to the system time since 1.1.1970 in clock ticks, clock = system time + offset
+    /// Close to 0 for a clock with epoch Unix, except for the difference between TAI and UTC
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn get_system_time_offset(&self) -> i64 {
+        let system_ticks = self.info.duration_to_ticks(self.system_time.duration_since(UNIX_EPOCH).unwrap_or_default());
+        self.clock.wrapping_sub(system_ticks) as i64
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// Test module
+
+#[cfg(test)]
+mod clock_tests {
+
+    use super::*;
+    use crate::xcp::xcp_test;
+
+    #[test]
+    fn test_clock_correlation_conversions() {
+        // Fake clock with 1us resolution
+        let info = XcpClockInfo {
+            epoch: XcpClockEpoch::Arbitrary,
+            ticks_per_s: 1_000_000,
+        };
+        assert_eq!(info.resolution(), Duration::from_micros(1));
+        let c = XcpClockCorrelation::new(info, || 1_000_000);
+        assert_eq!(c.get_clock(), 1_000_000);
+        assert_eq!(c.get_uncertainty(), Duration::ZERO);
+
+        let t = c.get_instant();
+        assert_eq!(c.instant_to_clock(t + Duration::from_millis(5)), 1_005_000);
+        let t_before = t.checked_sub(Duration::from_millis(5)).unwrap();
+        assert_eq!(c.instant_to_clock(t_before), 995_000);
+        assert_eq!(c.clock_to_instant(1_005_000), t + Duration::from_millis(5));
+        assert_eq!(c.clock_to_instant(995_000), t_before);
+
+        let s = c.get_system_time();
+        assert_eq!(c.system_time_to_clock(s + Duration::from_secs(2)), 3_000_000);
+        assert_eq!(c.system_time_to_clock(s - Duration::from_millis(1)), 999_000);
+        let since_epoch = s.duration_since(UNIX_EPOCH).unwrap();
+        assert_eq!(c.get_system_time_offset(), 1_000_000 - i64::try_from(since_epoch.as_micros()).unwrap());
+    }
+
+    #[test]
+    fn test_clock_correlation() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+        let info = xcp.clock_epoch_info();
+        assert_eq!(info.epoch, XcpClockEpoch::Arbitrary);
+        assert_eq!(info.resolution(), Duration::from_nanos(1));
+
+        let c = xcp.correlate_clock();
+        assert!(c.get_uncertainty() < Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(10));
+
+        // An Instant maps to the XCP clock read at the same time
+        let instant = Instant::now();
+        let clock = xcp.get_clock();
+        let max_error = info.duration_to_ticks(Duration::from_millis(1));
+        assert!(c.instant_to_clock(instant).abs_diff(clock) < max_error);
+        assert!(c.system_time_to_clock(SystemTime::now()).abs_diff(xcp.get_clock()) < max_error + info.duration_to_ticks(Duration::from_millis(10)));
+        assert_eq!(c.clock_to_instant(c.instant_to_clock(instant)), instant);
+    }
+}
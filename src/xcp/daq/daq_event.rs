@@ -4,12 +4,74 @@
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 
-use crate::{reg::RegistryMeasurement, xcp::*, RegistryDataType};
+use std::mem::offset_of;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::{reg::RegistryMeasurement, xcp::*, RegistryDataType, BLOB_LENGTH_PREFIX_SIZE};
+
+//----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+// DAQ error
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaqError {
+    #[error("daq error: not enough space in the transmit queue")]
+    QueueFull,
+
+    #[error("daq error: timeout waiting for space in the transmit queue")]
+    Timeout,
+
+    #[error("daq error: trigger without base object")]
+    NoBase,
+
+    #[error("daq error: base object with event offset {0} is not at this offset relative to the first base object")]
+    BaseOffset(i16),
+
+    #[error("daq error: measurement with event offset {offset} and size {len} is not inside a base object")]
+    Uncovered { offset: i16, len: usize },
+}
+
+// Transmit queue space of a DTO packet in addition to its payload
+// Transport layer header (4), DAQ header (4), timestamp (4) and alignment (4)
+const DTO_PACKET_OVERHEAD: usize = 16;
+
+// Poll interval of DaqEvent::wait_queue_space
+const QUEUE_SPACE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Admission of a trigger with the required queue space, depending on the free queue space and the space reserved for high priority events
+// Normal priority events must leave the reserve free, high priority events may use all of the queue
+fn queue_admit(priority: XcpEventPriority, free: usize, required: usize, reserve: usize) -> bool {
+    match priority {
+        XcpEventPriority::Normal => free >= required + reserve,
+        XcpEventPriority::High => free >= required,
+    }
+}
 
 //----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
 // XcpEvent
 
 impl Xcp {
+    /// Get the trigger timing statistics of all events with timing metrics enabled, see DaqEvent::enable_timing_metrics
+    /// The statistics are read from atomics, a concurrent trigger is not blocked
+    pub fn get_daq_timing_stats(&self) -> Vec<(&'static str, DaqTimingStats)> {
+        DAQ_TIMING_LIST.lock().unwrap().iter().filter_map(Weak::upgrade).map(|t| (t.name, t.get_stats())).collect()
+    }
+
+    /// Enable or disable the runtime gated measurements of a measurement group, see DaqEvent::add_gate
+    /// The measurements of a disabled group are in the A2L, but they are not captured and their auxiliary events are not triggered
+    /// Takes effect with the next trigger cycle of each event, a cycle already in progress completes with the previous state
+    pub fn enable_measurement_group(&self, group: &str, enabled: bool) {
+        daq_gate(group, enabled).store(enabled, Ordering::Release);
+    }
+
+    /// Check if a measurement group is enabled, an unknown group is disabled
+    pub fn is_measurement_group_enabled(&self, group: &str) -> bool {
+        DAQ_GATE_LIST.lock().unwrap().iter().any(|(g, enabled)| *g == group && enabled.load(Ordering::Acquire))
+    }
+
     /// Create a measurement event and a measurement variable directly associated to the event with memory offset 0
     pub fn create_measurement_object(&self, name: &'static str, data_type: RegistryDataType, x_dim: u16, y_dim: u16, comment: &'static str) -> XcpEvent {
         let event = self.create_event(name);
@@ -51,15 +113,358 @@ macro_rules! daq_event_ref {
     }};
 }
 
+// Base object of a validated trigger, address and size of the object and its offset relative to the event base
+#[derive(Debug, Clone, Copy)]
+struct DaqBase {
+    addr: usize,
+    len: usize,
+    offset: i16,
+}
+
+/// Builder for a trigger with relative addressing mode (XCP_ADDR_EXT_DYN), which captures one or multiple base objects
+/// Each object is added with its event offset, as used when its measurements were registered
+/// The trigger validates the objects against the registry, before the base pointer is given to the XCP server
+/// This is the safe alternative to XcpEvent::trigger_ext
+#[derive(Debug)]
+pub struct DaqTrigger<'a> {
+    event: XcpEvent,
+    bases: Vec<DaqBase>,
+    _lifetime: std::marker::PhantomData<&'a ()>,
+}
+
+impl<'a> DaqTrigger<'a> {
+    /// Add a base object, which is located at the given offset relative to the event base
+    #[must_use]
+    pub fn base<T>(mut self, obj: &'a T, offset: i16) -> Self {
+        self.bases.push(DaqBase {
+            addr: obj as *const T as usize,
+            len: std::mem::size_of::<T>(),
+            offset,
+        });
+        self
+    }
+
+    /// Validate the base objects and get the event base pointer
+    /// All objects must have the same address relative to the event base as their offset
+    /// All measurements registered with relative addressing on this event must be inside a base object
+    /// Locks the registry, the measurements must be registered before
+    pub fn validate(&self) -> Result<*const u8, DaqError> {
+        let first = self.bases.first().ok_or(DaqError::NoBase)?;
+        let base = first.addr.wrapping_add_signed(-isize::from(first.offset));
+        if let Some(b) = self.bases.iter().find(|b| b.addr != base.wrapping_add_signed(isize::from(b.offset))) {
+            return Err(DaqError::BaseOffset(b.offset));
+        }
+        let offsets = Xcp::get().get_registry().lock().get_event_offsets(self.event);
+        for (offset, len) in offsets {
+            let (start, end) = (isize::from(offset), isize::from(offset) + len as isize);
+            if !self.bases.iter().any(|b| start >= isize::from(b.offset) && end <= isize::from(b.offset) + b.len as isize) {
+                return Err(DaqError::Uncovered { offset, len });
+            }
+        }
+        Ok(base as *const u8)
+    }
+
+    /// Validate the base objects and trigger the event, nothing is triggered when the validation fails
+    pub fn trigger(&self) -> Result<(), DaqError> {
+        let base = self.validate()?;
+        // @@@@ Unsafe - C library call which will dereference the raw pointer base
+        // All measurements of the event are inside the base objects, which are borrowed for the lifetime of self
+        unsafe {
+            self.event.trigger_ext(base);
+        }
+        Ok(())
+    }
+}
+
+impl XcpEvent {
+    /// Create a builder for a validated trigger with one or multiple base objects, see DaqTrigger
+    pub fn trigger_builder<'a>(self) -> DaqTrigger<'a> {
+        DaqTrigger {
+            event: self,
+            bases: Vec::new(),
+            _lifetime: std::marker::PhantomData,
+        }
+    }
+
+    /// Trigger with a single registered object as base, its measurements registered with event offsets relative to the object start
+    /// Returns an error and does not trigger, when a measurement of this event is not inside the object
+    pub fn trigger_with<T>(self, obj: &T) -> Result<(), DaqError> {
+        self.trigger_builder().base(obj, 0).trigger()
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+// DaqGate
+
+// Gate flags of the measurement groups, created on first use and never dropped
+static DAQ_GATE_LIST: Mutex<Vec<(&'static str, &'static AtomicBool)>> = Mutex::new(Vec::new());
+
+// Get or create the gate flag of a measurement group, a new gate starts with the given state
+fn daq_gate(group: &str, enabled: bool) -> &'static AtomicBool {
+    let mut list = DAQ_GATE_LIST.lock().unwrap();
+    if let Some((_, flag)) = list.iter().find(|(g, _)| *g == group) {
+        return flag;
+    }
+    let flag: &'static AtomicBool = Box::leak(Box::new(AtomicBool::new(enabled)));
+    list.push((Box::leak(group.to_string().into_boxed_str()), flag));
+    flag
+}
+
+// Sampled state of a gate in the current trigger cycle
+const DAQ_GATE_UNSAMPLED: u8 = 0;
+const DAQ_GATE_CLOSED: u8 = 1;
+const DAQ_GATE_OPEN: u8 = 2;
+
+// Auxiliary event "<event>_<group>" of a DaqEvent, which transmits the measurements of a gated measurement group
+// The gate flag is sampled once per trigger cycle, by the first gated capture or by the trigger, so a cycle never transmits values which were not captured
+#[derive(Debug)]
+struct DaqGate {
+    group: &'static str,
+    event: XcpEvent,
+    enabled: &'static AtomicBool,
+    state: AtomicU8,
+}
+
+impl DaqGate {
+    fn new(event: XcpEvent, group: &'static str, enabled: bool) -> DaqGate {
+        DaqGate {
+            group,
+            event: Xcp::get().create_gate_event(event, group),
+            enabled: daq_gate(group, enabled),
+            state: AtomicU8::new(DAQ_GATE_UNSAMPLED),
+        }
+    }
+
+    // Get the gate state of the current trigger cycle
+    fn is_open(&self) -> bool {
+        match self.state.load(Ordering::Relaxed) {
+            DAQ_GATE_UNSAMPLED => {
+                let open = self.enabled.load(Ordering::Acquire);
+                self.state.store(if open { DAQ_GATE_OPEN } else { DAQ_GATE_CLOSED }, Ordering::Relaxed);
+                open
+            }
+            state => state == DAQ_GATE_OPEN,
+        }
+    }
+
+    // Trigger the auxiliary event with the base of the parent event, if the gate is open, and start a new cycle
+    fn trigger(&self, base: *const u8) {
+        let open = self.is_open();
+        self.state.store(DAQ_GATE_UNSAMPLED, Ordering::Relaxed);
+        if open {
+            // @@@@ Unsafe - C library call which will dereference the raw pointer base
+            unsafe {
+                self.event.trigger_ext(base);
+            }
+        }
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+// DaqOnChange
+
+// Capture buffer of the auxiliary event "<event>_onchange" of a DaqEvent
+// Holds the last captured values of the on change measurements, the auxiliary event is triggered only when any of them changed
+// XCP ODTs have a fixed layout, so the on change measurements are transmitted in their own DAQ list
+#[derive(Debug)]
+struct DaqOnChange<const N: usize> {
+    event: XcpEvent,
+    buffer_len: usize,
+    changed: AtomicBool,
+    daq_start_count: AtomicU32,
+    buffer: [u8; N],
+}
+
+impl<const N: usize> DaqOnChange<N> {
+    fn new(event: XcpEvent) -> DaqOnChange<N> {
+        DaqOnChange {
+            event,
+            buffer_len: 0,
+            changed: AtomicBool::new(true), // Transmit the initial values
+            daq_start_count: AtomicU32::new(Xcp::get().get_daq_start_count()),
+            buffer: [0; N],
+        }
+    }
+
+    // Trigger the auxiliary event, if any value changed since the last trigger or DAQ has been restarted
+    fn trigger(&self) {
+        let daq_start_count = Xcp::get().get_daq_start_count();
+        let restarted = self.daq_start_count.swap(daq_start_count, Ordering::Relaxed) != daq_start_count;
+        if self.changed.swap(false, Ordering::Relaxed) || restarted {
+            // @@@@ Unsafe - C library call which will dereference the raw pointer base
+            unsafe {
+                self.event.trigger_ext(self.buffer.as_ptr());
+            }
+        }
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+// DaqTiming
+
+/// Number of bins of the jitter histogram
+/// Bin 0 counts cycles with less than 1us jitter, bin i counts jitter in [2^(i-1), 2^i) us, the last bin counts all larger jitter
+pub const DAQ_TIMING_HIST_BINS: usize = 16;
+
+/// Trigger timing statistics of a DaqEvent, see DaqEvent::enable_timing_metrics
+/// Jitter is the absolute deviation of a cycle time from the mean cycle time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DaqTimingStats {
+    pub count: u32, // Number of measured cycles
+    pub cycle_us: u32,
+    pub cycle_min_us: u32,
+    pub cycle_max_us: u32,
+    pub cycle_mean_us: f64,
+    pub jitter_max_us: u32,
+    pub jitter_hist: [u32; DAQ_TIMING_HIST_BINS],
+}
+
+// Timing measurement signals of the auxiliary event "<event>_timing", addressed relative to the start of this struct
+#[repr(C)]
+#[derive(Debug)]
+struct DaqTimingData {
+    cycle_us: AtomicU32,
+    cycle_min_us: AtomicU32,
+    cycle_max_us: AtomicU32,
+    jitter_max_us: AtomicU32,
+    cycle_mean_us: AtomicU64, // f64 bits
+    count: AtomicU32,
+    jitter_hist: [AtomicU32; DAQ_TIMING_HIST_BINS],
+}
+
+// Inter trigger timing of a DaqEvent
+// Updated lock free on each trigger, the event owner is the only writer
+#[derive(Debug)]
+struct DaqTiming {
+    name: &'static str,
+    event: XcpEvent,
+    start_time: Instant,
+    last_trigger_ns: AtomicU64, // Time of the last trigger in ns since start_time, u64::MAX before the first trigger
+    data: DaqTimingData,
+}
+
+// All DaqTimings with timing metrics enabled, for Xcp::get_daq_timing_stats
+// Never locked by a trigger, a DaqTiming is dropped with its DaqEvent
+static DAQ_TIMING_LIST: Mutex<Vec<Weak<DaqTiming>>> = Mutex::new(Vec::new());
+
+impl DaqTiming {
+    fn new(name: &'static str, event: XcpEvent) -> DaqTiming {
+        DaqTiming {
+            name,
+            event,
+            start_time: Instant::now(),
+            last_trigger_ns: AtomicU64::new(u64::MAX),
+            data: DaqTimingData {
+                cycle_us: AtomicU32::new(0),
+                cycle_min_us: AtomicU32::new(u32::MAX),
+                cycle_max_us: AtomicU32::new(0),
+                jitter_max_us: AtomicU32::new(0),
+                cycle_mean_us: AtomicU64::new(0f64.to_bits()),
+                count: AtomicU32::new(0),
+                jitter_hist: std::array::from_fn(|_| AtomicU32::new(0)),
+            },
+        }
+    }
+
+    // Register the measurement signals "<name>_cycle_us", ... of the auxiliary event
+    fn register(&self, name: &str) {
+        let hist_bins: u16 = DAQ_TIMING_HIST_BINS.try_into().unwrap();
+        let signals: [(&str, usize, RegistryDataType, u16, &'static str); 6] = [
+            ("cycle_us", offset_of!(DaqTimingData, cycle_us), RegistryDataType::Ulong, 1, "Last cycle time"),
+            ("cycle_min_us", offset_of!(DaqTimingData, cycle_min_us), RegistryDataType::Ulong, 1, "Minimum cycle time"),
+            ("cycle_max_us", offset_of!(DaqTimingData, cycle_max_us), RegistryDataType::Ulong, 1, "Maximum cycle time"),
+            (
+                "cycle_mean_us",
+                offset_of!(DaqTimingData, cycle_mean_us),
+                RegistryDataType::Float64Ieee,
+                1,
+                "Mean cycle time",
+            ),
+            ("jitter_max_us", offset_of!(DaqTimingData, jitter_max_us), RegistryDataType::Ulong, 1, "Maximum jitter"),
+            (
+                "jitter_hist",
+                offset_of!(DaqTimingData, jitter_hist),
+                RegistryDataType::Ulong,
+                hist_bins,
+                "Jitter histogram",
+            ),
+        ];
+        let registry = Xcp::get().get_registry();
+        let mut registry = registry.lock();
+        for (suffix, offset, datatype, x_dim, comment) in signals {
+            // The names live as long as the registry, measurements are never dropped
+            let name: &'static str = Box::leak(format!("{}_{}", name, suffix).into_boxed_str());
+            let unit = if suffix == "jitter_hist" { "" } else { "us" };
+            let m = RegistryMeasurement::new(name, datatype, x_dim, 1, self.event, offset.try_into().unwrap(), 0u64, 1.0, 0.0, comment, unit, None);
+            if registry.add_measurement(m).is_err() {
+                error!("Error: Measurement {} already exists", name);
+            }
+        }
+    }
+
+    // Record the time since the last trigger and trigger the auxiliary event
+    // Costs a clock read and a few atomic operations, no allocation
+    // The time since start and the jitter are far below the truncation limits
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn trigger(&self) {
+        let now = self.start_time.elapsed().as_nanos() as u64;
+        let last = self.last_trigger_ns.swap(now, Ordering::Relaxed);
+        if last == u64::MAX {
+            return; // First trigger
+        }
+        let cycle_us = u32::try_from(now.saturating_sub(last) / 1000).unwrap_or(u32::MAX);
+
+        let d = &self.data;
+        let count = d.count.load(Ordering::Relaxed).saturating_add(1);
+        d.count.store(count, Ordering::Relaxed);
+        d.cycle_us.store(cycle_us, Ordering::Relaxed);
+        d.cycle_min_us.fetch_min(cycle_us, Ordering::Relaxed);
+        d.cycle_max_us.fetch_max(cycle_us, Ordering::Relaxed);
+        let mean = f64::from_bits(d.cycle_mean_us.load(Ordering::Relaxed));
+        let mean = mean + (f64::from(cycle_us) - mean) / f64::from(count);
+        d.cycle_mean_us.store(mean.to_bits(), Ordering::Relaxed);
+        let jitter_us = (f64::from(cycle_us) - mean).abs() as u32;
+        d.jitter_max_us.fetch_max(jitter_us, Ordering::Relaxed);
+        let bin = ((u32::BITS - jitter_us.leading_zeros()) as usize).min(DAQ_TIMING_HIST_BINS - 1);
+        d.jitter_hist[bin].fetch_add(1, Ordering::Relaxed);
+
+        // @@@@ Unsafe - C library call which will dereference the raw pointer base
+        unsafe {
+            self.event.trigger_ext(d as *const DaqTimingData as *const u8);
+        }
+    }
+
+    fn get_stats(&self) -> DaqTimingStats {
+        let d = &self.data;
+        let count = d.count.load(Ordering::Relaxed);
+        DaqTimingStats {
+            count,
+            cycle_us: d.cycle_us.load(Ordering::Relaxed),
+            cycle_min_us: if count == 0 { 0 } else { d.cycle_min_us.load(Ordering::Relaxed) },
+            cycle_max_us: d.cycle_max_us.load(Ordering::Relaxed),
+            cycle_mean_us: f64::from_bits(d.cycle_mean_us.load(Ordering::Relaxed)),
+            jitter_max_us: d.jitter_max_us.load(Ordering::Relaxed),
+            jitter_hist: std::array::from_fn(|i| d.jitter_hist[i].load(Ordering::Relaxed)),
+        }
+    }
+}
+
 //----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
 // DaqEvent
 
 /// DaqEvent is a wrapper for XcpEvent which adds the capabilizy to read variables from stack or adds an optional capture buffer to capture variable values
+/// Variables captured with daq_capture_on_change are transmitted by the auxiliary event "<event>_onchange", only when their value changed
+/// Once an event with XcpEventPriority::High exists, triggers of normal priority events are dropped, when they would use the queue space reserved for it
+/// Variables of a runtime gated measurement group are transmitted by the auxiliary event "<event>_<group>", only while the group is enabled
 #[derive(Debug)]
 pub struct DaqEvent<const N: usize> {
     event: XcpEvent,
+    priority: XcpEventPriority,
     buffer_len: usize,
     pub buffer: [u8; N],
+    on_change: Option<Box<DaqOnChange<N>>>,
+    timing: Option<Arc<DaqTiming>>,
+    gates: Vec<DaqGate>,
 }
 
 impl PartialEq for DaqEvent<0> {
@@ -74,24 +479,67 @@ impl<const N: usize> DaqEvent<N> {
         let xcp = Xcp::get();
         DaqEvent {
             event: xcp.create_event_ext(name, false, 0),
+            priority: XcpEventPriority::Normal,
             buffer_len: 0,
             buffer: [0; N],
+            on_change: None,
+            timing: None,
+            gates: Vec::new(),
         }
     }
 
     /// Create a new DaqEvent from an existing XcpEvent
+    /// A high priority event reserves the queue space for its capture buffer
     pub fn new_from(xcp_event: &XcpEvent) -> DaqEvent<N> {
-        DaqEvent {
+        let xcp = Xcp::get();
+        let daq_event = DaqEvent {
             event: *xcp_event,
+            priority: xcp.get_event_priority(*xcp_event),
             buffer_len: 0,
             buffer: [0; N],
+            on_change: None,
+            timing: None,
+            gates: Vec::new(),
+        };
+        if daq_event.priority == XcpEventPriority::High {
+            xcp.daq_priority_reserve.fetch_max(daq_event.get_required_queue_space(), Ordering::Relaxed);
         }
+        daq_event
     }
 
     fn get_xcp_event(&self) -> XcpEvent {
         self.event
     }
 
+    /// Set the data consistency of the event
+    /// XcpEventConsistency::Event tells the XCP tool, that all measurements of this event are one consistent snapshot
+    pub fn set_consistency(&self, consistency: XcpEventConsistency) {
+        Xcp::get().set_event_consistency(self.event, consistency);
+    }
+
+    /// Get the transmit priority of the event
+    pub fn get_priority(&self) -> XcpEventPriority {
+        self.priority
+    }
+
+    // Check the transmit queue space before a trigger and count the drops
+    // Normal priority events are checked only when a high priority event exists, they are dropped when they would use its reserved space
+    // High priority events are always triggered, a drop is counted when the capture buffer does not fit
+    // Without running DAQ nothing is transmitted, so nothing is dropped
+    fn admit(&self) -> bool {
+        let xcp = Xcp::get();
+        let reserve = xcp.daq_priority_reserve.load(Ordering::Relaxed);
+        if reserve == 0 || !xcp.get_session_status().contains(XcpSessionStatus::SS_DAQ) {
+            return true;
+        }
+        let size = xcp.get_transmit_queue_size();
+        if size == 0 || queue_admit(self.priority, xcp.get_transmit_queue_free(), self.get_required_queue_space(), reserve) {
+            return true;
+        }
+        xcp.daq_drop_count[self.priority as usize].fetch_add(1, Ordering::Relaxed);
+        self.priority == XcpEventPriority::High
+    }
+
     /// Get the capacity of the capture buffer
     #[allow(clippy::unused_self)]
     pub fn get_capacity(&self) -> usize {
@@ -113,17 +561,98 @@ impl<const N: usize> DaqEvent<N> {
         self.buffer[offset..offset + data.len()].copy_from_slice(data);
     }
 
+    /// Enable the trigger timing metrics of this event
+    /// Each trigger records the cycle time since the last trigger, its jitter and a jitter histogram
+    /// The statistics are the measurement signals "<event>_cycle_us", ..., "<event>_jitter_hist" of the auxiliary event "<event>_timing"
+    /// Requires a single instance event, must be called before the A2L file is written
+    pub fn enable_timing_metrics(&mut self) {
+        if self.timing.is_some() {
+            return;
+        }
+        let name = self.event.get_name();
+        let timing = Arc::new(DaqTiming::new(name, Xcp::get().create_timing_event(self.event)));
+        timing.register(name);
+        let mut list = DAQ_TIMING_LIST.lock().unwrap();
+        list.retain(|t| t.strong_count() > 0);
+        list.push(Arc::downgrade(&timing));
+        self.timing = Some(timing);
+    }
+
+    /// Get the trigger timing statistics, None if the timing metrics are not enabled
+    pub fn get_timing_stats(&self) -> Option<DaqTimingStats> {
+        self.timing.as_ref().map(|t| t.get_stats())
+    }
+
     /// Trigger for stack or capture buffer measurement with base pointer relative addressing
+    /// Triggers the on change event too, if any on change measurement changed its value
+    /// Dropped under transmit queue pressure, see XcpEventPriority
     pub fn trigger(&self) {
+        if let Some(timing) = &self.timing {
+            timing.trigger();
+        }
+        if !self.admit() {
+            for gate in &self.gates {
+                gate.state.store(DAQ_GATE_UNSAMPLED, Ordering::Relaxed);
+            }
+            return;
+        }
         let base: *const u8 = &self.buffer as *const u8;
         // @@@@ Unsafe - C library call which will dereference the raw pointer base
         unsafe {
             self.event.trigger_ext(base);
         }
+        if let Some(on_change) = &self.on_change {
+            on_change.trigger();
+        }
+        for gate in &self.gates {
+            gate.trigger(base);
+        }
+    }
+
+    /// Estimate the transmit queue space in bytes needed to transmit the complete capture buffer
+    /// Objects are not split across packets, one additional packet is added for the fragmentation
+    #[allow(clippy::unused_self)]
+    fn get_required_queue_space(&self) -> usize {
+        let max_payload = Xcp::get().get_max_dto_size().saturating_sub(DTO_PACKET_OVERHEAD).max(1);
+        let packets = N.div_ceil(max_payload) + 1;
+        N + packets * DTO_PACKET_OVERHEAD
+    }
+
+    /// Check if the transmit queue has enough free space to transmit the capture buffer of this event
+    /// Returns DaqError::QueueFull, if the data would be lost when the event is triggered now
+    /// Always ok, if the XCP server is not started
+    pub fn check_queue_space(&self) -> Result<(), DaqError> {
+        let xcp = Xcp::get();
+        if xcp.get_transmit_queue_size() == 0 || xcp.get_transmit_queue_free() >= self.get_required_queue_space() {
+            Ok(())
+        } else {
+            Err(DaqError::QueueFull)
+        }
+    }
+
+    /// Wait (sleep) until the transmit queue has enough free space to transmit the capture buffer of this event
+    /// Returns DaqError::Timeout, if there is still not enough space after timeout
+    /// Not intended for realtime tasks, use check_queue_space
+    pub fn wait_queue_space(&self, timeout: Duration) -> Result<(), DaqError> {
+        let start = Instant::now();
+        while self.check_queue_space().is_err() {
+            if start.elapsed() >= timeout {
+                return Err(DaqError::Timeout);
+            }
+            std::thread::sleep(QUEUE_SPACE_POLL_INTERVAL);
+        }
+        Ok(())
     }
 
     /// Trigger for stack measurement with absolute addressing
+    /// Dropped under transmit queue pressure, see XcpEventPriority
     pub fn trigger_abs(&self) {
+        if let Some(timing) = &self.timing {
+            timing.trigger();
+        }
+        if !self.admit() {
+            return;
+        }
         self.event.trigger_abs();
     }
 
@@ -169,6 +698,197 @@ impl<const N: usize> DaqEvent<N> {
         event_offset
     }
 
+    /// Allocate space for a struct in the capture buffer and register its fields as measurements
+    /// The fields are taken from the type description, e.g. of #[derive(XcpProtoMeasurement)] for a prost message
+    /// An empty comment uses the comment of the type description, e.g. of a newtype struct Rpm(f32)
+    /// Returns the offset for capture_struct
+    pub fn add_capture_struct<T: xcp_type_description::XcpTypeDescription + Copy>(&mut self, value: &T, comment: &'static str) -> i16 {
+        let event_offset: i16 = self.allocate(std::mem::size_of::<T>());
+        let event = self.get_xcp_event();
+        let type_description = value.type_description().expect("struct without type description");
+        for field in type_description.iter() {
+            let name: &'static str = Box::leak(field.name().to_string().into_boxed_str());
+            let field_offset: i16 = field.offset().try_into().expect("offset out of range");
+            let mut m = RegistryMeasurement::new(
+                name,
+                RegistryDataType::from_rust_type(field.datatype()),
+                field.x_dim().max(1).try_into().expect("x_dim out of range"),
+                field.y_dim().max(1).try_into().expect("y_dim out of range"),
+                event,
+                event_offset + field_offset,
+                0u64,
+                1.0,
+                0.0,
+                if comment.is_empty() { field.comment() } else { comment },
+                field.unit(),
+                None,
+            );
+            // Fields with extended limits have a plausible range (min, max) and a physically possible range
+            if let Some((min, max)) = field.extended_limits() {
+                m.set_limits(field.min(), field.max());
+                m.set_extended_limits(min, max);
+            }
+            if Xcp::get().get_registry().lock().add_measurement(m).is_err() {
+                error!("Error: Measurement {} already exists", name);
+            }
+        }
+        event_offset
+    }
+
+    /// Copy a struct registered with add_capture_struct to the capture buffer
+    pub fn capture_struct<T: Copy>(&mut self, value: &T, offset: i16) {
+        let offset: usize = offset.try_into().expect("offset negative");
+        let size = std::mem::size_of::<T>();
+        assert!(offset + size <= N, "DAQ buffer overflow");
+        // @@@@ Unsafe - copy the struct bytes into the capture buffer, T is Copy and has no references to other memory
+        unsafe {
+            std::ptr::copy_nonoverlapping(value as *const T as *const u8, self.buffer.as_mut_ptr().add(offset), size);
+        }
+    }
+
+    // Get the on change capture buffer, create or look up the auxiliary event on first use
+    fn get_on_change(&mut self) -> &mut DaqOnChange<N> {
+        let event = self.event;
+        self.on_change.get_or_insert_with(|| Box::new(DaqOnChange::new(Xcp::get().create_on_change_event(event))))
+    }
+
+    /// Associate a variable to the on change event of this DaqEvent, allocate space in its capture buffer and register it
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_capture_on_change(
+        &mut self,
+        name: &'static str,
+        size: usize,
+        datatype: RegistryDataType,
+        x_dim: u16,
+        y_dim: u16,
+        factor: f64,
+        offset: f64,
+        unit: &'static str,
+        comment: &'static str,
+    ) -> i16 {
+        let on_change = self.get_on_change();
+        let event_offset = on_change.buffer_len;
+        assert!(event_offset + size <= N, "DAQ buffer overflow");
+        on_change.buffer_len += size;
+        let event_offset: i16 = event_offset.try_into().expect("offset out of range");
+        trace!("Allocate on change DAQ buffer for {}, offset = {} {:?} and register measurement", name, event_offset, datatype);
+        if Xcp::get()
+            .get_registry()
+            .lock()
+            .add_measurement(RegistryMeasurement::new(
+                name,
+                datatype,
+                x_dim,
+                y_dim,
+                on_change.event,
+                event_offset,
+                0u64,
+                factor,
+                offset,
+                comment,
+                unit,
+                None,
+            ))
+            .is_err()
+        {
+            error!("Error: Measurement {} already exists", name);
+        }
+        event_offset
+    }
+
+    /// Copy to the capture buffer of the on change event
+    /// The on change event is triggered with the next trigger, if the value changed
+    pub fn capture_on_change(&mut self, data: &[u8], offset: i16) {
+        let offset: usize = offset.try_into().expect("offset negative");
+        let on_change = self.get_on_change();
+        let dst = &mut on_change.buffer[offset..offset + data.len()];
+        if dst != data {
+            dst.copy_from_slice(data);
+            on_change.changed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Attach the gate of a measurement group to this DaqEvent, create or look up its auxiliary event "<event>_<group>" on first use
+    /// A new group starts with the given state, it is switched at runtime with Xcp::enable_measurement_group
+    /// Requires a single instance event, returns the gate index for set_gate and is_gate_open
+    pub fn add_gate(&mut self, group: &'static str, enabled: bool) -> usize {
+        if let Some(index) = self.gates.iter().position(|g| g.group == group) {
+            return index;
+        }
+        self.gates.push(DaqGate::new(self.event, group, enabled));
+        self.gates.len() - 1
+    }
+
+    /// Move a variable registered on this DaqEvent to the auxiliary event of a gate, it keeps its address relative to the event base
+    pub fn set_gate(&self, name: &'static str, gate: usize) {
+        let gate = &self.gates[gate];
+        if Xcp::get().get_registry().lock().set_measurement_gate(name, self.event, gate.event, gate.group).is_err() {
+            error!("Error: Measurement {} not found", name);
+        }
+    }
+
+    /// Check if a gate is open in the current trigger cycle, gated variables are captured only when open
+    pub fn is_gate_open(&self, gate: usize) -> bool {
+        self.gates[gate].is_open()
+    }
+
+    /// Allocate space for a variable length blob in the capture buffer and register it as Blob with annotation
+    /// The blob is captured with a u16 little endian length prefix, max_size includes the prefix, None uses the remaining capture buffer
+    pub fn add_blob(&mut self, name: &'static str, max_size: Option<usize>, comment: &'static str, annotation: String) -> i16 {
+        self.add_blob_measurement(name, max_size, comment, annotation, None)
+    }
+
+    /// Allocate space for serialized data in the capture buffer and register it as Blob with IDL annotation
+    /// The serialized data is captured as variable length blob with the IDL hash as prefix of the blob data
+    pub fn add_serialized(&mut self, name: &'static str, max_size: Option<usize>, comment: &'static str, annotation: String, idl_hash: u32) -> i16 {
+        self.add_blob_measurement(name, max_size, comment, annotation, Some(idl_hash))
+    }
+
+    fn add_blob_measurement(&mut self, name: &'static str, max_size: Option<usize>, comment: &'static str, annotation: String, idl_hash: Option<u32>) -> i16 {
+        let max_size = max_size.unwrap_or(N - self.buffer_len);
+        assert!(max_size > BLOB_LENGTH_PREFIX_SIZE, "Blob buffer too small");
+        let event_offset: i16 = self.allocate(max_size);
+        trace!("Allocate DAQ buffer for blob {}, offset = {}, max_size = {}, idl_hash = {:?}", name, event_offset, max_size, idl_hash);
+        let mut m = RegistryMeasurement::new(
+            name,
+            RegistryDataType::Blob,
+            max_size.try_into().expect("buffer too large"), // x_dim is max buffer size in bytes
+            1,
+            self.get_xcp_event(),
+            event_offset,
+            0u64,
+            1.0,
+            0.0,
+            comment,
+            "",
+            Some(annotation),
+        );
+        if let Some(idl_hash) = idl_hash {
+            m.set_idl_hash(idl_hash);
+        }
+        if Xcp::get().get_registry().lock().add_measurement(m).is_err() {
+            error!("Error: Measurement {} already exists", name);
+        }
+        event_offset
+    }
+
+    /// Copy a variable length blob with length prefix (u16 little endian) to the capture buffer
+    /// The length prefix and data must fit into the max_size given in add_blob
+    pub fn capture_blob(&mut self, data: &[u8], offset: i16) {
+        let len: u16 = data.len().try_into().expect("blob too large");
+        self.capture(&len.to_le_bytes(), offset);
+        self.capture(data, offset + 2); // u16 length prefix
+    }
+
+    /// Copy serialized data as variable length blob with IDL hash prefix (u32 little endian) to the capture buffer
+    pub fn capture_serialized(&mut self, idl_hash: u32, data: &[u8], offset: i16) {
+        let len: u16 = (data.len() + 4).try_into().expect("serialized data too large");
+        let data_offset = offset + 2; // u16 length prefix
+        self.capture(&len.to_le_bytes(), offset);
+        self.capture(&idl_hash.to_le_bytes(), data_offset);
+        self.capture(data, data_offset + 4);
+    }
+
     /// Associate a variable on stack to this DaqEvent and register it
     #[allow(clippy::too_many_arguments)]
     pub fn add_stack(
@@ -245,6 +965,76 @@ impl<const N: usize> DaqEvent<N> {
             error!("Error: Measurement {} already exists", name);
         }
     }
+
+    /// Set the type of the physical value of a variable registered on this DaqEvent
+    /// e.g. Float64Ieee for a voltage in mV stored as i16 with factor 0.001
+    pub fn set_phys_type(&self, name: &'static str, phys_type: RegistryDataType) {
+        if Xcp::get().get_registry().lock().set_measurement_phys_type(name, self.get_xcp_event(), phys_type).is_err() {
+            error!("Error: Measurement {} not found", name);
+        }
+    }
+
+    /// Set the limits of the physical value of a variable registered on this DaqEvent
+    /// Without limits, the limits are the value range of the data type, integer limits are written as integers
+    pub fn set_limits(&self, name: &'static str, min: f64, max: f64) {
+        if Xcp::get().get_registry().lock().set_measurement_limits(name, self.get_xcp_event(), min, max).is_err() {
+            error!("Error: Measurement {} not found", name);
+        }
+    }
+
+    /// Set the physically possible range of a variable registered on this DaqEvent, which must enclose the plausible range set with set_limits
+    /// The tool scales with the physical range, the plausible range is written as annotation
+    pub fn set_extended_limits(&self, name: &'static str, min: f64, max: f64) {
+        let xcp_event = self.get_xcp_event();
+        if Xcp::get().get_registry().lock().set_measurement_extended_limits(name, xcp_event, min, max).is_err() {
+            error!("Error: Measurement {} not found", name);
+        }
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+// Array slices
+
+/// Length of an array type, if known at compile time
+/// Used by daq_register_slice to check the bounds of a slice with literal range at compile time
+pub trait DaqArrayLen {
+    const LEN: Option<usize>;
+}
+
+impl<T, const N: usize> DaqArrayLen for [T; N] {
+    const LEN: Option<usize> = Some(N);
+}
+
+impl<T> DaqArrayLen for [T] {
+    const LEN: Option<usize> = None;
+}
+
+impl<T> DaqArrayLen for Vec<T> {
+    const LEN: Option<usize> = None;
+}
+
+impl<T: DaqArrayLen + ?Sized> DaqArrayLen for Box<T> {
+    const LEN: Option<usize> = T::LEN;
+}
+
+impl<T: DaqArrayLen + ?Sized> DaqArrayLen for &T {
+    const LEN: Option<usize> = T::LEN;
+}
+
+impl<T: DaqArrayLen + ?Sized> DaqArrayLen for &mut T {
+    const LEN: Option<usize> = T::LEN;
+}
+
+/// Range START..END of a slice of an array
+/// Fails to compile, if the range is empty or exceeds the length of a fixed size array
+pub fn daq_slice_range<A: DaqArrayLen + ?Sized, const START: usize, const END: usize>(_array: &A) -> std::ops::Range<usize> {
+    const {
+        assert!(START < END, "empty slice");
+        if let Some(len) = A::LEN {
+            assert!(END <= len, "slice out of array bounds");
+        }
+    }
+    START..END
 }
 
 //----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
@@ -258,9 +1048,47 @@ impl<const N: usize> DaqEvent<N> {
 /// This creates a single instance of this DAQ event once or returns the DAQ event if it already exists by using a lazy static
 /// The DAQ event may have an optional capture buffer with the given capacity
 /// Multiple concurrently runing instances of a task or thread may savely trigger this DAQ event
+/// The optional period_us is the cycle time of the task in microseconds, it is the event cycle time in the A2L file
+/// The optional priority = High reserves transmit queue space for the capture buffer, see XcpEventPriority
 #[allow(unused_macros)]
 #[macro_export]
 macro_rules! daq_create_event {
+    // With capture buffer and transmit priority, e.g. priority = High
+    ( $name:expr, $capacity: expr, priority = $priority: ident ) => {{
+        lazy_static::lazy_static! {
+            static ref XCP_EVENT__: XcpEvent = {
+                let event = Xcp::get().create_event($name);
+                Xcp::get().set_event_priority(event, XcpEventPriority::$priority);
+                event
+            };
+        }
+        DaqEvent::<{ $capacity }>::new_from(&XCP_EVENT__)
+    }};
+    // Without capture buffer, with transmit priority
+    ( $name:expr, priority = $priority: ident ) => {{
+        lazy_static::lazy_static! {
+            static ref XCP_EVENT__: XcpEvent = {
+                let event = Xcp::get().create_event($name);
+                Xcp::get().set_event_priority(event, XcpEventPriority::$priority);
+                event
+            };
+        }
+        DaqEvent::<0>::new_from(&XCP_EVENT__)
+    }};
+    // With capture buffer and cycle time in microseconds
+    ( $name:expr, capacity = $capacity: expr, period_us = $period_us: expr ) => {{
+        lazy_static::lazy_static! {
+            static ref XCP_EVENT__: XcpEvent = Xcp::get().create_event_ext($name, false, 1000 * $period_us as u32);
+        }
+        DaqEvent::<{ $capacity }>::new_from(&XCP_EVENT__)
+    }};
+    // Without capture buffer, with cycle time in microseconds
+    ( $name:expr, period_us = $period_us: expr ) => {{
+        lazy_static::lazy_static! {
+            static ref XCP_EVENT__: XcpEvent = Xcp::get().create_event_ext($name, false, 1000 * $period_us as u32);
+        }
+        DaqEvent::<0>::new_from(&XCP_EVENT__)
+    }};
     // With capture buffer and cycle time
     // Value may be moved, variable addresses is capture buffer offset
     ( $name:expr, $capacity: expr, $cycle_time_ns: expr ) => {{
@@ -293,9 +1121,56 @@ macro_rules! daq_create_event {
 
 /// Capture the value of a variable with basic type into the the capture buffer of the given daq event
 /// Register the given variable metadata once
+/// With gate = "group", the variable belongs to a runtime gated measurement group and is captured only while the group is enabled, see DaqEvent::add_gate
+/// With enabled = bool, the variable has its own gate named like the variable, with the given initial state
 #[allow(unused_macros)]
 #[macro_export]
 macro_rules! daq_capture {
+    // name, event, comment, unit, gate = group
+    ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr, gate = $gate:expr ) => {{
+        let gate__ = $daq_event.add_gate($gate, false);
+        $crate::daq_capture!(@gated $id, $daq_event, $comment, $unit, gate__);
+    }};
+
+    // name, event, comment, unit, enabled = initial state of the gate named like the variable
+    ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr, enabled = $enabled:expr ) => {{
+        let gate__ = $daq_event.add_gate(stringify!($id), $enabled);
+        $crate::daq_capture!(@gated $id, $daq_event, $comment, $unit, gate__);
+    }};
+
+    // name, event, gate = group
+    ( $id:ident, $daq_event:expr, gate = $gate:expr ) => {{
+        $crate::daq_capture!($id, $daq_event, "", "", gate = $gate);
+    }};
+
+    // name, event, enabled = initial state
+    ( $id:ident, $daq_event:expr, enabled = $enabled:expr ) => {{
+        $crate::daq_capture!($id, $daq_event, "", "", enabled = $enabled);
+    }};
+
+    ( @gated $id:ident, $daq_event:expr, $comment:expr, $unit:expr, $gate:ident ) => {{
+        static DAQ_OFFSET__: std::sync::OnceLock<i16> = std::sync::OnceLock::new();
+        let byte_offset = *DAQ_OFFSET__.get_or_init(|| {
+            let byte_offset = $daq_event.add_capture(
+                stringify!($id),
+                std::mem::size_of_val(&$id),
+                $id.get_type(),
+                1, // x_dim
+                1, // y_dim
+                1.0,
+                0.0,
+                $unit,
+                $comment,
+                None,
+            );
+            $daq_event.set_gate(stringify!($id), $gate);
+            byte_offset
+        });
+        if $daq_event.is_gate_open($gate) {
+            $daq_event.capture(&($id.to_le_bytes()), byte_offset);
+        }
+    }};
+
     // name, event, comment, unit, factor,offset
     ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr, $factor:expr, $offset:expr ) => {{
         static DAQ_OFFSET__: std::sync::atomic::AtomicI16 = std::sync::atomic::AtomicI16::new(-32768);
@@ -328,7 +1203,104 @@ macro_rules! daq_capture {
         let byte_offset;
         match DAQ_OFFSET__.compare_exchange(-32768, 0, std::sync::atomic::Ordering::Relaxed, std::sync::atomic::Ordering::Relaxed) {
             Ok(_) => {
-                byte_offset = $daq_event.add_capture(
+                byte_offset = $daq_event.add_capture(
+                    stringify!($id),
+                    std::mem::size_of_val(&$id),
+                    $id.get_type(),
+                    1, // x_dim
+                    1, // y_dim
+                    1.0,
+                    0.0,
+                    $unit,
+                    $comment,
+                    None,
+                );
+                DAQ_OFFSET__.store(byte_offset, std::sync::atomic::Ordering::Relaxed);
+            }
+            Err(offset) => byte_offset = offset,
+        };
+        $daq_event.capture(&($id.to_le_bytes()), byte_offset);
+    }};
+
+    // name, event
+    ( $id:ident, $daq_event:expr ) => {{
+        static DAQ_OFFSET__: std::sync::atomic::AtomicI16 = std::sync::atomic::AtomicI16::new(-32768);
+        let byte_offset;
+        match DAQ_OFFSET__.compare_exchange(-32768, 0, std::sync::atomic::Ordering::Relaxed, std::sync::atomic::Ordering::Relaxed) {
+            Ok(_) => {
+                byte_offset = $daq_event.add_capture(
+                    stringify!($id),
+                    std::mem::size_of_val(&$id),
+                    $id.get_type(),
+                    1, // x_dim
+                    1, // y_dim
+                    1.0,
+                    0.0,
+                    "",
+                    "",
+                    None,
+                );
+                DAQ_OFFSET__.store(byte_offset, std::sync::atomic::Ordering::Relaxed);
+            }
+            Err(offset) => byte_offset = offset,
+        };
+        $daq_event.capture(&($id.to_le_bytes()), byte_offset);
+    }};
+}
+
+/// Capture the value of a variable with basic type in an async task into the capture buffer of the given daq event
+/// Pattern for task local data in async code, which may be moved to another worker thread at each await:
+/// Create the daq event with capture buffer in the async function with daq_create_event!(name, capacity), capture the values and trigger
+/// The daq event and its capture buffer are part of the future and move with it, the value is copied, no stack or thread local address is involved
+/// Register the given variable metadata once for all instances of the task,
+/// the first calls on concurrently running worker threads are synchronized, all instances use the same capture buffer offset
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! daq_capture_async {
+    // name, event, comment, unit, factor, offset
+    ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr, $factor:expr, $offset:expr ) => {{
+        static DAQ_OFFSET__: std::sync::OnceLock<i16> = std::sync::OnceLock::new();
+        let byte_offset = *DAQ_OFFSET__.get_or_init(|| {
+            $daq_event.add_capture(
+                stringify!($id),
+                std::mem::size_of_val(&$id),
+                $id.get_type(),
+                1, // x_dim
+                1, // y_dim
+                $factor,
+                $offset,
+                $unit,
+                $comment,
+                None,
+            )
+        });
+        $daq_event.capture(&($id.to_le_bytes()), byte_offset);
+    }};
+
+    // name, event, comment, unit
+    ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr ) => {{
+        $crate::daq_capture_async!($id, $daq_event, $comment, $unit, 1.0, 0.0);
+    }};
+
+    // name, event
+    ( $id:ident, $daq_event:expr ) => {{
+        $crate::daq_capture_async!($id, $daq_event, "", "", 1.0, 0.0);
+    }};
+}
+
+/// Capture the value of a variable with basic type, which changes rarely, into the on change capture buffer of the given daq event
+/// The value is transmitted by the auxiliary event "<event>_onchange", only when it changed since the last trigger of the daq event or when DAQ is started
+/// Register the given variable metadata once
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! daq_capture_on_change {
+    // name, event, comment, unit
+    ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr ) => {{
+        static DAQ_OFFSET__: std::sync::atomic::AtomicI16 = std::sync::atomic::AtomicI16::new(-32768);
+        let byte_offset;
+        match DAQ_OFFSET__.compare_exchange(-32768, 0, std::sync::atomic::Ordering::Relaxed, std::sync::atomic::Ordering::Relaxed) {
+            Ok(_) => {
+                byte_offset = $daq_event.add_capture_on_change(
                     stringify!($id),
                     std::mem::size_of_val(&$id),
                     $id.get_type(),
@@ -338,47 +1310,61 @@ macro_rules! daq_capture {
                     0.0,
                     $unit,
                     $comment,
-                    None,
                 );
                 DAQ_OFFSET__.store(byte_offset, std::sync::atomic::Ordering::Relaxed);
             }
             Err(offset) => byte_offset = offset,
         };
-        $daq_event.capture(&($id.to_le_bytes()), byte_offset);
+        $daq_event.capture_on_change(&($id.to_le_bytes()), byte_offset);
     }};
 
     // name, event
     ( $id:ident, $daq_event:expr ) => {{
-        static DAQ_OFFSET__: std::sync::atomic::AtomicI16 = std::sync::atomic::AtomicI16::new(-32768);
-        let byte_offset;
-        match DAQ_OFFSET__.compare_exchange(-32768, 0, std::sync::atomic::Ordering::Relaxed, std::sync::atomic::Ordering::Relaxed) {
-            Ok(_) => {
-                byte_offset = $daq_event.add_capture(
-                    stringify!($id),
-                    std::mem::size_of_val(&$id),
-                    $id.get_type(),
-                    1, // x_dim
-                    1, // y_dim
-                    1.0,
-                    0.0,
-                    "",
-                    "",
-                    None,
-                );
-                DAQ_OFFSET__.store(byte_offset, std::sync::atomic::Ordering::Relaxed);
-            }
-            Err(offset) => byte_offset = offset,
-        };
-        $daq_event.capture(&($id.to_le_bytes()), byte_offset);
+        $crate::daq_capture_on_change!($id, $daq_event, "", "");
     }};
 }
 
 /// Register a local variable with basic type for the given daq event
 /// Address format and addressing mode will be relative to the stack frame position of the variable holding the event
 /// No capture buffer required
+/// The optional physical type is the Rust type of the value after conversion with factor and offset, e.g. f64 for a voltage in mV stored as i16
+/// With gate = "group" or enabled = bool, the variable is transmitted only while its measurement group is enabled, the daq event must be mutable
 #[allow(unused_macros)]
 #[macro_export]
 macro_rules! daq_register {
+    // name, event, comment, unit, gate = group
+    ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr, gate = $gate:expr ) => {{
+        let gate__ = $daq_event.add_gate($gate, false);
+        $crate::daq_register!(@gated $id, $daq_event, $comment, $unit, gate__);
+    }};
+    // name, event, comment, unit, enabled = initial state of the gate named like the variable
+    ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr, enabled = $enabled:expr ) => {{
+        let gate__ = $daq_event.add_gate(stringify!($id), $enabled);
+        $crate::daq_register!(@gated $id, $daq_event, $comment, $unit, gate__);
+    }};
+    // name, event, gate = group
+    ( $id:ident, $daq_event:expr, gate = $gate:expr ) => {{
+        $crate::daq_register!($id, $daq_event, "", "", gate = $gate);
+    }};
+    // name, event, enabled = initial state
+    ( $id:ident, $daq_event:expr, enabled = $enabled:expr ) => {{
+        $crate::daq_register!($id, $daq_event, "", "", enabled = $enabled);
+    }};
+    ( @gated $id:ident, $daq_event:expr, $comment:expr, $unit:expr, $gate:ident ) => {{
+        static ONCE: std::sync::Once = std::sync::Once::new();
+        ONCE.call_once(|| {
+            $daq_event.add_stack(stringify!($id), &$id as *const _ as *const u8, $id.get_type(), 1, 1, 1.0, 0.0, $unit, $comment);
+            $daq_event.set_gate(stringify!($id), $gate);
+        });
+    }};
+    // name, event, comment, unit, factor, offset, physical type
+    ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr, $factor:expr, $offset:expr, $phys_type:ty ) => {{
+        static ONCE: std::sync::Once = std::sync::Once::new();
+        ONCE.call_once(|| {
+            $daq_event.add_stack(stringify!($id), &$id as *const _ as *const u8, $id.get_type(), 1, 1, $factor, $offset, $unit, $comment);
+            $daq_event.set_phys_type(stringify!($id), RegistryDataType::from_rust_basic_type(stringify!($phys_type)));
+        });
+    }};
     // name, event, comment, unit, factor, offset
     ( $id:ident, $daq_event:expr, $comment:expr, $unit:expr, $factor:expr, $offset:expr ) => {{
         static ONCE: std::sync::Once = std::sync::Once::new();
@@ -437,6 +1423,40 @@ macro_rules! daq_register_ref {
     }};
 }
 
+/// Register a sub range of an array of basic type in static memory or on heap as its own measurement signal for the given daq event
+/// The slice is registered with x_dim = length of the slice, at the address of its first element
+/// Address format and addressing mode will be absolute addressing mode, the event must be triggered with trigger_abs
+/// Assuming that the memory location is reachable in absolute addressing mode, otherwise panic
+/// A literal range is checked against the length of a fixed size array at compile time, otherwise the range is checked at registration
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! daq_register_slice {
+    // name, literal range, event, comment, unit
+    ( $id:ident, $start:literal .. $end:literal, $daq_event:expr, $comment:expr, $unit:expr ) => {{
+        $crate::daq_register_slice!(@register $id, $crate::daq_slice_range::<_, $start, $end>(&$id), $daq_event, $comment, $unit)
+    }};
+    // name, range, event, comment, unit
+    ( $id:ident, $range:expr, $daq_event:expr, $comment:expr, $unit:expr ) => {{
+        $crate::daq_register_slice!(@register $id, $range, $daq_event, $comment, $unit)
+    }};
+    ( @register $id:ident, $range:expr, $daq_event:expr, $comment:expr, $unit:expr ) => {{
+        static ONCE: std::sync::Once = std::sync::Once::new();
+        ONCE.call_once(|| {
+            let range: std::ops::Range<usize> = $range;
+            assert!(
+                range.start < range.end && range.end <= $id.len(),
+                "slice {:?} out of bounds of {} with length {}",
+                range,
+                stringify!($id),
+                $id.len()
+            );
+            let slice = &$id[range];
+            let dim = slice.len().try_into().expect("dim too large");
+            $daq_event.add_heap(stringify!($id), slice.as_ptr() as *const u8, slice[0].get_type(), dim, 1, 1.0, 0.0, $unit, $comment);
+        });
+    }};
+}
+
 /// Capture the CDR serialized value of a variable into the capture buffer of the given daq event
 /// Register the given metadata once
 /// This includes the serialization schema as annotation text of the variable (Vector VLSD, variable length signal description)
@@ -444,31 +1464,66 @@ macro_rules! daq_register_ref {
 #[macro_export]
 macro_rules! daq_serialize {
     // name, event, comment
+    // Uses the remaining capture buffer
     ( $id:ident, $daq_event:expr, $comment:expr) => {{
+        $crate::daq_serialize!(@serialize $id, $daq_event, $comment, None)
+    }};
+    // name, event, comment, max_size
+    // Multiple serialized variables with a maximum size (including the 2 byte length and 4 byte IDL hash prefix) may share one event
+    ( $id:ident, $daq_event:expr, $comment:expr, $max_size:expr) => {{
+        $crate::daq_serialize!(@serialize $id, $daq_event, $comment, Some($max_size))
+    }};
+    ( @serialize $id:ident, $daq_event:expr, $comment:expr, $max_size:expr) => {{
         static DAQ_OFFSET__: std::sync::atomic::AtomicI16 = std::sync::atomic::AtomicI16::new(-32768);
+        static IDL_HASH__: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
         let byte_offset;
         match DAQ_OFFSET__.compare_exchange(-32768, 0, std::sync::atomic::Ordering::Relaxed, std::sync::atomic::Ordering::Relaxed) {
             Ok(_) => {
                 // @@@@ Experimental: Hard coded type here for point_cloud demo
-                let annotation = GeneratorCollection::generate(&IDL::CDR, &$id.description()).unwrap();
-                byte_offset = $daq_event.add_capture(
-                    stringify!($id),
-                    std::mem::size_of_val(&$id),
-                    RegistryDataType::Blob,
-                    $daq_event.buffer.len().try_into().expect("buffer too large"), // x_dim is buffer size in bytes
-                    1,                                                             // y_dim
-                    1.0,
-                    0.0,
-                    "",
-                    $comment,
-                    Some(annotation),
-                );
+                let (annotation, idl_hash) = GeneratorCollection::generate_with_hash(&IDL::CDR, &$id.description()).unwrap();
+                byte_offset = $daq_event.add_serialized(stringify!($id), $max_size, $comment, annotation, idl_hash);
+                IDL_HASH__.store(idl_hash, std::sync::atomic::Ordering::Relaxed);
                 DAQ_OFFSET__.store(byte_offset, std::sync::atomic::Ordering::Relaxed);
             }
             Err(offset) => byte_offset = offset,
         };
         let v = cdr::serialize::<_, _, cdr::CdrBe>(&$id, cdr::Infinite).unwrap();
-        $daq_event.capture(&v, byte_offset);
+        let max_size: Option<usize> = $max_size;
+        if let Some(max_size) = max_size {
+            assert!(v.len() + 4 + $crate::BLOB_LENGTH_PREFIX_SIZE <= max_size, "Serialized data of {} exceeds max_size", stringify!($id));
+        }
+        $daq_event.capture_serialized(IDL_HASH__.load(std::sync::atomic::Ordering::Relaxed), &v, byte_offset);
+    }};
+}
+
+/// Capture the CDR serialized value of a variable like daq_serialize, if the transmit queue has space to transmit the capture buffer of the given daq event
+/// Returns Err(DaqError::QueueFull) without serializing, the caller should not trigger the event and may count the drop
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! daq_try_serialize {
+    // name, event, comment
+    ( $id:ident, $daq_event:expr, $comment:expr) => {{
+        $daq_event.check_queue_space().map(|_| $crate::daq_serialize!($id, $daq_event, $comment))
+    }};
+    // name, event, comment, max_size
+    ( $id:ident, $daq_event:expr, $comment:expr, $max_size:expr) => {{
+        $daq_event.check_queue_space().map(|_| $crate::daq_serialize!($id, $daq_event, $comment, $max_size))
+    }};
+}
+
+/// Capture the CDR serialized value of a variable like daq_serialize, wait until the transmit queue has space to transmit the capture buffer of the given daq event
+/// Returns Err(DaqError::Timeout) without serializing, if there is still not enough space after timeout (std::time::Duration)
+/// For non realtime producers only, the calling thread sleeps
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! daq_serialize_blocking {
+    // name, event, comment, timeout
+    ( $id:ident, $daq_event:expr, $comment:expr, timeout = $timeout:expr) => {{
+        $daq_event.wait_queue_space($timeout).map(|_| $crate::daq_serialize!($id, $daq_event, $comment))
+    }};
+    // name, event, comment, max_size, timeout
+    ( $id:ident, $daq_event:expr, $comment:expr, $max_size:expr, timeout = $timeout:expr) => {{
+        $daq_event.wait_queue_space($timeout).map(|_| $crate::daq_serialize!($id, $daq_event, $comment, $max_size))
     }};
 }
 
@@ -483,9 +1538,29 @@ macro_rules! daq_serialize {
 /// When the macro is called multiple times, the DAQ event is created once for each thread
 /// This is thread safe, there is no potential race with other threads
 /// Multiple concurrently runing instances of a task use the DAQ event assiated to their thread
+/// The optional instance label (e.g. a loop index) is attached to event and signal names instead of the instance index,
+/// which depends on thread spawn order
 #[allow(unused_macros)]
 #[macro_export]
 macro_rules! daq_create_event_tli {
+    ( $name:expr, $capacity: literal, instance = $instance:expr ) => {{
+        thread_local! {
+            static XCP_EVENT__: std::cell::Cell<XcpEvent> = const { std::cell::Cell::new(XcpEvent::XCP_UNDEFINED_EVENT) }
+        }
+        if XCP_EVENT__.get() == XcpEvent::XCP_UNDEFINED_EVENT {
+            XCP_EVENT__.set(Xcp::get().create_event_instance($name, $instance));
+        }
+        DaqEvent::<$capacity>::new_from(&XCP_EVENT__.get())
+    }};
+    ( $name:expr, instance = $instance:expr ) => {{
+        thread_local! {
+            static XCP_EVENT__: std::cell::Cell<XcpEvent> = const { std::cell::Cell::new(XcpEvent::XCP_UNDEFINED_EVENT) }
+        }
+        if XCP_EVENT__.get() == XcpEvent::XCP_UNDEFINED_EVENT {
+            XCP_EVENT__.set(Xcp::get().create_event_instance($name, $instance));
+        }
+        DaqEvent::<0>::new_from(&XCP_EVENT__.get())
+    }};
     ( $name:expr, $capacity: literal ) => {{
         thread_local! {
             static XCP_EVENT__: std::cell::Cell<XcpEvent> = const { std::cell::Cell::new(XcpEvent::XCP_UNDEFINED_EVENT) }
@@ -625,9 +1700,13 @@ macro_rules! daq_register_tli {
 
 /// Create a multi instance task DAQ event
 /// Each call will create a new instance of an event named "<name>_<instance_index>""
+/// or "<name>_<instance>", if an explicit instance label is given
 #[allow(unused_macros)]
 #[macro_export]
 macro_rules! daq_create_event_instance {
+    ( $name:expr, instance = $instance:expr ) => {{
+        DaqEvent::<0>::new_from(&Xcp::get().create_event_instance($name, $instance))
+    }};
     ( $name:expr ) => {{
         DaqEvent::<0>::new_from(&Xcp::get().create_event_ext($name, true, 0))
     }};
@@ -663,6 +1742,8 @@ mod daq_tests {
 
     use crate::reg::*;
     use crate::xcp::*;
+    use xcp_idl_generator::prelude::*;
+    use xcp_type_description::prelude::*;
 
     //-----------------------------------------------------------------------------
     // Test local variable register
@@ -740,6 +1821,453 @@ mod daq_tests {
         xcp.write_a2l().unwrap(); // @@@@ Remove: force A2L write
     }
 
+    //-----------------------------------------------------------------------------
+    // Test trigger timing metrics with a known cycle time
+    #[test]
+    fn test_event_timing_metrics() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        let mut event = daq_create_event!("timed_task", 8);
+        assert!(event.get_timing_stats().is_none());
+        event.enable_timing_metrics();
+        let signal: u32 = 0;
+        daq_capture!(signal, event);
+
+        const CYCLES: u32 = 50;
+        for _ in 0..=CYCLES {
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            event.trigger();
+        }
+
+        // The sleep time is a lower bound of the cycle time, the mean allows for a loaded test system
+        let stats = event.get_timing_stats().unwrap();
+        info!("{:?}", stats);
+        assert_eq!(stats.count, CYCLES);
+        assert!(stats.cycle_min_us >= 2000);
+        assert!(stats.cycle_min_us <= stats.cycle_us && stats.cycle_us <= stats.cycle_max_us);
+        assert!(stats.cycle_mean_us >= 2000.0 && stats.cycle_mean_us < 10000.0);
+        assert!(stats.jitter_max_us <= stats.cycle_max_us - stats.cycle_min_us);
+        assert_eq!(stats.jitter_hist.iter().sum::<u32>(), CYCLES);
+        assert!(xcp.get_daq_timing_stats().contains(&("timed_task", stats)));
+
+        // The statistics are measurement signals of the auxiliary event
+        let a2l = xcp.generate_a2l_string().unwrap();
+        assert!(a2l.contains("/begin EVENT \"timed_task_timing\""));
+        for name in ["timed_task_cycle_us", "timed_task_cycle_mean_us", "timed_task_jitter_max_us"] {
+            assert!(a2l.contains(&format!("/begin MEASUREMENT {} ", name)));
+        }
+        assert!(a2l.lines().any(|l| l.contains("MEASUREMENT timed_task_jitter_hist ") && l.contains(&format!("MATRIX_DIM {}", DAQ_TIMING_HIST_BINS))));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the queue admission policy with a simulated transmit queue under pressure
+    // A high priority event every 10 cycles competes with a normal priority event every cycle, the queue drains less than the offered load
+    #[test]
+    fn test_event_priority_queue_admission() {
+        const QUEUE_SIZE: usize = 4096;
+        const DRAIN_PER_CYCLE: usize = 600;
+        const HIGH_SIZE: usize = 1000;
+        const NORMAL_SIZE: usize = 800;
+        const CYCLES: usize = 10000;
+
+        // Returns the drop count per priority
+        let simulate = |reserve: usize| {
+            let mut level: usize = 0;
+            let mut drops = [0usize; 2];
+            for cycle in 0..CYCLES {
+                level = level.saturating_sub(DRAIN_PER_CYCLE);
+                let mut offer = |priority: XcpEventPriority, size: usize| {
+                    if queue_admit(priority, QUEUE_SIZE - level, size, reserve) {
+                        level += size;
+                    } else {
+                        drops[priority as usize] += 1;
+                    }
+                };
+                if cycle % 10 == 0 {
+                    offer(XcpEventPriority::High, HIGH_SIZE);
+                }
+                offer(XcpEventPriority::Normal, NORMAL_SIZE);
+            }
+            drops
+        };
+
+        // Without reserve (FIFO), both events suffer
+        let [normal, high] = simulate(0);
+        info!("FIFO drops: high={} normal={}", high, normal);
+        assert!(high > CYCLES / 10 / 10);
+
+        // With reserve, the high priority event is not dropped, the normal priority event absorbs the drops
+        let [normal, high] = simulate(HIGH_SIZE);
+        info!("Priority drops: high={} normal={}", high, normal);
+        assert_eq!(high, 0);
+        assert!(normal > CYCLES / 4);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test the A2L priority of an event created with priority = High
+    #[test]
+    fn test_event_priority() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        let event = daq_create_event!("priority_task", 16, priority = High);
+        assert_eq!(event.get_priority(), XcpEventPriority::High);
+        assert_eq!(daq_create_event!("normal_task", 16).get_priority(), XcpEventPriority::Normal);
+
+        // Without server, the queue size is 0 and nothing is dropped
+        event.trigger();
+        assert_eq!(xcp.get_daq_drop_count(XcpEventPriority::High), 0);
+
+        let a2l = xcp.generate_a2l_string().unwrap();
+        let event_line = |name: &str| a2l.lines().find(|l| l.contains(&format!("/begin EVENT \"{}\"", name))).unwrap().to_string();
+        assert!(event_line("priority_task").contains(" DAQ 0xFF 0 0 1 CONSISTENCY"));
+        assert!(event_line("normal_task").contains(" DAQ 0xFF 0 0 0 CONSISTENCY"));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test explicit instance labels of multi instance events
+    #[test]
+    fn test_event_instance_label() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        // Create instances in reverse order
+        let mut handles = Vec::new();
+        for label in ["rear_right", "front_left"] {
+            handles.push(std::thread::spawn(move || {
+                let mut event = daq_create_event_tli!("labeled_task", 8, instance = label);
+                let signal: f64 = 1.0;
+                daq_capture_tli!(signal, event);
+                event.get_xcp_event()
+            }));
+        }
+        let events: Vec<XcpEvent> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(events[0].get_instance_label(), Some("rear_right"));
+        let event_index = daq_create_event_instance!("indexed_task", instance = 3);
+        assert_eq!(event_index.get_xcp_event().get_instance_suffix(), "3");
+
+        {
+            let r = xcp.get_registry();
+            let reg = r.lock();
+            let names: Vec<&str> = reg.get_measurement_list().iter().map(RegistryMeasurement::get_name).collect();
+            assert!(names.contains(&"signal_front_left"));
+            assert!(names.contains(&"signal_rear_right"));
+        }
+
+        // Events are sorted by label, independent from creation order
+        let a2l = xcp.generate_a2l_string().unwrap();
+        let front_left = a2l.find("/begin EVENT \"labeled_task_front_left\"").unwrap();
+        let rear_right = a2l.find("/begin EVENT \"labeled_task_rear_right\"").unwrap();
+        assert!(front_left < rear_right);
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test registration of a sub range of an array on heap
+    #[test]
+    fn test_daq_register_slice() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        static RING_BUFFER: [f64; 4096] = [0.0; 4096];
+        static HISTORY: [u16; 100] = [0; 100];
+        let ring_buffer = &RING_BUFFER;
+        let history = &HISTORY;
+        let start = 10;
+        let event = daq_create_event!("slice_task");
+        daq_register_slice!(ring_buffer, 0..256, event, "most recent samples", "V");
+        daq_register_slice!(history, start..start + 20, event, "", "");
+        assert_eq!(daq_slice_range::<_, 1, 3>(&[0u8; 4]), 1..3);
+
+        let a2l = xcp.generate_a2l_string().unwrap();
+        let measurement = |name: &str| a2l.lines().find(|l| l.contains(&format!("/begin MEASUREMENT {} ", name))).unwrap().to_string();
+        let (ext, addr) = Xcp::get_abs_ext_addr(ring_buffer.as_ptr() as u64);
+        let m = measurement("ring_buffer");
+        assert!(m.contains("FLOAT64_IEEE") && m.contains(r#"PHYS_UNIT "V""#) && m.contains(" MATRIX_DIM 256"));
+        assert!(m.contains(&format!("ECU_ADDRESS 0x{:X} ECU_ADDRESS_EXTENSION {}", addr, ext)));
+        let (ext, addr) = Xcp::get_abs_ext_addr(history[start..].as_ptr() as u64);
+        let m = measurement("history");
+        assert!(m.contains("UWORD") && m.contains(" MATRIX_DIM 20"));
+        assert!(m.contains(&format!("ECU_ADDRESS 0x{:X} ECU_ADDRESS_EXTENSION {}", addr, ext)));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test capture of a prost message with XcpProtoMeasurement type description
+    #[test]
+    fn test_capture_struct() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        #[derive(Clone, Copy, PartialEq, prost::Message, XcpProtoMeasurement)]
+        struct ProtoData {
+            #[prost(fixed32, tag = "1")]
+            counter: u32,
+            #[prost(double, tag = "2")]
+            signal: f64,
+            #[prost(sint32, tag = "3")]
+            delta: i32,
+        }
+        let data = ProtoData {
+            counter: 7,
+            signal: 0.5,
+            delta: -1,
+        };
+
+        let mut event = daq_create_event!("proto_task", 32);
+        let offset = event.add_capture_struct(&data, "");
+        event.capture_struct(&data, offset);
+        let offset = offset as usize + std::mem::offset_of!(ProtoData, signal);
+        assert_eq!(event.buffer[offset..offset + 8], 0.5f64.to_le_bytes());
+        event.trigger();
+
+        let a2l = xcp.generate_a2l_string().unwrap();
+        let measurement = |name: &str| a2l.lines().find(|l| l.contains(&format!("/begin MEASUREMENT {} ", name))).unwrap().to_string();
+        assert!(measurement("ProtoData.counter").contains(" ULONG "));
+        assert!(measurement("ProtoData.signal").contains(" FLOAT64_IEEE "));
+        assert!(measurement("ProtoData.delta").contains(" SLONG "));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test capture of newtypes, a newtype is a measurement with the name of the struct
+    #[test]
+    fn test_capture_newtype() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        #[derive(Clone, Copy, XcpTypeDescription)]
+        #[type_description(comment = "Engine speed", unit = "1/min", min = "0", max = "8000")]
+        struct Rpm(f32);
+        #[derive(Clone, Copy, XcpTypeDescription)]
+        struct SpeedTable([f32; 16]);
+
+        let mut event = daq_create_event!("newtype_task", 128);
+        let offset = event.add_capture_struct(&Rpm(1500.0), "");
+        event.capture_struct(&Rpm(1500.0), offset);
+        let _ = event.add_capture_struct(&SpeedTable([0.0; 16]), "Speed table");
+        event.trigger();
+
+        let a2l = xcp.generate_a2l_string().unwrap();
+        let measurement = |name: &str| a2l.lines().find(|l| l.contains(&format!("/begin MEASUREMENT {} ", name))).unwrap().to_string();
+        let m = measurement("Rpm");
+        assert!(m.contains(r#""Engine speed""#) && m.contains(" FLOAT32_IEEE ") && m.contains(r#"PHYS_UNIT "1/min""#));
+        let m = measurement("SpeedTable");
+        assert!(m.contains(r#""Speed table""#) && m.contains(" MATRIX_DIM 16"));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test range check of a slice, which is not known at compile time
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_daq_register_slice_out_of_bounds() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+
+        let event = daq_create_event!("slice_out_of_bounds_task");
+        let samples: Vec<f32> = vec![0.0; 100];
+        let end = 110;
+        daq_register_slice!(samples, 90..end, event, "", "");
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test event consistency attribute, a struct measured as one consistent snapshot
+    #[test]
+    fn test_event_consistency() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        let mut event = daq_create_event!("snapshot_task", 16);
+        event.set_consistency(XcpEventConsistency::Event);
+        let snapshot_x: f64 = 1.0;
+        let snapshot_y: f64 = 2.0;
+        daq_capture!(snapshot_x, event);
+        daq_capture!(snapshot_y, event);
+        let _default_event = daq_create_event!("default_task");
+
+        let a2l = xcp.generate_a2l_string().unwrap();
+        let event_line = |name: &str| a2l.lines().find(|l| l.contains(&format!("/begin EVENT \"{}\"", name))).unwrap().to_string();
+        assert!(event_line("snapshot_task").ends_with("CONSISTENCY EVENT /end EVENT"));
+        assert!(event_line("default_task").ends_with("CONSISTENCY DAQ /end EVENT"));
+
+        // The attribute survives loading and writing the A2L
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l, None, &mut log_msgs, true).unwrap();
+        let a2l = a2l_file.write_to_string();
+        assert!(a2l.contains("CONSISTENCY EVENT"));
+        assert!(a2l.contains("CONSISTENCY DAQ"));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test event cycle time from period_us
+    #[test]
+    fn test_event_period() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        let _event1 = daq_create_event!("period_task_1ms", period_us = 1000);
+        let _event2 = daq_create_event!("period_task_250us", capacity = 16, period_us = 250);
+        let _event3 = daq_create_event!("period_task_sporadic", 16);
+
+        // TIME_CYCLE TIME_UNIT, UNIT_10US = 4, UNIT_1US = 3
+        let a2l = xcp.generate_a2l_string().unwrap();
+        let event_line = |name: &str| a2l.lines().find(|l| l.contains(&format!("/begin EVENT \"{}\"", name))).unwrap().to_string();
+        assert!(event_line("period_task_1ms").contains(" DAQ 0xFF 100 4 0 CONSISTENCY "));
+        assert!(event_line("period_task_250us").contains(" DAQ 0xFF 250 3 0 CONSISTENCY "));
+        assert!(event_line("period_task_sporadic").contains(" DAQ 0xFF 0 0 0 CONSISTENCY "));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test lookup of events by name
+    #[test]
+    fn test_find_event() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        let event = daq_create_event!("scheduled_task");
+        let _instance1 = daq_create_event_instance!("scheduled_instance", instance = "left");
+        let instance2 = daq_create_event_instance!("scheduled_instance", instance = "right");
+
+        assert!(xcp.find_event("scheduled_task").unwrap() == event.get_xcp_event());
+        assert!(xcp.find_event("scheduled_instance_right").unwrap() == instance2.get_xcp_event());
+        assert!(xcp.find_event("scheduled_instance").is_none());
+        assert!(xcp.find_event("unknown_task").is_none());
+        let names: Vec<String> = xcp.get_events().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["scheduled_task", "scheduled_instance_left", "scheduled_instance_right"]);
+
+        // A scheduler, which did not create the event, triggers it from another thread
+        let heap_value = Box::new(1u32);
+        event.add_heap("scheduled_value", &*heap_value as *const u32 as *const u8, RegistryDataType::Ulong, 1, 1, 1.0, 0.0, "", "");
+        std::thread::spawn(|| Xcp::get().find_event("scheduled_task").unwrap().trigger_abs()).join().unwrap();
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test collision of explicit instance labels
+    #[test]
+    fn test_event_instance_label_collision() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        let _event1 = daq_create_event_instance!("colliding_task", instance = "left");
+        let _event2 = daq_create_event_instance!("colliding_task", instance = "left");
+        assert!(matches!(xcp.write_a2l(), Err(XcpError::Registry(RegistryError::Duplicate(_)))));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test IDL hash of serialized data and multiple serialized variables on one event
+    #[derive(Debug, serde::Serialize, IdlGenerator)]
+    struct TestSerializedPosition {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Debug, serde::Serialize, IdlGenerator)]
+    struct TestSerializedStatus {
+        counter: u32,
+        positions: Vec<TestSerializedPosition>,
+    }
+
+    #[test]
+    fn test_serialized_idl_hash() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        let test_serialized_position = TestSerializedPosition { x: 1.0, y: 2.0 };
+        let test_serialized_status = TestSerializedStatus {
+            counter: 1,
+            positions: vec![TestSerializedPosition { x: 3.0, y: 4.0 }],
+        };
+
+        // Hash is stable and differs between types
+        let (idl_position, hash_position) = GeneratorCollection::generate_with_hash(&IDL::CDR, test_serialized_position.description()).unwrap();
+        let (_, hash_position2) = GeneratorCollection::generate_with_hash(&IDL::CDR, test_serialized_position.description()).unwrap();
+        let (_, hash_status) = GeneratorCollection::generate_with_hash(&IDL::CDR, test_serialized_status.description()).unwrap();
+        assert_eq!(hash_position, hash_position2);
+        assert_ne!(hash_position, hash_status);
+        assert!(idl_position.contains(&format!("0x{:08X}", hash_position)));
+
+        // Two serialized variables with max sizes share one event
+        let mut event = daq_create_event!("serialized_task", 256);
+        daq_serialize!(test_serialized_position, event, "position", 64);
+        daq_serialize!(test_serialized_status, event, "status", 128);
+        event.trigger();
+
+        // Check the length and hash prefix of the captured data
+        let v = cdr::serialize::<_, _, cdr::CdrBe>(&test_serialized_position, cdr::Infinite).unwrap();
+        assert_eq!(u16::from_le_bytes([event.buffer[0], event.buffer[1]]) as usize, v.len() + 4);
+        let position = &event.buffer[2..6 + v.len()];
+        let status = &event.buffer[66..192];
+        assert_eq!(check_idl_hash(position, hash_position).unwrap(), &v[..]);
+        assert!(check_idl_hash(status, hash_status).is_some());
+        assert!(check_idl_hash(status, hash_position).is_none());
+
+        {
+            let r = xcp.get_registry();
+            let reg = r.lock();
+            let m = reg.get_measurement_list().iter().find(|m| m.get_name() == "test_serialized_status").unwrap();
+            assert_eq!(m.get_idl_hash(), Some(hash_status));
+        }
+
+        let a2l = xcp.generate_a2l_string().unwrap();
+        assert!(a2l.contains(&format!("\"0x{:08X}\"", hash_position)));
+        assert!(a2l.contains(&format!("\"0x{:08X}\"", hash_status)));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test bounded sequences in the generated IDL
+    const TEST_MAX_TRACK_LEN: usize = 16;
+
+    #[derive(Debug, serde::Serialize, IdlGenerator)]
+    struct TestSerializedTrack {
+        #[idl(max_len = 500)]
+        positions: Vec<TestSerializedPosition>,
+        #[idl(max_len = TEST_MAX_TRACK_LEN)]
+        counters: Vec<u32>,
+        unbounded: Vec<u32>,
+    }
+
+    #[test]
+    fn test_serialized_idl_bounded_sequence() {
+        let test_serialized_track = TestSerializedTrack {
+            positions: Vec::new(),
+            counters: Vec::new(),
+            unbounded: Vec::new(),
+        };
+        let idl = GeneratorCollection::generate(&IDL::CDR, test_serialized_track.description()).unwrap();
+        assert!(idl.contains("\"sequence<TestSerializedPosition, 500> positions;\""));
+        assert!(idl.contains("\"sequence<uint32, 16> counters;\""));
+        assert!(idl.contains("\"sequence<uint32> unbounded;\""));
+        assert!(idl.contains("struct TestSerializedPosition {"));
+    }
+
+    //-----------------------------------------------------------------------------
+    // Test variable length blobs with length prefix
+    #[test]
+    fn test_blob_capture() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        let mut event = daq_create_event!("blob_task", 64);
+        let offset1 = event.add_blob("test_blob1", Some(16), "blob with max size", String::new());
+        let offset2 = event.add_blob("test_blob2", None, "blob in remaining buffer", String::new());
+        assert_eq!(offset1, 0);
+        assert_eq!(offset2, 16);
+
+        // Length changes from cycle to cycle
+        for len in [10u8, 3, 14] {
+            let data: Vec<u8> = (0..len).collect();
+            let len = len as usize;
+            event.capture_blob(&data, offset1);
+            event.capture_blob(&data[..len / 2], offset2);
+            event.trigger();
+            assert_eq!(u16::from_le_bytes([event.buffer[0], event.buffer[1]]) as usize, len);
+            assert_eq!(event.buffer[2..2 + len], data[..]);
+            assert_eq!(u16::from_le_bytes([event.buffer[16], event.buffer[17]]) as usize, len / 2);
+        }
+
+        // Max size in A2L includes the length prefix
+        let a2l = xcp.generate_a2l_string().unwrap();
+        assert!(a2l.contains("/begin BLOB test_blob1 \"blob with max size\" 0x0 16 "));
+        assert!(a2l.contains("/begin BLOB test_blob2 \"blob in remaining buffer\" 0x10 48 "));
+    }
+
     //-----------------------------------------------------------------------------
     // Test A2L file generation for local variables
     #[test]
@@ -798,4 +2326,63 @@ mod daq_tests {
 
         xcp.write_a2l().unwrap(); // @@@@ Remove: force A2L write
     }
+
+    //-----------------------------------------------------------------------------
+    // Test the validated trigger with one or multiple base objects
+    #[derive(Clone, Copy, Default)]
+    #[repr(C)]
+    struct TriggerSample {
+        counter: u32,
+        value: f64,
+    }
+
+    fn register_at(event: XcpEvent, name: &'static str, datatype: RegistryDataType, offset: usize) {
+        let m = RegistryMeasurement::new(name, datatype, 1, 1, event, offset as i16, 0, 1.0, 0.0, "", "", None);
+        Xcp::get().get_registry().lock().add_measurement(m).unwrap();
+    }
+
+    #[test]
+    fn test_trigger_with() {
+        xcp_test::test_setup(log::LevelFilter::Info);
+        let xcp = Xcp::get();
+
+        // Single object, measurements registered relative to the object start
+        let event = xcp.create_event("trigger_with");
+        register_at(event, "sample.counter", RegistryDataType::Ulong, offset_of!(TriggerSample, counter));
+        register_at(event, "sample.value", RegistryDataType::Float64Ieee, offset_of!(TriggerSample, value));
+        let sample = TriggerSample::default();
+        assert_eq!(event.trigger_with(&sample), Ok(()));
+
+        // Misuse, the object is smaller than the registered layout
+        let counter: u32 = 0;
+        assert_eq!(
+            event.trigger_with(&counter),
+            Err(DaqError::Uncovered {
+                offset: offset_of!(TriggerSample, value) as i16,
+                len: 8
+            })
+        );
+
+        // Multiple objects, each at its event offset
+        let event = xcp.create_event("trigger_builder");
+        register_at(event, "counter", RegistryDataType::Ulong, offset_of!(TriggerSample, counter));
+        register_at(event, "value", RegistryDataType::Float64Ieee, offset_of!(TriggerSample, value));
+        let offset = offset_of!(TriggerSample, value) as i16;
+        let trigger = event.trigger_builder().base(&sample.counter, 0).base(&sample.value, offset);
+        assert_eq!(trigger.validate(), Ok(&sample as *const _ as *const u8));
+        assert_eq!(trigger.trigger(), Ok(()));
+
+        // Misuse, objects not at their event offsets or missing
+        let value: f64 = 0.0;
+        assert_eq!(
+            event.trigger_builder().base(&sample.counter, 0).base(&value, offset).trigger(),
+            Err(DaqError::BaseOffset(offset))
+        );
+        assert_eq!(
+            event.trigger_builder().base(&sample.value, offset).base(&sample.counter, 4).trigger(),
+            Err(DaqError::BaseOffset(4))
+        );
+        assert_eq!(event.trigger_builder().base(&sample.counter, 0).trigger(), Err(DaqError::Uncovered { offset, len: 8 }));
+        assert_eq!(event.trigger_builder().trigger(), Err(DaqError::NoBase));
+    }
 }
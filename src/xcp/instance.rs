@@ -0,0 +1,255 @@
+//-----------------------------------------------------------------------------
+// Module instance
+// Additional XCP instances with their own registry, event list and A2L file
+// The Xcp singleton (Xcp::get()) is the default instance, it owns the XCP server of xcplib
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use parking_lot::Mutex;
+use std::{net::Ipv4Addr, sync::Arc};
+
+use super::{EventList, Xcp, XcpError, XcpEvent, XcpTransportLayer};
+use crate::reg::Registry;
+
+//------------------------------------------------------------------------------------------
+// XcpInstanceBuilder
+
+/// A builder for an additional XCP instance
+#[derive(Debug)]
+pub struct XcpInstanceBuilder {
+    app_name: &'static str, // Registry name, file name for the registry A2L generator
+    epk: &'static str,      // EPK string for A2L version check
+    tl: XcpTransportLayer,
+    addr: Ipv4Addr,
+    port: u16,
+    a2l_strict: bool, // Fail the A2L generation on errors of the A2L check
+}
+
+impl XcpInstanceBuilder {
+    /// Set the application name, used as A2L file name
+    #[must_use]
+    pub fn app_name(mut self, app_name: &'static str) -> Self {
+        self.app_name = app_name;
+        self
+    }
+
+    /// Set the EPK to enable the XCP tool to check the A2L file fits the code
+    #[must_use]
+    pub fn epk(mut self, epk: &'static str) -> Self {
+        self.epk = epk;
+        self
+    }
+
+    /// Set the transport layer
+    #[must_use]
+    pub fn transport_layer(mut self, tl: XcpTransportLayer) -> Self {
+        self.tl = tl;
+        self
+    }
+
+    /// Set the ip address of the server
+    #[must_use]
+    pub fn addr<A>(mut self, addr: A) -> Self
+    where
+        A: Into<Ipv4Addr>,
+    {
+        self.addr = addr.into();
+        self
+    }
+
+    /// Set the port of the server
+    #[must_use]
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Fail the A2L generation, if the A2L check reports errors
+    #[must_use]
+    pub fn a2l_strict(mut self, strict: bool) -> Self {
+        self.a2l_strict = strict;
+        self
+    }
+
+    /// Create the instance with an empty registry
+    /// Transport layer parameters are registered to make the A2L plug&play
+    pub fn build(self) -> XcpInstance {
+        let mut registry = Registry::new();
+        registry.set_name(self.app_name);
+        registry.set_epk(self.epk, Xcp::XCP_EPK_ADDR);
+        registry.set_tl_params(self.tl.protocol_name(), self.addr, self.port);
+        registry.set_a2l_strict(self.a2l_strict);
+        debug!("Create XcpInstance {} on {}:{}", self.app_name, self.addr, self.port);
+
+        XcpInstance {
+            name: self.app_name,
+            event_list: Mutex::new(EventList::new()),
+            registry: Arc::new(Mutex::new(registry)),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------------------
+// XcpInstance
+
+/// An additional XCP instance, isolated from the Xcp singleton and from other instances
+/// Owns its registry and event list, event names and measurement names may collide with other instances
+/// xcplib is single instance, so only A2L generation is supported, start_server returns XcpError::UnsupportedMultiInstance
+/// Calibration segments, and measurement and calibration over XCP, are only supported by the Xcp singleton
+pub struct XcpInstance {
+    name: &'static str,
+    event_list: Mutex<EventList>,
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl XcpInstance {
+    /// Create a builder with default parameters
+    /// app_name "xcp_instance", EPK "EPK", UDP on 127.0.0.1:5555
+    pub fn builder() -> XcpInstanceBuilder {
+        XcpInstanceBuilder {
+            app_name: "xcp_instance",
+            epk: "EPK",
+            tl: XcpTransportLayer::Udp,
+            addr: Ipv4Addr::LOCALHOST,
+            port: 5555,
+            a2l_strict: false,
+        }
+    }
+
+    /// Get the application name
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Start the XCP server of this instance
+    /// Not supported, the XCP server of xcplib is owned by the Xcp singleton
+    #[allow(clippy::unused_self)]
+    pub fn start_server(&self) -> Result<(), XcpError> {
+        error!("XcpInstance {}: XCP server not supported, xcplib is single instance", self.name);
+        Err(XcpError::UnsupportedMultiInstance)
+    }
+
+    //------------------------------------------------------------------------------------------
+    // XCP events
+
+    /// Create XCP event
+    /// index==0 single instance
+    /// index>0 multi instance (instance number is attached to name)
+    pub fn create_event_ext(&self, name: &'static str, indexed: bool, cycle_time_ns: u32) -> XcpEvent {
+        self.event_list.lock().create_instance_local_event(name, indexed, cycle_time_ns)
+    }
+
+    /// Create XCP event
+    /// Single instance
+    pub fn create_event(&self, name: &'static str) -> XcpEvent {
+        self.create_event_ext(name, false, 0)
+    }
+
+    //------------------------------------------------------------------------------------------
+    // Registry
+
+    /// Get a clone of the registry of this instance
+    pub fn get_registry(&self) -> Arc<Mutex<Registry>> {
+        Arc::clone(&self.registry)
+    }
+
+    /// Write the A2L file of this instance
+    /// Returns false, if the A2L file has already been written
+    pub fn write_a2l(&self) -> Result<bool, XcpError> {
+        let mut registry = self.registry.lock();
+        if registry.is_frozen() {
+            return Ok(false);
+        }
+        self.register_events(&mut registry)?;
+        registry.write_a2l()?;
+        registry.freeze();
+        Ok(true)
+    }
+
+    /// Generate the A2L of this instance as a string, without writing the A2L file
+    /// Finalizes the registry like write_a2l, all registrations from now on will cause panic
+    pub fn generate_a2l_string(&self) -> Result<String, XcpError> {
+        let mut registry = self.registry.lock();
+        if registry.is_frozen() {
+            return Err(XcpError::Io(std::io::Error::other("Registry is closed")));
+        }
+        self.register_events(&mut registry)?;
+        let mut a2l = Vec::with_capacity(16 * 1024);
+        registry.write_a2l_to(&mut a2l)?;
+        registry.freeze();
+        Ok(String::from_utf8(a2l).expect("A2L is not valid UTF-8"))
+    }
+
+    // Register all events, event channels are the creation order and not remapped
+    fn register_events(&self, registry: &mut Registry) -> Result<(), XcpError> {
+        let mut event_list = self.event_list.lock();
+        event_list.sort_and_check()?;
+        event_list.add_to_registry(registry);
+        Ok(())
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+// Test
+// Tests for XCP instances
+//----------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod instance_tests {
+
+    use super::*;
+    use crate::reg::{RegistryDataType, RegistryMeasurement};
+    use crate::xcp::xcp_test;
+
+    fn add_measurement(instance_registry: &Arc<Mutex<Registry>>, name: &'static str, event: XcpEvent) {
+        instance_registry
+            .lock()
+            .add_measurement(RegistryMeasurement::new(name, RegistryDataType::Ulong, 1, 1, event, 0, 0, 1.0, 0.0, "", "", None))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_instance_registry_isolation() {
+        let xcp = xcp_test::test_setup(log::LevelFilter::Info);
+
+        let simulation = XcpInstance::builder().app_name("test_simulation").port(5556).build();
+        let adapter = XcpInstance::builder().app_name("test_adapter").transport_layer(XcpTransportLayer::Tcp).port(5557).build();
+
+        // Same event names in the default instance and in both instances
+        let default_event = xcp.create_event("task");
+        let simulation_event = simulation.create_event("task");
+        let adapter_event = adapter.create_event("task");
+        let adapter_event2 = adapter.create_event("task2");
+        assert_eq!(simulation_event.get_channel(), 0);
+        assert_eq!(adapter_event2.get_channel(), 1);
+
+        add_measurement(&xcp.get_registry(), "default_signal", default_event);
+        add_measurement(&simulation.get_registry(), "simulation_signal", simulation_event);
+        add_measurement(&adapter.get_registry(), "adapter_signal", adapter_event);
+        add_measurement(&adapter.get_registry(), "adapter_signal2", adapter_event2);
+
+        let a2l_default = xcp.generate_a2l_string().unwrap();
+        let a2l_simulation = simulation.generate_a2l_string().unwrap();
+        let a2l_adapter = adapter.generate_a2l_string().unwrap();
+
+        assert!(a2l_default.contains("default_signal"));
+        assert!(!a2l_default.contains("simulation_signal") && !a2l_default.contains("adapter_signal"));
+        assert!(a2l_simulation.contains("simulation_signal"));
+        assert!(!a2l_simulation.contains("default_signal") && !a2l_simulation.contains("adapter_signal"));
+        assert!(a2l_adapter.contains("adapter_signal") && a2l_adapter.contains("adapter_signal2"));
+        assert!(!a2l_adapter.contains("default_signal") && !a2l_adapter.contains("simulation_signal"));
+
+        // Transport layer parameters of each instance
+        assert!(a2l_simulation.contains("UDP") && a2l_simulation.contains("5556"));
+        assert!(a2l_adapter.contains("TCP") && a2l_adapter.contains("5557"));
+
+        // Event channels of the instances are not affected by the remapping of the default instance
+        assert_eq!(adapter_event2.get_channel(), 1);
+        assert!(a2l_adapter.contains("\"task2\" \"task2\" 1 DAQ"));
+
+        // Only the default instance owns the XCP server
+        assert!(matches!(simulation.start_server(), Err(XcpError::UnsupportedMultiInstance)));
+        assert!(simulation.generate_a2l_string().is_err());
+    }
+}
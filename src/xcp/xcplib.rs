@@ -18,9 +18,15 @@ extern "C" {
         cb_flush: ::std::option::Option<unsafe extern "C" fn() -> u8>,
     );
 }
+extern "C" {
+    pub fn ApplXcpRegisterCommandCallback(cb_command: ::std::option::Option<unsafe extern "C" fn(cmd: u8)>);
+}
 extern "C" {
     pub fn ApplXcpSetA2lName(name: *const ::std::os::raw::c_char);
 }
+extern "C" {
+    pub fn ApplXcpSetA2lData(data: *const ::std::os::raw::c_char, len: u32);
+}
 extern "C" {
     pub fn ApplXcpSetEpk(epk: *const ::std::os::raw::c_char);
 }
@@ -30,9 +36,20 @@ extern "C" {
 extern "C" {
     pub fn XcpInit();
 }
+#[cfg(feature = "tokio")]
+extern "C" {
+    pub fn XcpStart();
+}
+#[cfg(feature = "tokio")]
+extern "C" {
+    pub fn XcpReset();
+}
 extern "C" {
     pub fn XcpDisconnect();
 }
+extern "C" {
+    pub fn XcpGetSessionStatus() -> u16;
+}
 extern "C" {
     pub fn XcpEvent(event: u16);
 }
@@ -45,6 +62,55 @@ extern "C" {
 extern "C" {
     pub fn ApplXcpGetAddr(p: *const u8) -> u32;
 }
+extern "C" {
+    pub fn ApplXcpGetClock64() -> u64;
+}
+extern "C" {
+    pub fn XcpTlSetMaxSizes(max_cto: u16, max_dto: u16) -> u8;
+}
+#[cfg(feature = "tokio")]
+extern "C" {
+    pub fn XcpTlInit(queue_size: u32) -> u8;
+}
+#[cfg(feature = "tokio")]
+extern "C" {
+    pub fn XcpTlShutdown();
+}
+#[cfg(feature = "tokio")]
+extern "C" {
+    pub fn XcpTlCommand(msgLen: u16, msgBuf: *const u8) -> u8;
+}
+#[cfg(feature = "tokio")]
+extern "C" {
+    pub fn XcpTlTransmitQueuePeekMsg(msg_len: *mut u16) -> *const u8;
+}
+#[cfg(feature = "tokio")]
+extern "C" {
+    pub fn XcpTlTransmitQueueNextMsg();
+}
+#[cfg(feature = "tokio")]
+extern "C" {
+    pub fn XcpTlTransmitQueueHasMsg() -> u8;
+}
+#[cfg(feature = "tokio")]
+extern "C" {
+    pub fn XcpTlFlushTransmitBuffer();
+}
+extern "C" {
+    pub fn XcpTlGetTransmitQueueSize() -> u32;
+}
+extern "C" {
+    pub fn XcpTlGetTransmitQueueFree() -> u32;
+}
+extern "C" {
+    pub fn XcpTlGetMaxDtoSize() -> u16;
+}
+extern "C" {
+    pub fn XcpTlGetStats(tx_packets: *mut u64, tx_bytes: *mut u64, tx_queue_overruns: *mut u64, rx_packets: *mut u64);
+}
+extern "C" {
+    pub fn XcpEthTlSetOptions(queue_size: u32, sndbuf: u32, rcvbuf: u32);
+}
 extern "C" {
     pub fn XcpEthServerInit(addr: *const u8, port: u16, useTCP: u8) -> u8;
 }
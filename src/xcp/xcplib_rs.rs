@@ -20,6 +20,34 @@ pub fn register_callbacks(
     unimplemented!();
 }
 
+pub fn register_command_callback(_cb_command: ::std::option::Option<unsafe extern "C" fn(cmd: u8)>) {
+    unimplemented!();
+}
+
+pub fn get_session_status() -> u16 {
+    unimplemented!();
+}
+
+pub fn get_clock() -> u64 {
+    unimplemented!();
+}
+
+pub fn get_transmit_queue_size() -> u32 {
+    unimplemented!();
+}
+
+pub fn get_transmit_queue_free() -> u32 {
+    unimplemented!();
+}
+
+pub fn get_max_dto_size() -> u16 {
+    unimplemented!();
+}
+
+pub fn get_transport_stats() -> (u64, u64, u64, u64) {
+    unimplemented!();
+}
+
 pub fn disconnect() {
     unimplemented!();
 }
@@ -0,0 +1,63 @@
+// test_a2l_diff
+// Test the A2L diff of the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_a2l_diff
+use xcp_client::a2l::*;
+
+fn write_a2l(filename: &str, objects: &str) {
+    let a2l = format!(
+        r#"ASAP2_VERSION 1 71
+/begin PROJECT test ""
+/begin MODULE test ""
+/begin RECORD_LAYOUT U8 FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+/begin RECORD_LAYOUT U32 FNC_VALUES 1 ULONG ROW_DIR DIRECT /end RECORD_LAYOUT
+{objects}
+/end MODULE
+/end PROJECT
+"#
+    );
+    std::fs::write(filename, a2l).unwrap();
+}
+
+#[test]
+fn test_a2l_diff() {
+    write_a2l(
+        "test_a2l_diff_old.a2l",
+        r#"
+/begin MEASUREMENT counter "" UWORD NO_COMPU_METHOD 0 0 0 65535 ECU_ADDRESS 0x1000 /end MEASUREMENT
+/begin MEASUREMENT speed "" FLOAT32_IEEE NO_COMPU_METHOD 0 0 0 100 ECU_ADDRESS 0x1004 /end MEASUREMENT
+/begin MEASUREMENT removed "" UBYTE NO_COMPU_METHOD 0 0 0 255 ECU_ADDRESS 0x1008 /end MEASUREMENT
+/begin CHARACTERISTIC param "" VALUE 0x80000000 U8 0 NO_COMPU_METHOD 0 255 /end CHARACTERISTIC
+/begin BLOB data "" 0x2000 256 /end BLOB
+"#,
+    );
+    write_a2l(
+        "test_a2l_diff_new.a2l",
+        r#"
+/begin MEASUREMENT counter "" ULONG NO_COMPU_METHOD 0 0 0 65535 ECU_ADDRESS 0x1000 /end MEASUREMENT
+/begin MEASUREMENT speed "" FLOAT32_IEEE NO_COMPU_METHOD 0 0 0 100 ECU_ADDRESS 0x1010 /end MEASUREMENT
+/begin MEASUREMENT added "" UBYTE NO_COMPU_METHOD 0 0 0 255 ECU_ADDRESS 0x1008 /end MEASUREMENT
+/begin CHARACTERISTIC param "" VALUE 0x80000000 U32 0 NO_COMPU_METHOD 0 255 /end CHARACTERISTIC
+/begin BLOB data "" 0x2000 256 /end BLOB
+"#,
+    );
+
+    let diff = diff_a2l("test_a2l_diff_old.a2l", "test_a2l_diff_new.a2l").unwrap();
+    println!("{}", diff);
+    assert_eq!(diff.added, vec!["added".to_string()]);
+    assert_eq!(diff.removed, vec!["removed".to_string()]);
+    assert_eq!(diff.type_changed.len(), 2);
+    assert_eq!(diff.type_changed[0], ("counter".to_string(), "UWORD".to_string(), "ULONG".to_string()));
+    assert_eq!(diff.type_changed[1].0, "param");
+    assert_eq!(diff.address_changed, vec![("speed".to_string(), 0x1004, 0x1010)]);
+    assert!(!diff.is_compatible());
+    assert!(diff.to_json().starts_with(r#"{"added":["added"],"removed":["removed"],"type_changed":[{"name":"counter","old_type":"UWORD","new_type":"ULONG"}"#));
+
+    // Same file
+    let diff = diff_a2l("test_a2l_diff_old.a2l", "test_a2l_diff_old.a2l").unwrap();
+    assert!(diff.is_empty());
+    assert!(diff.is_compatible());
+
+    let _ = std::fs::remove_file("test_a2l_diff_old.a2l");
+    let _ = std::fs::remove_file("test_a2l_diff_new.a2l");
+}
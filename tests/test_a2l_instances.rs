@@ -0,0 +1,74 @@
+// test_a2l_instances
+// Test the expansion of TYPEDEF_STRUCTURE instances in the A2L reader of the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_a2l_instances
+use xcp_client::a2l::a2l_reader::*;
+
+const A2L: &str = r#"ASAP2_VERSION 1 71
+/begin PROJECT test ""
+/begin MODULE test ""
+/begin RECORD_LAYOUT U8 FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+/begin RECORD_LAYOUT F64 FNC_VALUES 1 FLOAT64_IEEE ROW_DIR DIRECT /end RECORD_LAYOUT
+/begin TYPEDEF_CHARACTERISTIC T_u8 "" VALUE U8 0 NO_COMPU_METHOD 0 255 /end TYPEDEF_CHARACTERISTIC
+/begin TYPEDEF_CHARACTERISTIC T_f64 "" VALUE F64 0 NO_COMPU_METHOD -100 100 /end TYPEDEF_CHARACTERISTIC
+/begin TYPEDEF_MEASUREMENT M_u16 "" UWORD NO_COMPU_METHOD 0 0 0 65535 /end TYPEDEF_MEASUREMENT
+/begin TYPEDEF_STRUCTURE Point "" 16
+  /begin STRUCTURE_COMPONENT x T_f64 0 /end STRUCTURE_COMPONENT
+  /begin STRUCTURE_COMPONENT y T_f64 8 /end STRUCTURE_COMPONENT
+/end TYPEDEF_STRUCTURE
+/begin TYPEDEF_STRUCTURE Params "" 56
+  /begin STRUCTURE_COMPONENT enable T_u8 0 /end STRUCTURE_COMPONENT
+  /begin STRUCTURE_COMPONENT origin Point 8 /end STRUCTURE_COMPONENT
+  /begin STRUCTURE_COMPONENT points Point 24 MATRIX_DIM 2 /end STRUCTURE_COMPONENT
+/end TYPEDEF_STRUCTURE
+/begin TYPEDEF_STRUCTURE Signals "" 4
+  /begin STRUCTURE_COMPONENT counter M_u16 0 /end STRUCTURE_COMPONENT
+  /begin STRUCTURE_COMPONENT history M_u16 2 MATRIX_DIM 8 /end STRUCTURE_COMPONENT
+/end TYPEDEF_STRUCTURE
+/begin INSTANCE params "" Params 0x80010000 /end INSTANCE
+/begin INSTANCE signals "" Signals 0x1000 ECU_ADDRESS_EXTENSION 2 /end INSTANCE
+/end MODULE
+/end PROJECT
+"#;
+
+#[test]
+fn test_a2l_instances() {
+    std::fs::write("test_a2l_instances.a2l", A2L).unwrap();
+    let a2l_file = a2l_load("test_a2l_instances.a2l").unwrap();
+    let _ = std::fs::remove_file("test_a2l_instances.a2l");
+
+    // Nested structures and arrays of structures are expanded into fully qualified calibration parameters
+    assert_eq!(
+        a2l_get_characteristics(&a2l_file),
+        vec![
+            "params.enable",
+            "params.origin.x",
+            "params.origin.y",
+            "params.points[0].x",
+            "params.points[0].y",
+            "params.points[1].x",
+            "params.points[1].y"
+        ]
+    );
+    for (name, addr) in [
+        ("params.enable", 0x80010000),
+        ("params.origin.y", 0x80010010),
+        ("params.points[0].x", 0x80010018),
+        ("params.points[1].y", 0x80010030),
+    ] {
+        let (a2l_addr, _, _) = a2l_find_characteristic(&a2l_file, name).unwrap();
+        assert_eq!(a2l_addr.addr, addr, "{}", name);
+        assert_eq!(a2l_addr.ext, 0);
+        assert_eq!(a2l_addr.event, 0x8001);
+    }
+    let (_, a2l_type, a2l_limits) = a2l_find_characteristic(&a2l_file, "params.points[1].x").unwrap();
+    assert_eq!(a2l_type.size, 8);
+    assert_eq!(a2l_limits.lower, -100.0);
+
+    // Measurements keep the address extension of the instance and the dimension of the component
+    assert_eq!(a2l_get_measurements(&a2l_file), vec!["signals.counter", "signals.history"]);
+    let m = &a2l_file.project.module[0].measurement[1];
+    assert_eq!(m.ecu_address.as_ref().unwrap().address, 0x1002);
+    assert_eq!(m.ecu_address_extension.as_ref().unwrap().extension, 2);
+    assert_eq!(m.matrix_dim.as_ref().unwrap().dim_list, vec![8]);
+}
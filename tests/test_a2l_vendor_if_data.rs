@@ -0,0 +1,46 @@
+// test_a2l_vendor_if_data
+// Test the collection of vendor specific IF_DATA blocks in the A2L reader of the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_a2l_vendor_if_data
+use xcp_client::a2l::a2l_reader::*;
+use xcp_client::xcp_client::*;
+
+const A2L: &str = r#"ASAP2_VERSION 1 71
+/begin PROJECT test ""
+/begin MODULE test ""
+/* IF_DATA in comments and strings is ignored: /begin IF_DATA COMMENT "x" /end IF_DATA */
+/begin MOD_PAR "/begin IF_DATA STRING /end IF_DATA" /end MOD_PAR
+/begin IF_DATA MYVENDOR "custom data" /end IF_DATA
+/begin IF_DATA OTHERVENDOR
+  /begin SETTINGS 1 2 "a \"quoted\" text" /end SETTINGS
+/end IF_DATA
+/begin RECORD_LAYOUT U8 FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+/begin MEASUREMENT counter "" UWORD NO_COMPU_METHOD 0 0 0 65535 ECU_ADDRESS 0x1000
+  /begin IF_DATA MYVENDOR "measurement data" 42 /end IF_DATA
+/end MEASUREMENT
+/begin CHARACTERISTIC param "" VALUE 0x80000000 U8 0 NO_COMPU_METHOD 0 255 /end CHARACTERISTIC
+/end MODULE
+/end PROJECT
+"#;
+
+#[tokio::test]
+async fn test_a2l_vendor_if_data() {
+    std::fs::write("test_a2l_vendor_if_data.a2l", A2L).unwrap();
+
+    // Raw text of all blocks of a vendor, in file order
+    let extensions = a2l_load_extensions("test_a2l_vendor_if_data.a2l").unwrap();
+    assert_eq!(extensions.vendor_if_data.len(), 2);
+    assert_eq!(extensions.vendor_if_data["MYVENDOR"], "\"custom data\"\n\"measurement data\" 42");
+    assert_eq!(extensions.vendor_if_data["OTHERVENDOR"], r#"/begin SETTINGS 1 2 "a \"quoted\" text" /end SETTINGS"#);
+
+    // Retrievable from the XCP client after loading the A2L file, without connecting to a server
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    xcp_client.a2l_loader(Some("test_a2l_vendor_if_data.a2l"), false).await.unwrap();
+    let _ = std::fs::remove_file("test_a2l_vendor_if_data.a2l");
+    assert_eq!(xcp_client.get_vendor_if_data("MYVENDOR"), Some("\"custom data\"\n\"measurement data\" 42"));
+    assert!(xcp_client.get_vendor_if_data("OTHERVENDOR").unwrap().starts_with("/begin SETTINGS"));
+    assert_eq!(xcp_client.get_vendor_if_data("XCP"), None);
+    assert_eq!(xcp_client.get_vendor_if_data("COMMENT"), None);
+    assert_eq!(xcp_client.get_vendor_if_data("STRING"), None);
+    assert_eq!(xcp_client.get_characteristics(), vec!["param"]);
+}
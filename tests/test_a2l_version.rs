@@ -0,0 +1,76 @@
+// test_a2l_version
+// Integration test for the A2L version, the registry writes the EPK as HEADER VERSION and the XCP client verifies it against the EPK in server memory
+// Uses the test XCP client in module xcp_client
+
+// cargo test --features=tokio -- --test-threads=1 --nocapture  --test test_a2l_version
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+const EPK: &str = "EPK_TEST_A2L_VERSION";
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    counter_max: u32,
+}
+
+const CAL_PAGE: CalPage = CalPage { counter_max: 1000 };
+
+//-----------------------------------------------------------------------------
+// Integration test A2L version, read the EPK and the A2L version and compare, verify against an A2L with another version
+
+#[tokio::test]
+async fn test_a2l_version() {
+    init_logging();
+
+    info!("Running test_a2l_version");
+
+    let (xcp, loopback) = XcpBuilder::new("test_a2l_version")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk(EPK)
+        .start_loopback_server(0)
+        .unwrap();
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+
+    let mut xcp_client = XcpClient::new_loopback(loopback.tx, loopback.rx);
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+
+    // Read the EPK from server memory and the A2L version
+    let a2l_file = xcp_client.get_a2l_file().unwrap();
+    let version = a2l_file.project.header.as_ref().unwrap().version.as_ref().unwrap().version_identifier.clone();
+    let addr_epk = a2l_file.project.module[0].mod_par.as_ref().unwrap().addr_epk[0].address;
+    let data = xcp_client.short_upload(addr_epk, 0, EPK.len() as u8).await.unwrap();
+    assert_eq!(&data[1..=EPK.len()], EPK.as_bytes());
+    assert_eq!(version, EPK);
+    assert!(xcp_client.verify_a2l_version().await.unwrap());
+
+    // A2L files of other application versions, with an EPK of the same length and a longer one, do not match
+    let a2l = std::fs::read_to_string("xcp_client_autodetect.a2l").unwrap();
+    for other_version in ["EPK_TEST_A2L_VERSIO2", "EPK_TEST_A2L_VERSION_2"] {
+        let other_a2l = a2l.replace(&format!(r#"VERSION "{}""#, EPK), &format!(r#"VERSION "{}""#, other_version));
+        assert_ne!(a2l, other_a2l);
+        std::fs::write("test_a2l_version_other.a2l", other_a2l).unwrap();
+        xcp_client.read_a2l("test_a2l_version_other.a2l", false).await.unwrap();
+        assert!(!xcp_client.verify_a2l_version().await.unwrap());
+    }
+
+    xcp_client.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    xcp.stop_async_server().await;
+
+    let _ = std::fs::remove_file("test_a2l_version.a2l");
+    let _ = std::fs::remove_file("test_a2l_version_other.a2l");
+}
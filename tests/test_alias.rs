@@ -0,0 +1,71 @@
+// test_alias
+// Integration test for alias names of calibration parameters
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_alias
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    ampl: f64,
+    period: f64,
+}
+
+const CAL_PAGE: CalPage = CalPage { ampl: 1.0, period: 1.0 };
+
+//-----------------------------------------------------------------------------
+// Integration test alias, a write to the alias is visible under the original name
+
+#[tokio::test]
+async fn test_alias() {
+    init_logging();
+
+    info!("Running test_alias");
+
+    let xcp = XcpBuilder::new("test_alias")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_ALIAS")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+    xcp.register_alias("CalPage.ampl", "CalPage.amplitude").unwrap();
+    assert!(xcp.register_alias("CalPage.unknown", "CalPage.alias").is_err());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect and upload the A2L
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+
+    // Both names upload the same value
+    let ampl = xcp_client.create_calibration_object("CalPage.ampl").await.unwrap();
+    let amplitude = xcp_client.create_calibration_object("CalPage.amplitude").await.unwrap();
+    assert_eq!(xcp_client.get_value_f64(ampl), 1.0);
+    assert_eq!(xcp_client.get_value_f64(amplitude), 1.0);
+
+    // Write the alias, read the original
+    xcp_client.set_value_f64(amplitude, 2.0).await.unwrap();
+    xcp_client.read_value_u64(ampl).await.unwrap();
+    assert_eq!(xcp_client.get_value_f64(ampl), 2.0);
+    cal_page.sync();
+    assert_eq!(cal_page.ampl, 2.0);
+
+    xcp_client.disconnect().await.unwrap();
+    xcp.stop_server();
+
+    let _ = std::fs::remove_file("test_alias.a2l");
+}
@@ -0,0 +1,170 @@
+// test_blob_decoder
+// Integration test for measurement of variable length blobs (protobuf serialized data)
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_blob_decoder
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use prost::Message;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// Protobuf message of the protobuf demo
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TestData {
+    #[prost(fixed32, tag = "1")]
+    pub counter: u32,
+    #[prost(double, tag = "2")]
+    pub signal: f64,
+    #[prost(string, tag = "3")]
+    pub text: String,
+}
+
+//-----------------------------------------------------------------------------
+// Blob decoder, decodes and collects the protobuf messages
+
+struct TestDataDecoder {
+    messages: Arc<Mutex<Vec<TestData>>>,
+}
+
+impl XcpBlobDecoder for TestDataDecoder {
+    fn decode(&mut self, name: &str, _timestamp: u64, data: &[u8]) {
+        assert_eq!(name, "test_data");
+        self.messages.lock().push(TestData::decode(data).unwrap());
+    }
+}
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, passes the blobs of ODT 0 to the blob decoder
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    daq_header_size: u8,
+    blob_decoder: TestDataDecoder,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        self.daq_header_size = daq_header_size;
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        assert_eq!(self.daq_header_size, 4);
+        let daq = u16::from_le_bytes([buf[2], buf[3]]) as usize;
+        assert_eq!(buf[0], 0); // odt
+        let timestamp = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]) as u64;
+        let data = &buf[8..];
+        for odt_entry in &self.odt_entries[daq] {
+            let blob = odt_entry.get_blob(data).expect("blob length exceeds max size");
+            self.blob_decoder.decode(&odt_entry.name, timestamp, blob);
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, protobuf serialized data with variable length in a 1ms cycle
+
+fn task() {
+    let annotation = r#"/begin ANNOTATION ANNOTATION_LABEL "ObjectDescription" ANNOTATION_ORIGIN "application/protobuf"
+    /begin ANNOTATION_TEXT
+        "<DynamicObject>"
+        "<RootType>TestData</RootType>"
+        "</DynamicObject>"
+        "message TestData {"
+        "  fixed32 counter = 1;"
+        "  double signal = 2;"
+        "  string text = 3;"
+        "}"
+    /end ANNOTATION_TEXT
+/end ANNOTATION"#
+        .to_string();
+
+    let mut event = daq_create_event!("task", 256);
+    let offset = event.add_blob("test_data", None, "protobuf serialized test data", annotation);
+    let mut test_data = TestData {
+        counter: 0,
+        signal: 0.0,
+        text: String::new(),
+    };
+    let mut buf = Vec::new();
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        test_data.counter += 1;
+        test_data.signal = test_data.counter as f64 * 0.5;
+        test_data.text = "x".repeat((test_data.counter % 100) as usize); // length changes from cycle to cycle
+        buf.clear();
+        test_data.encode(&mut buf).unwrap();
+        event.capture_blob(&buf, offset);
+        event.trigger();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test blob measurement and decoding
+
+#[tokio::test]
+async fn test_blob_decoder() {
+    init_logging();
+
+    info!("Running test_blob_decoder");
+
+    let xcp = XcpBuilder::new("test_blob_decoder")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_BLOB_DECODER")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and measure the blob
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        daq_header_size: 0,
+        blob_decoder: TestDataDecoder {
+            messages: Arc::clone(&messages),
+        },
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    xcp_client.create_measurement_object("test_data").unwrap();
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    // Check the decoded payloads
+    let messages = messages.lock();
+    info!("{} messages decoded", messages.len());
+    assert!(messages.len() > 100);
+    for m in messages.iter() {
+        assert_eq!(m.signal, m.counter as f64 * 0.5);
+        assert_eq!(m.text.len(), (m.counter % 100) as usize);
+    }
+    assert!(messages.windows(2).all(|w| w[1].counter > w[0].counter));
+
+    let _ = std::fs::remove_file("test_blob_decoder.a2l");
+}
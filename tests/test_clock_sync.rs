@@ -0,0 +1,96 @@
+// test_clock_sync
+// Integration test for the clock synchronization of the XCP client with the server DAQ clock
+// Uses the test XCP client in module xcp_client
+
+// cargo test --features=tokio -- --test-threads=1 --nocapture  --test test_clock_sync
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, records the clock synchronization
+
+#[derive(Default)]
+struct DaqDecoder {
+    clock_sync: Option<XcpTimeSyncResult>,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, _odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {}
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, _daq_header_size: u8) {}
+    fn decode(&mut self, _lost: u32, _buf: &[u8]) {}
+    fn set_clock_sync(&mut self, clock_sync: XcpTimeSyncResult) {
+        self.clock_sync = Some(clock_sync);
+    }
+}
+
+fn system_time_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+}
+
+//-----------------------------------------------------------------------------
+// Integration test clock sync, correlate the server DAQ clock with the client wall clock
+
+#[tokio::test]
+async fn test_clock_sync() {
+    init_logging();
+
+    info!("Running test_clock_sync");
+
+    let (xcp, loopback) = XcpBuilder::new("test_clock_sync")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_CLOCK_SYNC")
+        .start_loopback_server(0)
+        .unwrap();
+
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder::default()));
+    let mut xcp_client = XcpClient::new_loopback(loopback.tx, loopback.rx);
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+
+    // Not synchronized, server timestamps are unchanged
+    assert!(xcp_client.get_clock_sync().is_none());
+    assert_eq!(xcp_client.server_to_master_ns(1234), 1234);
+    assert!(xcp_client.check_clock_sync().await.unwrap().is_none());
+
+    // Synchronize
+    let clock_sync = xcp_client.sync_clock().await.unwrap();
+    info!("Clock sync: {:?}, offset={}ns", clock_sync, clock_sync.offset_ns());
+    assert_eq!(xcp_client.get_clock_sync(), Some(clock_sync));
+    assert_eq!(daq_decoder.lock().clock_sync, Some(clock_sync));
+    assert!(clock_sync.accuracy_ns < 10_000_000, "round trip too slow");
+    assert_eq!(xcp_client.server_to_master_ns(clock_sync.server_ts_ns), clock_sync.master_ts_ns);
+    assert_eq!(xcp_client.server_to_master_ns(clock_sync.server_ts_ns - 1000), clock_sync.master_ts_ns - 1000);
+
+    // The server DAQ clock converted to wall clock time matches the system time
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let t1 = system_time_ns();
+    let server_ts_ns = xcp.get_clock() * xcp_client.get_timestamp_resolution();
+    let t2 = system_time_ns();
+    let master_ts_ns = xcp_client.server_to_master_ns(server_ts_ns);
+    info!("Server clock {}ns, wall clock {}ns, system time {}ns..{}ns", server_ts_ns, master_ts_ns, t1, t2);
+    let tolerance_ns = 2_000_000 + clock_sync.accuracy_ns as u64;
+    assert!(master_ts_ns + tolerance_ns >= t1 && master_ts_ns <= t2 + tolerance_ns, "wall clock deviation too large");
+
+    // Periodic resync
+    xcp_client.set_clock_sync_interval(Some(Duration::from_secs(60)));
+    assert!(xcp_client.check_clock_sync().await.unwrap().is_none());
+    xcp_client.set_clock_sync_interval(Some(Duration::ZERO));
+    let resync = xcp_client.check_clock_sync().await.unwrap().unwrap();
+    assert!(resync.server_ts_ns > clock_sync.server_ts_ns);
+    assert_eq!(daq_decoder.lock().clock_sync, Some(resync));
+
+    xcp_client.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    xcp.stop_async_server().await;
+
+    let _ = std::fs::remove_file("test_clock_sync.a2l");
+}
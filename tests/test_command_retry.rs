@@ -0,0 +1,144 @@
+// test_command_retry
+// Integration test for the command timeout and the retry of idempotent commands in module xcp_client
+// A proxy between the client and the loopback transport of the server drops selected commands
+
+// cargo test --features=tokio -- --test-threads=1 --nocapture  --test test_command_retry
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+use xcp_client::xcp_client::*;
+
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(200);
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    counter_max: u32,
+}
+
+const CAL_PAGE: CalPage = CalPage { counter_max: 1000 };
+
+//-----------------------------------------------------------------------------
+// Lossy proxy, counts the commands sent by the client and drops the given number of the next commands with a command code
+
+#[derive(Default)]
+struct Proxy {
+    sent: HashMap<u8, u32>,
+    drop: HashMap<u8, u32>,
+}
+
+impl Proxy {
+    fn drop_next(&mut self, code: u8, count: u32) {
+        self.drop.insert(code, count);
+    }
+
+    fn sent(&self, code: u8) -> u32 {
+        self.sent.get(&code).copied().unwrap_or(0)
+    }
+}
+
+fn start_proxy(tx: mpsc::Sender<Vec<u8>>) -> (mpsc::Sender<Vec<u8>>, Arc<Mutex<Proxy>>) {
+    let (proxy_tx, mut proxy_rx) = mpsc::channel::<Vec<u8>>(16);
+    let proxy = Arc::new(Mutex::new(Proxy::default()));
+    let state = Arc::clone(&proxy);
+    tokio::spawn(async move {
+        while let Some(segment) = proxy_rx.recv().await {
+            // XCP on UDP transport layer header (len, ctr), followed by the command code
+            let code = segment[4];
+            let drop = {
+                let mut state = state.lock();
+                *state.sent.entry(code).or_default() += 1;
+                match state.drop.get_mut(&code) {
+                    Some(count) if *count > 0 => {
+                        *count -= 1;
+                        true
+                    }
+                    _ => false,
+                }
+            };
+            if drop {
+                info!("proxy: command 0x{:02X} dropped", code);
+            } else if tx.send(segment).await.is_err() {
+                break;
+            }
+        }
+    });
+    (proxy_tx, proxy)
+}
+
+fn error_code(e: &(dyn Error + 'static)) -> u8 {
+    e.downcast_ref::<xcp_client::xcp_client::XcpError>().expect("XcpError expected").get_error_code()
+}
+
+//-----------------------------------------------------------------------------
+// Integration test command retry
+
+#[tokio::test]
+async fn test_command_retry() {
+    init_logging();
+
+    info!("Running test_command_retry");
+
+    let (xcp, loopback) = XcpBuilder::new("test_command_retry")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_COMMAND_RETRY")
+        .start_loopback_server(0)
+        .unwrap();
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+
+    // Connect through the proxy
+    let (proxy_tx, proxy) = start_proxy(loopback.tx);
+    let mut xcp_client = XcpClient::new_loopback(proxy_tx, loopback.rx);
+    xcp_client.set_command_timeout(COMMAND_TIMEOUT);
+    xcp_client.set_retries(2);
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    let counter_max = xcp_client.create_calibration_object("CalPage.counter_max").await.unwrap();
+
+    // Idempotent commands are sent again after a timeout
+    proxy.lock().drop_next(CC_SHORT_UPLOAD, 1);
+    let sent = proxy.lock().sent(CC_SHORT_UPLOAD);
+    let start = Instant::now();
+    assert_eq!(xcp_client.read_value_u64(counter_max).await.unwrap(), 1000);
+    assert!(start.elapsed() >= COMMAND_TIMEOUT + CMD_RETRY_BACKOFF);
+    assert_eq!(proxy.lock().sent(CC_SHORT_UPLOAD), sent + 2);
+
+    proxy.lock().drop_next(CC_SHORT_DOWNLOAD, 2);
+    xcp_client.set_value_u64(counter_max, 2000).await.unwrap();
+    assert_eq!(xcp_client.read_value_u64(counter_max).await.unwrap(), 2000);
+    assert!(!xcp_client.is_session_lost());
+
+    // Commands depending on the MTA are never retried
+    proxy.lock().drop_next(CC_UPLOAD, 1);
+    let sent = proxy.lock().sent(CC_UPLOAD);
+    let e = xcp_client.upload_a2l(false).await.unwrap_err();
+    assert_eq!(error_code(e.as_ref()), ERROR_CMD_TIMEOUT);
+    assert_eq!(proxy.lock().sent(CC_UPLOAD), sent + 1);
+
+    // The command fails, when all retries time out
+    proxy.lock().drop_next(CC_SHORT_UPLOAD, 3);
+    let sent = proxy.lock().sent(CC_SHORT_UPLOAD);
+    let e = xcp_client.read_value_u64(counter_max).await.unwrap_err();
+    assert_eq!(error_code(e.as_ref()), ERROR_CMD_TIMEOUT);
+    assert_eq!(proxy.lock().sent(CC_SHORT_UPLOAD), sent + 3);
+    assert!(xcp_client.is_session_lost());
+
+    xcp_client.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    xcp.stop_async_server().await;
+
+    let _ = std::fs::remove_file("test_command_retry.a2l");
+}
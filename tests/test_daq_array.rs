@@ -0,0 +1,175 @@
+// test_daq_array
+// Integration test for the client side decoding of array and matrix measurements
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_daq_array
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::a2l::a2l_reader::A2lTypeEncoding;
+use xcp_client::xcp_client::*;
+
+const ARRAY_DIM: usize = 128; // 1024 bytes, objects are not split across ODTs, must fit into one DTO
+const MATRIX_X_DIM: usize = 3;
+const MATRIX_Y_DIM: usize = 4;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, reassembles the ODTs and decodes all values of each sample
+
+type Sample = Vec<(String, DaqValue)>;
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    odt_assembler: Option<DaqOdtAssembler>,
+    samples: Arc<Mutex<Vec<Sample>>>,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.odt_assembler = Some(DaqOdtAssembler::new(&odt_entries));
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        assert_eq!(daq_header_size, 4);
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        let odt = buf[0];
+        let daq = u16::from_le_bytes([buf[2], buf[3]]);
+        let data = if odt == 0 { &buf[8..] } else { &buf[4..] }; // Timestamp in ODT 0 only
+        let Some(event_data) = self.odt_assembler.as_mut().unwrap().add(daq, odt, data) else {
+            return;
+        };
+        let sample = self.odt_entries[daq as usize]
+            .iter()
+            .map(|odt_entry| (odt_entry.name.clone(), odt_entry.get_value(event_data).unwrap()))
+            .collect();
+        self.samples.lock().push(sample);
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, array1[i] = counter + i and matrix[x][y] = counter + x * MATRIX_Y_DIM + y, 1ms cycle
+
+fn task() {
+    let mut counter: u32 = 0;
+    let mut array1 = [0.0f64; ARRAY_DIM];
+    let mut event = daq_create_event!("task1", 64);
+    daq_register_array!(array1, event);
+    let matrix_offset = event.add_capture(
+        "matrix",
+        MATRIX_X_DIM * MATRIX_Y_DIM * 2,
+        RegistryDataType::Sword,
+        MATRIX_X_DIM as u16,
+        MATRIX_Y_DIM as u16,
+        1.0,
+        0.0,
+        "",
+        "test matrix",
+        None,
+    );
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        counter += 1;
+        for (i, a) in array1.iter_mut().enumerate() {
+            *a = (counter as usize + i) as f64;
+        }
+        let matrix: Vec<u8> = (0..MATRIX_X_DIM * MATRIX_Y_DIM).flat_map(|i| ((counter as usize + i) as i16).to_le_bytes()).collect();
+        event.capture(&matrix, matrix_offset);
+        daq_capture!(counter, event);
+        event.trigger();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test array and matrix decoding
+
+#[tokio::test]
+async fn test_daq_array() {
+    init_logging();
+
+    info!("Running test_daq_array");
+
+    let xcp = XcpBuilder::new("test_daq_array")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_DAQ_ARRAY")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and measure
+    let samples = Arc::new(Mutex::new(Vec::new()));
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        odt_assembler: None,
+        samples: Arc::clone(&samples),
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    for name in ["counter", "array1", "matrix"] {
+        xcp_client.create_measurement_object(name).unwrap();
+    }
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    // The dimensions of the A2L objects are known in the ODT entries
+    {
+        let daq_decoder = daq_decoder.lock();
+        let odt_entries: Vec<&OdtEntry> = daq_decoder.odt_entries.iter().flatten().collect();
+        let array1 = odt_entries.iter().find(|e| e.name == "array1").unwrap();
+        assert!(matches!(array1.a2l_type.encoding, A2lTypeEncoding::Float));
+        assert_eq!((array1.a2l_type.dim, array1.a2l_type.x_dim, array1.a2l_type.y_dim), (ARRAY_DIM as u16, ARRAY_DIM as u16, 1));
+        assert_eq!(array1.a2l_type.get_element_size(), 8);
+        let matrix = odt_entries.iter().find(|e| e.name == "matrix").unwrap();
+        assert!(matches!(matrix.a2l_type.encoding, A2lTypeEncoding::Signed));
+        assert_eq!((matrix.a2l_type.x_dim, matrix.a2l_type.y_dim), (MATRIX_X_DIM as u16, MATRIX_Y_DIM as u16));
+        assert_eq!(matrix.a2l_type.get_element_size(), 2);
+    }
+
+    // Each sample has the pattern of its counter
+    let samples = samples.lock();
+    info!("{} samples decoded", samples.len());
+    assert!(samples.len() > 100);
+    for sample in samples.iter() {
+        let value = |name: &str| &sample.iter().find(|(n, _)| n == name).unwrap().1;
+        let DaqValue::Scalar(counter) = *value("counter") else {
+            panic!("counter is not a scalar");
+        };
+        let DaqValue::Array(array1) = value("array1") else {
+            panic!("array1 is not an array");
+        };
+        assert_eq!(array1.len(), ARRAY_DIM);
+        assert!(array1.iter().enumerate().all(|(i, v)| *v == counter + i as f64));
+        let DaqValue::Matrix(matrix) = value("matrix") else {
+            panic!("matrix is not a matrix");
+        };
+        assert_eq!(matrix.len(), MATRIX_X_DIM);
+        for (x, row) in matrix.iter().enumerate() {
+            assert_eq!(row.len(), MATRIX_Y_DIM);
+            assert!(row.iter().enumerate().all(|(y, v)| *v == counter + (x * MATRIX_Y_DIM + y) as f64));
+        }
+    }
+
+    let _ = std::fs::remove_file("test_daq_array.a2l");
+}
@@ -0,0 +1,137 @@
+// test_daq_async
+// Integration test for measurement of task local data in async tasks, which are moved between tokio worker threads
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_daq_async
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+const TASK_COUNT: u32 = 8;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+static MIGRATIONS: AtomicU32 = AtomicU32::new(0);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, collects the pairs (value, check) of each sample
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    samples: Arc<Mutex<Vec<(u32, u32)>>>,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        assert_eq!(daq_header_size, 4);
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        let daq = u16::from_le_bytes([buf[2], buf[3]]) as usize;
+        let data = &buf[8..]; // 4 byte ODT header and 4 byte timestamp
+        let mut value = None;
+        let mut check = None;
+        for odt_entry in &self.odt_entries[daq] {
+            let o = odt_entry.offset as usize;
+            let v = u32::from_le_bytes([data[o], data[o + 1], data[o + 2], data[o + 3]]);
+            match odt_entry.name.as_str() {
+                "value" => value = Some(v),
+                "check" => check = Some(v),
+                name => panic!("unexpected odt entry {}", name),
+            }
+        }
+        self.samples.lock().push((value.unwrap(), check.unwrap()));
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Async task, captures value and its complement check, the worker thread may change at each await
+
+async fn task(task_index: u32) {
+    let mut event = daq_create_event!("async_task", 16);
+    let mut cycle: u32 = 0;
+    while RUN.load(Ordering::Relaxed) {
+        cycle += 1;
+        let value: u32 = (task_index << 24) | cycle;
+        let check: u32 = !value;
+        daq_capture_async!(value, event, "task index and cycle", "");
+        daq_capture_async!(check, event, "complement of value", "");
+        event.trigger();
+
+        let thread_id = std::thread::current().id();
+        tokio::time::sleep(Duration::from_micros(500)).await;
+        if std::thread::current().id() != thread_id {
+            MIGRATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test async capture
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_daq_async() {
+    init_logging();
+
+    info!("Running test_daq_async");
+
+    let xcp = XcpBuilder::new("test_daq_async")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_DAQ_ASYNC")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let tasks: Vec<_> = (1..=TASK_COUNT).map(|i| tokio::spawn(task(i))).collect();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and measure
+    let samples = Arc::new(Mutex::new(Vec::new()));
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        samples: Arc::clone(&samples),
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    xcp_client.create_measurement_object("value").unwrap();
+    xcp_client.create_measurement_object("check").unwrap();
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    for t in tasks {
+        t.await.unwrap();
+    }
+    xcp.stop_server();
+
+    // The tasks have been moved between worker threads, each sample is consistent and belongs to one of the tasks
+    let samples = samples.lock();
+    let migrations = MIGRATIONS.load(Ordering::Relaxed);
+    info!("{} samples, {} task migrations", samples.len(), migrations);
+    assert!(migrations > 0);
+    assert!(samples.len() > 1000);
+    let mut last_cycle = [0u32; TASK_COUNT as usize + 1];
+    for &(value, check) in samples.iter() {
+        assert_eq!(check, !value);
+        let task_index = (value >> 24) as usize;
+        assert!((1..=TASK_COUNT as usize).contains(&task_index));
+        let cycle = value & 0x00FF_FFFF;
+        assert!(cycle > last_cycle[task_index]);
+        last_cycle[task_index] = cycle;
+    }
+
+    let _ = std::fs::remove_file("test_daq_async.a2l");
+}
@@ -0,0 +1,93 @@
+// test_daq_backpressure
+// Integration test for the transmit queue backpressure of serialized objects (daq_try_serialize, daq_serialize_blocking)
+// Uses the async server with a transmit queue of one packet
+
+// cargo test --features=tokio -- --test-threads=1 --nocapture  --test test_daq_backpressure
+
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use std::time::{Duration, Instant};
+
+// Transmit queue size in packets
+const QUEUE_SIZE: u32 = 1;
+
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, serde::Serialize, IdlGenerator)]
+struct Sample {
+    counter: u32,
+    values: Vec<f32>,
+}
+
+//-----------------------------------------------------------------------------
+// Integration test transmit queue backpressure
+
+#[tokio::test]
+async fn test_daq_backpressure() {
+    init_logging();
+
+    info!("Running test_daq_backpressure");
+
+    let xcp = XcpBuilder::new("test_daq_backpressure")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_DAQ_BACKPRESSURE")
+        .start_async_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555, QUEUE_SIZE)
+        .await
+        .unwrap();
+
+    // The queue of one packet is empty, no client connected
+    let queue_size = xcp.get_transmit_queue_size();
+    info!("Transmit queue size = {} bytes, max DTO size = {} bytes", queue_size, xcp.get_max_dto_size());
+    assert!(queue_size > xcp.get_max_dto_size() && queue_size < 2 * xcp.get_max_dto_size());
+    assert_eq!(xcp.get_transmit_queue_free(), queue_size);
+    let gauge = TransmitQueueGauge::new("transmit_queue");
+    assert_eq!(gauge.level_percent(), 0.0);
+
+    // Small events fit into the queue
+    let sample1 = Sample {
+        counter: 1,
+        values: vec![1.0; 16],
+    };
+    let mut event1 = daq_create_event!("event1", 256);
+    assert_eq!(daq_try_serialize!(sample1, event1, "small sample"), Ok(()));
+    let len = u16::from_le_bytes([event1.buffer[0], event1.buffer[1]]) as usize;
+    assert!(len > 16 * 4 + 4, "serialized sample not captured");
+    event1.trigger();
+    let sample4 = Sample {
+        counter: 4,
+        values: vec![4.0; 16],
+    };
+    let mut event3 = daq_create_event!("event3", 256);
+    assert_eq!(daq_serialize_blocking!(sample4, event3, "small sample", timeout = TIMEOUT), Ok(()));
+    let len = u16::from_le_bytes([event3.buffer[0], event3.buffer[1]]) as usize;
+    assert!(len > 16 * 4 + 4, "serialized sample not captured");
+    event3.trigger();
+
+    // Two serialized samples in one event do not fit into the queue, the samples are not serialized
+    let sample2 = Sample {
+        counter: 2,
+        values: vec![2.0; 16],
+    };
+    let sample3 = Sample {
+        counter: 3,
+        values: vec![3.0; 16],
+    };
+    let mut event2 = daq_create_event!("event2", 2048);
+    assert_eq!(daq_try_serialize!(sample2, event2, "large sample", 1024), Err(DaqError::QueueFull));
+    assert_eq!(daq_try_serialize!(sample3, event2, "large sample", 1024), Err(DaqError::QueueFull));
+    assert!(event2.buffer.iter().all(|b| *b == 0));
+
+    // Blocking serialize waits for the timeout
+    let start = Instant::now();
+    assert_eq!(daq_serialize_blocking!(sample2, event2, "large sample", 1024, timeout = TIMEOUT), Err(DaqError::Timeout));
+    assert!(start.elapsed() >= TIMEOUT);
+    assert!(event2.buffer.iter().all(|b| *b == 0));
+
+    xcp.stop_async_server().await;
+    let _ = std::fs::remove_file("test_daq_backpressure.a2l");
+}
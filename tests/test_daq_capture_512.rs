@@ -0,0 +1,147 @@
+// test_daq_capture_512
+// Integration test for an event with a 512 byte capture buffer, which is split into multiple ODTs
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_daq_capture_512
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+const MAX_CTO: u16 = 64;
+const MAX_DTO: u16 = 256; // Max ODT payload is 248 bytes in ODT 0 and 252 bytes in the following ODTs
+
+const CAPTURE_BUFFER_SIZE: usize = 512;
+const ARRAY_NAMES: [&str; 4] = ["array0", "array1", "array2", "array3"];
+const ARRAY_DIM: usize = CAPTURE_BUFFER_SIZE / ARRAY_NAMES.len() / 8;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, reassembles the ODTs and checks the arrays
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    odt_assembler: Option<DaqOdtAssembler>,
+    odt_count: usize,
+    counters: Arc<Mutex<Vec<u64>>>,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        let odt_assembler = DaqOdtAssembler::new(&odt_entries);
+        self.odt_count = odt_assembler.get_odt_count(0);
+        self.odt_assembler = Some(odt_assembler);
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        assert_eq!(daq_header_size, 4);
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        assert!(buf.len() <= MAX_DTO as usize);
+        let odt = buf[0];
+        let daq = u16::from_le_bytes([buf[2], buf[3]]);
+        let data = if odt == 0 { &buf[8..] } else { &buf[4..] }; // Timestamp in ODT 0 only
+        let Some(event_data) = self.odt_assembler.as_mut().unwrap().add(daq, odt, data) else {
+            return;
+        };
+
+        // Element j of array i is counter + i * ARRAY_DIM + j
+        let mut counter = None;
+        for odt_entry in &self.odt_entries[daq as usize] {
+            let i = ARRAY_NAMES.iter().position(|name| *name == odt_entry.name).unwrap();
+            let o = odt_entry.offset as usize;
+            let values: Vec<u64> = event_data[o..o + ARRAY_DIM * 8]
+                .chunks_exact(8)
+                .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+                .collect();
+            let c = *counter.get_or_insert(values[0] - (i * ARRAY_DIM) as u64);
+            assert!(values.iter().enumerate().all(|(j, v)| *v == c + (i * ARRAY_DIM + j) as u64));
+        }
+        self.counters.lock().push(counter.unwrap());
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, four u64 arrays fill the capture buffer, 1ms cycle
+
+fn task() {
+    let mut event = daq_create_event!("task", CAPTURE_BUFFER_SIZE);
+    let offsets: Vec<i16> = ARRAY_NAMES
+        .iter()
+        .map(|name| event.add_capture(name, ARRAY_DIM * 8, RegistryDataType::AUint64, ARRAY_DIM as u16, 1, 1.0, 0.0, "", "test array", None))
+        .collect();
+    let mut counter: u64 = 0;
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        counter += 1;
+        for (i, offset) in offsets.iter().enumerate() {
+            let bytes: Vec<u8> = (0..ARRAY_DIM).flat_map(|j| (counter + (i * ARRAY_DIM + j) as u64).to_le_bytes()).collect();
+            event.capture(&bytes, *offset);
+        }
+        event.trigger();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test multi ODT capture buffer
+
+#[tokio::test]
+async fn test_daq_capture_512() {
+    init_logging();
+
+    info!("Running test_daq_capture_512");
+
+    let xcp = XcpBuilder::new("test_daq_capture_512")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_DAQ_CAPTURE_512")
+        .set_max_cto_dto(MAX_CTO, MAX_DTO)
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and measure all arrays
+    let counters = Arc::new(Mutex::new(Vec::new()));
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        odt_assembler: None,
+        odt_count: 0,
+        counters: Arc::clone(&counters),
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    for name in ARRAY_NAMES {
+        xcp_client.create_measurement_object(name).unwrap();
+    }
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    // Each 128 byte array needs its own ODT, the event data is reassembled from all of them
+    assert_eq!(daq_decoder.lock().odt_count, ARRAY_NAMES.len());
+    let counters = counters.lock();
+    info!("{} events decoded", counters.len());
+    assert!(counters.len() > 100);
+    assert!(counters.windows(2).all(|w| w[1] > w[0]));
+
+    let _ = std::fs::remove_file("test_daq_capture_512.a2l");
+}
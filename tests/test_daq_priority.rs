@@ -0,0 +1,150 @@
+// test_daq_priority
+// Integration test for event priorities under transmit queue pressure
+// Uses the async server with a transmit queue of two packets and the test XCP client in module xcp_client
+
+// cargo test --features=tokio -- --test-threads=1 --nocapture  --test test_daq_priority
+
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+// Transmit queue size in packets
+const QUEUE_SIZE: u32 = 2;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, counts the samples of both events
+
+#[derive(Default)]
+struct Samples {
+    control: u64,
+    bulk: u64,
+}
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    samples: Arc<Mutex<Samples>>,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        assert_eq!(daq_header_size, 4);
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        let daq = u16::from_le_bytes([buf[2], buf[3]]) as usize;
+        let mut samples = self.samples.lock();
+        for odt_entry in &self.odt_entries[daq] {
+            match odt_entry.name.as_str() {
+                "control_counter" => samples.control += 1,
+                "bulk_counter" => samples.bulk += 1,
+                _ => panic!("unexpected measurement {}", odt_entry.name),
+            }
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, 1ms cycle, a high priority event with a small and a normal priority event with a large capture buffer
+// With a high priority event, normal priority events are checked against the queue space, the capture buffer of the bulk event never fits
+
+fn task() {
+    let mut control_event = daq_create_event!("control", 64, priority = High);
+    let mut bulk_event = daq_create_event!("bulk", 3000);
+    let mut control_counter: u32 = 0;
+    let mut bulk_counter: u32 = 0;
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        control_counter += 1;
+        bulk_counter += 1;
+        daq_capture!(control_counter, control_event);
+        daq_capture!(bulk_counter, bulk_event);
+        control_event.trigger();
+        bulk_event.trigger();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test event priority
+
+#[tokio::test]
+async fn test_daq_priority() {
+    init_logging();
+
+    info!("Running test_daq_priority");
+
+    let xcp = XcpBuilder::new("test_daq_priority")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_DAQ_PRIORITY")
+        .start_async_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555, QUEUE_SIZE)
+        .await
+        .unwrap();
+    info!("Transmit queue size = {} bytes", xcp.get_transmit_queue_size());
+    let t = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and measure both counters
+    let samples = Arc::new(Mutex::new(Samples::default()));
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        samples: Arc::clone(&samples),
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    xcp_client.create_measurement_object("control_counter").unwrap();
+    xcp_client.create_measurement_object("bulk_counter").unwrap();
+
+    // The A2L EVENT carries the priority
+    let a2l = std::fs::read_to_string("test_daq_priority.a2l").unwrap();
+    assert!(a2l.lines().any(|l| l.contains("/begin EVENT \"control\"") && l.contains(" 0 0 1 CONSISTENCY")));
+    assert!(a2l.lines().any(|l| l.contains("/begin EVENT \"bulk\"") && l.contains(" 0 0 0 CONSISTENCY")));
+
+    // Without running DAQ, nothing is transmitted and no drops are counted
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(xcp.get_daq_drop_count(XcpEventPriority::High), 0);
+    assert_eq!(xcp.get_daq_drop_count(XcpEventPriority::Normal), 0);
+
+    // The high priority event reserves its queue space, the bulk event does not fit into the rest of the queue
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    let high = xcp.get_daq_drop_count(XcpEventPriority::High);
+    let normal = xcp.get_daq_drop_count(XcpEventPriority::Normal);
+
+    // Drops are not counted after DAQ stopped
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(xcp.get_daq_drop_count(XcpEventPriority::Normal), normal);
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t.join().unwrap();
+    xcp.stop_async_server().await;
+
+    let samples = samples.lock();
+    info!("Samples: control = {}, bulk = {}", samples.control, samples.bulk);
+    info!("Dropped: high = {}, normal = {}", high, normal);
+    assert!(normal > 100, "normal priority event must be dropped");
+    assert_eq!(samples.bulk, 0);
+    assert_eq!(high, 0);
+    assert!(samples.control * 10 >= normal * 9, "high priority event must get through");
+
+    let _ = std::fs::remove_file("test_daq_priority.a2l");
+    let _ = std::fs::remove_file("xcp_client_autodetect.a2l");
+}
@@ -0,0 +1,124 @@
+// test_daq_slice
+// Integration test for measurement of a sub range of a large array in static memory
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_daq_slice
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+const RING_BUFFER_SIZE: usize = 1024;
+const SLICE_START: usize = 256;
+const SLICE_LEN: usize = 32;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+static RING_BUFFER: static_cell::StaticCell<[u32; RING_BUFFER_SIZE]> = static_cell::StaticCell::new();
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, collects the samples of the slice
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    samples: Arc<Mutex<Vec<Vec<u32>>>>,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        assert_eq!(daq_header_size, 4);
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        let daq = u16::from_le_bytes([buf[2], buf[3]]) as usize;
+        let data = &buf[8..]; // 4 byte ODT header and 4 byte timestamp
+        for odt_entry in &self.odt_entries[daq] {
+            assert_eq!(odt_entry.name, "ring_buffer");
+            assert_eq!(odt_entry.a2l_type.dim as usize, SLICE_LEN);
+            assert_eq!(odt_entry.a2l_type.size as usize, SLICE_LEN * 4);
+            let o = odt_entry.offset as usize;
+            let sample = data[o..o + SLICE_LEN * 4].chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+            self.samples.lock().push(sample);
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, 1ms cycle, fills the ring buffer with cycle * RING_BUFFER_SIZE + index and measures a slice in the middle
+
+fn task() {
+    let ring_buffer: &'static mut [u32; RING_BUFFER_SIZE] = RING_BUFFER.init([0; RING_BUFFER_SIZE]);
+    let event = daq_create_event!("task");
+    daq_register_slice!(ring_buffer, 256..288, event, "slice of the ring buffer", "");
+
+    let mut cycle: u32 = 0;
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        cycle += 1;
+        for (i, v) in ring_buffer.iter_mut().enumerate() {
+            *v = cycle * RING_BUFFER_SIZE as u32 + i as u32;
+        }
+        event.trigger_abs();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test array slice measurement
+
+#[tokio::test]
+async fn test_daq_slice() {
+    init_logging();
+
+    info!("Running test_daq_slice");
+
+    let xcp = XcpBuilder::new("test_daq_slice")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_DAQ_SLICE")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and measure the slice
+    let samples = Arc::new(Mutex::new(Vec::new()));
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        samples: Arc::clone(&samples),
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    xcp_client.create_measurement_object("ring_buffer").unwrap();
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    // Each sample contains the elements SLICE_START..SLICE_START+SLICE_LEN of one cycle
+    let samples = samples.lock();
+    info!("{} samples", samples.len());
+    assert!(samples.len() > 200);
+    for sample in samples.iter() {
+        assert_eq!(sample[0] as usize % RING_BUFFER_SIZE, SLICE_START);
+        assert!(sample.windows(2).all(|w| w[1] == w[0] + 1));
+    }
+
+    let _ = std::fs::remove_file("test_daq_slice.a2l");
+}
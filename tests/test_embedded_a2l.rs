@@ -0,0 +1,58 @@
+// test_embedded_a2l
+// Integration test for the upload of an A2L embedded in the application binary
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_embedded_a2l
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+static COUNTER: u32 = 0;
+
+//-----------------------------------------------------------------------------
+// Integration test embedded A2L
+// The A2L generated in memory is embedded, the uploaded A2L must be identical
+
+#[tokio::test]
+async fn test_embedded_a2l() {
+    init_logging();
+
+    info!("Running test_embedded_a2l");
+
+    let xcp = XcpBuilder::new("test_embedded_a2l")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_EMBEDDED_A2L")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+
+    let event = xcp.create_event("task");
+    daq_register_static!(COUNTER, event, "counter");
+
+    // Generate the A2L, as the runtime generator would write it, and embed it
+    let a2l: &'static str = xcp.generate_a2l_string().unwrap().leak();
+    xcp.set_embedded_a2l(a2l);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect and upload the A2L
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    assert!(xcp_client.get_a2l_file().is_some());
+    xcp_client.disconnect().await.unwrap();
+    xcp.stop_server();
+
+    // The uploaded A2L is identical to the embedded A2L
+    let uploaded = std::fs::read_to_string("xcp_client_autodetect.a2l").unwrap();
+    assert_eq!(uploaded, a2l);
+    assert!(!std::path::Path::new("test_embedded_a2l.a2l").exists());
+
+    let _ = std::fs::remove_file("xcp_client_autodetect.a2l");
+}
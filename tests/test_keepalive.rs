@@ -0,0 +1,82 @@
+// test_keepalive
+// Integration test for the XCP server keep-alive connection supervision
+// Uses the test XCP client in module xcp_client and a raw UDP socket as a silent client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_keepalive
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+use xcp_client::xcp_client::*;
+
+//-----------------------------------------------------------------------------
+// Decoders, count the keep-alive heartbeats received by the XCP client
+
+static HEARTBEAT_COUNT: AtomicU32 = AtomicU32::new(0);
+
+struct ServTextDecoder;
+
+impl XcpTextDecoder for ServTextDecoder {
+    fn decode(&self, data: &[u8]) {
+        if data.starts_with(b"XCP keep-alive") {
+            HEARTBEAT_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn is_connected(xcp: &Xcp) -> bool {
+    xcp.get_session_status().contains(XcpSessionStatus::SS_CONNECTED)
+}
+
+//-----------------------------------------------------------------------------
+// Integration test keep-alive
+
+#[tokio::test]
+async fn test_keepalive() {
+    init_logging();
+
+    info!("Running test_keepalive");
+
+    let xcp = XcpBuilder::new("test_keepalive")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_KEEPALIVE")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    xcp.set_keepalive(1, 1);
+
+    // The XCP client answers the heartbeats and stays connected, while idle for longer than idle + timeout
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(4)).await;
+    assert!(HEARTBEAT_COUNT.load(Ordering::Relaxed) > 0);
+    assert!(is_connected(xcp));
+    xcp_client.disconnect().await.unwrap();
+    assert!(!is_connected(xcp));
+
+    // A client which stops sending commands gets disconnected
+    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket.send_to(&[0x02, 0x00, 0x00, 0x00, 0xFF, 0x00], "127.0.0.1:5555").await.unwrap(); // CONNECT
+    let mut buf = [0u8; 256];
+    tokio::time::timeout(Duration::from_secs(1), socket.recv_from(&mut buf)).await.unwrap().unwrap();
+    assert!(is_connected(xcp));
+    let last_command_time = xcp.get_last_command_time();
+
+    let start = Instant::now();
+    while is_connected(xcp) {
+        assert!(start.elapsed() < Duration::from_secs(4), "XCP server did not disconnect the silent client");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    info!("Silent client disconnected after {}ms", start.elapsed().as_millis());
+    assert!(start.elapsed() >= Duration::from_secs(2)); // idle + timeout
+    assert_eq!(xcp.get_last_command_time(), last_command_time);
+
+    xcp.stop_server();
+    let _ = std::fs::remove_file("test_keepalive.a2l");
+}
@@ -0,0 +1,73 @@
+// test_loopback
+// Integration test for the in-process loopback transport, client and server communicate without sockets
+// Uses the test XCP client in module xcp_client
+
+// cargo test --features=tokio -- --test-threads=1 --nocapture  --test test_loopback
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    ampl: f64,
+    counter_max: u32,
+}
+
+const CAL_PAGE: CalPage = CalPage { ampl: 1.5, counter_max: 1000 };
+
+//-----------------------------------------------------------------------------
+// Integration test loopback, connect, upload the A2L, read and write calibration parameters
+
+#[tokio::test]
+async fn test_loopback() {
+    init_logging();
+
+    info!("Running test_loopback");
+
+    let (xcp, loopback) = XcpBuilder::new("test_loopback")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_LOOPBACK")
+        .start_loopback_server(0)
+        .unwrap();
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+
+    // Only one server per process
+    assert!(xcp.start_loopback_server(0).is_err());
+
+    // Connect and upload the A2L
+    let mut xcp_client = XcpClient::new_loopback(loopback.tx, loopback.rx);
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    let (rx_packets, _) = xcp_client.get_rx_stats();
+    assert!(rx_packets > 0);
+
+    // Read the default values
+    let ampl = xcp_client.create_calibration_object("CalPage.ampl").await.unwrap();
+    let counter_max = xcp_client.create_calibration_object("CalPage.counter_max").await.unwrap();
+    assert_eq!(xcp_client.get_value_f64(ampl), 1.5);
+    assert_eq!(xcp_client.get_value_u64(counter_max), 1000);
+
+    // Write and read back
+    xcp_client.set_value_u64(counter_max, 2000).await.unwrap();
+    assert_eq!(xcp_client.read_value_u64(counter_max).await.unwrap(), 2000);
+    cal_page.sync();
+    assert_eq!(cal_page.counter_max, 2000);
+
+    xcp_client.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    xcp.stop_async_server().await;
+
+    let _ = std::fs::remove_file("test_loopback.a2l");
+}
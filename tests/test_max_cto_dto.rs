@@ -0,0 +1,134 @@
+// test_max_cto_dto
+// Integration test for a XCP server with reduced max CTO and DTO size
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_max_cto_dto
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+const MAX_CTO: u16 = 64;
+const MAX_DTO: u16 = 256;
+
+const BLOB_NAMES: [&str; 3] = ["blob1", "blob2", "blob3"];
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, checks the DTO size and counts complete events
+
+struct DaqDecoder {
+    odt_assembler: Option<DaqOdtAssembler>,
+    odt_count: usize,
+    event_count: u32,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        let odt_assembler = DaqOdtAssembler::new(&odt_entries);
+        self.odt_count = odt_assembler.get_odt_count(0);
+        self.odt_assembler = Some(odt_assembler);
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        assert_eq!(daq_header_size, 4);
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        assert!(buf.len() <= MAX_DTO as usize);
+        let odt = buf[0];
+        let daq = u16::from_le_bytes([buf[2], buf[3]]);
+        let data = if odt == 0 { &buf[8..] } else { &buf[4..] };
+        if self.odt_assembler.as_mut().unwrap().add(daq, odt, data).is_some() {
+            self.event_count += 1;
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, blobs which do not fit into a single DTO in a 1ms cycle
+
+fn task() {
+    let mut event = daq_create_event!("task", 1024);
+    let offsets: Vec<i16> = BLOB_NAMES.iter().map(|name| event.add_blob(name, Some(200), "test blob", String::new())).collect();
+    let mut counter: u32 = 0;
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        counter = counter.wrapping_add(1);
+        for offset in &offsets {
+            event.capture_blob(&counter.to_le_bytes(), *offset);
+        }
+        event.trigger();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test max CTO and DTO size
+
+#[tokio::test]
+async fn test_max_cto_dto() {
+    init_logging();
+
+    info!("Running test_max_cto_dto");
+
+    // Impossible combinations are rejected
+    assert!(XcpBuilder::new("test_max_cto_dto").set_max_cto_dto(MAX_CTO, 32).start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555).is_err());
+    assert!(XcpBuilder::new("test_max_cto_dto").set_max_cto_dto(60, MAX_DTO).start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555).is_err());
+    assert!(XcpBuilder::new("test_max_cto_dto").set_max_cto_dto(MAX_CTO, 0xFFFC).start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555).is_err());
+
+    let xcp = XcpBuilder::new("test_max_cto_dto")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_MAX_CTO_DTO")
+        .set_max_cto_dto(MAX_CTO, MAX_DTO)
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, the server reports the reduced sizes
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_assembler: None,
+        odt_count: 0,
+        event_count: 0,
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    assert_eq!(xcp_client.get_max_cto_size() as u16, MAX_CTO);
+    assert_eq!(xcp_client.get_max_dto_size(), MAX_DTO);
+
+    // Upload the A2L in CTO sized chunks, the protocol layer parameters contain the reduced sizes
+    xcp_client.upload_a2l(false).await.unwrap();
+    let a2l = std::fs::read_to_string("test_max_cto_dto.a2l").unwrap();
+    assert!(a2l.contains(&format!("0x104 1000 2000 0 0 0 0 0 {} {} BYTE_ORDER_MSB_LAST", MAX_CTO, MAX_DTO)));
+
+    // Measure, the event is split into multiple DTOs
+    for name in BLOB_NAMES {
+        xcp_client.create_measurement_object(name).unwrap();
+    }
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    let daq_decoder = daq_decoder.lock();
+    info!("{} events in {} ODTs", daq_decoder.event_count, daq_decoder.odt_count);
+    assert!(daq_decoder.odt_count > 1);
+    assert!(daq_decoder.event_count > 100);
+
+    let _ = std::fs::remove_file("test_max_cto_dto.a2l");
+}
@@ -0,0 +1,114 @@
+// test_mdf4_decoder
+// Integration test for recording a measurement to an MDF4 file with Mdf4DaqDecoder
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_mdf4_decoder
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::mdf4_decoder::Mdf4DaqDecoder;
+use xcp_client::xcp_client::*;
+
+const MDF_FILE: &str = "test_mdf4_decoder.mf4";
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// Test task, measurement of a counter in a 1ms cycle
+
+fn task() {
+    let mut counter: u32 = 0;
+    let event = daq_create_event!("task");
+    daq_register!(counter, event, "test counter", "cnt", 0.5, 0.0);
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        counter = counter.wrapping_add(1);
+        event.trigger();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Minimal MDF4 block access for checking the file
+
+// Find the first block with the given id and return its data (block header and links excluded) and its length field
+fn find_block<'a>(mdf: &'a [u8], id: &[u8; 4]) -> (&'a [u8], usize) {
+    let pos = mdf.windows(4).position(|w| w == id).expect("block not found");
+    let length = u64::from_le_bytes(mdf[pos + 8..pos + 16].try_into().unwrap()) as usize;
+    let link_count = u64::from_le_bytes(mdf[pos + 16..pos + 24].try_into().unwrap()) as usize;
+    (&mdf[pos + 24 + link_count * 8..pos + length], length)
+}
+
+//-----------------------------------------------------------------------------
+// Integration test MDF4 recording
+
+#[tokio::test]
+async fn test_mdf4_decoder() {
+    init_logging();
+
+    info!("Running test_mdf4_decoder");
+
+    let xcp = XcpBuilder::new("test_mdf4_decoder")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_MDF4_DECODER")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and record the counter to the MDF file
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(Mdf4DaqDecoder::new(MDF_FILE)));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    daq_decoder.lock().set_a2l(xcp_client.get_a2l_file().unwrap());
+    xcp_client.create_measurement_object("counter").unwrap();
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    // Finalized MDF file with channel name and unit from the A2L
+    let record_count = daq_decoder.lock().get_record_count() as usize;
+    info!("{} records written", record_count);
+    assert!(record_count > 100);
+    let mdf = std::fs::read(MDF_FILE).unwrap();
+    assert_eq!(&mdf[0..8], b"MDF     ");
+    assert!(mdf.windows(8).any(|w| w == b"counter\0"));
+    assert!(mdf.windows(4).any(|w| w == b"cnt\0"));
+
+    // Channel group cycle count
+    let (cg_data, _) = find_block(&mdf, b"##CG");
+    assert_eq!(u64::from_le_bytes(cg_data[8..16].try_into().unwrap()) as usize, record_count);
+
+    // Records with record id 0, 64 bit time and the u32 counter
+    let (dt_data, _) = find_block(&mdf, b"##DT");
+    assert_eq!(dt_data.len(), record_count * 14);
+    let mut last: Option<(u64, u32)> = None;
+    for record in dt_data.chunks(14) {
+        assert_eq!(u16::from_le_bytes(record[0..2].try_into().unwrap()), 0);
+        let t = u64::from_le_bytes(record[2..10].try_into().unwrap());
+        let counter = u32::from_le_bytes(record[10..14].try_into().unwrap());
+        if let Some((t_last, counter_last)) = last {
+            assert!(t > t_last);
+            assert!(counter > counter_last);
+        }
+        last = Some((t, counter));
+    }
+
+    let _ = std::fs::remove_file(MDF_FILE);
+    let _ = std::fs::remove_file("test_mdf4_decoder.a2l");
+}
@@ -0,0 +1,170 @@
+// test_measurement_group
+// Integration test for runtime gated measurement groups
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_measurement_group
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, collects the samples of the ungated and the gated signals
+
+#[derive(Default)]
+struct Samples {
+    counter: Vec<u32>,
+    diag_counter: Vec<u32>,
+    stack_counter: Vec<u32>,
+    diag_stack_counter: Vec<u32>,
+}
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    samples: Arc<Mutex<Samples>>,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        assert_eq!(daq_header_size, 4);
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        let daq = u16::from_le_bytes([buf[2], buf[3]]) as usize;
+        let data = &buf[8..]; // 4 byte ODT header and 4 byte timestamp
+        let mut samples = self.samples.lock();
+        for odt_entry in &self.odt_entries[daq] {
+            let o = odt_entry.offset as usize;
+            let value = u32::from_le_bytes([data[o], data[o + 1], data[o + 2], data[o + 3]]);
+            match odt_entry.name.as_str() {
+                "counter" => samples.counter.push(value),
+                "diag_counter" => samples.diag_counter.push(value),
+                "stack_counter" => samples.stack_counter.push(value),
+                "diag_stack_counter" => samples.diag_stack_counter.push(value),
+                _ => panic!("unexpected measurement {}", odt_entry.name),
+            }
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test tasks, 1ms cycle, the diag signals are copies of the counters in the measurement group "diag"
+
+fn capture_task() {
+    let mut event = daq_create_event!("capture_task", 16);
+    let mut counter: u32 = 0;
+    let mut diag_counter: u32;
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        counter += 1;
+        diag_counter = counter;
+        daq_capture!(counter, event);
+        daq_capture!(diag_counter, event, "diagnostic copy of counter", "", gate = "diag");
+        event.trigger();
+    }
+}
+
+fn stack_task() {
+    let mut event = daq_create_event!("stack_task");
+    let mut stack_counter: u32 = 0;
+    let mut diag_stack_counter: u32;
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        stack_counter += 1;
+        diag_stack_counter = stack_counter;
+        daq_register!(stack_counter, event);
+        daq_register!(diag_stack_counter, event, gate = "diag");
+        event.trigger();
+    }
+}
+
+// The gated samples must be one gap free run of counter values, which starts after the first and ends before the last ungated sample
+fn check_gated(name: &str, counter: &[u32], gated: &[u32]) {
+    info!("{}: {} ungated samples, {} gated samples", name, counter.len(), gated.len());
+    assert!(counter.len() > 500);
+    assert!(counter.windows(2).all(|w| w[1] == w[0] + 1));
+    assert!(gated.len() > 100, "no data while the group is enabled");
+    assert!(gated.len() * 2 < counter.len(), "data while the group is disabled");
+    assert!(gated.windows(2).all(|w| w[1] == w[0] + 1));
+    assert!(gated[0] > counter[0]);
+    assert!(gated.last().unwrap() < counter.last().unwrap());
+}
+
+//-----------------------------------------------------------------------------
+// Integration test runtime gated measurement group
+
+#[tokio::test]
+async fn test_measurement_group() {
+    init_logging();
+
+    info!("Running test_measurement_group");
+
+    let xcp = XcpBuilder::new("test_measurement_group")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_MEASUREMENT_GROUP")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let t1 = thread::spawn(capture_task);
+    let t2 = thread::spawn(stack_task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!xcp.is_measurement_group_enabled("diag"));
+
+    // Connect, upload the A2L and measure all signals
+    let samples = Arc::new(Mutex::new(Samples::default()));
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        samples: Arc::clone(&samples),
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    for name in ["counter", "diag_counter", "stack_counter", "diag_stack_counter"] {
+        xcp_client.create_measurement_object(name).unwrap();
+    }
+
+    // The gated signals are in the A2L, on their auxiliary event and annotated with their group
+    let a2l = std::fs::read_to_string("test_measurement_group.a2l").unwrap();
+    assert!(a2l.contains("\"capture_task_diag\""));
+    assert!(a2l.contains("\"stack_task_diag\""));
+    assert_eq!(a2l.matches(r#"ANNOTATION_LABEL "MEASUREMENT_GROUP""#).count(), 2);
+
+    // Measure with the group disabled, enabled and disabled again
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    xcp.enable_measurement_group("diag", true);
+    assert!(xcp.is_measurement_group_enabled("diag"));
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    xcp.enable_measurement_group("diag", false);
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    t2.join().unwrap();
+    xcp.stop_server();
+
+    let samples = samples.lock();
+    check_gated("capture_task", &samples.counter, &samples.diag_counter);
+    check_gated("stack_task", &samples.stack_counter, &samples.diag_stack_counter);
+
+    let _ = std::fs::remove_file("test_measurement_group.a2l");
+    let _ = std::fs::remove_file("xcp_client_autodetect.a2l");
+}
@@ -0,0 +1,139 @@
+// test_monotone_axis
+// Integration test for the monotony check of axis calibration parameters on XCP writes (GuardRailsMode)
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_monotone_axis
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::a2l::a2l_reader;
+use xcp_client::xcp_client::*;
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    #[type_description(axis = "monotone_increasing", unit = "rpm")]
+    lookup_axis: [f32; 16],
+    #[type_description(axis = "monotone_increasing")]
+    index_axis: [u8; 4],
+    curve: [f32; 16],
+}
+
+const CAL_PAGE: CalPage = CalPage {
+    lookup_axis: [
+        0.0, 100.0, 200.0, 300.0, 400.0, 500.0, 600.0, 700.0, 800.0, 900.0, 1000.0, 1100.0, 1200.0, 1300.0, 1400.0, 1500.0,
+    ],
+    index_axis: [1, 2, 4, 8],
+    curve: [0.0; 16],
+};
+
+//-----------------------------------------------------------------------------
+
+// Read the axis values from the XCP server
+async fn read_axis(xcp_client: &mut XcpClient, addr: u32) -> Vec<f32> {
+    let mut values = Vec::new();
+    for i in 0..16 {
+        let resp = xcp_client.short_upload(addr + i * 4, 0, 4).await.unwrap();
+        values.push(f32::from_le_bytes(resp[1..5].try_into().unwrap()));
+    }
+    values
+}
+
+fn is_increasing(values: &[f32]) -> bool {
+    values.windows(2).all(|w| w[0] < w[1])
+}
+
+fn error_code(e: &(dyn std::error::Error + 'static)) -> u8 {
+    e.downcast_ref::<xcp_client::xcp_client::XcpError>().unwrap().get_error_code()
+}
+
+//-----------------------------------------------------------------------------
+// Integration test monotone axis
+
+#[tokio::test]
+async fn test_monotone_axis() {
+    init_logging();
+
+    info!("Running test_monotone_axis");
+
+    let xcp = XcpBuilder::new("test_monotone_axis")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_MONOTONE_AXIS")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    assert_eq!(xcp.get_guard_rails(), GuardRailsMode::Monotonicity);
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect and upload the A2L
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    let addr = a2l_reader::a2l_find_characteristic(xcp_client.get_a2l_file().unwrap(), "CalPage.lookup_axis")
+        .unwrap()
+        .0
+        .addr;
+    let index_addr = a2l_reader::a2l_find_characteristic(xcp_client.get_a2l_file().unwrap(), "CalPage.index_axis")
+        .unwrap()
+        .0
+        .addr;
+    let curve_addr = a2l_reader::a2l_find_characteristic(xcp_client.get_a2l_file().unwrap(), "CalPage.curve").unwrap().0.addr;
+
+    // A non monotone write is rejected, the axis is unchanged
+    let err = xcp_client.short_download(addr + 3 * 4, 0, &50.0f32.to_le_bytes()).await.unwrap_err();
+    assert_eq!(error_code(err.as_ref()), CRC_WRITE_PROTECTED);
+    let err = xcp_client.short_download(addr + 15 * 4, 0, &1400.0f32.to_le_bytes()).await.unwrap_err();
+    assert_eq!(error_code(err.as_ref()), CRC_WRITE_PROTECTED);
+    assert_eq!(read_axis(&mut xcp_client, addr).await, CAL_PAGE.lookup_axis);
+
+    // A write of multiple elements is checked as a whole
+    let bytes: Vec<u8> = [250.0f32, 280.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+    xcp_client.short_download(addr + 2 * 4, 0, &bytes).await.unwrap();
+    let bytes: Vec<u8> = [290.0f32, 250.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let err = xcp_client.short_download(addr + 2 * 4, 0, &bytes).await.unwrap_err();
+    assert_eq!(error_code(err.as_ref()), CRC_WRITE_PROTECTED);
+
+    // Integer axis and a write to other calibration parameters are not affected
+    assert!(xcp_client.short_download(index_addr + 2, 0, &[1]).await.is_err());
+    xcp_client.short_download(index_addr + 2, 0, &[3]).await.unwrap();
+    xcp_client.short_download(curve_addr, 0, &10.0f32.to_le_bytes()).await.unwrap();
+
+    // The application always sees a strictly increasing axis
+    cal_page.sync();
+    assert!(is_increasing(&cal_page.lookup_axis));
+    assert_eq!(cal_page.lookup_axis[2..4], [250.0, 280.0]);
+    assert_eq!(cal_page.index_axis, [1, 2, 3, 8]);
+
+    // Clamped, to the next representable value above the previous and below the next element
+    xcp.set_guard_rails(GuardRailsMode::MonotonicityClamped);
+    xcp_client.short_download(addr + 5 * 4, 0, &0.0f32.to_le_bytes()).await.unwrap();
+    xcp_client.short_download(addr + 8 * 4, 0, &10000.0f32.to_le_bytes()).await.unwrap();
+    xcp_client.short_download(index_addr + 1, 0, &[0]).await.unwrap();
+    let axis = read_axis(&mut xcp_client, addr).await;
+    assert!(is_increasing(&axis));
+    assert_eq!(axis[5], f32::from_bits(400.0f32.to_bits() + 1));
+    assert_eq!(axis[8], f32::from_bits(900.0f32.to_bits() - 1));
+    cal_page.sync();
+    assert_eq!(cal_page.lookup_axis, axis[..]);
+    assert_eq!(cal_page.index_axis, [1, 2, 3, 8]);
+
+    // Without guard rails, any value is written
+    xcp.set_guard_rails(GuardRailsMode::Off);
+    xcp_client.short_download(addr + 3 * 4, 0, &50.0f32.to_le_bytes()).await.unwrap();
+    assert!(!is_increasing(&read_axis(&mut xcp_client, addr).await));
+
+    xcp_client.disconnect().await.unwrap();
+    xcp.stop_server();
+
+    let _ = std::fs::remove_file("test_monotone_axis.a2l");
+}
@@ -0,0 +1,145 @@
+// test_multi_odt
+// Integration test for measurement of an event which does not fit into a single ODT
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_multi_odt
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+const BLOB_NAMES: [&str; 3] = ["blob1", "blob2", "blob3"];
+const BLOB_SIZE: usize = 1000; // Two blobs exceed the max DTO size, each blob needs its own ODT
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, assembles the ODTs of an event and checks the blobs
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    odt_assembler: Option<DaqOdtAssembler>,
+    daq_header_size: u8,
+    odt_count: usize,
+    counters: Arc<Mutex<Vec<u32>>>,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        let odt_assembler = DaqOdtAssembler::new(&odt_entries);
+        self.odt_count = odt_assembler.get_odt_count(0);
+        self.odt_assembler = Some(odt_assembler);
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        self.daq_header_size = daq_header_size;
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        assert_eq!(self.daq_header_size, 4);
+        let odt = buf[0];
+        let daq = u16::from_le_bytes([buf[2], buf[3]]);
+        let data = if odt == 0 { &buf[8..] } else { &buf[4..] }; // Timestamp in ODT 0 only
+        let Some(event_data) = self.odt_assembler.as_mut().unwrap().add(daq, odt, data) else {
+            return;
+        };
+
+        // Each blob contains the event counter followed by fill bytes of the blob index
+        let mut counter = None;
+        for (i, odt_entry) in self.odt_entries[daq as usize].iter().enumerate() {
+            let blob = odt_entry.get_blob(event_data).expect("blob length exceeds max size");
+            assert_eq!(odt_entry.name, BLOB_NAMES[i]);
+            let c = u32::from_le_bytes(blob[0..4].try_into().unwrap());
+            assert_eq!(blob.len(), 4 + (c as usize % 100) + i);
+            assert!(blob[4..].iter().all(|b| *b == i as u8));
+            assert_eq!(*counter.get_or_insert(c), c);
+        }
+        self.counters.lock().push(counter.unwrap());
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, three large blobs in a 1ms cycle
+
+fn task() {
+    let mut event = daq_create_event!("task", 3072);
+    let offsets: Vec<i16> = BLOB_NAMES
+        .iter()
+        .map(|name| event.add_blob(name, Some(BLOB_SIZE), "test blob", String::new()))
+        .collect();
+    let mut counter: u32 = 0;
+    let mut buf = Vec::new();
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        counter += 1;
+        for (i, offset) in offsets.iter().enumerate() {
+            buf.clear();
+            buf.extend_from_slice(&counter.to_le_bytes());
+            buf.resize(4 + (counter as usize % 100) + i, i as u8);
+            event.capture_blob(&buf, *offset);
+        }
+        event.trigger();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test multi ODT measurement and decoding
+
+#[tokio::test]
+async fn test_multi_odt() {
+    init_logging();
+
+    info!("Running test_multi_odt");
+
+    let xcp = XcpBuilder::new("test_multi_odt")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_MULTI_ODT")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and measure the blobs
+    let counters = Arc::new(Mutex::new(Vec::new()));
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        odt_assembler: None,
+        daq_header_size: 0,
+        odt_count: 0,
+        counters: Arc::clone(&counters),
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    for name in BLOB_NAMES {
+        xcp_client.create_measurement_object(name).unwrap();
+    }
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    // Check the event spans 3 ODTs and the decoded events
+    assert_eq!(daq_decoder.lock().odt_count, 3);
+    let counters = counters.lock();
+    info!("{} events decoded", counters.len());
+    assert!(counters.len() > 100);
+    assert!(counters.windows(2).all(|w| w[1] > w[0]));
+
+    let _ = std::fs::remove_file("test_multi_odt.a2l");
+}
@@ -0,0 +1,117 @@
+// test_multi_xcp_client
+// Integration test for the multi server client MultiXcpClient in module xcp_client
+// Only one server per process, the multi client uses the loopback transport to the server of this process
+
+// cargo test --features=tokio -- --test-threads=1 --nocapture  --test test_multi_xcp_client
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    counter_max: u32,
+}
+
+const CAL_PAGE: CalPage = CalPage { counter_max: 1000 };
+
+//-----------------------------------------------------------------------------
+// Test task, 1ms cycle
+
+fn task(cal_page: CalSeg<CalPage>) {
+    let mut event = daq_create_event!("mainloop", 16);
+    let mut loop_counter: u32 = 0;
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        loop_counter += 1;
+        if loop_counter > cal_page.counter_max {
+            loop_counter = 0;
+        }
+        daq_capture!(loop_counter, event);
+        event.trigger();
+        cal_page.sync();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test multi client, connect, calibrate, measure the merged stream and disconnect
+
+#[tokio::test]
+async fn test_multi_xcp_client() {
+    init_logging();
+
+    info!("Running test_multi_xcp_client");
+
+    let (xcp, loopback) = XcpBuilder::new("test_multi_xcp_client")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_MULTI_XCP_CLIENT")
+        .start_loopback_server(0)
+        .unwrap();
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+    let t = {
+        let cal_page = cal_page.clone();
+        thread::spawn(move || task(cal_page))
+    };
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // The A2L files are written to the given directory
+    let a2l_dir = std::env::temp_dir().join("test_multi_xcp_client");
+    std::fs::create_dir_all(&a2l_dir).unwrap();
+    let a2l_path = a2l_dir.join("xcp_client_autodetect_0.a2l");
+    let _ = std::fs::remove_file(&a2l_path);
+
+    let mut multi_client = MultiXcpClient::from_clients(vec![XcpClient::new_loopback(loopback.tx, loopback.rx)]);
+    assert_eq!(multi_client.len(), 1);
+    let mut merged_stream = multi_client.merged_stream().unwrap();
+    assert!(multi_client.merged_stream().is_none());
+    multi_client.connect_all(&a2l_dir).await.unwrap();
+    assert!(a2l_path.exists());
+    assert!(!std::path::Path::new("xcp_client_autodetect_0.a2l").exists());
+    assert!(multi_client.get_client(0).get_measurements().iter().any(|m| m == "loop_counter"));
+
+    // Calibrate
+    multi_client.set_value_all("CalPage.counter_max", 500.0).await.unwrap();
+    let counter_max = multi_client.get_client(0).create_calibration_object("CalPage.counter_max").await.unwrap();
+    assert_eq!(multi_client.get_client(0).read_value_u64(counter_max).await.unwrap(), 500);
+
+    // Measure, all samples are tagged with the server index
+    multi_client.start_measurement_all("loop_counter").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    multi_client.stop_measurement_all().await.unwrap();
+    let mut sample_count = 0;
+    while let Ok((server_index, sample)) = merged_stream.try_recv() {
+        assert_eq!(server_index, 0);
+        assert_eq!(sample.lost, 0);
+        sample_count += 1;
+    }
+    info!("Samples: {}", sample_count);
+    assert!(sample_count > 50);
+
+    multi_client.disconnect_all().await.unwrap();
+
+    // An unreachable server fails connect_all
+    let mut multi_client = MultiXcpClient::new(vec!["127.0.0.1:5599".parse().unwrap()]);
+    assert!(multi_client.connect_all(&a2l_dir).await.is_err());
+
+    RUN.store(false, Ordering::Relaxed);
+    t.join().unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    xcp.stop_async_server().await;
+
+    let _ = std::fs::remove_dir_all(&a2l_dir);
+    let _ = std::fs::remove_file("test_multi_xcp_client.a2l");
+}
@@ -0,0 +1,131 @@
+// test_on_change
+// Integration test for on change measurement of rarely changing signals on a fast event
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_on_change
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+const FLAG_CYCLES: u32 = 100; // The flag changes every 100 cycles of the 1ms task
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, collects the samples of counter and flag
+
+#[derive(Default)]
+struct Samples {
+    counter: Vec<u32>,
+    flag: Vec<u32>,
+}
+
+struct DaqDecoder {
+    odt_entries: Vec<Vec<OdtEntry>>,
+    samples: Arc<Mutex<Samples>>,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.odt_entries = odt_entries;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        assert_eq!(daq_header_size, 4);
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        let daq = u16::from_le_bytes([buf[2], buf[3]]) as usize;
+        let data = &buf[8..]; // 4 byte ODT header and 4 byte timestamp
+        let mut samples = self.samples.lock();
+        for odt_entry in &self.odt_entries[daq] {
+            let o = odt_entry.offset as usize;
+            let value = u32::from_le_bytes([data[o], data[o + 1], data[o + 2], data[o + 3]]);
+            match odt_entry.name.as_str() {
+                "counter" => samples.counter.push(value),
+                "flag" => samples.flag.push(value),
+                _ => panic!("unexpected measurement {}", odt_entry.name),
+            }
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, 1ms cycle, counter is captured on each cycle, flag only on change
+
+fn task() {
+    let mut event = daq_create_event!("task", 16);
+    let mut counter: u32 = 0;
+    let mut flag: u32;
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        counter += 1;
+        flag = counter / FLAG_CYCLES;
+        daq_capture!(counter, event);
+        daq_capture_on_change!(flag, event, "rarely changing flag", "");
+        event.trigger();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Integration test on change measurement
+
+#[tokio::test]
+async fn test_on_change() {
+    init_logging();
+
+    info!("Running test_on_change");
+
+    let xcp = XcpBuilder::new("test_on_change")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_ON_CHANGE")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and measure counter and flag
+    let samples = Arc::new(Mutex::new(Samples::default()));
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder {
+        odt_entries: Vec::new(),
+        samples: Arc::clone(&samples),
+    }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    xcp_client.create_measurement_object("counter").unwrap();
+    xcp_client.create_measurement_object("flag").unwrap();
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    // The flag is transmitted once on DAQ start and then only on change
+    let samples = samples.lock();
+    info!("{} counter samples, {} flag samples", samples.counter.len(), samples.flag.len());
+    assert!(samples.counter.len() > 500);
+    assert!(!samples.flag.is_empty());
+    assert!(samples.flag.len() * 10 < samples.counter.len());
+    assert!(samples.flag.windows(2).all(|w| w[1] == w[0] + 1));
+    let first_counter = samples.counter[0];
+    let last_counter = *samples.counter.last().unwrap();
+    assert!(samples.flag[0] <= first_counter / FLAG_CYCLES + 1);
+    assert!(*samples.flag.last().unwrap() >= last_counter / FLAG_CYCLES - 1);
+
+    let _ = std::fs::remove_file("test_on_change.a2l");
+}
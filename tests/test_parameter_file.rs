@@ -0,0 +1,184 @@
+// test_parameter_file
+// Integration test for the batch calibration from JSON and CSV parameter files and the dump of calibration parameters
+// Uses the test XCP client in module xcp_client
+
+// cargo test --features=tokio -- --test-threads=1 --nocapture  --test test_parameter_file
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::param_file::*;
+use xcp_client::xcp_client::*;
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    #[type_description(min = "0", max = "500")]
+    ampl: f64,
+    counter_max: u32,
+    #[type_description(min = "-100", max = "100")]
+    offset: i16,
+    curve: [f32; 8],
+    map: [[u8; 4]; 3],
+}
+
+const CAL_PAGE: CalPage = CalPage {
+    ampl: 1.0,
+    counter_max: 100,
+    offset: 0,
+    curve: [0.0; 8],
+    map: [[0; 4]; 3],
+};
+
+// Parameter set with nested objects, arrays, index syntax and 4 invalid entries
+const PARAMETERS_JSON: &str = r#"{
+    "CalPage.ampl": 2.5,
+    "CalPage": { "counter_max": 2000, "curve": [0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.5, 4.0] },
+    "CalPage.offset": -7,
+    "CalPage.map[2][1]": 7,
+    "CalPage.map[0][3]": 3,
+    "CalPage.unknown": 1,
+    "CalPage.map[3][0]": 1,
+    "CalPage.map": 1,
+    "CalPage.comment": "text"
+}"#;
+
+// Parameter set with values out of range and an invalid value
+const PARAMETERS_CSV: &str = "name,value
+# Comment
+CalPage.ampl,600
+CalPage.offset,-101
+CalPage.curve[1],abc
+CalPage.map[1][1],300
+CalPage.map[1][2],9
+";
+
+fn failure_reason<'a>(report: &'a ParameterFileReport, name: &str) -> &'a str {
+    report.failed.iter().find(|f| f.name == name).map(|f| f.reason.as_str()).unwrap_or_default()
+}
+
+//-----------------------------------------------------------------------------
+// Integration test parameter file, dry run, apply, read back and compare, dump and apply again
+
+#[tokio::test]
+async fn test_parameter_file() {
+    init_logging();
+
+    info!("Running test_parameter_file");
+
+    let (xcp, loopback) = XcpBuilder::new("test_parameter_file")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_PARAMETER_FILE")
+        .start_loopback_server(0)
+        .unwrap();
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+
+    let mut xcp_client = XcpClient::new_loopback(loopback.tx, loopback.rx);
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+
+    // Dry run, validates without writing
+    std::fs::write("test_parameter_file.json", PARAMETERS_JSON).unwrap();
+    let report = xcp_client.apply_parameter_file("test_parameter_file.json", ParameterFileFormat::Json, true).await.unwrap();
+    assert_eq!(report.applied.len(), 13);
+    assert_eq!(report.failed.len(), 4);
+    assert_eq!(failure_reason(&report, "CalPage.unknown"), "not found");
+    assert!(failure_reason(&report, "CalPage.map[3][0]").contains("out of range"));
+    assert!(failure_reason(&report, "CalPage.map").contains("index required"));
+    assert!(failure_reason(&report, "CalPage.comment").contains("not a numeric value"));
+    cal_page.sync();
+    assert_eq!(cal_page.ampl, 1.0);
+    assert_eq!(cal_page.map[2][1], 0);
+
+    // Apply and read back
+    let report = xcp_client.apply_parameter_file("test_parameter_file.json", ParameterFileFormat::Json, false).await.unwrap();
+    assert_eq!(report.applied.len(), 13);
+    assert_eq!(report.failed.len(), 4);
+    cal_page.sync();
+    assert_eq!(cal_page.ampl, 2.5);
+    assert_eq!(cal_page.counter_max, 2000);
+    assert_eq!(cal_page.offset, -7);
+    assert_eq!(cal_page.curve, [0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.5, 4.0]);
+    assert_eq!(cal_page.map, [[0, 0, 0, 3], [0, 0, 0, 0], [0, 7, 0, 0]]);
+    let counter_max = xcp_client.create_calibration_object("CalPage.counter_max").await.unwrap();
+    assert_eq!(xcp_client.get_value_u64(counter_max), 2000);
+
+    // Values out of range are rejected, the valid entries of the file are applied
+    std::fs::write("test_parameter_file.csv", PARAMETERS_CSV).unwrap();
+    let report = xcp_client.apply_parameter_file("test_parameter_file.csv", ParameterFileFormat::Csv, false).await.unwrap();
+    assert_eq!(report.applied, vec![("CalPage.map[1][2]".to_string(), 9.0)]);
+    assert_eq!(report.failed.len(), 4);
+    assert!(failure_reason(&report, "CalPage.ampl").contains("out of range"));
+    assert!(failure_reason(&report, "CalPage.offset").contains("out of range"));
+    assert!(failure_reason(&report, "CalPage.curve[1]").contains("invalid value"));
+    assert!(failure_reason(&report, "CalPage.map[1][1]").contains("out of range"));
+    cal_page.sync();
+    assert_eq!(cal_page.ampl, 2.5);
+    assert_eq!(cal_page.map[1], [0, 0, 9, 0]);
+
+    // Dump in both formats and compare with the applied values
+    for (path, format) in [
+        ("test_parameter_file_dump.json", ParameterFileFormat::Json),
+        ("test_parameter_file_dump.csv", ParameterFileFormat::Csv),
+    ] {
+        assert_eq!(xcp_client.dump_parameters("CalPage.", path, format).await.unwrap(), 23);
+        let mut report = ParameterFileReport::default();
+        let entries = read_parameter_file(path, format, &mut report).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(entries.len(), 23);
+        let value = |name: &str| entries.iter().find(|(n, _)| n == name).map(|(_, v)| *v);
+        assert_eq!(value("CalPage.ampl"), Some(2.5));
+        assert_eq!(value("CalPage.offset"), Some(-7.0));
+        assert_eq!(value("CalPage.curve[7]"), Some(4.0));
+        assert_eq!(value("CalPage.map[2][1]"), Some(7.0));
+        assert_eq!(value("CalPage.map[1][2]"), Some(9.0));
+        assert_eq!(value("CalPage.map[0][0]"), Some(0.0));
+    }
+
+    // Clear all values, then apply the dump again
+    let mut report = ParameterFileReport::default();
+    let entries = read_parameter_file("test_parameter_file_dump.csv", ParameterFileFormat::Csv, &mut report).unwrap();
+    let zeros: Vec<(String, f64)> = entries.into_iter().map(|(name, _)| (name, 0.0)).collect();
+    write_parameter_file("test_parameter_file_zero.json", ParameterFileFormat::Json, &zeros).unwrap();
+    let report = xcp_client
+        .apply_parameter_file("test_parameter_file_zero.json", ParameterFileFormat::Json, false)
+        .await
+        .unwrap();
+    assert!(report.is_ok());
+    cal_page.sync();
+    assert_eq!(cal_page.ampl, 0.0);
+    assert_eq!(cal_page.map, [[0; 4]; 3]);
+    let report = xcp_client
+        .apply_parameter_file("test_parameter_file_dump.csv", ParameterFileFormat::Csv, false)
+        .await
+        .unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.applied.len(), 23);
+    cal_page.sync();
+    assert_eq!(cal_page.curve[7], 4.0);
+    assert_eq!(cal_page.map, [[0, 0, 0, 3], [0, 0, 9, 0], [0, 7, 0, 0]]);
+
+    xcp_client.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    xcp.stop_async_server().await;
+
+    for path in [
+        "test_parameter_file.json",
+        "test_parameter_file.csv",
+        "test_parameter_file_dump.json",
+        "test_parameter_file_dump.csv",
+        "test_parameter_file_zero.json",
+        "test_parameter_file.a2l",
+    ] {
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,131 @@
+// test_reconnect
+// Integration test for XCP client session resume after a XCP server restart
+// Uses the test XCP client in module xcp_client
+
+// cargo test --features=a2l_reader --features=serde -- --test-threads=1 --nocapture  --test test_reconnect
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, counts DAQ events since measurement start
+
+struct DaqDecoder {
+    event_count: u32,
+    start_count: u32,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, _odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.event_count = 0;
+        self.start_count += 1;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, _daq_header_size: u8) {}
+
+    fn decode(&mut self, _lost: u32, _buf: &[u8]) {
+        self.event_count += 1;
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, measurement of a counter in a 1ms cycle
+
+fn task() {
+    let mut counter: u32 = 0;
+    let event = daq_create_event!("task");
+    daq_register!(counter, event);
+
+    while RUN.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+        counter = counter.wrapping_add(1);
+        event.trigger();
+    }
+}
+
+fn start_server() -> &'static Xcp {
+    XcpBuilder::new("test_reconnect")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_RECONNECT")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap()
+}
+
+//-----------------------------------------------------------------------------
+// Integration test session resume with automatic DAQ restart
+
+#[tokio::test]
+async fn test_reconnect() {
+    init_logging();
+
+    info!("Running test_reconnect");
+
+    let xcp = start_server();
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect, upload the A2L and start measurement
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder { event_count: 0, start_count: 0 }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    xcp_client.create_measurement_object("counter").unwrap();
+    xcp_client.set_reconnect(ReconnectPolicy {
+        retries: 10,
+        backoff: Duration::from_millis(200),
+    });
+    xcp_client.set_daq_timeout(Duration::from_millis(500));
+    let states = Arc::new(Mutex::new(Vec::new()));
+    xcp_client.set_state_callback({
+        let states = Arc::clone(&states);
+        move |state| states.lock().push(state)
+    });
+    xcp_client.start_measurement().await.unwrap();
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert!(!xcp_client.check_session().await.unwrap());
+    assert!(daq_decoder.lock().event_count > 0);
+
+    // Restart the XCP server while measuring
+    info!("Restart XCP server");
+    xcp.stop_server();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let xcp = start_server();
+
+    // Data flows again after the session has been resumed
+    let mut resumed = false;
+    for _ in 0..100 {
+        if xcp_client.check_session().await.unwrap() {
+            resumed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(resumed);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(daq_decoder.lock().start_count, 2);
+    assert!(daq_decoder.lock().event_count > 0);
+    assert_eq!(states.lock().first(), Some(&XcpSessionState::Disconnected));
+    assert_eq!(states.lock().last(), Some(&XcpSessionState::Resumed));
+
+    xcp_client.stop_measurement().await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    let _ = std::fs::remove_file("test_reconnect.a2l");
+}
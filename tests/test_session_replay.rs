@@ -0,0 +1,83 @@
+// test_session_replay
+// Integration test for the session transcript recording and replay in module xcp_client
+// Client and server communicate with the in-process loopback transport
+
+// cargo test --features=tokio -- --test-threads=1 --nocapture  --test test_session_replay
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+const SESSION_FILE: &str = "test_session_replay.xcps";
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    counter_max: u32,
+}
+
+const CAL_PAGE: CalPage = CalPage { counter_max: 1000 };
+
+//-----------------------------------------------------------------------------
+// Integration test session replay, record a session, replay it and detect a modified response
+
+#[tokio::test]
+async fn test_session_replay() {
+    init_logging();
+
+    info!("Running test_session_replay");
+
+    let (xcp, loopback) = XcpBuilder::new("test_session_replay")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_SESSION_REPLAY")
+        .start_loopback_server(0)
+        .unwrap();
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+
+    // Record a session, which leaves the calibration parameter unchanged
+    let mut xcp_client = XcpClient::new_loopback(loopback.tx, loopback.rx);
+    xcp_client.set_command_timeout(Duration::from_millis(500));
+    xcp_client.record_session(Path::new(SESSION_FILE)).unwrap();
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+    let counter_max = xcp_client.create_calibration_object("CalPage.counter_max").await.unwrap();
+    xcp_client.get_daq_clock().await.unwrap();
+    xcp_client.set_value_u64(counter_max, 2000).await.unwrap();
+    assert_eq!(xcp_client.read_value_u64(counter_max).await.unwrap(), 2000);
+    xcp_client.set_value_u64(counter_max, 1000).await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Replay, all responses match, the server clock of GET_DAQ_CLOCK differs
+    xcp_client.replay_session(Path::new(SESSION_FILE)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Modify the calibration parameter, the replay detects the different response to the first read
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.set_value_u64(counter_max, 3000).await.unwrap();
+    xcp_client.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let e = xcp_client.replay_session(Path::new(SESSION_FILE)).await.unwrap_err();
+    info!("{}", e);
+    assert!(e.to_string().contains("(0xF4)")); // SHORT_UPLOAD
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Not a session transcript file
+    assert!(xcp_client.replay_session(Path::new("Cargo.toml")).await.is_err());
+
+    xcp.stop_async_server().await;
+
+    let _ = std::fs::remove_file(SESSION_FILE);
+    let _ = std::fs::remove_file("test_session_replay.a2l");
+}
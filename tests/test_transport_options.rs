@@ -0,0 +1,178 @@
+// test_transport_options
+// Integration test for the transport layer options (MTU, socket buffer sizes, queue size) and the transport layer statistics
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_transport_options
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::time::{Duration, Instant};
+use xcp_client::xcp_client::*;
+
+const JUMBO_MTU: u16 = 9000;
+const JUMBO_MAX_DTO: u16 = 8964; // MTU - IP header - UDP header - XCP transport layer header
+const SOCKET_BUFFER_SIZE: u32 = 4 * 1024 * 1024;
+
+const CAPTURE_BUFFER_SIZE: usize = 8192;
+const ARRAY_DIM: usize = 31; // Max ODT entry size is 255 bytes
+const ARRAY_COUNT: usize = 32;
+
+static RUN: AtomicBool = AtomicBool::new(true);
+
+//-----------------------------------------------------------------------------
+// DAQ decoder, counts the DTOs received
+
+struct DaqDecoder {
+    dto_count: u64,
+}
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, _odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {
+        self.dto_count = 0;
+    }
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, daq_header_size: u8) {
+        assert_eq!(daq_header_size, 4);
+    }
+
+    fn decode(&mut self, _lost: u32, _buf: &[u8]) {
+        self.dto_count += 1;
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Test task, u64 arrays fill an 8K capture buffer, triggered as fast as possible to saturate the transport layer
+
+fn task() {
+    let mut event = daq_create_event!("task", CAPTURE_BUFFER_SIZE);
+    let offsets: Vec<i16> = (0..ARRAY_COUNT)
+        .map(|i| {
+            let name: &'static str = Box::leak(format!("array{}", i).into_boxed_str());
+            event.add_capture(name, ARRAY_DIM * 8, RegistryDataType::AUint64, ARRAY_DIM as u16, 1, 1.0, 0.0, "", "test array", None)
+        })
+        .collect();
+    let mut counter: u64 = 0;
+
+    while RUN.load(Ordering::Relaxed) {
+        counter += 1;
+        let bytes: Vec<u8> = (0..ARRAY_DIM).flat_map(|j| (counter + j as u64).to_le_bytes()).collect();
+        for offset in &offsets {
+            event.capture(&bytes, *offset);
+        }
+        event.trigger();
+        thread::yield_now();
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Connect, measure all arrays for 1s and return the throughput in bytes/s
+// Check the server transport layer statistics against the bytes received by the client
+
+async fn measure(xcp: &Xcp, name: &str, max_dto: u16) -> f64 {
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder { dto_count: 0 }));
+    xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder).await.unwrap();
+    assert_eq!(xcp_client.get_max_dto_size(), max_dto);
+    xcp_client.upload_a2l(false).await.unwrap();
+    for i in 0..ARRAY_COUNT {
+        xcp_client.create_measurement_object(&format!("array{}", i)).unwrap();
+    }
+
+    let (_, rx_bytes_start) = xcp_client.get_rx_stats();
+    let start = Instant::now();
+    xcp_client.start_measurement().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    xcp_client.stop_measurement().await.unwrap();
+    let elapsed = start.elapsed().as_secs_f64();
+    let (_, rx_bytes_stop) = xcp_client.get_rx_stats();
+    xcp_client.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let throughput = (rx_bytes_stop - rx_bytes_start) as f64 / elapsed;
+    let (rx_packets, rx_bytes) = xcp_client.get_rx_stats();
+    let stats = xcp.get_transport_stats();
+    info!(
+        "{}: max_dto={} {:.1} MByte/s, {} DTOs, server {:?}, client rx_packets={} rx_bytes={}",
+        name,
+        max_dto,
+        throughput / 1E6,
+        daq_decoder.lock().dto_count,
+        stats,
+        rx_packets,
+        rx_bytes
+    );
+
+    // Everything transmitted by the server was received by the client, except UDP packets dropped by the client socket
+    // With a saturated transport layer and jumbo frames, the default client socket receive buffer overflows and about 10% are lost
+    assert!(stats.rx_packets > 0);
+    assert!(stats.tx_packets >= rx_packets);
+    assert!(stats.tx_bytes >= rx_bytes);
+    assert!(rx_bytes as f64 >= stats.tx_bytes as f64 * 0.75);
+    assert!(stats.tx_queue_overruns > 0); // The test task saturates the transport layer
+
+    throughput
+}
+
+//-----------------------------------------------------------------------------
+// Integration test transport layer options
+
+#[tokio::test]
+async fn test_transport_options() {
+    init_logging();
+
+    info!("Running test_transport_options");
+
+    // MTU beyond the jumbo frame maximum is rejected
+    let options = XcpTransportOptions { mtu: 9200, ..Default::default() };
+    assert!(XcpBuilder::new("test_transport_options")
+        .start_server_with_options(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555, 0, options)
+        .is_err());
+
+    // Jumbo frames with large socket buffers
+    let options = XcpTransportOptions {
+        mtu: JUMBO_MTU,
+        send_buffer_size: SOCKET_BUFFER_SIZE,
+        receive_buffer_size: SOCKET_BUFFER_SIZE,
+    };
+    let xcp = XcpBuilder::new("test_transport_options")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_TRANSPORT_OPTIONS")
+        .start_server_with_options(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555, 64, options)
+        .unwrap();
+    assert_eq!(xcp.get_max_dto_size(), JUMBO_MAX_DTO as usize);
+    assert_eq!(xcp.get_transmit_queue_size(), 64 * (JUMBO_MAX_DTO as usize + 4));
+    let t1 = thread::spawn(task);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let jumbo_throughput = measure(xcp, "jumbo", JUMBO_MAX_DTO).await;
+
+    // The A2L protocol layer parameters contain the max DTO size derived from the MTU
+    let a2l = std::fs::read_to_string("test_transport_options.a2l").unwrap();
+    assert!(a2l.contains(&format!("0x104 1000 2000 0 0 0 0 0 248 {} BYTE_ORDER_MSB_LAST", JUMBO_MAX_DTO)));
+    xcp.stop_server();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Default MTU of 1500
+    let xcp = XcpBuilder::new("test_transport_options")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_TRANSPORT_OPTIONS")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let default_throughput = measure(xcp, "default", 1464).await;
+
+    RUN.store(false, Ordering::Relaxed);
+    t1.join().unwrap();
+    xcp.stop_server();
+
+    // Larger DTOs need fewer packets for the same data
+    assert!(jumbo_throughput > default_throughput);
+
+    let _ = std::fs::remove_file("test_transport_options.a2l");
+}
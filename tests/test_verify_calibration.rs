@@ -0,0 +1,115 @@
+// test_verify_calibration
+// Integration test for the verification of calibration values against golden values in JSON format
+// and for the client side check of the calibration limits
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_verify_calibration
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    #[type_description(min = "0", max = "500")]
+    ampl: f64,
+    period: f64,
+    counter_max: u32,
+}
+
+const CAL_PAGE: CalPage = CalPage {
+    ampl: 1.0,
+    period: 1.0,
+    counter_max: 100,
+};
+
+// Golden values, all differ from the defaults
+const GOLDEN_JSON: &str = r#"{ "ampl": 2.0, "period": 5.0, "counter_max": 500 }"#;
+
+//-----------------------------------------------------------------------------
+// Integration test calibration verification, the write of ampl is skipped
+
+#[tokio::test]
+async fn test_verify_calibration() {
+    init_logging();
+
+    info!("Running test_verify_calibration");
+
+    let xcp = XcpBuilder::new("test_verify_calibration")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_VERIFY_CALIBRATION")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect and upload the A2L
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+
+    // Program the golden values, deliberately skip ampl
+    let period = xcp_client.create_calibration_object("CalPage.period").await.unwrap();
+    xcp_client.set_value_f64(period, 5.0).await.unwrap();
+    let counter_max = xcp_client.create_calibration_object("CalPage.counter_max").await.unwrap();
+    xcp_client.set_value_u64(counter_max, 500).await.unwrap();
+
+    // Verify, only ampl mismatches
+    let mismatches = xcp_client.verify_calibration(GOLDEN_JSON, "CalPage").await.unwrap();
+    assert_eq!(
+        mismatches,
+        vec![CalibrationMismatch {
+            name: "CalPage.ampl".to_string(),
+            expected: 2.0,
+            actual: 1.0,
+            tolerance: CALIBRATION_RELATIVE_TOLERANCE,
+        }]
+    );
+
+    // Verify again after the missing write
+    let ampl = xcp_client.create_calibration_object("CalPage.ampl").await.unwrap();
+    xcp_client.set_value_f64(ampl, 2.0).await.unwrap();
+    assert!(xcp_client.verify_calibration(GOLDEN_JSON, "CalPage").await.unwrap().is_empty());
+
+    // A value out of the limits is not sent
+    let err = xcp_client.set_value_f64(ampl, 10000.0).await.unwrap_err();
+    let err = err.downcast_ref::<xcp_client::xcp_client::XcpError>().unwrap();
+    assert_eq!(err.get_error_code(), ERROR_LIMIT);
+    assert_eq!(
+        err.get_value_out_of_range(),
+        Some(&ValueOutOfRange {
+            name: "CalPage.ampl".to_string(),
+            min: 0.0,
+            max: 500.0,
+            value: 10000.0,
+        })
+    );
+    xcp_client.read_value_u64(ampl).await.unwrap();
+    assert_eq!(xcp_client.get_value_f64(ampl), 2.0);
+    let counter_max_err = xcp_client.set_value_i64(counter_max, -1).await.unwrap_err();
+    assert_eq!(counter_max_err.downcast_ref::<xcp_client::xcp_client::XcpError>().unwrap().get_error_code(), ERROR_LIMIT);
+
+    // Clamped to the limits
+    xcp_client.set_value_clamped_f64(ampl, 10000.0).await.unwrap();
+    xcp_client.read_value_u64(ampl).await.unwrap();
+    assert_eq!(xcp_client.get_value_f64(ampl), 500.0);
+    xcp_client.set_value_clamped_f64(counter_max, -1.0).await.unwrap();
+    xcp_client.read_value_u64(counter_max).await.unwrap();
+    assert_eq!(xcp_client.get_value_u64(counter_max), 0);
+
+    xcp_client.disconnect().await.unwrap();
+    xcp.stop_server();
+
+    let _ = std::fs::remove_file("test_verify_calibration.a2l");
+}
@@ -0,0 +1,69 @@
+// test_wide_integers
+// Integration test for 128 bit integer calibration parameters, which are split into two 64 bit words
+// Uses the test XCP client in module xcp_client
+
+// cargo test -- --test-threads=1 --nocapture  --test test_wide_integers
+use xcp::*;
+
+mod xcp_test_fixture;
+use xcp_test_fixture::*;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Duration;
+use xcp_client::xcp_client::*;
+
+//-----------------------------------------------------------------------------
+// Calibration parameters
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, XcpTypeDescription)]
+struct CalPage {
+    nonce: u128,
+    counter: i128,
+}
+
+const CAL_PAGE: CalPage = CalPage {
+    nonce: 0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210,
+    counter: -2,
+};
+
+//-----------------------------------------------------------------------------
+// Integration test 128 bit integers, upload both words and reassemble the value
+
+#[tokio::test]
+async fn test_wide_integers() {
+    init_logging();
+
+    info!("Running test_wide_integers");
+
+    let xcp = XcpBuilder::new("test_wide_integers")
+        .set_log_level(OPTION_XCP_LOG_LEVEL)
+        .set_epk("EPK_TEST_WIDE_INTEGERS")
+        .start_server(XcpTransportLayer::Udp, [127, 0, 0, 1], 5555)
+        .unwrap();
+    let cal_page = xcp.create_calseg("CalPage", &CAL_PAGE);
+    cal_page.register_fields();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connect and upload the A2L
+    let mut xcp_client = XcpClient::new("127.0.0.1:5555".parse().unwrap(), "0.0.0.0:0".parse().unwrap());
+    xcp_client.connect(Arc::new(Mutex::new(DaqDecoder)), ServTextDecoder).await.unwrap();
+    xcp_client.upload_a2l(false).await.unwrap();
+
+    // There is no 128 bit calibration parameter, only the two words
+    assert!(xcp_client.create_calibration_object("CalPage.nonce").await.is_err());
+    let lo = xcp_client.create_calibration_object("CalPage.nonce.lo").await.unwrap();
+    let hi = xcp_client.create_calibration_object("CalPage.nonce.hi").await.unwrap();
+    assert_eq!(decode_u128(xcp_client.get_value_u64(lo), xcp_client.get_value_u64(hi)), CAL_PAGE.nonce);
+
+    let lo = xcp_client.create_calibration_object("CalPage.counter.lo").await.unwrap();
+    let hi = xcp_client.create_calibration_object("CalPage.counter.hi").await.unwrap();
+    assert_eq!(decode_i128(xcp_client.get_value_u64(lo), xcp_client.get_value_i64(hi)), CAL_PAGE.counter);
+
+    xcp_client.disconnect().await.unwrap();
+    xcp.stop_server();
+
+    let _ = std::fs::remove_file("test_wide_integers.a2l");
+}
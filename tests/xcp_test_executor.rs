@@ -277,6 +277,14 @@ pub async fn xcp_test_executor(_xcp: &Xcp, test_mode_cal: TestModeCal, test_mode
         }
     }
 
+    //-------------------------------------------------------------------------------------------------------------------------------------
+    // Get status, ECU is on RAM page and DAQ is not running after connect
+    info!("XCP GET_STATUS");
+    let status = xcp_client.get_status().await.unwrap();
+    info!("  {}", status);
+    assert_eq!(status.cal_page, 0);
+    assert!(!status.is_daq_running());
+
     //-------------------------------------------------------------------------------------------------------------------------------------
     // Get id
     info!("XCP GET_ID XCP_IDT_ASAM_NAME");
@@ -315,8 +323,10 @@ pub async fn xcp_test_executor(_xcp: &Xcp, test_mode_cal: TestModeCal, test_mode
         tokio::time::sleep(Duration::from_micros(10000)).await;
 
         //-------------------------------------------------------------------------------------------------------------------------------------
-        // Check EPK upload
-        let res = xcp_client.short_upload(0x80000000, 0, 8).await;
+        // Check EPK upload, the EPK is a read only calibration parameter in the EPK memory segment
+        let (epk_addr, _, _) = a2l_reader::a2l_find_characteristic(xcp_client.get_a2l_file().unwrap(), "EPK").unwrap();
+        assert_eq!(epk_addr.addr, 0x80000000);
+        let res = xcp_client.short_upload(epk_addr.addr, epk_addr.ext, 8).await;
         let resp: Vec<u8> = match res {
             Err(e) => {
                 panic!("Could not upload EPK, Error: {}", e);
@@ -417,6 +427,11 @@ pub async fn xcp_test_executor(_xcp: &Xcp, test_mode_cal: TestModeCal, test_mode
             };
             xcp_client.start_measurement().await.unwrap();
 
+            // Check DAQ running state
+            let status = xcp_client.get_status().await.unwrap();
+            assert!(status.is_daq_running());
+            assert_eq!(status.session_status & SS_DAQ_RUNNING, SS_DAQ_RUNNING);
+
             // Test for DURATION_DAQ_TEST_MS time, do a calibration of counter_max to 255 in the middle of the time
             let starttime = Instant::now();
             tokio::time::sleep(Duration::from_millis(DAQ_TEST_DURATION_MS / 2)).await;
@@ -508,6 +523,18 @@ pub async fn xcp_test_executor(_xcp: &Xcp, test_mode_cal: TestModeCal, test_mode
             xcp_client.set_value_i64(test_i32, -1).await.unwrap();
             let v = xcp_client.get_value_i64(test_i32);
             debug!("test_i32 = {}", v);
+            xcp_client.set_value(test_i32, Value::I64(-2)).await.unwrap();
+            assert_eq!(xcp_client.get_value(test_i32), Value::I64(-2));
+
+            // Test typed float
+            debug!("Create calibration object CalPage1.TestInts.test_f32");
+            let test_f32 = xcp_client
+                .create_calibration_object("CalPage1.TestInts.test_f32")
+                .await
+                .expect("could not create calibration object CalPage1.TestInts.test_f32");
+            assert_eq!(xcp_client.get_value(test_f32), Value::F64(0.123456E-10f32 as f64));
+            xcp_client.set_value(test_f32, Value::F64(1.5)).await.unwrap();
+            assert_eq!(xcp_client.get_value(test_f32), Value::F64(1.5));
 
             // Test static
             debug!("Create calibration object static_vars.test_u32");
@@ -0,0 +1,39 @@
+//-----------------------------------------------------------------------------
+// Module xcp_test_fixture
+// Common logging setup and decoders of the integration tests with the test XCP client in module xcp_client
+
+#![allow(dead_code)]
+
+use xcp_client::xcp_client::*;
+
+// Logging
+pub const OPTION_LOG_LEVEL: log::LevelFilter = log::LevelFilter::Info;
+pub const OPTION_XCP_LOG_LEVEL: u8 = 3;
+
+/// Initialize the logger of a test, log to stdout without timestamps
+pub fn init_logging() {
+    env_logger::Builder::new()
+        .target(env_logger::Target::Stdout)
+        .filter_level(OPTION_LOG_LEVEL)
+        .format_timestamp(None)
+        .format_module_path(false)
+        .format_target(false)
+        .try_init()
+        .ok();
+}
+
+//-----------------------------------------------------------------------------
+// DAQ and text decoder, not used
+// Tests which check DAQ data define their own DaqDecoder, which shadows this one
+
+pub struct DaqDecoder;
+
+impl XcpDaqDecoder for DaqDecoder {
+    fn start(&mut self, _odt_entries: Vec<Vec<OdtEntry>>, _timestamp: u64) {}
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, _daq_header_size: u8) {}
+    fn decode(&mut self, _lost: u32, _buf: &[u8]) {}
+}
+
+pub struct ServTextDecoder;
+
+impl XcpTextDecoder for ServTextDecoder {}
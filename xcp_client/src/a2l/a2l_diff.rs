@@ -0,0 +1,139 @@
+//-----------------------------------------------------------------------------
+// Module a2l_diff
+// Compare two A2L files and report structural changes of measurements, characteristics and blobs
+// Uses a2lfile crate to load the A2L files
+
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::collections::BTreeMap;
+
+use a2lfile::{A2lError, A2lFile};
+
+use super::a2l_reader::a2l_load;
+
+/// Structural differences between two A2L files
+/// Objects are identified by name, all lists are sorted by name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct A2lDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub type_changed: Vec<(String, String, String)>, // (name, old_type, new_type)
+    pub address_changed: Vec<(String, u32, u32)>,    // (name, old_addr, new_addr)
+}
+
+impl A2lDiff {
+    /// True, if there are no differences at all
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.type_changed.is_empty() && self.address_changed.is_empty()
+    }
+
+    /// True, if no object was removed or changed its type
+    /// Added objects and address changes are compatible, they are resolved by the A2L file of the new server
+    pub fn is_compatible(&self) -> bool {
+        self.removed.is_empty() && self.type_changed.is_empty()
+    }
+
+    /// JSON representation for tool integration
+    pub fn to_json(&self) -> String {
+        let added: Vec<String> = self.added.iter().map(|n| json_string(n)).collect();
+        let removed: Vec<String> = self.removed.iter().map(|n| json_string(n)).collect();
+        let type_changed: Vec<String> = self
+            .type_changed
+            .iter()
+            .map(|(n, o, t)| format!("{{\"name\":{},\"old_type\":{},\"new_type\":{}}}", json_string(n), json_string(o), json_string(t)))
+            .collect();
+        let address_changed: Vec<String> = self
+            .address_changed
+            .iter()
+            .map(|(n, o, a)| format!("{{\"name\":{},\"old_address\":{},\"new_address\":{}}}", json_string(n), o, a))
+            .collect();
+        format!(
+            "{{\"added\":[{}],\"removed\":[{}],\"type_changed\":[{}],\"address_changed\":[{}]}}",
+            added.join(","),
+            removed.join(","),
+            type_changed.join(","),
+            address_changed.join(",")
+        )
+    }
+}
+
+impl std::fmt::Display for A2lDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for name in &self.added {
+            writeln!(f, "+ {}", name)?;
+        }
+        for name in &self.removed {
+            writeln!(f, "- {}", name)?;
+        }
+        for (name, old_type, new_type) in &self.type_changed {
+            writeln!(f, "~ {}: type {} -> {}", name, old_type, new_type)?;
+        }
+        for (name, old_addr, new_addr) in &self.address_changed {
+            writeln!(f, "~ {}: address 0x{:08X} -> 0x{:08X}", name, old_addr, new_addr)?;
+        }
+        Ok(())
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut r = String::with_capacity(s.len() + 2);
+    r.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            c if c.is_control() => r.push_str(&format!("\\u{:04x}", c as u32)),
+            c => r.push(c),
+        }
+    }
+    r.push('"');
+    r
+}
+
+// Collect all measurements, characteristics and blobs with type and address, indexed by name
+fn a2l_get_instances(a2l_file: &A2lFile) -> BTreeMap<String, (String, u32)> {
+    let module = &a2l_file.project.module[0];
+    let mut map = BTreeMap::new();
+    for m in module.measurement.iter() {
+        let addr = m.ecu_address.as_ref().map(|a| a.address).unwrap_or_default();
+        map.insert(m.name.clone(), (m.datatype.to_string(), addr));
+    }
+    for c in module.characteristic.iter() {
+        map.insert(c.name.clone(), (format!("{:?} {}", c.characteristic_type, c.deposit), c.address));
+    }
+    for b in module.blob.iter() {
+        map.insert(b.name.clone(), (format!("BLOB {}", b.size), b.start_address));
+    }
+    map
+}
+
+/// Compare the A2L files old and new
+pub fn diff_a2l(old: &str, new: &str) -> Result<A2lDiff, A2lError> {
+    let old_instances = a2l_get_instances(&a2l_load(old)?);
+    let new_instances = a2l_get_instances(&a2l_load(new)?);
+
+    let mut diff = A2lDiff::default();
+    for (name, (old_type, old_addr)) in old_instances.iter() {
+        match new_instances.get(name) {
+            None => diff.removed.push(name.clone()),
+            Some((new_type, new_addr)) => {
+                if old_type != new_type {
+                    diff.type_changed.push((name.clone(), old_type.clone(), new_type.clone()));
+                }
+                if old_addr != new_addr {
+                    diff.address_changed.push((name.clone(), *old_addr, *new_addr));
+                }
+            }
+        }
+    }
+    for name in new_instances.keys() {
+        if !old_instances.contains_key(name) {
+            diff.added.push(name.clone());
+        }
+    }
+    debug!("diff_a2l {} {}: {:?}", old, new, diff);
+    Ok(diff)
+}
@@ -10,6 +10,7 @@ use log::{debug, error, info, trace, warn};
 
 use a2lfile::*;
 use a2lfile::{A2lError, A2lFile};
+use std::collections::HashMap;
 
 use super::ifdata;
 
@@ -25,12 +26,35 @@ pub enum A2lTypeEncoding {
     Signed = -1,
     Unsigned = 1,
     Float = 0,
+    Blob = 2, // Variable length blob with u16 length prefix, size is the max size including the prefix
 }
 
+/// Size of the length prefix of variable length blobs
+pub const A2L_BLOB_LENGTH_PREFIX_SIZE: usize = 2;
+
+// Annotation label of the plausible limits of a measurement, see xcp::reg a2l_writer
+const PLAUSIBLE_LIMITS_ANNOTATION_LABEL: &str = "PLAUSIBLE_LIMITS";
+
+// Record layout name suffix of column major maps, see xcp::reg a2l_writer
+const COLUMN_DIR_RECORD_LAYOUT_SUFFIX: &str = "_COL";
+
+// IF_DATA blocks interpreted by the A2L reader, all others are vendor specific extensions
+const A2L_KNOWN_IF_DATA: [&str; 2] = ["XCP", "XCPplus"];
+
 #[derive(Debug, Clone, Copy)]
 pub struct A2lType {
-    pub size: u8,
-    pub encoding: A2lTypeEncoding,
+    pub size: u16,  // Total size in bytes, including all elements of an array
+    pub dim: u16,   // Number of elements, 1 for scalars and blobs
+    pub x_dim: u16, // Dimensions of an array or matrix (MATRIX_DIM or NUMBER), x_dim * y_dim = dim
+    pub y_dim: u16,
+    pub encoding: A2lTypeEncoding, // Type of a single element
+}
+
+impl A2lType {
+    /// Size of a single element in bytes
+    pub fn get_element_size(&self) -> u16 {
+        self.size / self.dim
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -39,6 +63,31 @@ pub struct A2lLimits {
     pub upper: f64,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct A2lDisplay {
+    pub format: Option<String>,
+    pub step: Option<f64>,
+    pub extended_limits: Option<A2lLimits>,
+    pub display: Option<String>, // Display name (DISPLAY_IDENTIFIER)
+}
+
+/// Linear conversion of a measurement, phys = factor * raw + offset
+#[derive(Debug, Clone)]
+pub struct A2lConversion {
+    pub factor: f64,
+    pub offset: f64,
+    pub unit: String,
+}
+
+/// Single element of a calibration parameter, a scalar or one value of a CURVE, MAP or VAL_BLK
+#[derive(Debug, Clone)]
+pub struct A2lCharacteristicElement {
+    pub addr: A2lAddr,
+    pub a2l_type: A2lType,
+    pub limits: A2lLimits,
+    pub conversion: A2lConversion,
+}
+
 pub fn a2l_load<P: AsRef<std::path::Path>>(filename: P) -> Result<a2lfile::A2lFile, a2lfile::A2lError> {
     let filename = filename.as_ref();
     trace!("Load A2L file {}", filename.display());
@@ -48,13 +97,15 @@ pub fn a2l_load<P: AsRef<std::path::Path>>(filename: P) -> Result<a2lfile::A2lFi
         warn!("A2l Loader: {}", log_msg);
     }
     match res {
-        Ok(a2l_file) => {
+        Ok(mut a2l_file) => {
             // Perform a consistency check
             let mut logmsgs = Vec::<String>::new();
             a2l_file.check(&mut logmsgs);
             for log_msg in logmsgs {
                 warn!("A2l Checker: {}", log_msg);
             }
+            // Make the instances of typedefs accessible by name
+            a2l_expand_instances(&mut a2l_file);
             Ok(a2l_file)
         }
 
@@ -65,6 +116,191 @@ pub fn a2l_load<P: AsRef<std::path::Path>>(filename: P) -> Result<a2lfile::A2lFi
     }
 }
 
+//-----------------------------------------------------------------------------
+// Vendor specific extensions
+
+/// Vendor specific extensions of an A2L file, which are not interpreted by the A2L reader
+#[derive(Debug, Clone, Default)]
+pub struct A2lExtensions {
+    /// Raw text of the IF_DATA blocks other than IF_DATA XCP, keyed by vendor name (the IF_DATA tag)
+    /// Multiple blocks of the same vendor, e.g. in the MODULE and in objects, are separated by a line break
+    pub vendor_if_data: HashMap<String, String>,
+}
+
+// Token spans of an A2L text, a string is a single token, comments are skipped
+fn a2l_tokens(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_whitespace() {
+            i += 1;
+        } else if bytes[i..].starts_with(b"/*") {
+            i = bytes[i + 2..].windows(2).position(|w| w == b"*/").map_or(bytes.len(), |n| i + 2 + n + 2);
+        } else if bytes[i..].starts_with(b"//") {
+            i = bytes[i..].iter().position(|&c| c == b'\n').map_or(bytes.len(), |n| i + n);
+        } else if bytes[i] == b'"' {
+            let start = i;
+            i += 1;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'\\' => i += 2,
+                    b'"' if bytes.get(i + 1) == Some(&b'"') => i += 2,
+                    b'"' => {
+                        i += 1;
+                        break;
+                    }
+                    _ => i += 1,
+                }
+            }
+            i = i.min(bytes.len());
+            tokens.push((start, i));
+        } else {
+            let start = i;
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'"' {
+                i += 1;
+            }
+            tokens.push((start, i));
+        }
+    }
+    tokens
+}
+
+impl A2lExtensions {
+    /// Collect the raw text of the vendor specific IF_DATA blocks from an A2L text
+    pub fn from_a2l_text(text: &str) -> A2lExtensions {
+        let mut extensions = A2lExtensions::default();
+        let tokens = a2l_tokens(text);
+        let token = |k: usize| &text[tokens[k].0..tokens[k].1];
+        let mut k = 0;
+        while k + 2 < tokens.len() {
+            if token(k) != "/begin" || token(k + 1) != "IF_DATA" {
+                k += 1;
+                continue;
+            }
+            let Some(end) = (k + 2..tokens.len() - 1).find(|&e| token(e) == "/end" && token(e + 1) == "IF_DATA") else {
+                warn!("IF_DATA without /end IF_DATA");
+                break;
+            };
+            let vendor = token(k + 2);
+            if end > k + 2 && !A2L_KNOWN_IF_DATA.contains(&vendor) {
+                let raw = text[tokens[k + 2].1..tokens[end].0].trim();
+                debug!("Vendor specific IF_DATA {}: {}", vendor, raw);
+                extensions
+                    .vendor_if_data
+                    .entry(vendor.to_string())
+                    .and_modify(|t| {
+                        t.push('\n');
+                        t.push_str(raw);
+                    })
+                    .or_insert_with(|| raw.to_string());
+            }
+            k = end + 2;
+        }
+        extensions
+    }
+}
+
+/// Read the vendor specific extensions of an A2L file
+pub fn a2l_load_extensions<P: AsRef<std::path::Path>>(filename: P) -> Result<A2lExtensions, std::io::Error> {
+    let bytes = std::fs::read(filename)?;
+    Ok(A2lExtensions::from_a2l_text(&String::from_utf8_lossy(&bytes)))
+}
+
+//-----------------------------------------------------------------------------
+// Typedefs and instances
+
+// Flat objects synthesized from INSTANCEs
+#[derive(Default)]
+struct A2lInstanceObjects {
+    measurement: Vec<Measurement>,
+    characteristic: Vec<Characteristic>,
+    blob: Vec<Blob>,
+}
+
+// Expand an object of type type_ref at addr, structures recursively component by component
+fn a2l_expand_type(module: &Module, instance: &Instance, type_ref: &str, name: String, addr: u32, matrix_dim: Option<&MatrixDim>, objects: &mut A2lInstanceObjects) {
+    if let Some(s) = module.typedef_structure.iter().find(|t| t.name == type_ref) {
+        // Arrays of structures are expanded element by element, multi dimensional arrays with a flat index
+        let dim: u32 = matrix_dim.map_or(1, |d| d.dim_list.iter().map(|&d| d as u32).product());
+        for i in 0..dim {
+            let name = if matrix_dim.is_some() { format!("{}[{}]", name, i) } else { name.clone() };
+            let addr = addr + i * s.total_size;
+            for c in &s.structure_component {
+                let name = format!("{}.{}", name, c.component_name);
+                a2l_expand_type(module, instance, &c.component_type, name, addr + c.address_offset, c.matrix_dim.as_ref(), objects);
+            }
+        }
+    } else if let Some(t) = module.typedef_measurement.iter().find(|t| t.name == type_ref) {
+        let mut m = Measurement::new(
+            name,
+            t.long_identifier.clone(),
+            t.datatype,
+            t.conversion.clone(),
+            t.resolution,
+            t.accuracy,
+            t.lower_limit,
+            t.upper_limit,
+        );
+        m.ecu_address = Some(EcuAddress::new(addr));
+        m.ecu_address_extension = instance.ecu_address_extension.clone();
+        m.matrix_dim = matrix_dim.or(t.matrix_dim.as_ref()).cloned();
+        m.if_data = instance.if_data.clone();
+        objects.measurement.push(m);
+    } else if let Some(t) = module.typedef_characteristic.iter().find(|t| t.name == type_ref) {
+        let mut c = Characteristic::new(
+            name,
+            t.long_identifier.clone(),
+            t.characteristic_type,
+            addr,
+            t.record_layout.clone(),
+            t.max_diff,
+            t.conversion.clone(),
+            t.lower_limit,
+            t.upper_limit,
+        );
+        c.ecu_address_extension = instance.ecu_address_extension.clone();
+        c.matrix_dim = matrix_dim.or(t.matrix_dim.as_ref()).cloned();
+        c.extended_limits = t.extended_limits.clone();
+        c.format = t.format.clone();
+        c.step_size = t.step_size.clone();
+        objects.characteristic.push(c);
+    } else if let Some(t) = module.typedef_blob.iter().find(|t| t.name == type_ref) {
+        let mut b = Blob::new(name, t.long_identifier.clone(), addr, t.size);
+        b.ecu_address_extension = instance.ecu_address_extension.clone();
+        b.if_data = instance.if_data.clone();
+        objects.blob.push(b);
+    } else {
+        warn!("Type {} of instance {} not found", type_ref, name);
+    }
+}
+
+/// Expand all INSTANCEs into flat MEASUREMENT, CHARACTERISTIC and BLOB objects
+/// The names are fully qualified (instance.component.subcomponent, arrays of structures as name[i]),
+/// the addresses are the instance start address plus the component offsets
+/// Returns the number of synthesized objects
+pub fn a2l_expand_instances(a2l_file: &mut A2lFile) -> usize {
+    let module = &mut a2l_file.project.module[0];
+    let mut objects = A2lInstanceObjects::default();
+    for instance in &module.instance {
+        a2l_expand_type(
+            module,
+            instance,
+            &instance.type_ref,
+            instance.name.clone(),
+            instance.start_address,
+            instance.matrix_dim.as_ref(),
+            &mut objects,
+        );
+    }
+    let count = objects.measurement.len() + objects.characteristic.len() + objects.blob.len();
+    debug!("Expanded {} instances into {} objects", module.instance.len(), count);
+    module.measurement.extend(objects.measurement);
+    module.characteristic.extend(objects.characteristic);
+    module.blob.extend(objects.blob);
+    count
+}
+
 pub fn a2l_get_characteristics(a2l_file: &A2lFile) -> Vec<String> {
     let mut v = Vec::<String>::with_capacity(a2l_file.project.module[0].characteristic.len());
     for c in a2l_file.project.module[0].characteristic.iter() {
@@ -93,9 +329,9 @@ pub fn a2l_find_characteristic(a2l_file: &A2lFile, name: &str) -> Option<(A2lAdd
 
         // Record layout
         // Hardcode xcp-lite and XCPlite names
-        let a2l_size: u8;
+        let a2l_size: u16;
         let a2l_encoding: A2lTypeEncoding;
-        match c.deposit.as_str() {
+        match c.deposit.strip_suffix(COLUMN_DIR_RECORD_LAYOUT_SUFFIX).unwrap_or(&c.deposit) {
             "U8" | "R_UBYTE" => {
                 a2l_size = 1;
                 a2l_encoding = A2lTypeEncoding::Unsigned;
@@ -150,6 +386,9 @@ pub fn a2l_find_characteristic(a2l_file: &A2lFile, name: &str) -> Option<(A2lAdd
             },
             A2lType {
                 size: a2l_size,
+                dim: 1,
+                x_dim: 1,
+                y_dim: 1,
                 encoding: a2l_encoding,
             },
             A2lLimits {
@@ -160,21 +399,247 @@ pub fn a2l_find_characteristic(a2l_file: &A2lFile, name: &str) -> Option<(A2lAdd
     }
 }
 
+// Get the optional display format, step size, extended limits and display name of a characteristic
+pub fn a2l_find_characteristic_display(a2l_file: &A2lFile, name: &str) -> Option<A2lDisplay> {
+    let c = a2l_file.project.module[0].characteristic.iter().find(|c| c.name == name)?;
+    Some(A2lDisplay {
+        format: c.format.as_ref().map(|f| f.format_string.clone()),
+        step: c.step_size.as_ref().map(|s| s.step_size),
+        extended_limits: c.extended_limits.as_ref().map(|l| A2lLimits {
+            lower: l.lower_limit,
+            upper: l.upper_limit,
+        }),
+        display: c.display_identifier.as_ref().map(|d| d.display_name.clone()),
+    })
+}
+
+// Linear conversion of a COMPU_METHOD with an optional PHYS_UNIT
+fn a2l_get_conversion(module: &Module, conversion: &str, phys_unit: Option<&PhysUnit>) -> A2lConversion {
+    let compu_method = module.compu_method.iter().find(|c| c.name == conversion);
+    let (factor, offset) = compu_method
+        .filter(|c| c.conversion_type == ConversionType::Linear)
+        .and_then(|c| c.coeffs_linear.as_ref())
+        .map_or((1.0, 0.0), |l| (l.a, l.b));
+    let unit = match (phys_unit, compu_method) {
+        (Some(u), _) => u.unit.clone(),
+        (None, Some(c)) => c.unit.clone(),
+        (None, None) => String::new(),
+    };
+    A2lConversion { factor, offset, unit }
+}
+
+// Get the linear conversion and physical unit of a measurement
+// The unit is taken from PHYS_UNIT or from the COMPU_METHOD, conversions other than LINEAR are ignored (factor 1, offset 0)
+pub fn a2l_find_measurement_conversion(a2l_file: &A2lFile, name: &str) -> Option<A2lConversion> {
+    let module = &a2l_file.project.module[0];
+    let m = module.measurement.iter().find(|m| m.name == name)?;
+    Some(a2l_get_conversion(module, &m.conversion, m.phys_unit.as_ref()))
+}
+
+// Get the linear conversion and physical unit of a characteristic, same rules as for measurements
+pub fn a2l_find_characteristic_conversion(a2l_file: &A2lFile, name: &str) -> Option<A2lConversion> {
+    let module = &a2l_file.project.module[0];
+    let c = module.characteristic.iter().find(|c| c.name == name)?;
+    Some(a2l_get_conversion(module, &c.conversion, c.phys_unit.as_ref()))
+}
+
+// Dimensions of a characteristic, empty for a VALUE, [x_dim] for a CURVE, [x_dim, y_dim] for a MAP, MATRIX_DIM or NUMBER for a VAL_BLK
+// and the memory layout of a MAP, true if column major (RECORD_LAYOUT FNC_VALUES COLUMN_DIR)
+fn a2l_get_characteristic_dims(module: &Module, c: &Characteristic) -> Result<(Vec<usize>, bool), String> {
+    let dims: Vec<usize> = match c.characteristic_type {
+        CharacteristicType::Value => Vec::new(),
+        CharacteristicType::Curve | CharacteristicType::Map => c.axis_descr.iter().map(|a| a.max_axis_points as usize).collect(),
+        CharacteristicType::ValBlk => match (&c.matrix_dim, &c.number) {
+            (Some(m), _) => m.dim_list.iter().map(|&d| d as usize).filter(|&d| d > 1).collect(),
+            (None, Some(n)) => vec![n.number as usize],
+            (None, None) => return Err("VAL_BLK without MATRIX_DIM or NUMBER".to_string()),
+        },
+        t => return Err(format!("characteristic type {:?} not supported", t)),
+    };
+    let column_major = dims.len() == 2
+        && module
+            .record_layout
+            .iter()
+            .find(|r| r.name == c.deposit)
+            .and_then(|r| r.fnc_values.as_ref())
+            .is_some_and(|f| f.index_mode == IndexMode::ColumnDir);
+    Ok((dims, column_major))
+}
+
+// Split the array index suffix from a name, "map[3][4]" -> ("map", [3, 4])
+fn a2l_split_index(name: &str) -> (&str, Vec<usize>) {
+    let mut base = name;
+    let mut index = Vec::new();
+    while let Some(s) = base.strip_suffix(']') {
+        let Some((b, i)) = s.rsplit_once('[') else { break };
+        let Ok(i) = i.trim().parse::<usize>() else { break };
+        index.insert(0, i);
+        base = b;
+    }
+    (base, index)
+}
+
+/// Find a calibration parameter or a single element of an array calibration parameter, addressed as name[i] or name[i][j]
+/// Components of expanded instances are addressed with their full name, e.g. "params.points[0].x"
+/// For maps, i is the index on the x axis and j the index on the y axis, independent of the memory layout
+/// Returns the reason, if the name can not be resolved
+pub fn a2l_find_characteristic_element(a2l_file: &A2lFile, name: &str) -> Result<A2lCharacteristicElement, String> {
+    let module = &a2l_file.project.module[0];
+
+    // The names of expanded instances may contain an index, try the full name first
+    let (c, index) = match module.characteristic.iter().find(|c| c.name == name) {
+        Some(c) => (c, Vec::new()),
+        None => {
+            let (base, index) = a2l_split_index(name);
+            match module.characteristic.iter().find(|c| c.name == base) {
+                Some(c) if !index.is_empty() => (c, index),
+                _ => return Err("not found".to_string()),
+            }
+        }
+    };
+
+    let (dims, column_major) = a2l_get_characteristic_dims(module, c)?;
+    if index.len() != dims.len() {
+        return Err(if index.is_empty() {
+            format!("array with dimensions {:?}, index required", dims)
+        } else {
+            format!("index {:?} does not match dimensions {:?}", index, dims)
+        });
+    }
+    if index.iter().zip(&dims).any(|(i, d)| i >= d) {
+        return Err(format!("index {:?} out of range {:?}", index, dims));
+    }
+    let flat_index = if column_major {
+        index[1] * dims[0] + index[0]
+    } else {
+        index.iter().zip(&dims).fold(0, |f, (i, d)| f * d + i)
+    };
+
+    let (mut addr, a2l_type, limits) = a2l_find_characteristic(a2l_file, &c.name).ok_or_else(|| format!("record layout {} not supported", c.deposit))?;
+    addr.addr += (flat_index * a2l_type.size as usize) as u32;
+    let conversion = a2l_get_conversion(module, &c.conversion, c.phys_unit.as_ref());
+    Ok(A2lCharacteristicElement {
+        addr,
+        a2l_type,
+        limits,
+        conversion,
+    })
+}
+
+/// Get the names of all elements of a calibration parameter in index order, the name itself for a scalar, name[i] or name[i][j] for arrays
+pub fn a2l_get_characteristic_elements(a2l_file: &A2lFile, name: &str) -> Result<Vec<String>, String> {
+    let module = &a2l_file.project.module[0];
+    let c = module.characteristic.iter().find(|c| c.name == name).ok_or("not found")?;
+    let (dims, _) = a2l_get_characteristic_dims(module, c)?;
+    if dims.is_empty() {
+        return Ok(vec![name.to_string()]);
+    }
+    let count: usize = dims.iter().product();
+    let mut names = Vec::with_capacity(count);
+    for n in 0..count {
+        // Decompose the flat index, last dimension varies fastest
+        let mut index = vec![0; dims.len()];
+        let mut rest = n;
+        for (i, d) in index.iter_mut().zip(&dims).rev() {
+            *i = rest % d;
+            rest /= d;
+        }
+        let suffix: String = index.iter().map(|i| format!("[{}]", i)).collect();
+        names.push(format!("{}{}", name, suffix));
+    }
+    Ok(names)
+}
+
+// Get the limits of a measurement and the optional plausible limits
+// When a measurement has a physically possible range, LIMITS is that range and the plausible range is an annotation with label PLAUSIBLE_LIMITS
+pub fn a2l_find_measurement_limits(a2l_file: &A2lFile, name: &str) -> Option<(A2lLimits, Option<A2lLimits>)> {
+    let m = a2l_file.project.module[0].measurement.iter().find(|m| m.name == name)?;
+    let limits = A2lLimits {
+        lower: m.lower_limit,
+        upper: m.upper_limit,
+    };
+    let plausible_limits = m
+        .annotation
+        .iter()
+        .find(|a| a.annotation_label.as_ref().is_some_and(|l| l.label == PLAUSIBLE_LIMITS_ANNOTATION_LABEL))
+        .and_then(|a| a.annotation_text.as_ref())
+        .and_then(|t| match t.annotation_text_list.as_slice() {
+            [lower, upper] => Some(A2lLimits {
+                lower: lower.trim().parse().ok()?,
+                upper: upper.trim().parse().ok()?,
+            }),
+            _ => None,
+        });
+    Some((limits, plausible_limits))
+}
+
 pub fn a2l_get_measurements(a2l_file: &A2lFile) -> Vec<String> {
     let mut v = Vec::<String>::with_capacity(a2l_file.project.module[0].measurement.len());
     for m in a2l_file.project.module[0].measurement.iter() {
         v.push(m.name.clone());
     }
+    for b in a2l_file.project.module[0].blob.iter() {
+        v.push(b.name.clone());
+    }
     v
 }
 
+// Get the fixed event number from IF_DATA XCP DAQ_EVENT FIXED_EVENT_LIST
+fn a2l_get_fixed_event(ifdata_vec: &[IfData]) -> u16 {
+    let mut a2l_event: u16 = 0xFFFF;
+    for ifdata in ifdata_vec {
+        // println!("if_data: {:#?}", if_data);
+        let decoded_ifdata = ifdata::A2mlVector::load_from_ifdata(ifdata).unwrap();
+        //println!("decoded_ifdata: {:#?}", decoded_ifdata);
+        if let Some(xcp) = decoded_ifdata.xcp {
+            //println!("xcp: {:#?}", xcp);
+            if let Some(daq_event) = xcp.daq_event {
+                //println!("daq_event: {:#?}", daq_event);
+                if let Some(fixed_event_list) = daq_event.fixed_event_list {
+                    //println!("fixed_event_list: {:#?}", fixed_event_list);
+                    a2l_event = fixed_event_list.event[0].item;
+                    //println!("event =  {:#?}", a2l_event)
+                }
+            }
+        }
+    }
+    assert_ne!(a2l_event, 0xFFFF, "IF_DATA fixed event number not found");
+    a2l_event
+}
+
+// Variable length blob, max size includes the length prefix
+fn a2l_find_blob(a2l_file: &A2lFile, name: &str) -> Option<(A2lAddr, A2lType)> {
+    let b = a2l_file.project.module[0].blob.iter().find(|b| b.name == name)?;
+    let a2l_ext: u8 = if let Some(e) = b.ecu_address_extension.clone() { e.extension } else { 0 }.try_into().unwrap();
+    let a2l_size: u16 = b.size.try_into().expect("Blob size too large");
+    assert!(a2l_size as usize > A2L_BLOB_LENGTH_PREFIX_SIZE, "Blob size too small");
+    let a2l_event = a2l_get_fixed_event(&b.if_data);
+
+    Some((
+        A2lAddr {
+            ext: a2l_ext,
+            addr: b.start_address,
+            event: a2l_event,
+        },
+        A2lType {
+            size: a2l_size,
+            dim: 1,
+            x_dim: 1,
+            y_dim: 1,
+            encoding: A2lTypeEncoding::Blob,
+        },
+    ))
+}
+
 pub fn a2l_find_measurement(a2l_file: &A2lFile, name: &str) -> Option<(A2lAddr, A2lType)> {
-    let m = a2l_file.project.module[0].measurement.iter().find(|m| m.name == name)?;
+    let Some(m) = a2l_file.project.module[0].measurement.iter().find(|m| m.name == name) else {
+        return a2l_find_blob(a2l_file, name);
+    };
     let a2l_addr: u32 = m.ecu_address.clone().expect("Measurement ecu_address not found!").address;
     let a2l_ext: u8 = if let Some(e) = m.ecu_address_extension.clone() { e.extension } else { 0 }.try_into().unwrap();
 
     let get_type = m.datatype;
-    let a2l_size: u8 = match get_type {
+    let a2l_size: u16 = match get_type {
         DataType::Sbyte => 1,
         DataType::Sword => 2,
         DataType::Slong => 4,
@@ -202,26 +667,20 @@ pub fn a2l_find_measurement(a2l_file: &A2lFile, name: &str) -> Option<(A2lAddr,
     };
     assert!(a2l_size > 0, "a2l_size is zero");
 
-    let mut a2l_event: u16 = 0xFFFF;
-    let ifdata_vec = m.if_data.clone();
+    // Arrays are measured as a whole, the size is the element size times the number of elements
+    // Dimensions beyond the second are folded into y_dim
+    let (a2l_x_dim, a2l_y_dim): (u16, u16) = match (&m.matrix_dim, &m.array_size) {
+        (Some(matrix_dim), _) => match matrix_dim.dim_list.split_first() {
+            Some((x_dim, rest)) => (*x_dim, rest.iter().product::<u16>()),
+            None => (1, 1),
+        },
+        (None, Some(array_size)) => (array_size.number, 1),
+        (None, None) => (1, 1),
+    };
+    let a2l_dim: u16 = a2l_x_dim.checked_mul(a2l_y_dim).expect("Measurement array too large");
+    let a2l_size: u16 = a2l_size.checked_mul(a2l_dim).expect("Measurement array too large");
 
-    for ifdata in &ifdata_vec {
-        // println!("if_data: {:#?}", if_data);
-        let decoded_ifdata = ifdata::A2mlVector::load_from_ifdata(ifdata).unwrap();
-        //println!("decoded_ifdata: {:#?}", decoded_ifdata);
-        if let Some(xcp) = decoded_ifdata.xcp {
-            //println!("xcp: {:#?}", xcp);
-            if let Some(daq_event) = xcp.daq_event {
-                //println!("daq_event: {:#?}", daq_event);
-                if let Some(fixed_event_list) = daq_event.fixed_event_list {
-                    //println!("fixed_event_list: {:#?}", fixed_event_list);
-                    a2l_event = fixed_event_list.event[0].item;
-                    //println!("event =  {:#?}", a2l_event)
-                }
-            }
-        }
-    }
-    assert_ne!(a2l_event, 0xFFFF, "IF_DATA fixed event number not found");
+    let a2l_event = a2l_get_fixed_event(&m.if_data);
 
     Some((
         A2lAddr {
@@ -231,11 +690,28 @@ pub fn a2l_find_measurement(a2l_file: &A2lFile, name: &str) -> Option<(A2lAddr,
         },
         A2lType {
             size: a2l_size,
+            dim: a2l_dim,
+            x_dim: a2l_x_dim,
+            y_dim: a2l_y_dim,
             encoding: a2l_encoding,
         },
     ))
 }
 
+/// Cycle time of an event channel in ns from IF_DATA XCP DAQ EVENT TIME_CYCLE and TIME_UNIT
+/// None if the event is not found or sporadic (TIME_CYCLE 0)
+pub fn a2l_get_event_cycle_time_ns(a2l_file: &A2lFile, event: u16) -> Option<u64> {
+    a2l_file.project.module[0]
+        .if_data
+        .iter()
+        .filter_map(ifdata::A2mlVector::load_from_ifdata)
+        .filter_map(|i| i.xcp?.daq)
+        .flat_map(|daq| daq.event)
+        .find(|e| e.event_channel_number == event)
+        .filter(|e| e.time_cycle > 0)
+        .map(|e| e.time_cycle as u64 * 10u64.pow(e.time_unit as u32))
+}
+
 pub fn a2l_printf_info(a2l_file: &A2lFile) {
     // MOD_PAR
     println!("MOD_PAR:");
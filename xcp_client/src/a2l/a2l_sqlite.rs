@@ -0,0 +1,206 @@
+//-----------------------------------------------------------------------------
+// Module a2l_sqlite
+// Export the instances, events and calibration segments of an A2L file to a SQLite database
+// Same schema as the registry export of xcp-lite (Registry::export_sqlite), addresses are A2L addresses
+
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::path::Path;
+
+use a2lfile::{A2lFile, Annotation, IfData};
+use rusqlite::{params, Connection, Transaction};
+
+use super::ifdata;
+
+const SCHEMA: &str = r"
+CREATE TABLE module (name TEXT NOT NULL, epk TEXT, epk_addr INTEGER NOT NULL, protocol TEXT, addr TEXT, port INTEGER);
+CREATE TABLE instances (
+    id INTEGER PRIMARY KEY, name TEXT NOT NULL, object_type TEXT NOT NULL, value_type TEXT NOT NULL, x_dim INTEGER NOT NULL, y_dim INTEGER NOT NULL,
+    min REAL, max REAL, unit TEXT NOT NULL, comment TEXT NOT NULL, addr_offset INTEGER NOT NULL, addr_ext INTEGER NOT NULL, event_id INTEGER, calseg_name TEXT,
+    factor REAL, offset REAL, axis_pts_ref TEXT, x_axis_input TEXT, annotation TEXT, idl_hash INTEGER
+);
+CREATE TABLE annotations (instance_id INTEGER NOT NULL, label TEXT NOT NULL, origin TEXT NOT NULL, text TEXT NOT NULL);
+CREATE TABLE typedefs (id INTEGER PRIMARY KEY, name TEXT NOT NULL, size INTEGER NOT NULL);
+CREATE TABLE typedef_fields (typedef_id INTEGER NOT NULL, name TEXT NOT NULL, value_type TEXT NOT NULL, offset INTEGER NOT NULL);
+CREATE TABLE events (id INTEGER PRIMARY KEY, name TEXT NOT NULL, period_us REAL NOT NULL, instance_index INTEGER NOT NULL, instance_label TEXT, consistency TEXT NOT NULL);
+CREATE TABLE calseg_list (id INTEGER PRIMARY KEY, name TEXT NOT NULL, size INTEGER NOT NULL);
+";
+
+// Fixed event of a measurement or calibration parameter, if any
+fn get_fixed_event(ifdata_vec: &[IfData]) -> Option<u16> {
+    ifdata_vec
+        .iter()
+        .filter_map(ifdata::A2mlVector::load_from_ifdata)
+        .filter_map(|i| i.xcp?.daq_event?.fixed_event_list)
+        .find_map(|l| l.event.first().map(|e| e.item))
+}
+
+// Value type name of a record layout, the record layouts of xcp-lite are named by deposit type
+fn deposit_to_value_type(deposit: &str) -> &str {
+    match deposit {
+        "U8" => "UBYTE",
+        "U16" => "UWORD",
+        "U32" => "ULONG",
+        "U64" => "A_UINT64",
+        "S8" => "SBYTE",
+        "S16" => "SWORD",
+        "S32" => "SLONG",
+        "S64" => "A_INT64",
+        "F32" => "FLOAT32_IEEE",
+        "F64" => "FLOAT64_IEEE",
+        d => d.strip_prefix("A_").unwrap_or(d),
+    }
+}
+
+fn write_annotations(tx: &Transaction, id: i64, annotations: &[Annotation]) -> rusqlite::Result<()> {
+    for a in annotations {
+        let label = a.annotation_label.as_ref().map(|l| l.label.as_str()).unwrap_or_default();
+        let origin = a.annotation_origin.as_ref().map(|o| o.origin.as_str()).unwrap_or_default();
+        let text = a.annotation_text.as_ref().map(|t| t.annotation_text_list.join("\n")).unwrap_or_default();
+        tx.execute("INSERT INTO annotations VALUES (?1, ?2, ?3, ?4)", params![id, label, origin, text])?;
+    }
+    Ok(())
+}
+
+/// Export the A2L file to a SQLite database file, an existing file is replaced
+pub fn a2l_export_sqlite(a2l_file: &A2lFile, path: &Path) -> rusqlite::Result<()> {
+    info!("Export A2L to SQLite database {}", path.display());
+    let _ = std::fs::remove_file(path);
+    let module = &a2l_file.project.module[0];
+
+    let mut conn = Connection::open(path)?;
+    let tx = conn.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+
+    // Module
+    let epk = module.mod_par.as_ref().and_then(|m| m.epk.as_ref()).map(|e| e.identifier.clone());
+    let epk_addr = module.mod_par.as_ref().and_then(|m| m.addr_epk.first()).map(|a| a.address).unwrap_or_default();
+    tx.execute(
+        "INSERT INTO module VALUES (?1, ?2, ?3, NULL, NULL, NULL)",
+        params![module.name, epk, epk_addr],
+    )?;
+
+    // Events
+    for i in module.if_data.iter().filter_map(ifdata::A2mlVector::load_from_ifdata) {
+        if let Some(daq) = i.xcp.and_then(|x| x.daq) {
+            for e in daq.event {
+                let cycle_time_ns = e.time_cycle as f64 * 10f64.powi(e.time_unit as i32);
+                tx.execute(
+                    "INSERT INTO events VALUES (?1, ?2, ?3, 0, NULL, 'DAQ')",
+                    params![e.event_channel_number, e.event_channel_name, cycle_time_ns / 1000.0],
+                )?;
+            }
+        }
+    }
+
+    // Calibration segments
+    if let Some(mod_par) = &module.mod_par {
+        for (n, s) in mod_par.memory_segment.iter().filter(|s| s.name != "epk").enumerate() {
+            tx.execute("INSERT INTO calseg_list VALUES (?1, ?2, ?3)", params![n, s.name, s.size])?;
+        }
+    }
+
+    // Instances
+    let mut id: i64 = 0;
+    for m in &module.measurement {
+        id += 1;
+        let (x_dim, y_dim) = match &m.matrix_dim {
+            Some(d) => (d.dim_list.first().copied().unwrap_or(1), d.dim_list.get(1).copied().unwrap_or(1)),
+            None => (1, 1),
+        };
+        let unit = m.phys_unit.as_ref().map(|u| u.unit.as_str()).unwrap_or_default();
+        tx.execute(
+            "INSERT INTO instances VALUES (?1, ?2, 'MEASUREMENT', ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, NULL, NULL, NULL, NULL, NULL, NULL, NULL)",
+            params![
+                id,
+                m.name,
+                m.datatype.to_string(),
+                x_dim,
+                y_dim,
+                m.lower_limit,
+                m.upper_limit,
+                unit,
+                m.long_identifier,
+                m.ecu_address.as_ref().map(|a| a.address).unwrap_or_default(),
+                m.ecu_address_extension.as_ref().map(|e| e.extension).unwrap_or_default(),
+                get_fixed_event(&m.if_data)
+            ],
+        )?;
+        write_annotations(&tx, id, &m.annotation)?;
+    }
+    for b in &module.blob {
+        id += 1;
+        tx.execute(
+            "INSERT INTO instances VALUES (?1, ?2, 'MEASUREMENT', 'BLOB', ?3, 1, NULL, NULL, '', ?4, ?5, ?6, ?7, NULL, NULL, NULL, NULL, NULL, NULL, NULL)",
+            params![
+                id,
+                b.name,
+                b.size,
+                b.long_identifier,
+                b.start_address,
+                b.ecu_address_extension.as_ref().map(|e| e.extension).unwrap_or_default(),
+                get_fixed_event(&b.if_data)
+            ],
+        )?;
+        write_annotations(&tx, id, &b.annotation)?;
+    }
+    for a in &module.axis_pts {
+        id += 1;
+        let unit = a.phys_unit.as_ref().map(|u| u.unit.as_str()).unwrap_or_default();
+        tx.execute(
+            "INSERT INTO instances VALUES (?1, ?2, 'AXIS', ?3, ?4, 1, ?5, ?6, ?7, ?8, ?9, ?10, ?11, NULL, NULL, NULL, NULL, NULL, NULL, NULL)",
+            params![
+                id,
+                a.name,
+                deposit_to_value_type(&a.deposit_record),
+                a.max_axis_points,
+                a.lower_limit,
+                a.upper_limit,
+                unit,
+                a.long_identifier,
+                a.address,
+                a.ecu_address_extension.as_ref().map(|e| e.extension).unwrap_or_default(),
+                get_fixed_event(&a.if_data)
+            ],
+        )?;
+        write_annotations(&tx, id, &a.annotation)?;
+    }
+    for c in &module.characteristic {
+        id += 1;
+        let (x_dim, y_dim) = match (&c.matrix_dim, c.axis_descr.as_slice()) {
+            (Some(d), _) => (d.dim_list.first().copied().unwrap_or(1), d.dim_list.get(1).copied().unwrap_or(1)),
+            (None, [x]) => (x.max_axis_points, 1),
+            (None, [x, y, ..]) => (x.max_axis_points, y.max_axis_points),
+            (None, []) => (1, 1),
+        };
+        let x_axis = c.axis_descr.first();
+        let axis_pts_ref = x_axis.and_then(|x| x.axis_pts_ref.as_ref()).map(|r| r.axis_points.as_str());
+        let x_axis_input = x_axis.map(|x| x.input_quantity.as_str()).filter(|i| *i != "NO_INPUT_QUANTITY");
+        let unit = c.phys_unit.as_ref().map(|u| u.unit.as_str()).unwrap_or_default();
+        tx.execute(
+            "INSERT INTO instances VALUES (?1, ?2, 'CHARACTERISTIC', ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, NULL, NULL, NULL, ?13, ?14, NULL, NULL)",
+            params![
+                id,
+                c.name,
+                deposit_to_value_type(&c.deposit),
+                x_dim,
+                y_dim,
+                c.lower_limit,
+                c.upper_limit,
+                unit,
+                c.long_identifier,
+                c.address,
+                c.ecu_address_extension.as_ref().map(|e| e.extension).unwrap_or_default(),
+                get_fixed_event(&c.if_data),
+                axis_pts_ref,
+                x_axis_input
+            ],
+        )?;
+        write_annotations(&tx, id, &c.annotation)?;
+    }
+
+    tx.commit()
+}
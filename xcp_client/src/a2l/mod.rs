@@ -1,2 +1,7 @@
+pub mod a2l_diff;
 pub mod a2l_reader;
+pub mod a2l_sqlite;
 pub mod ifdata;
+
+#[allow(unused_imports)]
+pub use a2l_diff::{diff_a2l, A2lDiff};
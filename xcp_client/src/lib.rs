@@ -9,4 +9,7 @@
 #![crate_name = "xcp_client"]
 
 pub mod a2l;
+pub mod mdf4_decoder;
+mod mdflib;
+pub mod param_file;
 pub mod xcp_client;
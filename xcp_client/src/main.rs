@@ -8,6 +8,7 @@ mod xcp_client;
 use xcp_client::*;
 mod a2l;
 mod mdflib;
+mod param_file;
 
 //----------------------------------------------------------------------------------------------
 // Logging
@@ -38,36 +39,42 @@ impl ToLogLevelFilter for u8 {
 
 const MAX_EVENT: usize = 16;
 
-#[derive(Debug)]
 struct DaqDecoder {
     daq_odt_entries: Option<Vec<Vec<OdtEntry>>>,
+    odt_assembler: Option<DaqOdtAssembler>,
+    blob_decoder: Box<dyn XcpBlobDecoder + Send>,
     timestamp_resolution: u64,
     daq_header_size: u8,
     event_count: usize,
     byte_count: usize,
     daq_timestamp: [u64; MAX_EVENT],
+    clock_sync: Option<XcpTimeSyncResult>,
 }
 
 impl DaqDecoder {
-    pub fn new() -> DaqDecoder {
+    pub fn new(blob_decoder: Box<dyn XcpBlobDecoder + Send>) -> DaqDecoder {
         DaqDecoder {
             daq_odt_entries: None,
+            odt_assembler: None,
+            blob_decoder,
             timestamp_resolution: 0,
             daq_header_size: 0,
             event_count: 0,
             byte_count: 0,
             daq_timestamp: [0; MAX_EVENT],
+            clock_sync: None,
         }
     }
 }
 
 // Hard coded decoder for DAQ data
 // This is a simple example, a real application would need to decode the data according to the actual measurement setup
-// Assumes first signal is a 32 bit counter and there is only one ODT
+// Events with multiple ODTs are collected until complete and then decoded
 impl XcpDaqDecoder for DaqDecoder {
     // Set start time and init
     fn start(&mut self, daq_odt_entries: Vec<Vec<OdtEntry>>, timestamp: u64) {
         // Init
+        self.odt_assembler = Some(DaqOdtAssembler::new(&daq_odt_entries));
         self.daq_odt_entries = Some(daq_odt_entries);
         self.event_count = 0;
         self.byte_count = 0;
@@ -121,6 +128,11 @@ impl XcpDaqDecoder for DaqDecoder {
         self.timestamp_resolution = timestamp_resolution;
     }
 
+    // Set clock synchronization for wall clock timestamps
+    fn set_clock_sync(&mut self, clock_sync: XcpTimeSyncResult) {
+        self.clock_sync = Some(clock_sync);
+    }
+
     // Decode DAQ data
     fn decode(&mut self, lost: u32, buf: &[u8]) {
         let daq: u16;
@@ -150,11 +162,10 @@ impl XcpDaqDecoder for DaqDecoder {
         }
 
         assert!(daq < MAX_EVENT as u16);
-        assert!(odt == 0);
 
-        // Decode full 64 bit daq timestamp
-        let t_last = self.daq_timestamp[daq as usize];
-        let t: u64 = if odt == 0 {
+        // Decode full 64 bit daq timestamp from the first ODT
+        if odt == 0 {
+            let t_last = self.daq_timestamp[daq as usize];
             let tl = (t_last & 0xFFFFFFFF) as u32;
             let mut th = (t_last >> 32) as u32;
             if timestamp_raw < tl {
@@ -165,67 +176,41 @@ impl XcpDaqDecoder for DaqDecoder {
                 warn!("Timestamp of daq {} declining {} -> {}", daq, t_last, t);
             }
             self.daq_timestamp[daq as usize] = t;
-            t
-        } else {
-            t_last
+        }
+        let t = self.daq_timestamp[daq as usize];
+
+        // Collect the ODTs of the event, decode when complete
+        let Some(data) = self.odt_assembler.as_mut().unwrap().add(daq, odt, data) else {
+            return;
         };
 
-        println!("DAQ: lost={}, daq={}, odt={}, t={}ns", lost, daq, odt, t);
+        // Wall clock timestamp, if the clock is synchronized
+        match self.clock_sync {
+            Some(clock_sync) => {
+                let wall_clock = clock_sync.server_to_master_ns(t * self.timestamp_resolution);
+                println!("DAQ: lost={}, daq={}, t={}ns, wall_clock={}ns", lost, daq, t, wall_clock);
+            }
+            None => println!("DAQ: lost={}, daq={}, t={}ns", lost, daq, t),
+        }
 
         // Get daq list
         let daq_list = &self.daq_odt_entries.as_ref().unwrap()[daq as usize];
 
         // Decode all odt entries
         for odt_entry in daq_list.iter() {
-            let value_size = odt_entry.a2l_type.size as usize;
-            let mut value_offset = odt_entry.offset as usize + value_size - 1;
-            let mut value: u64 = 0;
-            loop {
-                value |= data[value_offset] as u64;
-                if value_offset == odt_entry.offset as usize {
-                    break;
-                };
-                value <<= 8;
-                value_offset -= 1;
-            }
-            match odt_entry.a2l_type.encoding {
-                A2lTypeEncoding::Signed => {
-                    match value_size {
-                        1 => {
-                            let signed_value: i8 = value as u8 as i8;
-                            println!(" {} = {}", odt_entry.name, signed_value);
-                        }
-                        2 => {
-                            let signed_value: i16 = value as u16 as i16;
-                            println!(" {} = {}", odt_entry.name, signed_value);
-                        }
-                        4 => {
-                            let signed_value: i32 = value as u32 as i32;
-                            println!(" {} = {}", odt_entry.name, signed_value);
-                        }
-                        8 => {
-                            let signed_value: i64 = value as i64;
-                            println!(" {} = {}", odt_entry.name, signed_value);
-                        }
-                        _ => {
-                            warn!("Unsupported signed value size {}", value_size);
-                        }
-                    };
-                }
-                A2lTypeEncoding::Unsigned => {
-                    println!(" {} = {}", odt_entry.name, value);
-                }
-                A2lTypeEncoding::Float => {
-                    if odt_entry.a2l_type.size == 4 {
-                        #[allow(clippy::transmute_int_to_float)]
-                        let value: f32 = unsafe { std::mem::transmute(value as u32) };
-                        println!(" {} = {}", odt_entry.name, value);
-                    } else {
-                        #[allow(clippy::transmute_int_to_float)]
-                        let value: f64 = unsafe { std::mem::transmute(value) };
-                        println!(" {} = {}", odt_entry.name, value);
-                    }
+            // Variable length blob with length prefix
+            if let A2lTypeEncoding::Blob = odt_entry.a2l_type.encoding {
+                match odt_entry.get_blob(data) {
+                    Some(blob) => self.blob_decoder.decode(&odt_entry.name, t, blob),
+                    None => warn!("Blob {} length exceeds max size {}", odt_entry.name, odt_entry.a2l_type.size),
                 }
+                continue;
+            }
+
+            // Scalars, arrays and matrices
+            match odt_entry.get_value(data) {
+                Some(value) => println!(" {} = {}", odt_entry.name, value),
+                None => warn!("Unsupported type of {}", odt_entry.name),
             }
         }
 
@@ -234,6 +219,13 @@ impl XcpDaqDecoder for DaqDecoder {
     }
 }
 
+//------------------------------------------------------------------------
+// Handle incomming blob data (serialized data objects), hex dump
+
+struct BlobHexDecoder;
+
+impl XcpBlobDecoder for BlobHexDecoder {}
+
 //------------------------------------------------------------------------
 // Handle incomming SERV_TEXT data
 
@@ -286,6 +278,10 @@ struct Args {
     #[clap(long)]
     print_a2l: bool,
 
+    /// Print the raw text of the vendor specific IF_DATA blocks of a vendor in the A2L file
+    #[arg(long, value_name = "VENDOR")]
+    print_if_data: Option<String>,
+
     /// Lists all measurement variables
     #[clap(long)]
     list_mea: bool,
@@ -294,6 +290,14 @@ struct Args {
     #[clap(long)]
     list_cal: bool,
 
+    /// Print the XCP server session status, resource protection and calibration page
+    #[clap(long)]
+    status: bool,
+
+    /// Synchronize the client wall clock with the server DAQ clock and print the offset, resync every second during measurement
+    #[clap(long)]
+    sync_clock: bool,
+
     /// Specifies the variables names for DAQ measurement, 'all' or a list of names separated by space
     #[arg(short, long, value_delimiter = ' ', num_args = 1..)]
     measurement_list: Vec<String>,
@@ -301,30 +305,175 @@ struct Args {
     /// A2L filename, default is upload A2L file
     #[arg(short, long)]
     a2l_filename: Option<String>,
+
+    /// Connect to multiple XCP servers in parallel, list of server addresses separated by comma (e.g. 127.0.0.1:5555,127.0.0.1:5556)
+    #[arg(long, value_delimiter = ',', num_args = 1..)]
+    multi_server: Vec<String>,
+
+    /// Compare two A2L files (old new) and report added, removed, type changed and address changed objects, fails if an object was removed or changed its type
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"])]
+    diff_a2l: Vec<String>,
+
+    /// Print the A2L diff in JSON format
+    #[clap(long)]
+    json: bool,
+
+    /// Export the measurement and calibration objects, events and calibration segments of the A2L file to a SQLite database file
+    #[arg(long, value_name = "PATH")]
+    export_sqlite: Option<String>,
+
+    /// Verify the calibration values against golden values in a JSON file, fails if a value does not match
+    #[arg(long, value_name = "PATH")]
+    verify_cal: Option<String>,
+
+    /// Name prefix of the calibration objects to verify (e.g. calibration segment name)
+    #[arg(long, default_value = "")]
+    verify_cal_prefix: String,
+
+    /// Apply a parameter file with physical values of calibration parameters (.json or .csv), fails if an entry could not be applied
+    #[arg(long, value_name = "PATH")]
+    apply_params: Option<String>,
+
+    /// Only validate the names and values of the parameter file, do not write
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Dump the current values of calibration parameters to a parameter file (.json or .csv)
+    #[arg(long, value_name = "PATH")]
+    dump_params: Option<String>,
+
+    /// Name pattern of the calibration parameters to dump, default is all
+    #[arg(long, default_value = "")]
+    dump_params_pattern: String,
+
+    /// Record all XCP packets sent and received to a session transcript file (e.g. session.xcps)
+    #[arg(long, value_name = "PATH")]
+    record: Option<String>,
+
+    /// Replay a session transcript file against the XCP server, fails if a response differs from the recorded response
+    #[arg(long, value_name = "PATH")]
+    replay: Option<String>,
 }
 
 //------------------------------------------------------------------------
+#[allow(clippy::too_many_arguments)]
 async fn xcp_client(
     dest_addr: std::net::SocketAddr,
     local_addr: std::net::SocketAddr,
     a2l_filename: Option<String>,
     print_a2l: bool,
+    print_if_data: Option<String>,
     list_cal: bool,
     list_mea: bool,
+    status: bool,
+    sync_clock: bool,
     measurement_list: Vec<String>,
+    export_sqlite: Option<String>,
+    verify_cal: Option<(String, String)>,
+    apply_params: Option<(String, bool)>,
+    dump_params: Option<(String, String)>,
+    record: Option<String>,
 ) -> Result<(), Box<dyn Error>> {
     // Create xcp_client
     let mut xcp_client = XcpClient::new(dest_addr, local_addr);
+    if let Some(path) = record {
+        xcp_client.record_session(std::path::Path::new(&path))?;
+        info!("Record session to {}", path);
+    }
 
     // Connect to the XCP server
     info!("XCP Connect");
-    let daq_decoder = Arc::new(Mutex::new(DaqDecoder::new()));
+    let daq_decoder = Arc::new(Mutex::new(DaqDecoder::new(Box::new(BlobHexDecoder))));
     xcp_client.connect(Arc::clone(&daq_decoder), ServTextDecoder::new()).await?;
 
+    // Print the server status
+    if status {
+        let status = xcp_client.get_status().await?;
+        println!();
+        println!("XCP server status:");
+        println!(" session_status = 0x{:02X}", status.session_status);
+        println!(" resource_protection = 0x{:02X}", status.resource_protection);
+        println!(" session_cfg = {}", status.session_cfg);
+        println!(" cal_page = {}", status.cal_page);
+        println!(" flags = {}", status.flags().join(" "));
+        println!();
+    }
+
+    // Synchronize the clock and print the offset of the server DAQ clock to the client wall clock
+    if sync_clock {
+        let clock_sync = xcp_client.sync_clock().await?;
+        println!();
+        println!("XCP clock sync:");
+        println!(" server_ts = {}ns", clock_sync.server_ts_ns);
+        println!(" master_ts = {}ns", clock_sync.master_ts_ns);
+        println!(" offset = {}ns", clock_sync.offset_ns());
+        println!(" accuracy = {}ns", clock_sync.accuracy_ns);
+        println!();
+        xcp_client.set_clock_sync_interval(Some(std::time::Duration::from_secs(1)));
+    }
+
     // Upload A2L file
     info!("Load A2L file");
     xcp_client.a2l_loader(a2l_filename, print_a2l).await?;
 
+    // Print the vendor specific IF_DATA
+    if let Some(vendor) = print_if_data {
+        match xcp_client.get_vendor_if_data(&vendor) {
+            Some(text) => {
+                println!();
+                println!("IF_DATA {}:", vendor);
+                println!("{}", text);
+                println!();
+            }
+            None => println!("No IF_DATA {} in the A2L file", vendor),
+        }
+    }
+
+    // Export the A2L file to a SQLite database
+    if let Some(path) = export_sqlite {
+        a2l::a2l_sqlite::a2l_export_sqlite(xcp_client.get_a2l_file().unwrap(), std::path::Path::new(&path))?;
+        println!("Exported A2L to {}", path);
+    }
+
+    // Verify the calibration values against the golden JSON file
+    if let Some((path, prefix)) = verify_cal {
+        let golden_json = std::fs::read_to_string(&path)?;
+        let mismatches = xcp_client.verify_calibration(&golden_json, &prefix).await?;
+        xcp_client.disconnect().await?;
+        if !mismatches.is_empty() {
+            return Err(format!("Calibration verification failed, {} mismatches against {}", mismatches.len(), path).into());
+        }
+        println!("Calibration verification passed against {}", path);
+        return Ok(());
+    }
+
+    // Apply a parameter file
+    if let Some((path, dry_run)) = apply_params {
+        let format = param_file::ParameterFileFormat::from_path(&path).ok_or(format!("Unknown parameter file format {}", path))?;
+        let report = xcp_client.apply_parameter_file(&path, format, dry_run).await?;
+        for failure in &report.failed {
+            println!("FAIL {}: {}", failure.name, failure.reason);
+        }
+        if !report.is_ok() {
+            xcp_client.disconnect().await?;
+            return Err(format!(
+                "{} of {} parameters of {} could not be applied",
+                report.failed.len(),
+                report.failed.len() + report.applied.len(),
+                path
+            )
+            .into());
+        }
+        println!("{} parameters of {} {}", report.applied.len(), path, if dry_run { "validated" } else { "applied" });
+    }
+
+    // Dump calibration parameters to a parameter file
+    if let Some((path, pattern)) = dump_params {
+        let format = param_file::ParameterFileFormat::from_path(&path).ok_or(format!("Unknown parameter file format {}", path))?;
+        let count = xcp_client.dump_parameters(&pattern, &path, format).await?;
+        println!("{} parameter values dumped to {}", count, path);
+    }
+
     // Print all calibration objects with current value
     if list_cal {
         println!();
@@ -332,21 +481,10 @@ async fn xcp_client(
         let cal_objects = xcp_client.get_characteristics();
         for name in &cal_objects {
             let h = xcp_client.create_calibration_object(name).await?;
-            let o = xcp_client.get_calibration_object(h);
-
-            match o.get_type().encoding {
-                A2lTypeEncoding::Signed => {
-                    let v = xcp_client.get_value_i64(h);
-                    println!(" {} = {}", name, v);
-                }
-                A2lTypeEncoding::Unsigned => {
-                    let v = xcp_client.get_value_u64(h);
-                    println!(" {} = {}", name, v);
-                }
-                A2lTypeEncoding::Float => {
-                    let v = xcp_client.get_value_f64(h);
-                    println!(" {} = {:.8}", name, v);
-                }
+            match xcp_client.get_value(h) {
+                Value::F64(v) => println!(" {} = {:.8}", name, v),
+                Value::Bytes(_) => println!(" {} = <blob>", name),
+                v => println!(" {} = {}", name, v),
             }
         }
         println!();
@@ -413,11 +551,16 @@ async fn xcp_client(
         }
         info!("");
 
-        // Measure for 6 seconds
+        // Measure for 6 seconds, resync the clock periodically
         // 32 bit DAQ timestamp will overflow after 4.2s
         let start_time = tokio::time::Instant::now();
         xcp_client.start_measurement().await?;
-        tokio::time::sleep(std::time::Duration::from_secs(6)).await;
+        while start_time.elapsed() < std::time::Duration::from_secs(6) {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            if let Some(clock_sync) = xcp_client.check_clock_sync().await? {
+                info!("Clock resync: offset={}ns, accuracy={}ns", clock_sync.offset_ns(), clock_sync.accuracy_ns);
+            }
+        }
         xcp_client.stop_measurement().await?;
         let elapsed_time = start_time.elapsed().as_micros();
 
@@ -438,6 +581,46 @@ async fn xcp_client(
     Ok(())
 }
 
+//------------------------------------------------------------------------
+async fn multi_xcp_client(servers: Vec<std::net::SocketAddr>, measurement_list: Vec<String>) -> Result<(), Box<dyn Error>> {
+    // Create multi server xcp_client
+    let mut multi_client = MultiXcpClient::new(servers);
+    let mut merged_stream = multi_client.merged_stream().unwrap();
+
+    // Connect to all XCP servers and upload their A2L files to the current directory
+    info!("XCP Connect to {} servers", multi_client.len());
+    multi_client.connect_all(std::path::Path::new(".")).await?;
+
+    // Calibration
+    // Change the value of CalPage1.counter_max to 255 on all servers (if exists on all servers - from main.rs, multi_thread_demo.rs)
+    let n = multi_client.len();
+    if (0..n).all(|i| multi_client.get_client(i).get_characteristics().iter().any(|c| c == "CalPage1.counter_max")) {
+        multi_client.set_value_all("CalPage1.counter_max", 255.0).await?;
+        info!("Set CalPage1.counter_max to 255 on all servers");
+    }
+
+    // Measure all variables containing the first name of the measurement list, or all variables
+    let pattern = measurement_list.first().filter(|p| *p != "all").map_or("", |p| p.as_str());
+    multi_client.start_measurement_all(pattern).await?;
+
+    // Measure for 6 seconds and count the events per server
+    let mut event_count = vec![0usize; multi_client.len()];
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(6);
+    while let Ok(Some((server_index, sample))) = tokio::time::timeout_at(deadline, merged_stream.recv()).await {
+        trace!("DAQ: server={}, lost={}, len={}", server_index, sample.lost, sample.data.len());
+        event_count[server_index] += 1;
+    }
+    multi_client.stop_measurement_all().await?;
+    for (i, n) in event_count.iter().enumerate() {
+        info!("Server {}: {} events", i, n);
+    }
+
+    // Disconnect
+    multi_client.disconnect_all().await?;
+
+    Ok(())
+}
+
 //------------------------------------------------------------------------
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -452,6 +635,19 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .format_target(false)
         .init();
 
+    if !args.diff_a2l.is_empty() {
+        let diff = a2l::diff_a2l(&args.diff_a2l[0], &args.diff_a2l[1]).map_err(|e| format!("{}", e))?;
+        if args.json {
+            println!("{}", diff.to_json());
+        } else {
+            print!("{}", diff);
+        }
+        if !diff.is_compatible() {
+            return Err(format!("A2L {} is not compatible with {}", args.diff_a2l[1], args.diff_a2l[0]).into());
+        }
+        return Ok(());
+    }
+
     let dest_addr: std::net::SocketAddr = args.dest_addr.parse().map_err(|e| format!("{}", e))?;
     let local_addr: std::net::SocketAddr = args.bind_addr.parse().map_err(|e| format!("{}", e))?;
     info!("dest_addr: {}", dest_addr);
@@ -466,5 +662,23 @@ async fn main() -> Result<(), Box<dyn Error>> {
         info!("a2l_filename: {}", args.a2l_filename.as_ref().unwrap());
     }
 
-    xcp_client(dest_addr, local_addr, args.a2l_filename, args.print_a2l, args.list_cal, args.list_mea, measurement_list).await
+    if let Some(path) = args.replay {
+        let mut xcp_client = XcpClient::new(dest_addr, local_addr);
+        xcp_client.replay_session(std::path::Path::new(&path)).await?;
+        println!("Replay of {} passed", path);
+        return Ok(());
+    }
+
+    if !args.multi_server.is_empty() {
+        let servers = args
+            .multi_server
+            .iter()
+            .map(|s| s.parse::<std::net::SocketAddr>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{}", e))?;
+        info!("multi_server: {:?}", servers);
+        return multi_xcp_client(servers, measurement_list).await;
+    }
+
+    xcp_client(dest_addr, local_addr, args.a2l_filename, args.print_a2l, args.print_if_data, args.list_cal, args.list_mea, args.status, args.sync_clock, measurement_list, args.export_sqlite, args.verify_cal.map(|path| (path, args.verify_cal_prefix)), args.apply_params.map(|path| (path, args.dry_run)), args.dump_params.map(|path| (path, args.dump_params_pattern)), args.record).await
 }
@@ -0,0 +1,241 @@
+//-----------------------------------------------------------------------------
+// Module mdf4_decoder
+// DAQ decoder which writes the measurement data to an ASAM MDF4 (MF4) file
+// Uses the mdflib C library
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::a2l::a2l_reader::{a2l_find_measurement_conversion, a2l_get_measurements, A2lConversion, A2lTypeEncoding};
+use crate::mdflib;
+use crate::xcp_client::{DaqOdtAssembler, OdtEntry, XcpDaqDecoder};
+
+// mdflib has a global state, only one MDF file can be written at a time
+static MDF_FILE_OPEN: AtomicBool = AtomicBool::new(false);
+
+// Each record starts with the DAQ list number as 16 bit record id and a 64 bit raw timestamp, followed by the event data
+const MDF_RECORD_ID_SIZE: usize = 2;
+const MDF_TIME_CHANNEL_SIZE: usize = 8;
+const MDF_RECORD_HEADER_SIZE: usize = MDF_RECORD_ID_SIZE + MDF_TIME_CHANNEL_SIZE;
+
+/// DAQ decoder which writes the measurement to an MDF4 file
+/// Each DAQ list is a channel group with a time channel in s since the measurement start
+/// Channel names, types, linear conversions and units are taken from the ODT entries and the A2L file (set_a2l)
+/// Only scalar channels are written, arrays, matrices and blobs are skipped
+/// mdflib supports only one open MDF file per process, a measurement start fails with an error, while another Mdf4DaqDecoder is recording
+pub struct Mdf4DaqDecoder {
+    filename: PathBuf,
+    conversions: HashMap<String, A2lConversion>,
+    odt_assembler: Option<DaqOdtAssembler>,
+    timestamp_resolution: u64,
+    daq_header_size: u8,
+    start_timestamp: u64,
+    daq_timestamp: Vec<u64>,
+    daq_recorded: Vec<bool>, // DAQ lists with at least one channel, mdflib drops empty channel groups
+    record: Vec<u8>,
+    record_count: u64,
+    open: bool,
+}
+
+impl Mdf4DaqDecoder {
+    /// Create a decoder which writes each measurement to filename, an existing file is overwritten
+    pub fn new<P: AsRef<Path>>(filename: P) -> Mdf4DaqDecoder {
+        Mdf4DaqDecoder {
+            filename: filename.as_ref().to_path_buf(),
+            conversions: HashMap::new(),
+            odt_assembler: None,
+            timestamp_resolution: 1,
+            daq_header_size: 4,
+            start_timestamp: 0,
+            daq_timestamp: Vec::new(),
+            daq_recorded: Vec::new(),
+            record: Vec::new(),
+            record_count: 0,
+            open: false,
+        }
+    }
+
+    /// Take the linear conversions and physical units of all measurements from the A2L file
+    /// Must be called before the measurement is started, channels without conversion are written with factor 1 and offset 0
+    pub fn set_a2l(&mut self, a2l_file: &a2lfile::A2lFile) {
+        self.conversions = a2l_get_measurements(a2l_file)
+            .into_iter()
+            .filter_map(|name| a2l_find_measurement_conversion(a2l_file, &name).map(|c| (name, c)))
+            .collect();
+    }
+
+    /// Number of records written in the current or last measurement
+    pub fn get_record_count(&self) -> u64 {
+        self.record_count
+    }
+
+    // Create the MDF file with one channel group for each DAQ list and write the header
+    // Returns the DAQ lists with at least one channel
+    fn create(&self, daq_odt_entries: &[Vec<OdtEntry>]) -> Result<Vec<bool>, String> {
+        let filename = CString::new(self.filename.to_string_lossy().as_bytes()).map_err(|e| e.to_string())?;
+        // Time conversion from raw timestamp ticks to s
+        let time_conv = self.timestamp_resolution as f64 * 1E-9;
+        let mut daq_recorded = vec![false; daq_odt_entries.len()];
+        unsafe {
+            if mdflib::mdfOpen(filename.as_ptr()) == 0 {
+                return Err(format!("Could not create MDF file {}", self.filename.display()));
+            }
+            for (daq, odt_entries) in daq_odt_entries.iter().enumerate() {
+                let record_len = MDF_RECORD_HEADER_SIZE + odt_entries.iter().map(|o| o.a2l_type.size as usize).sum::<usize>();
+                if mdflib::mdfCreateChannelGroup(daq as u32, record_len as u32, MDF_TIME_CHANNEL_SIZE as u32, time_conv) == 0 {
+                    return Err(format!("Could not create channel group for DAQ list {}", daq));
+                }
+                for odt_entry in odt_entries {
+                    let size = odt_entry.a2l_type.get_element_size();
+                    match odt_entry.a2l_type.encoding {
+                        A2lTypeEncoding::Blob => {
+                            info!("MDF: blob {} skipped", odt_entry.name);
+                            continue;
+                        }
+                        _ if odt_entry.a2l_type.dim > 1 => {
+                            info!("MDF: array {} skipped", odt_entry.name);
+                            continue;
+                        }
+                        A2lTypeEncoding::Float if size < 4 => {
+                            warn!("MDF: unsupported float type of {} skipped", odt_entry.name);
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    let (factor, offset, unit) = match self.conversions.get(&odt_entry.name) {
+                        Some(c) => (c.factor, c.offset, c.unit.as_str()),
+                        None => (1.0, 0.0, ""),
+                    };
+                    let name = CString::new(odt_entry.name.as_str()).map_err(|e| e.to_string())?;
+                    let unit = CString::new(unit).map_err(|e| e.to_string())?;
+                    // The byte offset includes the record id
+                    let byte_offset = (MDF_RECORD_HEADER_SIZE + odt_entry.offset as usize) as u32;
+                    if mdflib::mdfCreateChannel(name.as_ptr(), size as u8, odt_entry.a2l_type.encoding as i8, 1, byte_offset, factor, offset, unit.as_ptr()) == 0 {
+                        return Err(format!("Could not create channel {}", odt_entry.name));
+                    }
+                    daq_recorded[daq] = true;
+                }
+            }
+            if mdflib::mdfWriteHeader() == 0 {
+                return Err(format!("Could not write MDF header to {}", self.filename.display()));
+            }
+        }
+        Ok(daq_recorded)
+    }
+
+    // Finalize and close the MDF file
+    fn close(&mut self) {
+        if self.open {
+            self.open = false;
+            if unsafe { mdflib::mdfClose() } == 0 {
+                error!("MDF: could not finalize {}", self.filename.display());
+            }
+            MDF_FILE_OPEN.store(false, Ordering::Release);
+            info!("MDF: {} records written to {}", self.record_count, self.filename.display());
+        }
+    }
+}
+
+impl Drop for Mdf4DaqDecoder {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl XcpDaqDecoder for Mdf4DaqDecoder {
+    fn start(&mut self, daq_odt_entries: Vec<Vec<OdtEntry>>, timestamp_raw64: u64) {
+        self.close();
+        self.odt_assembler = Some(DaqOdtAssembler::new(&daq_odt_entries));
+        self.start_timestamp = timestamp_raw64;
+        self.daq_timestamp = vec![timestamp_raw64; daq_odt_entries.len()];
+        self.daq_recorded = vec![false; daq_odt_entries.len()];
+        self.record_count = 0;
+
+        if MDF_FILE_OPEN.swap(true, Ordering::Acquire) {
+            error!("MDF: another MDF file is open, {} not created", self.filename.display());
+            return;
+        }
+        match self.create(&daq_odt_entries) {
+            Ok(daq_recorded) => {
+                self.daq_recorded = daq_recorded;
+                self.open = true;
+            }
+            Err(e) => {
+                error!("MDF: {}", e);
+                unsafe { mdflib::mdfClose() };
+                MDF_FILE_OPEN.store(false, Ordering::Release);
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        self.close();
+    }
+
+    fn set_daq_properties(&mut self, timestamp_resolution: u64, daq_header_size: u8) {
+        self.timestamp_resolution = timestamp_resolution;
+        self.daq_header_size = daq_header_size;
+    }
+
+    fn decode(&mut self, _lost: u32, buf: &[u8]) {
+        if !self.open {
+            return;
+        }
+
+        // Decode header and raw timestamp of the first ODT
+        let (daq, odt, header_size) = if self.daq_header_size == 4 {
+            if buf.len() < 4 {
+                return;
+            }
+            (u16::from_le_bytes([buf[2], buf[3]]), buf[0], 4)
+        } else {
+            if buf.len() < 2 {
+                return;
+            }
+            (buf[1] as u16, buf[0], 2)
+        };
+        let Some(t_last) = self.daq_timestamp.get_mut(daq as usize) else {
+            warn!("MDF: unknown DAQ list {}", daq);
+            return;
+        };
+        let data = if odt == 0 {
+            let Some(timestamp_raw) = buf.get(header_size..header_size + 4) else {
+                return;
+            };
+            let timestamp_raw = u32::from_le_bytes(timestamp_raw.try_into().unwrap());
+
+            // Extend the 32 bit timestamp to 64 bit
+            let mut th = *t_last >> 32;
+            if timestamp_raw < (*t_last & 0xFFFFFFFF) as u32 {
+                th += 1;
+            }
+            *t_last = th << 32 | timestamp_raw as u64;
+            &buf[header_size + 4..]
+        } else {
+            &buf[header_size..]
+        };
+        let t = *t_last - self.start_timestamp.min(*t_last);
+
+        // Collect the ODTs of the event, write a record when complete
+        let Some(data) = self.odt_assembler.as_mut().unwrap().add(daq, odt, data) else {
+            return;
+        };
+        if !self.daq_recorded[daq as usize] {
+            return;
+        }
+        self.record.clear();
+        self.record.extend_from_slice(&daq.to_le_bytes());
+        self.record.extend_from_slice(&t.to_le_bytes());
+        self.record.extend_from_slice(data);
+        if unsafe { mdflib::mdfWriteRecord(self.record.as_ptr(), self.record.len() as u32) } == 0 {
+            error!("MDF: write to {} failed", self.filename.display());
+            return;
+        }
+        self.record_count += 1;
+    }
+}
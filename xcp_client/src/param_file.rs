@@ -0,0 +1,223 @@
+//-----------------------------------------------------------------------------
+// Module param_file
+// Parameter files with physical values of calibration parameters, used by XcpClient::apply_parameter_file and XcpClient::dump_parameters
+//
+// JSON: an object with the parameter names as keys, nested objects are flattened to "name.field", arrays to "name[i]" and "name[i][j]"
+//   { "CalPage.ampl": 2.5, "CalPage": { "curve": [1.0, 2.0] }, "CalPage.map[2][1]": 7 }
+// CSV: one "name,value" line per parameter, with an optional "name,value" header, empty lines and lines starting with '#' are ignored
+//   CalPage.ampl,2.5
+//   CalPage.map[2][1],7
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use std::error::Error;
+use std::path::Path;
+
+use crate::a2l::a2l_reader::{A2lAddr, A2lCharacteristicElement, A2lTypeEncoding};
+
+/// File format of a parameter file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterFileFormat {
+    Json,
+    Csv,
+}
+
+impl ParameterFileFormat {
+    /// Format from the file extension, .json or .csv
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ParameterFileFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(ParameterFileFormat::Json),
+            "csv" => Some(ParameterFileFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// Parameter file entry which could not be applied, with the reason
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterFileFailure {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Result of applying a parameter file
+#[derive(Debug, Clone, Default)]
+pub struct ParameterFileReport {
+    pub applied: Vec<(String, f64)>, // Names and physical values written, or validated in dry run mode
+    pub failed: Vec<ParameterFileFailure>,
+}
+
+impl ParameterFileReport {
+    /// All entries applied
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    pub(crate) fn fail<T: ToString>(&mut self, name: &str, reason: T) {
+        let reason = reason.to_string();
+        warn!("Parameter {}: {}", name, reason);
+        self.failed.push(ParameterFileFailure { name: name.to_string(), reason });
+    }
+}
+
+// Resolved parameter file entry with the raw value bytes to download
+pub(crate) struct ParameterWrite {
+    pub name: String,
+    pub value: f64,
+    pub addr: A2lAddr,
+    pub data: Vec<u8>,
+}
+
+// Flatten a JSON value into names and numeric values, non numeric values are reported as failed
+fn parse_json(text: &str, report: &mut ParameterFileReport) -> Result<Vec<(String, f64)>, Box<dyn Error>> {
+    let json: serde_json::Value = serde_json::from_str(text)?;
+    if !json.is_object() {
+        return Err("parameter file is not a JSON object".into());
+    }
+    let mut entries = Vec::new();
+    let mut stack: Vec<(String, &serde_json::Value)> = vec![(String::new(), &json)];
+    while let Some((path, value)) = stack.pop() {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map.iter().rev() {
+                    let name = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    stack.push((name, value));
+                }
+            }
+            serde_json::Value::Array(array) => {
+                for (i, value) in array.iter().enumerate().rev() {
+                    stack.push((format!("{}[{}]", path, i), value));
+                }
+            }
+            serde_json::Value::Number(n) => entries.push((path, n.as_f64().unwrap())),
+            serde_json::Value::Bool(b) => entries.push((path, if *b { 1.0 } else { 0.0 })),
+            _ => report.fail(&path, "not a numeric value"),
+        }
+    }
+    Ok(entries)
+}
+
+// Parse "name,value" lines, lines with an invalid value are reported as failed
+fn parse_csv(text: &str, report: &mut ParameterFileReport) -> Vec<(String, f64)> {
+    let mut entries = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((name, value)) = line.split_once(',') else {
+            report.fail(line, format!("line {}: expected name,value", n + 1));
+            continue;
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("name") && value.eq_ignore_ascii_case("value") {
+            continue; // Header
+        }
+        match value.parse::<f64>() {
+            Ok(v) => entries.push((name.to_string(), v)),
+            Err(_) => report.fail(name, format!("line {}: invalid value '{}'", n + 1, value)),
+        }
+    }
+    entries
+}
+
+/// Read the names and physical values of a parameter file in file order
+/// Entries with non numeric values are added to the failed entries of the report
+pub fn read_parameter_file<P: AsRef<Path>>(path: P, format: ParameterFileFormat, report: &mut ParameterFileReport) -> Result<Vec<(String, f64)>, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)?;
+    match format {
+        ParameterFileFormat::Json => parse_json(&text, report),
+        ParameterFileFormat::Csv => Ok(parse_csv(&text, report)),
+    }
+}
+
+// Integral values are written as integers
+fn format_value(value: f64) -> serde_json::Value {
+    if value.fract() == 0.0 && value.abs() < (1u64 << 53) as f64 {
+        serde_json::Value::from(value as i64)
+    } else {
+        serde_json::Value::from(value)
+    }
+}
+
+/// Write names and physical values to a parameter file, a flat JSON object or CSV with header
+pub fn write_parameter_file<P: AsRef<Path>>(path: P, format: ParameterFileFormat, entries: &[(String, f64)]) -> Result<(), Box<dyn Error>> {
+    let text = match format {
+        ParameterFileFormat::Json => {
+            let map: serde_json::Map<String, serde_json::Value> = entries.iter().map(|(name, value)| (name.clone(), format_value(*value))).collect();
+            serde_json::to_string_pretty(&serde_json::Value::Object(map))?
+        }
+        ParameterFileFormat::Csv => {
+            let mut text = String::from("name,value\n");
+            for (name, value) in entries {
+                text.push_str(&format!("{},{}\n", name, format_value(*value)));
+            }
+            text
+        }
+    };
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+/// Check a physical value against the limits of a calibration parameter element and convert it to the raw value bytes (little endian)
+/// Integer raw values are rounded to the nearest integer and must be in the range of the data type
+pub fn encode_phys_value(element: &A2lCharacteristicElement, phys: f64) -> Result<Vec<u8>, String> {
+    if !phys.is_finite() {
+        return Err(format!("value {} is not finite", phys));
+    }
+    let limits = &element.limits;
+    if phys < limits.lower || phys > limits.upper {
+        return Err(format!("value {} out of range [{}, {}]", phys, limits.lower, limits.upper));
+    }
+    let conversion = &element.conversion;
+    if conversion.factor == 0.0 {
+        return Err("conversion factor is 0".to_string());
+    }
+    let raw = (phys - conversion.offset) / conversion.factor;
+    let size = element.a2l_type.size as usize;
+    match (element.a2l_type.encoding, size) {
+        (A2lTypeEncoding::Float, 4) => Ok((raw as f32).to_le_bytes().to_vec()),
+        (A2lTypeEncoding::Float, 8) => Ok(raw.to_le_bytes().to_vec()),
+        (A2lTypeEncoding::Unsigned, 1..=8) => {
+            let raw = raw.round();
+            let max = if size == 8 { u64::MAX as f64 } else { ((1u64 << (8 * size)) - 1) as f64 };
+            if raw < 0.0 || raw > max {
+                return Err(format!("raw value {} out of range of the data type", raw));
+            }
+            Ok((raw as u64).to_le_bytes()[..size].to_vec())
+        }
+        (A2lTypeEncoding::Signed, 1..=8) => {
+            let raw = raw.round();
+            let max = if size == 8 { i64::MAX as f64 } else { ((1i64 << (8 * size - 1)) - 1) as f64 };
+            if raw < -max - 1.0 || raw > max {
+                return Err(format!("raw value {} out of range of the data type", raw));
+            }
+            Ok((raw as i64).to_le_bytes()[..size].to_vec())
+        }
+        (encoding, size) => Err(format!("data type {:?} with size {} not supported", encoding, size)),
+    }
+}
+
+/// Convert the raw value bytes (little endian) of a calibration parameter element to the physical value
+pub fn decode_phys_value(element: &A2lCharacteristicElement, data: &[u8]) -> Option<f64> {
+    let size = element.a2l_type.size as usize;
+    if data.len() < size || size == 0 || size > 8 {
+        return None;
+    }
+    let mut bytes = [0u8; 8];
+    bytes[..size].copy_from_slice(&data[..size]);
+    let unsigned = u64::from_le_bytes(bytes);
+    let raw = match (element.a2l_type.encoding, size) {
+        (A2lTypeEncoding::Float, 4) => f32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+        (A2lTypeEncoding::Float, 8) => f64::from_le_bytes(bytes),
+        (A2lTypeEncoding::Unsigned, _) => unsigned as f64,
+        (A2lTypeEncoding::Signed, _) => {
+            let shift = 64 - 8 * size as u32;
+            (((unsigned << shift) as i64) >> shift) as f64
+        }
+        _ => return None,
+    };
+    Some(raw * element.conversion.factor + element.conversion.offset)
+}
@@ -11,6 +11,7 @@ use log::{debug, error, info, trace, warn};
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use bytes::{BufMut, BytesMut};
+use futures::future::join_all;
 use parking_lot::Mutex;
 use std::collections::HashMap;
 use std::error::Error;
@@ -18,24 +19,35 @@ use std::io::Cursor;
 use std::io::Write;
 use std::net::SocketAddr;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::net::UdpSocket;
 use tokio::select;
-use tokio::sync::mpsc::{self, Receiver, Sender};
-use tokio::time::{timeout, Duration};
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender};
+use tokio::time::{timeout, Duration, Instant};
 
 #[allow(unused_imports)]
 use crate::a2l::a2l_reader::{
-    a2l_find_characteristic, a2l_find_measurement, a2l_get_characteristics, a2l_get_measurements, a2l_load, a2l_printf_info, A2lAddr, A2lLimits, A2lType,
+    a2l_find_characteristic, a2l_find_characteristic_element, a2l_find_measurement, a2l_get_characteristic_elements, a2l_get_characteristics, a2l_get_event_cycle_time_ns,
+    a2l_get_measurements, a2l_load, a2l_load_extensions, a2l_printf_info, A2lAddr, A2lExtensions, A2lLimits, A2lType, A2lTypeEncoding, A2L_BLOB_LENGTH_PREFIX_SIZE,
 };
+use crate::param_file::{decode_phys_value, encode_phys_value, read_parameter_file, write_parameter_file, ParameterFileFormat, ParameterFileReport, ParameterWrite};
 
 //--------------------------------------------------------------------------------------------------------------------------------------------------
 // XCP Parameters
 
 pub const CMD_TIMEOUT: Duration = Duration::from_secs(3);
+pub const CMD_RETRY_BACKOFF: Duration = Duration::from_millis(100); // Delay before the first retry of a command, doubled for each further retry
 
 pub const XCPTL_MAX_SEGMENT_SIZE: usize = 2048 * 2;
 
+// Max size of an ODT entry (XCPlite default XCP_MAX_ODT_ENTRY_SIZE), larger objects are split into multiple ODT entries
+pub const MAX_ODT_ENTRY_SIZE: u16 = 248;
+
+// Max number of ODT entries per ODT and max number of ODTs per DAQ list
+pub const MAX_ODT_ENTRIES: u16 = 0x7C;
+pub const MAX_ODTS: usize = 0x7C;
+
 //--------------------------------------------------------------------------------------------------------------------------------------------------
 // XCP error type
 
@@ -70,20 +82,55 @@ pub const ERROR_TL_HEADER: u8 = 0xF1;
 pub const ERROR_A2L: u8 = 0xF2;
 pub const ERROR_LIMIT: u8 = 0xF3;
 pub const ERROR_ODT_SIZE: u8 = 0xF4;
+pub const ERROR_SESSION_LOST: u8 = 0xF5;
+
+/// Calibration value out of the A2L limits of a calibration object, details of error code ERROR_LIMIT
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueOutOfRange {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub value: f64,
+}
 
 #[derive(Default)]
 pub struct XcpError {
     code: u8,
     cmd: u8,
+    value_out_of_range: Option<Box<ValueOutOfRange>>,
 }
 
 impl XcpError {
     pub fn new(code: u8, cmd: u8) -> XcpError {
-        XcpError { code, cmd }
+        XcpError {
+            code,
+            cmd,
+            value_out_of_range: None,
+        }
     }
+
+    /// Error code ERROR_LIMIT for a value out of the A2L limits of a calibration object
+    pub fn value_out_of_range(name: &str, min: f64, max: f64, value: f64) -> XcpError {
+        XcpError {
+            code: ERROR_LIMIT,
+            cmd: 0,
+            value_out_of_range: Some(Box::new(ValueOutOfRange {
+                name: name.to_string(),
+                min,
+                max,
+                value,
+            })),
+        }
+    }
+
     pub fn get_error_code(&self) -> u8 {
         self.code
     }
+
+    /// Get the calibration object, the limits and the rejected value, if the value was out of the A2L limits
+    pub fn get_value_out_of_range(&self) -> Option<&ValueOutOfRange> {
+        self.value_out_of_range.as_deref()
+    }
 }
 
 impl std::fmt::Display for XcpError {
@@ -100,11 +147,18 @@ impl std::fmt::Display for XcpError {
                 write!(f, "A2L file error")
             }
             ERROR_LIMIT => {
-                write!(f, "Calibration value limit exceeded")
+                if let Some(v) = &self.value_out_of_range {
+                    write!(f, "Calibration value limit exceeded: {} = {} out of range [{}, {}]", v.name, v.value, v.min, v.max)
+                } else {
+                    write!(f, "Calibration value limit exceeded")
+                }
             }
             ERROR_ODT_SIZE => {
                 write!(f, "ODT max size exceeded")
             }
+            ERROR_SESSION_LOST => {
+                write!(f, "Session lost")
+            }
             CRC_CMD_SYNCH => {
                 write!(f, "SYNCH")
             }
@@ -196,10 +250,13 @@ impl std::error::Error for XcpError {}
 pub const CC_CONNECT: u8 = 0xFF;
 pub const CC_DISCONNECT: u8 = 0xFE;
 pub const CC_SHORT_DOWNLOAD: u8 = 0xED;
+pub const CC_GET_STATUS: u8 = 0xFD;
 pub const CC_SYNC: u8 = 0xFC;
 pub const CC_GET_ID: u8 = 0xFA;
 pub const CC_UPLOAD: u8 = 0xF5;
 pub const CC_SHORT_UPLOAD: u8 = 0xF4;
+pub const CC_SET_MTA: u8 = 0xF6;
+pub const CC_DOWNLOAD: u8 = 0xF0;
 pub const CC_USER: u8 = 0xF1;
 pub const CC_NOP: u8 = 0xC1;
 pub const CC_SET_CAL_PAGE: u8 = 0xEB;
@@ -228,13 +285,19 @@ pub const CC_ALLOC_ODT: u8 = 0xD4;
 pub const CC_ALLOC_ODT_ENTRY: u8 = 0xD3;
 pub const CC_TIME_CORRELATION_PROPERTIES: u8 = 0xC6;
 
+// XCP event codes
+pub const EVC_SESSION_TERMINATED: u8 = 0x07;
+
 #[derive(Debug)]
 enum XcpCommand {
     Connect = CC_CONNECT as isize,
     Disconnect = CC_DISCONNECT as isize,
+    GetStatus = CC_GET_STATUS as isize,
     ShortDownload = CC_SHORT_DOWNLOAD as isize,
     Upload = CC_UPLOAD as isize,
     ShortUpload = CC_SHORT_UPLOAD as isize,
+    SetMta = CC_SET_MTA as isize,
+    Download = CC_DOWNLOAD as isize,
     User = CC_USER as isize,
     Sync = CC_SYNC as isize,
     Nop = CC_NOP as isize,
@@ -266,14 +329,44 @@ enum XcpCommand {
     TimeCorrelationProperties = CC_TIME_CORRELATION_PROPERTIES as isize,
 }
 
+// Commands which may be sent again after a response timeout without changing the result
+// Commands depending on or modifying the MTA or DAQ pointer of the server (UPLOAD, DOWNLOAD, SET_DAQ_PTR and WRITE_DAQ sequences), allocating DAQ resources or starting and stopping DAQ are not
+fn is_idempotent(code: u8) -> bool {
+    matches!(
+        code,
+        CC_CONNECT
+            | CC_GET_STATUS
+            | CC_SYNC
+            | CC_GET_ID
+            | CC_SHORT_UPLOAD
+            | CC_SHORT_DOWNLOAD
+            | CC_SET_CAL_PAGE
+            | CC_GET_CAL_PAGE
+            | CC_GET_SEGMENT_INFO
+            | CC_GET_PAGE_INFO
+            | CC_SET_SEGMENT_MODE
+            | CC_GET_SEGMENT_MODE
+            | CC_GET_DAQ_LIST_MODE
+            | CC_GET_DAQ_CLOCK
+            | CC_GET_DAQ_PROCESSOR_INFO
+            | CC_GET_DAQ_RESOLUTION_INFO
+            | CC_GET_DAQ_LIST_INFO
+            | CC_GET_DAQ_EVENT_INFO
+            | CC_TIME_CORRELATION_PROPERTIES
+    )
+}
+
 impl From<u8> for XcpCommand {
     fn from(code: u8) -> Self {
         match code {
             CC_CONNECT => XcpCommand::Connect,
             CC_DISCONNECT => XcpCommand::Disconnect,
+            CC_GET_STATUS => XcpCommand::GetStatus,
             CC_SHORT_DOWNLOAD => XcpCommand::ShortDownload,
             CC_UPLOAD => XcpCommand::Upload,
             CC_SHORT_UPLOAD => XcpCommand::ShortUpload,
+            CC_SET_MTA => XcpCommand::SetMta,
+            CC_DOWNLOAD => XcpCommand::Download,
             CC_USER => XcpCommand::User,
             CC_SYNC => XcpCommand::Sync,
             CC_NOP => XcpCommand::Nop,
@@ -319,6 +412,21 @@ pub const XCP_IDT_ASAM_URL: u8 = 3;
 pub const XCP_IDT_ASAM_UPLOAD: u8 = 4;
 pub const XCP_IDT_ASAM_EPK: u8 = 5;
 
+// XCP session status bits (GET_STATUS)
+pub const SS_STORE_CAL_REQ: u8 = 0x01;
+pub const SS_PAG_CFG_LOST: u8 = 0x02;
+pub const SS_STORE_DAQ_REQ: u8 = 0x04;
+pub const SS_CLEAR_DAQ_REQ: u8 = 0x08;
+pub const SS_DAQ_CFG_LOST: u8 = 0x10;
+pub const SS_DAQ_RUNNING: u8 = 0x40;
+pub const SS_RESUME: u8 = 0x80;
+
+// XCP resource protection bits (GET_STATUS)
+pub const RM_CAL_PAG: u8 = 0x01;
+pub const RM_DAQ: u8 = 0x04;
+pub const RM_STIM: u8 = 0x08;
+pub const RM_PGM: u8 = 0x10;
+
 // XCP get/set calibration page mode
 const CAL_PAGE_MODE_ECU: u8 = 0x01;
 const CAL_PAGE_MODE_XCP: u8 = 0x02;
@@ -372,6 +480,43 @@ impl XcpCommandBuilder {
     }
 }
 
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// Value
+// Typed value of a calibration object, decoded according to its A2L type encoding
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Bytes(Vec<u8>),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::U64(v) => std::fmt::Display::fmt(v, f),
+            Value::I64(v) => std::fmt::Display::fmt(v, f),
+            Value::F64(v) => std::fmt::Display::fmt(v, f),
+            Value::Bytes(v) => write!(f, "<{} bytes>", v.len()),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// 128 bit integers
+// A2L has no 128 bit type, u128 and i128 are registered as the two adjacent 64 bit words <name>.lo and <name>.hi
+
+/// Reassemble an u128 value from its 64 bit words
+pub fn decode_u128(lo: u64, hi: u64) -> u128 {
+    (u128::from(hi) << 64) | u128::from(lo)
+}
+
+/// Reassemble an i128 value from its 64 bit words, the high word is signed
+pub fn decode_i128(lo: u64, hi: i64) -> i128 {
+    (i128::from(hi) << 64) | i128::from(lo)
+}
+
 //--------------------------------------------------------------------------------------------------------------------------------------------------
 // CalibrationObject
 // Describes a calibration object with name, address, type, limits and caches it actual value
@@ -399,10 +544,28 @@ impl XcpCalibrationObject {
         }
     }
 
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
     pub fn get_type(&self) -> A2lType {
         self.get_type
     }
 
+    /// Get the A2L limits (LOWER_LIMIT and UPPER_LIMIT)
+    pub fn get_limits(&self) -> A2lLimits {
+        self.a2l_limits
+    }
+
+    // Check a value against the A2L limits, before it is sent to the XCP server
+    fn check_limits(&self, value: f64) -> Result<(), XcpError> {
+        if value > self.a2l_limits.upper || value < self.a2l_limits.lower || value.is_nan() {
+            warn!("{} = {} out of range [{}, {}]", self.name, value, self.a2l_limits.lower, self.a2l_limits.upper);
+            return Err(XcpError::value_out_of_range(&self.name, self.a2l_limits.lower, self.a2l_limits.upper, value));
+        }
+        Ok(())
+    }
+
     pub fn set_value(&mut self, bytes: &[u8]) {
         self.value = bytes.to_vec();
     }
@@ -433,6 +596,39 @@ impl XcpCalibrationObject {
         }
         value
     }
+
+    pub fn get_value_f64(&self) -> f64 {
+        if self.get_type.size == 4 {
+            f32::from_le_bytes(self.value[0..4].try_into().unwrap()) as f64
+        } else {
+            f64::from_le_bytes(self.value[0..8].try_into().unwrap())
+        }
+    }
+
+    /// Get the cached value, decoded according to the type encoding
+    pub fn get_typed_value(&self) -> Value {
+        match self.get_type.encoding {
+            A2lTypeEncoding::Signed => Value::I64(self.get_value_i64()),
+            A2lTypeEncoding::Unsigned => Value::U64(self.get_value_u64()),
+            A2lTypeEncoding::Float => Value::F64(self.get_value_f64()),
+            A2lTypeEncoding::Blob => Value::Bytes(self.value.clone()),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// CalibrationMismatch
+// Result of a calibration verification against golden values, a calibration object with a value out of tolerance
+
+// Relative tolerance for the comparison of float calibration objects
+pub const CALIBRATION_RELATIVE_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationMismatch {
+    pub name: String,
+    pub expected: f64,
+    pub actual: f64,
+    pub tolerance: f64, // 0.0 for integer types (exact comparison)
 }
 
 //--------------------------------------------------------------------------------------------------------------------------------------------------
@@ -493,6 +689,24 @@ pub trait XcpTextDecoder {
     }
 }
 
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// Blob decoder trait for variable length blobs (serialized data) in XCP DAQ messages
+
+pub trait XcpBlobDecoder {
+    /// Handle a variable length blob measurement from a DAQ message
+    /// Data is the blob content without length prefix, default is a hex dump
+    fn decode(&mut self, name: &str, timestamp: u64, data: &[u8]) {
+        print!(" {} = [{} bytes, t={}]", name, data.len(), timestamp);
+        for (i, b) in data.iter().enumerate() {
+            if i % 16 == 0 {
+                print!("\n  {:04X}:", i);
+            }
+            print!(" {:02X}", b);
+        }
+        println!();
+    }
+}
+
 //--------------------------------------------------------------------------------------------------------------------------------------------------
 // DAQ decoder trait for XCP DAQ messages
 
@@ -503,7 +717,138 @@ pub struct OdtEntry {
     pub name: String,
     pub a2l_type: A2lType,
     pub a2l_addr: A2lAddr,
-    pub offset: u16, // offset from data start, not including daq header and timestamp
+    pub odt: u8,     // ODT which contains the object
+    pub offset: u16, // offset in the event data (payloads of all ODTs of the daq list), not including daq header and timestamp
+}
+
+impl OdtEntry {
+    /// Get the data of a variable length blob from a DAQ packet, without the length prefix
+    /// Returns None, if the length prefix exceeds the max size of the blob
+    pub fn get_blob<'a>(&self, data: &'a [u8]) -> Option<&'a [u8]> {
+        let offset = self.offset as usize;
+        let blob = data.get(offset..offset + self.a2l_type.size as usize)?;
+        let len = u16::from_le_bytes([blob[0], blob[1]]) as usize;
+        blob.get(A2L_BLOB_LENGTH_PREFIX_SIZE..A2L_BLOB_LENGTH_PREFIX_SIZE + len)
+    }
+
+    // Decode the element i of an array or matrix from the event data (little endian)
+    fn get_element(&self, data: &[u8], i: usize) -> Option<f64> {
+        let size = self.a2l_type.get_element_size() as usize;
+        let offset = self.offset as usize + i * size;
+        let bytes = data.get(offset..offset + size)?;
+        let mut b = [0u8; 8];
+        b[..size].copy_from_slice(bytes);
+        let value = u64::from_le_bytes(b);
+        match (self.a2l_type.encoding, size) {
+            (A2lTypeEncoding::Unsigned, _) => Some(value as f64),
+            (A2lTypeEncoding::Signed, 1) => Some(value as u8 as i8 as f64),
+            (A2lTypeEncoding::Signed, 2) => Some(value as u16 as i16 as f64),
+            (A2lTypeEncoding::Signed, 4) => Some(value as u32 as i32 as f64),
+            (A2lTypeEncoding::Signed, 8) => Some(value as i64 as f64),
+            (A2lTypeEncoding::Float, 4) => Some(f32::from_bits(value as u32) as f64),
+            (A2lTypeEncoding::Float, 8) => Some(f64::from_bits(value)),
+            _ => None,
+        }
+    }
+
+    /// Decode the value of a scalar, array or matrix from the event data
+    /// Returns None for blobs, unsupported element types (FLOAT16) or if the data is too short
+    pub fn get_value(&self, data: &[u8]) -> Option<DaqValue> {
+        let t = &self.a2l_type;
+        if t.x_dim > 1 && t.y_dim > 1 {
+            let values = (0..t.dim as usize).map(|i| self.get_element(data, i)).collect::<Option<Vec<f64>>>()?;
+            Some(DaqValue::Matrix(values.chunks_exact(t.y_dim as usize).map(<[f64]>::to_vec).collect()))
+        } else if t.dim > 1 {
+            Some(DaqValue::Array((0..t.dim as usize).map(|i| self.get_element(data, i)).collect::<Option<Vec<f64>>>()?))
+        } else {
+            Some(DaqValue::Scalar(self.get_element(data, 0)?))
+        }
+    }
+}
+
+/// Decoded value of a measurement in a DAQ sample
+#[derive(Debug, Clone, PartialEq)]
+pub enum DaqValue {
+    Scalar(f64),
+    Array(Vec<f64>),
+    Matrix(Vec<Vec<f64>>), // x_dim rows of y_dim values, element [x][y]
+}
+
+impl std::fmt::Display for DaqValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DaqValue::Scalar(v) => write!(f, "{}", v),
+            DaqValue::Array(v) => write!(f, "{:?}", v),
+            DaqValue::Matrix(v) => write!(f, "{:?}", v),
+        }
+    }
+}
+
+/// Collects the ODTs of each DAQ list until the event is complete
+/// The event data is the concatenation of the ODT payloads (without daq header, timestamp and fill bytes), OdtEntry offsets refer to the event data
+pub struct DaqOdtAssembler {
+    odt_sizes: Vec<Vec<u16>>,     // Payload size of each ODT of each daq list
+    event_data: Vec<Vec<u8>>,     // Event data collected so far for each daq list
+    next_odt: Vec<Option<usize>>, // Next expected ODT for each daq list, None while waiting for ODT 0
+}
+
+impl DaqOdtAssembler {
+    pub fn new(daq_odt_entries: &[Vec<OdtEntry>]) -> DaqOdtAssembler {
+        let odt_sizes: Vec<Vec<u16>> = daq_odt_entries
+            .iter()
+            .map(|odt_entries| {
+                let mut sizes = Vec::new();
+                for odt_entry in odt_entries {
+                    let odt = odt_entry.odt as usize;
+                    if sizes.len() <= odt {
+                        sizes.resize(odt + 1, 0);
+                    }
+                    sizes[odt] += odt_entry.a2l_type.size;
+                }
+                sizes
+            })
+            .collect();
+        let daq_count = odt_sizes.len();
+        DaqOdtAssembler {
+            odt_sizes,
+            event_data: vec![Vec::new(); daq_count],
+            next_odt: vec![None; daq_count],
+        }
+    }
+
+    /// Number of ODTs of a daq list
+    pub fn get_odt_count(&self, daq: u16) -> usize {
+        self.odt_sizes.get(daq as usize).map_or(0, Vec::len)
+    }
+
+    /// Add the payload of an ODT (without daq header and timestamp)
+    /// Returns the complete event data of the daq list, when the last ODT of the event has been added
+    /// Events with missing or out of order ODTs are dropped
+    pub fn add(&mut self, daq: u16, odt: u8, data: &[u8]) -> Option<&[u8]> {
+        let daq = daq as usize;
+        let odt = odt as usize;
+        let odt_size = *self.odt_sizes.get(daq)?.get(odt)? as usize;
+        if odt == 0 {
+            self.event_data[daq].clear();
+        } else if self.next_odt[daq] != Some(odt) {
+            warn!("DAQ list {} odt {} out of sequence, event dropped", daq, odt);
+            self.next_odt[daq] = None;
+            return None;
+        }
+        let Some(payload) = data.get(..odt_size) else {
+            warn!("DAQ list {} odt {} too short, event dropped", daq, odt);
+            self.next_odt[daq] = None;
+            return None;
+        };
+        self.event_data[daq].extend_from_slice(payload);
+        if odt + 1 < self.odt_sizes[daq].len() {
+            self.next_odt[daq] = Some(odt + 1);
+            None
+        } else {
+            self.next_odt[daq] = None;
+            Some(&self.event_data[daq])
+        }
+    }
 }
 
 pub trait XcpDaqDecoder {
@@ -520,6 +865,10 @@ pub trait XcpDaqDecoder {
 
     /// Set measurement timestamp resolution in ns per raw timestamp tick and DAQ header size (2 (ODTB/DAQB or 4 (ODTB,_,DAQW))
     fn set_daq_properties(&mut self, timestamp_resolution: u64, daq_header_size: u8);
+
+    /// Latest clock synchronization with the XCP server (XcpClient::sync_clock)
+    /// To convert DAQ timestamps in ns to client wall clock time with XcpTimeSyncResult::server_to_master_ns
+    fn set_clock_sync(&mut self, _clock_sync: XcpTimeSyncResult) {}
 }
 
 //--------------------------------------------------------------------------------------------------------------------------------------------------
@@ -529,12 +878,343 @@ pub trait XcpDaqDecoder {
 pub struct XcpTaskControl {
     running: bool,
     connected: bool,
+    daq_header_size: u8,
 }
 
 impl XcpTaskControl {
     #[allow(clippy::new_without_default)]
     pub fn new() -> XcpTaskControl {
-        XcpTaskControl { running: false, connected: false }
+        XcpTaskControl {
+            running: false,
+            connected: false,
+            daq_header_size: 4,
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// XCP server status
+
+/// Server status from GET_STATUS and the current ECU calibration page from GET_CAL_PAGE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XcpStatus {
+    /// Current session status, SS_xxx bits
+    pub session_status: u8,
+    /// Current resource protection status, RM_xxx bits
+    pub resource_protection: u8,
+    /// Session configuration id (low byte)
+    pub session_cfg: u8,
+    /// Current ECU calibration page, 0 = RAM (working page), 1 = FLASH (reference page)
+    pub cal_page: u8,
+}
+
+impl XcpStatus {
+    pub fn is_daq_running(&self) -> bool {
+        self.session_status & SS_DAQ_RUNNING != 0
+    }
+
+    /// Names of all flags set in the status
+    pub fn flags(&self) -> Vec<&'static str> {
+        const SESSION_FLAGS: [(u8, &str); 7] = [
+            (SS_STORE_CAL_REQ, "STORE_CAL_REQ"),
+            (SS_PAG_CFG_LOST, "PAG_CFG_LOST"),
+            (SS_STORE_DAQ_REQ, "STORE_DAQ_REQ"),
+            (SS_CLEAR_DAQ_REQ, "CLEAR_DAQ_REQ"),
+            (SS_DAQ_CFG_LOST, "DAQ_CFG_LOST"),
+            (SS_DAQ_RUNNING, "DAQ_RUNNING"),
+            (SS_RESUME, "RESUME"),
+        ];
+        const PROTECTION_FLAGS: [(u8, &str); 4] = [(RM_CAL_PAG, "PROTECTED_CAL"), (RM_DAQ, "PROTECTED_DAQ"), (RM_STIM, "PROTECTED_STIM"), (RM_PGM, "PROTECTED_PGM")];
+
+        let mut flags: Vec<&'static str> = SESSION_FLAGS.iter().filter(|(bit, _)| self.session_status & bit != 0).map(|(_, name)| *name).collect();
+        flags.extend(PROTECTION_FLAGS.iter().filter(|(bit, _)| self.resource_protection & bit != 0).map(|(_, name)| *name));
+        flags.push(if self.cal_page == 0 { "CAL_PAGE_RAM" } else { "CAL_PAGE_ROM" });
+        flags
+    }
+}
+
+impl std::fmt::Display for XcpStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "session_status=0x{:02X} resource_protection=0x{:02X} session_cfg={} cal_page={} [{}]",
+            self.session_status,
+            self.resource_protection,
+            self.session_cfg,
+            self.cal_page,
+            self.flags().join(", ")
+        )
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// Clock synchronization
+
+/// Correlation of the XCP server DAQ clock with the client wall clock from GET_DAQ_CLOCK (XcpClient::sync_clock)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XcpTimeSyncResult {
+    /// Server DAQ clock in ns
+    pub server_ts_ns: u64,
+    /// Client wall clock in ns since the UNIX epoch, at the midpoint of the GET_DAQ_CLOCK round trip
+    pub master_ts_ns: u64,
+    /// Accuracy in ns, half of the GET_DAQ_CLOCK round trip time
+    pub accuracy_ns: u32,
+}
+
+impl XcpTimeSyncResult {
+    /// Convert a server DAQ clock value in ns to client wall clock time in ns since the UNIX epoch
+    pub fn server_to_master_ns(&self, server_ts_ns: u64) -> u64 {
+        self.master_ts_ns.wrapping_add(server_ts_ns.wrapping_sub(self.server_ts_ns))
+    }
+
+    /// Offset in ns to add to a server DAQ clock value to get the client wall clock time
+    pub fn offset_ns(&self) -> i64 {
+        self.master_ts_ns.wrapping_sub(self.server_ts_ns) as i64
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// Session loss detection and automatic reconnect
+
+/// Reconnect policy for automatic session resume
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectPolicy {
+    /// Number of reconnect attempts
+    pub retries: u32,
+    /// Delay before each reconnect attempt
+    pub backoff: Duration,
+}
+
+/// Session state changes reported to the state callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XcpSessionState {
+    Disconnected,
+    Reconnecting,
+    Resumed,
+}
+
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// Session recording
+
+/// Receiver of all XCP on UDP transport layer segments sent to and received from the XCP server
+pub trait SessionRecorder {
+    /// Segment sent to the XCP server
+    fn on_send(&mut self, data: &[u8]);
+
+    /// Segment received from the XCP server
+    fn on_recv(&mut self, data: &[u8]);
+
+    /// Flush recorded segments, called on disconnect
+    fn flush(&mut self) {}
+}
+
+/// Session recorder which discards all segments, the default of XcpClient
+pub struct NullSessionRecorder;
+
+impl SessionRecorder for NullSessionRecorder {
+    fn on_send(&mut self, _data: &[u8]) {}
+    fn on_recv(&mut self, _data: &[u8]) {}
+}
+
+// Session transcript file format
+// Header SESSION_FILE_MAGIC, followed by one record per segment: direction (u8), timestamp in ns since the start of the recording (u64), length (u32) and the segment
+// All values are little endian
+const SESSION_FILE_MAGIC: &[u8; 8] = b"XCPS\x01\x00\x00\x00";
+const SESSION_SENT: u8 = 0;
+const SESSION_RECEIVED: u8 = 1;
+const SESSION_RECORD_HEADER_SIZE: usize = 13;
+
+/// Session recorder which writes all segments with timestamps to a session transcript file, e.g. session.xcps
+pub struct FileSessionRecorder {
+    writer: std::io::BufWriter<std::fs::File>,
+    start_time: Instant,
+    failed: bool,
+}
+
+impl FileSessionRecorder {
+    /// Create the session transcript file, an existing file is overwritten
+    pub fn new(path: &Path) -> std::io::Result<FileSessionRecorder> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writer.write_all(SESSION_FILE_MAGIC)?;
+        Ok(FileSessionRecorder {
+            writer,
+            start_time: Instant::now(),
+            failed: false,
+        })
+    }
+
+    fn write_record(&mut self, direction: u8, data: &[u8]) {
+        if self.failed {
+            return;
+        }
+        let timestamp = self.start_time.elapsed().as_nanos() as u64;
+        let mut header = [0u8; SESSION_RECORD_HEADER_SIZE];
+        header[0] = direction;
+        header[1..9].copy_from_slice(&timestamp.to_le_bytes());
+        header[9..13].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        if let Err(e) = self.writer.write_all(&header).and_then(|_| self.writer.write_all(data)) {
+            error!("Session recording failed: {}", e);
+            self.failed = true;
+        }
+    }
+}
+
+impl SessionRecorder for FileSessionRecorder {
+    fn on_send(&mut self, data: &[u8]) {
+        self.write_record(SESSION_SENT, data);
+    }
+
+    fn on_recv(&mut self, data: &[u8]) {
+        self.write_record(SESSION_RECEIVED, data);
+    }
+
+    fn flush(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            error!("Session recording failed: {}", e);
+        }
+    }
+}
+
+// Recorded segment of a session transcript file
+struct SessionRecord {
+    sent: bool,
+    timestamp: Duration, // Since the start of the recording
+    data: Vec<u8>,
+}
+
+fn read_session(path: &Path) -> std::io::Result<Vec<SessionRecord>> {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), msg));
+    let bytes = std::fs::read(path)?;
+    if !bytes.starts_with(SESSION_FILE_MAGIC) {
+        return Err(invalid("not a session transcript file"));
+    }
+    let mut records = Vec::new();
+    let mut i = SESSION_FILE_MAGIC.len();
+    while i < bytes.len() {
+        if i + SESSION_RECORD_HEADER_SIZE > bytes.len() {
+            return Err(invalid("truncated record"));
+        }
+        let sent = bytes[i] == SESSION_SENT;
+        let timestamp = u64::from_le_bytes(bytes[i + 1..i + 9].try_into().unwrap());
+        let len = u32::from_le_bytes(bytes[i + 9..i + 13].try_into().unwrap()) as usize;
+        i += SESSION_RECORD_HEADER_SIZE;
+        if i + len > bytes.len() {
+            return Err(invalid("truncated record"));
+        }
+        records.push(SessionRecord {
+            sent,
+            timestamp: Duration::from_nanos(timestamp),
+            data: bytes[i..i + len].to_vec(),
+        });
+        i += len;
+    }
+    Ok(records)
+}
+
+// XCP messages of a transport layer segment, without the transport layer headers
+fn segment_messages(data: &[u8]) -> Vec<&[u8]> {
+    let mut messages = Vec::new();
+    let mut i = 0;
+    while i + 4 < data.len() {
+        let len = data[i] as usize + ((data[i + 1] as usize) << 8);
+        if len == 0 || i + 4 + len > data.len() {
+            break;
+        }
+        messages.push(&data[i + 4..i + 4 + len]);
+        i += 4 + len;
+    }
+    messages
+}
+
+// Command response as passed from the receive task to send_command, error responses are truncated to the error code
+fn response_message(message: &[u8]) -> Option<&[u8]> {
+    match message[0] {
+        0xFF => Some(message),
+        0xFE => Some(&message[..message.len().min(2)]),
+        _ => None,
+    }
+}
+
+// Recorded command with the recorded response, None if the command timed out during recording
+struct ReplayCommand<'a> {
+    timestamp: Duration,
+    segment: &'a [u8],
+    expected: Option<&'a [u8]>,
+}
+
+// Check a replayed response against the recorded response
+// Responses of GET_DAQ_CLOCK contain the server clock, only their length is checked
+fn response_matches(cmd: u8, recorded: &[u8], replayed: &[u8]) -> bool {
+    if cmd == CC_GET_DAQ_CLOCK && recorded[0] == 0xFF && replayed[0] == 0xFF {
+        recorded.len() == replayed.len()
+    } else {
+        recorded == replayed
+    }
+}
+
+// Decoders of the receive task during replay, DAQ and SERV_TEXT data is ignored
+struct ReplayDaqDecoder;
+
+impl XcpDaqDecoder for ReplayDaqDecoder {
+    fn decode(&mut self, _lost: u32, _data: &[u8]) {}
+    fn start(&mut self, _odt_entries: Vec<Vec<OdtEntry>>, _timestamp_raw64: u64) {}
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, _daq_header_size: u8) {}
+}
+
+struct ReplayTextDecoder;
+
+impl XcpTextDecoder for ReplayTextDecoder {
+    fn decode(&self, _data: &[u8]) {}
+}
+
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// XcpClientTransport
+
+// Transport to the XCP server, a UDP socket or the loopback channels of a server in the same process
+// Both carry XCP on UDP transport layer segments
+enum XcpClientTransport {
+    Udp {
+        socket: UdpSocket,
+        dest_addr: SocketAddr,
+    },
+    Loopback {
+        tx: Sender<Vec<u8>>,
+        rx: Arc<tokio::sync::Mutex<Receiver<Vec<u8>>>>, // Owned by the receive task while connected
+    },
+}
+
+impl XcpClientTransport {
+    // Send a segment to the server, or for UDP to the given source address of a received segment
+    async fn send(&self, data: &[u8], addr: Option<SocketAddr>) -> std::io::Result<()> {
+        match self {
+            XcpClientTransport::Udp { socket, dest_addr } => {
+                socket.send_to(data, addr.unwrap_or(*dest_addr)).await?;
+            }
+            XcpClientTransport::Loopback { tx, .. } => {
+                tx.send(data.to_vec()).await.map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Receive a segment from the server into buf, returns the size and for UDP the source address
+    // The loopback receiver must be locked by the caller, size 0 means the server has been closed
+    async fn recv(&self, loopback_rx: Option<&mut Receiver<Vec<u8>>>, buf: &mut [u8]) -> std::io::Result<(usize, Option<SocketAddr>)> {
+        match (self, loopback_rx) {
+            (XcpClientTransport::Udp { socket, .. }, _) => {
+                let (size, addr) = socket.recv_from(buf).await?;
+                Ok((size, Some(addr)))
+            }
+            (XcpClientTransport::Loopback { .. }, Some(rx)) => match rx.recv().await {
+                Some(data) => {
+                    let size = data.len().min(buf.len());
+                    buf[..size].copy_from_slice(&data[..size]);
+                    Ok((size, None))
+                }
+                None => Ok((0, None)),
+            },
+            (XcpClientTransport::Loopback { .. }, None) => Err(std::io::Error::from(std::io::ErrorKind::NotConnected)),
+        }
     }
 }
 
@@ -545,7 +1225,7 @@ impl XcpTaskControl {
 pub struct XcpClient {
     bind_addr: SocketAddr,
     dest_addr: SocketAddr,
-    socket: Option<Arc<UdpSocket>>,
+    transport: Option<Arc<XcpClientTransport>>,
     rx_cmd_resp: Option<mpsc::Receiver<Vec<u8>>>,
     tx_task_control: Option<mpsc::Sender<XcpTaskControl>>,
     task_control: XcpTaskControl,
@@ -556,8 +1236,27 @@ pub struct XcpClient {
     timestamp_resolution_ns: u64,
     daq_header_size: u8,
     a2l_file: Option<a2lfile::A2lFile>,
+    a2l_extensions: A2lExtensions,
+    a2l_uploaded: bool,
     calibration_objects: Vec<XcpCalibrationObject>,
     measurement_objects: Vec<XcpMeasurementObject>,
+    reconnect_policy: Option<ReconnectPolicy>,
+    daq_timeout: Option<Duration>,
+    command_timeout: Duration,
+    command_retries: u32,
+    state_callback: Option<Box<dyn Fn(XcpSessionState) + Send>>,
+    session_lost: bool,
+    session_terminated: Arc<AtomicBool>,
+    last_daq_time: Arc<Mutex<Instant>>,
+    daq_sample_count: Arc<Mutex<Vec<u32>>>, // Samples (ODT 0 packets) received for each DAQ list
+    daq_cycle_time_ns: Vec<u64>,            // Expected cycle time of each DAQ list from the A2L event, 0 if unknown
+    daq_start_time: Instant,
+    rx_stats: Arc<Mutex<(u64, u64)>>, // Received UDP packets and bytes since connect
+    loopback: bool,                   // Connected to a server in the same process with the transport created in new_loopback
+    session_recorder: Arc<Mutex<dyn SessionRecorder + Send>>,
+    clock_sync: Option<XcpTimeSyncResult>, // Latest clock synchronization
+    clock_sync_interval: Option<Duration>, // Periodic clock synchronization in check_clock_sync
+    clock_sync_time: Instant,              // Time of the latest clock synchronization
 }
 
 impl XcpClient {
@@ -569,7 +1268,7 @@ impl XcpClient {
         XcpClient {
             bind_addr,
             dest_addr,
-            socket: None,
+            transport: None,
             rx_cmd_resp: None,
             tx_task_control: None,
             task_control: XcpTaskControl::new(),
@@ -580,28 +1279,72 @@ impl XcpClient {
             timestamp_resolution_ns: 1,
             daq_header_size: 4,
             a2l_file: None,
+            a2l_extensions: A2lExtensions::default(),
+            a2l_uploaded: false,
             calibration_objects: Vec::new(),
             measurement_objects: Vec::new(),
+            reconnect_policy: None,
+            daq_timeout: None,
+            command_timeout: CMD_TIMEOUT,
+            command_retries: 0,
+            state_callback: None,
+            session_lost: false,
+            session_terminated: Arc::new(AtomicBool::new(false)),
+            last_daq_time: Arc::new(Mutex::new(Instant::now())),
+            daq_sample_count: Arc::new(Mutex::new(Vec::new())),
+            daq_cycle_time_ns: Vec::new(),
+            daq_start_time: Instant::now(),
+            rx_stats: Arc::new(Mutex::new((0, 0))),
+            loopback: false,
+            session_recorder: Arc::new(Mutex::new(NullSessionRecorder)),
+            clock_sync: None,
+            clock_sync_interval: None,
+            clock_sync_time: Instant::now(),
         }
     }
 
+    /// Create a client for a server in the same process, which was started with the loopback transport (xcp::Xcp::start_loopback_server)
+    /// tx and rx are the channels of the client side of the transport, there are no sockets involved
+    pub fn new_loopback(tx: Sender<Vec<u8>>, rx: Receiver<Vec<u8>>) -> XcpClient {
+        let unspecified: SocketAddr = (std::net::Ipv4Addr::UNSPECIFIED, 0).into();
+        let mut xcp_client = XcpClient::new(unspecified, unspecified);
+        xcp_client.transport = Some(Arc::new(XcpClientTransport::Loopback {
+            tx,
+            rx: Arc::new(tokio::sync::Mutex::new(rx)),
+        }));
+        xcp_client.loopback = true;
+        xcp_client
+    }
+
     //------------------------------------------------------------------------
     // receiver task
     // Handle incomming data from XCP server
+    #[allow(clippy::too_many_arguments)]
     async fn receive_task(
-        socket: Arc<UdpSocket>,
+        transport: Arc<XcpClientTransport>,
         tx_resp: Sender<Vec<u8>>,
         mut rx_daq_decoder: Receiver<XcpTaskControl>,
         decode_serv_text: impl XcpTextDecoder,
         decode_daq: Arc<Mutex<impl XcpDaqDecoder>>,
+        session_terminated: Arc<AtomicBool>,
+        last_daq_time: Arc<Mutex<Instant>>,
+        daq_sample_count: Arc<Mutex<Vec<u32>>>,
+        rx_stats: Arc<Mutex<(u64, u64)>>,
+        session_recorder: Arc<Mutex<dyn SessionRecorder + Send>>,
     ) -> Result<(), Box<dyn Error>> {
         let mut ctr_last: u16 = 0;
         let mut ctr_first: bool = true;
         let mut ctr_lost: u32 = 0;
 
-        let mut buf: [u8; 8000] = [0; 8000];
+        let mut buf = vec![0u8; 65536]; // Large enough for jumbo frames
         let mut task_control: Option<XcpTaskControl> = None;
 
+        // The loopback receiver is locked until the task terminates
+        let mut loopback_rx = match transport.as_ref() {
+            XcpClientTransport::Loopback { rx, .. } => Some(rx.lock().await),
+            XcpClientTransport::Udp { .. } => None,
+        };
+
         loop {
             select! {
 
@@ -636,14 +1379,20 @@ impl XcpClient {
                 } // rx_daq_decoder.recv
 
                 // Handle the data from socket
-                res = socket.recv_from(&mut buf) => {
+                res = transport.recv(loopback_rx.as_deref_mut(), &mut buf) => {
                     match res {
-                        Ok((size, _)) => {
+                        Ok((size, addr)) => {
                             // Handle the data from recv_from
                             if size == 0 {
                                 warn!("xcp_receive: socket closed");
                                 return Ok(());
                             }
+                            {
+                                let mut rx_stats = rx_stats.lock();
+                                rx_stats.0 += 1;
+                                rx_stats.1 += size as u64;
+                            }
+                            session_recorder.lock().on_recv(&buf[..size]);
 
                             let mut i: usize = 0;
                             while i < size {
@@ -681,13 +1430,25 @@ impl XcpClient {
                                     0xFD => {
                                         // Event
                                         let event_code = buf[i + 5];
-                                        warn!("xcp_receive: ignored XCP event = 0x{:0X}", event_code);
+                                        if event_code == EVC_SESSION_TERMINATED {
+                                            warn!("xcp_receive: session terminated by XCP server");
+                                            session_terminated.store(true, Ordering::Relaxed);
+                                        } else {
+                                            warn!("xcp_receive: ignored XCP event = 0x{:0X}", event_code);
+                                        }
                                     }
                                     0xFC => {
                                         // Service
                                         let service_code = buf[i + 5];
                                         if service_code == 0x01 {
                                             decode_serv_text.decode(&buf[i + 6..i + len + 4]);
+                                            // Respond with CC_NOP, the server uses SERV_TEXT as keep-alive heartbeat and expects any command
+                                            let mut cmd = XcpCommandBuilder::new(CC_NOP);
+                                            let nop = cmd.build();
+                                            session_recorder.lock().on_send(nop);
+                                            if let Err(e) = transport.send(nop, addr).await {
+                                                warn!("xcp_receive: keep-alive response failed, {}", e);
+                                            }
                                         } else {
                                             // Unknown PID
                                             warn!(
@@ -705,6 +1466,15 @@ impl XcpClient {
                                                 let mut m = decode_daq.lock(); // @@@@ Unnessesary mutex ?????
                                                 m.decode(ctr_lost, &buf[i + 4..i + 4 + len]);
                                                 ctr_lost = 0;
+                                                *last_daq_time.lock() = Instant::now();
+
+                                                // Count the samples of each DAQ list, each sample starts with ODT 0
+                                                if pid == 0 {
+                                                    let daq = if c.daq_header_size == 4 { buf[i + 6] as usize + ((buf[i + 7] as usize) << 8) } else { buf[i + 5] as usize };
+                                                    if let Some(count) = daq_sample_count.lock().get_mut(daq) {
+                                                        *count += 1;
+                                                    }
+                                                }
                                             } // running
                                         }
                                     }
@@ -728,14 +1498,35 @@ impl XcpClient {
     //------------------------------------------------------------------------
     // XCP command service
     // Send a XCP command and wait for the response
+    // Idempotent commands are sent again after a response timeout, according to the number of retries
     async fn send_command(&mut self, cmd_bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
-        //
-        // Send command
-        let socket = self.socket.as_ref().unwrap();
-        socket.send_to(cmd_bytes, self.dest_addr).await?;
-
-        // Wait for response channel with timeout
-        let res = timeout(CMD_TIMEOUT, self.rx_cmd_resp.as_mut().unwrap().recv()).await; // rx channel
+        let retries = if is_idempotent(cmd_bytes[4]) { self.command_retries } else { 0 };
+        let mut retry = 0;
+        let res = loop {
+            //
+            // Send command
+            self.session_recorder.lock().on_send(cmd_bytes);
+            self.transport.as_ref().unwrap().send(cmd_bytes, None).await?;
+
+            // Wait for response channel with timeout
+            let res = timeout(self.command_timeout, self.rx_cmd_resp.as_mut().unwrap().recv()).await; // rx channel
+            if res.is_ok() || retry >= retries {
+                break res;
+            }
+            let backoff = CMD_RETRY_BACKOFF.saturating_mul(1 << retry.min(16));
+            retry += 1;
+            warn!(
+                "xcp_command: {:?} timeout, retry {} of {} in {}ms",
+                XcpCommand::from(cmd_bytes[4]),
+                retry,
+                retries,
+                backoff.as_millis()
+            );
+            tokio::time::sleep(backoff).await;
+
+            // Discard a late response to the previous attempt
+            while self.rx_cmd_resp.as_mut().unwrap().try_recv().is_ok() {}
+        };
         match res {
             Ok(res) => {
                 match res {
@@ -763,7 +1554,8 @@ impl XcpClient {
                 }
             }
             Err(_) => {
-                // Timeout, return with XcpError
+                // Timeout, the session may be lost, return with XcpError
+                self.session_lost = true;
                 Err(Box::new(XcpError::new(ERROR_CMD_TIMEOUT, cmd_bytes[4])) as Box<dyn Error>)
             }
         }
@@ -777,27 +1569,68 @@ impl XcpClient {
         T: XcpTextDecoder + Send + 'static,
         D: XcpDaqDecoder + Send + 'static,
     {
-        // Create socket
-        let socket = UdpSocket::bind(self.bind_addr).await?;
-        self.socket = Some(Arc::new(socket));
+        self.start_receive_task(Arc::clone(&daq_decoder), text_decoder).await?;
+
+        // Keep the the DAQ decoder for measurement start
+        self.daq_decoder = Some(daq_decoder);
+
+        self.connect_session().await
+    }
+
+    // Create the socket and spawn the receive task
+    async fn start_receive_task<D, T>(&mut self, daq_decoder: Arc<Mutex<D>>, text_decoder: T) -> Result<(), Box<dyn Error>>
+    where
+        T: XcpTextDecoder + Send + 'static,
+        D: XcpDaqDecoder + Send + 'static,
+    {
+        // Create socket, the loopback transport is created in new_loopback
+        if !self.loopback {
+            let socket = UdpSocket::bind(self.bind_addr).await?;
+            self.transport = Some(Arc::new(XcpClientTransport::Udp {
+                socket,
+                dest_addr: self.dest_addr,
+            }));
+        }
 
         // Spawn a rx task to handle incomming data
         // Hand over the DAQ decoder and the text decoder
         // Create channels for command responses and DAQ state control
         {
-            let socket = Arc::clone(self.socket.as_ref().unwrap());
+            let transport = Arc::clone(self.transport.as_ref().unwrap());
             let (tx_resp, rx_resp) = mpsc::channel(1);
             self.rx_cmd_resp = Some(rx_resp); // rx XCP command response channel
             let (tx_daq, rx_daq) = mpsc::channel(3);
             self.tx_task_control = Some(tx_daq); // tx XCP DAQ control channel
-            let daq_decoder_clone = Arc::clone(&daq_decoder);
+            let session_terminated = Arc::clone(&self.session_terminated);
+            let last_daq_time = Arc::clone(&self.last_daq_time);
+            let daq_sample_count = Arc::clone(&self.daq_sample_count);
+            *self.rx_stats.lock() = (0, 0);
+            let rx_stats = Arc::clone(&self.rx_stats);
+            let session_recorder = Arc::clone(&self.session_recorder);
 
             tokio::spawn(async move {
-                let _res = XcpClient::receive_task(socket, tx_resp, rx_daq, text_decoder, daq_decoder_clone).await;
+                let _res = XcpClient::receive_task(
+                    transport,
+                    tx_resp,
+                    rx_daq,
+                    text_decoder,
+                    daq_decoder,
+                    session_terminated,
+                    last_daq_time,
+                    daq_sample_count,
+                    rx_stats,
+                    session_recorder,
+                )
+                .await;
             });
             tokio::time::sleep(Duration::from_millis(100)).await; // wait for the receive task to start
         }
 
+        Ok(())
+    }
+
+    // Connect to the server, receive task is already running
+    async fn connect_session(&mut self) -> Result<(), Box<dyn Error>> {
         // Connect
         let data = self.send_command(XcpCommandBuilder::new(CC_CONNECT).add_u8(0).build()).await?;
         assert!(data.len() >= 8);
@@ -822,17 +1655,274 @@ impl XcpClient {
         self.timestamp_resolution_ns = self.get_daq_resolution_info().await?;
 
         // Set the DAQ decoder
-        daq_decoder.lock().set_daq_properties(self.timestamp_resolution_ns, self.daq_header_size);
+        self.daq_decoder.as_ref().unwrap().lock().set_daq_properties(self.timestamp_resolution_ns, self.daq_header_size);
 
-        // Keep the the DAQ decoder for measurement start
-        self.daq_decoder = Some(daq_decoder);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------
+    // Reconnect and session resume
+
+    /// Enable automatic session resume with check_session
+    pub fn set_reconnect(&mut self, policy: ReconnectPolicy) {
+        self.reconnect_policy = Some(policy);
+    }
+
+    /// DAQ silence beyond this threshold while measurement is running is considered as loss of session
+    pub fn set_daq_timeout(&mut self, daq_timeout: Duration) {
+        self.daq_timeout = Some(daq_timeout);
+    }
+
+    /// Timeout for the response to each XCP command, default is CMD_TIMEOUT
+    pub fn set_command_timeout(&mut self, command_timeout: Duration) {
+        self.command_timeout = command_timeout;
+    }
+
+    /// Number of retries of idempotent XCP commands after a response timeout, default is 0
+    /// The delay before the first retry is CMD_RETRY_BACKOFF, doubled for each further retry
+    /// Commands depending on or modifying the MTA or DAQ pointer of the server (e.g. UPLOAD or WRITE_DAQ) are never retried
+    pub fn set_retries(&mut self, retries: u32) {
+        self.command_retries = retries;
+    }
+
+    /// Record all segments sent to and received from the XCP server with timestamps to a session transcript file, for replay with replay_session
+    /// Must be called before connect, the file is flushed on disconnect
+    pub fn record_session(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.session_recorder = Arc::new(Mutex::new(FileSessionRecorder::new(path)?));
+        Ok(())
+    }
+
+    /// Set a custom session recorder, must be called before connect
+    pub fn set_session_recorder(&mut self, session_recorder: Arc<Mutex<dyn SessionRecorder + Send>>) {
+        self.session_recorder = session_recorder;
+    }
+
+    /// Replay a session transcript file recorded with record_session against the XCP server
+    /// The recorded commands are sent with the recorded timing, each response must match the recorded response byte for byte
+    /// Responses of GET_DAQ_CLOCK contain the server clock and are only checked for length
+    /// Responses of commands which timed out during recording are not checked, keep-alive NOPs are not replayed
+    /// The client must not be connected, the replayed session is not recorded
+    pub async fn replay_session(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        // Pair each recorded command with the first response received before the next command
+        let records = read_session(path)?;
+        let mut commands: Vec<ReplayCommand> = Vec::new();
+        for record in &records {
+            for message in segment_messages(&record.data) {
+                if record.sent {
+                    if message[0] != CC_NOP {
+                        commands.push(ReplayCommand {
+                            timestamp: record.timestamp,
+                            segment: &record.data,
+                            expected: None,
+                        });
+                    }
+                } else if let Some(response) = response_message(message) {
+                    if let Some(command @ ReplayCommand { expected: None, .. }) = commands.last_mut() {
+                        command.expected = Some(response);
+                    }
+                }
+            }
+        }
+        info!("Replay {} commands from {}", commands.len(), path.display());
+
+        self.session_recorder = Arc::new(Mutex::new(NullSessionRecorder));
+        self.start_receive_task(Arc::new(Mutex::new(ReplayDaqDecoder)), ReplayTextDecoder).await?;
+        let start_time = Instant::now();
+        let mut res = Ok(());
+        for (i, command) in commands.iter().enumerate() {
+            tokio::time::sleep_until(start_time + command.timestamp).await;
+            self.transport.as_ref().unwrap().send(command.segment, None).await?;
+            let replayed = timeout(self.command_timeout, self.rx_cmd_resp.as_mut().unwrap().recv()).await.ok().flatten();
+            let cmd = command.segment[4];
+            let matches = match (command.expected, &replayed) {
+                (None, _) => true,
+                (Some(expected), Some(replayed)) => response_matches(cmd, expected, replayed),
+                (Some(_), None) => false,
+            };
+            if !matches {
+                res = Err(format!(
+                    "Replay of {} failed at command {} (0x{:02X}), expected response {:02X?}, got {:02X?}",
+                    path.display(),
+                    i,
+                    cmd,
+                    command.expected,
+                    replayed
+                )
+                .into());
+                break;
+            }
+        }
+
+        // Stop the receive task
+        self.task_control.connected = false;
+        self.task_control.running = false;
+        self.tx_task_control.as_ref().unwrap().send(self.task_control).await?;
+        res
+    }
+
+    /// Set a callback for session state changes
+    pub fn set_state_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(XcpSessionState) + Send + 'static,
+    {
+        self.state_callback = Some(Box::new(callback));
+    }
+
+    fn notify_state(&self, state: XcpSessionState) {
+        info!("XCP session state: {:?}", state);
+        if let Some(callback) = &self.state_callback {
+            callback(state);
+        }
+    }
+
+    /// Check if the session has been lost
+    /// Loss of session is a command timeout, DAQ silence beyond the DAQ timeout or the server's session terminated event
+    pub fn is_session_lost(&self) -> bool {
+        if self.session_lost || self.session_terminated.load(Ordering::Relaxed) {
+            return true;
+        }
+        if let Some(daq_timeout) = self.daq_timeout {
+            if self.task_control.running && self.last_daq_time.lock().elapsed() > daq_timeout {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Check for loss of session and resume the session according to the reconnect policy
+    /// Has to be called periodically, returns true if the session has been resumed
+    /// The A2L is verified via EPK, measurement objects are resolved again and DAQ is restarted, if it was running
+    pub async fn check_session(&mut self) -> Result<bool, Box<dyn Error>> {
+        if !self.is_session_lost() {
+            return Ok(false);
+        }
+        let policy = match self.reconnect_policy {
+            Some(policy) => policy,
+            None => return Err(Box::new(XcpError::new(ERROR_SESSION_LOST, 0)) as Box<dyn Error>),
+        };
+
+        // Stop DAQ processing in the receive task
+        self.notify_state(XcpSessionState::Disconnected);
+        let daq_running = self.task_control.running;
+        if daq_running {
+            self.task_control.running = false;
+            self.tx_task_control.as_ref().unwrap().send(self.task_control).await?;
+            self.daq_decoder.as_ref().unwrap().lock().stop();
+        }
+
+        for attempt in 1..=policy.retries {
+            self.notify_state(XcpSessionState::Reconnecting);
+            tokio::time::sleep(policy.backoff).await;
+            info!("Reconnect attempt {} of {}", attempt, policy.retries);
+            self.session_lost = false;
+            self.session_terminated.store(false, Ordering::Relaxed);
+            if self.resume_session(daq_running).await.is_ok() {
+                self.notify_state(XcpSessionState::Resumed);
+                return Ok(true);
+            }
+            warn!("Reconnect attempt {} failed", attempt);
+        }
+
+        self.session_lost = true;
+        self.notify_state(XcpSessionState::Disconnected);
+        Err(Box::new(XcpError::new(ERROR_SESSION_LOST, 0)) as Box<dyn Error>)
+    }
 
+    async fn resume_session(&mut self, daq_running: bool) -> Result<(), Box<dyn Error>> {
+        self.connect_session().await?;
+        self.verify_a2l().await?;
+        if daq_running {
+            self.start_measurement().await?;
+        }
+        Ok(())
+    }
+
+    /// Verify that the loaded A2L matches the application, by comparing the A2L version (HEADER VERSION) with the EPK in server memory at ADDR_EPK
+    /// The A2L written by the xcp-lite registry has the EPK as version
+    /// Returns false and logs a warning, if they differ, which indicates an A2L file which does not belong to the application
+    pub async fn verify_a2l_version(&mut self) -> Result<bool, Box<dyn Error>> {
+        let a2l_file = self.a2l_file.as_ref().ok_or_else(|| Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>)?;
+        let version = a2l_file.project.header.as_ref().and_then(|h| h.version.as_ref()).map(|v| v.version_identifier.clone());
+        let addr_epk = a2l_file.project.module[0].mod_par.as_ref().and_then(|m| m.addr_epk.first()).map(|a| a.address);
+        let (Some(version), Some(addr_epk)) = (version, addr_epk) else {
+            warn!("A2L has no version or no EPK address");
+            return Err(Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>);
+        };
+
+        // Upload the EPK with the length of the version, an EPK shorter than the version is out of range
+        let epk = match self.upload_epk(addr_epk, version.len()).await {
+            Ok(epk) => epk,
+            Err(e) => {
+                warn!("A2L version {} does not match the EPK, EPK upload failed: {}", version, e);
+                return Ok(false);
+            }
+        };
+        if epk != version.as_bytes() {
+            warn!("A2L version {} does not match the EPK {}", version, String::from_utf8_lossy(&epk));
+            return Ok(false);
+        }
+        info!("A2L version {} verified", version);
+        Ok(true)
+    }
+
+    // Upload len bytes of the EPK at addr_epk, in chunks limited by the max CTO size
+    async fn upload_epk(&mut self, addr_epk: u32, len: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut epk = Vec::with_capacity(len);
+        while epk.len() < len {
+            let n = (len - epk.len()).min(self.max_cto_size as usize - 1);
+            let data = self.short_upload(addr_epk + epk.len() as u32, 0, n as u8).await?;
+            if data.len() <= n {
+                return Err(Box::new(XcpError::new(ERROR_TL_HEADER, CC_SHORT_UPLOAD)) as Box<dyn Error>);
+            }
+            epk.extend_from_slice(&data[1..=n]);
+        }
+        Ok(epk)
+    }
+
+    // Verify the A2L by comparing its EPK with the EPK in server memory
+    // Upload the A2L again if the EPK has changed and the A2L was uploaded, and resolve all objects again
+    async fn verify_a2l(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(a2l_file) = &self.a2l_file else {
+            return Ok(());
+        };
+        let Some(mod_par) = &a2l_file.project.module[0].mod_par else {
+            return Ok(());
+        };
+        let (Some(epk), Some(addr_epk)) = (mod_par.epk.as_ref(), mod_par.addr_epk.first()) else {
+            return Ok(());
+        };
+        let epk = epk.identifier.clone();
+        let addr_epk = addr_epk.address;
+        if self.upload_epk(addr_epk, epk.len()).await? == epk.as_bytes() {
+            info!("EPK {} verified", epk);
+            return Ok(());
+        }
+        warn!("EPK has changed");
+        if !self.a2l_uploaded {
+            return Err(Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>);
+        }
+        self.upload_a2l(false).await?;
+
+        // Resolve all measurement and calibration objects again
+        let a2l_file = self.a2l_file.as_ref().unwrap();
+        for m in self.measurement_objects.iter_mut() {
+            let (a2l_addr, a2l_type) = a2l_find_measurement(a2l_file, &m.name).ok_or_else(|| Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>)?;
+            m.a2l_addr = a2l_addr;
+            m.a2l_type = a2l_type;
+        }
+        for c in self.calibration_objects.iter_mut() {
+            let (a2l_addr, a2l_type, a2l_limits) = a2l_find_characteristic(a2l_file, &c.name).ok_or_else(|| Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>)?;
+            c.a2l_addr = a2l_addr;
+            c.get_type = a2l_type;
+            c.a2l_limits = a2l_limits;
+        }
         Ok(())
     }
 
     //------------------------------------------------------------------------
     pub async fn disconnect(&mut self) -> Result<(), Box<dyn Error>> {
         self.send_command(XcpCommandBuilder::new(CC_DISCONNECT).add_u8(0).build()).await?;
+        self.session_recorder.lock().flush();
 
         self.task_control.connected = false;
         self.task_control.running = false;
@@ -884,6 +1974,29 @@ impl XcpClient {
         }
     }
 
+    //------------------------------------------------------------------------
+    // Get server status
+    // Session status, resource protection and session configuration id from GET_STATUS, calibration page from GET_CAL_PAGE
+    pub async fn get_status(&mut self) -> Result<XcpStatus, Box<dyn Error>> {
+        let data = self.send_command(XcpCommandBuilder::new(CC_GET_STATUS).build()).await?;
+        if data.len() < 6 {
+            error!("GET_STATUS -> invalid response {:?}", data);
+            return Err(Box::new(XcpError::new(CRC_CMD_SYNTAX, CC_GET_STATUS)) as Box<dyn Error>);
+        }
+        let session_status = data[1];
+        let resource_protection = data[2];
+        let session_cfg = data[4]; // Session configuration id is a word in little endian byte order
+        let cal_page = self.get_ecu_page().await?;
+        let status = XcpStatus {
+            session_status,
+            resource_protection,
+            session_cfg,
+            cal_page,
+        };
+        debug!("GET_STATUS -> {}", status);
+        Ok(status)
+    }
+
     //------------------------------------------------------------------------
     // Execute a XCP command with no other parameters
     pub async fn command(&mut self, command_code: u8) -> Result<Vec<u8>, Box<dyn Error>> {
@@ -956,6 +2069,21 @@ impl XcpClient {
         Ok(data)
     }
 
+    pub async fn set_mta(&mut self, addr: u32, ext: u8) -> Result<(), Box<dyn Error>> {
+        trace!("set_mta addr={}:{:08X}", ext, addr);
+        self.send_command(XcpCommandBuilder::new(CC_SET_MTA).add_u8(0).add_u8(0).add_u8(ext).add_u32(addr).build())
+            .await?;
+        Ok(())
+    }
+
+    // Download to the MTA, the MTA is incremented by the data size
+    pub async fn download(&mut self, data_bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        let len: u8 = data_bytes.len().try_into().unwrap();
+        trace!("download len={} data={:?}", len, data_bytes);
+        self.send_command(XcpCommandBuilder::new(CC_DOWNLOAD).add_u8(len).add_u8_slice(data_bytes).build()).await?;
+        Ok(())
+    }
+
     pub async fn modify_begin(&mut self) -> Result<(), Box<dyn Error>> {
         self.send_command(XcpCommandBuilder::new(CC_USER).add_u8(1).add_u8(0).add_u8(0).build()).await?;
         Ok(())
@@ -1157,6 +2285,69 @@ impl XcpClient {
         Ok(timestamp_ns)
     }
 
+    /// Synchronize the client wall clock with the server DAQ clock
+    /// The server DAQ clock from GET_DAQ_CLOCK is assigned to the client wall clock at the midpoint of the round trip
+    /// The result is stored for server_to_master_ns and passed to the DAQ decoder
+    pub async fn sync_clock(&mut self) -> Result<XcpTimeSyncResult, Box<dyn Error>> {
+        let system_time = std::time::SystemTime::now();
+        let start_time = Instant::now();
+        let server_ts_ns = self.get_daq_clock().await?;
+        let round_trip = start_time.elapsed();
+        let master_time = system_time + round_trip / 2;
+        let master_ts_ns = master_time.duration_since(std::time::UNIX_EPOCH)?.as_nanos() as u64;
+        let accuracy_ns = u32::try_from(round_trip.as_nanos() / 2).unwrap_or(u32::MAX);
+
+        let clock_sync = XcpTimeSyncResult {
+            server_ts_ns,
+            master_ts_ns,
+            accuracy_ns,
+        };
+        debug!(
+            "Clock sync: server={}ns, master={}ns, offset={}ns, accuracy={}ns",
+            server_ts_ns,
+            master_ts_ns,
+            clock_sync.offset_ns(),
+            accuracy_ns
+        );
+        self.clock_sync = Some(clock_sync);
+        self.clock_sync_time = Instant::now();
+        if let Some(daq_decoder) = self.daq_decoder.as_ref() {
+            daq_decoder.lock().set_clock_sync(clock_sync);
+        }
+        Ok(clock_sync)
+    }
+
+    /// Get the latest clock synchronization, None if sync_clock was not called yet
+    pub fn get_clock_sync(&self) -> Option<XcpTimeSyncResult> {
+        self.clock_sync
+    }
+
+    /// Convert a server DAQ clock value in ns to client wall clock time in ns since the UNIX epoch
+    /// Based on the latest clock synchronization, the value is returned unchanged if sync_clock was not called yet
+    pub fn server_to_master_ns(&self, server_ts: u64) -> u64 {
+        match self.clock_sync {
+            Some(clock_sync) => clock_sync.server_to_master_ns(server_ts),
+            None => server_ts,
+        }
+    }
+
+    /// Enable periodic clock synchronization with check_clock_sync, None disables it
+    pub fn set_clock_sync_interval(&mut self, interval: Option<Duration>) {
+        self.clock_sync_interval = interval;
+    }
+
+    /// Synchronize the clock again, if the clock sync interval has elapsed
+    /// Has to be called periodically, returns the new clock synchronization, if one was done
+    pub async fn check_clock_sync(&mut self) -> Result<Option<XcpTimeSyncResult>, Box<dyn Error>> {
+        let Some(interval) = self.clock_sync_interval else {
+            return Ok(None);
+        };
+        if self.clock_sync.is_some() && self.clock_sync_time.elapsed() < interval {
+            return Ok(None);
+        }
+        Ok(Some(self.sync_clock().await?))
+    }
+
     //-------------------------------------------------------------------------------------------------
     // A2L upload and load
 
@@ -1170,6 +2361,27 @@ impl XcpClient {
         self.a2l_loader(Some(filename), print_info).await
     }
 
+    // Upload the A2L via XCP to a file
+    async fn upload_a2l_to(&mut self, a2l_filename: &Path) -> Result<(), Box<dyn Error>> {
+        info!("Upload A2L to {}", a2l_filename.display());
+        let file = std::fs::File::create(a2l_filename)?;
+        let mut writer = std::io::BufWriter::new(file);
+        let (file_size, _) = self.get_id(XCP_IDT_ASAM_UPLOAD).await?;
+        assert!(file_size > 0);
+        let mut size = file_size;
+        while size > 0 {
+            let max_n = (self.max_cto_size as u32 - 1).min(200); // Upload data size is limited by the max CTO size
+            let n = size.min(max_n) as u8;
+            size -= n as u32;
+            let data = self.upload(n).await?;
+            trace!("xcp_client.upload: {} bytes = {:?}", data.len(), data);
+            writer.write_all(&data[1..=n as usize])?;
+        }
+        writer.flush()?;
+        info!("  Upload complete, {} bytes loaded", file_size);
+        Ok(())
+    }
+
     // Get the A2L via XCP or from file and read it
     pub async fn a2l_loader<P: AsRef<Path>>(&mut self, filename: Option<P>, print_info: bool) -> Result<(), Box<dyn Error>> {
         let a2l_filename = filename.as_ref().map(|p| p.as_ref()).unwrap_or(Path::new("xcp_client_autodetect.a2l"));
@@ -1177,24 +2389,9 @@ impl XcpClient {
         // Upload the A2L via XCP
         // Be aware the file name may be the original A2L file written by registry
         if filename.is_none() {
-            info!("Upload A2L to {}", a2l_filename.display());
-            {
-                let file = std::fs::File::create(a2l_filename)?;
-                let mut writer = std::io::BufWriter::new(file);
-                let (file_size, _) = self.get_id(XCP_IDT_ASAM_UPLOAD).await?;
-                assert!(file_size > 0);
-                let mut size = file_size;
-                while size > 0 {
-                    let n = if size > 200 { 200 } else { size as u8 };
-                    size -= n as u32;
-                    let data = self.upload(n).await?;
-                    trace!("xcp_client.upload: {} bytes = {:?}", data.len(), data);
-                    writer.write_all(&data[1..=n as usize])?;
-                }
-                writer.flush()?;
-                info!("  Upload complete, {} bytes loaded", file_size);
-            }
+            self.upload_a2l_to(a2l_filename).await?;
         }
+        self.a2l_uploaded = filename.is_none();
 
         // Read the A2L file
         //info!("Read A2L {}", a2l_filename.display());
@@ -1203,6 +2400,7 @@ impl XcpClient {
                 a2l_printf_info(&a2l_file);
             }
             self.a2l_file = Some(a2l_file);
+            self.a2l_extensions = a2l_load_extensions(a2l_filename)?;
         } else {
             error!("Could not read A2L file {}", a2l_filename.display());
             return Err(Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>);
@@ -1211,10 +2409,30 @@ impl XcpClient {
         Ok(())
     }
 
+    /// Max CTO size of the XCP server, valid after connect
+    pub fn get_max_cto_size(&self) -> u8 {
+        self.max_cto_size
+    }
+
+    /// Max DTO size of the XCP server, valid after connect
+    pub fn get_max_dto_size(&self) -> u16 {
+        self.max_dto_size
+    }
+
+    /// Number of UDP packets and bytes received from the XCP server since connect, including the XCP transport layer headers
+    pub fn get_rx_stats(&self) -> (u64, u64) {
+        *self.rx_stats.lock()
+    }
+
     pub fn get_a2l_file(&self) -> Option<&a2lfile::A2lFile> {
         self.a2l_file.as_ref()
     }
 
+    /// Raw text of the vendor specific IF_DATA blocks of a vendor in the loaded A2L file, the content between IF_DATA <vendor> and /end IF_DATA
+    pub fn get_vendor_if_data(&self, vendor: &str) -> Option<&str> {
+        self.a2l_extensions.vendor_if_data.get(vendor).map(|s| s.as_str())
+    }
+
     //------------------------------------------------------------------------
     // A2l
 
@@ -1243,7 +2461,7 @@ impl XcpClient {
             let (a2l_addr, a2l_type, a2l_limits) = res.unwrap();
 
             let mut o = XcpCalibrationObject::new(name, a2l_addr, a2l_type, a2l_limits);
-            let resp = self.short_upload(o.a2l_addr.addr, o.a2l_addr.ext, o.get_type.size).await?;
+            let resp = self.short_upload(o.a2l_addr.addr, o.a2l_addr.ext, o.get_type.size as u8).await?;
             o.value = resp[1..=o.get_type.size as usize].to_vec();
             trace!("upload {}: addr = {:?} type = {:?} limit={:?} value={:?}\n", name, a2l_addr, a2l_type, a2l_limits, o.value);
             self.calibration_objects.push(o);
@@ -1251,44 +2469,78 @@ impl XcpClient {
         }
     }
 
+    /// Set the value of an unsigned integer calibration object
+    /// Values out of the A2L limits are not sent and return XcpError ERROR_LIMIT with details in get_value_out_of_range
     pub async fn set_value_u64(&mut self, handle: XcpCalibrationObjectHandle, value: u64) -> Result<(), Box<dyn Error>> {
         let obj = &self.calibration_objects[handle.0];
-        if (value as f64) > obj.a2l_limits.upper || (value as f64) < obj.a2l_limits.lower {
-            return Err(Box::new(XcpError::new(ERROR_LIMIT, 0)) as Box<dyn Error>);
-        }
+        obj.check_limits(value as f64)?;
         let size: usize = obj.get_type.size as usize;
         let slice = &value.to_le_bytes()[0..size];
         self.short_download(obj.a2l_addr.addr, obj.a2l_addr.ext, slice).await?;
         self.calibration_objects[handle.0].set_value(slice);
         Ok(())
     }
+
+    /// Set the value of a signed integer calibration object
+    /// Values out of the A2L limits are not sent and return XcpError ERROR_LIMIT with details in get_value_out_of_range
     pub async fn set_value_i64(&mut self, handle: XcpCalibrationObjectHandle, value: i64) -> Result<(), Box<dyn Error>> {
         let obj = &self.calibration_objects[handle.0];
-        if (value as f64) > obj.a2l_limits.upper || (value as f64) < obj.a2l_limits.lower {
-            return Err(Box::new(XcpError::new(ERROR_LIMIT, 0)) as Box<dyn Error>);
-        }
+        obj.check_limits(value as f64)?;
         let size: usize = obj.get_type.size as usize;
         let slice = &value.to_le_bytes()[0..size];
         self.short_download(obj.a2l_addr.addr, obj.a2l_addr.ext, slice).await?;
         self.calibration_objects[handle.0].set_value(slice);
         Ok(())
     }
+
+    /// Set the value of a float calibration object
+    /// Values out of the A2L limits are not sent and return XcpError ERROR_LIMIT with details in get_value_out_of_range
     pub async fn set_value_f64(&mut self, handle: XcpCalibrationObjectHandle, value: f64) -> Result<(), Box<dyn Error>> {
         let obj = &self.calibration_objects[handle.0];
-        if value > obj.a2l_limits.upper || value < obj.a2l_limits.lower {
-            return Err(Box::new(XcpError::new(ERROR_LIMIT, 0)) as Box<dyn Error>);
-        }
-        let size: usize = obj.get_type.size as usize;
-        let slice = &value.to_le_bytes()[0..size];
-        self.short_download(obj.a2l_addr.addr, obj.a2l_addr.ext, slice).await?;
-        self.calibration_objects[handle.0].set_value(slice);
+        obj.check_limits(value)?;
+        let bytes = if obj.get_type.size == 4 { (value as f32).to_le_bytes().to_vec() } else { value.to_le_bytes().to_vec() };
+        self.short_download(obj.a2l_addr.addr, obj.a2l_addr.ext, &bytes).await?;
+        self.calibration_objects[handle.0].set_value(&bytes);
         Ok(())
     }
 
+    /// Set a value clamped to the A2L limits of the calibration object
+    /// The value is converted to the type encoding of the calibration object like set_value
+    pub async fn set_value_clamped_f64(&mut self, handle: XcpCalibrationObjectHandle, value: f64) -> Result<(), Box<dyn Error>> {
+        let limits = self.calibration_objects[handle.0].a2l_limits;
+        self.set_value(handle, Value::F64(value.max(limits.lower).min(limits.upper))).await
+    }
+
+    /// Set a typed value
+    /// Numeric values are converted to the type encoding of the calibration object, bytes must match its size
+    pub async fn set_value(&mut self, handle: XcpCalibrationObjectHandle, value: Value) -> Result<(), Box<dyn Error>> {
+        let obj = &self.calibration_objects[handle.0];
+        match (obj.get_type.encoding, value) {
+            (_, Value::Bytes(bytes)) => {
+                if bytes.len() != obj.get_type.size as usize {
+                    return Err(Box::new(XcpError::new(ERROR_LIMIT, 0)) as Box<dyn Error>);
+                }
+                self.short_download(obj.a2l_addr.addr, obj.a2l_addr.ext, &bytes).await?;
+                self.calibration_objects[handle.0].set_value(&bytes);
+                Ok(())
+            }
+            (A2lTypeEncoding::Blob, _) => Err(Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>),
+            (A2lTypeEncoding::Signed, Value::I64(v)) => self.set_value_i64(handle, v).await,
+            (A2lTypeEncoding::Signed, Value::U64(v)) => self.set_value_i64(handle, v.try_into().map_err(|_| XcpError::new(ERROR_LIMIT, 0))?).await,
+            (A2lTypeEncoding::Signed, Value::F64(v)) => self.set_value_i64(handle, v as i64).await,
+            (A2lTypeEncoding::Unsigned, Value::U64(v)) => self.set_value_u64(handle, v).await,
+            (A2lTypeEncoding::Unsigned, Value::I64(v)) => self.set_value_u64(handle, v.try_into().map_err(|_| XcpError::new(ERROR_LIMIT, 0))?).await,
+            (A2lTypeEncoding::Unsigned, Value::F64(v)) => self.set_value_u64(handle, v as u64).await,
+            (A2lTypeEncoding::Float, Value::F64(v)) => self.set_value_f64(handle, v).await,
+            (A2lTypeEncoding::Float, Value::U64(v)) => self.set_value_f64(handle, v as f64).await,
+            (A2lTypeEncoding::Float, Value::I64(v)) => self.set_value_f64(handle, v as f64).await,
+        }
+    }
+
     pub async fn read_value_u64(&mut self, index: XcpCalibrationObjectHandle) -> Result<u64, Box<dyn Error>> {
         let a2l_addr = self.calibration_objects[index.0].a2l_addr;
         let get_type = self.calibration_objects[index.0].get_type;
-        let resp = self.short_upload(a2l_addr.addr, a2l_addr.ext, get_type.size).await?;
+        let resp = self.short_upload(a2l_addr.addr, a2l_addr.ext, get_type.size as u8).await?;
         let value = resp[1..=get_type.size as usize].to_vec();
         self.calibration_objects[index.0].value = value;
         Ok(self.get_value_u64(index))
@@ -1305,12 +2557,174 @@ impl XcpClient {
     }
     pub fn get_value_f64(&mut self, index: XcpCalibrationObjectHandle) -> f64 {
         let obj = &self.calibration_objects[index.0];
-        let v = obj.get_value_u64();
-        #[allow(clippy::transmute_int_to_float)]
-        // @@@@ - unsafe - Test XCP client
-        unsafe {
-            std::mem::transmute(v)
+        obj.get_value_f64()
+    }
+
+    /// Get the cached value, decoded according to the type encoding of the calibration object
+    pub fn get_value(&mut self, index: XcpCalibrationObjectHandle) -> Value {
+        let obj = &self.calibration_objects[index.0];
+        obj.get_typed_value()
+    }
+
+    /// Verify the calibration values of the XCP server against golden values in JSON format
+    /// Each numeric field in the JSON object is compared with the uploaded value of the calibration object '<prefix>.<field>', nested objects are prefixed accordingly
+    /// Integer types are compared exactly, float types with relative tolerance CALIBRATION_RELATIVE_TOLERANCE
+    /// Returns the list of mismatches
+    pub async fn verify_calibration(&mut self, golden_json: &str, prefix: &str) -> Result<Vec<CalibrationMismatch>, Box<dyn Error>> {
+        let golden: serde_json::Value = serde_json::from_str(golden_json)?;
+
+        // Collect the names and expected values of all numeric fields
+        let mut fields: Vec<(String, f64)> = Vec::new();
+        let mut stack: Vec<(String, &serde_json::Value)> = vec![(prefix.to_string(), &golden)];
+        while let Some((path, value)) = stack.pop() {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map.iter().rev() {
+                        let name = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                        stack.push((name, value));
+                    }
+                }
+                serde_json::Value::Number(n) => fields.push((path, n.as_f64().unwrap())),
+                serde_json::Value::Bool(b) => fields.push((path, if *b { 1.0 } else { 0.0 })),
+                _ => warn!("verify_calibration: {} is not a numeric value, skipped", path),
+            }
+        }
+
+        // Upload and compare
+        let mut mismatches = Vec::new();
+        for (name, expected) in fields {
+            let handle = self.create_calibration_object(&name).await?;
+            let (actual, tolerance, ok) = match self.get_value(handle) {
+                Value::U64(v) => (v as f64, 0.0, v as f64 == expected),
+                Value::I64(v) => (v as f64, 0.0, v as f64 == expected),
+                Value::F64(v) => {
+                    let tolerance = CALIBRATION_RELATIVE_TOLERANCE;
+                    let ok = if expected == 0.0 { v.abs() < tolerance } else { (v - expected).abs() / expected.abs() < tolerance };
+                    (v, tolerance, ok)
+                }
+                Value::Bytes(_) => return Err(Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>),
+            };
+            if ok {
+                info!("PASS {} = {}", name, actual);
+            } else {
+                error!("FAIL {} = {}, expected {}", name, actual, expected);
+                mismatches.push(CalibrationMismatch { name, expected, actual, tolerance });
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// Apply a parameter file with physical values of calibration parameters, see module param_file for the JSON and CSV format
+    /// Names are resolved in the A2L, array elements are addressed as name[i] or name[i][j]
+    /// Values are checked against the limits and converted to raw values with the linear conversion of the calibration parameter
+    /// Values at contiguous addresses are downloaded in one batch with SET_MTA and DOWNLOAD, all batches as one atomic calibration change
+    /// In dry run mode, names and values are only validated, nothing is written
+    /// Returns a report of the applied entries (in address order) and the failed entries with the reason
+    pub async fn apply_parameter_file<P: AsRef<Path>>(&mut self, path: P, format: ParameterFileFormat, dry_run: bool) -> Result<ParameterFileReport, Box<dyn Error>> {
+        let a2l_file = self.a2l_file.as_ref().ok_or_else(|| Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>)?;
+        let mut report = ParameterFileReport::default();
+        let entries = read_parameter_file(path, format, &mut report)?;
+
+        // Resolve the names, check the limits and convert to raw values
+        let mut writes: Vec<ParameterWrite> = Vec::with_capacity(entries.len());
+        for (name, value) in entries {
+            match a2l_find_characteristic_element(a2l_file, &name).and_then(|e| Ok((e.addr, encode_phys_value(&e, value)?))) {
+                Ok((addr, data)) => writes.push(ParameterWrite { name, value, addr, data }),
+                Err(reason) => report.fail(&name, reason),
+            }
+        }
+        if dry_run {
+            report.applied = writes.into_iter().map(|w| (w.name, w.value)).collect();
+            return Ok(report);
+        }
+
+        // Group the values into batches with contiguous addresses in the same 64K segment
+        // The sort is stable, values with the same address are written in file order
+        writes.sort_by_key(|w| (w.addr.ext, w.addr.addr));
+        let mut batches: Vec<Vec<ParameterWrite>> = Vec::new();
+        for w in writes {
+            let contiguous = batches
+                .last()
+                .and_then(|b| b.last())
+                .is_some_and(|last| last.addr.ext == w.addr.ext && last.addr.addr + last.data.len() as u32 == w.addr.addr && last.addr.addr >> 16 == w.addr.addr >> 16);
+            if contiguous {
+                batches.last_mut().unwrap().push(w);
+            } else {
+                batches.push(vec![w]);
+            }
+        }
+        debug!(
+            "apply_parameter_file: {} values in {} batches",
+            batches.iter().map(|b| b.len()).sum::<usize>(),
+            batches.len()
+        );
+
+        self.modify_begin().await?;
+        for batch in batches {
+            let values: Vec<&[u8]> = batch.iter().map(|w| w.data.as_slice()).collect();
+            match self.download_values(batch[0].addr, &values).await {
+                Ok(()) => report.applied.extend(batch.into_iter().map(|w| (w.name, w.value))),
+                Err(e) => {
+                    for w in batch {
+                        report.fail(&w.name, &e);
+                    }
+                }
+            }
+        }
+        self.modify_end().await?;
+
+        info!("Parameter file applied, {} values written, {} failed", report.applied.len(), report.failed.len());
+        Ok(report)
+    }
+
+    // Download values to contiguous addresses starting at addr, with one SET_MTA and as few DOWNLOAD commands as possible
+    // Values are not split across DOWNLOAD commands
+    async fn download_values(&mut self, addr: A2lAddr, values: &[&[u8]]) -> Result<(), Box<dyn Error>> {
+        let max_size = self.max_cto_size as usize - 2;
+        self.set_mta(addr.addr, addr.ext).await?;
+        let mut data: Vec<u8> = Vec::with_capacity(max_size);
+        for value in values {
+            if data.len() + value.len() > max_size {
+                self.download(&data).await?;
+                data.clear();
+            }
+            data.extend_from_slice(value);
+        }
+        if !data.is_empty() {
+            self.download(&data).await?;
+        }
+        Ok(())
+    }
+
+    /// Upload the physical values of all calibration parameters with names containing pattern (empty pattern for all) to a parameter file
+    /// Arrays are written element by element as name[i] or name[i][j], the file can be applied again with apply_parameter_file
+    /// Calibration parameters of unsupported type (e.g. ASCII strings) are skipped
+    /// Returns the number of values written
+    pub async fn dump_parameters<P: AsRef<Path>>(&mut self, pattern: &str, path: P, format: ParameterFileFormat) -> Result<usize, Box<dyn Error>> {
+        let a2l_file = self.a2l_file.as_ref().ok_or_else(|| Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>)?;
+        let mut elements = Vec::new();
+        for name in a2l_get_characteristics(a2l_file).iter().filter(|name| name.contains(pattern)) {
+            match a2l_get_characteristic_elements(a2l_file, name) {
+                Ok(names) => {
+                    for name in names {
+                        let element = a2l_find_characteristic_element(a2l_file, &name)?;
+                        elements.push((name, element));
+                    }
+                }
+                Err(reason) => debug!("dump_parameters: {} skipped, {}", name, reason),
+            }
+        }
+
+        let mut entries: Vec<(String, f64)> = Vec::with_capacity(elements.len());
+        for (name, element) in elements {
+            let size = element.a2l_type.size;
+            let resp = self.short_upload(element.addr.addr, element.addr.ext, size as u8).await?;
+            let value = decode_phys_value(&element, &resp[1..=size as usize]).ok_or_else(|| Box::new(XcpError::new(ERROR_A2L, 0)) as Box<dyn Error>)?;
+            entries.push((name, value));
         }
+        write_parameter_file(path, format, &entries)?;
+        info!("{} parameter values dumped", entries.len());
+        Ok(entries.len())
     }
 
     //------------------------------------------------------------------------
@@ -1348,6 +2762,7 @@ impl XcpClient {
         let mut max_event: u16 = 0;
         for i in 0..signal_count {
             let event = self.measurement_objects[i].get_addr().event;
+            let odt_entry_count = self.measurement_objects[i].get_type().size.div_ceil(MAX_ODT_ENTRY_SIZE);
             if event < min_event {
                 min_event = event;
             }
@@ -1355,7 +2770,7 @@ impl XcpClient {
                 max_event = event;
             }
             let count = event_map.entry(event).or_insert(0);
-            *count += 1;
+            *count += odt_entry_count;
         }
         let event_count: u16 = event_map.len() as u16;
         info!("event/daq count = {}", event_count);
@@ -1374,69 +2789,98 @@ impl XcpClient {
         self.alloc_daq(daq_count).await?;
         debug!("alloc_daq count={}", daq_count);
 
-        // Alloc one ODT for each DAQ list (event)
-        // @@@@ Restriction: Only one ODT per DAQ list supported yet
+        // Distribute the signals of each DAQ list (event) to ODTs
+        // The first ODT contains the timestamp, objects are not split across ODTs
+        let mut daq_odts: Vec<Vec<Vec<usize>>> = Vec::with_capacity(daq_count as usize); // Measurement object indices of each ODT of each DAQ list
+        let max_odt_size = |odt: usize| self.max_dto_size - self.daq_header_size as u16 - if odt == 0 { 4 } else { 0 };
         for daq in 0..daq_count {
-            self.alloc_odt(daq, 1).await?;
-            debug!("Alloc daq={}, odt_count={}", daq, 1);
+            let event = event_list[daq as usize].0;
+            let mut odts: Vec<Vec<usize>> = vec![Vec::new()];
+            let mut odt_size: u16 = 0;
+            let mut odt_entry_count: u16 = 0;
+            for (i, m) in self.measurement_objects.iter().enumerate() {
+                if m.a2l_addr.event != event {
+                    continue; // Only add signals for the daq list event
+                }
+                let size = m.a2l_type.size;
+                let entry_count = size.div_ceil(MAX_ODT_ENTRY_SIZE);
+                // Start a new ODT, if the object does not fit into the current one
+                if !odts.last().unwrap().is_empty() && (odt_size + size > max_odt_size(odts.len() - 1) || odt_entry_count + entry_count >= MAX_ODT_ENTRIES) {
+                    odts.push(Vec::new());
+                    odt_size = 0;
+                    odt_entry_count = 0;
+                }
+                if size > max_odt_size(odts.len() - 1) || entry_count >= MAX_ODT_ENTRIES || odts.len() > MAX_ODTS {
+                    return Err(Box::new(XcpError::new(ERROR_ODT_SIZE, 0)) as Box<dyn Error>);
+                }
+                odts.last_mut().unwrap().push(i);
+                odt_size += size;
+                odt_entry_count += entry_count;
+            }
+            daq_odts.push(odts);
+        }
+
+        // Alloc the ODTs for each DAQ list (event)
+        for daq in 0..daq_count {
+            let odt_count = daq_odts[daq as usize].len() as u8;
+            self.alloc_odt(daq, odt_count).await?;
+            debug!("Alloc daq={}, odt_count={}", daq, odt_count);
         }
 
         // Alloc ODT entries (signal count) for each ODT/DAQ list
         for daq in 0..daq_count {
-            let odt_entry_count = event_list[daq as usize].1;
-            assert!(odt_entry_count < 0x7C, "odt_entry_count >= 0x7C");
-            self.alloc_odt_entries(daq, 0, odt_entry_count as u8).await?;
-            debug!("Alloc odt_entries: daq={}, odt={}, odt_entry_count={}", daq, 0, odt_entry_count);
+            for (odt, objects) in daq_odts[daq as usize].iter().enumerate() {
+                let odt_entry_count: u16 = objects.iter().map(|&i| self.measurement_objects[i].a2l_type.size.div_ceil(MAX_ODT_ENTRY_SIZE)).sum();
+                self.alloc_odt_entries(daq, odt as u8, odt_entry_count as u8).await?;
+                debug!("Alloc odt_entries: daq={}, odt={}, odt_entry_count={}", daq, odt, odt_entry_count);
+            }
         }
 
         // Create all ODT entries for each daq/event list and store information for the DAQ decoder
         for daq in 0..daq_count {
-            //
-            let event = event_list[daq as usize].0;
-            let odt = 0; // Only one odt per daq list supported yet
-            let odt_entry_count = self.measurement_objects.len();
-
-            // Create ODT entries for this daq list
             let mut odt_entries = Vec::new();
-            let mut odt_size: u16 = 0;
-            self.set_daq_ptr(daq, odt, 0).await?;
-            for odt_entry in 0..odt_entry_count {
-                let m = &mut self.measurement_objects[odt_entry];
-                let a2l_addr = m.a2l_addr;
-                if a2l_addr.event == event {
-                    // Only add signals for the daq list event
+            let mut event_offset: u16 = 0; // Offset in the event data of all ODTs
+            let odts = std::mem::take(&mut daq_odts[daq as usize]);
+            for (odt, objects) in odts.iter().enumerate() {
+                let odt = odt as u8;
+                // DTO offset of the first object, after daq header and timestamp
+                let mut dto_offset: u16 = self.daq_header_size as u16 + if odt == 0 { 4 } else { 0 };
+                self.set_daq_ptr(daq, odt, 0).await?;
+                for &i in objects {
+                    let m = &mut self.measurement_objects[i];
+                    let a2l_addr = m.a2l_addr;
                     let a2l_type: A2lType = m.a2l_type;
                     m.daq = daq;
                     m.odt = odt;
-                    m.offset = odt_size + 6;
+                    m.offset = dto_offset;
 
                     debug!(
                         "WRITE_DAQ {} daq={}, odt={},  type={:?}, size={}, ext={}, addr=0x{:08X}, offset={}",
-                        m.name,
-                        daq,
-                        odt,
-                        a2l_type.encoding,
-                        a2l_type.size,
-                        a2l_addr.ext,
-                        a2l_addr.addr,
-                        odt_size + 6
+                        m.name, daq, odt, a2l_type.encoding, a2l_type.size, a2l_addr.ext, a2l_addr.addr, dto_offset
                     );
 
                     odt_entries.push(OdtEntry {
                         name: m.name.clone(),
                         a2l_type,
                         a2l_addr,
-                        offset: odt_size,
+                        odt,
+                        offset: event_offset,
                     });
 
-                    self.write_daq(a2l_addr.ext, a2l_addr.addr, a2l_type.size).await?;
-
-                    odt_size += a2l_type.size as u16;
-                    if odt_size > self.max_dto_size - 6 {
-                        return Err(Box::new(XcpError::new(ERROR_ODT_SIZE, 0)) as Box<dyn Error>);
+                    // Objects larger than the max ODT entry size (blobs) are split into multiple ODT entries
+                    let mut size = a2l_type.size;
+                    let mut addr = a2l_addr.addr;
+                    while size > 0 {
+                        let len = size.min(MAX_ODT_ENTRY_SIZE);
+                        self.write_daq(a2l_addr.ext, addr, len as u8).await?;
+                        addr += len as u32;
+                        size -= len;
                     }
+
+                    dto_offset += a2l_type.size;
+                    event_offset += a2l_type.size;
                 }
-            } // odt_entries
+            } // odts
 
             daq_odt_entries.push(odt_entries);
         }
@@ -1458,8 +2902,16 @@ impl XcpClient {
         let daq_clock = self.get_daq_clock_raw().await?;
         self.daq_decoder.as_ref().unwrap().lock().start(daq_odt_entries, daq_clock);
 
+        // Reset the sample counters and get the expected cycle time of each DAQ list from the A2L event
+        let a2l_file = self.a2l_file.as_ref().unwrap();
+        self.daq_cycle_time_ns = event_list.iter().map(|&(event, _)| a2l_get_event_cycle_time_ns(a2l_file, event).unwrap_or(0)).collect();
+        *self.daq_sample_count.lock() = vec![0; daq_count as usize];
+        self.daq_start_time = Instant::now();
+
         // Send running=true throught the DAQ control channel to the receive task
+        *self.last_daq_time.lock() = Instant::now();
         self.task_control.running = true;
+        self.task_control.daq_header_size = self.daq_header_size;
         self.tx_task_control.as_ref().unwrap().send(self.task_control).await.unwrap();
 
         // Start DAQ
@@ -1482,6 +2934,198 @@ impl XcpClient {
         // Stop the DAQ decoder
         self.daq_decoder.as_ref().unwrap().lock().stop();
 
+        self.check_daq_rate();
+
         res
     }
+
+    // Compare the number of samples of each DAQ list with the expected number from the event cycle time
+    // Warn, if the actual DAQ rate deviates by more than 20%
+    fn check_daq_rate(&self) {
+        let elapsed_ns = self.daq_start_time.elapsed().as_nanos() as f64;
+        let daq_sample_count = self.daq_sample_count.lock();
+        for (daq, (&count, &cycle_time_ns)) in daq_sample_count.iter().zip(self.daq_cycle_time_ns.iter()).enumerate() {
+            if cycle_time_ns == 0 {
+                continue; // Sporadic event
+            }
+            let expected = elapsed_ns / cycle_time_ns as f64;
+            let deviation = (count as f64 - expected).abs() / expected;
+            if deviation > 0.2 {
+                warn!(
+                    "DAQ list {}: {} samples received, expected {:.0} from cycle time {}us, deviation {:.0}%",
+                    daq,
+                    count,
+                    expected,
+                    cycle_time_ns / 1000,
+                    deviation * 100.0
+                );
+            } else {
+                debug!("DAQ list {}: {} samples received, expected {:.0}", daq, count, expected);
+            }
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------------------------------------------------
+// MultiXcpClient
+// Parallel connection to multiple XCP servers (multi ECU test rigs)
+
+/// DAQ sample received from one of the servers of a MultiXcpClient
+/// Raw DAQ packet, transport layer header has been stripped
+#[derive(Debug, Clone)]
+pub struct DaqSample {
+    pub lost: u32,
+    pub data: Vec<u8>,
+}
+
+// DAQ decoder which tags all DAQ packets with the server index and forwards them to the merged sample stream
+struct MultiDaqDecoder {
+    server_index: usize,
+    tx_samples: UnboundedSender<(usize, DaqSample)>,
+}
+
+impl XcpDaqDecoder for MultiDaqDecoder {
+    fn decode(&mut self, lost: u32, data: &[u8]) {
+        let sample = DaqSample { lost, data: data.to_vec() };
+        if self.tx_samples.send((self.server_index, sample)).is_err() {
+            trace!("MultiDaqDecoder: merged stream of server {} closed", self.server_index);
+        }
+    }
+
+    fn start(&mut self, _odt_entries: Vec<Vec<OdtEntry>>, _timestamp_raw64: u64) {}
+
+    fn set_daq_properties(&mut self, _timestamp_resolution: u64, _daq_header_size: u8) {}
+}
+
+// Text decoder which prefixes SERV_TEXT messages with the server index
+#[derive(Debug, Clone, Copy)]
+struct MultiServTextDecoder(usize);
+
+impl XcpTextDecoder for MultiServTextDecoder {
+    fn decode(&self, data: &[u8]) {
+        print!("[SERV_TEXT {}] ", self.0);
+        let mut j = 0;
+        while j < data.len() {
+            if data[j] == 0 {
+                break;
+            }
+            print!("{}", data[j] as char);
+            j += 1;
+        }
+    }
+}
+
+/// XCP client for multiple XCP servers
+/// All operations are executed on all servers in parallel
+pub struct MultiXcpClient {
+    clients: Vec<XcpClient>,
+    tx_samples: UnboundedSender<(usize, DaqSample)>,
+    rx_samples: Option<UnboundedReceiver<(usize, DaqSample)>>,
+}
+
+impl MultiXcpClient {
+    //------------------------------------------------------------------------
+    // new
+    // Each client binds to an ephemeral local port
+    pub fn new(servers: Vec<SocketAddr>) -> MultiXcpClient {
+        let bind_addr: SocketAddr = "0.0.0.0:0".parse().unwrap();
+        MultiXcpClient::from_clients(servers.into_iter().map(|dest_addr| XcpClient::new(dest_addr, bind_addr)).collect())
+    }
+
+    /// Create a MultiXcpClient from not yet connected clients, e.g. a loopback client (XcpClient::new_loopback)
+    /// The server index is the index in clients
+    pub fn from_clients(clients: Vec<XcpClient>) -> MultiXcpClient {
+        let (tx_samples, rx_samples) = mpsc::unbounded_channel();
+        MultiXcpClient {
+            clients,
+            tx_samples,
+            rx_samples: Some(rx_samples),
+        }
+    }
+
+    /// Number of servers
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Get the client for the server with index server_index
+    pub fn get_client(&mut self, server_index: usize) -> &mut XcpClient {
+        &mut self.clients[server_index]
+    }
+
+    /// Take the merged DAQ sample stream of all servers, samples are tagged with the server index
+    /// Returns None, if the stream has already been taken
+    pub fn merged_stream(&mut self) -> Option<UnboundedReceiver<(usize, DaqSample)>> {
+        self.rx_samples.take()
+    }
+
+    // Return the first error of all servers
+    fn check_all(results: Vec<Result<(), Box<dyn Error>>>) -> Result<(), Box<dyn Error>> {
+        results.into_iter().collect()
+    }
+
+    /// Connect to all servers and upload their A2L files
+    /// The A2L file of server i is written to a2l_dir/xcp_client_autodetect_<i>.a2l
+    pub async fn connect_all(&mut self, a2l_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let tasks = self
+            .clients
+            .iter_mut()
+            .enumerate()
+            .map(|(i, client)| {
+                let daq_decoder = Arc::new(Mutex::new(MultiDaqDecoder {
+                    server_index: i,
+                    tx_samples: self.tx_samples.clone(),
+                }));
+                async move {
+                    client.connect(daq_decoder, MultiServTextDecoder(i)).await?;
+                    // Each server gets its own A2L file
+                    let a2l_path = a2l_dir.join(format!("xcp_client_autodetect_{}.a2l", i));
+                    client.upload_a2l_to(&a2l_path).await?;
+                    client.read_a2l(&a2l_path, false).await
+                }
+            });
+        MultiXcpClient::check_all(join_all(tasks).await)
+    }
+
+    /// Disconnect from all servers
+    pub async fn disconnect_all(&mut self) -> Result<(), Box<dyn Error>> {
+        let tasks = self.clients.iter_mut().map(|client| client.disconnect());
+        MultiXcpClient::check_all(join_all(tasks).await)
+    }
+
+    /// Create measurement objects for all measurement variables containing pattern and start DAQ on all servers
+    /// An empty pattern measures all variables
+    pub async fn start_measurement_all(&mut self, pattern: &str) -> Result<(), Box<dyn Error>> {
+        for (i, client) in self.clients.iter_mut().enumerate() {
+            let names: Vec<String> = client.get_measurements().into_iter().filter(|name| name.contains(pattern)).collect();
+            info!("Server {}: {} measurement objects matching '{}'", i, names.len(), pattern);
+            for name in &names {
+                client.create_measurement_object(name);
+            }
+        }
+        let tasks = self.clients.iter_mut().map(|client| client.start_measurement());
+        MultiXcpClient::check_all(join_all(tasks).await)
+    }
+
+    /// Stop DAQ on all servers
+    pub async fn stop_measurement_all(&mut self) -> Result<(), Box<dyn Error>> {
+        let tasks = self.clients.iter_mut().map(|client| client.stop_measurement());
+        MultiXcpClient::check_all(join_all(tasks).await)
+    }
+
+    /// Write the same calibration value to all servers
+    /// The value is converted to the type of the calibration object on each server
+    pub async fn set_value_all(&mut self, name: &str, value: f64) -> Result<(), Box<dyn Error>> {
+        let tasks = self.clients.iter_mut().map(|client| MultiXcpClient::set_value(client, name, value));
+        MultiXcpClient::check_all(join_all(tasks).await)
+    }
+
+    async fn set_value(client: &mut XcpClient, name: &str, value: f64) -> Result<(), Box<dyn Error>> {
+        let handle = client.create_calibration_object(name).await?;
+        client.set_value(handle, Value::F64(value)).await
+    }
 }
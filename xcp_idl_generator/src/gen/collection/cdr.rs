@@ -3,6 +3,8 @@ use crate::gen::Generator;
 use crate::gen::TypeMapping;
 use crate::types::Struct;
 use crate::STRUCTS;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
 use regex::Regex;
 use std::sync::Once;
 
@@ -31,6 +33,21 @@ impl CdrGenerator {
                     translated_type = translated_type.replace(key, value);
                 }
 
+                // Vec<T> annotated with #[idl(max_len = N)] is a bounded sequence<T, N>
+                if field.datatype().starts_with("Vec<") {
+                    match field.max_len() {
+                        Some(max_len) => {
+                            translated_type.pop();
+                            translated_type = format!("{}, {}>", translated_type, max_len);
+                        }
+                        None => warn!(
+                            "{}.{}: unbounded sequence, annotate with #[idl(max_len = N)] to specify the maximum length",
+                            input.type_name(),
+                            field.name()
+                        ),
+                    }
+                }
+
                 format!("\"{} {};\"", translated_type, field.name())
             })
             .collect::<Vec<String>>()
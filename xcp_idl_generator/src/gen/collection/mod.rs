@@ -1,6 +1,6 @@
 pub mod cdr;
 
-use super::{Generator, Struct, IDL};
+use super::{idl_hash, Generator, Struct, IDL};
 use cdr::CdrGenerator;
 use std::{
     collections::HashMap,
@@ -23,6 +23,17 @@ impl GeneratorCollection {
         Some(generator.generate(input))
     }
 
+    /// Generate the IDL annotation and a content hash of the IDL text
+    /// The hash is appended as ANNOTATION "IdlHash", the serialized data is expected to be prefixed with the hash
+    pub fn generate_with_hash(idl_type: &IDL, input: &Struct) -> Option<(String, u32)> {
+        let idl = GeneratorCollection::generate(idl_type, input)?;
+        let hash = idl_hash(&idl);
+        let annotation = format!(
+            "{idl}/begin ANNOTATION ANNOTATION_LABEL \"IdlHash\" ANNOTATION_ORIGIN \"\" /begin ANNOTATION_TEXT \"0x{hash:08X}\" /end ANNOTATION_TEXT /end ANNOTATION\n"
+        );
+        Some((annotation, hash))
+    }
+
     pub fn instance() -> &'static GeneratorCollection {
         static mut INSTANCE: Option<GeneratorCollection> = None;
         static INIT: Once = Once::new();
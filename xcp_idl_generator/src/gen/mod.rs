@@ -3,6 +3,23 @@ use std::collections::HashMap;
 
 pub mod collection;
 
+/// Content hash of a generated IDL text (32 bit FNV-1a), stable across builds and platforms
+/// Used to detect mismatches between the IDL description in the A2L and the serialized data
+pub fn idl_hash(idl: &str) -> u32 {
+    idl.bytes().fold(0x811C9DC5u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x01000193))
+}
+
+/// Check the IDL hash prefix (u32 little endian) of a serialized blob
+/// Returns the serialized data without prefix, or None if the hash does not match the expected hash
+pub fn check_idl_hash(blob: &[u8], expected_hash: u32) -> Option<&[u8]> {
+    let prefix: [u8; 4] = blob.get(0..4)?.try_into().ok()?;
+    if u32::from_le_bytes(prefix) == expected_hash {
+        Some(&blob[4..])
+    } else {
+        None
+    }
+}
+
 pub trait Generator {
     fn generate(&self, input: &Struct) -> String;
     fn type_mapping(&self) -> &'static TypeMapping;
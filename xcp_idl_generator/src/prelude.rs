@@ -1,5 +1,5 @@
 pub use crate::gen::collection::GeneratorCollection;
-pub use crate::gen::Generator;
+pub use crate::gen::{check_idl_hash, idl_hash, Generator};
 pub use crate::types::{Field, FieldList, Struct, IDL};
 
 pub use crate::{IdlGenerator, STRUCTS};
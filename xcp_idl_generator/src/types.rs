@@ -38,11 +38,16 @@ impl FieldList {
 }
 
 #[derive(Debug)]
-pub struct Field(&'static str, &'static str);
+pub struct Field(&'static str, &'static str, Option<usize>);
 
 impl Field {
     pub fn new(name: &'static str, field_type: &'static str) -> Self {
-        Field(name, field_type)
+        Field(name, field_type, None)
+    }
+
+    /// Vec<T> field with maximum number of elements, annotated with #[idl(max_len = N)]
+    pub fn with_max_len(name: &'static str, field_type: &'static str, max_len: usize) -> Self {
+        Field(name, field_type, Some(max_len))
     }
 
     pub fn name(&self) -> &str {
@@ -52,6 +57,10 @@ impl Field {
     pub fn datatype(&self) -> &str {
         self.1
     }
+
+    pub fn max_len(&self) -> Option<usize> {
+        self.2
+    }
 }
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Eq, Hash, PartialEq)]
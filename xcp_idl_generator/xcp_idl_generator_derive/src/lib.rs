@@ -2,9 +2,56 @@ extern crate proc_macro;
 
 use proc_macro::{Span, TokenStream};
 use quote::{quote, ToTokens};
-use syn::{parse_macro_input, Data, DeriveInput, Ident};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Expr, Ident, Lit, Token};
 
-#[proc_macro_derive(IdlGenerator)]
+// Item of an #[idl(...)] field attribute, e.g. max_len = 500
+struct IdlAttributeItem {
+    key: Ident,
+    value: Expr,
+}
+
+impl Parse for IdlAttributeItem {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key: Ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value: Expr = input.parse()?;
+        Ok(IdlAttributeItem { key, value })
+    }
+}
+
+// Parse the #[idl(max_len = N)] attributes of a field
+// N may be an integer literal or a constant expression, it is only allowed for Vec<T> fields
+fn parse_idl_attributes(attributes: &[Attribute], f_type_str: &str) -> syn::Result<Option<Expr>> {
+    let mut max_len = None;
+    for attribute in attributes {
+        if !attribute.path.is_ident("idl") {
+            continue;
+        }
+        let items = attribute.parse_args_with(Punctuated::<IdlAttributeItem, Token![,]>::parse_terminated)?;
+        for item in items {
+            match item.key.to_string().as_str() {
+                "max_len" => {
+                    if !f_type_str.starts_with("Vec<") {
+                        return Err(Error::new_spanned(&item.key, "max_len requires a Vec<T> field"));
+                    }
+                    if let Expr::Lit(expr_lit) = &item.value {
+                        match &expr_lit.lit {
+                            Lit::Int(lit_int) if lit_int.base10_parse::<usize>()? > 0 => {}
+                            lit => return Err(Error::new_spanned(lit, "max_len must be a positive integer")),
+                        }
+                    }
+                    max_len = Some(item.value);
+                }
+                key => return Err(Error::new_spanned(&item.key, format!("Unsupported idl attribute: {}", key))),
+            }
+        }
+    }
+    Ok(max_len)
+}
+
+#[proc_macro_derive(IdlGenerator, attributes(idl))]
 pub fn idl_generator_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let data_type = &input.ident;
@@ -13,7 +60,7 @@ pub fn idl_generator_derive(input: TokenStream) -> TokenStream {
         Data::Struct(data_struct) => {
             let register_function_name = Ident::new(&format!("register_{}", data_type), Span::call_site().into());
 
-            let field_handlers: Vec<_> = data_struct
+            let field_handlers: syn::Result<Vec<_>> = data_struct
                 .fields
                 .iter()
                 .map(|field| {
@@ -24,14 +71,27 @@ pub fn idl_generator_derive(input: TokenStream) -> TokenStream {
                     let f_type_str = field_type.into_token_stream().to_string();
                     let f_type_str = f_type_str.replace(" ", "");
 
-                    quote! {
-                        struct_fields.push(Field::new(
-                            #f_name_str,
-                            #f_type_str
-                        ));
-                    }
+                    Ok(match parse_idl_attributes(&field.attrs, &f_type_str)? {
+                        Some(max_len) => quote! {
+                            struct_fields.push(Field::with_max_len(
+                                #f_name_str,
+                                #f_type_str,
+                                (#max_len) as usize
+                            ));
+                        },
+                        None => quote! {
+                            struct_fields.push(Field::new(
+                                #f_name_str,
+                                #f_type_str
+                            ));
+                        },
+                    })
                 })
                 .collect();
+            let field_handlers = match field_handlers {
+                Ok(field_handlers) => field_handlers,
+                Err(e) => return e.to_compile_error().into(),
+            };
 
             quote! {
                 impl IdlGenerator for #data_type {
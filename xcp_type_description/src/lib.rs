@@ -19,12 +19,44 @@ pub struct FieldDescriptor {
     unit: &'static str,
     x_dim: usize,
     y_dim: usize,
-    offset: u16,
+    offset: u32,
+    x_axis_input: &'static str,
+    step: Option<f64>,
+    format: &'static str,
+    extended_limits: Option<(f64, f64)>,
+    display: &'static str,
+    ifdata: &'static [&'static str],
+    verbal_bool: Option<bool>,
+    monotone_increasing: bool,
+    function: &'static str,
+    ref_functions: &'static [&'static str],
+    slew_rate: Option<f64>,
 }
 
 impl FieldDescriptor {
     #[allow(clippy::too_many_arguments)]
-    pub fn new(name: String, datatype: &'static str, comment: &'static str, min: f64, max: f64, unit: &'static str, x_dim: usize, y_dim: usize, offset: u16) -> Self {
+    pub fn new(
+        name: String,
+        datatype: &'static str,
+        comment: &'static str,
+        min: f64,
+        max: f64,
+        unit: &'static str,
+        x_dim: usize,
+        y_dim: usize,
+        offset: u32,
+        x_axis_input: &'static str,
+        step: Option<f64>,
+        format: &'static str,
+        extended_limits: Option<(f64, f64)>,
+        display: &'static str,
+        ifdata: &'static [&'static str],
+        verbal_bool: Option<bool>,
+        monotone_increasing: bool,
+        function: &'static str,
+        ref_functions: &'static [&'static str],
+        slew_rate: Option<f64>,
+    ) -> Self {
         FieldDescriptor {
             name,
             datatype,
@@ -35,6 +67,17 @@ impl FieldDescriptor {
             y_dim,
             unit,
             offset,
+            x_axis_input,
+            step,
+            format,
+            extended_limits,
+            display,
+            ifdata,
+            verbal_bool,
+            monotone_increasing,
+            function,
+            ref_functions,
+            slew_rate,
         }
     }
 
@@ -80,13 +123,78 @@ impl FieldDescriptor {
         }
     }
 
-    pub fn offset(&self) -> u16 {
+    /// Offset of the field relative to the start of the outermost struct
+    /// Calibration segments are limited to 64 KiB, because the XCP address encodes the segment index in the high word and the offset in the low word
+    pub fn offset(&self) -> u32 {
         self.offset
     }
 
+    /// Name of the measurement signal which is the input quantity of the x axis
+    pub fn x_axis_input(&self) -> &'static str {
+        self.x_axis_input
+    }
+
+    /// Step size for incrementing and decrementing the value in the calibration tool
+    pub fn step(&self) -> Option<f64> {
+        self.step
+    }
+
+    /// Display format string, e.g. "%6.2" for 6 characters with 2 decimal places
+    pub fn format(&self) -> &'static str {
+        self.format
+    }
+
+    /// Extended limits (min, max), which enclose min and max
+    pub fn extended_limits(&self) -> Option<(f64, f64)> {
+        self.extended_limits
+    }
+
+    /// Display name shown in the tool instead of the name, which may get long for nested structs
+    pub fn display(&self) -> &'static str {
+        self.display
+    }
+
+    /// Vendor specific IF_DATA blocks, written verbatim to the A2L file
+    pub fn ifdata(&self) -> &'static [&'static str] {
+        self.ifdata
+    }
+
+    /// Show a bool as "false" and "true" in the tool (A2L COMPU_VTAB), None if not specified for the field
+    pub fn verbal_bool(&self) -> Option<bool> {
+        self.verbal_bool
+    }
+
+    /// Axis values must be strictly increasing, XCP writes which violate this are rejected or clamped
+    pub fn monotone_increasing(&self) -> bool {
+        self.monotone_increasing
+    }
+
+    /// Name of the ECU function which defines this field (A2L FUNCTION DEF_CHARACTERISTIC), empty if none
+    pub fn function(&self) -> &'static str {
+        self.function
+    }
+
+    /// Names of the ECU functions which use this field (A2L FUNCTION REF_CHARACTERISTIC)
+    pub fn ref_functions(&self) -> &'static [&'static str] {
+        self.ref_functions
+    }
+
+    /// Max rate of change in units per second of the value applied by a calibration segment with slew enabled, None if not specified for the field
+    pub fn slew_rate(&self) -> Option<f64> {
+        self.slew_rate
+    }
+
     pub fn set_name(&mut self, name: String) {
         self.name = name;
     }
+
+    pub fn set_offset(&mut self, offset: u32) {
+        self.offset = offset;
+    }
+
+    pub fn set_function(&mut self, function: &'static str) {
+        self.function = function;
+    }
 }
 
 // The XcpTypeDescription trait implementation for Rust primitives is
@@ -100,7 +208,7 @@ macro_rules! impl_xcp_type_description_for_primitive {
     };
 }
 
-impl_xcp_type_description_for_primitive!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, bool, char, String);
+impl_xcp_type_description_for_primitive!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char, String);
 
 // The implementation of the XcpTypeDescription trait for
 // arrays is also a blanket (empty) trait implementation
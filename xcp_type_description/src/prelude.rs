@@ -1,2 +1,2 @@
 pub use crate::{FieldDescriptor, StructDescriptor, XcpTypeDescription};
-pub use xcp_type_description_derive::XcpTypeDescription;
+pub use xcp_type_description_derive::{XcpProtoMeasurement, XcpTypeDescription};
@@ -0,0 +1,9 @@
+// Compile tests for the XcpTypeDescription and XcpProtoMeasurement derive macros
+// Run with TRYBUILD=overwrite to update the expected compiler output of the compile-fail cases
+
+#[test]
+fn type_description_derive() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass/*.rs");
+    t.compile_fail("tests/ui/fail/*.rs");
+}
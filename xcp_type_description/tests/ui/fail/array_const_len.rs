@@ -0,0 +1,10 @@
+use xcp_type_description::prelude::*;
+
+const LEN: usize = 4;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    a: [u8; LEN],
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(axis = "monotone_increasing")]
+    a: f32,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use xcp_type_description::prelude::*;
+
+const CAL_PAGE: CalPage = CalPage { a: 1.0 };
+
+#[derive(Clone, Copy, XcpTypeDescription)]
+#[derive(Default)]
+#[type_description(default_const = "CAL_PAGE")]
+struct CalPage {
+    a: f64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(Clone, Copy, XcpTypeDescription)]
+#[type_description(default_const = "CAL_PAGE + 1")]
+struct CalPage {
+    a: f64,
+}
+
+fn main() {}
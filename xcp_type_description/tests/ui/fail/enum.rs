@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+enum CalPage {
+    A,
+    B,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(min = "0", max = "100", extended_max = "50")]
+    a: f64,
+}
+
+fn main() {}
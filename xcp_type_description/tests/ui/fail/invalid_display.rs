@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(display = "engine speed")]
+    a: f64,
+}
+
+fn main() {}
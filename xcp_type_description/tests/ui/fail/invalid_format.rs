@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(format = "8.3f")]
+    a: f64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(function = "Idle Control")]
+    a: f64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(ifdata = "CANAPE_EXT 100")]
+    a: f64,
+}
+
+fn main() {}
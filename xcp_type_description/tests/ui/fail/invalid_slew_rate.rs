@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(slew_rate = "-1.0")]
+    a: f64,
+}
+
+fn main() {}
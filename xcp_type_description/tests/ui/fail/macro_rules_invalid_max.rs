@@ -0,0 +1,16 @@
+// Error span points into the macro_rules! invocation
+use xcp_type_description::prelude::*;
+
+macro_rules! cal_struct {
+    ($name:ident, $max:literal) => {
+        #[derive(XcpTypeDescription)]
+        struct $name {
+            #[type_description(max = $max)]
+            a: u8,
+        }
+    };
+}
+
+cal_struct!(CalPage, "1e");
+
+fn main() {}
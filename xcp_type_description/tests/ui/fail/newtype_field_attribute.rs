@@ -0,0 +1,6 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct Rpm(#[type_description(unit = "1/min")] f32);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpProtoMeasurement)]
+struct TestData {
+    #[prost(uint32, repeated, tag = "1")]
+    samples: Vec<u32>,
+}
+
+fn main() {}
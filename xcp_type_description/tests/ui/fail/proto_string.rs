@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpProtoMeasurement)]
+struct TestData {
+    #[prost(string, tag = "1")]
+    name: String,
+}
+
+fn main() {}
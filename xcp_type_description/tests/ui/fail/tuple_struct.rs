@@ -0,0 +1,6 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage(u8, u16);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(verbal_bool = "true")]
+    a: u8,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(x_axis_input = "speed")]
+    a: u8,
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+// Attributes wrapped in cfg_attr
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[cfg_attr(all(), type_description(comment = "enabled", unit = "m"))]
+    #[cfg_attr(any(), type_description(comment = "disabled"))]
+    a: u32,
+    #[cfg_attr(any(), type_description(unknown_key = "ignored"))]
+    b: u8,
+}
+
+fn main() {
+    let cal_page = CalPage { a: 0, b: 0 };
+    let d: Vec<FieldDescriptor> = cal_page.type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].comment(), "enabled");
+    assert_eq!(d[0].unit(), "m");
+    assert_eq!(d[1].comment(), "");
+}
@@ -0,0 +1,20 @@
+// impl Default generated from the const default page
+use xcp_type_description::prelude::*;
+
+mod pages {
+    pub const CAL_PAGE: super::CalPage = super::CalPage { a: 1.5, b: [1, 2, 3] };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, XcpTypeDescription)]
+#[type_description(default_const = "pages::CAL_PAGE")]
+struct CalPage {
+    #[type_description(min = "0", max = "100")]
+    a: f64,
+    b: [u8; 3],
+}
+
+fn main() {
+    assert_eq!(CalPage::default(), pages::CAL_PAGE);
+    let d: Vec<FieldDescriptor> = CalPage::default().type_description().unwrap().into_iter().collect();
+    assert_eq!(d.len(), 2);
+}
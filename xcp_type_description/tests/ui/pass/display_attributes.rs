@@ -0,0 +1,26 @@
+// Step size, display format, extended limits, display name and IF_DATA, a missing extended limit defaults to min or max
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(min = "0", max = "100", step = "0.5", format = "%8.3")]
+    #[type_description(extended_min = "-10", display = "A")]
+    #[type_description(ifdata = "/begin IF_DATA CANAPE_EXT 100 /end IF_DATA", ifdata = "/begin IF_DATA ETK 0 /end IF_DATA")]
+    a: f64,
+    b: u8,
+}
+
+fn main() {
+    let cal_page = CalPage { a: 0.0, b: 0 };
+    let d: Vec<FieldDescriptor> = cal_page.type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].step(), Some(0.5));
+    assert_eq!(d[0].format(), "%8.3");
+    assert_eq!(d[0].extended_limits(), Some((-10.0, 100.0)));
+    assert_eq!(d[0].display(), "A");
+    assert_eq!(d[0].ifdata(), ["/begin IF_DATA CANAPE_EXT 100 /end IF_DATA", "/begin IF_DATA ETK 0 /end IF_DATA"]);
+    assert_eq!(d[1].step(), None);
+    assert_eq!(d[1].format(), "");
+    assert_eq!(d[1].extended_limits(), None);
+    assert_eq!(d[1].display(), "");
+    assert!(d[1].ifdata().is_empty());
+}
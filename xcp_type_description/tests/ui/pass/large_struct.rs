@@ -0,0 +1,37 @@
+// Field offsets of structs larger than 64 KiB, also in nested structs
+use xcp_type_description::prelude::*;
+
+#[derive(Clone, Copy, XcpTypeDescription)]
+#[repr(C)]
+struct Map {
+    values: [[f32; 64]; 64],
+    scale: f32,
+}
+
+#[derive(XcpTypeDescription)]
+#[repr(C)]
+struct LargePage {
+    map1: Map,
+    map2: Map,
+    map3: Map,
+    map4: Map,
+    map5: Map,
+}
+
+fn main() {
+    let map = Map {
+        values: [[0.0; 64]; 64],
+        scale: 1.0,
+    };
+    let page = Box::new(LargePage {
+        map1: map,
+        map2: map,
+        map3: map,
+        map4: map,
+        map5: map,
+    });
+    let d: Vec<FieldDescriptor> = page.type_description().unwrap().into_iter().collect();
+    let scale5 = d.iter().rfind(|f| f.name() == "LargePage.Map.scale").unwrap();
+    assert!(scale5.offset() > 0xFFFF);
+    assert_eq!(scale5.offset() as usize, 4 * std::mem::size_of::<Map>() + 64 * 64 * 4);
+}
@@ -0,0 +1,50 @@
+// Struct generated by macro_rules!, types and array lengths arrive wrapped in None-delimited groups
+use xcp_type_description::prelude::*;
+
+macro_rules! cal_struct {
+    ($name:ident { $( $(#[$m:meta])* $field:ident : $t:ty ),* $(,)? }) => {
+        #[derive(XcpTypeDescription)]
+        struct $name {
+            $( $(#[$m])* $field: $t ),*
+        }
+    };
+}
+
+macro_rules! cal_map {
+    ($name:ident, $t:ty, $x:expr, $y:expr) => {
+        #[derive(XcpTypeDescription)]
+        struct $name {
+            #[type_description(comment = "map", unit = "m")]
+            map: [[$t; $x]; $y],
+        }
+    };
+}
+
+cal_struct!(CalPage {
+    #[type_description(comment = "value", min = "-10", max = "10")]
+    value: i16,
+    curve: [f32; 8],
+});
+
+cal_map!(CalMap, u8, 4, 2);
+
+fn main() {
+    let cal_page = CalPage { value: 0, curve: [0.0; 8] };
+    let d: Vec<FieldDescriptor> = cal_page.type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].name(), "CalPage.value");
+    assert_eq!(d[0].datatype(), "i16");
+    assert_eq!(d[0].characteristic_type(), "VALUE");
+    assert_eq!(d[0].comment(), "value");
+    assert_eq!((d[0].min(), d[0].max()), (-10.0, 10.0));
+    assert_eq!(d[1].name(), "CalPage.curve");
+    assert_eq!(d[1].datatype(), "[f32; 8]");
+    assert_eq!(d[1].characteristic_type(), "CURVE");
+    assert_eq!((d[1].x_dim(), d[1].y_dim()), (8, 0));
+
+    let cal_map = CalMap { map: [[0; 4]; 2] };
+    let d: Vec<FieldDescriptor> = cal_map.type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].datatype(), "[[u8; 4]; 2]");
+    assert_eq!(d[0].characteristic_type(), "MAP");
+    assert_eq!((d[0].x_dim(), d[0].y_dim()), (2, 4));
+    assert_eq!(d[0].unit(), "m");
+}
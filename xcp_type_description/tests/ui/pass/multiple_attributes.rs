@@ -0,0 +1,24 @@
+// Keys in any order, spread over multiple attributes, the last occurrence wins
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    #[type_description(unit = "s", max = "100")]
+    #[doc = "other attributes are ignored"]
+    #[type_description(comment = "first", min = "1")]
+    #[type_description(comment = "last")]
+    a: f64,
+    #[type_description(x_axis_input = "speed")]
+    #[type_description(unit = "m")]
+    curve: [f32; 16],
+}
+
+fn main() {
+    let cal_page = CalPage { a: 0.0, curve: [0.0; 16] };
+    let d: Vec<FieldDescriptor> = cal_page.type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].comment(), "last");
+    assert_eq!(d[0].unit(), "s");
+    assert_eq!((d[0].min(), d[0].max()), (1.0, 100.0));
+    assert_eq!(d[1].x_axis_input(), "speed");
+    assert_eq!(d[1].unit(), "m");
+}
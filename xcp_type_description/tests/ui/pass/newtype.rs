@@ -0,0 +1,39 @@
+// A newtype is described under the name of the struct, with the struct level attributes
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+#[type_description(comment = "Engine speed", unit = "1/min", min = "0", max = "8000")]
+struct Rpm(f32);
+
+#[derive(XcpTypeDescription)]
+#[type_description(unit = "Nm")]
+struct TorqueCurve([f32; 16]);
+
+#[derive(XcpTypeDescription)]
+struct Limits {
+    low: u8,
+    high: u8,
+}
+
+#[derive(XcpTypeDescription)]
+struct EngineLimits(Limits);
+
+fn main() {
+    let d: Vec<FieldDescriptor> = Rpm(0.0).type_description().unwrap().into_iter().collect();
+    assert_eq!(d.len(), 1);
+    assert_eq!(d[0].name(), "Rpm");
+    assert_eq!(d[0].datatype(), "f32");
+    assert_eq!(d[0].comment(), "Engine speed");
+    assert_eq!(d[0].unit(), "1/min");
+    assert_eq!((d[0].min(), d[0].max()), (0.0, 8000.0));
+    assert_eq!(d[0].offset(), 0);
+
+    let d: Vec<FieldDescriptor> = TorqueCurve([0.0; 16]).type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].name(), "TorqueCurve");
+    assert_eq!((d[0].x_dim(), d[0].y_dim()), (16, 0));
+
+    let d: Vec<FieldDescriptor> = EngineLimits(Limits { low: 0, high: 0 }).type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].name(), "EngineLimits.low");
+    assert_eq!(d[1].name(), "EngineLimits.high");
+    assert_eq!(d[1].offset(), 1);
+}
@@ -0,0 +1,32 @@
+// Prost message fields are described with the types of their #[prost(...)] attributes
+use xcp_type_description::prelude::*;
+
+#[derive(XcpProtoMeasurement)]
+struct TestData {
+    #[prost(fixed32, tag = "1")]
+    counter: u32,
+    #[prost(double, tag = "2")]
+    signal: f64,
+    #[prost(bytes = "vec", tag = "3")]
+    payload: Vec<u8>,
+    #[prost(float, repeated, tag = "4")]
+    #[xcp(max_len = "8")]
+    samples: Vec<f32>,
+}
+
+fn main() {
+    let test_data = TestData {
+        counter: 0,
+        signal: 0.0,
+        payload: Vec::new(),
+        samples: Vec::new(),
+    };
+    let d: Vec<FieldDescriptor> = test_data.type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].name(), "TestData.counter");
+    assert_eq!(d[0].datatype(), "u32");
+    assert_eq!(d[1].datatype(), "f64");
+    assert_eq!(d[1].offset() as usize, std::mem::offset_of!(TestData, signal));
+    assert_eq!(d[2].datatype(), "blob");
+    assert_eq!(d[3].datatype(), "f32");
+    assert_eq!(d[3].x_dim(), 8);
+}
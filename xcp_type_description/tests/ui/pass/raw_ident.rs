@@ -0,0 +1,17 @@
+// Raw identifiers are registered without the r# prefix
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct r#CalPage {
+    #[type_description(comment = "type")]
+    r#type: u8,
+    r#match: [i32; 4],
+}
+
+fn main() {
+    let cal_page = CalPage { r#type: 0, r#match: [0; 4] };
+    let d: Vec<FieldDescriptor> = cal_page.type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].name(), "CalPage.type");
+    assert_eq!(d[1].name(), "CalPage.match");
+    assert_eq!(d[1].x_dim(), 4);
+}
@@ -0,0 +1,17 @@
+// 128 bit integers are registered with the limits of the 64 bit words
+use xcp_type_description::prelude::*;
+
+#[derive(XcpTypeDescription)]
+struct CalPage {
+    nonce: u128,
+    counter: i128,
+}
+
+fn main() {
+    let cal_page = CalPage { nonce: 0, counter: 0 };
+    let d: Vec<FieldDescriptor> = cal_page.type_description().unwrap().into_iter().collect();
+    assert_eq!(d[0].datatype(), "u128");
+    assert_eq!(d[0].min(), 0.0);
+    assert_eq!(d[1].datatype(), "i128");
+    assert_eq!(d[1].min(), -1e12);
+}
@@ -1,10 +1,11 @@
 extern crate proc_macro;
 
+mod proto;
 mod utils;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput};
+use syn::{ext::IdentExt, parse_macro_input, Data, DeriveInput, Error, Fields};
 use utils::*;
 
 #[proc_macro_derive(XcpTypeDescription, attributes(type_description))]
@@ -13,40 +14,129 @@ pub fn xcp_type_description_derive(input: TokenStream) -> TokenStream {
     let data_type = &input.ident;
 
     let gen = match input.data {
-        Data::Struct(data_struct) => generate_type_description_impl(data_struct, data_type),
-        _ => panic!("XcpTypeDescription macro only supports structs"),
+        Data::Struct(data_struct) => {
+            let newtype = matches!(&data_struct.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1);
+            generate_type_description_impl(data_struct, &input.attrs, data_type).and_then(|type_description_impl| {
+                let default_impl = generate_default_impl(&input.attrs, data_type, newtype)?;
+                Ok(quote! {
+                    #type_description_impl
+                    #default_impl
+                })
+            })
+        }
+        _ => Err(Error::new_spanned(data_type, "XcpTypeDescription macro only supports structs")),
+    };
+
+    gen.unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+// XcpTypeDescription for prost generated message structs
+// The field types are taken from the #[prost(...)] attributes, repeated fields need #[xcp(max_len = "N")]
+#[proc_macro_derive(XcpProtoMeasurement, attributes(prost, xcp))]
+pub fn xcp_proto_measurement_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let data_type = &input.ident;
+
+    let gen = match input.data {
+        Data::Struct(data_struct) => proto::generate_type_description_impl(data_struct, data_type),
+        _ => Err(Error::new_spanned(data_type, "XcpProtoMeasurement macro only supports structs")),
     };
 
-    gen.into()
+    gen.unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+// impl Default returning the const default page, if the struct has a #[type_description(default_const = "...")] attribute
+fn generate_default_impl(attributes: &Vec<syn::Attribute>, data_type: &syn::Ident, newtype: bool) -> syn::Result<proc_macro2::TokenStream> {
+    let StructAttributes { default_const } = parse_struct_attributes(attributes, newtype)?;
+    Ok(match default_const {
+        Some(default_const) => quote! {
+            impl Default for #data_type {
+                fn default() -> Self {
+                    #default_const
+                }
+            }
+        },
+        None => quote! {},
+    })
 }
 
-fn generate_type_description_impl(data_struct: syn::DataStruct, data_type: &syn::Ident) -> proc_macro2::TokenStream {
-    let field_handlers = data_struct.fields.iter().map(|field| {
-        let field_name = &field.ident;
+fn generate_type_description_impl(data_struct: syn::DataStruct, attributes: &[syn::Attribute], data_type: &syn::Ident) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &data_struct.fields {
+        Fields::Named(fields) => &fields.named,
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => return generate_newtype_impl(&fields.unnamed[0], attributes, data_type),
+        fields => return Err(Error::new_spanned(fields, "XcpTypeDescription macro only supports structs with named fields or newtypes")),
+    };
+
+    // Raw identifiers (r#type) are registered without the r# prefix
+    let data_type_name = data_type.unraw().to_string();
+
+    let mut field_handlers = Vec::with_capacity(fields.len());
+    for field in fields {
+        let field_name = field.ident.as_ref().unwrap();
+        let field_name_str = field_name.unraw().to_string();
         let field_type = &field.ty;
         let field_attributes = &field.attrs;
-        let (x_dim, y_dim) = dimensions(field_type);
-        let (comment, min, max, unit) = parse_characteristic_attributes(field_attributes, field_type);
+        let field_datatype = datatype_name(field_type)?;
+        let (x_dim, y_dim) = dimensions(field_type)?;
+        let CharacteristicAttributes {
+            comment,
+            min,
+            max,
+            unit,
+            x_axis_input,
+            step,
+            format,
+            extended_limits,
+            display,
+            ifdata,
+            verbal_bool,
+            monotone_increasing,
+            function,
+            ref_functions,
+            slew_rate,
+        } = parse_characteristic_attributes(field_attributes, field_type)?;
+        let step = match step {
+            Some(step) => quote! { Some(#step) },
+            None => quote! { None },
+        };
+        let extended_limits = match extended_limits {
+            Some((extended_min, extended_max)) => quote! { Some((#extended_min, #extended_max)) },
+            None => quote! { None },
+        };
+        let slew_rate = match slew_rate {
+            Some(slew_rate) => quote! { Some(#slew_rate) },
+            None => quote! { None },
+        };
+        let verbal_bool = match verbal_bool {
+            Some(verbal_bool) => quote! { Some(#verbal_bool) },
+            None => quote! { None },
+        };
 
-        quote! {
+        field_handlers.push(quote! {
             // Offset is the address of the field relative to the address of the struct
-            let offset = ((&self.#field_name as *const _ as *const u8 as usize) - (self as *const _ as *const u8 as usize)) as u16;
+            let offset = ((&self.#field_name as *const _ as *const u8 as usize) - (self as *const _ as *const u8 as usize)) as u32;
 
             // Check if the type of the field implements the XcpTypeDescription trait
             // If this is the case, the type_description is a nested struct and its name must
             // be prefixed by the name of the parent. Consider the following:
             // struct Child { id: u32 }
             // struct Parent { child : Child } -> the name of Child.id type_description should be Parent.Child.id
+            // The offset of Child.id is relative to Child and must be made relative to Parent
             if let Some(inner_type_description) = <#field_type as XcpTypeDescription>::type_description(&self.#field_name) {
                 type_description.extend(inner_type_description.into_iter().map(|mut characteristic| {
-                    characteristic.set_name(format!("{}.{}", stringify!(#data_type), characteristic.name()));
+                    characteristic.set_name(format!("{}.{}", #data_type_name, characteristic.name()));
+                    characteristic.set_offset(characteristic.offset() + offset);
+                    // A function of the nested struct field applies to all of its fields without a function
+                    if !#function.is_empty() && characteristic.function().is_empty() {
+                        characteristic.set_function(#function);
+                    }
                     characteristic
                 }));
             // If the type does not implement the XcpTypeDescription trait, we can simply create a new FieldDescriptor from it
             } else {
                 type_description.push(FieldDescriptor::new(
-                    format!("{}.{}", stringify!(#data_type), stringify!(#field_name)),
-                    stringify!(#field_type),
+                    format!("{}.{}", #data_type_name, #field_name_str),
+                    #field_datatype,
                     #comment,
                     #min,
                     #max,
@@ -54,18 +144,126 @@ fn generate_type_description_impl(data_struct: syn::DataStruct, data_type: &syn:
                     #x_dim,
                     #y_dim,
                     offset,
+                    #x_axis_input,
+                    #step,
+                    #format,
+                    #extended_limits,
+                    #display,
+                    &[#(#ifdata),*],
+                    #verbal_bool,
+                    #monotone_increasing,
+                    #function,
+                    &[#(#ref_functions),*],
+                    #slew_rate,
                 ));
             }
+        });
+    }
+
+    Ok(quote! {
+        impl #data_type {
+            // Field offsets are u32
+            const __XCP_TYPE_DESCRIPTION_SIZE_CHECK: () = assert!(std::mem::size_of::<Self>() <= 0xFFFF_FFFF, "struct size exceeds the u32 field offset");
         }
-    });
 
-    quote! {
         impl XcpTypeDescription for #data_type {
             fn type_description(&self) -> Option<StructDescriptor> {
+                #[allow(clippy::let_unit_value)]
+                let _ = Self::__XCP_TYPE_DESCRIPTION_SIZE_CHECK;
                 let mut type_description = StructDescriptor::new();
                 #(#field_handlers)*
                 Some(type_description)
             }
         }
+    })
+}
+
+// A newtype struct Rpm(f32) is a transparent wrapper, the inner type is described under the name of the struct
+// The attributes of the inner value are the struct level #[type_description(...)] attributes
+fn generate_newtype_impl(field: &syn::Field, attributes: &[syn::Attribute], data_type: &syn::Ident) -> syn::Result<proc_macro2::TokenStream> {
+    if let Some(attribute) = field.attrs.iter().find(|a| a.path.is_ident("type_description")) {
+        return Err(Error::new_spanned(attribute, "type_description of a newtype must be set on the struct"));
     }
+
+    let data_type_name = data_type.unraw().to_string();
+    let field_type = &field.ty;
+    let field_datatype = datatype_name(field_type)?;
+    let (x_dim, y_dim) = dimensions(field_type)?;
+    let CharacteristicAttributes {
+        comment,
+        min,
+        max,
+        unit,
+        x_axis_input,
+        step,
+        format,
+        extended_limits,
+        display,
+        ifdata,
+        verbal_bool,
+        monotone_increasing,
+        function,
+        ref_functions,
+        slew_rate,
+    } = parse_characteristic_attributes(&newtype_attributes(attributes)?, field_type)?;
+    let step = match step {
+        Some(step) => quote! { Some(#step) },
+        None => quote! { None },
+    };
+    let extended_limits = match extended_limits {
+        Some((extended_min, extended_max)) => quote! { Some((#extended_min, #extended_max)) },
+        None => quote! { None },
+    };
+    let slew_rate = match slew_rate {
+        Some(slew_rate) => quote! { Some(#slew_rate) },
+        None => quote! { None },
+    };
+    let verbal_bool = match verbal_bool {
+        Some(verbal_bool) => quote! { Some(#verbal_bool) },
+        None => quote! { None },
+    };
+
+    Ok(quote! {
+        impl XcpTypeDescription for #data_type {
+            fn type_description(&self) -> Option<StructDescriptor> {
+                // A newtype around a struct keeps the fields of the inner struct, prefixed with the name of the newtype instead
+                if let Some(inner_type_description) = <#field_type as XcpTypeDescription>::type_description(&self.0) {
+                    let mut type_description = StructDescriptor::new();
+                    type_description.extend(inner_type_description.into_iter().map(|mut characteristic| {
+                        let name = match characteristic.name().split_once('.') {
+                            Some((_, field_name)) => format!("{}.{}", #data_type_name, field_name),
+                            None => #data_type_name.to_string(),
+                        };
+                        characteristic.set_name(name);
+                        characteristic
+                    }));
+                    return Some(type_description);
+                }
+                let mut type_description = StructDescriptor::new();
+                type_description.push(FieldDescriptor::new(
+                    #data_type_name.to_string(),
+                    #field_datatype,
+                    #comment,
+                    #min,
+                    #max,
+                    #unit,
+                    #x_dim,
+                    #y_dim,
+                    0,
+                    #x_axis_input,
+                    #step,
+                    #format,
+                    #extended_limits,
+                    #display,
+                    &[#(#ifdata),*],
+                    #verbal_bool,
+                    #monotone_increasing,
+                    #function,
+                    &[#(#ref_functions),*],
+                    #slew_rate,
+                ));
+                Some(type_description)
+            }
+        }
+    })
 }
@@ -0,0 +1,139 @@
+// XcpTypeDescription for prost generated message structs, see XcpProtoMeasurement
+
+use quote::quote;
+use syn::{ext::IdentExt, Attribute, Error, Fields, Lit, Meta, NestedMeta, Result};
+
+pub fn generate_type_description_impl(data_struct: syn::DataStruct, data_type: &syn::Ident) -> Result<proc_macro2::TokenStream> {
+    let fields = match &data_struct.fields {
+        Fields::Named(fields) => &fields.named,
+        fields => return Err(Error::new_spanned(fields, "XcpProtoMeasurement macro only supports structs with named fields")),
+    };
+
+    let data_type_name = data_type.unraw().to_string();
+
+    let mut field_handlers = Vec::with_capacity(fields.len());
+    for field in fields {
+        let field_name = field.ident.as_ref().unwrap();
+        let field_name_str = field_name.unraw().to_string();
+        let ProstField { datatype, repeated } = parse_prost_attribute(&field.attrs, field_name)?;
+        let max_len = parse_max_len(&field.attrs)?;
+        let x_dim = match (repeated, max_len) {
+            (false, None) => 0,
+            (true, Some(max_len)) => max_len,
+            (true, None) => return Err(Error::new_spanned(field_name, "repeated field requires #[xcp(max_len = \"N\")]")),
+            (false, Some(_)) => return Err(Error::new_spanned(field_name, "max_len is only allowed for repeated fields")),
+        };
+
+        field_handlers.push(quote! {
+            let offset = ::core::mem::offset_of!(#data_type, #field_name) as u32;
+            type_description.push(FieldDescriptor::new(
+                format!("{}.{}", #data_type_name, #field_name_str),
+                #datatype,
+                "",
+                0.0,
+                0.0,
+                "",
+                #x_dim,
+                0,
+                offset,
+                "",
+                None,
+                "",
+                None,
+                "",
+                &[],
+                None,
+                false,
+                "",
+                &[],
+                None,
+            ));
+        });
+    }
+
+    Ok(quote! {
+        impl XcpTypeDescription for #data_type {
+            fn type_description(&self) -> Option<StructDescriptor> {
+                let mut type_description = StructDescriptor::new();
+                #(#field_handlers)*
+                Some(type_description)
+            }
+        }
+    })
+}
+
+struct ProstField {
+    datatype: &'static str, // Rust type name as used by the registry, "blob" for bytes
+    repeated: bool,
+}
+
+// Parse #[prost(fixed32, tag = "1")], #[prost(double, repeated, tag = "2")] or #[prost(bytes = "vec", tag = "3")]
+fn parse_prost_attribute(attributes: &[Attribute], field_name: &syn::Ident) -> Result<ProstField> {
+    let attribute = attributes
+        .iter()
+        .find(|attribute| attribute.path.is_ident("prost"))
+        .ok_or_else(|| Error::new_spanned(field_name, "Expected a #[prost(...)] attribute"))?;
+    let meta_list = match attribute.parse_meta()? {
+        Meta::List(list) => list,
+        meta => return Err(Error::new_spanned(meta, "Expected a list of attributes for prost")),
+    };
+
+    let mut datatype = None;
+    let mut repeated = false;
+    for nested in &meta_list.nested {
+        let path = match nested {
+            NestedMeta::Meta(Meta::Path(path)) => path,
+            NestedMeta::Meta(Meta::NameValue(nv)) => &nv.path, // tag = "1", bytes = "vec", packed = "false"
+            nested => return Err(Error::new_spanned(nested, "Unexpected prost attribute")),
+        };
+        let Some(ident) = path.get_ident() else {
+            return Err(Error::new_spanned(path, "Unexpected prost attribute"));
+        };
+        match ident.to_string().as_str() {
+            "tag" | "packed" => {}
+            "repeated" => repeated = true,
+            "optional" | "required" => return Err(Error::new_spanned(ident, "optional fields are not supported by XcpProtoMeasurement")),
+            "double" => datatype = Some("f64"),
+            "float" => datatype = Some("f32"),
+            "int32" | "sint32" | "sfixed32" => datatype = Some("i32"),
+            "uint32" | "fixed32" => datatype = Some("u32"),
+            "int64" | "sint64" | "sfixed64" => datatype = Some("i64"),
+            "uint64" | "fixed64" => datatype = Some("u64"),
+            "bool" => datatype = Some("bool"),
+            "bytes" => datatype = Some("blob"),
+            s => return Err(Error::new_spanned(ident, format!("prost type {} is not supported by XcpProtoMeasurement", s))),
+        }
+    }
+
+    match datatype {
+        Some(datatype) => Ok(ProstField { datatype, repeated }),
+        None => Err(Error::new_spanned(attribute, "Expected a prost scalar type")),
+    }
+}
+
+// Parse #[xcp(max_len = "N")]
+fn parse_max_len(attributes: &[Attribute]) -> Result<Option<usize>> {
+    let mut max_len = None;
+    for attribute in attributes.iter().filter(|attribute| attribute.path.is_ident("xcp")) {
+        let meta_list = match attribute.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "Expected a list of attributes for xcp")),
+        };
+        for nested in meta_list.nested {
+            let name_value = match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("max_len") => nv,
+                nested => return Err(Error::new_spanned(nested, "Expected max_len = \"N\" in xcp")),
+            };
+            let value = match &name_value.lit {
+                Lit::Str(s) => s.value().parse::<usize>().ok(),
+                Lit::Int(i) => i.base10_parse::<usize>().ok(),
+                _ => None,
+            };
+            match value {
+                Some(value) if value > 0 => max_len = Some(value),
+                _ => return Err(Error::new_spanned(&name_value.lit, "max_len must be a positive integer")),
+            }
+        }
+    }
+    Ok(max_len)
+}
@@ -1,10 +1,146 @@
-use syn::{Attribute, Lit, Meta, NestedMeta, Type, TypeArray, TypePath};
+use syn::{Attribute, Error, Expr, ExprGroup, ExprParen, Lit, Meta, NestedMeta, Result, Type, TypeArray, TypeGroup, TypeParen, TypePath};
 
-pub fn parse_characteristic_attributes(attributes: &Vec<Attribute>, field_type: &Type) -> (String, f64, f64, String) {
+/// Remove invisible (None-delimited) groups and parentheses around a type
+/// Types passed as $t:ty through macro_rules! arrive wrapped in a None-delimited group
+pub fn unwrap_type(ty: &Type) -> &Type {
+    match ty {
+        Type::Group(TypeGroup { elem, .. }) | Type::Paren(TypeParen { elem, .. }) => unwrap_type(elem),
+        _ => ty,
+    }
+}
+
+// Remove invisible groups and parentheses around an expression, e.g. an array length passed as $n:expr
+fn unwrap_expr(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Group(ExprGroup { expr, .. }) | Expr::Paren(ExprParen { expr, .. }) => unwrap_expr(expr),
+        _ => expr,
+    }
+}
+
+/// Attributes of a struct from #[type_description(...)]
+pub struct StructAttributes {
+    pub default_const: Option<Expr>,
+}
+
+/// Parse the #[type_description(...)] attributes of a struct
+/// default_const = "<path>" names the const default page, which is returned by a generated impl Default
+/// A newtype also accepts the field attributes, which are parsed by parse_characteristic_attributes
+pub fn parse_struct_attributes(attributes: &Vec<Attribute>, newtype: bool) -> Result<StructAttributes> {
+    let mut default_const: Option<Expr> = None;
+
+    for attribute in attributes {
+        if !attribute.path.is_ident("type_description") {
+            continue;
+        }
+
+        let meta_list = match attribute.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "Expected a list of attributes for type_description")),
+        };
+
+        for nested in meta_list.nested {
+            let name_value = match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) => nv,
+                nested => return Err(Error::new_spanned(nested, "Expected name-value pairs in type_description")),
+            };
+            let value = match &name_value.lit {
+                Lit::Str(s) => s,
+                lit => return Err(Error::new_spanned(lit, "Expected string literal in type_description")),
+            };
+            if !name_value.path.is_ident("default_const") {
+                if newtype {
+                    continue;
+                }
+                return Err(Error::new_spanned(&name_value.path, "Unsupported struct type description item, expected default_const"));
+            }
+            let path = syn::parse_str::<Expr>(&value.value()).map_err(|e| Error::new_spanned(value, format!("Invalid default_const: {}", e)))?;
+            if !matches!(path, Expr::Path(_)) {
+                return Err(Error::new_spanned(value, "Invalid default_const, expected the path of a const"));
+            }
+            // A derived Default would conflict with the generated impl
+            if attributes.iter().any(derives_default) {
+                return Err(Error::new_spanned(value, "default_const conflicts with #[derive(Default)], remove one of them"));
+            }
+            default_const = Some(path);
+        }
+    }
+
+    Ok(StructAttributes { default_const })
+}
+
+// #[derive(..., Default, ...)]
+fn derives_default(attribute: &Attribute) -> bool {
+    if !attribute.path.is_ident("derive") {
+        return false;
+    }
+    match attribute.parse_meta() {
+        Ok(Meta::List(list)) => list.nested.iter().any(|nested| match nested {
+            NestedMeta::Meta(meta) => meta.path().segments.last().is_some_and(|s| s.ident == "Default"),
+            NestedMeta::Lit(_) => false,
+        }),
+        _ => false,
+    }
+}
+
+/// The #[type_description(...)] attributes of a newtype struct without default_const, which applies to the struct only
+pub fn newtype_attributes(attributes: &[Attribute]) -> Result<Vec<Attribute>> {
+    let mut newtype_attributes = Vec::new();
+    for attribute in attributes {
+        if !attribute.path.is_ident("type_description") {
+            continue;
+        }
+        let nested: Vec<NestedMeta> = match attribute.parse_meta()? {
+            Meta::List(list) => list
+                .nested
+                .into_iter()
+                .filter(|nested| !matches!(nested, NestedMeta::Meta(meta) if meta.path().is_ident("default_const")))
+                .collect(),
+            meta => return Err(Error::new_spanned(meta, "Expected a list of attributes for type_description")),
+        };
+        newtype_attributes.push(syn::parse_quote! { #[type_description(#(#nested),*)] });
+    }
+    Ok(newtype_attributes)
+}
+
+/// Attributes of a field from #[type_description(...)], with default min and max of the field type
+pub struct CharacteristicAttributes {
+    pub comment: String,
+    pub min: f64,
+    pub max: f64,
+    pub unit: String,
+    pub x_axis_input: String,
+    pub step: Option<f64>,
+    pub format: String,
+    pub extended_limits: Option<(f64, f64)>,
+    pub display: String,
+    pub ifdata: Vec<String>,
+    pub verbal_bool: Option<bool>,
+    pub monotone_increasing: bool,
+    pub function: String,
+    pub ref_functions: Vec<String>,
+    pub slew_rate: Option<f64>,
+}
+
+/// Parse all #[type_description(...)] attributes of a field
+/// Keys may appear in any order and may be spread over multiple attributes, the last occurrence of a key wins
+/// Except ifdata and ref_function, each occurrence adds an IF_DATA block or a referencing function
+pub fn parse_characteristic_attributes(attributes: &Vec<Attribute>, field_type: &Type) -> Result<CharacteristicAttributes> {
     let mut comment = String::new();
     let mut min: f64 = 0.0;
     let mut max: f64 = 0.0;
     let mut unit = String::new();
+    let mut x_axis_input = String::new();
+    let mut step: Option<f64> = None;
+    let mut format = String::new();
+    let mut extended_min: Option<(f64, Lit)> = None;
+    let mut extended_max: Option<(f64, Lit)> = None;
+    let mut display = String::new();
+    let mut ifdata: Vec<String> = Vec::new();
+    let mut verbal_bool: Option<bool> = None;
+    let mut monotone_increasing: bool = false;
+    let mut function = String::new();
+    let mut ref_functions: Vec<String> = Vec::new();
+    let mut slew_rate: Option<f64> = None;
 
     let mut min_set: bool = false;
     let mut max_set: bool = false;
@@ -17,35 +153,47 @@ pub fn parse_characteristic_attributes(attributes: &Vec<Attribute>, field_type:
             continue;
         }
 
-        let meta_list = match attribute.parse_meta() {
-            Ok(Meta::List(list)) => list,                                      // #[type_description(key = "This is correct)"]
-            _ => panic!("Expected a list of attributes for type_description"), // #[type_description = "This is incorrect"]
+        let meta_list = match attribute.parse_meta()? {
+            Meta::List(list) => list, // #[type_description(key = "This is correct)"]
+            meta => return Err(Error::new_spanned(meta, "Expected a list of attributes for type_description")), // #[type_description = "This is incorrect"]
         };
 
         for nested in meta_list.nested {
             let name_value = match nested {
-                NestedMeta::Meta(Meta::NameValue(nv)) => nv,                  // #[type_description(comment = "This is correct")]
-                _ => panic!("Expected name-value pairs in type_description"), // #[type_description(comment)] -> Incorrect
+                NestedMeta::Meta(Meta::NameValue(nv)) => nv, // #[type_description(comment = "This is correct")]
+                nested => return Err(Error::new_spanned(nested, "Expected name-value pairs in type_description")), // #[type_description(comment)] -> Incorrect
             };
 
             let key = name_value
                 .path
                 .get_ident() // #[type_description(comment = "This is correct")]
-                .unwrap_or_else(|| panic!("Expected identifier in type_description")) // #[type_description("comment" = "This is incorrect")]
+                .ok_or_else(|| Error::new_spanned(&name_value.path, "Expected identifier in type_description"))? // #[type_description(a::b = "This is incorrect")]
                 .to_string();
 
             //TODO: Figure out how to handle with Num after changing min,max,unit to range
             let value = match &name_value.lit {
                 Lit::Str(s) => s.value(),
-                _ => panic!("Expected string literal for key: {} in type_description", key),
+                lit => return Err(Error::new_spanned(lit, format!("Expected string literal for key: {} in type_description", key))),
             };
 
             match key.as_str() {
                 "comment" => parse_comment(&value, &mut comment),
-                "min" => parse_min(&value, &mut min, &mut min_set),
-                "max" => parse_max(&value, &mut max, &mut max_set),
+                "min" => parse_min(&value, &mut min, &mut min_set).map_err(|e| Error::new_spanned(&name_value.lit, e))?,
+                "max" => parse_max(&value, &mut max, &mut max_set).map_err(|e| Error::new_spanned(&name_value.lit, e))?,
                 "unit" => parse_unit(&value, &mut unit),
-                _ => panic!("Unsupported type description item: {}", key),
+                "x_axis_input" => parse_x_axis_input(&value, &mut x_axis_input, field_type).map_err(|e| Error::new_spanned(&name_value, e))?,
+                "step" => parse_step(&value, &mut step).map_err(|e| Error::new_spanned(&name_value.lit, e))?,
+                "format" => parse_format(&value, &mut format).map_err(|e| Error::new_spanned(&name_value.lit, e))?,
+                "display" => parse_display(&value, &mut display).map_err(|e| Error::new_spanned(&name_value.lit, e))?,
+                "ifdata" => parse_ifdata(&value, &mut ifdata).map_err(|e| Error::new_spanned(&name_value.lit, e))?,
+                "verbal_bool" => parse_verbal_bool(&value, &mut verbal_bool, field_type).map_err(|e| Error::new_spanned(&name_value, e))?,
+                "function" => parse_function(&value, &mut function).map_err(|e| Error::new_spanned(&name_value.lit, e))?,
+                "ref_function" => parse_ref_function(&value, &mut ref_functions).map_err(|e| Error::new_spanned(&name_value.lit, e))?,
+                "slew_rate" => parse_slew_rate(&value, &mut slew_rate, field_type).map_err(|e| Error::new_spanned(&name_value, e))?,
+                "axis" => parse_axis(&value, &mut monotone_increasing, field_type).map_err(|e| Error::new_spanned(&name_value, e))?,
+                "extended_min" => extended_min = Some((parse_limit(&value, &key).map_err(|e| Error::new_spanned(&name_value.lit, e))?, name_value.lit.clone())),
+                "extended_max" => extended_max = Some((parse_limit(&value, &key).map_err(|e| Error::new_spanned(&name_value.lit, e))?, name_value.lit.clone())),
+                _ => return Err(Error::new_spanned(&name_value.path, format!("Unsupported type description item: {}", key))),
             }
         }
     }
@@ -62,34 +210,90 @@ pub fn parse_characteristic_attributes(attributes: &Vec<Attribute>, field_type:
         }
     }
 
-    (comment, min, max, unit)
+    // Extended limits must enclose min and max, a missing extended limit defaults to min or max
+    if let Some((extended_min, lit)) = &extended_min {
+        if *extended_min > min {
+            return Err(Error::new_spanned(lit, format!("extended_min {} must not be greater than min {}", extended_min, min)));
+        }
+    }
+    if let Some((extended_max, lit)) = &extended_max {
+        if *extended_max < max {
+            return Err(Error::new_spanned(lit, format!("extended_max {} must not be less than max {}", extended_max, max)));
+        }
+    }
+    let extended_limits = if extended_min.is_some() || extended_max.is_some() {
+        Some((extended_min.map_or(min, |(v, _)| v), extended_max.map_or(max, |(v, _)| v)))
+    } else {
+        None
+    };
+
+    Ok(CharacteristicAttributes {
+        comment,
+        min,
+        max,
+        unit,
+        x_axis_input,
+        step,
+        format,
+        extended_limits,
+        display,
+        ifdata,
+        verbal_bool,
+        monotone_increasing,
+        function,
+        ref_functions,
+        slew_rate,
+    })
 }
 
-pub fn dimensions(ty: &Type) -> (usize, usize) {
-    match ty {
+// Array length as integer literal
+fn array_length(len: &Expr) -> Result<usize> {
+    match unwrap_expr(len) {
+        Expr::Lit(expr_lit) => {
+            if let Lit::Int(lit_int) = &expr_lit.lit {
+                lit_int.base10_parse::<usize>()
+            } else {
+                Err(Error::new_spanned(len, "Expected an integer literal for array length"))
+            }
+        }
+        _ => Err(Error::new_spanned(len, "Expected an integer literal for array length")),
+    }
+}
+
+// Element type of an array, the type itself otherwise
+fn element_type(ty: &Type) -> &Type {
+    match unwrap_type(ty) {
+        Type::Array(TypeArray { elem, .. }) => element_type(elem),
+        ty => ty,
+    }
+}
+
+pub fn dimensions(ty: &Type) -> Result<(usize, usize)> {
+    match unwrap_type(ty) {
         Type::Array(TypeArray { elem, len, .. }) => {
-            let length = match len {
-                syn::Expr::Lit(expr_lit) => {
-                    if let Lit::Int(lit_int) = &expr_lit.lit {
-                        lit_int.base10_parse::<usize>().unwrap()
-                    } else {
-                        panic!("Expected an integer literal for array length");
-                    }
-                }
-                _ => panic!("Expected an integer literal for array length"),
-            };
+            let length = array_length(len)?;
 
-            let (inner_x, inner_y) = dimensions(elem);
+            let (inner_x, inner_y) = dimensions(elem)?;
 
             if inner_x == 0 && inner_y == 0 {
-                (length, 0)
+                Ok((length, 0))
             } else if inner_y == 0 {
-                (length, inner_x)
+                Ok((length, inner_x))
             } else {
-                (inner_x, inner_y)
+                Ok((inner_x, inner_y))
             }
         }
-        _ => (0, 0),
+        _ => Ok((0, 0)),
+    }
+}
+
+/// Rust type name of a field as used by the registry, e.g. "f32" or "[[u8; 8]; 4]"
+/// Invisible groups are removed, so the name does not depend on how the struct was generated
+pub fn datatype_name(ty: &Type) -> Result<String> {
+    match unwrap_type(ty) {
+        Type::Array(TypeArray { elem, len, .. }) => Ok(format!("[{}; {}]", datatype_name(elem)?, array_length(len)?)),
+        Type::Path(TypePath { qself: None, path }) if path.segments.len() == 1 && path.segments[0].arguments.is_empty() => Ok(path.segments[0].ident.to_string()),
+        ty => Ok(quote::quote!(#ty).to_string()),
     }
 }
 
@@ -98,37 +302,168 @@ fn parse_unit(attribute: &str, unit: &mut String) {
     *unit = attribute.to_string();
 }
 
+#[inline]
+fn parse_x_axis_input(attribute: &str, x_axis_input: &mut String, field_type: &Type) -> std::result::Result<(), String> {
+    if dimensions(field_type).map_err(|e| e.to_string())? == (0, 0) {
+        return Err("x_axis_input requires a curve or map".to_string());
+    }
+    *x_axis_input = attribute.to_string();
+    Ok(())
+}
+
 #[inline]
 fn parse_comment(attribute: &str, comment: &mut String) {
     *comment = attribute.to_string()
 }
 
 #[inline]
-fn parse_max(attribute: &str, max: &mut f64, max_set: &mut bool) {
-    let parsed_max = attribute.parse::<f64>().expect("Failed to parse max");
+fn parse_step(attribute: &str, step: &mut Option<f64>) -> std::result::Result<(), String> {
+    let parsed_step = attribute.parse::<f64>().map_err(|e| format!("Failed to parse step: {}", e))?;
+    if !parsed_step.is_finite() || parsed_step <= 0.0 {
+        return Err("step must be a positive number".to_string());
+    }
+    *step = Some(parsed_step);
+    Ok(())
+}
+
+// A2L display format "%Length.Layout", e.g. "%8.3" or "%6"
+#[inline]
+fn parse_format(attribute: &str, format: &mut String) -> std::result::Result<(), String> {
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let valid = match attribute.strip_prefix('%') {
+        Some(f) => match f.split_once('.') {
+            Some((length, layout)) => is_number(length) && is_number(layout),
+            None => is_number(f),
+        },
+        None => false,
+    };
+    if !valid {
+        return Err(format!("Invalid format \"{}\", expected \"%<length>.<digits>\", e.g. \"%8.3\"", attribute));
+    }
+    *format = attribute.to_string();
+    Ok(())
+}
+
+// A2L DISPLAY_IDENTIFIER, an identifier which is shown in the tool instead of the name
+#[inline]
+fn parse_display(attribute: &str, display: &mut String) -> std::result::Result<(), String> {
+    let valid = attribute.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') && attribute.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']'));
+    if !valid {
+        return Err(format!("Invalid display identifier \"{}\", expected letters, digits, '_', '.', '[' or ']'", attribute));
+    }
+    *display = attribute.to_string();
+    Ok(())
+}
+
+// Vendor specific IF_DATA block, written verbatim to the A2L file
+#[inline]
+fn parse_ifdata(attribute: &str, ifdata: &mut Vec<String>) -> std::result::Result<(), String> {
+    let text = attribute.trim();
+    if !text.starts_with("/begin IF_DATA") || !text.ends_with("/end IF_DATA") {
+        return Err("Invalid ifdata, expected \"/begin IF_DATA <name> ... /end IF_DATA\"".to_string());
+    }
+    ifdata.push(text.to_string());
+    Ok(())
+}
+
+// Verbal conversion table "false" and "true" for a bool or an array of bool
+#[inline]
+fn parse_verbal_bool(attribute: &str, verbal_bool: &mut Option<bool>, field_type: &Type) -> std::result::Result<(), String> {
+    if datatype_name(element_type(field_type)).map_err(|e| e.to_string())? != "bool" {
+        return Err("verbal_bool requires a bool or an array of bool".to_string());
+    }
+    *verbal_bool = Some(attribute.parse::<bool>().map_err(|_| "Invalid verbal_bool, expected \"true\" or \"false\"".to_string())?);
+    Ok(())
+}
+
+// Max rate of change in units per second of the value applied by a calibration segment with slew enabled
+#[inline]
+fn parse_slew_rate(attribute: &str, slew_rate: &mut Option<f64>, field_type: &Type) -> std::result::Result<(), String> {
+    if datatype_name(element_type(field_type)).map_err(|e| e.to_string())? == "bool" {
+        return Err("slew_rate requires a numeric type".to_string());
+    }
+    let rate = attribute.parse::<f64>().map_err(|e| format!("Failed to parse slew_rate: {}", e))?;
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err("slew_rate must be a positive number".to_string());
+    }
+    *slew_rate = Some(rate);
+    Ok(())
+}
+
+// Axis property of a one dimensional array, "monotone_increasing" requires strictly increasing values
+#[inline]
+fn parse_axis(attribute: &str, monotone_increasing: &mut bool, field_type: &Type) -> std::result::Result<(), String> {
+    let (x_dim, y_dim) = dimensions(field_type).map_err(|e| e.to_string())?;
+    if x_dim <= 1 || y_dim != 0 || datatype_name(element_type(field_type)).map_err(|e| e.to_string())? == "bool" {
+        return Err("axis requires a one dimensional numeric array".to_string());
+    }
+    if attribute != "monotone_increasing" {
+        return Err(format!("Invalid axis \"{}\", expected \"monotone_increasing\"", attribute));
+    }
+    *monotone_increasing = true;
+    Ok(())
+}
+
+// Name of an ECU function, an A2L identifier
+fn check_function_name(attribute: &str) -> std::result::Result<(), String> {
+    let valid = attribute.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') && attribute.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.'));
+    if !valid {
+        return Err(format!("Invalid function name \"{}\", expected letters, digits, '_' or '.'", attribute));
+    }
+    Ok(())
+}
+
+// ECU function which defines the field (A2L FUNCTION DEF_CHARACTERISTIC)
+#[inline]
+fn parse_function(attribute: &str, function: &mut String) -> std::result::Result<(), String> {
+    check_function_name(attribute)?;
+    *function = attribute.to_string();
+    Ok(())
+}
+
+// ECU function which uses the field (A2L FUNCTION REF_CHARACTERISTIC)
+#[inline]
+fn parse_ref_function(attribute: &str, ref_functions: &mut Vec<String>) -> std::result::Result<(), String> {
+    check_function_name(attribute)?;
+    if !ref_functions.iter().any(|f| f == attribute) {
+        ref_functions.push(attribute.to_string());
+    }
+    Ok(())
+}
+
+#[inline]
+fn parse_limit(attribute: &str, key: &str) -> std::result::Result<f64, String> {
+    attribute.parse::<f64>().map_err(|e| format!("Failed to parse {}: {}", key, e))
+}
+
+#[inline]
+fn parse_max(attribute: &str, max: &mut f64, max_set: &mut bool) -> std::result::Result<(), String> {
+    let parsed_max = attribute.parse::<f64>().map_err(|e| format!("Failed to parse max: {}", e))?;
     *max = parsed_max;
     *max_set = true;
+    Ok(())
 }
 
 #[inline]
-fn parse_min(attribute: &str, min: &mut f64, min_set: &mut bool) {
-    let parsed_min = attribute.parse::<f64>().expect("Failed to parse max");
+fn parse_min(attribute: &str, min: &mut f64, min_set: &mut bool) -> std::result::Result<(), String> {
+    let parsed_min = attribute.parse::<f64>().map_err(|e| format!("Failed to parse min: {}", e))?;
     *min = parsed_min;
     *min_set = true;
+    Ok(())
 }
 
 fn get_default_min_value_for_type(ty: &Type) -> Option<f64> {
-    match ty {
+    match unwrap_type(ty) {
         Type::Path(TypePath { path, .. }) => {
-            let segment = path.segments.last().expect("Expected a path segment");
+            let segment = path.segments.last()?;
             match segment.ident.to_string().as_str() {
-                "bool" | "u8" | "u16" | "u32" | "u64" | "usize" => Some(0.0),
+                "bool" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => Some(0.0),
                 "i8" => Some(i8::MIN as f64),
                 "i16" => Some(i16::MIN as f64),
                 "i32" => Some(i32::MIN as f64),
-                "i64" | "isize" => Some(-1000000000000.0), //Some(i64::MIN as f64),
-                "f32" => Some(-1000000000000.0),           //Some(f32::MIN as f64)
-                "f64" => Some(-1000000000000.0),           //Some(f64::MIN)
+                "i64" | "i128" | "isize" => Some(-1000000000000.0), //Some(i64::MIN as f64),
+                "f32" => Some(-1000000000000.0),                    //Some(f32::MIN as f64)
+                "f64" => Some(-1000000000000.0),                    //Some(f64::MIN)
                 _ => None,
             }
         }
@@ -137,9 +472,9 @@ fn get_default_min_value_for_type(ty: &Type) -> Option<f64> {
 }
 
 fn get_default_max_value_for_type(ty: &Type) -> Option<f64> {
-    match ty {
+    match unwrap_type(ty) {
         Type::Path(TypePath { path, .. }) => {
-            let segment = path.segments.last().expect("Expected a path segment");
+            let segment = path.segments.last()?;
             match segment.ident.to_string().as_str() {
                 "bool" => Some(255.0), //TODO: Discuss 255 vs 1 for bool values
                 "u8" => Some(u8::MAX as f64),
@@ -152,6 +487,7 @@ fn get_default_max_value_for_type(ty: &Type) -> Option<f64> {
                 "isize" => Some(isize::MAX as f64),
                 "u64" => Some(1000000000000.0), //Some(u64::MAX as f64),
                 "i64" => Some(1000000000000.0), //Some(i64::MAX as f64),
+                "u128" | "i128" => Some(1000000000000.0),
                 "f32" => Some(1000000000000.0), //Some(f32::MAX as f64),
                 "f64" => Some(1000000000000.0), //Some(f64::MAX),
                 _ => None,
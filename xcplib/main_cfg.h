@@ -24,6 +24,7 @@
   #define OPTION_ENABLE_TCP
   #define OPTION_ENABLE_UDP
   #define OPTION_MTU                  UDP MTU
+  #define OPTION_MAX_MTU              Maximum UDP MTU selectable at runtime (jumbo frames), default is OPTION_MTU
   #define OPTION_QUEUE_SIZE           Size of the DAQ queue in XCP DTO/CRM packets (not messages as in V1.x) 
   #define OPTION_DAQ_MEM_SIZE         Size of memory for DAQ setup in bytes
   #define OPTION_ENABLE_A2L_UPLOAD    Enable GET_ID A2L upload
@@ -37,6 +38,7 @@
 #define OPTION_ENABLE_TCP
 #define OPTION_ENABLE_UDP
 #define OPTION_MTU 1500 
+// #define OPTION_MAX_MTU 9000 // Jumbo frames, defined by the cargo feature jumbo_frames, the transmit queue memory grows with the max MTU
 #define OPTION_QUEUE_SIZE 200     
 #define OPTION_DAQ_MEM_SIZE (3000*5)  
 #define OPTION_ENABLE_A2L_UPLOAD
@@ -222,6 +222,19 @@ BOOL socketOpen(SOCKET* sp, BOOL useTCP, BOOL nonBlocking, BOOL reuseaddr, BOOL
     return TRUE;
 }
 
+// Set the socket send and receive buffer sizes in bytes, 0 keeps the operating system default
+BOOL socketSetBufferSizes(SOCKET sock, uint32_t sndbuf, uint32_t rcvbuf) {
+    if (sndbuf > 0 && setsockopt(sock, SOL_SOCKET, SO_SNDBUF, &sndbuf, sizeof(sndbuf)) < 0) {
+        DBG_PRINTF_ERROR("ERROR %d: cannot set send buffer size %u!\n", socketGetLastError(), sndbuf);
+        return FALSE;
+    }
+    if (rcvbuf > 0 && setsockopt(sock, SOL_SOCKET, SO_RCVBUF, &rcvbuf, sizeof(rcvbuf)) < 0) {
+        DBG_PRINTF_ERROR("ERROR %d: cannot set receive buffer size %u!\n", socketGetLastError(), rcvbuf);
+        return FALSE;
+    }
+    return TRUE;
+}
+
 BOOL socketBind(SOCKET sock, uint8_t* addr, uint16_t port) {
 
     // Bind the socket to any address and the specified port
@@ -444,6 +457,20 @@ BOOL socketOpen(SOCKET* sp, BOOL useTCP, BOOL nonBlocking, BOOL reuseaddr, BOOL
     return TRUE;
 }
 
+// Set the socket send and receive buffer sizes in bytes, 0 keeps the operating system default
+BOOL socketSetBufferSizes(SOCKET sock, uint32_t sndbuf, uint32_t rcvbuf) {
+    if (sndbuf > 0 && setsockopt(sock, SOL_SOCKET, SO_SNDBUF, (const char*)&sndbuf, sizeof(sndbuf)) != 0) {
+        DBG_PRINTF_ERROR("ERROR %d: cannot set send buffer size %u!\n", socketGetLastError(), sndbuf);
+        return FALSE;
+    }
+    if (rcvbuf > 0 && setsockopt(sock, SOL_SOCKET, SO_RCVBUF, (const char*)&rcvbuf, sizeof(rcvbuf)) != 0) {
+        DBG_PRINTF_ERROR("ERROR %d: cannot set receive buffer size %u!\n", socketGetLastError(), rcvbuf);
+        return FALSE;
+    }
+    return TRUE;
+}
+
+
 
 BOOL socketBind(SOCKET sock, uint8_t *addr, uint16_t port) {
 
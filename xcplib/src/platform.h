@@ -144,6 +144,7 @@ extern int32_t socketGetLastError();
 extern void socketCleanup();
 extern BOOL socketOpen(SOCKET* sp, BOOL useTCP, BOOL nonBlocking, BOOL reuseaddr, BOOL timestamps);
 extern BOOL socketBind(SOCKET sock, uint8_t* addr, uint16_t port);
+extern BOOL socketSetBufferSizes(SOCKET sock, uint32_t sndbuf, uint32_t rcvbuf);
 extern BOOL socketJoin(SOCKET sock, uint8_t* maddr);
 extern BOOL socketListen(SOCKET sock);
 extern SOCKET socketAccept(SOCKET sock, uint8_t* addr);
@@ -570,7 +570,7 @@
 /* UPLOAD */
 #define CRO_UPLOAD_LEN                                  2
 #define CRO_UPLOAD_SIZE                                 CRO_BYTE(1)
-#define CRM_UPLOAD_MAX_SIZE                             ((uint8_t)(XCPTL_MAX_CTO_SIZE-1))
+#define CRM_UPLOAD_MAX_SIZE                             ((uint8_t)(XcpTlGetMaxCtoSize()-1))
 #define CRM_UPLOAD_LEN                                  1 /* +CRO_UPLOAD_SIZE */
 #define CRM_UPLOAD_DATA                                 (&CRM_BYTE(1))
 
@@ -579,7 +579,7 @@
 #define CRO_SHORT_UPLOAD_SIZE                           CRO_BYTE(1)
 #define CRO_SHORT_UPLOAD_EXT                            CRO_BYTE(3)
 #define CRO_SHORT_UPLOAD_ADDR                           CRO_DWORD(1)
-#define CRM_SHORT_UPLOAD_MAX_SIZE                       ((uint8_t)(XCPTL_MAX_CTO_SIZE-1))
+#define CRM_SHORT_UPLOAD_MAX_SIZE                       ((uint8_t)(XcpTlGetMaxCtoSize()-1))
 #define CRM_SHORT_UPLOAD_LEN                            1u /* +CRO_SHORT_UPLOAD_SIZE */
 #define CRM_SHORT_UPLOAD_DATA                           (&CRM_BYTE(1))
 
@@ -591,26 +591,26 @@
 #define CRM_BUILD_CHECKSUM_RESULT                       CRM_DWORD(1)
 
 /* DOWNLOAD */
-#define CRO_DOWNLOAD_MAX_SIZE                           ((uint8_t)(XCPTL_MAX_CTO_SIZE-2))
+#define CRO_DOWNLOAD_MAX_SIZE                           ((uint8_t)(XcpTlGetMaxCtoSize()-2))
 #define CRO_DOWNLOAD_LEN                                2 /* + CRO_DOWNLOAD_SIZE */
 #define CRO_DOWNLOAD_SIZE                               CRO_BYTE(1)
 #define CRO_DOWNLOAD_DATA                               (&CRO_BYTE(2))
 #define CRM_DOWNLOAD_LEN                                1
 
 /* DOWNLOAD_NEXT */
-#define CRO_DOWNLOAD_NEXT_MAX_SIZE                      ((uint8_t)(XCPTL_MAX_CTO_SIZE-2))
+#define CRO_DOWNLOAD_NEXT_MAX_SIZE                      ((uint8_t)(XcpTlGetMaxCtoSize()-2))
 #define CRO_DOWNLOAD_NEXT_LEN                           2 /* + size */
 #define CRO_DOWNLOAD_NEXT_SIZE                          CRO_BYTE(1)
 #define CRO_DOWNLOAD_NEXT_DATA                          (&CRO_BYTE(2))
 #define CRM_DOWNLOAD_NEXT_LEN                           1
 
 /* DOWNLOAD_MAX */
-#define CRO_DOWNLOAD_MAX_MAX_SIZE                       ((uint8_t)(XCPTL_MAX_CTO_SIZE-1))
+#define CRO_DOWNLOAD_MAX_MAX_SIZE                       ((uint8_t)(XcpTlGetMaxCtoSize()-1))
 #define CRO_DOWNLOAD_MAX_DATA                           (&CRO_BYTE(1))
 #define CRM_DOWNLOAD_MAX_LEN                            1
 
 /* SHORT_DOWNLOAD */
-#define CRO_SHORT_DOWNLOAD_MAX_SIZE                     ((uint8_t)(XCPTL_MAX_CTO_SIZE-8))
+#define CRO_SHORT_DOWNLOAD_MAX_SIZE                     ((uint8_t)(XcpTlGetMaxCtoSize()-8))
 #define CRO_SHORT_DOWNLOAD_LEN                          8
 #define CRO_SHORT_DOWNLOAD_SIZE                         CRO_BYTE(1)
 #define CRO_SHORT_DOWNLOAD_EXT                          CRO_BYTE(3)
@@ -882,7 +882,7 @@
 #define CRM_PROGRAM_CLEAR_LEN                           1
 
 /* PROGRAM */
-#define CRO_PROGRAM_MAX_SIZE                            ((uint8_t)(XCPTL_MAX_CTO_SIZE-2))
+#define CRO_PROGRAM_MAX_SIZE                            ((uint8_t)(XcpTlGetMaxCtoSize()-2))
 #define CRO_PROGRAM_LEN                                 2 /* + CRO_PROGRAM_SIZE */
 #define CRO_PROGRAM_SIZE                                CRO_BYTE(1)
 #define CRO_PROGRAM_DATA                                (&CRO_BYTE(2))
@@ -923,7 +923,7 @@
 #define CRM_PROGRAM_FORMAT_LEN                          1
 
 /* PROGRAM_NEXT */
-#define CRO_PROGRAM_NEXT_MAX_SIZE                       ((uint8_t)(XCPTL_MAX_CTO_SIZE-2))
+#define CRO_PROGRAM_NEXT_MAX_SIZE                       ((uint8_t)(XcpTlGetMaxCtoSize()-2))
 #define CRO_PROGRAM_NEXT_LEN                            2 /* + size */
 #define CRO_PROGRAM_NEXT_SIZE                           CRO_BYTE(1)
 #define CRO_PROGRAM_NEXT_DATA                           (&CRO_BYTE(2))
@@ -932,7 +932,7 @@
 #define CRM_PROGRAM_NEXT_SIZE_EXPECTED_DATA             CRM_BYTE(2)
 
 /* PROGRAM_MAX */
-#define CRO_PROGRAM_MAX_MAX_SIZE                        ((uint8_t)(XCPTL_MAX_CTO_SIZE-1))
+#define CRO_PROGRAM_MAX_MAX_SIZE                        ((uint8_t)(XcpTlGetMaxCtoSize()-1))
 #define CRO_PROGRAM_MAX_DATA                            (&CRO_BYTE(1))
 #define CRM_PROGRAM_MAX_LEN                             1
 
@@ -1184,7 +1184,7 @@ typedef struct {
 #define CRM_TL_GET_SERVER_ID_ID                             CRM_BYTE(26)
 #define CRM_TL_GET_SERVER_ID_MAC(n)                         CRM_BYTE(26+n) /*+CRM_TL_GET_SERVER_ID_ID_LEN*/
 
-#define CRM_TL_GET_SERVER_ID_MAX_LEN                        (XCPTL_MAX_CTO_SIZE-(26+6))
+#define CRM_TL_GET_SERVER_ID_MAX_LEN                        (XcpTlGetMaxCtoSize()-(26+6))
 
 /* GET_SERVER_ID_EXTENDED */
 #define TL_SLV_DETECT_STATUS_SLV_ID_EXT_RADAR_DATA          (1<<0)
@@ -44,6 +44,13 @@ static struct {
 #endif
 
 } gXcpTl;
+
+// Options set before XcpEthTlInit, 0 selects the default
+static struct {
+    uint32_t queue_size; // Transmit queue size in packets
+    uint32_t sndbuf; // Socket send buffer size in bytes
+    uint32_t rcvbuf; // Socket receive buffer size in bytes
+} gXcpTlOptions = { XCPTL_QUEUE_SIZE, 0, 0 };
 #endif
 
 
@@ -155,6 +162,7 @@ static int handleXcpCommand(tXcpCtoMessage *p, uint8_t *srcAddr, uint16_t srcPor
 
     // gXcpTl.LastCrmCtr = p->ctr;
     connected = XcpIsConnected();
+    XcpTlCountRxPacket();
 
 #ifdef DBG_LEVEL
     if (DBG_LEVEL >= 5) {
@@ -187,7 +195,7 @@ static int handleXcpCommand(tXcpCtoMessage *p, uint8_t *srcAddr, uint16_t srcPor
             }
         }
 #endif // UDP
-        if (p->dlc>XCPTL_MAX_CTO_SIZE) return 0;
+        if (p->dlc>XcpTlGetMaxCtoSize()) return 0;
         XcpCommand((const uint32_t*)&p->packet[0], (uint8_t)p->dlc); // Handle command
     }
 
@@ -321,7 +329,7 @@ static int handleXcpMulticastCommand(int n, tXcpCtoMessage* p, uint8_t* dstAddr,
 
     // Valid socket data received, at least transport layer header and 1 byte
     if (n >= XCPTL_TRANSPORT_LAYER_HEADER_SIZE + 1 && p->dlc <= n- XCPTL_TRANSPORT_LAYER_HEADER_SIZE) {
-        if (p->dlc >= XCPTL_MAX_CTO_SIZE) return 0; // Error
+        if (p->dlc >= XcpTlGetMaxCtoSize()) return 0; // Error
         XcpCommand((const uint32_t*)&p->packet[0],(uint8_t)p->dlc); // Handle command
     }
     else {
@@ -375,9 +383,17 @@ extern void* XcpTlMulticastThread(void* par)
 
 //-------------------------------------------------------------------------------------------------------
 
+// Set the transmit queue size in packets and the socket send and receive buffer sizes in bytes, 0 selects the default
+// Must be called before XcpEthTlInit
+void XcpEthTlSetOptions(uint32_t queue_size, uint32_t sndbuf, uint32_t rcvbuf) {
+    gXcpTlOptions.queue_size = queue_size;
+    gXcpTlOptions.sndbuf = sndbuf;
+    gXcpTlOptions.rcvbuf = rcvbuf;
+}
+
 BOOL XcpEthTlInit(const uint8_t* addr, uint16_t port, BOOL useTCP, BOOL blockingRx) {
 
-    if (!XcpTlInit()) return FALSE;
+    if (!XcpTlInit(gXcpTlOptions.queue_size)) return FALSE;
 
     uint8_t bind_addr[4] = {0,0,0,0}; // Bind to ANY(0.0.0.0)
     if (addr != NULL)  { // Bind to given addr 
@@ -396,6 +412,7 @@ BOOL XcpEthTlInit(const uint8_t* addr, uint16_t port, BOOL useTCP, BOOL blocking
     if (useTCP) 
     { // TCP
         if (!socketOpen(&gXcpTl.ListenSock, TRUE /* useTCP */, !blockingRx, TRUE /*reuseAddr*/, FALSE /* timestamps*/)) return FALSE;
+        if (!socketSetBufferSizes(gXcpTl.ListenSock, gXcpTlOptions.sndbuf, gXcpTlOptions.rcvbuf)) return FALSE; // Inherited by the accepted socket
         if (!socketBind(gXcpTl.ListenSock, bind_addr, gXcpTl.ServerPort)) return FALSE; 
         if (!socketListen(gXcpTl.ListenSock)) return FALSE; // Put socket in listen mode
         DBG_PRINTF3("  Listening for TCP connections on %u.%u.%u.%u port %u\n", bind_addr[0], bind_addr[1], bind_addr[2], bind_addr[3], port);
@@ -411,6 +428,7 @@ BOOL XcpEthTlInit(const uint8_t* addr, uint16_t port, BOOL useTCP, BOOL blocking
 #endif
     { // UDP
         if (!socketOpen(&gXcpTl.Sock, FALSE /* useTCP */, !blockingRx, TRUE /*reuseAddr*/, FALSE /* timestamps*/)) return FALSE;
+        if (!socketSetBufferSizes(gXcpTl.Sock, gXcpTlOptions.sndbuf, gXcpTlOptions.rcvbuf)) return FALSE;
         if (!socketBind(gXcpTl.Sock, bind_addr, port)) return FALSE; // Bind on ANY, when serverAddr=255.255.255.255
         DBG_PRINTF3("  Listening for XCP commands on UDP %u.%u.%u.%u port %u\n", bind_addr[0], bind_addr[1], bind_addr[2], bind_addr[3], port);
     }
@@ -8,6 +8,7 @@
 /* ETH transport Layer functions called by server */
 #if defined(XCPTL_ENABLE_UDP) || defined(XCPTL_ENABLE_TCP)
 
+extern void XcpEthTlSetOptions(uint32_t queue_size, uint32_t sndbuf, uint32_t rcvbuf); // Set transmit queue size and socket buffer sizes before XcpEthTlInit, 0 selects the default
 extern BOOL XcpEthTlInit(const uint8_t* addr, uint16_t port, BOOL useTCP, BOOL blockingRx); // Start transport layer
 extern void XcpEthTlShutdown();
 #ifdef PLATFORM_ENABLE_GET_LOCAL_ADDR
@@ -579,8 +579,8 @@ static BOOL  XcpAdjustOdtSize(uint16_t daq, uint16_t odt, uint8_t size) {
     DaqListOdtSize(odt) = (uint16_t)(DaqListOdtSize(odt) + size);
 #endif
 #ifdef XCP_ENABLE_TEST_CHECKS
-    if (DaqListOdtSize(odt) > (XCPTL_MAX_DTO_SIZE-2)-(odt==0?4:0)) { // -6/2 bytes for odt+daq+timestamp 
-        DBG_PRINTF_ERROR("ERROR: ODT size %u exceed XCPTL_MAX_DTO_SIZE %u!\n", DaqListOdtSize(odt), XCPTL_MAX_DTO_SIZE);
+    if (DaqListOdtSize(odt) > (XcpTlGetMaxDtoSize()-2)-(odt==0?4:0)) { // -6/2 bytes for odt+daq+timestamp 
+        DBG_PRINTF_ERROR("ERROR: ODT size %u exceed MAX_DTO_SIZE %u!\n", DaqListOdtSize(odt), XcpTlGetMaxDtoSize());
         return FALSE;
     }
 #endif
@@ -1101,7 +1101,10 @@ static uint8_t XcpAsyncCommand( BOOL async, const uint32_t* cmdBuf, uint8_t cmdL
   uint8_t err = 0;
 
   if (!isStarted()) return CRC_GENERIC;
-  if (CRO_LEN > XCPTL_MAX_CTO_SIZE) return CRC_CMD_SYNTAX;
+  if (CRO_LEN > XcpTlGetMaxCtoSize()) return CRC_CMD_SYNTAX;
+
+  // Notify the application about the command
+  if (!async) ApplXcpCommand(CRO_CMD);
 
   // Prepare the default response
   CRM_CMD = PID_RES; /* Response, no error */
@@ -1132,8 +1135,8 @@ static uint8_t XcpAsyncCommand( BOOL async, const uint32_t* cmdBuf, uint8_t cmdL
       CRM_LEN = CRM_CONNECT_LEN;
       CRM_CONNECT_TRANSPORT_VERSION = (uint8_t)( (uint16_t)XCP_TRANSPORT_LAYER_VERSION >> 8 ); /* Major versions of the XCP Protocol Layer and Transport Layer Specifications. */
       CRM_CONNECT_PROTOCOL_VERSION =  (uint8_t)( (uint16_t)XCP_PROTOCOL_LAYER_VERSION >> 8 );
-      CRM_CONNECT_MAX_CTO_SIZE = XCPTL_MAX_CTO_SIZE;
-      CRM_CONNECT_MAX_DTO_SIZE = XCPTL_MAX_DTO_SIZE;
+      CRM_CONNECT_MAX_CTO_SIZE = (uint8_t)XcpTlGetMaxCtoSize();
+      CRM_CONNECT_MAX_DTO_SIZE = XcpTlGetMaxDtoSize();
       CRM_CONNECT_RESOURCE = RM_DAQ|RM_CAL_PAG; /* DAQ and CAL supported */
       CRM_CONNECT_COMM_BASIC = CMB_OPTIONAL; // GET_COMM_MODE_INFO available, byte order Intel, address granularity byte, no server block mode
       assert(*(uint8_t*)&gXcp.SessionStatus==0); // Intel byte order
@@ -1150,7 +1153,7 @@ static uint8_t XcpAsyncCommand( BOOL async, const uint32_t* cmdBuf, uint8_t cmdL
           return CRC_CMD_IGNORED;
       }
 
-      if (CRO_LEN<1 || CRO_LEN>XCPTL_MAX_CTO_SIZE) error(CRC_CMD_SYNTAX);
+      if (CRO_LEN<1 || CRO_LEN>XcpTlGetMaxCtoSize()) error(CRC_CMD_SYNTAX);
       switch (CRO_CMD)
       {
 
@@ -1736,7 +1739,7 @@ static uint8_t XcpAsyncCommand( BOOL async, const uint32_t* cmdBuf, uint8_t cmdL
                           CRM_GET_DAQ_CLOCK_MCAST_TIME = (uint32_t)ApplXcpGetClock64();
                           CRM_GET_DAQ_CLOCK_MCAST_SYNCH_STATE = ApplXcpGetClockState();
                       #endif
-                      if (CRM_LEN> XCPTL_MAX_CTO_SIZE) error(CRC_CMD_UNKNOWN); // Extended mode needs enough CTO size 
+                      if (CRM_LEN> XcpTlGetMaxCtoSize()) error(CRC_CMD_UNKNOWN); // Extended mode needs enough CTO size 
                   }
                   else
                   { // Legacy format
@@ -1804,7 +1807,7 @@ static uint8_t XcpAsyncCommand( BOOL async, const uint32_t* cmdBuf, uint8_t cmdL
                    CRM_GET_DAQ_CLOCK_TIME = (uint32_t)ApplXcpGetClock64();
                    CRM_GET_DAQ_CLOCK_SYNCH_STATE = ApplXcpGetClockState();
                 #endif
-                if (CRM_LEN > XCPTL_MAX_CTO_SIZE) error(CRC_CMD_UNKNOWN); // Extended mode needs enough CTO size               
+                if (CRM_LEN > XcpTlGetMaxCtoSize()) error(CRC_CMD_UNKNOWN); // Extended mode needs enough CTO size               
               }
               else
               #endif // >= 0x0103
@@ -1898,7 +1901,7 @@ void XcpSendEvent(uint8_t ev, uint8_t evc, const uint8_t* d, uint8_t l)
   crm.b[0] = ev; /* Event*/
   crm.b[1] = evc;  /* Eventcode */
   uint8_t i;
-  for (i = 0; i < l && i < XCPTL_MAX_CTO_SIZE-4; i++) crm.b[i+2] = d[i];
+  for (i = 0; i < l && i < XcpTlGetMaxCtoSize()-4; i++) crm.b[i+2] = d[i];
   XcpTlSendCrm((const uint8_t*)&crm, l+2);
 }
 
@@ -1918,7 +1921,7 @@ void XcpPrint( const char *str ) {
   crm.b[1] = 0x01;  /* Eventcode SERV_TEXT */
   uint8_t i;
   uint16_t l = (uint16_t)strlen(str);
-  for (i = 0; i < l && i < XCPTL_MAX_CTO_SIZE-4; i++) crm.b[i+2] = str[i];
+  for (i = 0; i < l && i < XcpTlGetMaxCtoSize()-4; i++) crm.b[i+2] = str[i];
   crm.b[i+2] = '\n';
   crm.b[i+3] = 0;
   XcpTlSendCrm((const uint8_t*)&crm, l+4);
@@ -1962,7 +1965,7 @@ void XcpStart()
 
 #ifdef DBG_LEVEL
     DBG_PRINT3("Init XCP protocol layer\n");
-    DBG_PRINTF3("  Version=%u.%u, MAX_CTO=%u, MAX_DTO=%u, DAQ_MEM=%u, MAX_DAQ=%u, MAX_ODT_ENTRY=%u, MAX_ODT_ENTRYSIZE=%u\n", XCP_PROTOCOL_LAYER_VERSION >> 8, XCP_PROTOCOL_LAYER_VERSION & 0xFF, XCPTL_MAX_CTO_SIZE, XCPTL_MAX_DTO_SIZE, XCP_DAQ_MEM_SIZE, (1 << sizeof(uint16_t) * 8) - 1, (1 << sizeof(uint16_t) * 8) - 1, (1 << (sizeof(uint8_t) * 8)) - 1);
+    DBG_PRINTF3("  Version=%u.%u, MAX_CTO=%u, MAX_DTO=%u, DAQ_MEM=%u, MAX_DAQ=%u, MAX_ODT_ENTRY=%u, MAX_ODT_ENTRYSIZE=%u\n", XCP_PROTOCOL_LAYER_VERSION >> 8, XCP_PROTOCOL_LAYER_VERSION & 0xFF, XcpTlGetMaxCtoSize(), XcpTlGetMaxDtoSize(), XCP_DAQ_MEM_SIZE, (1 << sizeof(uint16_t) * 8) - 1, (1 << sizeof(uint16_t) * 8) - 1, (1 << (sizeof(uint8_t) * 8)) - 1);
     DBG_PRINTF3("  %u KiB memory used\n", (unsigned int)sizeof(gXcp) / 1024);
     DBG_PRINT3("  Options=(");
 
@@ -129,6 +129,7 @@ extern tXcpEvent* XcpGetEvent(uint16_t event);
 /* Callbacks on connect, disconnect, measurement prepare, start and stop */
 extern BOOL ApplXcpConnect();
 extern void ApplXcpDisconnect();
+extern void ApplXcpCommand(uint8_t cmd); /* Notification on every received command */
 #if XCP_PROTOCOL_LAYER_VERSION >= 0x0104
 extern BOOL ApplXcpPrepareDaq();
 #endif
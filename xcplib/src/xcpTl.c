@@ -24,13 +24,60 @@ static struct {
 } gXcpTl;
 #endif
 
+// Runtime max CTO and DTO size, limited by the compile time maximum XCPTL_MAX_CTO_SIZE and XCPTL_MAX_DTO_SIZE
+static uint16_t gXcpTlMaxCtoSize = XCPTL_MAX_CTO_SIZE;
+static uint16_t gXcpTlMaxDtoSize = XCPTL_DEFAULT_DTO_SIZE;
 
-BOOL XcpTlInit() {
+// Number of received command messages, incremented by the receive thread only
+static uint64_t gXcpTlRxPackets = 0;
 
-    XcpTlInitTransmitQueue();
+
+// Set the max CTO and DTO size, 0 selects XCPTL_MAX_CTO_SIZE or XCPTL_DEFAULT_DTO_SIZE
+// Command responses are transmitted in the DTO transmit queue, the CTO size must not exceed the DTO size
+BOOL XcpTlSetMaxSizes(uint16_t max_cto, uint16_t max_dto) {
+
+    if (max_cto == 0) max_cto = XCPTL_MAX_CTO_SIZE;
+    if (max_dto == 0) max_dto = XCPTL_DEFAULT_DTO_SIZE;
+
+    if (max_cto < 8 || max_cto > XCPTL_MAX_CTO_SIZE || (max_cto & 0x07) != 0) {
+        DBG_PRINTF_ERROR("ERROR: Invalid MAX_CTO_SIZE %u, must be a multiple of 8 in 8..%u\n", max_cto, XCPTL_MAX_CTO_SIZE);
+        return FALSE;
+    }
+    if (max_dto < 8 || max_dto > XCPTL_MAX_DTO_SIZE || (max_dto & 0x03) != 0) {
+        DBG_PRINTF_ERROR("ERROR: Invalid MAX_DTO_SIZE %u, must be a multiple of 4 in 8..%u\n", max_dto, XCPTL_MAX_DTO_SIZE);
+        return FALSE;
+    }
+    if (max_cto > max_dto) {
+        DBG_PRINTF_ERROR("ERROR: MAX_CTO_SIZE %u exceeds MAX_DTO_SIZE %u\n", max_cto, max_dto);
+        return FALSE;
+    }
+    gXcpTlMaxCtoSize = max_cto;
+    gXcpTlMaxDtoSize = max_dto;
+    return TRUE;
+}
+
+uint16_t XcpTlGetMaxCtoSize() {
+    return gXcpTlMaxCtoSize;
+}
+
+uint16_t XcpTlGetMaxDtoSize() {
+    return gXcpTlMaxDtoSize;
+}
+
+// Segment size - DTO size is the size of the transport layer headers
+uint16_t XcpTlGetMaxSegmentSize() {
+    return (uint16_t)(gXcpTlMaxDtoSize + (XCPTL_MAX_SEGMENT_SIZE - XCPTL_MAX_DTO_SIZE));
+}
+
+
+// Queue size in packets, 0 = XCPTL_QUEUE_SIZE
+BOOL XcpTlInit(uint32_t queue_size) {
+
+    XcpTlInitTransmitQueue(queue_size);
+    gXcpTlRxPackets = 0;
     
     DBG_PRINT3("Init XCP transport layer\n");
-    DBG_PRINTF3("  MAX_CTO_SIZE=%u\n", XCPTL_MAX_CTO_SIZE);
+    DBG_PRINTF3("  MAX_CTO_SIZE=%u, MAX_DTO_SIZE=%u\n", gXcpTlMaxCtoSize, gXcpTlMaxDtoSize);
 #ifdef XCPTL_ENABLE_MULTICAST
     DBG_PRINT3("        Option ENABLE_MULTICAST (not recommended)\n");
 #endif
@@ -81,10 +128,11 @@ uint8_t XcpTlCommand( uint16_t msgLen, const uint8_t* msgBuf) {
     BOOL connected = XcpIsConnected();
     tXcpCtoMessage* p = (tXcpCtoMessage*)msgBuf;
     assert(msgLen>=p->dlc+XCPTL_TRANSPORT_LAYER_HEADER_SIZE);
+    XcpTlCountRxPacket();
 
     /* Connected */
     if (connected) {
-        if (p->dlc>XCPTL_MAX_CTO_SIZE) return CRC_CMD_SYNTAX;
+        if (p->dlc>gXcpTlMaxCtoSize) return CRC_CMD_SYNTAX;
         return XcpCommand((const uint32_t*)&p->packet[0], p->dlc); // Handle command
     }
 
@@ -104,6 +152,19 @@ uint8_t XcpTlCommand( uint16_t msgLen, const uint8_t* msgBuf) {
 }
 
 
+// Count a received command message
+void XcpTlCountRxPacket() {
+    gXcpTlRxPackets++;
+}
+
+// Get the transport layer statistics since XcpTlInit
+// Transmitted messages (UDP datagrams or TCP segments with one or more XCP packets) and bytes including transport layer headers, transmit queue overruns and received command messages
+void XcpTlGetStats(uint64_t* tx_packets, uint64_t* tx_bytes, uint64_t* tx_queue_overruns, uint64_t* rx_packets) {
+    XcpTlGetTransmitQueueStats(tx_packets, tx_bytes, tx_queue_overruns);
+    if (rx_packets != NULL) *rx_packets = gXcpTlRxPackets;
+}
+
+
 // Transmit all completed and fully commited UDP frames
 // Returns number of bytes sent or -1 on error
 int32_t XcpTlHandleTransmitQueue() {
@@ -47,12 +47,20 @@ extern BOOL XcpTlWaitForTransmitQueueEmpty(uint16_t timeout_ms); // Wait (sleep)
 extern BOOL XcpTlNotifyTransmitQueueHandler();
 
 // Transport layer functions called by XCP server
-extern BOOL XcpTlInit(); // Start generic transport layer
+extern BOOL XcpTlSetMaxSizes(uint16_t max_cto, uint16_t max_dto); // Set the runtime max CTO and DTO size (<= XCPTL_MAX_CTO_SIZE/XCPTL_MAX_DTO_SIZE, 0 = maximum CTO and default DTO size), call before XcpTlInit, returns FALSE on invalid sizes
+extern uint16_t XcpTlGetMaxCtoSize(); // Runtime max CTO size
+extern uint16_t XcpTlGetMaxDtoSize(); // Runtime max DTO size
+extern uint16_t XcpTlGetMaxSegmentSize(); // Runtime max segment size
+extern BOOL XcpTlInit(uint32_t queue_size); // Start generic transport layer, queue size in packets, 0 = XCPTL_QUEUE_SIZE
 extern void XcpTlShutdown(); // Stop generic transport layer
 extern uint8_t XcpTlCommand(uint16_t msgLen, const uint8_t* msgBuf); // Handle XCP message
 extern const uint8_t* XcpTlTransmitQueuePeekMsg(uint16_t* msg_len);  // Check if there is a fully commited message segment buffer in the transmit queue
 extern void XcpTlTransmitQueueNextMsg(); // Remove the next transmit queue entry
 extern BOOL XcpTlTransmitQueueHasMsg(); // Check if there is enough data in the transmit queue for a transport layer message
+extern uint32_t XcpTlGetTransmitQueueSize(); // Transmit queue size in bytes, 0 if not initialized
+extern uint32_t XcpTlGetTransmitQueueFree(); // Free space in the transmit queue in bytes
+extern void XcpTlCountRxPacket(); // Count a received command message for the transport layer statistics
+extern void XcpTlGetStats(uint64_t* tx_packets, uint64_t* tx_bytes, uint64_t* tx_queue_overruns, uint64_t* rx_packets); // Transport layer statistics since XcpTlInit
 
 extern int32_t XcpTlHandleTransmitQueue(); // Send all outgoing packets in the transmit queue
 extern BOOL XcpTlWaitForTransmitData(uint32_t timeout_ms); // Wait for at least timeout_ms, until packets are pending in the transmit queue
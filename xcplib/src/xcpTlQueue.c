@@ -152,11 +152,13 @@ static uint64_t lockTimeHistogram[HISTOGRAM_SIZE] = {0,0,0,0,0,0,0,0,0,0,0,0,0,0
 #define COMMITTED 1 // Committed by producer
 
 
-// Buffer size is one entry larger than the queue size, message data is never wraped around for zero copy
-#define MPSC_BUFFER_SIZE ((XCPTL_QUEUE_SIZE+1)*(XCPTL_MAX_DTO_SIZE+XCPTL_TRANSPORT_LAYER_HEADER_SIZE))  
-#define MPSC_QUEUE_SIZE ((XCPTL_QUEUE_SIZE)*(XCPTL_MAX_DTO_SIZE+XCPTL_TRANSPORT_LAYER_HEADER_SIZE))  
+// Buffer size is one entry larger than the maximum queue size, message data is never wraped around for zero copy
+// The queue size used may be reduced at runtime (XcpTlInitTransmitQueue), entries have the runtime max DTO size
+#define MPSC_ENTRY_SIZE (XCPTL_MAX_DTO_SIZE+XCPTL_TRANSPORT_LAYER_HEADER_SIZE)
+#define MPSC_BUFFER_SIZE ((XCPTL_QUEUE_SIZE+1)*MPSC_ENTRY_SIZE)  
+#define MPSC_QUEUE_SIZE (gXcpTlQueue.queue_size)  
 
-#define MPSC_QUEUE_TRANSMIT_THRESHOLD ((XCPTL_MAX_SEGMENT_SIZE*100)/80) // Enough data for transmit, if queue level is 80% of a message
+#define MPSC_QUEUE_TRANSMIT_THRESHOLD ((XcpTlGetMaxSegmentSize()*100)/80) // Enough data for transmit, if queue level is 80% of a message
     
 
 
@@ -164,10 +166,14 @@ static struct {
     char buffer[MPSC_BUFFER_SIZE];   // Preallocated buffer
     atomic_uint_fast64_t head;  // Consumer reads from head
     atomic_uint_fast64_t tail;  // Producers write to tail
+    uint32_t queue_size; // Queue size in bytes used
     uint16_t tail_len;  // Length of the next message in the queue (determined by peek)
     uint16_t ctr;   // Next DTO data transmit message packet counter
     uint16_t overruns; // Overrun counter
     BOOL flush;     // There is a packet in the queue which has priority
+    atomic_uint_fast64_t tx_msgs; // Statistics: transmitted messages
+    atomic_uint_fast64_t tx_bytes; // Statistics: transmitted bytes
+    atomic_uint_fast64_t tx_overruns; // Statistics: packets lost because the queue was full
     MUTEX mutex;    // Mutex for queue producers
 } gXcpTlQueue;
 
@@ -175,11 +181,18 @@ static struct {
 static atomic_flag lock = ATOMIC_FLAG_INIT;
 #endif
 
-void XcpTlInitTransmitQueue() {
+// Queue size in packets, 0 or values larger than XCPTL_QUEUE_SIZE select XCPTL_QUEUE_SIZE
+void XcpTlInitTransmitQueue(uint32_t queue_size) {
+
+    if (queue_size == 0 || queue_size > XCPTL_QUEUE_SIZE) queue_size = XCPTL_QUEUE_SIZE;
+    gXcpTlQueue.queue_size = queue_size * (XcpTlGetMaxDtoSize() + XCPTL_TRANSPORT_LAYER_HEADER_SIZE);
 
     DBG_PRINT3("Init XCP transport layer queue\n");
-    DBG_PRINTF3("  SEGMENT_SIZE=%u, QUEUE_SIZE=%u, ALIGNMENT=%u, %uKiB queue memory used\n", XCPTL_MAX_SEGMENT_SIZE, XCPTL_QUEUE_SIZE, XCPTL_PACKET_ALIGNMENT, (unsigned int)sizeof(gXcpTlQueue) / 1024);
+    DBG_PRINTF3("  SEGMENT_SIZE=%u, QUEUE_SIZE=%u, ALIGNMENT=%u, %uKiB queue memory used\n", XcpTlGetMaxSegmentSize(), queue_size, XCPTL_PACKET_ALIGNMENT, (unsigned int)sizeof(gXcpTlQueue) / 1024);
     gXcpTlQueue.overruns = 0;
+    atomic_store_explicit(&gXcpTlQueue.tx_msgs, 0, memory_order_relaxed);
+    atomic_store_explicit(&gXcpTlQueue.tx_bytes, 0, memory_order_relaxed);
+    atomic_store_explicit(&gXcpTlQueue.tx_overruns, 0, memory_order_relaxed);
     gXcpTlQueue.ctr = 0;
     gXcpTlQueue.flush = FALSE;
     mutexInit(&gXcpTlQueue.mutex, FALSE, 1000);
@@ -288,6 +301,7 @@ uint8_t* XcpTlGetTransmitBuffer(void** handle, uint16_t packet_len) {
 
     if (entry==NULL) {
         gXcpTlQueue.overruns++;
+        atomic_fetch_add_explicit(&gXcpTlQueue.tx_overruns, 1, memory_order_relaxed);
         return NULL;
     }
 
@@ -332,6 +346,19 @@ static uint32_t XcpTlGetTransmitQueueLevel() {
     return (uint32_t)(head-tail);
 }
 
+// Get the transmit queue size in bytes, 0 if the transport layer is not initialized
+// This function is thread safe, any thread can ask for the queue size
+uint32_t XcpTlGetTransmitQueueSize() {
+    return MPSC_QUEUE_SIZE;
+}
+
+// Get the free space in the transmit queue in bytes
+// This function is thread safe, any thread can ask for the free space, the value is a snapshot
+uint32_t XcpTlGetTransmitQueueFree() {
+    uint32_t level = XcpTlGetTransmitQueueLevel();
+    return level < MPSC_QUEUE_SIZE ? MPSC_QUEUE_SIZE - level : 0;
+}
+
 // Wait (sleep) until transmit queue is empty 
 // This function is thread safe, any thread can wait for transmit queue empty
 // Timeout after 1s
@@ -407,7 +434,7 @@ const uint8_t * XcpTlTransmitQueuePeekMsg( uint16_t* msg_len ) {
             // Add this entry
             assert(entry->dlc<=XCPTL_MAX_DTO_SIZE); // Max DTO size
             len1 = entry->dlc + XCPTL_TRANSPORT_LAYER_HEADER_SIZE; 
-            if (len+len1 > XCPTL_MAX_SEGMENT_SIZE ) break; // Max segment size reached
+            if (len+len1 > XcpTlGetMaxSegmentSize() ) break; // Max segment size reached
             len += len1;
             entry->ctr = gXcpTlQueue.ctr++;
         }
@@ -424,11 +451,21 @@ const uint8_t * XcpTlTransmitQueuePeekMsg( uint16_t* msg_len ) {
 }
 
 
+// Get the number of transmitted messages and bytes and the number of packets lost because the queue was full
+void XcpTlGetTransmitQueueStats(uint64_t* tx_msgs, uint64_t* tx_bytes, uint64_t* overruns) {
+    if (tx_msgs != NULL) *tx_msgs = atomic_load_explicit(&gXcpTlQueue.tx_msgs, memory_order_relaxed);
+    if (tx_bytes != NULL) *tx_bytes = atomic_load_explicit(&gXcpTlQueue.tx_bytes, memory_order_relaxed);
+    if (overruns != NULL) *overruns = atomic_load_explicit(&gXcpTlQueue.tx_overruns, memory_order_relaxed);
+}
+
+
 // Advance the transmit queue tail by the message lentgh obtained from the last peek
 void XcpTlTransmitQueueNextMsg() {
     
     DBG_PRINTF5("XcpTlTransmitQueueNext: msg_len = %u\n", gXcpTlQueue.tail_len );
     if (gXcpTlQueue.tail_len==0) return;
+    atomic_fetch_add_explicit(&gXcpTlQueue.tx_msgs,1,memory_order_relaxed);
+    atomic_fetch_add_explicit(&gXcpTlQueue.tx_bytes,gXcpTlQueue.tail_len,memory_order_relaxed);
     atomic_fetch_add_explicit(&gXcpTlQueue.tail,gXcpTlQueue.tail_len,memory_order_relaxed);
     gXcpTlQueue.tail_len = 0;
     gXcpTlQueue.flush = FALSE;
@@ -1,7 +1,8 @@
 
 
 
-extern void XcpTlInitTransmitQueue();
+extern void XcpTlInitTransmitQueue(uint32_t queue_size); // Queue size in packets, 0 = XCPTL_QUEUE_SIZE
 extern void XcpTlResetTransmitQueue();
 extern void XcpTlFreeTransmitQueue() ;
+extern void XcpTlGetTransmitQueueStats(uint64_t* tx_msgs, uint64_t* tx_bytes, uint64_t* overruns); // Transmitted messages and bytes and overruns since XcpTlInitTransmitQueue
 
@@ -34,13 +34,19 @@
 // DTO size
 // Maximum size of a XCP data packet (DAQ,STIM)
 #define XCPTL_MAX_DTO_SIZE (XCPTL_MAX_SEGMENT_SIZE-8) // Segment size - XCP transport layer header size, size must be mod 8 
+#define XCPTL_DEFAULT_DTO_SIZE (XCPTL_DEFAULT_SEGMENT_SIZE-8) // DTO size used, if not set at runtime (XcpTlSetMaxSizes)
 
 // Segment size is the maximum data buffer size given to sockets send/sendTo, for UDP it is the UDP MTU
-// Jumbo frames are supported, but it might be more efficient to use a smaller segment sizes
+// Jumbo frames are supported up to OPTION_MAX_MTU, the default is OPTION_MTU, but it might be more efficient to use a smaller segment sizes
 #ifdef OPTION_MTU
-    #define XCPTL_MAX_SEGMENT_SIZE (OPTION_MTU-20-8) // UDP MTU (MTU - IP-header - UDP-header)
+    #define XCPTL_DEFAULT_SEGMENT_SIZE (OPTION_MTU-20-8) // UDP MTU (MTU - IP-header - UDP-header)
 #else
-    #define XCPTL_MAX_SEGMENT_SIZE (1500-20-8)
+    #define XCPTL_DEFAULT_SEGMENT_SIZE (1500-20-8)
+#endif
+#ifdef OPTION_MAX_MTU
+    #define XCPTL_MAX_SEGMENT_SIZE (OPTION_MAX_MTU-20-8)
+#else
+    #define XCPTL_MAX_SEGMENT_SIZE XCPTL_DEFAULT_SEGMENT_SIZE
 #endif
 
 // Alignment for packet concatenation
@@ -57,6 +57,7 @@ static uint8_t (*callback_freeze_cal)() = NULL;
 static uint8_t (*callback_read)(uint32_t src, uint8_t size, uint8_t* dst)  = NULL;
 static uint8_t (*callback_write)(uint32_t dst, uint8_t size, const uint8_t* src, uint8_t delay)  = NULL;
 static uint8_t (*callback_flush)()  = NULL;
+static void    (*callback_command)(uint8_t cmd)  = NULL;
 
 
 void ApplXcpRegisterCallbacks(
@@ -91,6 +92,11 @@ void ApplXcpRegisterCallbacks(
 #endif
 }
 
+// Optional callback on every received command, used for connection supervision
+void ApplXcpRegisterCommandCallback(void (*cb_command)(uint8_t cmd)) {
+    callback_command = cb_command;
+}
+
 
 /**************************************************************************/
 // General notifications from XCPlite.c
@@ -109,6 +115,10 @@ void ApplXcpDisconnect() {
     DBG_PRINT3("XCP disconnect\n");
 }
 
+void ApplXcpCommand(uint8_t cmd) {
+    if (callback_command!=NULL) callback_command(cmd);
+}
+
 #if XCP_PROTOCOL_LAYER_VERSION >= 0x0104
 BOOL ApplXcpPrepareDaq() { 
     DBG_PRINT3("XCP prepare DAQ\n");
@@ -402,6 +412,8 @@ uint8_t ApplXcpGetCalPageMode(uint8_t segment) {
 /**************************************************************************/
 
 static const char *gXcpA2lName = NULL; // A2L filename (without extension .a2l)
+static const char *gXcpA2lData = NULL; // Embedded A2L content, uploaded instead of the A2L file
+static uint32_t gXcpA2lDataLength = 0;
 static const char *gXcpEpk = NULL; // EPK 
 
 // This is used by the Rust ffi to set the A2L name generated by the registry
@@ -409,6 +421,12 @@ void ApplXcpSetA2lName(const char *name) {
     DBG_PRINTF3("Set A2L name to '%s'\n", name);
     gXcpA2lName = (char*)name; // must be static lifetime
 }
+// This is used by the Rust ffi to set an A2L, which is embedded in the application binary
+void ApplXcpSetA2lData(const char *data, uint32_t len) {
+    DBG_PRINTF3("Set embedded A2L, size=%u\n", len);
+    gXcpA2lData = data; // must be static lifetime
+    gXcpA2lDataLength = len;
+}
 void ApplXcpSetEpk(const char *epk) {
     DBG_PRINTF3("Set EPK to '%s'\n", epk);
     gXcpEpk = (char*)epk; // must be static lifetime
@@ -418,6 +436,7 @@ void ApplXcpSetEpk(const char *epk) {
 
 static FILE* gXcpFile = NULL; // A2l file content
 static uint32_t gXcpFileLength = 0; // A2L file length
+static BOOL gXcpA2lDataOpen = FALSE; // Upload of the embedded A2L in progress
 
 
 void closeA2lFile() {
@@ -429,6 +448,15 @@ void closeA2lFile() {
 
 uint32_t openA2lFile() {
     char filename[256];
+
+    // Embedded A2L
+    if (gXcpA2lData!=NULL) {
+        gXcpA2lDataOpen = TRUE;
+        gXcpFileLength = gXcpA2lDataLength;
+        DBG_PRINTF4("Embedded A2L ready for upload, size=%u\n", gXcpFileLength);
+        return gXcpFileLength;
+    }
+
     if (gXcpA2lName==NULL) return 0; // A2L file is not available
     SNPRINTF((char*)filename, 255, "%s.a2l", gXcpA2lName);
     
@@ -449,6 +477,12 @@ uint32_t openA2lFile() {
 }
 
 BOOL ApplXcpReadA2L(uint8_t size, uint32_t addr, uint8_t* data) {
+    if (gXcpA2lDataOpen) {
+        if (addr + size > gXcpFileLength) return FALSE;
+        memcpy(data, gXcpA2lData + addr, size);
+        if (addr + size == gXcpFileLength) gXcpA2lDataOpen = FALSE; // Complete sequential read
+        return TRUE;
+    }
     if (gXcpFile == NULL) return FALSE;
     if (addr + size > gXcpFileLength) return FALSE;
     if (size!=fread(data, 1, (uint32_t)size, gXcpFile)) return FALSE;
@@ -19,5 +19,8 @@ extern void ApplXcpRegisterCallbacks(
     uint8_t (*cb_flush)()
 );
 
+extern void ApplXcpRegisterCommandCallback(void (*cb_command)(uint8_t cmd));
+
 extern void ApplXcpSetA2lName(const char *name);
+extern void ApplXcpSetA2lData(const char *data, uint32_t len);
 extern void ApplXcpSetEpk(const char *name);
\ No newline at end of file